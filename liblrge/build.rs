@@ -0,0 +1,95 @@
+//! With the `system-minimap2` feature, link the minimap2 library installed on the system, rather
+//! than the copy compiled by `minimap2-sys`.
+//!
+//! The library is looked for in `MINIMAP2_LIB_DIR`, if it is set, and then in the linker's default
+//! search paths. It is linked dynamically, unless `MINIMAP2_STATIC` is set. The build fails unless
+//! the library's `minimap.h` - looked for in `MINIMAP2_INCLUDE_DIR`, `MINIMAP2_LIB_DIR`, and the
+//! usual include directories - is the minimap2 release the declarations were written for.
+//!
+//! If `vendored-minimap2` is also enabled - e.g., with `--all-features` - the copy compiled by
+//! `minimap2-sys` is used, so nothing is linked here.
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// The version of minimap2 the declarations in `src/minimap2/ffi.rs` follow, and are tested
+/// against. The layouts of minimap2's structs can change between releases, so no other version is
+/// linked.
+const SUPPORTED_VERSION: &str = "2.29-r1283";
+
+/// The directories `minimap.h` is looked for in, after `MINIMAP2_INCLUDE_DIR` and
+/// `MINIMAP2_LIB_DIR`.
+const INCLUDE_DIRS: [&str; 3] = [
+    "/usr/include",
+    "/usr/include/minimap2",
+    "/usr/local/include",
+];
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    if env::var_os("CARGO_FEATURE_SYSTEM_MINIMAP2").is_none() {
+        return;
+    }
+    // the version the declarations are compiled with, which is checked against minimap2-sys
+    println!("cargo:rustc-env=LRGE_MINIMAP2_VERSION={SUPPORTED_VERSION}");
+    if env::var_os("CARGO_FEATURE_VENDORED_MINIMAP2").is_some() {
+        // the declarations are still compiled, to test them against the minimap2-sys bindings
+        return;
+    }
+    for var in [
+        "MINIMAP2_LIB_DIR",
+        "MINIMAP2_INCLUDE_DIR",
+        "MINIMAP2_STATIC",
+    ] {
+        println!("cargo:rerun-if-env-changed={var}");
+    }
+    let lib_dir = env::var_os("MINIMAP2_LIB_DIR").map(PathBuf::from);
+
+    let header_dirs: Vec<PathBuf> = env::var_os("MINIMAP2_INCLUDE_DIR")
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(lib_dir.clone())
+        .chain(INCLUDE_DIRS.into_iter().map(PathBuf::from))
+        .collect();
+    let Some((header, contents)) = header_dirs.iter().find_map(|dir| {
+        let header = dir.join("minimap.h");
+        let contents = fs::read_to_string(&header).ok()?;
+        Some((header, contents))
+    }) else {
+        panic!(
+            "could not find minimap.h to check the version of the system minimap2 in any of {}; \
+             set MINIMAP2_INCLUDE_DIR to the directory containing it",
+            header_dirs
+                .iter()
+                .map(|dir| dir.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    };
+    println!("cargo:rerun-if-changed={}", header.display());
+    let version = contents.lines().find_map(|line| {
+        let version = line.strip_prefix("#define MM_VERSION")?.trim();
+        Some(version.trim_matches('"'))
+    });
+    match version {
+        Some(SUPPORTED_VERSION) => {}
+        Some(version) => panic!(
+            "{} is minimap2 {version}, but the system-minimap2 feature only supports minimap2 \
+             {SUPPORTED_VERSION}, as the layouts of its structs can differ between releases",
+            header.display()
+        ),
+        None => panic!("{} does not define MM_VERSION", header.display()),
+    }
+
+    if let Some(dir) = &lib_dir {
+        println!("cargo:rustc-link-search=native={}", dir.display());
+    }
+    if env::var_os("MINIMAP2_STATIC").is_some() {
+        println!("cargo:rustc-link-lib=static=minimap2");
+    } else {
+        println!("cargo:rustc-link-lib=minimap2");
+    }
+    for lib in ["z", "m", "pthread"] {
+        println!("cargo:rustc-link-lib={lib}");
+    }
+}
@@ -0,0 +1,387 @@
+//! Removal of residual adapters, and splitting of chimeric reads, from the sampled reads.
+//!
+//! Basecallers trim most adapters, but some are left at the ends of reads, and two molecules can be
+//! ligated together (or read through in one go) into a chimeric read, with an adapter at the
+//! junction. Neither part of a chimeric read overlaps the other's neighbours in the genome, and
+//! adapters make overlaps between reads that are not neighbours, so both bias the estimate.
+//!
+//! An [`AdapterScan`] finds the adapters in a read with a simple motif scan: exact matches of
+//! short seeds from each adapter (in both orientations) are extended to the whole adapter, which
+//! is a hit if enough of its bases match. Hits near either end of the read are trimmed off with
+//! everything beyond them, and a hit in the middle is treated as a chimeric junction - the read is
+//! split there, and only its longest part is kept, so that the sampled reads stay independent.
+use std::ops::Range;
+
+use log::info;
+
+use crate::hash::HashMap;
+use crate::Platform;
+
+/// Adapters that are left on Oxford Nanopore reads: the top strand of the ligation (Y) adapter,
+/// and the rapid adapter.
+pub const ONT_ADAPTERS: [&str; 2] = [
+    "AATGTACTTCGTTCAGTTACGTATTGCT",
+    "GTTTTCGCATTTATCGTGAAACGCTTTCGCGTTTTTCGTGCGCCGCTTCA",
+];
+
+/// The SMRTbell hairpin adapter of PacBio reads.
+pub const PACBIO_ADAPTERS: [&str; 1] = ["ATCTCTCTCAACAACAACAACGGAGGAGGAGGAAAAGAGAGAGAT"];
+
+/// The default number of bases at each end of a read in which adapters are trimmed, rather than
+/// split at.
+pub const DEFAULT_ADAPTER_END_SIZE: usize = 150;
+
+/// The default fraction of an adapter's bases that must match a read for it to be found.
+pub const DEFAULT_MIN_ADAPTER_IDENTITY: f32 = 0.8;
+
+/// The length of the seeds that are matched exactly. Each is packed, 2 bits per base, into a `u32`.
+const SEED_LEN: usize = 12;
+
+/// The fewest bases of an adapter that must be in a read to be found - adapters cut off by the end
+/// of a read are still found if this much of them is left.
+const MIN_ADAPTER_MATCH: usize = 20;
+
+/// A scan for adapters in reads.
+///
+/// # Examples
+///
+/// ```
+/// use liblrge::adapter::AdapterScan;
+/// use liblrge::Platform;
+///
+/// let scan = AdapterScan::for_platform(Platform::Nanopore).end_size(200);
+/// let custom = AdapterScan::new(["AATGTACTTCGTTCAGTTACGTATTGCT"]).min_identity(0.9);
+/// ```
+#[derive(Debug, Clone)]
+pub struct AdapterScan {
+    /// The adapters, and their reverse complements
+    adapters: Vec<Vec<u8>>,
+    /// The (index, offset) of each occurrence of a seed in the adapters
+    seeds: HashMap<u32, Vec<(usize, usize)>>,
+    end_size: usize,
+    min_identity: f32,
+}
+
+/// What was removed from a read by an [`AdapterScan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Cleaned {
+    /// The part of the read that is kept
+    pub(crate) range: Range<usize>,
+    /// Whether an adapter was trimmed from either end of the read
+    pub(crate) trimmed: bool,
+    /// Whether the read was split at an adapter in the middle
+    pub(crate) split: bool,
+}
+
+/// How many of the sampled reads had adapters removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AdapterSummary {
+    /// The number of reads that were scanned for adapters
+    pub num_reads: usize,
+    /// The number of reads with an adapter trimmed from either end
+    pub num_trimmed: usize,
+    /// The number of reads split at an adapter in the middle - i.e., chimeric reads
+    pub num_split: usize,
+}
+
+impl AdapterSummary {
+    /// Add what was removed from a read.
+    pub(crate) fn add(&mut self, cleaned: &Cleaned) {
+        self.num_reads += 1;
+        self.num_trimmed += usize::from(cleaned.trimmed);
+        self.num_split += usize::from(cleaned.split);
+    }
+}
+
+/// Log how many of the sampled reads had adapters removed, if they were scanned for them.
+pub(crate) fn log_adapter_summary(summary: Option<AdapterSummary>) {
+    if let Some(summary) = summary {
+        info!(
+            "Trimmed adapters from {} and split {} chimeric reads of {} sampled reads",
+            summary.num_trimmed, summary.num_split, summary.num_reads
+        );
+    }
+}
+
+/// The 2-bit code of a base, or `None` if it is not `A`, `C`, `G`, or `T`.
+fn encode(base: u8) -> Option<u32> {
+    match base {
+        b'A' | b'a' => Some(0),
+        b'C' | b'c' => Some(1),
+        b'G' | b'g' => Some(2),
+        b'T' | b't' => Some(3),
+        _ => None,
+    }
+}
+
+fn reverse_complement(seq: &[u8]) -> Vec<u8> {
+    seq.iter()
+        .rev()
+        .map(|b| match b.to_ascii_uppercase() {
+            b'A' => b'T',
+            b'C' => b'G',
+            b'G' => b'C',
+            b'T' => b'A',
+            b => b,
+        })
+        .collect()
+}
+
+/// Call `f` with the end position and packed value of each seed of `seq` without ambiguous bases.
+fn for_each_seed(seq: &[u8], mut f: impl FnMut(usize, u32) -> Option<usize>) {
+    let mask = (1u32 << (2 * SEED_LEN)) - 1;
+    let mut seed = 0;
+    let mut valid = 0;
+    let mut i = 0;
+    while i < seq.len() {
+        let Some(code) = encode(seq[i]) else {
+            valid = 0;
+            i += 1;
+            continue;
+        };
+        seed = ((seed << 2) | code) & mask;
+        valid += 1;
+        i += 1;
+        if valid >= SEED_LEN {
+            // `f` can skip ahead, e.g., past an adapter it has found
+            if let Some(next) = f(i, seed).filter(|&next| next > i) {
+                i = next;
+                valid = 0;
+            }
+        }
+    }
+}
+
+impl AdapterScan {
+    /// Scan for the given adapters, in both orientations. Adapters shorter than 12 bases are
+    /// ignored.
+    pub fn new<I, S>(adapters: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<[u8]>,
+    {
+        let adapters: Vec<Vec<u8>> = adapters
+            .into_iter()
+            .map(|a| a.as_ref().to_ascii_uppercase())
+            .filter(|a| a.len() >= SEED_LEN)
+            .flat_map(|a| [reverse_complement(&a), a])
+            .collect();
+        let mut seeds: HashMap<u32, Vec<(usize, usize)>> = HashMap::default();
+        for (index, adapter) in adapters.iter().enumerate() {
+            for_each_seed(adapter, |end, seed| {
+                seeds.entry(seed).or_default().push((index, end - SEED_LEN));
+                None
+            });
+        }
+        Self {
+            adapters,
+            seeds,
+            end_size: DEFAULT_ADAPTER_END_SIZE,
+            min_identity: DEFAULT_MIN_ADAPTER_IDENTITY,
+        }
+    }
+
+    /// Scan for the adapters of a sequencing platform - [`ONT_ADAPTERS`] or [`PACBIO_ADAPTERS`].
+    pub fn for_platform(platform: Platform) -> Self {
+        match platform {
+            Platform::Nanopore | Platform::NanoporeDuplex => Self::new(ONT_ADAPTERS),
+            Platform::PacBio => Self::new(PACBIO_ADAPTERS),
+        }
+    }
+
+    /// Set the number of bases at each end of a read in which adapters are trimmed. An adapter
+    /// further into the read is a chimeric junction, and the read is split there. By default, this
+    /// is [`DEFAULT_ADAPTER_END_SIZE`].
+    pub fn end_size(mut self, end_size: usize) -> Self {
+        self.end_size = end_size;
+        self
+    }
+
+    /// Set the fraction of an adapter's bases that must match a read for it to be found. By
+    /// default, this is [`DEFAULT_MIN_ADAPTER_IDENTITY`].
+    pub fn min_identity(mut self, min_identity: f32) -> Self {
+        self.min_identity = min_identity;
+        self
+    }
+
+    /// The range of `seq` that adapter `index` matches, if enough of its bases do, when its base at
+    /// `offset` is aligned with base `pos` of `seq`. Adapters cut off by either end of `seq` are
+    /// matched with what is left of them.
+    fn extend(&self, seq: &[u8], index: usize, offset: usize, pos: usize) -> Option<Range<usize>> {
+        let adapter = &self.adapters[index];
+        let start = pos.saturating_sub(offset);
+        let adapter_start = offset - (pos - start);
+        let len = (adapter.len() - adapter_start).min(seq.len() - start);
+        if len < adapter.len().min(MIN_ADAPTER_MATCH) {
+            return None;
+        }
+        let matches = seq[start..start + len]
+            .iter()
+            .zip(&adapter[adapter_start..])
+            .filter(|(s, a)| s.eq_ignore_ascii_case(a))
+            .count();
+        (matches as f32 >= self.min_identity * len as f32).then_some(start..start + len)
+    }
+
+    /// The ranges of `seq` that adapters match, in order.
+    fn find(&self, seq: &[u8]) -> Vec<Range<usize>> {
+        let mut hits: Vec<Range<usize>> = Vec::new();
+        for_each_seed(seq, |end, seed| {
+            let hit = self
+                .seeds
+                .get(&seed)?
+                .iter()
+                .find_map(|&(index, offset)| self.extend(seq, index, offset, end - SEED_LEN))?;
+            // the seed can be in an adapter that was found from an earlier seed
+            if hits.last().is_some_and(|last| last.end > hit.start) {
+                return None;
+            }
+            let next = hit.end;
+            hits.push(hit);
+            Some(next)
+        });
+        hits
+    }
+
+    /// Find the adapters in `seq`, and the part of it that is kept once they are removed.
+    pub(crate) fn clean(&self, seq: &[u8]) -> Cleaned {
+        let mut range = 0..seq.len();
+        let mut junctions = Vec::new();
+        for hit in self.find(seq) {
+            if hit.start < self.end_size {
+                range.start = range.start.max(hit.end);
+            } else if hit.end + self.end_size > seq.len() {
+                range.end = range.end.min(hit.start);
+            } else {
+                junctions.push(hit);
+            }
+        }
+        let trimmed = range != (0..seq.len());
+        if range.is_empty() {
+            // the read is all adapter, which is left to the overlaps to deal with
+            return Cleaned {
+                range: 0..seq.len(),
+                trimmed: false,
+                split: false,
+            };
+        }
+
+        let split = !junctions.is_empty();
+        let mut longest = range.start..range.start;
+        let mut start = range.start;
+        for junction in junctions
+            .into_iter()
+            .chain(std::iter::once(range.end..range.end))
+            .filter(|j| j.start >= range.start && j.end <= range.end)
+        {
+            if junction.start.saturating_sub(start) > longest.len() {
+                longest = start..junction.start;
+            }
+            start = junction.end;
+        }
+        Cleaned {
+            range: longest,
+            trimmed,
+            split,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADAPTER: &str = ONT_ADAPTERS[0];
+
+    /// A pseudo-random sequence of `len` bases, which will not contain an adapter.
+    fn random_seq(len: usize, seed: u64) -> String {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                b"ACGT"[(state >> 62) as usize] as char
+            })
+            .collect()
+    }
+
+    fn rc(seq: &str) -> String {
+        String::from_utf8(reverse_complement(seq.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn test_clean_read_without_adapters() {
+        let scan = AdapterScan::for_platform(Platform::Nanopore);
+        let read = random_seq(2000, 1);
+        let cleaned = scan.clean(read.as_bytes());
+        assert_eq!(cleaned.range, 0..2000);
+        assert!(!cleaned.trimmed);
+        assert!(!cleaned.split);
+    }
+
+    #[test]
+    fn test_clean_trims_adapters_at_ends() {
+        let scan = AdapterScan::new([ADAPTER]);
+        let read = format!(
+            "{}{ADAPTER}{}{}",
+            random_seq(10, 2),
+            random_seq(1000, 3),
+            rc(ADAPTER)
+        );
+        let cleaned = scan.clean(read.as_bytes());
+        assert_eq!(cleaned.range, 10 + ADAPTER.len()..10 + ADAPTER.len() + 1000);
+        assert!(cleaned.trimmed);
+        assert!(!cleaned.split);
+    }
+
+    #[test]
+    fn test_clean_with_mismatches_and_partial_adapter() {
+        let scan = AdapterScan::new([ADAPTER]);
+        let mut adapter = ADAPTER.as_bytes().to_vec();
+        adapter[3] = b'C';
+        adapter[20] = b'A';
+        // the adapter is cut off by the start of the read
+        let read = format!(
+            "{}{}",
+            String::from_utf8(adapter[4..].to_vec()).unwrap(),
+            random_seq(1000, 4)
+        );
+        assert_eq!(
+            scan.clean(read.as_bytes()).range,
+            ADAPTER.len() - 4..read.len()
+        );
+
+        let scan = AdapterScan::new([ADAPTER]).min_identity(1.0);
+        assert_eq!(scan.clean(read.as_bytes()).range, 0..read.len());
+    }
+
+    #[test]
+    fn test_clean_splits_chimeric_read() {
+        let scan = AdapterScan::new([ADAPTER]).end_size(100);
+        let read = format!("{}{ADAPTER}{}", random_seq(600, 5), random_seq(1000, 6));
+        let cleaned = scan.clean(read.as_bytes());
+        assert_eq!(cleaned.range, 600 + ADAPTER.len()..read.len());
+        assert!(!cleaned.trimmed);
+        assert!(cleaned.split);
+
+        let mut summary = AdapterSummary::default();
+        summary.add(&cleaned);
+        assert_eq!(
+            summary,
+            AdapterSummary {
+                num_reads: 1,
+                num_trimmed: 0,
+                num_split: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_clean_read_of_adapters_is_kept() {
+        let scan = AdapterScan::new([ADAPTER]);
+        let read = format!("{ADAPTER}{ADAPTER}");
+        assert_eq!(scan.clean(read.as_bytes()).range, 0..read.len());
+    }
+}
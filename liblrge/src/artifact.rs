@@ -0,0 +1,201 @@
+//! The intermediate files (artifacts) written by a strategy.
+//!
+//! By default, a strategy writes its intermediate files to its temporary directory (see
+//! [`twoset::Builder::tmpdir`][crate::twoset::Builder::tmpdir]) and leaves them there, so they are
+//! kept or removed along with the directory. Each [`Artifact`] can instead be written to a path of
+//! your choosing, with `artifact_path` on the builders, or removed as soon as the strategy is
+//! finished with it, with `keep_artifact`. Artifacts that a strategy does not write - e.g., the
+//! target reads for the [`AvaStrategy`][crate::AvaStrategy] - are ignored.
+//!
+//! If the strategy creates its own temporary directory (see
+//! [`twoset::Builder::create_tmpdir`][crate::twoset::Builder::create_tmpdir]), everything in it is
+//! removed when the strategy is dropped, except the artifacts explicitly kept with
+//! `keep_artifact`. So, you can archive just the files you need, without managing the directory.
+//!
+//! # Examples
+//!
+//! Keep only the overlaps, for provenance, while the rest of the temporary directory is removed.
+//!
+//! ```no_run
+//! use liblrge::artifact::Artifact;
+//! use liblrge::twoset::Builder;
+//!
+//! let tmpdir = tempfile::tempdir().unwrap();
+//! let strategy = Builder::new()
+//!     .tmpdir(tmpdir.path())
+//!     .artifact_path(Artifact::Overlaps, "results/overlaps.paf")
+//!     .keep_artifact(Artifact::Target, false)
+//!     .keep_artifact(Artifact::Query, false)
+//!     .build("path/to/reads.fastq");
+//! ```
+//!
+//! Or, keep only the overlaps in a temporary directory that the strategy creates, and otherwise
+//! removes when it is dropped.
+//!
+//! ```no_run
+//! use liblrge::artifact::Artifact;
+//! use liblrge::twoset::Builder;
+//!
+//! let strategy = Builder::new()
+//!     .create_tmpdir(true)
+//!     .keep_artifact(Artifact::Overlaps, true)
+//!     .build("path/to/reads.fastq");
+//! ```
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+
+use crate::error::LrgeError;
+
+/// An intermediate file written by a strategy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Artifact {
+    /// The sampled target reads of the [`TwoSetStrategy`][crate::TwoSetStrategy] (`target.fa`)
+    Target,
+    /// The sampled query reads of the [`TwoSetStrategy`][crate::TwoSetStrategy] (`query.fa`)
+    Query,
+    /// The sampled reads of the [`AvaStrategy`][crate::AvaStrategy] (`reads.fa`)
+    Reads,
+    /// The overlaps between the reads, in the chosen [`OverlapFormat`][crate::OverlapFormat]
+    /// (e.g., `overlaps.paf`)
+    Overlaps,
+}
+
+impl fmt::Display for Artifact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Artifact::Target => "target reads",
+            Artifact::Query => "query reads",
+            Artifact::Reads => "reads",
+            Artifact::Overlaps => "overlaps",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Where each artifact is written, and which are removed once the strategy is finished with them.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Artifacts {
+    paths: HashMap<Artifact, PathBuf>,
+    discarded: HashSet<Artifact>,
+    /// The artifacts explicitly kept. These are left in place even when the temporary directory
+    /// the strategy created is removed.
+    kept: HashSet<Artifact>,
+}
+
+impl Artifacts {
+    pub(crate) fn set_path(&mut self, artifact: Artifact, path: PathBuf) {
+        self.paths.insert(artifact, path);
+    }
+
+    pub(crate) fn set_keep(&mut self, artifact: Artifact, keep: bool) {
+        if keep {
+            self.discarded.remove(&artifact);
+            self.kept.insert(artifact);
+        } else {
+            self.discarded.insert(artifact);
+            self.kept.remove(&artifact);
+        }
+    }
+
+    /// The artifacts explicitly kept with [`Artifacts::set_keep`].
+    pub(crate) fn kept(&self) -> impl Iterator<Item = Artifact> + '_ {
+        self.kept.iter().copied()
+    }
+
+    /// The path of an artifact - the path it was given, or else `default_name` in `tmpdir`.
+    pub(crate) fn path(&self, artifact: Artifact, tmpdir: &Path, default_name: &str) -> PathBuf {
+        self.paths
+            .get(&artifact)
+            .cloned()
+            .unwrap_or_else(|| tmpdir.join(default_name))
+    }
+
+    /// Remove the artifact at `path` if it is not being kept.
+    pub(crate) fn clean_up(&self, artifact: Artifact, path: &Path) -> crate::Result<()> {
+        if !self.discarded.contains(&artifact) {
+            return Ok(());
+        }
+        match std::fs::remove_file(path) {
+            Ok(()) => {
+                debug!("Removed {artifact} file {}", path.display());
+                Ok(())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Check that the directory each artifact is to be written to exists.
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        for (artifact, path) in &self.paths {
+            let parent = path.parent().filter(|p| !p.as_os_str().is_empty());
+            if parent.is_some_and(|dir| !dir.is_dir()) {
+                return Err(LrgeError::invalid_configuration(
+                    "artifact_path",
+                    format!(
+                        "the directory for the {artifact} file {} does not exist",
+                        path.display()
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_artifact_path() {
+        let mut artifacts = Artifacts::default();
+        let tmpdir = Path::new("/tmp/lrge");
+        assert_eq!(
+            artifacts.path(Artifact::Target, tmpdir, "target.fa"),
+            tmpdir.join("target.fa")
+        );
+
+        artifacts.set_path(Artifact::Overlaps, PathBuf::from("out.paf"));
+        assert_eq!(
+            artifacts.path(Artifact::Overlaps, tmpdir, "overlaps.paf"),
+            PathBuf::from("out.paf")
+        );
+        assert!(artifacts.validate().is_ok());
+
+        artifacts.set_path(Artifact::Query, PathBuf::from("/missing/dir/query.fa"));
+        assert!(matches!(
+            artifacts.validate(),
+            Err(LrgeError::InvalidConfiguration {
+                option: "artifact_path",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_clean_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("target.fa");
+        std::fs::write(&path, ">r\nACGT\n").unwrap();
+
+        let mut artifacts = Artifacts::default();
+        artifacts.clean_up(Artifact::Target, &path).unwrap();
+        assert!(path.exists());
+
+        artifacts.set_keep(Artifact::Target, false);
+        artifacts.clean_up(Artifact::Target, &path).unwrap();
+        assert!(!path.exists());
+        // a file that is already gone is not an error
+        artifacts.clean_up(Artifact::Target, &path).unwrap();
+
+        artifacts.set_keep(Artifact::Target, true);
+        assert!(artifacts.discarded.is_empty());
+        assert_eq!(artifacts.kept().collect::<Vec<_>>(), vec![Artifact::Target]);
+        artifacts.set_keep(Artifact::Target, false);
+        assert_eq!(artifacts.kept().count(), 0);
+    }
+}
@@ -29,22 +29,23 @@
 //! // you might want to handle cases where some proportion of query reads did not overlap with target reads
 //! ```
 //!
-//! By default, the intermediate reads and overlap files are written to a temporary directory and
-//! cleaned up after the strategy object is dropped. This is done via the use of the [`tempfile`](https://crates.io/crates/tempfile) crate.
+//! By default, the intermediate reads and overlap files are written to the temporary directory and
+//! left there. With [`Builder::create_tmpdir`], they are instead written to a new directory inside
+//! it, which is cleaned up after the strategy object is dropped. This is done via the use of the
+//! [`tempfile`](https://crates.io/crates/tempfile) crate.
 //! The intermediate reads file will be placed inside the temporary directory and names `reads.fq`,
-//! while the overlap file will be named `overlaps.paf`.
+//! while the overlap file will be named `overlaps.paf` (or another extension, with
+//! [`Builder::overlap_format`]). The per-read estimates are written to
+//! `estimates.tsv`, which can be used to recalculate the estimate with
+//! [`SavedEstimates`][crate::estimate::SavedEstimates].
 //!
 //! You can set your own temporary directory by using the [`Builder::tmpdir`] method.
-use std::io::Write;
 mod builder;
 
-use std::collections::{HashMap, HashSet};
-use std::ffi::CString;
-use std::fs::File;
-use std::io::BufWriter;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::AtomicU32;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 
 use crossbeam_channel as channel;
 use log::{debug, info, trace, warn};
@@ -52,14 +53,36 @@ use needletail::parse_fastx_file;
 use rayon::prelude::*;
 
 pub use self::builder::Builder;
+use crate::adapter::{log_adapter_summary, AdapterScan, AdapterSummary};
+use crate::artifact::{Artifact, Artifacts};
 use crate::error::LrgeError;
-use crate::estimate::per_read_estimate;
+use crate::estimate::{
+    check_no_mapping_fraction, contained_fraction, duplicate_fraction, num_estimated_reads,
+    write_per_read_estimates, FlaggedReads, PerReadEstimate, PerReadEstimator, RawEstimates,
+    ESTIMATES_FILENAME,
+};
+use crate::gc::GcSummary;
+use crate::hash::HashSet;
 use crate::io::FastqRecordExt;
-use crate::minimap2::{AlignerWrapper, Preset};
-use crate::{io, unique_random_set, Estimate, Platform};
+use crate::memory::{bytes_in_ram, MemoryPlan};
+use crate::minimap2::mapping::Contained;
+use crate::minimap2::{AlignerWrapper, MappingSink, OptionOverrides, OverlapWriter};
+use crate::read_filter::ReadFilter;
+use crate::stream::EstimateSink;
+use crate::tmpdir::ManagedTmpdir;
+use crate::trim::QualityTrim;
+use crate::{sample, Estimate, OverlapFormat, PafReader, Platform};
 
 /// The default number of reads to use in the all-vs-all strategy.
 pub const DEFAULT_AVA_NUM_READS: usize = 25_000;
+/// The default number of reads buffered in the channel between the reader and the mapping threads.
+pub const DEFAULT_AVA_CHANNEL_CAPACITY: usize = 25_000;
+/// The number of overlaps a mapping thread buffers before it first deduplicates its buffer.
+const MIN_OVERLAP_BUFFER: usize = 4096;
+/// The number of overlaps per read that are assumed to be held in memory when planning a memory
+/// budget. A read overlaps about twice as many reads as the coverage of the sample, which is
+/// rarely more than 50x.
+const PLANNED_OVERLAPS_PER_READ: usize = 100;
 
 /// A strategy that compares overlaps between two sets of reads.
 ///
@@ -73,20 +96,101 @@ pub struct AvaStrategy {
     input: PathBuf,
     /// The number of reads to use in the strategy.
     num_reads: usize,
+    /// The fraction of the input to use, instead of `num_reads`.
+    read_fraction: Option<f32>,
     /// The number of bases to use in the strategy.
     num_bases: usize,
     /// Remove overlaps for internal matches.
     remove_internal: bool,
     /// Maximum overhang ratio
     max_overhang_ratio: f32,
+    /// Maximum overhang, in bases
+    max_overhang_size: Option<usize>,
+    /// Overlaps with a greater divergence (`dv`) than this are not counted.
+    max_divergence: Option<f32>,
+    /// Overlaps with a lower mapping quality than this are not counted.
+    min_mapq: Option<u8>,
+    /// Overlaps with a shorter alignment block than this are not counted.
+    min_overlap_len: Option<usize>,
+    /// Only count the highest-scoring chain to each read.
+    primary_only: bool,
+    /// Weight each overlap by its length, rather than counting it once.
+    length_weighted: bool,
+    /// Exclude reads contained in a read they overlap from the estimate.
+    exclude_contained: bool,
+    /// Exclude reads that are a duplicate of a read they overlap from the estimate.
+    exclude_duplicates: bool,
     /// The directory to which all intermediate files will be written.
     tmpdir: PathBuf,
+    /// The directory created inside `tmpdir` for the intermediate files, if there is one.
+    managed_tmpdir: Option<ManagedTmpdir>,
     /// Number of threads to use with minimap2.
     threads: usize,
+    /// Number of threads to use when building the minimap2 index.
+    index_threads: usize,
+    /// A caller-provided thread pool to map reads in.
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
     /// The (optional) seed to use for randomly selecting reads.
     seed: Option<u64>,
     /// Sequencing platform of the reads.
     platform: Platform,
+    /// Approximate memory budget, in bytes.
+    max_memory: Option<usize>,
+    /// The number of reads buffered between the reader and the mapping threads.
+    channel_capacity: usize,
+    /// The maximum fraction of reads allowed to have no overlaps.
+    max_no_mapping_fraction: Option<f32>,
+    /// The maximum number of malformed input records to skip. If `None`, they are an error.
+    max_bad_records: Option<usize>,
+    /// Whether every record is parsed, and so validated, when the reads are counted.
+    validate_records: bool,
+    /// Whether the read count is cached in a sidecar file next to the input.
+    cache_count: bool,
+    /// A file of the IDs of the only reads that can be sampled.
+    allowlist: Option<PathBuf>,
+    /// A file of the IDs of reads that cannot be sampled.
+    blocklist: Option<PathBuf>,
+    /// How to trim the low-quality ends of the sampled reads, if at all.
+    quality_trim: Option<QualityTrim>,
+    /// The scan for adapters to remove from the sampled reads, if any.
+    adapters: Option<AdapterScan>,
+    /// The format the overlaps are written in.
+    overlap_format: OverlapFormat,
+    /// Whether the overlaps are sorted and deduplicated before they are written.
+    sort_overlaps: bool,
+    /// Where the intermediate files are written, and which are kept.
+    artifacts: Artifacts,
+    /// Whether minimap2 maps pairs of reads in both directions.
+    dual: bool,
+    /// Passes each per-read estimate to the caller's callback, if there is one.
+    on_estimate: Option<EstimateSink>,
+    /// Passes each overlap to the caller's callback, if there is one.
+    on_mapping: Option<MappingSink>,
+    /// Calculates the genome size estimate for each read from its overlaps.
+    estimator: Arc<dyn PerReadEstimator>,
+    /// The caller's changes to the minimap2 options.
+    overrides: OptionOverrides,
+    /// The number of reads in the input file. This is only known once the reads are sampled.
+    input_num_reads: Option<u64>,
+    /// The total number of bases in the input file. This is only known once the reads are sampled.
+    input_num_bases: Option<u64>,
+    /// The number of malformed input records skipped. This is only known once the reads are sampled.
+    input_num_skipped: Option<u64>,
+    /// The GC content of the sampled reads. This is only known once the reads are sampled.
+    gc_content: Option<GcSummary>,
+    /// How many sampled reads had adapters removed. This is only known once the reads are sampled.
+    adapter_summary: Option<AdapterSummary>,
+    /// The fraction of reads contained in a read they overlap. This is only known once the reads are
+    /// overlapped.
+    contained_fraction: Option<f32>,
+    /// The fraction of reads that are a duplicate of a read they overlap. This is only known once
+    /// the reads are overlapped.
+    duplicate_fraction: Option<f32>,
+    /// The number of overlaps the estimates were calculated from. This is only known once the
+    /// reads are overlapped.
+    num_overlaps: Option<u64>,
+    /// The per-read estimates, once the genome size has been estimated.
+    raw_estimates: Option<RawEstimates>,
 }
 
 impl AvaStrategy {
@@ -104,19 +208,165 @@ impl AvaStrategy {
         self.num_reads
     }
 
+    /// The path an intermediate file is written to, or `None` if this strategy does not write it.
+    /// See [`Builder::artifact_path`]. The path is returned even if the file has not been written
+    /// yet, or has been removed (see [`Builder::keep_artifact`]).
+    pub fn artifact_path(&self, artifact: Artifact) -> Option<PathBuf> {
+        let path = match artifact {
+            Artifact::Reads => self
+                .artifacts
+                .path(Artifact::Reads, &self.tmpdir, "reads.fa"),
+            Artifact::Overlaps => self.artifacts.path(
+                Artifact::Overlaps,
+                &self.tmpdir,
+                self.overlap_format.filename(),
+            ),
+            Artifact::Target | Artifact::Query => return None,
+        };
+        Some(path)
+    }
+
+    /// The overlaps between the reads, as a lazy iterator over the records of the overlaps file,
+    /// so they can be post-processed after the estimate is generated without parsing the file
+    /// yourself. They are read back from the file as they are iterated over, so must still be
+    /// there - e.g., not [removed][Builder::keep_artifact], and, if the strategy creates its own
+    /// temporary directory, the strategy has not been dropped. To see the overlaps as they are
+    /// found instead, use [`Builder::on_mapping`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidConfiguration`][LrgeError::InvalidConfiguration] error if the overlaps
+    /// are not written in [PAF][OverlapFormat::Paf], or an [`IoError`][LrgeError::IoError] if the
+    /// file cannot be opened - e.g., the estimate has not been generated yet.
+    pub fn overlaps(&self) -> crate::Result<PafReader> {
+        let path = self
+            .artifact_path(Artifact::Overlaps)
+            .expect("the overlaps are always written");
+        PafReader::from_overlaps(&path, self.overlap_format)
+    }
+
+    /// Remove the intermediate files that are not being kept.
+    fn clean_up_artifacts(&self) -> crate::Result<()> {
+        for artifact in [Artifact::Reads, Artifact::Overlaps] {
+            if let Some(path) = self.artifact_path(artifact) {
+                self.artifacts.clean_up(artifact, &path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Count the reads in the input file to turn the fraction of them to use (see
+    /// [`Builder::read_fraction`]) into a [number of reads][Self::num_reads], if a fraction was
+    /// given. This is done when the estimate is generated, but can be done beforehand to see how
+    /// many reads will be overlapped - e.g., for a dry run.
+    pub fn resolve_fraction(&mut self) -> crate::Result<()> {
+        let Some(fraction) = self.read_fraction.take() else {
+            return Ok(());
+        };
+        let filter = ReadFilter::new(self.allowlist.as_deref(), self.blocklist.as_deref())?;
+        debug!("Counting reads in input file...");
+        let n_fq_reads = sample::count_records(
+            &self.input,
+            self.max_bad_records,
+            &filter,
+            self.validate_records,
+            self.cache_count,
+        )?;
+        // each read must be overlapped with at least one other
+        self.num_reads = sample::resolve_fraction(fraction, n_fq_reads).max(2);
+        Ok(())
+    }
+
+    /// The number of threads reads are mapped with.
+    pub fn mapping_threads(&self) -> usize {
+        self.thread_pool
+            .as_ref()
+            .map_or(self.threads, |pool| pool.current_num_threads())
+    }
+
+    /// Map reads in the given thread pool, rather than building a new one.
+    pub(crate) fn set_thread_pool(&mut self, pool: Arc<rayon::ThreadPool>) {
+        self.thread_pool = Some(pool);
+    }
+
+    /// The directory the intermediate files are written to. If the strategy creates its own
+    /// directory (see [`Builder::create_tmpdir`]), this is the directory it is created in until the
+    /// estimate is generated.
+    pub fn tmpdir(&self) -> &Path {
+        &self.tmpdir
+    }
+
+    /// Set the directory to which all intermediate files will be written.
+    pub(crate) fn set_tmpdir(&mut self, tmpdir: PathBuf) {
+        self.tmpdir = tmpdir;
+    }
+
+    /// Create the strategy's own temporary directory, if it has one, and retain the intermediate
+    /// files that are explicitly kept when it is removed.
+    fn prepare_tmpdir(&mut self) -> crate::Result<()> {
+        let Some(managed) = &mut self.managed_tmpdir else {
+            return Ok(());
+        };
+        self.tmpdir = managed.create(&self.tmpdir)?;
+        let kept: Vec<PathBuf> = self
+            .artifacts
+            .kept()
+            .filter_map(|artifact| self.artifact_path(artifact))
+            .collect();
+        if let Some(managed) = &mut self.managed_tmpdir {
+            kept.into_iter().for_each(|path| managed.retain(path));
+        }
+        Ok(())
+    }
+
     /// Subsample the reads in the input file to `num_reads`.
     fn subsample_reads(&mut self) -> crate::Result<(PathBuf, usize)> {
-        debug!("Counting records in input file...");
-        let n_fq_reads = io::count_records(&self.input)?;
-        debug!("Found {} reads in input file", n_fq_reads);
-
-        if n_fq_reads > u32::MAX as usize {
-            let msg = format!(
-                "Number of reads in input file ({}) exceeds maximum allowed value ({})",
-                n_fq_reads,
-                u32::MAX
+        self.resolve_fraction()?;
+        MemoryPlan::check(
+            self.max_memory,
+            self.num_reads,
+            self.mapping_threads(),
+            self.num_reads.saturating_mul(PLANNED_OVERLAPS_PER_READ),
+        )?;
+        let filter = ReadFilter::new(self.allowlist.as_deref(), self.blocklist.as_deref())?;
+        debug!("Sampling {} reads from input file...", self.num_reads);
+        let spill_file = self.tmpdir.join("sample.fa");
+        let sample = sample::sample_records(
+            &self.input,
+            self.num_reads,
+            self.seed,
+            &spill_file,
+            self.max_bad_records,
+            &filter,
+            self.quality_trim,
+        )?;
+        let n_fq_reads = sample.num_records();
+        self.input_num_reads = Some(n_fq_reads as u64);
+        self.input_num_bases = Some(sample.num_bases());
+        self.input_num_skipped = Some(sample.num_skipped() as u64);
+        debug!(
+            "Found {} reads ({} bases) in input file",
+            n_fq_reads,
+            sample.num_bases()
+        );
+        if filter.is_active() {
+            info!(
+                "Excluded {} reads of the input file by their IDs",
+                sample.num_excluded()
+            );
+        }
+        if sample.num_rna() > 0 {
+            info!(
+                "{} sampled reads contain U bases (direct RNA?); these are converted to T",
+                sample.num_rna()
+            );
+        }
+        if sample.num_trimmed() > 0 {
+            info!(
+                "Trimmed {} low-quality bases from the ends of {} input reads",
+                sample.num_trimmed_bases(),
+                sample.num_trimmed()
             );
-            return Err(LrgeError::TooManyReadsError(msg));
         }
 
         if n_fq_reads < self.num_reads {
@@ -127,30 +377,17 @@ impl AvaStrategy {
             self.num_reads = n_fq_reads;
         }
 
-        let mut indices: HashSet<u32> =
-            unique_random_set(self.num_reads, n_fq_reads as u32, self.seed)
-                .iter()
-                .cloned()
-                .collect();
-
-        let out_file = self.tmpdir.join("reads.fa");
+        let out_file = self
+            .artifacts
+            .path(Artifact::Reads, &self.tmpdir, "reads.fa");
 
         debug!("Writing subsampled reads to temporary files...");
-        let mut writer = File::create(&out_file).map(BufWriter::new)?;
-        let mut sum_len = 0;
-        let mut idx: u32 = 0;
-        io::iter_records(&self.input, |id, seq| {
-            if indices.remove(&idx) {
-                sum_len += seq.len();
-                writer.write_all(b">")?;
-                writer.write_all(id)?;
-                writer.write_all(b"\n")?;
-                writer.write_all(seq)?;
-                writer.write_all(b"\n")?;
-            }
-            idx += 1;
-            Ok(())
-        })?;
+        let (sum_lens, gc_content, adapter_summary) = sample
+            .write_groups_with_adapters(&[(self.num_reads, &out_file)], self.adapters.as_ref())?;
+        let sum_len = sum_lens[0];
+        self.gc_content = gc_content;
+        self.adapter_summary = adapter_summary;
+        log_adapter_summary(adapter_summary);
 
         self.num_bases = sum_len;
 
@@ -160,53 +397,64 @@ impl AvaStrategy {
         Ok((out_file, sum_len))
     }
 
-    /// Align the reads to each other, write them to a PAF file, and calculate the genome size
+    /// Align the reads to each other, write them to the overlaps file, and calculate the genome size
     /// estimate for each read
     fn align_reads(
         &self,
         aln_wrapper: AlignerWrapper,
         reads_file: PathBuf,
         sum_len: usize,
-    ) -> crate::Result<(Vec<f32>, u32)> {
-        // Bounded channel to control memory usage - i.e., 10000 records in the channel at a time
-        let (sender, receiver) = channel::bounded(25_000);
+        channel_capacity: usize,
+    ) -> crate::Result<(Vec<PerReadEstimate>, u32, FlaggedReads)> {
+        // Bounded channel to control memory usage - i.e., channel_capacity records in the channel at a time
+        let (sender, receiver) = channel::bounded(channel_capacity);
         let aligner = Arc::clone(&aln_wrapper.aligner); // Shared reference for the producer thread
         let overlap_threshold = aln_wrapper.aligner.mapopt.min_chain_score as u32;
-        let read_lengths: HashMap<Vec<u8>, usize> = HashMap::with_capacity(self.num_reads);
-        let read_lengths = Arc::new(Mutex::new(read_lengths));
-        let read_lengths_for_producer = Arc::clone(&read_lengths);
+        // the reads are indexed in the order they appear in the file, so a read's position in the
+        // file is its index ID (rid). All bookkeeping is keyed on these IDs, and the names and
+        // lengths are looked up in the index when needed
+        let index = Arc::clone(
+            aln_wrapper
+                .aligner
+                .index()
+                .expect("the aligner is built with an index"),
+        );
+        let num_ids = index.n_seq();
+        let mut names = HashSet::with_capacity_and_hasher(num_ids, Default::default());
+        for id in 0..num_ids {
+            if !names.insert(index.seq_name(id)) {
+                return Err(LrgeError::DuplicateReadIdentifier {
+                    read_id: String::from_utf8_lossy(index.seq_name(id)).to_string(),
+                    path: self.input.clone(),
+                });
+            }
+        }
+        drop(names);
 
-        // Producer: Read FASTQ records and send them to the channel
+        // Producer: Read FASTQ records and send them, with their ID, to the channel
+        let producer_index = Arc::clone(&index);
         let producer = std::thread::spawn(move || -> Result<(), LrgeError> {
-            let mut fastx_reader = parse_fastx_file(&reads_file).map_err(|e| {
-                LrgeError::FastqParseError(format!("Error parsing FASTQ file: {e}",))
-            })?;
-            let read_lengths = read_lengths_for_producer;
+            let mut fastx_reader =
+                parse_fastx_file(&reads_file).map_err(|source| LrgeError::FastqParseError {
+                    path: reads_file.clone(),
+                    source,
+                })?;
 
+            let mut id: u32 = 0;
             while let Some(record) = fastx_reader.next() {
                 match record {
                     Ok(rec) => {
-                        let rid = rec.read_id().to_owned();
-                        let msg = io::Message::Data((rid.to_owned(), rec.seq().into_owned()));
-
-                        {
-                            // Lock the read_lengths map and insert the read length
-                            let mut read_lengths_lock = read_lengths.lock().unwrap();
-                            if read_lengths_lock.insert(rid, rec.num_bases()).is_some() {
-                                return Err(LrgeError::DuplicateReadIdentifier(
-                                    String::from_utf8_lossy(rec.read_id()).to_string(),
-                                ));
-                            }
-                        }
-
-                        if sender.send(msg).is_err() {
+                        debug_assert_eq!(producer_index.seq_name(id as usize), rec.read_id());
+                        if sender.send((id, rec.seq().into_owned())).is_err() {
                             break; // Exit if the receiver is dropped
                         }
+                        id += 1;
                     }
-                    Err(e) => {
-                        return Err(LrgeError::FastqParseError(format!(
-                            "Error parsing query FASTQ file: {e}",
-                        )));
+                    Err(source) => {
+                        return Err(LrgeError::FastqParseError {
+                            path: reads_file,
+                            source,
+                        });
                     }
                 }
             }
@@ -216,144 +464,262 @@ impl AvaStrategy {
             Ok(())
         });
 
-        // Open the output PAF file for writing
-        let paf_path = self.tmpdir.join("overlaps.paf");
-        let mut buf = File::create(&paf_path).map(BufWriter::new)?;
-        let writer = csv::WriterBuilder::new()
-            .has_headers(false)
-            .delimiter(b'\t')
-            .from_writer(&mut buf);
-        let writer = Arc::new(Mutex::new(writer)); // thread-safe writer
-
-        // set the number of threads to use with rayon in the following mapping code
-        let pool = rayon::ThreadPoolBuilder::new()
-            .num_threads(self.threads)
-            .build()
-            .map_err(|e| {
-                LrgeError::ThreadError(format!("Error setting number of threads: {e}",))
-            })?;
-
-        let ovlap_counter: HashMap<Vec<u8>, usize> = HashMap::with_capacity(self.num_reads);
-        let ovlap_counter = Arc::new(Mutex::new(ovlap_counter));
-        let seen_pairs: HashSet<(Vec<u8>, Vec<u8>)> = HashSet::with_capacity(self.num_reads);
-        let seen_pairs = Arc::new(Mutex::new(seen_pairs));
-
-        debug!("Aligning reads and writing overlaps to PAF file...");
-        // Consumer: Process records from the channel in parallel
-        pool.install(|| -> Result<(), LrgeError> {
+        // Open the output overlaps file for writing
+        let overlaps_path = self
+            .artifact_path(Artifact::Overlaps)
+            .expect("the strategy writes overlaps");
+        let overlap_writer = OverlapWriter::new(
+            &overlaps_path,
+            self.overlap_format,
+            channel_capacity,
+            self.sort_overlaps,
+            self.on_mapping.clone(),
+        )?;
+
+        // the thread pool to use with rayon in the following mapping code
+        let pool = crate::mapping_pool(self.thread_pool.as_ref(), self.threads)?;
+
+        debug!(
+            "Aligning reads and writing overlaps to {} file...",
+            self.overlap_format
+        );
+        // Map phase: process records from the channel in parallel. Each worker collects the
+        // overlaps it finds in its own buffer, so they do not contend with one another. A buffer
+        // is deduplicated whenever it doubles in size, so it holds at most about twice as many
+        // overlaps as the worker has found pairs
+        let mapped = pool.install(|| -> Result<Vec<PairOverlap>, LrgeError> {
             receiver
                 .into_iter()
                 .par_bridge() // Parallelize the processing
-                .try_for_each(|record| -> Result<(), LrgeError> {
-                    let io::Message::Data((rid, seq)) = record;
-                    trace!("Processing read: {}", String::from_utf8_lossy(&rid));
-
-                    let qname = CString::new(rid.clone()).map_err(|e| {
-                        LrgeError::MapError(format!("Error converting read name to CString: {e}",))
-                    })?;
-
-                    // Use the shared aligner to perform alignment
-                    let mappings = aligner.map(&seq, Some(&qname)).map_err(|e| {
-                        LrgeError::MapError(format!(
-                            "Error mapping read {}: {}",
-                            String::from_utf8_lossy(&rid),
-                            e
-                        ))
-                    })?;
-
-                    {
-                        let mut ovlap_counter_lock = ovlap_counter.lock().unwrap();
-
-                        if !mappings.is_empty() {
-                            let mut writer_lock = writer.lock().unwrap();
-                            let mut seen_pairs_lock = seen_pairs.lock().unwrap();
-
-                            for mapping in &mappings {
-                                // write the PafRecord to the PAF file
-                                writer_lock.serialize(mapping)?;
-
-                                let tname = &mapping.target_name;
-
-                                if &rid == tname {
-                                    // Skip self-overlaps. if the qname is not in the ovlap_counter, we insert it with 0 overlaps
-                                    ovlap_counter_lock.entry(rid.clone()).or_insert(0);
-                                    continue;
-                                }
+                // each worker reuses one set of the targets a read has a chain to
+                .try_fold(
+                    || (HashSet::default(), Vec::new(), MIN_OVERLAP_BUFFER),
+                    |(mut chained, mut found, mut compact_at), (id, seq): (u32, Vec<u8>)| {
+                        let qname = index.seq_cname(id as usize);
+                        let rid = qname.to_bytes();
+                        trace!("Processing read: {}", String::from_utf8_lossy(rid));
+
+                        // Use the shared aligner to perform alignment
+                        let mappings =
+                            aligner
+                                .map(&seq, Some(qname))
+                                .map_err(|e| LrgeError::MapError {
+                                    read_id: String::from_utf8_lossy(rid).to_string(),
+                                    reason: e.to_string(),
+                                })?;
+
+                        chained.clear();
+                        for mapping in &mappings {
+                            let tid = mapping.target_id;
+
+                            // Skip self-overlaps
+                            if tid == id {
+                                continue;
+                            }
 
-                                if self.remove_internal
-                                    && mapping.is_internal(self.max_overhang_ratio)
-                                {
-                                    continue;
-                                }
+                            // minimap2 reports the chains in order of score, so the first is primary
+                            if self.primary_only && !chained.insert(tid) {
+                                continue;
+                            }
 
-                                let pair = if &rid < tname {
-                                    (rid.clone(), tname.clone())
-                                } else {
-                                    (tname.clone(), rid.clone())
-                                };
-                                if seen_pairs_lock.contains(&pair) {
-                                    continue;
-                                } else {
-                                    seen_pairs_lock.insert(pair);
-                                }
+                            if self.remove_internal
+                                && mapping
+                                    .is_internal(self.max_overhang_ratio, self.max_overhang_size)
+                            {
+                                continue;
+                            }
 
-                                *ovlap_counter_lock.entry(tname.clone()).or_insert(0) += 1;
-                                *ovlap_counter_lock.entry(rid.clone()).or_insert(0) += 1;
+                            if self.max_divergence.is_some_and(|max| mapping.dv > max) {
+                                continue;
+                            }
+                            if self
+                                .min_mapq
+                                .is_some_and(|min| mapping.mapq < u32::from(min))
+                            {
+                                continue;
                             }
+                            if self
+                                .min_overlap_len
+                                .is_some_and(|min| (mapping.block_len as usize) < min)
+                            {
+                                continue;
+                            }
+
+                            let query_bases = (mapping.query_end - mapping.query_start) as u32;
+                            let target_bases = (mapping.target_end - mapping.target_start) as u32;
+                            let contained = mapping
+                                .contained_read(self.max_overhang_ratio, self.max_overhang_size);
+                            let (query_contained, target_contained) = (
+                                contained == Some(Contained::Query),
+                                contained == Some(Contained::Target),
+                            );
+                            found.push(if id < tid {
+                                PairOverlap {
+                                    pair: pack_pair(id, tid),
+                                    bases: (query_bases, target_bases),
+                                    contained: (query_contained, target_contained),
+                                    duplicate: mapping.is_duplicate(),
+                                }
+                            } else {
+                                PairOverlap {
+                                    pair: pack_pair(id, tid),
+                                    bases: (target_bases, query_bases),
+                                    contained: (target_contained, query_contained),
+                                    duplicate: mapping.is_duplicate(),
+                                }
+                            });
+                        }
+                        if found.len() >= compact_at {
+                            found = dedup_overlaps(found);
+                            compact_at = (2 * found.len()).max(MIN_OVERLAP_BUFFER);
                         }
-                        // if the qname is not in the ovlap_counter, we insert it with 0 overlaps
-                        ovlap_counter_lock.entry(rid.clone()).or_insert(0);
-                    }
 
-                    Ok(())
-                })?;
-            Ok(())
-        })?;
+                        // hand the mappings to the writer thread to write to the overlaps file
+                        overlap_writer.write(mappings)?;
+
+                        Ok::<_, LrgeError>((chained, found, compact_at))
+                    },
+                )
+                .map(|buffer| buffer.map(|(_, found, _)| dedup_overlaps(found)))
+                // Reduce phase: the deduplicated buffers are merged pairwise, so only one overlap
+                // between each pair of reads is counted
+                .try_reduce(Vec::new, |a, b| Ok(merge_overlaps(a, b)))
+        });
+
+        // a mapping error may be caused by the writer thread failing, so check the writer first
+        overlap_writer.finish()?;
+        let overlaps = mapped?;
 
         // Wait for the producer to finish
         producer.join().map_err(|e| {
             LrgeError::ThreadError(format!("Thread panicked when joining: {e:?}",))
         })??;
 
-        debug!("Overlaps written to: {}", paf_path.to_string_lossy());
+        debug!("Overlaps written to: {}", overlaps_path.to_string_lossy());
+
+        let mut ovlap_counter = vec![0usize; num_ids];
+        // the total length of the overlaps of each read, on that read
+        let mut ovlap_bases = vec![0usize; num_ids];
+        let mut contained = vec![false; num_ids];
+        // of each pair of duplicates, the read with the larger ID is flagged, so one is kept
+        let mut duplicate = vec![false; num_ids];
+        for overlap in &overlaps {
+            let (a, b) = unpack_pair(overlap.pair);
+            ovlap_counter[a as usize] += 1;
+            ovlap_counter[b as usize] += 1;
+            ovlap_bases[a as usize] += overlap.bases.0 as usize;
+            ovlap_bases[b as usize] += overlap.bases.1 as usize;
+            contained[a as usize] |= overlap.contained.0;
+            contained[b as usize] |= overlap.contained.1;
+            if overlap.duplicate {
+                duplicate[b as usize] = true;
+            }
+        }
+
+        // collapse the duplicates, as if they had not been sampled - i.e., their overlaps are not
+        // counted for the reads they overlap, and they do not count towards the reads overlapped
+        let (mut num_reads, mut sum_len) = (self.num_reads, sum_len);
+        if self.exclude_duplicates {
+            for overlap in &overlaps {
+                let (a, b) = unpack_pair(overlap.pair);
+                let (a_bases, b_bases) = overlap.bases;
+                for (other, bases, dup) in [(a, a_bases, b), (b, b_bases, a)] {
+                    if duplicate[dup as usize] {
+                        ovlap_counter[other as usize] -= 1;
+                        ovlap_bases[other as usize] -= bases as usize;
+                    }
+                }
+            }
+            for id in (0..num_ids).filter(|&id| duplicate[id]) {
+                num_reads -= 1;
+                sum_len -= index.seq_len(id);
+            }
+        }
+        drop(overlaps);
 
-        let ovlap_counter = Arc::try_unwrap(ovlap_counter)
-            .unwrap()
-            .into_inner()
-            .unwrap();
-        let read_lengths = Arc::try_unwrap(read_lengths).unwrap().into_inner().unwrap();
         let no_mapping_count = AtomicU32::new(0);
-        let estimates = ovlap_counter
-            .par_iter()
-            .map(|(rid, n_ovlaps)| {
-                let est = if *n_ovlaps == 0 {
-                    no_mapping_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let num_contained = AtomicU32::new(0);
+        let num_duplicates = AtomicU32::new(0);
+        let num_excluded = AtomicU32::new(0);
+        let num_overlaps = AtomicU64::new(0);
+        let estimates: Vec<PerReadEstimate> = ovlap_counter
+            .into_par_iter()
+            .zip(ovlap_bases)
+            .zip(contained)
+            .zip(duplicate)
+            .enumerate()
+            .filter_map(|(id, (((n_ovlaps, n_bases), contained), duplicate))| {
+                let rid = index.seq_name(id);
+                if contained {
+                    num_contained.fetch_add(1, Ordering::Relaxed);
+                }
+                if duplicate {
+                    num_duplicates.fetch_add(1, Ordering::Relaxed);
+                }
+                if duplicate && self.exclude_duplicates {
+                    trace!("Excluding duplicate read: {}", String::from_utf8_lossy(rid));
+                    num_excluded.fetch_add(1, Ordering::Relaxed);
+                    return None;
+                }
+                if contained && self.exclude_contained {
+                    trace!("Excluding contained read: {}", String::from_utf8_lossy(rid));
+                    num_excluded.fetch_add(1, Ordering::Relaxed);
+                    return None;
+                }
+                num_overlaps.fetch_add(n_ovlaps as u64, Ordering::Relaxed);
+                let est = if n_ovlaps == 0 {
+                    no_mapping_count.fetch_add(1, Ordering::Relaxed);
                     trace!(
                         "No overlaps found for read: {}",
                         String::from_utf8_lossy(rid)
                     );
                     f32::INFINITY
                 } else {
-                    // safe to unwrap the Option here because we know the key exists
-                    let read_len = read_lengths.get(rid).unwrap();
-                    let avg_read_len = sum_len as f32 / (self.num_reads - 1) as f32;
-                    per_read_estimate(
-                        *read_len,
-                        avg_read_len,
-                        self.num_reads - 1,
-                        *n_ovlaps,
-                        overlap_threshold,
-                    )
+                    let avg_read_len = sum_len as f32 / (num_reads - 1) as f32;
+                    if self.length_weighted {
+                        self.estimator.estimate_length_weighted(
+                            index.seq_len(id),
+                            avg_read_len,
+                            num_reads - 1,
+                            n_bases,
+                            overlap_threshold,
+                        )
+                    } else {
+                        self.estimator.estimate(
+                            index.seq_len(id),
+                            avg_read_len,
+                            num_reads - 1,
+                            n_ovlaps,
+                            overlap_threshold,
+                        )
+                    }
                 };
                 trace!("Estimate for {}: {}", String::from_utf8_lossy(rid), est);
-                est
+                Some(PerReadEstimate {
+                    read_id: rid.to_vec(),
+                    estimate: est,
+                    mapped: n_ovlaps > 0,
+                })
             })
             .collect();
 
-        let no_mapping_count = no_mapping_count.load(std::sync::atomic::Ordering::Relaxed);
+        // the overlaps of a read are only all known once every read has been mapped
+        if let Some(sink) = &self.on_estimate {
+            estimates.iter().for_each(|est| sink.send(est));
+        }
+
+        let no_mapping_count = no_mapping_count.load(Ordering::Relaxed);
+        let flagged = FlaggedReads {
+            contained: num_contained.into_inner(),
+            duplicates: num_duplicates.into_inner(),
+            excluded: num_excluded.into_inner(),
+            overlaps: num_overlaps.into_inner(),
+        };
 
         if no_mapping_count > 0 {
-            let percent = (no_mapping_count as f32 / self.num_reads as f32) * 100.0;
+            // the same number of reads the no-mapping fraction is checked against
+            let num_estimated = num_estimated_reads(&estimates, &flagged);
+            let percent = (no_mapping_count as f32 / num_estimated as f32) * 100.0;
             info!(
                 "{} ({:.2}%) read(s) did not overlap any other reads",
                 no_mapping_count, percent
@@ -362,21 +728,251 @@ impl AvaStrategy {
             debug!("All reads had at least one overlap");
         }
 
-        Ok((estimates, no_mapping_count))
+        Ok((estimates, no_mapping_count, flagged))
     }
 }
 
 impl Estimate for AvaStrategy {
-    fn generate_estimates(&mut self) -> crate::Result<(Vec<f32>, u32)> {
+    fn input_num_reads(&self) -> Option<u64> {
+        self.input_num_reads
+    }
+
+    fn input_num_bases(&self) -> Option<u64> {
+        self.input_num_bases
+    }
+
+    fn input_num_skipped(&self) -> Option<u64> {
+        self.input_num_skipped
+    }
+
+    fn gc_content(&self) -> Option<GcSummary> {
+        self.gc_content.clone()
+    }
+
+    fn adapter_summary(&self) -> Option<AdapterSummary> {
+        self.adapter_summary
+    }
+
+    fn contained_fraction(&self) -> Option<f32> {
+        self.contained_fraction
+    }
+
+    fn duplicate_fraction(&self) -> Option<f32> {
+        self.duplicate_fraction
+    }
+
+    fn num_overlaps(&self) -> Option<u64> {
+        self.num_overlaps
+    }
+
+    fn raw_estimates(&self) -> Option<&RawEstimates> {
+        self.raw_estimates.as_ref()
+    }
+
+    fn retain_raw_estimates(&mut self, raw: RawEstimates) {
+        self.raw_estimates = Some(raw);
+    }
+
+    fn generate_estimates(&mut self) -> crate::Result<RawEstimates> {
+        let started = Instant::now();
+        self.prepare_tmpdir()?;
         let (reads_file, sum_len) = self.subsample_reads()?;
 
-        let preset = match self.platform {
-            Platform::PacBio => Preset::AvaPb,
-            Platform::Nanopore => Preset::AvaOnt,
+        let preset = self.platform.preset();
+
+        let plan = MemoryPlan::new(
+            self.max_memory,
+            sum_len,
+            self.num_reads,
+            sum_len as f32 / self.num_reads as f32,
+            self.mapping_threads(),
+            self.channel_capacity,
+            bytes_in_ram(&[&reads_file]),
+            self.num_reads.saturating_mul(PLANNED_OVERLAPS_PER_READ),
+        )?;
+        let aligner = AlignerWrapper::new(
+            &reads_file,
+            self.index_threads,
+            preset,
+            self.dual,
+            plan.index_batch_size,
+            &self.overrides,
+        )?;
+
+        let (estimates, no_mapping_count, flagged) =
+            self.align_reads(aligner, reads_file, sum_len, plan.channel_capacity)?;
+
+        let num_estimated = num_estimated_reads(&estimates, &flagged);
+        self.contained_fraction = Some(contained_fraction(
+            flagged.contained,
+            num_estimated,
+            self.exclude_contained,
+        ));
+        self.duplicate_fraction = Some(duplicate_fraction(
+            flagged.duplicates,
+            num_estimated,
+            self.exclude_duplicates,
+        ));
+        // each overlap is counted by both of its reads
+        self.num_overlaps = Some(flagged.overlaps.div_ceil(2));
+
+        write_per_read_estimates(&self.tmpdir.join(ESTIMATES_FILENAME), &estimates)?;
+        self.clean_up_artifacts()?;
+        check_no_mapping_fraction(
+            no_mapping_count,
+            num_estimated,
+            self.max_no_mapping_fraction,
+        )?;
+        Ok(RawEstimates::new(
+            estimates,
+            no_mapping_count,
+            started.elapsed(),
+        ))
+    }
+}
+
+/// An overlap between a pair of reads, as found in the map phase of [`AvaStrategy::align_reads`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PairOverlap {
+    /// The IDs of the reads, packed by [`pack_pair`]
+    pair: u64,
+    /// The length of the overlap on each read, the read with the smaller ID first
+    bases: (u32, u32),
+    /// Whether each read is contained in the other, the read with the smaller ID first
+    contained: (bool, bool),
+    /// Whether the reads are near-identical duplicates of each other
+    duplicate: bool,
+}
+
+impl PairOverlap {
+    /// Merge another overlap between the same pair of reads into this one. The bases of the
+    /// longest overlap are kept, and a read is contained, or the pair duplicates, if any overlap
+    /// says so - so the result is the same whatever order the overlaps are merged in.
+    fn merge(&mut self, other: &Self) {
+        debug_assert_eq!(self.pair, other.pair);
+        let len = |bases: (u32, u32)| u64::from(bases.0) + u64::from(bases.1);
+        if (len(other.bases), other.bases) > (len(self.bases), self.bases) {
+            self.bases = other.bases;
+        }
+        self.contained.0 |= other.contained.0;
+        self.contained.1 |= other.contained.1;
+        self.duplicate |= other.duplicate;
+    }
+}
+
+/// Sort the overlaps by pair, and merge the overlaps of each pair into one.
+fn dedup_overlaps(mut overlaps: Vec<PairOverlap>) -> Vec<PairOverlap> {
+    overlaps.sort_unstable_by_key(|overlap| overlap.pair);
+    overlaps.dedup_by(|next, kept| {
+        if next.pair == kept.pair {
+            kept.merge(next);
+            true
+        } else {
+            false
+        }
+    });
+    overlaps
+}
+
+/// Merge two lists of overlaps, each from [`dedup_overlaps`], into one sorted by pair with one
+/// overlap per pair.
+fn merge_overlaps(a: Vec<PairOverlap>, b: Vec<PairOverlap>) -> Vec<PairOverlap> {
+    if a.is_empty() {
+        return b;
+    } else if b.is_empty() {
+        return a;
+    }
+    let mut merged = Vec::with_capacity(a.len() + b.len());
+    let (mut a, mut b) = (a.into_iter().peekable(), b.into_iter().peekable());
+    while let (Some(x), Some(y)) = (a.peek(), b.peek()) {
+        let next = match x.pair.cmp(&y.pair) {
+            std::cmp::Ordering::Less => a.next(),
+            std::cmp::Ordering::Greater => b.next(),
+            std::cmp::Ordering::Equal => {
+                let mut overlap = *x;
+                overlap.merge(y);
+                b.next();
+                a.next().map(|_| overlap)
+            }
         };
+        merged.extend(next);
+    }
+    merged.extend(a);
+    merged.extend(b);
+    merged
+}
+
+/// Pack the pair of read IDs into a `u64`, which is the same for either order of the IDs.
+fn pack_pair(a: u32, b: u32) -> u64 {
+    (u64::from(a.min(b)) << 32) | u64::from(a.max(b))
+}
 
-        let aligner = AlignerWrapper::new(&reads_file, self.threads, preset, false)?;
+/// The IDs of a pair packed by [`pack_pair`], the smaller first.
+fn unpack_pair(pair: u64) -> (u32, u32) {
+    ((pair >> 32) as u32, pair as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_pair() {
+        assert_eq!(pack_pair(3, 7), pack_pair(7, 3));
+        assert_eq!(unpack_pair(pack_pair(7, 3)), (3, 7));
+        assert_eq!(unpack_pair(pack_pair(u32::MAX, 0)), (0, u32::MAX));
+        assert_ne!(pack_pair(1, 2), pack_pair(2, 2));
+    }
+
+    fn overlap(a: u32, b: u32, bases: (u32, u32)) -> PairOverlap {
+        PairOverlap {
+            pair: pack_pair(a, b),
+            bases,
+            contained: (false, false),
+            duplicate: false,
+        }
+    }
+
+    #[test]
+    fn test_dedup_overlaps_keeps_longest() {
+        let overlaps = dedup_overlaps(vec![
+            overlap(2, 3, (5, 6)),
+            overlap(2, 1, (20, 15)),
+            overlap(1, 2, (10, 20)),
+            overlap(1, 2, (8, 9)),
+        ]);
+        assert_eq!(overlaps, [overlap(1, 2, (20, 15)), overlap(2, 3, (5, 6))]);
+    }
+
+    #[test]
+    fn test_dedup_overlaps_flags_from_any_overlap() {
+        let mut contained = overlap(1, 2, (5, 5));
+        contained.contained = (false, true);
+        let mut duplicate = overlap(1, 2, (3, 3));
+        duplicate.duplicate = true;
+        let overlaps = dedup_overlaps(vec![overlap(1, 2, (10, 10)), contained, duplicate]);
+        assert_eq!(
+            overlaps,
+            [PairOverlap {
+                pair: pack_pair(1, 2),
+                bases: (10, 10),
+                contained: (false, true),
+                duplicate: true,
+            }]
+        );
+    }
 
-        self.align_reads(aligner, reads_file, sum_len)
+    #[test]
+    fn test_merge_overlaps() {
+        let a = dedup_overlaps(vec![overlap(1, 2, (10, 10)), overlap(3, 4, (5, 5))]);
+        let b = dedup_overlaps(vec![overlap(2, 1, (4, 4)), overlap(2, 3, (7, 7))]);
+        let expected = [
+            overlap(1, 2, (10, 10)),
+            overlap(2, 3, (7, 7)),
+            overlap(3, 4, (5, 5)),
+        ];
+        assert_eq!(merge_overlaps(a.clone(), b.clone()), expected);
+        assert_eq!(merge_overlaps(b, a.clone()), expected);
+        assert_eq!(merge_overlaps(a.clone(), Vec::new()), a);
     }
 }
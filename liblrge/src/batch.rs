@@ -0,0 +1,331 @@
+//! Run the estimates for many samples, sharing a thread pool and temporary directory between them.
+//!
+//! This is useful when embedding liblrge in a service or pipeline that estimates the genome size
+//! of many samples - e.g., a plate of isolates. Rather than every estimate building its own thread
+//! pool, all estimates map their reads in a single shared pool, and a fixed number of estimates
+//! are run at a time.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use liblrge::batch::{self, Strategy};
+//! use liblrge::{AvaStrategy, TwoSetStrategy};
+//!
+//! let strategies = vec![
+//!     Strategy::from(TwoSetStrategy::new("sample1.fastq")),
+//!     Strategy::from(AvaStrategy::new("sample2.fastq")),
+//! ];
+//!
+//! // run two estimates at a time
+//! let results = batch::run(strategies, 2).expect("Failed to run batch");
+//! for result in results {
+//!     match result {
+//!         Ok(est_result) => println!("{:?}", est_result.estimate),
+//!         Err(e) => eprintln!("Estimate failed: {e}"),
+//!     }
+//! }
+//! ```
+//!
+//! Use the [`Batch`] interface to customise the thread pool, temporary directory, and how the
+//! estimates are calculated.
+//!
+//! Each strategy in a batch is given its own subdirectory of the batch's temporary directory, which
+//! is removed once its estimate is done (unless [`Batch::keep_temp`] is set). The number of bytes of
+//! intermediate files written by each strategy is recorded in the [`BatchOutcome`], so callers can
+//! account for the temporary space a batch uses.
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use log::{debug, info};
+
+use crate::estimate::{validate_quantiles, EstimateResult, LOWER_QUANTILE, UPPER_QUANTILE};
+use crate::Estimate;
+
+pub use crate::strategy::Strategy;
+
+/// The result of a strategy and the number of bytes of intermediate files it wrote.
+type Outcome = (crate::Result<EstimateResult>, u64);
+
+/// The outcome of running a [`Batch`].
+pub struct BatchOutcome {
+    /// The result of each strategy, in the order the strategies were given.
+    pub results: Vec<crate::Result<EstimateResult>>,
+    /// The number of bytes of intermediate files written by each strategy, in the order the
+    /// strategies were given.
+    pub temp_bytes: Vec<u64>,
+}
+
+impl BatchOutcome {
+    /// The total number of bytes of intermediate files written by all strategies.
+    pub fn total_temp_bytes(&self) -> u64 {
+        self.temp_bytes.iter().sum()
+    }
+}
+
+/// Run many estimates, `concurrency` at a time, in a shared thread pool.
+///
+/// This uses the default [`Batch`] settings. See [`Batch`] to customise them.
+///
+/// # Returns
+///
+/// The result of each strategy, in the order the strategies were given.
+///
+/// # Errors
+///
+/// Returns an error if the shared thread pool cannot be built.
+pub fn run(
+    strategies: Vec<Strategy>,
+    concurrency: usize,
+) -> crate::Result<Vec<crate::Result<EstimateResult>>> {
+    Batch::new()
+        .concurrency(concurrency)
+        .run(strategies)
+        .map(|outcome| outcome.results)
+}
+
+/// A batch of estimates that share a thread pool and temporary directory.
+pub struct Batch {
+    concurrency: usize,
+    threads: usize,
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+    tmpdir: PathBuf,
+    keep_temp: bool,
+    finite: bool,
+    lower_quant: Option<f32>,
+    upper_quant: Option<f32>,
+}
+
+impl Default for Batch {
+    fn default() -> Self {
+        Self {
+            concurrency: 1,
+            threads: 1,
+            thread_pool: None,
+            tmpdir: std::env::temp_dir(),
+            keep_temp: false,
+            finite: true,
+            lower_quant: Some(LOWER_QUANTILE),
+            upper_quant: Some(UPPER_QUANTILE),
+        }
+    }
+}
+
+impl Batch {
+    /// Create a new batch with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of estimates to run at a time. By default, this is 1. The minimum is 1.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set the number of threads in the shared thread pool. By default, this is 1. A value of 0
+    /// means use all available cores.
+    ///
+    /// This is ignored if a thread pool is given with [`Batch::thread_pool`].
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Map the reads of all strategies in a caller-provided thread pool. By default (`None`), a new
+    /// pool with the number of threads given to [`Batch::threads`] is built.
+    pub fn thread_pool(mut self, thread_pool: Option<Arc<rayon::ThreadPool>>) -> Self {
+        self.thread_pool = thread_pool;
+        self
+    }
+
+    /// Set the directory in which each strategy's temporary directory is created. By default, this
+    /// is the `TMPDIR` environment variable. This overrides the temporary directory of each strategy.
+    pub fn tmpdir<P: AsRef<Path>>(mut self, tmpdir: P) -> Self {
+        self.tmpdir = tmpdir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Don't remove each strategy's temporary directory once its estimate is done. By default,
+    /// they are removed.
+    pub fn keep_temp(mut self, keep_temp: bool) -> Self {
+        self.keep_temp = keep_temp;
+        self
+    }
+
+    /// Set whether to only consider finite estimates. By default, this is `true`.
+    ///
+    /// See [`Estimate::estimate`] for more details.
+    pub fn finite(mut self, finite: bool) -> Self {
+        self.finite = finite;
+        self
+    }
+
+    /// Set the quantiles to calculate for each estimate. By default, these are [`LOWER_QUANTILE`]
+    /// and [`UPPER_QUANTILE`].
+    ///
+    /// See [`Estimate::estimate`] for more details.
+    pub fn quantiles(mut self, lower_quant: Option<f32>, upper_quant: Option<f32>) -> Self {
+        self.lower_quant = lower_quant;
+        self.upper_quant = upper_quant;
+        self
+    }
+
+    /// Run the estimate for each strategy.
+    ///
+    /// A strategy failing does not stop the others from running - its error is returned in its
+    /// place in [`BatchOutcome::results`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the quantiles are invalid (see [`Estimate::estimate`]), or if the shared
+    /// thread pool cannot be built.
+    pub fn run(self, strategies: Vec<Strategy>) -> crate::Result<BatchOutcome> {
+        validate_quantiles(self.lower_quant, self.upper_quant)?;
+        let pool = crate::mapping_pool(
+            self.thread_pool.as_ref(),
+            crate::resolve_threads(self.threads),
+        )?;
+        let num_strategies = strategies.len();
+        let concurrency = self.concurrency.min(num_strategies);
+        info!(
+            "Running {} estimates, {} at a time, with {} mapping threads",
+            num_strategies,
+            concurrency,
+            pool.current_num_threads()
+        );
+
+        let strategies: Vec<Mutex<Option<Strategy>>> = strategies
+            .into_iter()
+            .map(|s| Mutex::new(Some(s)))
+            .collect();
+        let outcomes: Vec<Mutex<Option<Outcome>>> =
+            (0..num_strategies).map(|_| Mutex::new(None)).collect();
+        let next = AtomicUsize::new(0);
+
+        std::thread::scope(|scope| {
+            for _ in 0..concurrency {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(slot) = strategies.get(i) else {
+                        break;
+                    };
+                    // each index is only taken by one thread, so the strategy is always present
+                    let mut strategy = slot.lock().unwrap().take().unwrap();
+                    let outcome = self.run_one(i, &mut strategy, &pool);
+                    *outcomes[i].lock().unwrap() = Some(outcome);
+                });
+            }
+        });
+
+        let (results, temp_bytes) = outcomes
+            .into_iter()
+            .map(|o| o.into_inner().unwrap().unwrap())
+            .unzip();
+
+        Ok(BatchOutcome {
+            results,
+            temp_bytes,
+        })
+    }
+
+    /// Run the estimate for a single strategy, returning the result and the number of bytes of
+    /// intermediate files it wrote.
+    fn run_one(
+        &self,
+        index: usize,
+        strategy: &mut Strategy,
+        pool: &Arc<rayon::ThreadPool>,
+    ) -> Outcome {
+        let tmpdir = self
+            .tmpdir
+            .join(format!("lrge-batch-{}-{index}", std::process::id()));
+        if let Err(e) = std::fs::create_dir_all(&tmpdir) {
+            return (Err(e.into()), 0);
+        }
+        debug!(
+            "Running estimate {index} in temporary directory {}",
+            tmpdir.to_string_lossy()
+        );
+
+        strategy.set_thread_pool(Arc::clone(pool));
+        strategy.set_tmpdir(tmpdir.clone());
+        let result = strategy.estimate(self.finite, self.lower_quant, self.upper_quant);
+
+        let temp_bytes = dir_size(&tmpdir);
+        if !self.keep_temp {
+            let _ = std::fs::remove_dir_all(&tmpdir);
+        }
+
+        (result, temp_bytes)
+    }
+}
+
+/// The total size, in bytes, of the files in a directory (not including subdirectories).
+fn dir_size(dir: &Path) -> u64 {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok()?.metadata().ok())
+                .filter(|m| m.is_file())
+                .map(|m| m.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AvaStrategy, TwoSetStrategy};
+    use std::io::Write;
+
+    #[test]
+    fn test_dir_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut file = std::fs::File::create(dir.path().join("a")).unwrap();
+        file.write_all(&[0; 100]).unwrap();
+        std::fs::write(dir.path().join("b"), [0; 20]).unwrap();
+        assert_eq!(dir_size(dir.path()), 120);
+        assert_eq!(dir_size(&dir.path().join("missing")), 0);
+    }
+
+    #[test]
+    fn test_batch_failed_strategies() {
+        let dir = tempfile::tempdir().unwrap();
+        let strategies = vec![
+            Strategy::from(TwoSetStrategy::new(dir.path().join("missing1.fq"))),
+            Strategy::from(AvaStrategy::new(dir.path().join("missing2.fq"))),
+        ];
+
+        let outcome = Batch::new()
+            .concurrency(2)
+            .tmpdir(dir.path())
+            .run(strategies)
+            .unwrap();
+
+        assert_eq!(outcome.results.len(), 2);
+        assert!(outcome.results.iter().all(|r| r.is_err()));
+        assert_eq!(outcome.temp_bytes.len(), 2);
+        // the temporary directory of each strategy is removed
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_batch_invalid_quantiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let strategies = vec![Strategy::from(TwoSetStrategy::new(
+            dir.path().join("missing.fq"),
+        ))];
+
+        let result = Batch::new()
+            .tmpdir(dir.path())
+            .quantiles(Some(0.7), Some(0.6))
+            .run(strategies);
+
+        assert!(matches!(
+            result,
+            Err(crate::error::LrgeError::InvalidQuantile(_))
+        ));
+    }
+}
@@ -0,0 +1,210 @@
+//! Pre-flight checks of an input file, so that problems are found before a long estimate is run.
+//!
+//! [`summarise_input`] reads the whole input once - which also checks that it decompresses - and
+//! counts its reads, bases, malformed records, and duplicate read IDs. The free space in the
+//! temporary directory, from [`available_space`], can be compared to
+//! [`InputSummary::temp_space_needed`].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use liblrge::check::{available_space, summarise_input};
+//!
+//! let summary = summarise_input("reads.fastq.gz").expect("Input could not be read");
+//! if summary.num_duplicates > 0 {
+//!     eprintln!("Duplicate read IDs: {:?}", summary.duplicate_ids);
+//! }
+//!
+//! let needed = summary.temp_space_needed(15_000);
+//! if available_space(std::env::temp_dir()).is_some_and(|space| space < needed) {
+//!     eprintln!("Not enough space in the temporary directory");
+//! }
+//! ```
+use std::hash::BuildHasher as _;
+use std::path::Path;
+
+use crate::hash::{BuildHasher, HashSet};
+use crate::io::iter_records;
+
+/// The maximum number of malformed records [`summarise_input`] skips. Any more is an error.
+pub const MAX_MALFORMED_RECORDS: usize = 1000;
+
+/// The maximum number of duplicated read IDs kept in [`InputSummary::duplicate_ids`].
+const MAX_REPORTED_DUPLICATES: usize = 10;
+
+/// The approximate number of bytes, other than the sequence, of a read written to the temporary
+/// directory - i.e., its ID and the FASTA formatting.
+const RECORD_OVERHEAD_BYTES: f64 = 64.0;
+
+/// A summary of an input file, from [`summarise_input`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputSummary {
+    /// The number of (well-formed) reads
+    pub num_reads: u64,
+    /// The total number of bases in the (well-formed) reads
+    pub num_bases: u64,
+    /// The number of malformed records, which were skipped
+    pub num_malformed: u64,
+    /// The number of reads with the same ID as an earlier read
+    pub num_duplicates: u64,
+    /// The first few duplicated read IDs
+    pub duplicate_ids: Vec<String>,
+}
+
+impl InputSummary {
+    /// The approximate disk space, in bytes, needed in the temporary directory to sample
+    /// `num_reads` reads from the input - the sampled reads, and the candidate reads written while
+    /// sampling. The overlaps are not included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::check::InputSummary;
+    ///
+    /// let summary = InputSummary {
+    ///     num_reads: 1_000_000,
+    ///     num_bases: 10_000_000_000,
+    ///     ..Default::default()
+    /// };
+    /// // ~20,000 candidate and sampled reads of ~10 kbp
+    /// let needed = summary.temp_space_needed(3_000);
+    /// assert!(needed > 200_000_000 && needed < 250_000_000);
+    /// ```
+    pub fn temp_space_needed(&self, num_reads: usize) -> u64 {
+        if self.num_reads == 0 || num_reads == 0 {
+            return 0;
+        }
+        let n = self.num_reads as f64;
+        let k = (num_reads as f64).min(n);
+        // the expected number of candidate reads written while sampling - see the sample module
+        let candidates = k * (1.0 + (n / k).ln());
+        let record_bytes = self.num_bases as f64 / n + RECORD_OVERHEAD_BYTES;
+        ((candidates + k) * record_bytes).ceil() as u64
+    }
+}
+
+/// Read all of the records in `input`, checking that it can be decompressed and parsed, and
+/// counting its reads, bases, malformed records, and duplicate read IDs.
+///
+/// Malformed records are logged and skipped, as with
+/// [`twoset::Builder::skip_bad_records`][crate::twoset::Builder::skip_bad_records], up to
+/// [`MAX_MALFORMED_RECORDS`]. To keep memory usage low for large inputs, read IDs are compared by
+/// a 64-bit hash, so there is a very small chance of a read being counted as a duplicate when it
+/// is not.
+///
+/// # Errors
+///
+/// Returns an [`IoError`][crate::error::LrgeError::IoError] if the input cannot be read or
+/// decompressed, or has more than [`MAX_MALFORMED_RECORDS`] malformed records.
+pub fn summarise_input<P: AsRef<Path>>(input: P) -> crate::Result<InputSummary> {
+    let mut summary = InputSummary::default();
+    let mut seen_ids: HashSet<u64> = HashSet::default();
+    let hasher = BuildHasher::default();
+
+    let num_malformed = iter_records(&input, Some(MAX_MALFORMED_RECORDS), |id, seq| {
+        summary.num_reads += 1;
+        summary.num_bases += seq.len() as u64;
+        if !seen_ids.insert(hasher.hash_one(id)) {
+            summary.num_duplicates += 1;
+            if summary.duplicate_ids.len() < MAX_REPORTED_DUPLICATES {
+                summary
+                    .duplicate_ids
+                    .push(String::from_utf8_lossy(id).into_owned());
+            }
+        }
+        Ok(())
+    })?;
+    summary.num_malformed = num_malformed as u64;
+
+    Ok(summary)
+}
+
+/// The space, in bytes, available to unprivileged users on the filesystem containing `path`. This
+/// is `None` if it cannot be determined - e.g., `path` does not exist, or the platform is not Unix.
+///
+/// # Examples
+///
+/// ```
+/// let space = liblrge::check::available_space(std::env::temp_dir());
+/// ```
+pub fn available_space<P: AsRef<Path>>(path: P) -> Option<u64> {
+    #[cfg(unix)]
+    {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = CString::new(path.as_ref().as_os_str().as_bytes()).ok()?;
+        let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+        // SAFETY: `path` is a valid C string, and `stats` is only read if statvfs succeeds
+        if unsafe { libc::statvfs(path.as_ptr(), stats.as_mut_ptr()) } != 0 {
+            return None;
+        }
+        let stats = unsafe { stats.assume_init() };
+        #[allow(clippy::unnecessary_cast)]
+        Some(stats.f_bavail as u64 * stats.f_frsize as u64)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_summarise_input() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            "@r1\nACGT\n+\n!!!!\n@r2 comment\nAC\n+\n!!\n@r1\nA\n+\n!\n@r3\nACGT\n+\n!!\n@r4\nAA\n+\n!!\n"
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        let summary = summarise_input(file.path()).unwrap();
+        assert_eq!(
+            summary,
+            InputSummary {
+                num_reads: 4,
+                num_bases: 9,
+                num_malformed: 1,
+                num_duplicates: 1,
+                duplicate_ids: vec!["r1".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_summarise_input_missing_file() {
+        assert!(summarise_input("does/not/exist.fq").is_err());
+    }
+
+    #[test]
+    fn test_temp_space_needed() {
+        let summary = InputSummary {
+            num_reads: 100,
+            num_bases: 100_000,
+            ..Default::default()
+        };
+        assert_eq!(summary.temp_space_needed(0), 0);
+        assert_eq!(InputSummary::default().temp_space_needed(10), 0);
+        // all reads are written once as candidates, and once as the sample
+        let all = summary.temp_space_needed(100);
+        assert_eq!(all, 2 * (100_000 + 100 * 64));
+        assert_eq!(summary.temp_space_needed(1_000), all);
+        assert!(summary.temp_space_needed(10) < all);
+    }
+
+    #[test]
+    fn test_available_space() {
+        let dir = tempfile::tempdir().unwrap();
+        if cfg!(unix) {
+            assert!(available_space(dir.path()).is_some());
+        }
+        assert!(available_space(dir.path().join("missing")).is_none());
+    }
+}
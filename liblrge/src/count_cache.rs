@@ -0,0 +1,162 @@
+//! Caching the number of reads in an input file in a sidecar file, so that repeated runs on the
+//! same input - e.g., parameter sweeps - do not count its reads again.
+//!
+//! The sidecar is written next to the input, with `.lrge-count` appended to its name. It records
+//! the size and modification time of the input when it was counted, and is only used while both are
+//! unchanged. Only counts of all the reads in the input, scanned without validating them, are
+//! cached, as any other count depends on more than the input.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use log::{debug, warn};
+
+/// The extension appended to the name of an input file to give its sidecar file.
+const SIDECAR_EXTENSION: &str = ".lrge-count";
+/// The first line of a sidecar file, which identifies its format.
+const HEADER: &str = "#lrge-count v1";
+
+/// The size and modification time of a file, which identify its contents.
+#[derive(Debug, PartialEq, Eq)]
+struct Stamp {
+    /// The size of the file, in bytes
+    size: u64,
+    /// The modification time of the file, in nanoseconds since the Unix epoch
+    mtime_ns: u128,
+}
+
+impl Stamp {
+    fn of(path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let mtime_ns = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_err(io::Error::other)?
+            .as_nanos();
+        Ok(Self {
+            size: metadata.len(),
+            mtime_ns,
+        })
+    }
+}
+
+/// The path of the sidecar file the read count of `input` is cached in.
+pub(crate) fn sidecar_path(input: &Path) -> PathBuf {
+    let mut name = input.as_os_str().to_owned();
+    name.push(SIDECAR_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// The number of reads and bases in `input` cached in its sidecar file, if there is one and `input`
+/// has not changed since it was written.
+pub(crate) fn read(input: &Path) -> Option<(usize, u64)> {
+    let path = sidecar_path(input);
+    let contents = fs::read_to_string(&path).ok()?;
+    let mut lines = contents.lines();
+    if lines.next() != Some(HEADER) {
+        debug!("Ignoring {}, as it is not a read count", path.display());
+        return None;
+    }
+    let mut size = None;
+    let mut mtime_ns = None;
+    let mut num_reads = None;
+    let mut num_bases = None;
+    for line in lines {
+        let (key, value) = line.split_once('\t')?;
+        match key {
+            "size" => size = value.parse().ok(),
+            "mtime_ns" => mtime_ns = value.parse().ok(),
+            "reads" => num_reads = value.parse().ok(),
+            "bases" => num_bases = value.parse().ok(),
+            _ => {}
+        }
+    }
+    let cached = Stamp {
+        size: size?,
+        mtime_ns: mtime_ns?,
+    };
+    if Stamp::of(input).ok()? != cached {
+        debug!(
+            "Ignoring {}, as the input has changed since it was written",
+            path.display()
+        );
+        return None;
+    }
+    Some((num_reads?, num_bases?))
+}
+
+/// Cache the number of reads and bases in `input` in its sidecar file. The cache only saves time,
+/// so a failure to write it - e.g., the input is in a read-only directory - is logged, rather than
+/// returned.
+pub(crate) fn write(input: &Path, (num_reads, num_bases): (usize, u64)) {
+    let path = sidecar_path(input);
+    let result = Stamp::of(input).and_then(|stamp| {
+        let contents = format!(
+            "{HEADER}\nsize\t{}\nmtime_ns\t{}\nreads\t{num_reads}\nbases\t{num_bases}\n",
+            stamp.size, stamp.mtime_ns
+        );
+        fs::write(&path, contents)
+    });
+    match result {
+        Ok(()) => debug!("Cached the read count in {}", path.display()),
+        Err(e) => warn!("Failed to cache the read count in {}: {e}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_sidecar_path() {
+        assert_eq!(
+            sidecar_path(Path::new("dir/reads.fq.gz")),
+            PathBuf::from("dir/reads.fq.gz.lrge-count")
+        );
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("reads.fq");
+        fs::write(&input, "@r1\nACGT\n+\nIIII\n").unwrap();
+        assert_eq!(read(&input), None);
+
+        write(&input, (1, 4));
+        assert!(sidecar_path(&input).exists());
+        assert_eq!(read(&input), Some((1, 4)));
+    }
+
+    #[test]
+    fn test_read_input_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("reads.fq");
+        fs::write(&input, "@r1\nACGT\n+\nIIII\n").unwrap();
+        write(&input, (1, 4));
+
+        let mut file = fs::OpenOptions::new().append(true).open(&input).unwrap();
+        file.write_all(b"@r2\nACGT\n+\nIIII\n").unwrap();
+        drop(file);
+        assert_eq!(read(&input), None);
+    }
+
+    #[test]
+    fn test_read_malformed() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("reads.fq");
+        fs::write(&input, "@r1\nACGT\n+\nIIII\n").unwrap();
+        let stamp = Stamp::of(&input).unwrap();
+
+        fs::write(sidecar_path(&input), "1\n").unwrap();
+        assert_eq!(read(&input), None);
+
+        let missing_bases = format!(
+            "{HEADER}\nsize\t{}\nmtime_ns\t{}\nreads\t1\n",
+            stamp.size, stamp.mtime_ns
+        );
+        fs::write(sidecar_path(&input), missing_bases).unwrap();
+        assert_eq!(read(&input), None);
+    }
+}
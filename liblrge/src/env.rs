@@ -0,0 +1,116 @@
+//! Default builder settings from environment variables.
+//!
+//! These allow container images and job scripts to configure a strategy without changing the code
+//! or command line that builds it.
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::error::LrgeError;
+use crate::seed::seed_from_str;
+use crate::Platform;
+
+/// The environment variable for the number of threads.
+pub const THREADS_VAR: &str = "LRGE_THREADS";
+/// The environment variable for the temporary directory.
+pub const TMPDIR_VAR: &str = "LRGE_TMPDIR";
+/// The environment variable for the random seed, which can be any string (see [`crate::seed`]).
+pub const SEED_VAR: &str = "LRGE_SEED";
+/// The environment variable for the sequencing platform.
+pub const PLATFORM_VAR: &str = "LRGE_PLATFORM";
+
+/// The builder settings given by environment variables. A setting is `None` if its variable is
+/// not set, or is empty.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct EnvDefaults {
+    pub(crate) threads: Option<usize>,
+    pub(crate) tmpdir: Option<PathBuf>,
+    pub(crate) seed: Option<u64>,
+    pub(crate) platform: Option<Platform>,
+}
+
+impl EnvDefaults {
+    /// Read the settings from the environment.
+    pub(crate) fn from_env() -> crate::Result<Self> {
+        Self::from_lookup(|var| std::env::var(var).ok())
+    }
+
+    /// Read the settings with `lookup`, which returns the value of a variable, if it is set.
+    fn from_lookup<F: Fn(&str) -> Option<String>>(lookup: F) -> crate::Result<Self> {
+        let get = |var: &str| lookup(var).filter(|v| !v.trim().is_empty());
+
+        Ok(Self {
+            threads: get(THREADS_VAR)
+                .map(|v| parse(THREADS_VAR, &v))
+                .transpose()?,
+            tmpdir: get(TMPDIR_VAR).map(PathBuf::from),
+            seed: get(SEED_VAR).map(|v| seed_from_str(v.trim())),
+            platform: get(PLATFORM_VAR)
+                .map(|v| parse(PLATFORM_VAR, &v))
+                .transpose()?,
+        })
+    }
+}
+
+fn parse<T: FromStr>(var: &'static str, value: &str) -> crate::Result<T> {
+    value.trim().parse().map_err(|_| {
+        LrgeError::invalid_configuration(var, format!("`{value}` is not a valid value"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |var| vars.get(var).cloned()
+    }
+
+    #[test]
+    fn test_from_lookup_unset() {
+        let defaults = EnvDefaults::from_lookup(lookup(&[(THREADS_VAR, "")])).unwrap();
+        assert_eq!(defaults, EnvDefaults::default());
+    }
+
+    #[test]
+    fn test_from_lookup() {
+        let defaults = EnvDefaults::from_lookup(lookup(&[
+            (THREADS_VAR, "8"),
+            (TMPDIR_VAR, "/scratch"),
+            (SEED_VAR, " 42 "),
+            (PLATFORM_VAR, "pb"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            defaults,
+            EnvDefaults {
+                threads: Some(8),
+                tmpdir: Some(PathBuf::from("/scratch")),
+                seed: Some(42),
+                platform: Some(Platform::PacBio),
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_lookup_invalid() {
+        let err = EnvDefaults::from_lookup(lookup(&[(THREADS_VAR, "abc")])).unwrap_err();
+        assert!(matches!(
+            err,
+            LrgeError::InvalidConfiguration {
+                option: THREADS_VAR,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_from_lookup_string_seed() {
+        let defaults = EnvDefaults::from_lookup(lookup(&[(SEED_VAR, " sample1 ")])).unwrap();
+        assert_eq!(defaults.seed, Some(seed_from_str("sample1")));
+    }
+}
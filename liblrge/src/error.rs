@@ -1,67 +1,203 @@
 //! Error handling for liblrge.
-use std::fmt;
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::memory::format_bytes;
 
 /// A custom error type to represent various errors in liblrge.
-#[derive(Debug)]
+///
+/// Variants carry the details of the failure (e.g., the file or read involved) so that callers can
+/// match on the cause, and the underlying error, where there is one, is available via
+/// [`source`][std::error::Error::source].
+#[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum LrgeError {
     /// An IO error occurred.
-    IoError(std::io::Error),
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
 
     /// A FASTQ parsing error occurred.
-    FastqParseError(String),
+    #[error("FASTQ parse error in {}: {source}", path.display())]
+    FastqParseError {
+        /// The file being parsed
+        path: PathBuf,
+        /// The underlying parsing error
+        #[source]
+        source: needletail::errors::ParseError,
+    },
 
-    /// Too many reads were requested.
-    TooManyReadsError(String),
-
-    /// Too few reads were requested.
-    TooFewReadsError(String),
+    /// The input file has too few reads.
+    #[error(
+        "Too few reads: the input file has {available} reads, but more than {required} are needed"
+    )]
+    TooFewReadsError {
+        /// The number of reads in the input file
+        available: usize,
+        /// The number of reads that the input file must have more than
+        required: usize,
+    },
 
     /// Invalid platform string.
+    #[error("Invalid platform: {0}")]
     InvalidPlatform(String),
 
-    /// Error when setting the number of threads
+    /// Invalid overlap format string.
+    #[error("Invalid overlap format: {0}")]
+    InvalidOverlapFormat(String),
+
+    /// The thread pool for mapping could not be built.
+    #[error("Error building thread pool: {0}")]
+    ThreadPoolError(#[from] rayon::ThreadPoolBuildError),
+
+    /// A thread panicked or stopped unexpectedly.
+    #[error("Error relating to threads: {0}")]
     ThreadError(String),
 
-    /// Error writing PAF file
-    PafWriteError(String),
+    /// A PAF file could not be parsed.
+    #[error("Error parsing PAF file {} at line {line}: {reason}", path.display())]
+    PafParseError {
+        /// The PAF file being parsed
+        path: PathBuf,
+        /// The (1-based) line that could not be parsed
+        line: usize,
+        /// Why the line could not be parsed
+        reason: String,
+    },
+
+    /// The minimap2 index could not be built.
+    #[error("Error building minimap2 index of {}: {reason}", path.display())]
+    IndexError {
+        /// The file being indexed
+        path: PathBuf,
+        /// Why the index could not be built
+        reason: String,
+    },
 
     /// Error mapping a read
-    MapError(String),
+    #[error("Error mapping read {read_id}: {reason}")]
+    MapError {
+        /// The identifier of the read
+        read_id: String,
+        /// Why the read could not be mapped
+        reason: String,
+    },
 
     /// Duplicate read identifiers were found.
-    DuplicateReadIdentifier(String),
+    #[error("Duplicate read identifier found in {}: {read_id}", path.display())]
+    DuplicateReadIdentifier {
+        /// The duplicated read identifier
+        read_id: String,
+        /// The file the duplicate was found in
+        path: PathBuf,
+    },
+
+    /// The strategy cannot fit within the requested memory budget.
+    #[error(
+        "Memory budget exceeded: approximately {} is needed to index {index_bases} bases and keep \
+         track of {num_reads} reads with {threads} thread(s), but the budget is {}. Try requesting \
+         fewer reads or threads",
+        format_bytes(*.required),
+        format_bytes(*.budget)
+    )]
+    MemoryBudgetError {
+        /// The approximate number of bytes needed
+        required: usize,
+        /// The memory budget, in bytes
+        budget: usize,
+        /// The number of bases to index
+        index_bases: usize,
+        /// The number of reads to keep track of
+        num_reads: usize,
+        /// The number of mapping threads
+        threads: usize,
+    },
+
+    /// A file of saved per-read estimates could not be parsed.
+    #[error(
+        "Error parsing per-read estimates: line {line} of {} is not a valid per-read estimate",
+        path.display()
+    )]
+    EstimatesParseError {
+        /// The file of per-read estimates
+        path: PathBuf,
+        /// The (1-based) line number that could not be parsed
+        line: usize,
+    },
+
+    /// Too many reads did not overlap any other reads.
+    #[error(
+        "Quality control failed: {no_mapping_count} of {num_reads} reads ({:.2}%) did not \
+         overlap, which is more than the maximum of {:.2}%",
+        *.no_mapping_count as f32 / *.num_reads as f32 * 100.0,
+        *.max_fraction * 100.0
+    )]
+    QualityControl {
+        /// The number of reads without an overlap
+        no_mapping_count: u32,
+        /// The number of reads an estimate was generated for
+        num_reads: usize,
+        /// The maximum allowed fraction of reads without an overlap
+        max_fraction: f32,
+    },
+
+    /// An invalid quantile was requested.
+    #[error("Invalid quantile: {0}")]
+    InvalidQuantile(String),
+
+    /// An [`Aggregator`][crate::estimate::Aggregator] could not combine the per-read estimates.
+    #[error("Failed to aggregate the per-read estimates: {0}")]
+    AggregationError(String),
+
+    /// A strategy builder was given an invalid configuration.
+    #[error("Invalid configuration for {option}: {reason}")]
+    InvalidConfiguration {
+        /// The builder option that is invalid
+        option: &'static str,
+        /// Why the option is invalid
+        reason: String,
+    },
 }
 
-impl fmt::Display for LrgeError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            LrgeError::IoError(err) => write!(f, "IO error: {err}",),
-            LrgeError::FastqParseError(msg) => write!(f, "FASTQ parse error: {msg}",),
-            LrgeError::TooManyReadsError(msg) => write!(f, "Too many reads requested: {msg}",),
-            LrgeError::TooFewReadsError(msg) => write!(f, "Too few reads requested: {msg}",),
-            LrgeError::InvalidPlatform(msg) => write!(f, "Invalid platform: {msg}",),
-            LrgeError::ThreadError(msg) => write!(f, "Error relating to threads: {msg}",),
-            LrgeError::PafWriteError(msg) => write!(f, "Error writing PAF file: {msg}",),
-            LrgeError::MapError(msg) => write!(f, "Error mapping a read: {msg}",),
-            LrgeError::DuplicateReadIdentifier(id) => {
-                write!(f, "Duplicate read identifier found: {id}",)
-            }
+impl LrgeError {
+    /// An [`InvalidConfiguration`][LrgeError::InvalidConfiguration] error for `option`.
+    pub(crate) fn invalid_configuration(option: &'static str, reason: impl Into<String>) -> Self {
+        LrgeError::InvalidConfiguration {
+            option,
+            reason: reason.into(),
         }
     }
 }
 
-impl std::error::Error for LrgeError {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
 
-/// Converts a `std::io::Error` into an [`LrgeError`].
-impl From<std::io::Error> for LrgeError {
-    fn from(error: std::io::Error) -> Self {
-        LrgeError::IoError(error)
+    #[test]
+    fn test_source_is_preserved() {
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+        let err = LrgeError::from(io);
+        let source = err.source().unwrap();
+        assert!(source.downcast_ref::<std::io::Error>().is_some());
     }
-}
 
-/// Converts a `csv::Error` into an [`LrgeError`].
-impl From<csv::Error> for LrgeError {
-    fn from(error: csv::Error) -> Self {
-        LrgeError::PafWriteError(error.to_string())
+    #[test]
+    fn test_structured_display() {
+        let err = LrgeError::DuplicateReadIdentifier {
+            read_id: "read1".to_string(),
+            path: PathBuf::from("reads.fq"),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Duplicate read identifier found in reads.fq: read1"
+        );
+
+        let err = LrgeError::QualityControl {
+            no_mapping_count: 3,
+            num_reads: 10,
+            max_fraction: 0.2,
+        };
+        assert!(err.to_string().contains("3 of 10 reads (30.00%)"));
     }
 }
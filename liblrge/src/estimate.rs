@@ -1,10 +1,38 @@
 //! A trait for generating genome size estimates, and calculating the median of those estimates.
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use log::{debug, info};
+
+use crate::adapter::AdapterSummary;
+use crate::error::LrgeError;
+use crate::gc::GcSummary;
+use crate::hash::{HashMap, HashSet};
+use crate::io::iter_records;
+use crate::poisson::PoissonInterval;
 
 /// The lower quantile we found to give the highest confidence in our analysis.
 pub const LOWER_QUANTILE: f32 = 0.15;
 /// The upper quantile we found to give the highest confidence in our analysis.
 pub const UPPER_QUANTILE: f32 = 0.65;
+/// The default minimum fraction of reads with a finite estimate for [`EstimateResult::outcome`] to
+/// consider there to be enough overlap for a meaningful estimate.
+pub const MIN_FINITE_FRACTION: f32 = 0.1;
+/// The intervals between quantiles of the per-read estimates whose confidence was calibrated in
+/// [our analysis][doi], as `(lower quantile, upper quantile, confidence)` - i.e., the fraction of
+/// the genomes whose true size was within the interval.
+///
+/// [doi]: https://doi.org/10.1101/2024.11.27.625777
+pub const CALIBRATED_INTERVALS: &[(f32, f32, f32)] = &[(LOWER_QUANTILE, UPPER_QUANTILE, 0.92)];
 
+/// The result of [`Estimate::estimate`].
+///
+/// With the `serde` feature enabled, this can be serialized and deserialized. Note that some
+/// formats, such as JSON, cannot represent an infinite estimate.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EstimateResult {
     /// The lower quantile of the estimates
     pub lower: Option<f32>,
@@ -14,17 +42,651 @@ pub struct EstimateResult {
     pub upper: Option<f32>,
     /// The number of reads that did not have an overlap
     pub no_mapping_count: u32,
+    /// The number of reads that an estimate was generated for (including those without an overlap)
+    pub num_reads: u32,
+    /// The number of reads with a finite estimate - i.e., that overlapped at least one other read
+    pub num_finite: u32,
+    /// The number of reads in the input file, if known
+    pub input_num_reads: Option<u64>,
+    /// The total number of bases in the input file, if known
+    pub input_num_bases: Option<u64>,
+    /// The number of malformed records in the input file that were skipped, if known
+    pub input_num_skipped: Option<u64>,
+    /// The GC content of the sampled reads, if known
+    pub gc_content: Option<GcSummary>,
+    /// The fraction of the reads that are contained in a read they overlap, if known
+    pub contained_fraction: Option<f32>,
+    /// The fraction of the reads that are a duplicate of a read they overlap, if known
+    pub duplicate_fraction: Option<f32>,
+    /// How many of the sampled reads had adapters removed, if they were scanned for them
+    pub adapters: Option<AdapterSummary>,
+    /// A confidence interval of the estimate from a Poisson model of the number of overlaps, if
+    /// the number of overlaps is known - see [`PoissonInterval`]
+    pub poisson_interval: Option<PoissonInterval>,
+    /// Any other quantiles of the estimates that were requested (see [`Median::with_quantiles`]),
+    /// as `(quantile, value)` pairs in the order they were requested. This is empty if there are
+    /// no estimates
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub quantiles: Vec<(f32, f32)>,
+}
+
+impl EstimateResult {
+    /// The fraction of the reads with a finite estimate. This is `None` if there are no reads.
+    pub fn finite_fraction(&self) -> Option<f32> {
+        (self.num_reads > 0).then(|| self.num_finite as f32 / self.num_reads as f32)
+    }
+
+    /// Whether there was enough overlap between the reads for the estimate to be meaningful. When
+    /// coverage is very low, most reads do not overlap another read, so most per-read estimates are
+    /// infinite and their median says little about the genome size.
+    ///
+    /// The outcome is [`EstimateOutcome::InsufficientOverlap`] if there is no estimate, the
+    /// estimate is infinite, or fewer than `min_finite_fraction` of the reads have a finite
+    /// estimate. [`MIN_FINITE_FRACTION`] is a reasonable default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::estimate::{EstimateOutcome, EstimateResult};
+    ///
+    /// let result = EstimateResult {
+    ///     lower: None,
+    ///     estimate: Some(4.5e6),
+    ///     upper: None,
+    ///     no_mapping_count: 95,
+    ///     num_reads: 100,
+    ///     num_finite: 5,
+    ///     input_num_reads: None,
+    ///     input_num_bases: None,
+    ///     input_num_skipped: None,
+    ///     gc_content: None,
+    ///     contained_fraction: None,
+    ///     duplicate_fraction: None,
+    ///     adapters: None,
+    ///     poisson_interval: None,
+    ///     quantiles: Vec::new(),
+    /// };
+    /// assert_eq!(result.outcome(0.01), EstimateOutcome::Estimated(4.5e6));
+    /// assert_eq!(
+    ///     result.outcome(0.1),
+    ///     EstimateOutcome::InsufficientOverlap {
+    ///         finite_fraction: 0.05
+    ///     }
+    /// );
+    /// ```
+    pub fn outcome(&self, min_finite_fraction: f32) -> EstimateOutcome {
+        let finite_fraction = self.finite_fraction().unwrap_or_default();
+        match self.estimate {
+            Some(est) if est.is_finite() && finite_fraction >= min_finite_fraction => {
+                EstimateOutcome::Estimated(est)
+            }
+            _ => EstimateOutcome::InsufficientOverlap { finite_fraction },
+        }
+    }
+
+    /// The interval between the `lower_quant` and `upper_quant` quantiles of the estimates, if
+    /// both are in [`EstimateResult::quantiles`]. Its confidence is given if the pair of quantiles
+    /// was calibrated - see [`calibrated_confidence`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use liblrge::estimate::{Median, LOWER_QUANTILE, UPPER_QUANTILE};
+    /// use liblrge::{twoset::Builder, Estimate};
+    ///
+    /// let median = Median::new(None, None)
+    ///     .unwrap()
+    ///     .with_quantiles(&[LOWER_QUANTILE, UPPER_QUANTILE])
+    ///     .unwrap();
+    /// let mut strategy = Builder::new().build("path/to/reads.fastq");
+    /// let est_result = strategy.estimate_with(true, &median).unwrap();
+    /// let interval = est_result.interval(LOWER_QUANTILE, UPPER_QUANTILE).unwrap();
+    /// println!("{}: {} - {}", interval.label(), interval.lower, interval.upper);
+    /// ```
+    pub fn interval(&self, lower_quant: f32, upper_quant: f32) -> Option<QuantileInterval> {
+        let value = |quant: f32| {
+            self.quantiles
+                .iter()
+                .find(|(q, _)| *q == quant)
+                .map(|&(_, value)| value)
+        };
+        Some(QuantileInterval {
+            lower_quantile: lower_quant,
+            upper_quantile: upper_quant,
+            lower: value(lower_quant)?,
+            upper: value(upper_quant)?,
+            confidence: calibrated_confidence(lower_quant, upper_quant),
+        })
+    }
+}
+
+/// An interval between two quantiles of the per-read estimates, from
+/// [`EstimateResult::interval`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QuantileInterval {
+    /// The lower quantile
+    pub lower_quantile: f32,
+    /// The upper quantile
+    pub upper_quantile: f32,
+    /// The lower quantile of the estimates
+    pub lower: f32,
+    /// The upper quantile of the estimates
+    pub upper: f32,
+    /// The calibrated confidence of the interval, if the quantiles are one of the
+    /// [`CALIBRATED_INTERVALS`]
+    pub confidence: Option<f32>,
+}
+
+impl QuantileInterval {
+    /// A label for the interval - its calibrated confidence (e.g., `92% CI`), or the quantiles it
+    /// is between (e.g., `q0.25-q0.75`) if it was not calibrated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::estimate::QuantileInterval;
+    ///
+    /// let mut interval = QuantileInterval {
+    ///     lower_quantile: 0.15,
+    ///     upper_quantile: 0.65,
+    ///     lower: 3e6,
+    ///     upper: 5e6,
+    ///     confidence: Some(0.92),
+    /// };
+    /// assert_eq!(interval.label(), "92% CI");
+    /// interval.confidence = None;
+    /// assert_eq!(interval.label(), "q0.15-q0.65");
+    /// ```
+    pub fn label(&self) -> String {
+        match self.confidence {
+            Some(confidence) => format!("{:.0}% CI", confidence * 100.0),
+            None => format!("q{}-q{}", self.lower_quantile, self.upper_quantile),
+        }
+    }
+}
+
+/// The calibrated confidence of the interval between the `lower_quant` and `upper_quant` quantiles
+/// of the per-read estimates, if it is one of the [`CALIBRATED_INTERVALS`].
+///
+/// # Examples
+///
+/// ```
+/// use liblrge::estimate::{calibrated_confidence, LOWER_QUANTILE, UPPER_QUANTILE};
+///
+/// assert_eq!(calibrated_confidence(LOWER_QUANTILE, UPPER_QUANTILE), Some(0.92));
+/// assert_eq!(calibrated_confidence(0.25, 0.75), None);
+/// ```
+pub fn calibrated_confidence(lower_quant: f32, upper_quant: f32) -> Option<f32> {
+    CALIBRATED_INTERVALS
+        .iter()
+        .find(|&&(lower, upper, _)| lower == lower_quant && upper == upper_quant)
+        .map(|&(_, _, confidence)| confidence)
+}
+
+/// Whether an [`EstimateResult`] has a meaningful genome size estimate - see
+/// [`EstimateResult::outcome`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EstimateOutcome {
+    /// There was enough overlap between the reads, and this is the genome size estimate
+    Estimated(f32),
+    /// Too few reads overlapped another read for the estimate to be meaningful. More reads, or
+    /// deeper sequencing, are needed
+    InsufficientOverlap {
+        /// The fraction of the reads with a finite estimate
+        finite_fraction: f32,
+    },
+}
+
+/// The name of the file, within a strategy's temporary directory, that the per-read estimates are
+/// written to.
+pub const ESTIMATES_FILENAME: &str = "estimates.tsv";
+
+/// The name of the file, within a strategy's temporary directory, that the overlaps are written to,
+/// in PAF format.
+pub const OVERLAPS_FILENAME: &str = "overlaps.paf";
+
+/// The genome size estimate for a single read.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PerReadEstimate {
+    /// The identifier of the read. With the `serde` feature, this is (de)serialized as a string
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::minimap2::mapping::serialize_bytes",
+            deserialize_with = "crate::minimap2::mapping::deserialize_bytes"
+        )
+    )]
+    pub read_id: Vec<u8>,
+    /// The genome size estimate for the read. This is infinite if the read had no overlaps
+    pub estimate: f32,
+    /// Whether the read had any overlaps
+    pub mapped: bool,
+}
+
+/// The per-read estimates generated by [`Estimate::generate_estimates`], along with the counts and
+/// timing of generating them, which [`Estimate::estimate`] aggregates into a genome size estimate.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RawEstimates {
+    /// The genome size estimate for each read. These may be finite or infinite, and are in no
+    /// particular order
+    pub per_read: Vec<PerReadEstimate>,
+    /// The number of reads that did not have an overlap
+    pub no_mapping_count: u32,
+    /// How long it took to generate the estimates, including sampling and overlapping the reads
+    pub elapsed: Duration,
+}
+
+impl RawEstimates {
+    /// The per-read estimates, the number of reads that did not have an overlap, and how long it
+    /// took to generate them.
+    pub fn new(per_read: Vec<PerReadEstimate>, no_mapping_count: u32, elapsed: Duration) -> Self {
+        Self {
+            per_read,
+            no_mapping_count,
+            elapsed,
+        }
+    }
+
+    /// The number of reads an estimate was generated for, including those without an overlap.
+    pub fn num_reads(&self) -> usize {
+        self.per_read.len()
+    }
+
+    /// The number of reads with a finite estimate - i.e., that overlapped at least one other read.
+    pub fn num_finite(&self) -> usize {
+        self.per_read
+            .iter()
+            .filter(|e| e.estimate.is_finite())
+            .count()
+    }
+
+    /// The per-read genome size estimates, sorted in ascending order, with or without the infinite
+    /// estimates. This is what an [`Aggregator`] is given.
+    pub fn sorted_values(&self, finite: bool) -> Vec<f32> {
+        let mut values: Vec<f32> = self
+            .per_read
+            .iter()
+            .map(|e| e.estimate)
+            .filter(|x| !finite || x.is_finite())
+            .collect();
+        values.sort_by(|a, b| a.total_cmp(b));
+        values
+    }
+}
+
+/// Check that no more than `max_fraction` of `num_reads` reads had no overlaps.
+pub(crate) fn check_no_mapping_fraction(
+    no_mapping_count: u32,
+    num_reads: usize,
+    max_fraction: Option<f32>,
+) -> crate::Result<()> {
+    let Some(max_fraction) = max_fraction else {
+        return Ok(());
+    };
+    if num_reads == 0 {
+        return Ok(());
+    }
+
+    let fraction = no_mapping_count as f32 / num_reads as f32;
+    if fraction > max_fraction {
+        return Err(LrgeError::QualityControl {
+            no_mapping_count,
+            num_reads,
+            max_fraction,
+        });
+    }
+    Ok(())
+}
+
+/// The number of reads that were estimated, given their `estimates` - which do not include the
+/// reads excluded for being contained or duplicates, so these are added back from `flagged`. This is
+/// what the fraction of reads without overlaps is reported and checked against.
+pub(crate) fn num_estimated_reads(estimates: &[PerReadEstimate], flagged: &FlaggedReads) -> usize {
+    estimates.len() + flagged.excluded as usize
+}
+
+/// The number of reads flagged by their overlaps, and of the overlaps counted, when the per-read
+/// estimates are generated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FlaggedReads {
+    /// The number of reads contained in a read they overlap
+    pub(crate) contained: u32,
+    /// The number of reads that are a duplicate of a read they overlap
+    pub(crate) duplicates: u32,
+    /// The number of flagged reads that were excluded from the estimates
+    pub(crate) excluded: u32,
+    /// The number of overlaps counted in the estimates of the reads that were not excluded
+    pub(crate) overlaps: u64,
+}
+
+impl std::ops::AddAssign for FlaggedReads {
+    fn add_assign(&mut self, other: Self) {
+        self.contained += other.contained;
+        self.duplicates += other.duplicates;
+        self.excluded += other.excluded;
+        self.overlaps += other.overlaps;
+    }
+}
+
+/// Log how many of `num_reads` reads are contained in a read they overlap, and return them as a
+/// fraction.
+pub(crate) fn contained_fraction(num_contained: u32, num_reads: usize, excluded: bool) -> f32 {
+    flagged_fraction(num_contained, num_reads, excluded, "contained in")
+}
+
+/// Log how many of `num_reads` reads are a duplicate of a read they overlap, and return them as a
+/// fraction.
+pub(crate) fn duplicate_fraction(num_duplicates: u32, num_reads: usize, excluded: bool) -> f32 {
+    flagged_fraction(num_duplicates, num_reads, excluded, "a duplicate of")
+}
+
+/// Log how many of `num_reads` reads are `relation` a read they overlap, and return them as a
+/// fraction.
+fn flagged_fraction(num_flagged: u32, num_reads: usize, excluded: bool, relation: &str) -> f32 {
+    let fraction = if num_reads == 0 {
+        0.0
+    } else {
+        num_flagged as f32 / num_reads as f32
+    };
+    if num_flagged > 0 {
+        info!(
+            "{} ({:.2}%) read(s) are {relation} a read they overlap{}",
+            num_flagged,
+            fraction * 100.0,
+            if excluded {
+                ", and were excluded from the estimate"
+            } else {
+                ""
+            }
+        );
+    } else {
+        debug!("No reads are {relation} a read they overlap");
+    }
+    fraction
+}
+
+/// Write the per-read estimates to a tab-delimited file with the columns `read_id`, `estimate`,
+/// and `mapped`.
+pub(crate) fn write_per_read_estimates(
+    path: &Path,
+    estimates: &[PerReadEstimate],
+) -> crate::Result<()> {
+    let mut writer = File::create(path).map(BufWriter::new)?;
+    writeln!(writer, "read_id\testimate\tmapped")?;
+    for est in estimates {
+        writeln!(
+            writer,
+            "{}\t{}\t{}",
+            String::from_utf8_lossy(&est.read_id),
+            est.estimate,
+            est.mapped
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// A read that did not overlap any other read, from [`reads_without_overlaps`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoOverlapRead {
+    /// The identifier of the read
+    pub read_id: Vec<u8>,
+    /// The length of the read
+    pub len: usize,
+}
+
+/// The reads among `estimates` that did not overlap any other read, with their lengths, in the
+/// order of `estimates`. Inspecting some of them - e.g., with BLAST - can show whether a high
+/// [`no_mapping_count`][EstimateResult::no_mapping_count] is due to contamination, adapters, or
+/// short junk reads.
+///
+/// The lengths are read from `reads`, which should be the reads that were estimated - i.e., the
+/// [`Query`][crate::artifact::Artifact::Query] reads of the
+/// [`TwoSetStrategy`][crate::TwoSetStrategy], or the [`Reads`][crate::artifact::Artifact::Reads] of
+/// the [`AvaStrategy`][crate::AvaStrategy]. Reads that are not in `reads` are left out.
+///
+/// # Errors
+///
+/// Returns an [`IoError`][LrgeError::IoError] if `reads` cannot be read.
+///
+/// # Examples
+///
+/// ```no_run
+/// use liblrge::estimate::{reads_without_overlaps, SavedEstimates};
+///
+/// let estimates = SavedEstimates::new("path/to/tmpdir").read().unwrap();
+/// for read in reads_without_overlaps(&estimates, "path/to/tmpdir/query.fa").unwrap() {
+///     println!("{}\t{}", String::from_utf8_lossy(&read.read_id), read.len);
+/// }
+/// ```
+pub fn reads_without_overlaps<P: AsRef<Path>>(
+    estimates: &[PerReadEstimate],
+    reads: P,
+) -> crate::Result<Vec<NoOverlapRead>> {
+    let unmapped: HashSet<&[u8]> = estimates
+        .iter()
+        .filter(|e| !e.mapped)
+        .map(|e| e.read_id.as_slice())
+        .collect();
+    if unmapped.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut lengths: HashMap<Vec<u8>, usize> = HashMap::default();
+    iter_records(reads, None, |id, seq| {
+        if unmapped.contains(id) {
+            lengths.insert(id.to_vec(), seq.len());
+        }
+        Ok(())
+    })?;
+
+    Ok(estimates
+        .iter()
+        .filter(|e| !e.mapped)
+        .filter_map(|e| {
+            lengths.get(&e.read_id).map(|&len| NoOverlapRead {
+                read_id: e.read_id.clone(),
+                len,
+            })
+        })
+        .collect())
+}
+
+/// Per-read estimates saved by a previous run of a strategy.
+///
+/// This allows the final estimate and quantiles to be recalculated - e.g., with different quantiles
+/// or finiteness settings - without sampling and overlapping the reads again.
+///
+/// # Examples
+///
+/// ```no_run
+/// use liblrge::estimate::SavedEstimates;
+/// use liblrge::Estimate;
+///
+/// // the temporary directory of a previous run, or the estimates file within it
+/// let mut saved = SavedEstimates::new("path/to/tmpdir");
+/// let est_result = saved.estimate(true, Some(0.25), Some(0.75)).expect("Failed to reestimate");
+/// ```
+pub struct SavedEstimates {
+    path: PathBuf,
+}
+
+impl SavedEstimates {
+    /// Load per-read estimates from `path`. This can be either the temporary directory of a previous
+    /// run, in which case the [`ESTIMATES_FILENAME`] file within it is used, or the estimates file
+    /// itself.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        let path = if path.is_dir() {
+            path.join(ESTIMATES_FILENAME)
+        } else {
+            path.to_path_buf()
+        };
+        Self { path }
+    }
+
+    /// Read the per-read estimates from the file.
+    pub fn read(&self) -> crate::Result<Vec<PerReadEstimate>> {
+        let reader = File::open(&self.path).map(BufReader::new)?;
+        let mut estimates = Vec::new();
+
+        for (i, line) in reader.lines().enumerate().skip(1) {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = || LrgeError::EstimatesParseError {
+                path: self.path.clone(),
+                line: i + 1,
+            };
+
+            let mut fields = line.split('\t');
+            let (Some(read_id), Some(estimate), Some(mapped), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid());
+            };
+            estimates.push(PerReadEstimate {
+                read_id: read_id.as_bytes().to_vec(),
+                estimate: estimate.parse().map_err(|_| invalid())?,
+                mapped: mapped.parse().map_err(|_| invalid())?,
+            });
+        }
+
+        Ok(estimates)
+    }
+}
+
+impl Estimate for SavedEstimates {
+    fn generate_estimates(&mut self) -> crate::Result<RawEstimates> {
+        let started = Instant::now();
+        let estimates = self.read()?;
+        let no_mapping_count = estimates.iter().filter(|e| !e.mapped).count() as u32;
+        Ok(RawEstimates::new(
+            estimates,
+            no_mapping_count,
+            started.elapsed(),
+        ))
+    }
 }
 
-/// This trait provides methods to generate estimates and calculate the median
-/// of those estimates, both with and without considering infinite values.
+/// This trait provides methods to generate estimates and aggregate them - by default, taking the
+/// median - both with and without considering infinite values.
 pub trait Estimate {
-    /// Generate a list of genome size estimates.
+    /// Generate the genome size estimate for each read.
     ///
     /// # Returns
     ///
-    /// A `Vec<f32>` containing the generated estimates. These estimates may be finite or infinite.
-    fn generate_estimates(&mut self) -> crate::Result<(Vec<f32>, u32)>;
+    /// The [`RawEstimates`] - an estimate for each read, which may be finite or infinite, along
+    /// with the number of reads that did not have an overlap and how long it took.
+    fn generate_estimates(&mut self) -> crate::Result<RawEstimates>;
+
+    /// The number of reads in the input file, if known. This is counted while sampling reads, so is
+    /// only available after the estimates have been generated.
+    ///
+    /// By default, this is `None`.
+    fn input_num_reads(&self) -> Option<u64> {
+        None
+    }
+
+    /// The total number of bases in the input file, if known. This is counted while sampling reads,
+    /// so is only available after the estimates have been generated.
+    ///
+    /// By default, this is `None`.
+    fn input_num_bases(&self) -> Option<u64> {
+        None
+    }
+
+    /// The number of malformed records in the input file that were skipped while sampling reads,
+    /// if known. Records are only skipped if the strategy was asked to (e.g., with
+    /// [`twoset::Builder::skip_bad_records`][crate::twoset::Builder::skip_bad_records]), so this is
+    /// only available after the estimates have been generated.
+    ///
+    /// By default, this is `None`.
+    fn input_num_skipped(&self) -> Option<u64> {
+        None
+    }
+
+    /// The GC content of the sampled reads, if known. This is calculated while writing the sampled
+    /// reads, so is only available after the estimates have been generated.
+    ///
+    /// By default, this is `None`.
+    fn gc_content(&self) -> Option<GcSummary> {
+        None
+    }
+
+    /// The fraction of the reads an estimate was generated for that are contained in (i.e., fully
+    /// covered by) a read they overlap, if known. This includes any contained reads that were
+    /// excluded from the estimate (e.g., with
+    /// [`twoset::Builder::exclude_contained`][crate::twoset::Builder::exclude_contained]), and is
+    /// only available after the estimates have been generated.
+    ///
+    /// By default, this is `None`.
+    fn contained_fraction(&self) -> Option<f32> {
+        None
+    }
+
+    /// The fraction of the reads an estimate was generated for that are a near-identical
+    /// duplicate of a read they overlap (e.g., PCR duplicates), if known. This includes any
+    /// duplicates that were excluded from the estimate (see
+    /// [`twoset::Builder::exclude_duplicates`][crate::twoset::Builder::exclude_duplicates]), and is
+    /// only available after the estimates have been generated.
+    ///
+    /// By default, this is `None`.
+    fn duplicate_fraction(&self) -> Option<f32> {
+        None
+    }
+
+    /// How many of the sampled reads had adapters trimmed or were split at a chimeric junction, if
+    /// they were scanned for adapters (e.g., with
+    /// [`twoset::Builder::remove_adapters`][crate::twoset::Builder::remove_adapters]). The reads
+    /// are scanned while they are written, so this is only available after the estimates have been
+    /// generated.
+    ///
+    /// By default, this is `None`.
+    fn adapter_summary(&self) -> Option<AdapterSummary> {
+        None
+    }
+
+    /// The number of independent overlaps the per-read estimates were calculated from, if known.
+    /// Each overlap between two sampled reads in all-vs-all is only counted once. This gives the
+    /// [`PoissonInterval`] of the estimate, so is only available after the estimates have been
+    /// generated.
+    ///
+    /// By default, this is `None`.
+    fn num_overlaps(&self) -> Option<u64> {
+        None
+    }
+
+    /// The per-read estimates from the last time the genome size was [estimated][Estimate::estimate],
+    /// if they are kept - so you can calculate your own statistics (e.g., other quantiles, or
+    /// plots) from them without generating the estimates again. The strategies keep them; they are
+    /// `None` before the genome size is estimated.
+    ///
+    /// By default, this is `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use liblrge::{twoset::Builder, Estimate};
+    ///
+    /// let mut strategy = Builder::new().build("path/to/reads.fastq");
+    /// let est_result = strategy.estimate(true, None, None).unwrap();
+    /// let raw = strategy.raw_estimates().unwrap();
+    /// let p90 = raw.sorted_values(true)[raw.num_finite() * 9 / 10];
+    /// ```
+    fn raw_estimates(&self) -> Option<&RawEstimates> {
+        None
+    }
+
+    /// Keep the per-read estimates, once the genome size has been estimated from them, so they can
+    /// be returned by [`Estimate::raw_estimates`].
+    ///
+    /// By default, they are dropped.
+    fn retain_raw_estimates(&mut self, _raw: RawEstimates) {}
 
     /// Generate an estimate of the genome size, taking the median of the per-read estimates.
     ///
@@ -51,6 +713,12 @@ pub trait Estimate {
     /// The estimate will be `None` if there are no finite estimates when `finite` is `true`, or if
     /// there are no estimates at all.
     ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidQuantile`][LrgeError::InvalidQuantile] error, before any estimates are
+    /// generated, if `lower_quant` is not between 0 and 0.5, `upper_quant` is not between 0.5 and
+    /// 1.0, or `lower_quant` is not less than `upper_quant`.
+    ///
     /// [doi]: https://doi.org/10.1101/2024.11.27.625777
     fn estimate(
         &mut self,
@@ -58,88 +726,366 @@ pub trait Estimate {
         lower_quant: Option<f32>,
         upper_quant: Option<f32>,
     ) -> crate::Result<EstimateResult> {
-        let (estimates, no_mapping_count) = self.generate_estimates()?;
+        let median = Median::new(lower_quant, upper_quant)?;
+        self.estimate_with(finite, &median)
+    }
+
+    /// Generate an estimate of the genome size, combining the per-read estimates with a custom
+    /// [`Aggregator`] rather than taking their median - e.g., to try the mode of a kernel density
+    /// estimate. [`Estimate::estimate`] is the same as this with a [`Median`].
+    ///
+    /// # Arguments
+    ///
+    /// * `finite`: Whether to consider only finite estimates - see [`Estimate::estimate`].
+    /// * `aggregator`: Combines the (sorted) per-read estimates into the genome size estimate and
+    ///   its bounds.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from generating the per-read estimates, or from the aggregator.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use liblrge::estimate::Median;
+    /// use liblrge::{twoset::Builder, Estimate};
+    ///
+    /// let mut strategy = Builder::new().build("path/to/reads.fastq");
+    /// // the median and interquartile range
+    /// let median = Median::new(Some(0.25), Some(0.75)).unwrap();
+    /// let est_result = strategy.estimate_with(true, &median).unwrap();
+    /// ```
+    fn estimate_with(
+        &mut self,
+        finite: bool,
+        aggregator: &dyn Aggregator,
+    ) -> crate::Result<EstimateResult> {
+        let raw = self.generate_estimates()?;
 
-        let iter: Box<dyn Iterator<Item = f32>> = if finite {
-            Box::new(estimates.iter().filter(|&x| x.is_finite()).copied())
+        let values = raw.sorted_values(finite);
+        let AggregateEstimate {
+            lower,
+            estimate,
+            upper,
+        } = aggregator.aggregate(&values)?;
+        let quantiles = if values.is_empty() {
+            Vec::new()
         } else {
-            Box::new(estimates.iter().copied())
+            aggregator
+                .quantiles()
+                .iter()
+                .map(|&q| (q, interpolate_quantile(&values, q)))
+                .collect()
         };
 
-        let (lower, median, upper) = median(iter, lower_quant, upper_quant);
-
-        Ok(EstimateResult {
+        let result = EstimateResult {
             lower,
-            estimate: median,
+            estimate,
             upper,
-            no_mapping_count,
-        })
+            no_mapping_count: raw.no_mapping_count,
+            num_reads: raw.num_reads() as u32,
+            num_finite: raw.num_finite() as u32,
+            input_num_reads: self.input_num_reads(),
+            input_num_bases: self.input_num_bases(),
+            input_num_skipped: self.input_num_skipped(),
+            gc_content: self.gc_content(),
+            contained_fraction: self.contained_fraction(),
+            duplicate_fraction: self.duplicate_fraction(),
+            adapters: self.adapter_summary(),
+            poisson_interval: estimate
+                .zip(self.num_overlaps())
+                .and_then(|(estimate, num_overlaps)| PoissonInterval::new(estimate, num_overlaps)),
+            quantiles,
+        };
+        self.retain_raw_estimates(raw);
+        Ok(result)
+    }
+}
+
+/// The genome size estimate, and the bounds around it, that an [`Aggregator`] combines the
+/// per-read estimates into.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AggregateEstimate {
+    /// The lower bound, if there is one
+    pub lower: Option<f32>,
+    /// The genome size estimate. This is `None` if there are no per-read estimates.
+    pub estimate: Option<f32>,
+    /// The upper bound, if there is one
+    pub upper: Option<f32>,
+}
+
+/// Combines the per-read estimates into a genome size estimate. This is the final step of
+/// [`Estimate::estimate_with`], so alternative aggregations can reuse all of the sampling and
+/// overlapping.
+///
+/// # Examples
+///
+/// ```
+/// use liblrge::estimate::{AggregateEstimate, Aggregator};
+///
+/// /// The mean of the per-read estimates, with no bounds.
+/// struct Mean;
+///
+/// impl Aggregator for Mean {
+///     fn aggregate(&self, estimates: &[f32]) -> liblrge::Result<AggregateEstimate> {
+///         let estimate = (!estimates.is_empty())
+///             .then(|| estimates.iter().sum::<f32>() / estimates.len() as f32);
+///         Ok(AggregateEstimate {
+///             estimate,
+///             ..Default::default()
+///         })
+///     }
+/// }
+///
+/// let result = Mean.aggregate(&[1.0, 2.0, 6.0]).unwrap();
+/// assert_eq!(result.estimate, Some(3.0));
+/// ```
+pub trait Aggregator {
+    /// Combine the per-read estimates into a genome size estimate.
+    ///
+    /// The estimates are sorted in ascending order, and only include infinite estimates if they
+    /// were requested (see [`Estimate::estimate`]). There may be no estimates, in which case the
+    /// genome size estimate should be `None`.
+    ///
+    /// # Errors
+    ///
+    /// Implementations can return an [`AggregationError`][LrgeError::AggregationError] if the
+    /// estimates cannot be combined.
+    fn aggregate(&self, estimates: &[f32]) -> crate::Result<AggregateEstimate>;
+
+    /// Any other quantiles of the per-read estimates to report in
+    /// [`EstimateResult::quantiles`], each between 0 and 1.
+    ///
+    /// By default, there are none.
+    fn quantiles(&self) -> &[f32] {
+        &[]
     }
 }
 
-fn median(
-    iter: impl Iterator<Item = f32>,
+/// The median of the per-read estimates, with optional lower and upper quantiles as its bounds -
+/// the default [`Aggregator`], used by [`Estimate::estimate`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Median {
     lower_quant: Option<f32>,
     upper_quant: Option<f32>,
-) -> (Option<f32>, Option<f32>, Option<f32>) {
-    let mut values: Vec<f32> = iter.collect();
-    let len = values.len();
+    quantiles: Vec<f32>,
+}
 
-    if len == 0 {
-        return (None, None, None);
+impl Median {
+    /// Create a median aggregator, with the given lower and upper quantiles as its bounds. See
+    /// [`Estimate::estimate`] for the valid quantiles.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidQuantile`][LrgeError::InvalidQuantile] error if the quantiles are
+    /// invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::estimate::{Aggregator, Median, LOWER_QUANTILE, UPPER_QUANTILE};
+    ///
+    /// let median = Median::new(Some(LOWER_QUANTILE), Some(UPPER_QUANTILE)).unwrap();
+    /// let result = median.aggregate(&[1.0, 2.0, 3.0]).unwrap();
+    /// assert_eq!(result.estimate, Some(2.0));
+    /// ```
+    pub fn new(lower_quant: Option<f32>, upper_quant: Option<f32>) -> crate::Result<Self> {
+        validate_quantiles(lower_quant, upper_quant)?;
+        Ok(Self {
+            lower_quant,
+            upper_quant,
+            quantiles: Vec::new(),
+        })
     }
 
-    // we unwrap here because we don't expect NaN values
-    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    /// Also report these quantiles of the per-read estimates, in [`EstimateResult::quantiles`] -
+    /// e.g., to report whatever interval is required in one run.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidQuantile`][LrgeError::InvalidQuantile] error if any quantile is not
+    /// between 0 and 1.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use liblrge::estimate::Median;
+    /// use liblrge::{twoset::Builder, Estimate};
+    ///
+    /// let median = Median::new(None, None)
+    ///     .unwrap()
+    ///     .with_quantiles(&[0.05, 0.25, 0.75, 0.95])
+    ///     .unwrap();
+    /// let mut strategy = Builder::new().build("path/to/reads.fastq");
+    /// let est_result = strategy.estimate_with(true, &median).unwrap();
+    /// for (quantile, value) in est_result.quantiles {
+    ///     println!("{quantile}: {value}");
+    /// }
+    /// ```
+    pub fn with_quantiles(mut self, quantiles: &[f32]) -> crate::Result<Self> {
+        if let Some(q) = quantiles.iter().find(|q| !(0.0..=1.0).contains(*q)) {
+            return Err(LrgeError::InvalidQuantile(format!(
+                "Quantile must be between 0.0 and 1.0, but got {q}"
+            )));
+        }
+        self.quantiles = quantiles.to_vec();
+        Ok(self)
+    }
+}
 
-    let mut quantiles = vec![0.5];
+impl Aggregator for Median {
+    fn aggregate(&self, estimates: &[f32]) -> crate::Result<AggregateEstimate> {
+        let quantile = |q: Option<f32>| -> crate::Result<Option<f32>> {
+            Ok(q.map(|q| calculate_quantile(estimates, q))
+                .transpose()?
+                .flatten())
+        };
+        Ok(AggregateEstimate {
+            lower: quantile(self.lower_quant)?,
+            estimate: quantile(Some(0.5))?,
+            upper: quantile(self.upper_quant)?,
+        })
+    }
+
+    fn quantiles(&self) -> &[f32] {
+        &self.quantiles
+    }
+}
+
+/// Check the quantiles passed to [`Estimate::estimate`] make sense - the lower quantile must be
+/// between 0 and 0.5, the upper quantile between 0.5 and 1.0, and the lower less than the upper.
+pub(crate) fn validate_quantiles(
+    lower_quant: Option<f32>,
+    upper_quant: Option<f32>,
+) -> crate::Result<()> {
     if let Some(lower) = lower_quant {
-        quantiles.push(lower);
+        if !(0.0..=0.5).contains(&lower) {
+            return Err(LrgeError::InvalidQuantile(format!(
+                "Lower quantile must be between 0.0 and 0.5, but got {lower}"
+            )));
+        }
     }
     if let Some(upper) = upper_quant {
-        quantiles.push(upper);
+        if !(0.5..=1.0).contains(&upper) {
+            return Err(LrgeError::InvalidQuantile(format!(
+                "Upper quantile must be between 0.5 and 1.0, but got {upper}"
+            )));
+        }
     }
-    let quantiles: Vec<_> = quantiles
-        .iter()
-        .map(|&q| calculate_quantile(&values, q))
-        .collect();
-    match (lower_quant, upper_quant) {
-        (Some(_), Some(_)) => (quantiles[1], quantiles[0], quantiles[2]),
-        (Some(_), None) => (quantiles[1], quantiles[0], None),
-        (None, Some(_)) => (quantiles[0], quantiles[1], quantiles[2]),
-        (None, None) => (None, quantiles[0], None),
+    if let (Some(lower), Some(upper)) = (lower_quant, upper_quant) {
+        if lower >= upper {
+            return Err(LrgeError::InvalidQuantile(format!(
+                "Lower quantile ({lower}) must be less than the upper quantile ({upper})"
+            )));
+        }
     }
+
+    Ok(())
 }
 
-fn calculate_quantile(data: &[f32], quantile: f32) -> Option<f32> {
-    if data.is_empty() {
-        return None;
-    }
+/// The median of the per-read `estimates`, along with the `lower_quant` and `upper_quant` quantiles
+/// of them, as calculated by [`Estimate::estimate`]. The estimates do not need to be sorted.
+///
+/// If there are no estimates, all of the values in the result are `None`.
+///
+/// # Errors
+///
+/// Returns an [`InvalidQuantile`][LrgeError::InvalidQuantile] error if the quantiles are invalid -
+/// see [`Estimate::estimate`].
+///
+/// # Examples
+///
+/// ```
+/// use liblrge::estimate::median;
+///
+/// let result = median([5.0, 1.0, 3.0, 2.0, 4.0], Some(0.25), Some(0.75)).unwrap();
+/// assert_eq!(result.lower, Some(2.0));
+/// assert_eq!(result.estimate, Some(3.0));
+/// assert_eq!(result.upper, Some(4.0));
+/// ```
+pub fn median(
+    estimates: impl IntoIterator<Item = f32>,
+    lower_quant: Option<f32>,
+    upper_quant: Option<f32>,
+) -> crate::Result<AggregateEstimate> {
+    let median = Median::new(lower_quant, upper_quant)?;
+    let mut values: Vec<f32> = estimates.into_iter().collect();
+    values.sort_by(|a, b| a.total_cmp(b));
+    median.aggregate(&values)
+}
+
+/// Calculate a quantile of `data`, which must be sorted in ascending order, interpolating between
+/// the two nearest values. This is how the median and its bounds are calculated from the per-read
+/// estimates.
+///
+/// Returns `None` if there is no data, or an [`InvalidQuantile`][LrgeError::InvalidQuantile]
+/// error if the quantile is not between 0 and 1.
+///
+/// # Examples
+///
+/// ```
+/// use liblrge::estimate::calculate_quantile;
+///
+/// let data = [1.0, 2.0, 3.0, 4.0];
+/// assert_eq!(calculate_quantile(&data, 0.5).unwrap(), Some(2.5));
+/// assert_eq!(calculate_quantile(&[], 0.5).unwrap(), None);
+/// assert!(calculate_quantile(&data, 1.5).is_err());
+/// ```
+pub fn calculate_quantile(data: &[f32], quantile: f32) -> crate::Result<Option<f32>> {
     if !(0.0..=1.0).contains(&quantile) {
-        panic!("Quantile must be between 0.0 and 1.0");
+        return Err(LrgeError::InvalidQuantile(format!(
+            "Quantile must be between 0.0 and 1.0, but got {quantile}"
+        )));
+    }
+    if data.is_empty() {
+        return Ok(None);
     }
 
+    Ok(Some(interpolate_quantile(data, quantile)))
+}
+
+/// Calculate a quantile of sorted, non-empty data. The quantile must be between 0 and 1.
+pub(crate) fn interpolate_quantile(data: &[f32], quantile: f32) -> f32 {
     let n = data.len();
     let pos = quantile * (n - 1) as f32;
     let idx = pos.floor() as usize;
     let frac = pos - idx as f32;
 
-    if idx + 1 < n {
-        Some(data[idx] * (1.0 - frac) + data[idx + 1] * frac)
+    // interpolating between equal values (e.g., two infinities) would give NaN
+    if idx + 1 < n && frac > 0.0 && data[idx] != data[idx + 1] {
+        data[idx] * (1.0 - frac) + data[idx + 1] * frac
     } else {
-        Some(data[idx])
+        data[idx]
     }
 }
 
 /// Estimate genome size using the formula from Equation 3 in [the paper][doi].
 ///
+/// # Arguments
+///
+/// * `read_len`: The length of the read being estimated from.
+/// * `avg_target_len`: The average length of the reads it was overlapped against.
+/// * `n_target_reads`: The number of reads it was overlapped against.
+/// * `n_ovlaps`: The number of those reads it overlaps.
+/// * `ovlap_thresh`: The minimum length of an overlap - the minimap2 `min_chain_score` in LRGE.
+///
 /// # Returns
 ///
 /// A floating point number representing the estimated genome size. If the number of overlaps is 0,
 /// this function will return [`f32::INFINITY`].
 ///
+/// # Examples
+///
+/// ```
+/// use liblrge::estimate::per_read_estimate;
+///
+/// let estimate = per_read_estimate(10_000, 12_000.0, 5_000, 25, 100);
+/// assert_eq!(estimate, 4_370_200.0);
+/// assert!(per_read_estimate(10_000, 12_000.0, 5_000, 0, 100).is_infinite());
+/// ```
+///
 /// [doi]: https://doi.org/10.1101/2024.11.27.625777
-pub(crate) fn per_read_estimate(
+pub fn per_read_estimate(
     read_len: usize,
     avg_target_len: f32,
     n_target_reads: usize,
@@ -156,15 +1102,184 @@ pub(crate) fn per_read_estimate(
         + ovlap_ratio * (read_len as f32 + avg_target_len - 2.0 * ovlap_thresh as f32 + 1.0)
 }
 
+/// Estimate genome size from the total length of a read's overlaps, rather than their number.
+///
+/// A read of length `L` is expected to share `L * Lt / G` bases with a random read of length `Lt`
+/// from a genome of size `G`, or `L * Lt - t^2` bases once overlaps shorter than the threshold `t`
+/// are discounted. So, from the bases of the read covered by overlaps with `n_target_reads`
+/// reads, `G = n_target_reads * (L * avg_target_len - t^2) / ovlap_bases`. Unlike
+/// [`per_read_estimate`], a tiny dovetail overlap counts for little.
+///
+/// # Arguments
+///
+/// * `read_len`: The length of the read being estimated from.
+/// * `avg_target_len`: The average length of the reads it was overlapped against.
+/// * `n_target_reads`: The number of reads it was overlapped against.
+/// * `ovlap_bases`: The total length of its overlaps with those reads, on the read.
+/// * `ovlap_thresh`: The minimum length of an overlap - the minimap2 `min_chain_score` in LRGE.
+///
+/// # Returns
+///
+/// A floating point number representing the estimated genome size. If the total length of the
+/// overlaps is 0, this function will return [`f32::INFINITY`].
+///
+/// # Examples
+///
+/// ```
+/// use liblrge::estimate::length_weighted_estimate;
+///
+/// let estimate = length_weighted_estimate(10_000, 12_000.0, 5_000, 119_990, 100);
+/// assert_eq!(estimate, 5_000_000.0);
+/// assert!(length_weighted_estimate(10_000, 12_000.0, 5_000, 0, 100).is_infinite());
+/// ```
+pub fn length_weighted_estimate(
+    read_len: usize,
+    avg_target_len: f32,
+    n_target_reads: usize,
+    ovlap_bases: usize,
+    ovlap_thresh: u32,
+) -> f32 {
+    if ovlap_bases == 0 {
+        return f32::INFINITY;
+    }
+
+    let thresh = ovlap_thresh as f64;
+    let expected_bases = read_len as f64 * avg_target_len as f64 - thresh * thresh;
+    (n_target_reads as f64 * expected_bases / ovlap_bases as f64) as f32
+}
+
+/// Calculates the genome size estimate for a single read from its overlaps.
+///
+/// The strategies use [`Equation3`] by default. Another estimator can be given to a strategy with
+/// `per_read_estimator` on its builder (e.g.,
+/// [`twoset::Builder::per_read_estimator`][crate::twoset::Builder::per_read_estimator]) to try an
+/// alternative correction, without changing how the reads are overlapped.
+///
+/// The estimator is called from the mapping threads, so it must be `Send` and `Sync`.
+///
+/// # Examples
+///
+/// ```
+/// use liblrge::estimate::PerReadEstimator;
+/// use liblrge::twoset::Builder;
+///
+/// /// Ignore the overlap threshold.
+/// struct NoThreshold;
+///
+/// impl PerReadEstimator for NoThreshold {
+///     fn estimate(
+///         &self,
+///         read_len: usize,
+///         avg_target_len: f32,
+///         num_target_reads: usize,
+///         num_overlaps: usize,
+///         _overlap_threshold: u32,
+///     ) -> f32 {
+///         let ratio = num_target_reads as f32 / num_overlaps as f32;
+///         read_len as f32 + ratio * (read_len as f32 + avg_target_len)
+///     }
+/// }
+///
+/// let builder = Builder::new().per_read_estimator(NoThreshold);
+/// ```
+pub trait PerReadEstimator: Send + Sync {
+    /// Estimate the genome size from a single read.
+    ///
+    /// # Arguments
+    ///
+    /// * `read_len`: The length of the read.
+    /// * `avg_target_len`: The average length of the reads it was overlapped with.
+    /// * `num_target_reads`: The number of reads it was overlapped with.
+    /// * `num_overlaps`: The number of those reads it overlaps. This is always at least 1 - reads
+    ///   without overlaps are given an infinite estimate without calling the estimator.
+    /// * `overlap_threshold`: The minimum chaining score of an overlap, which approximates the
+    ///   minimum length of an overlap that minimap2 can find.
+    fn estimate(
+        &self,
+        read_len: usize,
+        avg_target_len: f32,
+        num_target_reads: usize,
+        num_overlaps: usize,
+        overlap_threshold: u32,
+    ) -> f32;
+
+    /// Estimate the genome size from a single read, from the total length of its overlaps rather
+    /// than their number. This is used instead of [`estimate`][PerReadEstimator::estimate] when
+    /// overlaps are length-weighted (e.g., with
+    /// [`twoset::Builder::length_weighted`][crate::twoset::Builder::length_weighted]). By
+    /// default, this is [`length_weighted_estimate`].
+    ///
+    /// # Arguments
+    ///
+    /// * `read_len`: The length of the read.
+    /// * `avg_target_len`: The average length of the reads it was overlapped with.
+    /// * `num_target_reads`: The number of reads it was overlapped with.
+    /// * `overlap_bases`: The total length, on the read, of its overlaps with those reads. This is
+    ///   always at least 1.
+    /// * `overlap_threshold`: The minimum chaining score of an overlap, which approximates the
+    ///   minimum length of an overlap that minimap2 can find.
+    fn estimate_length_weighted(
+        &self,
+        read_len: usize,
+        avg_target_len: f32,
+        num_target_reads: usize,
+        overlap_bases: usize,
+        overlap_threshold: u32,
+    ) -> f32 {
+        length_weighted_estimate(
+            read_len,
+            avg_target_len,
+            num_target_reads,
+            overlap_bases,
+            overlap_threshold,
+        )
+    }
+}
+
+/// The formula from Equation 3 in [the paper][doi] - the default [`PerReadEstimator`].
+///
+/// [doi]: https://doi.org/10.1101/2024.11.27.625777
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Equation3;
+
+impl PerReadEstimator for Equation3 {
+    fn estimate(
+        &self,
+        read_len: usize,
+        avg_target_len: f32,
+        num_target_reads: usize,
+        num_overlaps: usize,
+        overlap_threshold: u32,
+    ) -> f32 {
+        per_read_estimate(
+            read_len,
+            avg_target_len,
+            num_target_reads,
+            num_overlaps,
+            overlap_threshold,
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// The median and quantiles of unsorted data, as a tuple.
+    fn median_bounds(
+        iter: impl Iterator<Item = f32>,
+        lower_quant: Option<f32>,
+        upper_quant: Option<f32>,
+    ) -> crate::Result<(Option<f32>, Option<f32>, Option<f32>)> {
+        let result = median(iter, lower_quant, upper_quant)?;
+        Ok((result.lower, result.estimate, result.upper))
+    }
+
     #[test]
     fn test_median_odd_length() {
         let data = vec![1.0f32, 3.0, 5.0, 7.0, 9.0];
         assert_eq!(
-            median(data.into_iter(), None, None),
+            median_bounds(data.into_iter(), None, None).unwrap(),
             (None, Some(5.0), None)
         );
     }
@@ -173,7 +1288,7 @@ mod tests {
     fn test_median_unsorted() {
         let data = vec![3.0f32, 1.0, 7.0, 5.0, 9.0];
         assert_eq!(
-            median(data.into_iter(), None, None),
+            median_bounds(data.into_iter(), None, None).unwrap(),
             (None, Some(5.0), None)
         );
     }
@@ -182,7 +1297,7 @@ mod tests {
     fn test_median_even_length() {
         let data = vec![1.0f32, 3.0, 5.0, 7.0];
         assert_eq!(
-            median(data.into_iter(), None, None),
+            median_bounds(data.into_iter(), None, None).unwrap(),
             (None, Some(4.0), None)
         );
     }
@@ -191,7 +1306,7 @@ mod tests {
     fn test_median_single_element() {
         let data = vec![10.0f32];
         assert_eq!(
-            median(data.into_iter(), None, None),
+            median_bounds(data.into_iter(), None, None).unwrap(),
             (None, Some(10.0), None)
         );
     }
@@ -199,14 +1314,17 @@ mod tests {
     #[test]
     fn test_median_empty() {
         let data: Vec<f32> = vec![];
-        assert_eq!(median(data.into_iter(), None, None), (None, None, None));
+        assert_eq!(
+            median_bounds(data.into_iter(), None, None).unwrap(),
+            (None, None, None)
+        );
     }
 
     #[test]
     fn test_median_with_negative_numbers() {
         let data = vec![-3.0f32, 1.0, 0.0, 3.0, -1.0];
         assert_eq!(
-            median(data.into_iter(), None, None),
+            median_bounds(data.into_iter(), None, None).unwrap(),
             (None, Some(0.0), None)
         );
     }
@@ -215,7 +1333,7 @@ mod tests {
     fn test_median_with_positive_infinity() {
         let data = vec![1.0f32, 2.0, 3.0, f32::INFINITY];
         assert_eq!(
-            median(data.into_iter(), None, None),
+            median_bounds(data.into_iter(), None, None).unwrap(),
             (None, Some(2.5), None)
         );
     }
@@ -224,7 +1342,7 @@ mod tests {
     fn test_median_with_negative_infinity() {
         let data = vec![f32::NEG_INFINITY, 1.0, 2.0, 3.0];
         assert_eq!(
-            median(data.into_iter(), None, None),
+            median_bounds(data.into_iter(), None, None).unwrap(),
             (None, Some(1.5), None)
         );
     }
@@ -233,7 +1351,7 @@ mod tests {
     fn test_median_with_both_infinities() {
         let data = vec![f32::NEG_INFINITY, 1.0, 2.0, f32::INFINITY];
         assert_eq!(
-            median(data.into_iter(), None, None),
+            median_bounds(data.into_iter(), None, None).unwrap(),
             (None, Some(1.5), None)
         );
     }
@@ -242,7 +1360,7 @@ mod tests {
     fn test_median_with_only_infinity() {
         let data = vec![f32::INFINITY, f32::INFINITY];
         assert_eq!(
-            median(data.into_iter(), None, None),
+            median_bounds(data.into_iter(), None, None).unwrap(),
             (None, Some(f32::INFINITY), None)
         );
     }
@@ -251,7 +1369,7 @@ mod tests {
     fn test_median_with_only_negative_infinity() {
         let data = vec![f32::NEG_INFINITY, f32::NEG_INFINITY];
         assert_eq!(
-            median(data.into_iter(), None, None),
+            median_bounds(data.into_iter(), None, None).unwrap(),
             (None, Some(f32::NEG_INFINITY), None)
         );
     }
@@ -260,7 +1378,7 @@ mod tests {
     fn test_median_with_inf_and_regular_values() {
         let data = vec![-1.0, f32::NEG_INFINITY, 0.0, 1.0, f32::INFINITY];
         assert_eq!(
-            median(data.into_iter(), None, None),
+            median_bounds(data.into_iter(), None, None).unwrap(),
             (None, Some(0.0), None)
         );
     }
@@ -269,7 +1387,7 @@ mod tests {
     fn test_median_with_quantiles() {
         let data = vec![1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0];
         assert_eq!(
-            median(data.into_iter(), Some(0.15), Some(0.65)),
+            median_bounds(data.into_iter(), Some(0.15), Some(0.65)).unwrap(),
             (Some(2.35), Some(5.5), Some(6.85))
         );
     }
@@ -289,16 +1407,70 @@ mod tests {
             f32::INFINITY,
         ];
         assert_eq!(
-            median(data.into_iter(), Some(0.15), Some(0.65)),
+            median_bounds(data.into_iter(), Some(0.15), Some(0.65)).unwrap(),
             (Some(2.35), Some(5.5), Some(f32::INFINITY))
         );
     }
 
     #[test]
-    #[should_panic(expected = "Quantile must be between 0.0 and 1.0")]
-    fn test_calculate_quantile_panics() {
+    fn test_calculate_quantile_invalid() {
         let data = vec![1.0f32, 2.0, 3.0, 4.0, 5.0];
-        calculate_quantile(&data, 1.1);
+        let err = calculate_quantile(&data, 1.1).unwrap_err();
+        assert!(matches!(err, LrgeError::InvalidQuantile(_)));
+        assert!(err
+            .to_string()
+            .contains("Quantile must be between 0.0 and 1.0"));
+        assert!(calculate_quantile(&[], -0.1).is_err());
+    }
+
+    #[test]
+    fn test_median_with_only_upper_quantile() {
+        let data = vec![1.0f32, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(
+            median_bounds(data.clone().into_iter(), None, Some(0.75)).unwrap(),
+            (None, Some(3.0), Some(4.0))
+        );
+        assert_eq!(
+            median_bounds(data.into_iter(), Some(0.25), None).unwrap(),
+            (Some(2.0), Some(3.0), None)
+        );
+    }
+
+    #[test]
+    fn test_median_invalid_quantile() {
+        let data = vec![1.0f32, 2.0, 3.0];
+        assert!(median_bounds(data.into_iter(), Some(-0.5), None).is_err());
+    }
+
+    #[test]
+    fn test_median_between_infinities() {
+        let data = vec![1.0f32, f32::INFINITY, f32::INFINITY, f32::INFINITY];
+        assert_eq!(
+            median_bounds(data.into_iter(), Some(0.5), Some(0.9)).unwrap(),
+            (
+                Some(f32::INFINITY),
+                Some(f32::INFINITY),
+                Some(f32::INFINITY)
+            )
+        );
+    }
+
+    #[test]
+    fn test_validate_quantiles() {
+        assert!(validate_quantiles(None, None).is_ok());
+        assert!(validate_quantiles(Some(0.15), Some(0.65)).is_ok());
+        assert!(validate_quantiles(Some(0.0), Some(1.0)).is_ok());
+        assert!(validate_quantiles(Some(0.25), None).is_ok());
+
+        let err = validate_quantiles(Some(0.6), Some(0.9)).unwrap_err();
+        assert!(matches!(err, LrgeError::InvalidQuantile(_)));
+        assert!(err.to_string().contains("Lower quantile must be between"));
+        let err = validate_quantiles(None, Some(1.5)).unwrap_err();
+        assert!(err.to_string().contains("Upper quantile must be between"));
+        assert!(validate_quantiles(Some(f32::NAN), None).is_err());
+
+        let err = validate_quantiles(Some(0.5), Some(0.5)).unwrap_err();
+        assert!(err.to_string().contains("must be less than"));
     }
 
     #[test]
@@ -321,6 +1493,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_length_weighted_estimate() {
+        // every read overlaps every other read along its whole length
+        assert_eq!(length_weighted_estimate(100, 100.0, 10, 1000, 0), 100.0);
+        // overlaps shorter than the threshold are not expected to be found
+        assert_eq!(length_weighted_estimate(100, 200.0, 10, 1000, 10), 199.0);
+        assert!(length_weighted_estimate(100, 200.0, 10, 0, 10).is_infinite());
+
+        let estimator: &dyn PerReadEstimator = &Equation3;
+        assert_eq!(
+            estimator.estimate_length_weighted(100, 200.0, 10, 1000, 10),
+            199.0
+        );
+    }
+
+    #[test]
+    fn test_equation3_estimator() {
+        let estimator: &dyn PerReadEstimator = &Equation3;
+        assert_eq!(
+            estimator.estimate(100, 200.0, 1000, 100, 10),
+            per_read_estimate(100, 200.0, 1000, 100, 10)
+        );
+    }
+
     #[test]
     fn test_per_read_estimate_zero_ovlaps() {
         let read_len = 100;
@@ -340,4 +1536,392 @@ mod tests {
             expected
         );
     }
+
+    #[test]
+    fn test_check_no_mapping_fraction() {
+        assert!(check_no_mapping_fraction(5, 10, None).is_ok());
+        assert!(check_no_mapping_fraction(5, 10, Some(0.5)).is_ok());
+        assert!(check_no_mapping_fraction(0, 0, Some(0.0)).is_ok());
+
+        let err = check_no_mapping_fraction(6, 10, Some(0.5)).unwrap_err();
+        assert!(matches!(err, LrgeError::QualityControl { .. }));
+        assert!(err
+            .to_string()
+            .contains("6 of 10 reads (60.00%) did not overlap"));
+    }
+
+    #[test]
+    fn test_num_estimated_reads() {
+        let estimates = vec![
+            PerReadEstimate {
+                read_id: b"read1".to_vec(),
+                estimate: 1e6,
+                mapped: true,
+            },
+            PerReadEstimate {
+                read_id: b"read2".to_vec(),
+                estimate: f32::INFINITY,
+                mapped: false,
+            },
+        ];
+        let flagged = FlaggedReads {
+            duplicates: 3,
+            excluded: 3,
+            ..Default::default()
+        };
+        assert_eq!(num_estimated_reads(&estimates, &flagged), 5);
+        assert_eq!(num_estimated_reads(&[], &FlaggedReads::default()), 0);
+    }
+
+    #[test]
+    fn test_saved_estimates_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let estimates = vec![
+            PerReadEstimate {
+                read_id: b"r1".to_vec(),
+                estimate: 100.0,
+                mapped: true,
+            },
+            PerReadEstimate {
+                read_id: b"r2".to_vec(),
+                estimate: f32::INFINITY,
+                mapped: false,
+            },
+            PerReadEstimate {
+                read_id: b"r3".to_vec(),
+                estimate: 300.5,
+                mapped: true,
+            },
+            PerReadEstimate {
+                read_id: b"r4".to_vec(),
+                estimate: 50.0,
+                mapped: true,
+            },
+        ];
+        write_per_read_estimates(&dir.path().join(ESTIMATES_FILENAME), &estimates).unwrap();
+
+        let mut saved = SavedEstimates::new(dir.path());
+        assert_eq!(saved.read().unwrap(), estimates);
+
+        let result = saved.estimate(true, None, None).unwrap();
+        assert_eq!(result.estimate, Some(100.0));
+        assert_eq!(result.no_mapping_count, 1);
+        assert_eq!(result.num_reads, 4);
+
+        let result = saved.estimate(false, None, None).unwrap();
+        assert_eq!(result.estimate, Some(200.25));
+    }
+
+    #[test]
+    fn test_raw_estimates() {
+        let estimate = |id: &str, estimate: f32| PerReadEstimate {
+            read_id: id.as_bytes().to_vec(),
+            estimate,
+            mapped: estimate.is_finite(),
+        };
+        let raw = RawEstimates::new(
+            vec![
+                estimate("r1", 300.0),
+                estimate("r2", f32::INFINITY),
+                estimate("r3", 100.0),
+            ],
+            1,
+            Duration::from_millis(5),
+        );
+        assert_eq!(raw.num_reads(), 3);
+        assert_eq!(raw.num_finite(), 2);
+        assert_eq!(raw.sorted_values(true), vec![100.0, 300.0]);
+        assert_eq!(raw.sorted_values(false), vec![100.0, 300.0, f32::INFINITY]);
+
+        let raw = RawEstimates::default();
+        assert_eq!(raw.num_reads(), 0);
+        assert!(raw.sorted_values(false).is_empty());
+    }
+
+    #[test]
+    fn test_reads_without_overlaps() {
+        let dir = tempfile::tempdir().unwrap();
+        let reads = dir.path().join("query.fa");
+        std::fs::write(&reads, ">r1\nACGT\n>r2\nAC\n>r3\nACGTACGT\n").unwrap();
+        let estimate = |id: &str, mapped: bool| PerReadEstimate {
+            read_id: id.as_bytes().to_vec(),
+            estimate: if mapped { 100.0 } else { f32::INFINITY },
+            mapped,
+        };
+        let estimates = vec![
+            estimate("r3", false),
+            estimate("r1", true),
+            estimate("r2", false),
+            estimate("missing", false),
+        ];
+
+        let reads_without = reads_without_overlaps(&estimates, &reads).unwrap();
+        assert_eq!(
+            reads_without,
+            vec![
+                NoOverlapRead {
+                    read_id: b"r3".to_vec(),
+                    len: 8
+                },
+                NoOverlapRead {
+                    read_id: b"r2".to_vec(),
+                    len: 2
+                },
+            ]
+        );
+
+        assert!(
+            reads_without_overlaps(&estimates[1..2], dir.path().join("gone.fa"))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(reads_without_overlaps(&estimates, dir.path().join("gone.fa")).is_err());
+    }
+
+    #[test]
+    fn test_saved_estimates_invalid_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("estimates.tsv");
+        std::fs::write(&path, "read_id\testimate\tmapped\nr1\tabc\ttrue\n").unwrap();
+
+        let result = SavedEstimates::new(&path).read();
+        assert!(matches!(
+            result,
+            Err(LrgeError::EstimatesParseError { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_median_new() {
+        assert_eq!(Median::new(None, None).unwrap(), Median::default());
+        assert!(Median::new(Some(0.25), Some(0.75)).is_ok());
+        let err = Median::new(Some(0.75), Some(0.25)).unwrap_err();
+        assert!(matches!(err, LrgeError::InvalidQuantile(_)));
+    }
+
+    #[test]
+    fn test_median_new_with_quantiles() {
+        let median = Median::default().with_quantiles(&[0.0, 0.5, 1.0]).unwrap();
+        assert_eq!(median.quantiles(), &[0.0, 0.5, 1.0]);
+        assert!(Median::default().quantiles().is_empty());
+        let err = Median::default().with_quantiles(&[0.5, 1.5]).unwrap_err();
+        assert!(matches!(err, LrgeError::InvalidQuantile(_)));
+    }
+
+    #[test]
+    fn test_interval() {
+        let result = EstimateResult {
+            lower: None,
+            estimate: Some(4e6),
+            upper: None,
+            no_mapping_count: 0,
+            num_reads: 10,
+            num_finite: 10,
+            input_num_reads: None,
+            input_num_bases: None,
+            input_num_skipped: None,
+            gc_content: None,
+            contained_fraction: None,
+            duplicate_fraction: None,
+            adapters: None,
+            poisson_interval: None,
+            quantiles: vec![(0.15, 3e6), (0.25, 3.5e6), (0.65, 5e6), (0.75, 6e6)],
+        };
+
+        let calibrated = result.interval(LOWER_QUANTILE, UPPER_QUANTILE).unwrap();
+        assert_eq!((calibrated.lower, calibrated.upper), (3e6, 5e6));
+        assert_eq!(calibrated.confidence, Some(0.92));
+        assert_eq!(calibrated.label(), "92% CI");
+
+        let iqr = result.interval(0.25, 0.75).unwrap();
+        assert_eq!((iqr.lower, iqr.upper), (3.5e6, 6e6));
+        assert_eq!(iqr.confidence, None);
+        assert_eq!(iqr.label(), "q0.25-q0.75");
+
+        assert_eq!(result.interval(0.05, 0.95), None);
+    }
+
+    #[test]
+    fn test_estimate_with_quantiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(ESTIMATES_FILENAME);
+        let estimates = [400.0, 100.0, f32::INFINITY, 300.0, 200.0, 500.0]
+            .into_iter()
+            .enumerate()
+            .map(|(i, estimate)| PerReadEstimate {
+                read_id: format!("r{i}").into_bytes(),
+                estimate,
+                mapped: estimate.is_finite(),
+            })
+            .collect::<Vec<_>>();
+        write_per_read_estimates(&path, &estimates).unwrap();
+
+        let mut saved = SavedEstimates::new(&path);
+        let median = Median::default()
+            .with_quantiles(&[0.95, 0.05, 0.5])
+            .unwrap();
+        let result = saved.estimate_with(true, &median).unwrap();
+        assert_eq!(
+            result.quantiles,
+            vec![(0.95, 480.0), (0.05, 120.0), (0.5, 300.0)]
+        );
+        assert_eq!(result.estimate, Some(300.0));
+
+        let result = saved.estimate_with(false, &median).unwrap();
+        assert_eq!(result.quantiles[0], (0.95, f32::INFINITY));
+
+        // only the median's quantiles are reported
+        let result = saved.estimate(true, Some(0.25), Some(0.75)).unwrap();
+        assert!(result.quantiles.is_empty());
+
+        write_per_read_estimates(&path, &[]).unwrap();
+        let result = saved.estimate_with(true, &median).unwrap();
+        assert!(result.quantiles.is_empty());
+    }
+
+    #[test]
+    fn test_estimate_with_custom_aggregator() {
+        /// The smallest and largest estimates as the bounds, and an error if there are none.
+        struct Range;
+
+        impl Aggregator for Range {
+            fn aggregate(&self, estimates: &[f32]) -> crate::Result<AggregateEstimate> {
+                let (Some(&lower), Some(&upper)) = (estimates.first(), estimates.last()) else {
+                    return Err(LrgeError::AggregationError("no estimates".to_string()));
+                };
+                Ok(AggregateEstimate {
+                    lower: Some(lower),
+                    estimate: Some((lower + upper) / 2.0),
+                    upper: Some(upper),
+                })
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(ESTIMATES_FILENAME);
+        let estimates = [300.0, f32::INFINITY, 100.0]
+            .into_iter()
+            .enumerate()
+            .map(|(i, estimate)| PerReadEstimate {
+                read_id: format!("r{i}").into_bytes(),
+                estimate,
+                mapped: estimate.is_finite(),
+            })
+            .collect::<Vec<_>>();
+        write_per_read_estimates(&path, &estimates).unwrap();
+
+        let mut saved = SavedEstimates::new(&path);
+        let result = saved.estimate_with(true, &Range).unwrap();
+        assert_eq!(result.lower, Some(100.0));
+        assert_eq!(result.estimate, Some(200.0));
+        assert_eq!(result.upper, Some(300.0));
+        assert_eq!(result.num_reads, 3);
+        assert_eq!(result.no_mapping_count, 1);
+
+        let result = saved.estimate_with(false, &Range).unwrap();
+        assert_eq!(result.upper, Some(f32::INFINITY));
+
+        // the default aggregation is the same as estimating with a median
+        let median = Median::new(Some(0.25), Some(0.75)).unwrap();
+        assert_eq!(
+            saved.estimate_with(true, &median).unwrap(),
+            saved.estimate(true, Some(0.25), Some(0.75)).unwrap()
+        );
+
+        write_per_read_estimates(&path, &[]).unwrap();
+        let err = saved.estimate_with(true, &Range).unwrap_err();
+        assert!(matches!(err, LrgeError::AggregationError(_)));
+    }
+
+    #[test]
+    fn test_estimate_outcome() {
+        let mut result = EstimateResult {
+            lower: None,
+            estimate: Some(1e6),
+            upper: None,
+            no_mapping_count: 8,
+            num_reads: 10,
+            num_finite: 2,
+            input_num_reads: None,
+            input_num_bases: None,
+            input_num_skipped: None,
+            gc_content: None,
+            contained_fraction: None,
+            duplicate_fraction: None,
+            adapters: None,
+            poisson_interval: None,
+            quantiles: Vec::new(),
+        };
+        assert_eq!(result.finite_fraction(), Some(0.2));
+        assert_eq!(result.outcome(0.2), EstimateOutcome::Estimated(1e6));
+        assert_eq!(
+            result.outcome(0.5),
+            EstimateOutcome::InsufficientOverlap {
+                finite_fraction: 0.2
+            }
+        );
+
+        // the median of the estimates including infinite ones
+        result.estimate = Some(f32::INFINITY);
+        assert_eq!(
+            result.outcome(0.0),
+            EstimateOutcome::InsufficientOverlap {
+                finite_fraction: 0.2
+            }
+        );
+
+        result.estimate = None;
+        result.num_reads = 0;
+        result.num_finite = 0;
+        assert_eq!(result.finite_fraction(), None);
+        assert_eq!(
+            result.outcome(0.0),
+            EstimateOutcome::InsufficientOverlap {
+                finite_fraction: 0.0
+            }
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let result = EstimateResult {
+            lower: Some(4e6),
+            estimate: Some(4.5e6),
+            upper: None,
+            no_mapping_count: 1,
+            num_reads: 10,
+            num_finite: 9,
+            input_num_reads: Some(9_000),
+            input_num_bases: None,
+            input_num_skipped: None,
+            gc_content: Some(GcSummary {
+                overall: 50.0,
+                quantiles: [40.0, 45.0, 50.0, 55.0, 60.0],
+                num_reads: 10,
+            }),
+            contained_fraction: Some(0.1),
+            duplicate_fraction: None,
+            adapters: None,
+            poisson_interval: None,
+            quantiles: Vec::new(),
+        };
+        let json = serde_json::to_string(&result).unwrap();
+        assert_eq!(
+            serde_json::from_str::<EstimateResult>(&json).unwrap(),
+            result
+        );
+
+        let per_read = PerReadEstimate {
+            read_id: b"read1".to_vec(),
+            estimate: 100.5,
+            mapped: true,
+        };
+        let json = serde_json::to_string(&per_read).unwrap();
+        assert!(json.contains(r#""read_id":"read1""#));
+        assert_eq!(
+            serde_json::from_str::<PerReadEstimate>(&json).unwrap(),
+            per_read
+        );
+    }
 }
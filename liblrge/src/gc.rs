@@ -0,0 +1,105 @@
+//! GC content of the sampled reads.
+//!
+//! The GC content is calculated while the sampled reads are written out, so it comes at almost no
+//! extra cost. Reads with an unusual GC content, relative to the rest of the sample, can be a cheap
+//! signal of contamination or of plasmids/other replicons.
+//!
+//! Only the bases `A`, `C`, `G`, and `T` (in any case) are counted - ambiguous bases such as `N`
+//! do not contribute to the GC content.
+use crate::estimate::interpolate_quantile;
+
+/// The quantiles of the per-read GC content that are reported in [`GcSummary::quantiles`].
+pub const GC_QUANTILES: [f32; 5] = [0.05, 0.25, 0.5, 0.75, 0.95];
+
+/// A summary of the GC content of the sampled reads.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GcSummary {
+    /// The GC content, as a percentage, of all sampled bases.
+    pub overall: f32,
+    /// The quantiles, given by [`GC_QUANTILES`], of the per-read GC content percentages.
+    pub quantiles: [f32; 5],
+    /// The number of reads the per-read GC content was calculated for.
+    pub num_reads: usize,
+}
+
+/// Counts the GC content of reads as they are written.
+#[derive(Debug, Default)]
+pub(crate) struct GcCounter {
+    gc_bases: u64,
+    acgt_bases: u64,
+    per_read: Vec<f32>,
+}
+
+impl GcCounter {
+    /// Add a read's sequence to the counts.
+    pub(crate) fn add(&mut self, seq: &[u8]) {
+        let mut gc = 0u64;
+        let mut acgt = 0u64;
+        for base in seq {
+            match base.to_ascii_uppercase() {
+                b'G' | b'C' => {
+                    gc += 1;
+                    acgt += 1;
+                }
+                b'A' | b'T' => acgt += 1,
+                _ => {}
+            }
+        }
+
+        if acgt > 0 {
+            self.per_read.push(gc as f32 / acgt as f32 * 100.0);
+        }
+        self.gc_bases += gc;
+        self.acgt_bases += acgt;
+    }
+
+    /// Summarise the GC content of all reads added. Returns `None` if no `A`, `C`, `G`, or `T`
+    /// bases were added.
+    pub(crate) fn summary(mut self) -> Option<GcSummary> {
+        if self.acgt_bases == 0 {
+            return None;
+        }
+
+        self.per_read.sort_by(|a, b| a.total_cmp(b));
+        // there is at least one read, as there is at least one A, C, G, or T base
+        let quantiles = GC_QUANTILES.map(|q| interpolate_quantile(&self.per_read, q));
+
+        Some(GcSummary {
+            overall: self.gc_bases as f32 / self.acgt_bases as f32 * 100.0,
+            quantiles,
+            num_reads: self.per_read.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gc_counter_empty() {
+        assert_eq!(GcCounter::default().summary(), None);
+
+        let mut counter = GcCounter::default();
+        counter.add(b"NNNN");
+        assert_eq!(counter.summary(), None);
+    }
+
+    #[test]
+    fn test_gc_counter() {
+        let mut counter = GcCounter::default();
+        counter.add(b"GGCC");
+        counter.add(b"atgc");
+        counter.add(b"AATTNN");
+        counter.add(b"NNNN");
+
+        let summary = counter.summary().unwrap();
+        // 6 G/C bases out of 12 A/C/G/T bases
+        assert_eq!(summary.overall, 50.0);
+        assert_eq!(summary.num_reads, 3);
+        assert_eq!(summary.quantiles[0], 5.0);
+        assert_eq!(summary.quantiles[2], 50.0);
+        assert_eq!(summary.quantiles[4], 95.0);
+    }
+}
@@ -0,0 +1,328 @@
+//! Export the overlap graph of the sampled reads as [GFA][gfa].
+//!
+//! The overlaps found by a strategy are written to
+//! [`OVERLAPS_FILENAME`][crate::estimate::OVERLAPS_FILENAME] in its temporary
+//! directory. An [`OverlapGraph`] turns them into a graph with a segment for each read, and a link
+//! for each dovetail overlap - i.e., where the end of one read overlaps the start of another.
+//! Overlaps where one read is contained in the other, or that are internal to both reads (e.g., a
+//! shared repeat), are not links. Viewing the graph in a tool such as [Bandage][bandage] can help
+//! to explain a suspicious estimate - e.g., reads from a contaminant form a separate component.
+//!
+//! The reads' sequences are not included, only their lengths, and reads without any overlaps are
+//! not in the PAF file, so are not in the graph.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use liblrge::estimate::OVERLAPS_FILENAME;
+//! use liblrge::gfa::OverlapGraph;
+//!
+//! let graph = OverlapGraph::from_paf(format!("path/to/tmpdir/{OVERLAPS_FILENAME}")).unwrap();
+//! graph.write(File::create("overlaps.gfa").unwrap()).unwrap();
+//! ```
+//!
+//! [gfa]: https://gfa-spec.github.io/GFA-spec/GFA1.html
+//! [bandage]: https://rrwick.github.io/Bandage/
+use std::cmp;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+use crate::minimap2::mapping::{read_paf, PafRecord};
+
+/// The maximum overhang, in bp, for an overlap to be a dovetail or containment, rather than
+/// internal. This is the default of [miniasm](https://github.com/lh3/miniasm).
+const MAX_HANG: i32 = 1000;
+/// The maximum overhang, as a fraction of the overlap length, for an overlap to be a dovetail or
+/// containment, rather than internal. This is the default of miniasm.
+const INT_FRAC: f32 = 0.8;
+
+/// The orientation of a segment in a link.
+type Orientation = char;
+
+/// A link between two segments, with the from segment's end overlapping the to segment's start.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Link {
+    from: Vec<u8>,
+    from_orient: Orientation,
+    to: Vec<u8>,
+    to_orient: Orientation,
+}
+
+impl Link {
+    /// The same link, traversed in the opposite direction.
+    fn reversed(self) -> Self {
+        Self {
+            from: self.to,
+            from_orient: flip(self.to_orient),
+            to: self.from,
+            to_orient: flip(self.from_orient),
+        }
+    }
+
+    /// The link in a canonical direction, so that a link is only added once, whichever read was
+    /// the query.
+    fn canonical(self) -> Self {
+        if (&self.from, self.from_orient) <= (&self.to, self.to_orient) {
+            self
+        } else {
+            self.reversed()
+        }
+    }
+}
+
+fn flip(orient: Orientation) -> Orientation {
+    if orient == '+' {
+        '-'
+    } else {
+        '+'
+    }
+}
+
+/// The link for an overlap, and the length of the overlap, if it is a dovetail overlap.
+///
+/// This follows the classification of overlaps in miniasm. The target coordinates are first put on
+/// the same strand as the query, then the overlap is internal if the sequence either side of it that
+/// is in both reads is too long, and a containment if either read is entirely within the other.
+fn dovetail(record: &PafRecord) -> Option<(Link, i32)> {
+    let (qs, qe, ql) = (record.query_start, record.query_end, record.query_len);
+    let tl = record.target_len;
+    let (ts, te) = if record.strand == '+' {
+        (record.target_start, record.target_end)
+    } else {
+        (tl - record.target_end, tl - record.target_start)
+    };
+
+    let overhang = cmp::min(qs, ts) + cmp::min(ql - qe, tl - te);
+    let maplen = cmp::max(qe - qs, te - ts);
+    if overhang as f32 > (MAX_HANG as f32).min(maplen as f32 * INT_FRAC) {
+        return None;
+    }
+    if (qs <= ts && ql - qe <= tl - te) || (qs >= ts && ql - qe >= tl - te) {
+        return None;
+    }
+
+    let link = if qs > ts {
+        // the query's end overlaps the target's start
+        Link {
+            from: record.query_name.clone(),
+            from_orient: '+',
+            to: record.target_name.clone(),
+            to_orient: record.strand,
+        }
+    } else {
+        // the target's end overlaps the query's start
+        Link {
+            from: record.target_name.clone(),
+            from_orient: record.strand,
+            to: record.query_name.clone(),
+            to_orient: '+',
+        }
+    };
+    Some((link, qe - qs))
+}
+
+/// The overlap graph of a set of reads.
+#[derive(Debug, Default)]
+pub struct OverlapGraph {
+    /// The length of each read
+    segments: BTreeMap<Vec<u8>, i32>,
+    /// The length of each dovetail overlap
+    links: BTreeMap<Link, i32>,
+}
+
+impl OverlapGraph {
+    /// Build the overlap graph from the overlaps in a PAF file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`IoError`][crate::error::LrgeError::IoError] if the file cannot be opened, or a
+    /// [`PafParseError`][crate::error::LrgeError::PafParseError] if it is not valid PAF.
+    pub fn from_paf<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        let mut graph = Self::default();
+        read_paf(path, |record| graph.add(&record))?;
+        Ok(graph)
+    }
+
+    /// Add the reads of an overlap, and the link between them, if it is a dovetail overlap.
+    fn add(&mut self, record: &PafRecord) {
+        self.segments
+            .insert(record.query_name.clone(), record.query_len);
+        self.segments
+            .insert(record.target_name.clone(), record.target_len);
+
+        if record.query_name == record.target_name {
+            return;
+        }
+        if let Some((link, overlap)) = dovetail(record) {
+            let overlap_len = self.links.entry(link.canonical()).or_insert(overlap);
+            *overlap_len = cmp::max(*overlap_len, overlap);
+        }
+    }
+
+    /// The number of segments (reads) in the graph.
+    pub fn num_segments(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// The number of links (dovetail overlaps) in the graph.
+    pub fn num_links(&self) -> usize {
+        self.links.len()
+    }
+
+    /// Write the graph in GFA (version 1) format. Segments and links are written in order of
+    /// their read names, so the output is the same for the same overlaps.
+    pub fn write<W: Write>(&self, mut writer: W) -> crate::Result<()> {
+        writeln!(writer, "H\tVN:Z:1.0")?;
+        for (name, len) in &self.segments {
+            writeln!(
+                writer,
+                "S\t{}\t*\tLN:i:{len}",
+                String::from_utf8_lossy(name)
+            )?;
+        }
+        for (link, overlap) in &self.links {
+            writeln!(
+                writer,
+                "L\t{}\t{}\t{}\t{}\t{overlap}M",
+                String::from_utf8_lossy(&link.from),
+                link.from_orient,
+                String::from_utf8_lossy(&link.to),
+                link.to_orient
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::LrgeError;
+    use crate::estimate::OVERLAPS_FILENAME;
+
+    fn record(
+        query: (&str, i32, i32, i32),
+        strand: char,
+        target: (&str, i32, i32, i32),
+    ) -> PafRecord {
+        PafRecord {
+            query_name: query.0.as_bytes().to_vec(),
+            query_len: query.1,
+            query_start: query.2,
+            query_end: query.3,
+            strand,
+            target_name: target.0.as_bytes().to_vec(),
+            target_len: target.1,
+            target_start: target.2,
+            target_end: target.3,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_dovetail() {
+        // the end of q overlaps the start of t
+        let (link, overlap) = dovetail(&record(
+            ("q", 10000, 6000, 10000),
+            '+',
+            ("t", 10000, 0, 4000),
+        ))
+        .unwrap();
+        assert_eq!((link.from.as_slice(), link.from_orient), (&b"q"[..], '+'));
+        assert_eq!((link.to.as_slice(), link.to_orient), (&b"t"[..], '+'));
+        assert_eq!(overlap, 4000);
+
+        // the end of t overlaps the start of q
+        let (link, _) = dovetail(&record(
+            ("q", 10000, 0, 4000),
+            '+',
+            ("t", 10000, 6000, 10000),
+        ))
+        .unwrap();
+        assert_eq!(link.from, b"t");
+
+        // the end of q overlaps the end of t, so the start of t's reverse complement
+        let (link, _) = dovetail(&record(
+            ("q", 10000, 6000, 10000),
+            '-',
+            ("t", 10000, 6000, 10000),
+        ))
+        .unwrap();
+        assert_eq!((link.from_orient, link.to_orient), ('+', '-'));
+    }
+
+    #[test]
+    fn test_not_dovetail() {
+        // q is contained in t
+        assert!(dovetail(&record(("q", 4000, 0, 4000), '+', ("t", 10000, 3000, 7000))).is_none());
+        // t is contained in q
+        assert!(dovetail(&record(("q", 10000, 3000, 7000), '+', ("t", 4000, 0, 4000))).is_none());
+        // internal to both reads
+        assert!(dovetail(&record(
+            ("q", 10000, 3000, 7000),
+            '+',
+            ("t", 10000, 3000, 7000)
+        ))
+        .is_none());
+    }
+
+    #[test]
+    fn test_graph_links_are_deduplicated() {
+        let mut graph = OverlapGraph::default();
+        graph.add(&record(
+            ("a", 10000, 6000, 10000),
+            '+',
+            ("b", 10000, 0, 4000),
+        ));
+        // the same overlap, with b as the query
+        graph.add(&record(
+            ("b", 10000, 0, 4100),
+            '+',
+            ("a", 10000, 6000, 10000),
+        ));
+        // a self-overlap, from mapping all-vs-all
+        graph.add(&record(("a", 10000, 0, 10000), '+', ("a", 10000, 0, 10000)));
+        // a containment only adds the contained read as a segment
+        graph.add(&record(("c", 1000, 0, 1000), '+', ("a", 10000, 2000, 3000)));
+
+        assert_eq!(graph.num_segments(), 3);
+        assert_eq!(graph.num_links(), 1);
+
+        let mut buf = Vec::new();
+        graph.write(&mut buf).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "H\tVN:Z:1.0\n\
+             S\ta\t*\tLN:i:10000\n\
+             S\tb\t*\tLN:i:10000\n\
+             S\tc\t*\tLN:i:1000\n\
+             L\ta\t+\tb\t+\t4100M\n"
+        );
+    }
+
+    #[test]
+    fn test_from_paf() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(OVERLAPS_FILENAME);
+        std::fs::write(
+            &path,
+            "a\t10000\t6000\t10000\t+\tb\t10000\t0\t4000\t3900\t4000\t0\ttp:A:P\tcm:i:59\ts1:i:190\tdv:f:0.0022\trl:i:56\n",
+        )
+        .unwrap();
+        let graph = OverlapGraph::from_paf(&path).unwrap();
+        assert_eq!((graph.num_segments(), graph.num_links()), (2, 1));
+
+        std::fs::write(&path, "a\tnot-a-number\n").unwrap();
+        assert!(matches!(
+            OverlapGraph::from_paf(&path),
+            Err(LrgeError::PafParseError { .. })
+        ));
+        assert!(matches!(
+            OverlapGraph::from_paf(dir.path().join("missing.paf")),
+            Err(LrgeError::IoError(_))
+        ));
+    }
+}
@@ -0,0 +1,9 @@
+//! The hashed collections used on the hot paths, such as checking read names are unique.
+//!
+//! Their keys are read names and IDs from files we wrote, so there is no need for the hash-flooding
+//! resistance of the standard library's SipHash, which shows up in profiles of large runs. The
+//! hasher for all of them is [`BuildHasher`], so swapping it out is a one-line change.
+pub(crate) type BuildHasher = rustc_hash::FxBuildHasher;
+
+pub(crate) type HashMap<K, V> = std::collections::HashMap<K, V, BuildHasher>;
+pub(crate) type HashSet<T> = std::collections::HashSet<T, BuildHasher>;
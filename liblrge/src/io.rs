@@ -1,6 +1,9 @@
+use std::collections::VecDeque;
+use std::ffi::CString;
+use std::fmt::Display;
 use std::fs::File;
 use std::io;
-use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
 use std::path::Path;
 
 #[cfg(feature = "bzip2")]
@@ -9,6 +12,9 @@ use bzip2::bufread::BzDecoder;
 use flate2::bufread::MultiGzDecoder;
 #[cfg(feature = "xz")]
 use liblzma::read::XzDecoder;
+use log::warn;
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
 use needletail::{parse_fastx_reader, FastxReader};
 #[cfg(feature = "zstd")]
 use zstd::stream::read::Decoder as ZstdDecoder;
@@ -62,42 +68,32 @@ fn detect_compression_format<R: Read + Seek>(reader: &mut R) -> io::Result<Compr
 
 pub(crate) enum SeqReader {
     Fastx(Box<dyn FastxReader>),
+    /// A FASTX reader that skips malformed records - see [`TolerantFastxReader`].
+    Tolerant(TolerantFastxReader<BufReader<Box<dyn Read + Send>>>),
     #[cfg(feature = "alignment")]
     Alignment(alignment::io::Reader<Box<dyn Read + Send>>),
+    /// An uncompressed FASTX file, read directly from memory - see [`MappedFastxReader`].
+    #[cfg(feature = "mmap")]
+    Mapped(MappedFastxReader),
 }
 
 impl SeqReader {
-    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    /// Open `path`, detecting its compression and format. If `tolerant` is `true`, FASTX files
+    /// are read with a [`TolerantFastxReader`], rather than needletail, so that malformed records
+    /// can be skipped. Otherwise, uncompressed FASTX files are memory mapped, if possible, and read
+    /// with a [`MappedFastxReader`].
+    pub fn new<P: AsRef<Path>>(path: P, tolerant: bool) -> io::Result<Self> {
         let mut file = File::open(&path).map(BufReader::new)?;
         let compression_format = detect_compression_format(&mut file)?;
 
-        let decompressed_reader: Box<dyn Read + Send> = match compression_format {
-            #[cfg(feature = "gzip")]
-            CompressionFormat::Gzip => Box::new(MultiGzDecoder::new(file)),
-            #[cfg(feature = "zstd")]
-            CompressionFormat::Zstd => Box::new(ZstdDecoder::new(file)?),
-            #[cfg(feature = "bzip2")]
-            CompressionFormat::Bzip2 => Box::new(BzDecoder::new(file)),
-            #[cfg(feature = "xz")]
-            CompressionFormat::Xz => Box::new(XzDecoder::new(file)),
-            CompressionFormat::None => Box::new(file),
-        };
-
-        // Sniff decompressed magic bytes
-        let mut reader = decompressed_reader;
-        let mut magic = [0; 4];
-        let n = reader.read(&mut magic)?;
-        let magic_slice = &magic[..n];
-
-        let is_alignment = magic_slice.starts_with(b"BAM\x01")
-            || magic_slice.starts_with(b"CRAM")
-            || magic_slice.starts_with(b"@HD")
-            || magic_slice.starts_with(b"@SQ")
-            || magic_slice.starts_with(b"@RG");
+        #[cfg(feature = "mmap")]
+        if compression_format == CompressionFormat::None && !tolerant {
+            if let Some(reader) = MappedFastxReader::open(file.get_ref()) {
+                return Ok(Self::Mapped(reader));
+            }
+        }
 
-        // Chain the sniffed bytes back to the reader
-        let full_reader: Box<dyn Read + Send> =
-            Box::new(io::Cursor::new(magic_slice.to_vec()).chain(reader));
+        let (full_reader, is_alignment) = decompress(file, compression_format)?;
 
         #[cfg(feature = "alignment")]
         if is_alignment {
@@ -112,58 +108,114 @@ impl SeqReader {
             ));
         }
 
+        if tolerant {
+            return Ok(Self::Tolerant(TolerantFastxReader::new(BufReader::new(
+                full_reader,
+            ))));
+        }
+
         let fastx_reader = parse_fastx_reader(full_reader)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
         Ok(Self::Fastx(fastx_reader))
     }
 }
 
-pub(crate) fn count_records<P: AsRef<Path>>(path: P) -> io::Result<usize> {
-    let mut reader = SeqReader::new(path)?;
-    let mut count = 0;
-    match &mut reader {
-        SeqReader::Fastx(r) => {
-            while let Some(res) = r.next() {
-                res.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-                count += 1;
-            }
-        }
-        #[cfg(feature = "alignment")]
-        SeqReader::Alignment(r) => {
-            let header = r.read_header()?;
-            for res in r.records(&header) {
-                res?;
-                count += 1;
-            }
-        }
-    }
-    if count == 0 {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "Is the file empty?",
-        ));
-    }
-    Ok(count)
+/// Decompress `file`, which is compressed with `compression_format`, and check whether it is an
+/// alignment (BAM/CRAM/SAM) file from its first bytes. The returned reader still starts with those
+/// bytes.
+fn decompress(
+    file: BufReader<File>,
+    compression_format: CompressionFormat,
+) -> io::Result<(Box<dyn Read + Send>, bool)> {
+    let decompressed_reader: Box<dyn Read + Send> = match compression_format {
+        #[cfg(feature = "gzip")]
+        CompressionFormat::Gzip => Box::new(MultiGzDecoder::new(file)),
+        #[cfg(feature = "zstd")]
+        CompressionFormat::Zstd => Box::new(ZstdDecoder::new(file)?),
+        #[cfg(feature = "bzip2")]
+        CompressionFormat::Bzip2 => Box::new(BzDecoder::new(file)),
+        #[cfg(feature = "xz")]
+        CompressionFormat::Xz => Box::new(XzDecoder::new(file)),
+        CompressionFormat::None => Box::new(file),
+    };
+
+    // Sniff decompressed magic bytes
+    let mut reader = decompressed_reader;
+    let mut magic = [0; 4];
+    let n = reader.read(&mut magic)?;
+    let magic_slice = &magic[..n];
+
+    let is_alignment = is_alignment_magic(magic_slice);
+
+    // Chain the sniffed bytes back to the reader
+    let full_reader: Box<dyn Read + Send> =
+        Box::new(io::Cursor::new(magic_slice.to_vec()).chain(reader));
+    Ok((full_reader, is_alignment))
+}
+
+/// Checks if the (decompressed) magic bytes at the start of a file are those of BAM, CRAM, or SAM.
+fn is_alignment_magic(magic: &[u8]) -> bool {
+    magic.starts_with(b"BAM\x01")
+        || magic.starts_with(b"CRAM")
+        || magic.starts_with(b"@HD")
+        || magic.starts_with(b"@SQ")
+        || magic.starts_with(b"@RG")
 }
 
+/// Call `callback` with the ID and sequence of each record in `path`.
+///
+/// By default (`max_bad_records` is `None`), a malformed record is an error. Otherwise, up to
+/// `max_bad_records` malformed records are logged and skipped, and one more is an error.
+///
+/// # Returns
+///
+/// The number of malformed records that were skipped.
 pub(crate) fn iter_records<P: AsRef<Path>>(
     path: P,
+    max_bad_records: Option<usize>,
     mut callback: impl FnMut(&[u8], &[u8]) -> io::Result<()>,
-) -> io::Result<()> {
-    let mut reader = SeqReader::new(path)?;
+) -> io::Result<usize> {
+    iter_records_with_quality(path, max_bad_records, |id, seq, _| callback(id, seq))
+}
+
+/// Call `callback` with the ID, sequence, and qualities of each record in `path`, as with
+/// [`iter_records`]. The qualities are Phred+33 encoded, as in FASTQ files, and are `None` for FASTA
+/// records and alignment records without qualities.
+pub(crate) fn iter_records_with_quality<P: AsRef<Path>>(
+    path: P,
+    max_bad_records: Option<usize>,
+    mut callback: impl FnMut(&[u8], &[u8], Option<&[u8]>) -> io::Result<()>,
+) -> io::Result<usize> {
+    let mut reader = SeqReader::new(path, max_bad_records.is_some())?;
+    let mut bad_records = BadRecords::new(max_bad_records.unwrap_or_default());
     match &mut reader {
         SeqReader::Fastx(r) => {
             while let Some(res) = r.next() {
                 let rec = res.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-                callback(rec.read_id(), &rec.seq())?;
+                callback(rec.read_id(), &rec.seq(), rec.qual())?;
             }
         }
+        SeqReader::Tolerant(r) => {
+            r.for_each_record(&mut bad_records, callback)?;
+        }
+        #[cfg(feature = "mmap")]
+        SeqReader::Mapped(r) => {
+            for_each_slice_record(&r.mmap, callback)?;
+        }
         #[cfg(feature = "alignment")]
         SeqReader::Alignment(r) => {
             let header = r.read_header()?;
             let mut seq_buf = Vec::new();
-            for res in r.records(&header) {
-                let record = res?;
+            let mut qual_buf = Vec::new();
+            for (i, res) in r.records(&header).enumerate() {
+                let record = match res {
+                    Ok(record) => record,
+                    Err(e) if max_bad_records.is_some() => {
+                        bad_records.skip(format_args!("record {}", i + 1), e)?;
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
                 if !record.flags().unwrap_or_default().is_unmapped() {
                     return Err(io::Error::new(
                         io::ErrorKind::InvalidData,
@@ -176,17 +228,505 @@ pub(crate) fn iter_records<P: AsRef<Path>>(
                 for base in sequence.as_ref().iter() {
                     seq_buf.push(base);
                 }
-                callback(name, &seq_buf)?;
+                qual_buf.clear();
+                for score in record.quality_scores().iter() {
+                    qual_buf.push(score?);
+                }
+                // missing qualities are empty, or 0xFF in BAM
+                let qual = if qual_buf.is_empty() || qual_buf.contains(&0xFF) {
+                    None
+                } else {
+                    qual_buf.iter_mut().for_each(|q| *q = q.saturating_add(33));
+                    Some(qual_buf.as_slice())
+                };
+                callback(name, &seq_buf, qual)?;
+            }
+        }
+    }
+    Ok(bad_records.count)
+}
+
+/// The size of the buffer FASTQ/FASTA files are [scanned][scan_records] through.
+const SCAN_BUFFER_SIZE: usize = 128 * 1024;
+
+/// Call `callback` with the ID and sequence length of each record in `path`, without parsing the
+/// records. This does less work than [`iter_records`] - e.g., for counting the records - but
+/// FASTQ/FASTA records are not checked, so malformed records are passed on like any other (see
+/// [`scan_fastx_records`]). Alignment files are parsed, as with [`iter_records`].
+pub(crate) fn scan_records<P: AsRef<Path>>(
+    path: P,
+    max_bad_records: Option<usize>,
+    mut callback: impl FnMut(&[u8], usize) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut file = File::open(&path).map(BufReader::new)?;
+    let compression_format = detect_compression_format(&mut file)?;
+
+    #[cfg(feature = "mmap")]
+    if compression_format == CompressionFormat::None {
+        if let Some(reader) = MappedFastxReader::open(file.get_ref()) {
+            return scan_fastx_records(&reader.mmap[..], callback);
+        }
+    }
+
+    let (reader, is_alignment) = decompress(file, compression_format)?;
+    if is_alignment {
+        drop(reader);
+        iter_records(path, max_bad_records, |id, seq| callback(id, seq.len()))?;
+        return Ok(());
+    }
+    scan_fastx_records(BufReader::with_capacity(SCAN_BUFFER_SIZE, reader), callback)
+}
+
+/// Call `callback` with the ID and sequence length of each record in the FASTQ/FASTA `reader`. The
+/// format is decided by its first character.
+///
+/// Only the header lines are copied out of the reader's buffer - the ends of the other lines are
+/// found with memchr, and the lines are skipped. FASTQ records are taken to be 4 lines, and FASTA
+/// records can be multi-line. Nothing else is checked, so a malformed record (e.g., a truncated
+/// record at the end of the file) is passed on like any other.
+fn scan_fastx_records<R: BufRead>(
+    mut reader: R,
+    mut callback: impl FnMut(&[u8], usize) -> io::Result<()>,
+) -> io::Result<()> {
+    let is_fasta = reader.fill_buf()?.first() == Some(&b'>');
+    let mut header = Vec::new();
+    loop {
+        header.clear();
+        if reader.read_until(b'\n', &mut header)? == 0 {
+            return Ok(());
+        }
+        let line = trim_line_end(&header);
+        if line.is_empty() {
+            continue;
+        }
+        let seq_len = if is_fasta {
+            let mut seq_len = 0;
+            while !matches!(reader.fill_buf()?.first(), None | Some(b'>')) {
+                seq_len += skip_line(&mut reader)?;
+            }
+            seq_len
+        } else {
+            let seq_len = skip_line(&mut reader)?;
+            // the separator and quality lines
+            skip_line(&mut reader)?;
+            skip_line(&mut reader)?;
+            seq_len
+        };
+        callback(record_id(&line[1..]), seq_len)?;
+    }
+}
+
+/// Skip the next line of `reader`, returning its length without the line ending. The length is 0
+/// at the end of the reader.
+fn skip_line<R: BufRead>(reader: &mut R) -> io::Result<usize> {
+    let mut len = 0;
+    // whether the line so far ends with a carriage return, which may be in an earlier buffer
+    let mut ends_with_cr = false;
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        if let Some(end) = memchr::memchr(b'\n', buf) {
+            if end > 0 {
+                ends_with_cr = buf[end - 1] == b'\r';
+            }
+            len += end;
+            reader.consume(end + 1);
+            break;
+        }
+        let n = buf.len();
+        ends_with_cr = buf[n - 1] == b'\r';
+        len += n;
+        reader.consume(n);
+    }
+    Ok(len - usize::from(ends_with_cr))
+}
+
+/// `line` without its line ending, if it has one.
+fn trim_line_end(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// Counts the malformed records that have been skipped, up to a maximum.
+struct BadRecords {
+    /// The maximum number of records that can be skipped
+    max: usize,
+    /// The number of records that have been skipped
+    count: usize,
+}
+
+impl BadRecords {
+    fn new(max: usize) -> Self {
+        Self { max, count: 0 }
+    }
+
+    /// Record that the malformed record at `location` was skipped, returning an error if this is
+    /// more than the maximum.
+    fn skip(&mut self, location: impl Display, reason: impl Display) -> io::Result<()> {
+        self.count += 1;
+        if self.count > self.max {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "More than {} malformed records - {location}: {reason}",
+                    self.max
+                ),
+            ));
+        }
+        warn!("Skipping malformed record ({location}): {reason}");
+        Ok(())
+    }
+}
+
+/// A minimal FASTQ/FASTA reader that can carry on after a malformed record - e.g., a truncated
+/// record, or a quality string of the wrong length - which needletail cannot.
+///
+/// FASTQ records must be 4 lines. After a malformed record, lines are skipped until one that
+/// starts with `@` is followed, two lines later, by one that starts with `+`. FASTA records can be
+/// multi-line, and any lines before the first header are skipped as a malformed record. The format
+/// is decided by the first character of the file.
+pub(crate) struct TolerantFastxReader<R> {
+    reader: R,
+    /// Lines that have been read, but not yet consumed
+    pending: VecDeque<Vec<u8>>,
+    /// The (1-based) number of the last line consumed
+    line_num: u64,
+}
+
+impl<R: BufRead> TolerantFastxReader<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            pending: VecDeque::new(),
+            line_num: 0,
+        }
+    }
+
+    /// Call `callback` with the ID, sequence, and qualities (for FASTQ) of each well-formed record,
+    /// skipping malformed records into `bad_records`.
+    fn for_each_record(
+        &mut self,
+        bad_records: &mut BadRecords,
+        callback: impl FnMut(&[u8], &[u8], Option<&[u8]>) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let is_fasta = loop {
+            match self.peek_line(0)? {
+                Some([]) => {
+                    self.next_line()?;
+                }
+                Some(line) => break line[0] == b'>',
+                None => return Ok(()),
+            }
+        };
+
+        if is_fasta {
+            self.for_each_fasta_record(bad_records, callback)
+        } else {
+            self.for_each_fastq_record(bad_records, callback)
+        }
+    }
+
+    fn for_each_fastq_record(
+        &mut self,
+        bad_records: &mut BadRecords,
+        mut callback: impl FnMut(&[u8], &[u8], Option<&[u8]>) -> io::Result<()>,
+    ) -> io::Result<()> {
+        while let Some(header) = self.next_line()? {
+            if header.is_empty() {
+                continue;
+            }
+            let location = format!("line {}", self.line_num);
+            if header[0] != b'@' {
+                bad_records.skip(location, "the header does not start with '@'")?;
+                self.resync_fastq()?;
+                continue;
+            }
+
+            let (Some(seq), Some(sep), Some(qual)) =
+                (self.next_line()?, self.next_line()?, self.next_line()?)
+            else {
+                bad_records.skip(location, "the record is truncated")?;
+                break;
+            };
+
+            let reason = if !sep.starts_with(b"+") {
+                "the separator does not start with '+'"
+            } else if seq.len() != qual.len() {
+                "the sequence and quality are different lengths"
+            } else {
+                callback(record_id(&header[1..]), &seq, Some(&qual))?;
+                continue;
+            };
+            bad_records.skip(location, reason)?;
+            // the next record may start within the lines of this one - e.g., if it is missing
+            // its quality line
+            self.unread([seq, sep, qual]);
+            self.resync_fastq()?;
+        }
+        Ok(())
+    }
+
+    fn for_each_fasta_record(
+        &mut self,
+        bad_records: &mut BadRecords,
+        mut callback: impl FnMut(&[u8], &[u8], Option<&[u8]>) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let mut seq = Vec::new();
+        while let Some(header) = self.next_line()? {
+            if !header.starts_with(b">") {
+                if !header.is_empty() {
+                    let location = format!("line {}", self.line_num);
+                    bad_records.skip(location, "the header does not start with '>'")?;
+                    while self.peek_line(0)?.is_some_and(|l| !l.starts_with(b">")) {
+                        self.next_line()?;
+                    }
+                }
+                continue;
+            }
+
+            seq.clear();
+            while self.peek_line(0)?.is_some_and(|l| !l.starts_with(b">")) {
+                if let Some(line) = self.next_line()? {
+                    seq.extend_from_slice(&line);
+                }
+            }
+            callback(record_id(&header[1..]), &seq, None)?;
+        }
+        Ok(())
+    }
+
+    /// Skip lines until the start of the next plausible FASTQ record.
+    fn resync_fastq(&mut self) -> io::Result<()> {
+        while let Some(line) = self.peek_line(0)? {
+            if line.starts_with(b"@") && self.peek_line(2)?.is_some_and(|l| l.starts_with(b"+")) {
+                break;
+            }
+            self.next_line()?;
+        }
+        Ok(())
+    }
+
+    /// Consume the next line, without its line ending.
+    fn next_line(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if self.pending.is_empty() && !self.read_line()? {
+            return Ok(None);
+        }
+        self.line_num += 1;
+        Ok(self.pending.pop_front())
+    }
+
+    /// Look at the `i`th line after the current one, without consuming it.
+    fn peek_line(&mut self, i: usize) -> io::Result<Option<&[u8]>> {
+        while self.pending.len() <= i {
+            if !self.read_line()? {
+                return Ok(None);
+            }
+        }
+        Ok(Some(&self.pending[i]))
+    }
+
+    /// Put consumed lines back, so they are the next lines.
+    fn unread<const N: usize>(&mut self, lines: [Vec<u8>; N]) {
+        self.line_num -= N as u64;
+        for line in lines.into_iter().rev() {
+            self.pending.push_front(line);
+        }
+    }
+
+    /// Read a line from the reader into the pending lines, returning `false` at the end of the
+    /// file.
+    fn read_line(&mut self) -> io::Result<bool> {
+        let mut line = Vec::new();
+        if self.reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(false);
+        }
+        if line.ends_with(b"\n") {
+            line.pop();
+            if line.ends_with(b"\r") {
+                line.pop();
+            }
+        }
+        self.pending.push_back(line);
+        Ok(true)
+    }
+}
+
+/// A reader for an uncompressed FASTQ/FASTA file that is memory mapped, so that it can be read
+/// without read syscalls or copying it into a buffer - the IDs and sequences passed on are slices
+/// of the file (other than the sequences of multi-line FASTA records).
+///
+/// Like needletail, malformed records are an error. FASTQ records must be 4 lines, and FASTA
+/// records can be multi-line.
+#[cfg(feature = "mmap")]
+pub(crate) struct MappedFastxReader {
+    mmap: Mmap,
+}
+
+#[cfg(feature = "mmap")]
+impl MappedFastxReader {
+    /// Map `file` into memory, if it can be and it starts like a FASTQ/FASTA file. Otherwise,
+    /// `None` is returned, and the file should be read another way - e.g., it is a pipe, or SAM.
+    fn open(file: &File) -> Option<Self> {
+        // SAFETY: the file must not be changed while it is mapped. This is the same assumption
+        // made of the input when it is read twice - once to sample it, and once to estimate.
+        let mmap = unsafe { Mmap::map(file) }.ok()?;
+        let is_fastx = matches!(mmap.first(), Some(b'@' | b'>')) && !is_alignment_magic(&mmap);
+        if !is_fastx {
+            return None;
+        }
+        #[cfg(unix)]
+        let _ = mmap.advise(memmap2::Advice::Sequential);
+        Some(Self { mmap })
+    }
+}
+
+/// The lines of a byte slice, without their line endings.
+#[cfg(feature = "mmap")]
+struct Lines<'a> {
+    data: &'a [u8],
+    /// The position of the start of the next line
+    pos: usize,
+    /// The (1-based) number of the last line returned
+    line_num: u64,
+}
+
+#[cfg(feature = "mmap")]
+impl<'a> Lines<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            line_num: 0,
+        }
+    }
+
+    /// Checks if the next line starts with `byte`, without consuming it.
+    fn next_starts_with(&self, byte: u8) -> bool {
+        self.data.get(self.pos) == Some(&byte)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl<'a> Iterator for Lines<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.data.len() {
+            return None;
+        }
+        let rest = &self.data[self.pos..];
+        let (mut line, len) = match memchr::memchr(b'\n', rest) {
+            Some(end) => (&rest[..end], end + 1),
+            None => (rest, rest.len()),
+        };
+        if let Some(stripped) = line.strip_suffix(b"\r") {
+            line = stripped;
+        }
+        self.pos += len;
+        self.line_num += 1;
+        Some(line)
+    }
+}
+
+#[cfg(feature = "mmap")]
+fn malformed_record(line_num: u64, reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Malformed record at line {line_num}: {reason}"),
+    )
+}
+
+/// Call `callback` with the ID, sequence, and qualities (for FASTQ) of each record in `data`, which
+/// is the contents of a FASTQ/FASTA file. The format is decided by its first character.
+#[cfg(feature = "mmap")]
+fn for_each_slice_record(
+    data: &[u8],
+    mut callback: impl FnMut(&[u8], &[u8], Option<&[u8]>) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut lines = Lines::new(data);
+
+    if data.first() == Some(&b'>') {
+        let mut seq = Vec::new();
+        while let Some(header) = lines.next() {
+            if header.is_empty() {
+                continue;
+            }
+            if header[0] != b'>' {
+                return Err(malformed_record(
+                    lines.line_num,
+                    "the header does not start with '>'",
+                ));
+            }
+            // most FASTA files of long reads have each sequence on a single line, which can be
+            // passed on without copying it
+            let first = if lines.next_starts_with(b'>') {
+                &[][..]
+            } else {
+                lines.next().unwrap_or_default()
+            };
+            if lines.pos >= data.len() || lines.next_starts_with(b'>') {
+                callback(record_id(&header[1..]), first, None)?;
+                continue;
+            }
+            seq.clear();
+            seq.extend_from_slice(first);
+            while !lines.next_starts_with(b'>') {
+                match lines.next() {
+                    Some(line) => seq.extend_from_slice(line),
+                    None => break,
+                }
             }
+            callback(record_id(&header[1..]), &seq, None)?;
         }
+        return Ok(());
+    }
+
+    while let Some(header) = lines.next() {
+        if header.is_empty() {
+            continue;
+        }
+        let line_num = lines.line_num;
+        if header[0] != b'@' {
+            return Err(malformed_record(
+                line_num,
+                "the header does not start with '@'",
+            ));
+        }
+        let (Some(seq), Some(sep), Some(qual)) = (lines.next(), lines.next(), lines.next()) else {
+            return Err(malformed_record(line_num, "the record is truncated"));
+        };
+        if !sep.starts_with(b"+") {
+            return Err(malformed_record(
+                line_num,
+                "the separator does not start with '+'",
+            ));
+        }
+        if seq.len() != qual.len() {
+            return Err(malformed_record(
+                line_num,
+                "the sequence and quality are different lengths",
+            ));
+        }
+        callback(record_id(&header[1..]), seq, Some(qual))?;
     }
     Ok(())
 }
 
+/// The read ID from a header line (without the leading `>` or `@`), which excludes the comment.
+fn record_id(header: &[u8]) -> &[u8] {
+    header
+        .split(|&x| x.is_ascii_whitespace())
+        .next()
+        .unwrap_or_default()
+}
+
 /// A message that can be sent in a channel.
 pub(crate) enum Message {
-    /// The intention is to send a read ID and a read sequence.
-    Data((Vec<u8>, Vec<u8>)),
+    /// The intention is to send a read ID and a read sequence. The ID is a C string, as that is how
+    /// it is passed to minimap2.
+    Data((CString, Vec<u8>)),
 }
 
 pub(crate) trait FastqRecordExt {
@@ -197,10 +737,7 @@ impl FastqRecordExt for needletail::parser::SequenceRecord<'_> {
     /// The needletail FastxRecord `id` method returns the whole header line, including the comment
     /// and the read ID. This method returns only the read ID.
     fn read_id(&self) -> &[u8] {
-        let id = self.id();
-        id.split(|&x| x.is_ascii_whitespace())
-            .next()
-            .unwrap_or_default()
+        record_id(self.id())
     }
 }
 
@@ -359,7 +896,7 @@ mod tests {
 
         let mut names = Vec::new();
         let mut seqs = Vec::new();
-        iter_records(temp_file.path(), |id, seq| {
+        iter_records(temp_file.path(), None, |id, seq| {
             names.push(id.to_vec());
             seqs.push(seq.to_vec());
             Ok(())
@@ -370,6 +907,102 @@ mod tests {
         assert_eq!(seqs, vec![b"GATTA"]);
     }
 
+    fn scan(data: &str) -> Vec<(String, usize)> {
+        let mut records = Vec::new();
+        scan_fastx_records(
+            io::BufReader::with_capacity(4, data.as_bytes()),
+            |id, len| {
+                records.push((String::from_utf8_lossy(id).to_string(), len));
+                Ok(())
+            },
+        )
+        .unwrap();
+        records
+    }
+
+    #[test]
+    fn test_scan_fastx_records_fastq() {
+        let data = "@r1 comment\nACGTACGT\n+\n@@@@@@@@\n\n@r2\r\nACG\r\n+r2\r\nIII\r\n@r3\nA\n+\nI";
+        assert_eq!(
+            scan(data),
+            [
+                ("r1".to_string(), 8),
+                ("r2".to_string(), 3),
+                ("r3".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_fastx_records_fasta() {
+        let data = ">r1 comment\nACGT\nAC\n\n>r2\r\nACGTACGTAC\r\n>r3\n>r4\nA";
+        assert_eq!(
+            scan(data),
+            [
+                ("r1".to_string(), 6),
+                ("r2".to_string(), 10),
+                ("r3".to_string(), 0),
+                ("r4".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_fastx_records_empty() {
+        assert!(scan("").is_empty());
+        assert!(scan("\n\n").is_empty());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_scan_records_gzip() {
+        use std::io::Write;
+
+        let mut file = tempfile::Builder::new()
+            .suffix(".fq.gz")
+            .tempfile()
+            .unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(&mut file, flate2::Compression::default());
+        encoder
+            .write_all(b"@r1\nACGT\n+\nIIII\n@r2\nAC\n+\nII\n")
+            .unwrap();
+        encoder.finish().unwrap();
+        let mut records = Vec::new();
+        scan_records(file.path(), None, |id, len| {
+            records.push((id.to_vec(), len));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(records, [(b"r1".to_vec(), 4), (b"r2".to_vec(), 2)]);
+    }
+
+    #[test]
+    fn test_iter_records_with_quality() {
+        use std::io::Write;
+        let read_quals = |data: &str, max_bad_records| {
+            let mut temp_file = tempfile::NamedTempFile::new().unwrap();
+            write!(temp_file, "{data}").unwrap();
+            let mut quals = Vec::new();
+            iter_records_with_quality(temp_file.path(), max_bad_records, |_, _, qual| {
+                quals.push(qual.map(|q| q.to_vec()));
+                Ok(())
+            })
+            .unwrap();
+            quals
+        };
+
+        let fastq = "@r1\nGATTA\n+\n!#%'I\n";
+        assert_eq!(read_quals(fastq, None), vec![Some(b"!#%'I".to_vec())]);
+        assert_eq!(read_quals(fastq, Some(1)), vec![Some(b"!#%'I".to_vec())]);
+        assert_eq!(read_quals(">r1\nGATTA\n", None), vec![None]);
+        assert_eq!(read_quals(">r1\nGATTA\n", Some(1)), vec![None]);
+        #[cfg(feature = "alignment")]
+        {
+            let sam = "@HD\tVN:1.6\tSO:unsorted\nR1\t4\t*\t0\t0\t*\t*\t0\t0\tGATTA\t!#%'I\nR2\t4\t*\t0\t0\t*\t*\t0\t0\tGATTA\t*\n";
+            assert_eq!(read_quals(sam, None), vec![Some(b"!#%'I".to_vec()), None]);
+        }
+    }
+
     #[test]
     #[cfg(feature = "alignment")]
     fn test_detect_mapped_alignment_errors() {
@@ -381,11 +1014,206 @@ mod tests {
         )
         .unwrap();
 
-        let result = iter_records(temp_file.path(), |_, _| Ok(()));
+        let result = iter_records(temp_file.path(), None, |_, _| Ok(()));
         assert!(result.is_err());
         assert!(result
             .unwrap_err()
             .to_string()
             .contains("Mapped records are not supported"));
     }
+
+    /// The records in `data` read by a [`TolerantFastxReader`], and the number skipped.
+    fn read_tolerant(data: &str, max: usize) -> io::Result<(Vec<(String, String)>, usize)> {
+        let mut reader = TolerantFastxReader::new(Cursor::new(data));
+        let mut bad_records = BadRecords::new(max);
+        let mut records = Vec::new();
+        reader.for_each_record(&mut bad_records, |id, seq, _| {
+            records.push((
+                String::from_utf8_lossy(id).into_owned(),
+                String::from_utf8_lossy(seq).into_owned(),
+            ));
+            Ok(())
+        })?;
+        Ok((records, bad_records.count))
+    }
+
+    fn record(id: &str, seq: &str) -> (String, String) {
+        (id.to_string(), seq.to_string())
+    }
+
+    #[test]
+    fn test_tolerant_reader_well_formed() {
+        let data = "@r1 comment\nACGT\n+\n!!!!\n@r2\r\nGG\r\n+r2\r\n@@\r\n";
+        let (records, skipped) = read_tolerant(data, 0).unwrap();
+        assert_eq!(records, vec![record("r1", "ACGT"), record("r2", "GG")]);
+        assert_eq!(skipped, 0);
+    }
+
+    #[test]
+    fn test_tolerant_reader_truncated_record() {
+        let data = "@r1\nACGT\n+\n!!!!\n@r2\nACGT\n";
+        let (records, skipped) = read_tolerant(data, 10).unwrap();
+        assert_eq!(records, vec![record("r1", "ACGT")]);
+        assert_eq!(skipped, 1);
+
+        // the quality is cut short
+        let data = "@r1\nACGT\n+\n!!!!\n@r2\nACGT\n+\n!!";
+        let (records, skipped) = read_tolerant(data, 10).unwrap();
+        assert_eq!(records, vec![record("r1", "ACGT")]);
+        assert_eq!(skipped, 1);
+    }
+
+    #[test]
+    fn test_tolerant_reader_resyncs_after_bad_record() {
+        // r2's quality is the wrong length
+        let data = "@r1\nA\n+\n!\n@r2\nACGT\n+\n!!\n@r3\nCC\n+\n@@\n";
+        let (records, skipped) = read_tolerant(data, 10).unwrap();
+        assert_eq!(records, vec![record("r1", "A"), record("r3", "CC")]);
+        assert_eq!(skipped, 1);
+
+        // r2 is missing its quality line, so r3's header is read in its place
+        let data = "@r1\nA\n+\n!\n@r2\nACGT\n+\n@r3\nCC\n+\n@@\n";
+        let (records, skipped) = read_tolerant(data, 10).unwrap();
+        assert_eq!(records, vec![record("r1", "A"), record("r3", "CC")]);
+        assert_eq!(skipped, 1);
+
+        // garbage before the first record
+        let data = "not a header\nACGT\n@r1\nA\n+\n!\n";
+        let (records, skipped) = read_tolerant(data, 10).unwrap();
+        assert_eq!(records, vec![record("r1", "A")]);
+        assert_eq!(skipped, 1);
+    }
+
+    #[test]
+    fn test_tolerant_reader_limit() {
+        let data = "@r1\nA\n+\n!!\n@r2\nA\n+\n!!\n@r3\nA\n+\n!\n";
+        let (records, skipped) = read_tolerant(data, 2).unwrap();
+        assert_eq!(records, vec![record("r3", "A")]);
+        assert_eq!(skipped, 2);
+
+        let err = read_tolerant(data, 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err
+            .to_string()
+            .contains("More than 1 malformed records - line 5"));
+    }
+
+    #[test]
+    fn test_tolerant_reader_fasta() {
+        let data = "\n>r1 comment\nAC\nGT\n>r2\n>r3\nTT\n";
+        let (records, skipped) = read_tolerant(data, 0).unwrap();
+        assert_eq!(
+            records,
+            vec![record("r1", "ACGT"), record("r2", ""), record("r3", "TT")]
+        );
+        assert_eq!(skipped, 0);
+    }
+
+    #[test]
+    fn test_iter_records_skips_bad_records() {
+        use std::io::Write;
+        let mut temp_file = tempfile::NamedTempFile::new().unwrap();
+        write!(temp_file, "@r1\nACGT\n+\n!!!!\n@r2\nACGT\n+\n!!").unwrap();
+
+        assert!(iter_records(temp_file.path(), None, |_, _| Ok(())).is_err());
+
+        let mut names = Vec::new();
+        let skipped = iter_records(temp_file.path(), Some(1), |id, _| {
+            names.push(id.to_vec());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(names, vec![b"r1"]);
+        assert_eq!(skipped, 1);
+    }
+
+    #[cfg(feature = "mmap")]
+    fn read_slice(data: &str) -> io::Result<Vec<(String, String)>> {
+        let mut records = Vec::new();
+        for_each_slice_record(data.as_bytes(), |id, seq, _| {
+            records.push((
+                String::from_utf8(id.to_vec()).unwrap(),
+                String::from_utf8(seq.to_vec()).unwrap(),
+            ));
+            Ok(())
+        })?;
+        Ok(records)
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn test_slice_reader_fastq() {
+        let data = "@r1 comment\nACGT\n+\n!!!!\n\n@r2\r\nGG\r\n+r2\r\n@@";
+        let records = read_slice(data).unwrap();
+        assert_eq!(records, vec![record("r1", "ACGT"), record("r2", "GG")]);
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn test_slice_reader_malformed_fastq() {
+        let err = read_slice("@r1\nA\n+\n!\n@r2\nACGT\n+\n!!\n").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "Malformed record at line 5: the sequence and quality are different lengths"
+        );
+
+        let err = read_slice("@r1\nA\n+\n!\n@r2\nACGT\n").unwrap_err();
+        assert!(err.to_string().contains("the record is truncated"));
+
+        let err = read_slice("@r1\nA\n-\n!\n").unwrap_err();
+        assert!(err.to_string().contains("the separator does not start"));
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn test_slice_reader_fasta() {
+        let data = ">r1 comment\nAC\nGT\n>r2\n>r3\nTT\n>r4\nA\r\nC";
+        let records = read_slice(data).unwrap();
+        assert_eq!(
+            records,
+            vec![
+                record("r1", "ACGT"),
+                record("r2", ""),
+                record("r3", "TT"),
+                record("r4", "AC")
+            ]
+        );
+
+        let records = read_slice(">r1\nACGT").unwrap();
+        assert_eq!(records, vec![record("r1", "ACGT")]);
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn test_uncompressed_fastx_is_mapped() {
+        use std::io::Write;
+        let mut temp_file = tempfile::NamedTempFile::new().unwrap();
+        write!(temp_file, "@r1\nACGT\n+\n!!!!\n").unwrap();
+        assert!(matches!(
+            SeqReader::new(temp_file.path(), false).unwrap(),
+            SeqReader::Mapped(_)
+        ));
+        // skipping malformed records needs the tolerant reader
+        assert!(matches!(
+            SeqReader::new(temp_file.path(), true).unwrap(),
+            SeqReader::Tolerant(_)
+        ));
+
+        let mut names = Vec::new();
+        iter_records(temp_file.path(), None, |id, seq| {
+            names.push(id.to_vec());
+            assert_eq!(seq, b"ACGT");
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(names, vec![b"r1"]);
+
+        let mut temp_file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(temp_file, "@HD\tVN:1.6").unwrap();
+        assert!(!matches!(
+            SeqReader::new(temp_file.path(), false),
+            Ok(SeqReader::Mapped(_))
+        ));
+    }
 }
@@ -0,0 +1,232 @@
+//! An independent estimate of the genome size from the number of "islands" - the connected
+//! components of the overlap graph - using [Lander-Waterman][lw] theory.
+//!
+//! If `N` reads of average length `L` are sampled at random from a genome of size `G`, and two
+//! reads must overlap by at least `T` bases to be joined, the expected number of islands is
+//!
+//! ```text
+//! E[islands] = N * exp(-c * (1 - T / L)),  where c = N * L / G
+//! ```
+//!
+//! so the genome size can be estimated from the number of islands observed among the sampled reads
+//! (including reads that overlap no others):
+//!
+//! ```text
+//! G = N * L * (1 - T / L) / ln(N / islands)
+//! ```
+//!
+//! This is a cross-check for the median of the per-read estimates, as it uses the connectivity of
+//! the reads rather than the number of overlaps of each one. It assumes the sampled reads are the
+//! reads that were overlapped against each other, so only the all-vs-all strategy's overlaps can be
+//! used. As for the [replicons][crate::replicon], only overlaps that are not internal matches
+//! join reads, so that repeats do not merge islands. Repeats that are longer than the reads still
+//! join islands that are not truly adjacent, which inflates the estimate.
+//!
+//! [lw]: https://doi.org/10.1016/0888-7543(88)90007-9
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use liblrge::estimate::OVERLAPS_FILENAME;
+//! use liblrge::islands::{island_estimate, DEFAULT_MIN_OVERLAP};
+//!
+//! let overlaps = format!("path/to/tmpdir/{OVERLAPS_FILENAME}");
+//! let reads = "path/to/tmpdir/reads.fa";
+//! if let Some(islands) = island_estimate(overlaps, reads, DEFAULT_MIN_OVERLAP).unwrap() {
+//!     println!("{} bp from {} islands", islands.estimate, islands.num_islands);
+//! }
+//! ```
+use std::path::Path;
+
+use crate::hash::HashSet;
+use crate::minimap2::mapping::read_paf;
+use crate::replicon::{Components, MAX_OVERHANG_RATIO};
+
+/// The default minimum overlap, in bases, for two reads to be joined. This is minimap2's minimum
+/// chaining score for its overlap presets, which approximates the minimum overlap length.
+pub const DEFAULT_MIN_OVERLAP: u32 = 100;
+
+/// The Lander-Waterman estimate of the genome size from the islands of the overlap graph.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IslandEstimate {
+    /// The genome size estimate, in bp
+    pub estimate: f32,
+    /// The number of reads
+    pub num_reads: usize,
+    /// The number of islands, including reads that overlap no others
+    pub num_islands: usize,
+    /// The average length of the reads
+    pub avg_read_len: f32,
+    /// The apparent depth of coverage of the reads, `c` in the equation above
+    pub coverage: f32,
+}
+
+/// The Lander-Waterman estimate of the genome size from the number of islands among `num_reads`
+/// reads of average length `avg_read_len`, which must overlap by at least `min_overlap` bases to be
+/// joined. Returns `None` if there are no reads, or no two reads overlap (i.e., there are as many
+/// islands as reads), as the genome size could then be anything larger.
+///
+/// # Examples
+///
+/// ```
+/// use liblrge::islands::lander_waterman;
+///
+/// // 1000 reads of 10 kbp in 10 islands
+/// let estimate = lander_waterman(1000, 10, 10_000.0, 1000).unwrap();
+/// assert!((estimate - 1_954_325.0).abs() < 10.0);
+/// assert_eq!(lander_waterman(1000, 1000, 10_000.0, 1000), None);
+/// ```
+pub fn lander_waterman(
+    num_reads: usize,
+    num_islands: usize,
+    avg_read_len: f32,
+    min_overlap: u32,
+) -> Option<f32> {
+    if num_islands == 0 || num_islands >= num_reads {
+        return None;
+    }
+    let coverage = apparent_coverage(num_reads, num_islands, avg_read_len, min_overlap)?;
+    Some(num_reads as f32 * avg_read_len / coverage)
+}
+
+/// The depth of coverage, `c`, that would give `num_islands` islands among `num_reads` reads.
+fn apparent_coverage(
+    num_reads: usize,
+    num_islands: usize,
+    avg_read_len: f32,
+    min_overlap: u32,
+) -> Option<f32> {
+    let sigma = 1.0 - min_overlap as f32 / avg_read_len;
+    if sigma <= 0.0 {
+        return None;
+    }
+    let ln_ratio = (num_reads as f64 / num_islands as f64).ln() as f32;
+    Some(ln_ratio / sigma)
+}
+
+/// Estimate the genome size from the islands of the overlaps in the PAF file `overlaps`, between
+/// the reads in the FASTA file `reads`. Overlaps with reads that are not in `reads` are ignored.
+/// Returns `None` if no two reads overlap, or the reads are not longer than `min_overlap` on
+/// average.
+///
+/// # Errors
+///
+/// Returns an [`IoError`][crate::error::LrgeError::IoError] if either file cannot be read, or a
+/// [`PafParseError`][crate::error::LrgeError::PafParseError] if the overlaps are not valid PAF.
+pub fn island_estimate<P: AsRef<Path>, Q: AsRef<Path>>(
+    overlaps: P,
+    reads: Q,
+    min_overlap: u32,
+) -> crate::Result<Option<IslandEstimate>> {
+    let mut components = Components::default();
+    read_paf(overlaps, |record| {
+        if record.query_name != record.target_name && !record.is_internal(MAX_OVERHANG_RATIO, None)
+        {
+            components.union(&record.query_name, &record.target_name);
+        }
+    })?;
+
+    let mut num_reads = 0;
+    let mut total_len = 0;
+    let mut num_singletons = 0;
+    let mut roots = HashSet::default();
+    crate::io::iter_records(reads.as_ref(), None, |id, seq| {
+        num_reads += 1;
+        total_len += seq.len();
+        match components.component(id) {
+            Some(root) => {
+                roots.insert(root);
+            }
+            None => num_singletons += 1,
+        }
+        Ok(())
+    })?;
+
+    let num_islands = roots.len() + num_singletons;
+    if num_reads == 0 {
+        return Ok(None);
+    }
+    let avg_read_len = total_len as f32 / num_reads as f32;
+    let estimate = lander_waterman(num_reads, num_islands, avg_read_len, min_overlap);
+    let coverage = apparent_coverage(num_reads, num_islands, avg_read_len, min_overlap);
+    Ok(estimate
+        .zip(coverage)
+        .map(|(estimate, coverage)| IslandEstimate {
+            estimate,
+            num_reads,
+            num_islands,
+            avg_read_len,
+            coverage,
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const TAGS: &str = "\ttp:A:S\tcm:i:59\ts1:i:190\tdv:f:0.0022\trl:i:0\n";
+
+    /// A PAF line where the end of `query` overlaps the start of `target`.
+    fn dovetail(query: &str, target: &str) -> String {
+        format!("{query}\t1000\t500\t1000\t+\t{target}\t1000\t0\t500\t500\t500\t0{TAGS}")
+    }
+
+    #[test]
+    fn test_lander_waterman() {
+        assert_eq!(lander_waterman(0, 0, 1000.0, 100), None);
+        assert_eq!(lander_waterman(10, 10, 1000.0, 100), None);
+        // reads no longer than the minimum overlap never join
+        assert_eq!(lander_waterman(10, 5, 100.0, 100), None);
+
+        // one island: c = ln(N) / (1 - T / L)
+        let coverage = (100f32).ln() / 0.9;
+        let estimate = lander_waterman(100, 1, 1000.0, 100).unwrap();
+        assert!((estimate - 100.0 * 1000.0 / coverage).abs() < 1.0);
+
+        // fewer islands means more coverage, so a smaller genome
+        assert!(lander_waterman(100, 2, 1000.0, 100).unwrap() > estimate);
+    }
+
+    #[test]
+    fn test_island_estimate() {
+        let dir = tempfile::tempdir().unwrap();
+        let overlaps = dir.path().join("overlaps.paf");
+        let reads = dir.path().join("reads.fa");
+        // a-b-c and d-e are islands, f overlaps no others
+        let lines = [
+            dovetail("a", "b"),
+            dovetail("b", "c"),
+            dovetail("d", "e"),
+            dovetail("f", "f"),
+            // an internal match does not join islands
+            format!("a\t1000\t400\t600\t+\td\t1000\t400\t600\t200\t200\t0{TAGS}"),
+            // nor does an overlap with a read that was not sampled
+            dovetail("c", "z"),
+        ];
+        std::fs::write(&overlaps, lines.concat()).unwrap();
+        let mut file = std::fs::File::create(&reads).unwrap();
+        for (name, len) in [
+            ("a", 1000),
+            ("b", 1000),
+            ("c", 1000),
+            ("d", 1000),
+            ("e", 1000),
+            ("f", 1000),
+        ] {
+            writeln!(file, ">{name}\n{}", "A".repeat(len)).unwrap();
+        }
+        drop(file);
+
+        let islands = island_estimate(&overlaps, &reads, 100).unwrap().unwrap();
+        assert_eq!(islands.num_reads, 6);
+        assert_eq!(islands.num_islands, 3);
+        assert_eq!(islands.avg_read_len, 1000.0);
+        assert_eq!(Some(islands.estimate), lander_waterman(6, 3, 1000.0, 100));
+        assert!((islands.coverage - 2f32.ln() / 0.9).abs() < 1e-6);
+
+        std::fs::write(&overlaps, dovetail("f", "f")).unwrap();
+        assert_eq!(island_estimate(&overlaps, &reads, 100).unwrap(), None);
+    }
+}
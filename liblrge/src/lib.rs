@@ -9,7 +9,8 @@
 //!
 //! ## Usage
 //!
-//! The library provides two strategies for estimating genome size:
+//! The library provides two strategies for estimating genome size, along with a faster, less
+//! accurate, sketch-based strategy:
 //!
 //! ### [`TwoSetStrategy`]
 //!
@@ -56,6 +57,42 @@
 //! // do something with the estimate
 //! ```
 //!
+//! ### [`SketchStrategy`]
+//!
+//! For a very fast, but less accurate, first pass, the sketch strategy finds the overlaps between a
+//! (random) set of reads from [FracMinHash sketches](sketch) of their k-mers, rather than aligning
+//! them. The estimates are then generated as in the all-vs-all strategy.
+//!
+//! ```no_run
+//! use liblrge::{Estimate, SketchStrategy};
+//!
+//! let mut strategy = SketchStrategy::new("path/to/reads.fastq");
+//! let est_result = strategy.estimate(false, None, None).expect("Failed to generate estimate");
+//! ```
+//!
+//! ### Choosing the strategy at runtime
+//!
+//! If the strategy is only known at runtime (e.g., from a command-line option), a
+//! [`StrategyBuilder`] takes the settings shared by both strategies, along with the
+//! [`StrategyKind`] to build, and builds a [`Strategy`] that can be either.
+//!
+//! ```no_run
+//! use liblrge::{Estimate, StrategyBuilder, StrategyKind};
+//!
+//! let use_ava = true;
+//! let kind = if use_ava {
+//!     StrategyKind::Ava { num_reads: 25_000 }
+//! } else {
+//!     StrategyKind::default()
+//! };
+//! let mut strategy = StrategyBuilder::new("path/to/reads.fastq")
+//!     .strategy(kind)
+//!     .threads(4)
+//!     .build();
+//!
+//! let est_result = strategy.estimate(false, None, None).expect("Failed to generate estimate");
+//! ```
+//!
 //! ## Features
 //!
 //! This library includes optional support for compressed file formats and alignment formats, controlled by feature flags.
@@ -69,24 +106,51 @@
 //! - **zstd**: Enables support for zstd-compressed files (`.zst`) using the [`zstd`][zstd] crate.
 //! - **bzip2**: Enables support for bzip2-compressed files (`.bz2`) using the [`bzip2`][bzip2] crate.
 //! - **xz**: Enables support for xz-compressed files (`.xz`) using the [`liblzma`][xz] crate.
+//! - **mmap** (default): Reads uncompressed FASTA/FASTQ files by memory mapping them with the [`memmap2`][memmap2] crate,
+//!   which avoids read syscalls and copying the reads into a buffer. The file must not be changed while it is being read.
+//! - **serde**: Derives [`serde`][serde]'s `Serialize` and `Deserialize` for [`EstimateResult`][estimate::EstimateResult],
+//!   [`PerReadEstimate`][estimate::PerReadEstimate], [`GcSummary`][gc::GcSummary], [`Platform`], and [`OverlapFormat`]. This is not enabled by default.
+//! - **vendored-minimap2** (default): Compiles the copy of minimap2 that comes with [`minimap2-sys`][minimap2-sys].
+//! - **system-minimap2**: Links a minimap2 library installed on the system instead (see
+//!   [below](#linking-a-system-minimap2)). One of the two minimap2 features must be enabled; if
+//!   both are, `vendored-minimap2` is used.
+//! - **clap**: Implements [`clap`][clap]'s `ValueEnum` for [`Platform`] and [`OverlapFormat`], so they can be used directly
+//!   as command-line arguments. This is not enabled by default.
 //!
 //! ### Enabling and Disabling Features
 //!
-//! By default, all features are enabled. However, you can selectively enable or disable them
+//! By default, all features except `serde`, `clap`, and `system-minimap2` are enabled. However, you can selectively enable or disable them
 //! in your `Cargo.toml` to reduce dependencies or target specific formats:
 //!
-//! To **disable all optional features**:
+//! To **disable all optional features** (the minimap2 library is not optional):
 //!
 //! ```toml
-//! liblrge = { version = "0.2.2", default-features = false }
+//! liblrge = { version = "0.2.2", default-features = false, features = ["vendored-minimap2"] }
 //! ```
 //!
 //! To enable only specific features, list them in `Cargo.toml`:
 //!
 //! ```toml
-//! liblrge = { version = "0.2.2", default-features = false, features = ["gzip", "alignment"] }
+//! liblrge = { version = "0.2.2", default-features = false, features = ["vendored-minimap2", "gzip", "alignment"] }
 //! ```
 //!
+//! ### Linking a system minimap2
+//!
+//! By default (the `vendored-minimap2` feature), the copy of minimap2 that comes with
+//! [`minimap2-sys`][minimap2-sys] is compiled. To link a minimap2 library installed on the system
+//! instead - e.g., a build of minimap2 tuned for your CPUs - use the `system-minimap2` feature:
+//!
+//! ```toml
+//! liblrge = { version = "0.3.0", default-features = false, features = ["system-minimap2", "compression"] }
+//! ```
+//!
+//! The library is looked for in the directory `MINIMAP2_LIB_DIR`, if it is set, and linked
+//! statically if `MINIMAP2_STATIC` is set. The library must be minimap2 2.29-r1283 - the release
+//! `minimap2-sys` bundles - as the declarations liblrge uses follow the layout of its structs. The
+//! build checks the version in the library's `minimap.h`, which is looked for in
+//! `MINIMAP2_INCLUDE_DIR`, `MINIMAP2_LIB_DIR`, `/usr/include`, and `/usr/local/include`, and fails
+//! if it is not found or is a different release.
+//!
 //! ## Format Detection
 //!
 //! The library uses [**magic bytes**][magic] at the start of the file to detect its compression
@@ -100,6 +164,10 @@
 //! [xz]: https://crates.io/liblzma
 //! [bzip2]: https://crates.io/crates/bzip2
 //! [noodles]: https://crates.io/crates/noodles
+//! [serde]: https://crates.io/crates/serde
+//! [memmap2]: https://crates.io/crates/memmap2
+//! [clap]: https://crates.io/crates/clap
+//! [minimap2-sys]: https://crates.io/crates/minimap2-sys
 //! [magic]: https://en.wikipedia.org/wiki/Magic_number_(programming)#In_files
 //!
 //! ## Disabling logging
@@ -125,142 +193,503 @@
 //! [log]: https://crates.io/crates/log
 //! [env_logger]: https://crates.io/crates/env_logger
 //! [doi]: https://doi.org/10.1101/2024.11.27.625777
+pub mod adapter;
+pub mod artifact;
 #[deny(missing_docs)]
 pub mod ava;
+pub mod batch;
+pub mod check;
+pub(crate) mod count_cache;
+pub mod env;
 pub mod error;
 pub mod estimate;
+pub mod gc;
+pub mod gfa;
+pub(crate) mod hash;
 pub(crate) mod io;
+pub mod islands;
+pub(crate) mod memory;
 pub(crate) mod minimap2;
+pub mod overlap_stats;
+pub mod poisson;
+pub mod rarefaction;
+pub(crate) mod read_filter;
+pub mod replicon;
+pub(crate) mod rng;
+pub(crate) mod sample;
+pub mod seed;
+pub mod sketch;
+pub mod strategy;
+pub mod stream;
+pub(crate) mod tmpdir;
+pub mod trim;
 pub mod twoset;
 
-use rand::rngs::StdRng;
-use rand::SeedableRng;
-
 pub use self::ava::AvaStrategy;
 pub use self::estimate::Estimate;
+pub use self::memory::is_ram_backed;
+pub use self::minimap2::{IdxOpt, MapOpt, PafReader, PafRecord};
+pub use self::sketch::SketchStrategy;
+pub use self::strategy::{AutoStrategy, Strategy, StrategyBuilder, StrategyKind};
 pub use self::twoset::TwoSetStrategy;
+use std::fmt;
+use std::path::Path;
 use std::str::FromStr;
+use std::sync::Arc;
 
 /// A type alias for `Result` with [`LrgeError`][crate::error::LrgeError] as the error type.
 pub type Result<T> = std::result::Result<T, error::LrgeError>;
 
+/// The version of this library (e.g., `0.3.0`).
+///
+/// # Examples
+///
+/// ```
+/// assert!(!liblrge::VERSION.is_empty());
+/// ```
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The version of minimap2 that this library is built with (e.g., `2.29-r1283`). The overlaps found
+/// between reads - and so the estimates - can change between minimap2 releases, so this should be
+/// recorded alongside an estimate to make it reproducible.
+///
+/// # Examples
+///
+/// ```
+/// let version = liblrge::minimap2_version();
+/// assert!(version.starts_with("2."));
+/// ```
+pub fn minimap2_version() -> &'static str {
+    minimap2::ffi::MM_VERSION
+        .to_str()
+        .expect("the minimap2 version is valid UTF-8")
+}
+
 /// The sequencing platform used to generate the reads.
 ///
+/// The platform determines the minimap2 preset used to overlap the reads - `ava-ont` for Oxford
+/// Nanopore (simplex) reads, and `ava-pb` for PacBio reads (both HiFi and CLR). Oxford Nanopore
+/// duplex reads are much more accurate than simplex reads - they behave more like HiFi reads - so
+/// they are overlapped with `ava-pb` too. A platform can be parsed from any of its
+/// [names][Platform::names], ignoring case. It is displayed - and, with the `serde` feature,
+/// serialized - as its canonical name, the first of those names, so it round-trips through both.
+///
 /// # Examples
 ///
 /// ```
 /// use std::str::FromStr;
 /// use liblrge::Platform;
 ///
-/// for platform in ["pacbio", "pb"] {
+/// for platform in ["pacbio", "pb", "hifi", "clr"] {
 ///     assert_eq!(Platform::from_str(platform).unwrap(), Platform::PacBio);
 /// }
 ///
-/// for platform in ["nanopore", "ont"] {
+/// for platform in ["nanopore", "ont", "ont-r10", "nanopore-r9", "rna"] {
 ///     assert_eq!(Platform::from_str(platform).unwrap(), Platform::Nanopore);
 /// }
+///
+/// assert_eq!(Platform::from_str("duplex").unwrap(), Platform::NanoporeDuplex);
 /// ```
-#[derive(Debug, Default, Eq, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
 pub enum Platform {
     PacBio,
     #[default]
     Nanopore,
+    NanoporeDuplex,
+}
+
+impl Platform {
+    /// All platforms.
+    pub const ALL: [Platform; 3] = [
+        Platform::Nanopore,
+        Platform::NanoporeDuplex,
+        Platform::PacBio,
+    ];
+
+    /// The names the platform can be parsed from. The first is its canonical (short) name, which
+    /// is also what it is displayed as.
+    pub fn names(&self) -> &'static [&'static str] {
+        match self {
+            Platform::Nanopore => &[
+                "ont",
+                "nanopore",
+                "ont-r9",
+                "ont-r10",
+                "nanopore-r9",
+                "nanopore-r10",
+                "rna",
+                "ont-rna",
+            ],
+            Platform::NanoporeDuplex => &["duplex", "ont-duplex", "nanopore-duplex"],
+            Platform::PacBio => &["pb", "pacbio", "hifi", "clr", "pacbio-hifi", "pacbio-clr"],
+        }
+    }
+
+    /// The name of the minimap2 preset used to overlap reads from the platform - e.g., `ava-ont`.
+    pub fn minimap2_preset(&self) -> &'static str {
+        self.preset().name()
+    }
+
+    /// The minimap2 preset used to overlap reads from the platform.
+    pub(crate) fn preset(&self) -> minimap2::Preset {
+        match self {
+            Platform::Nanopore => minimap2::Preset::AvaOnt,
+            Platform::NanoporeDuplex | Platform::PacBio => minimap2::Preset::AvaPb,
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.names()[0])
+    }
 }
 
 impl FromStr for Platform {
     type Err = error::LrgeError;
 
     fn from_str(s: &str) -> Result<Self> {
-        match s.to_lowercase().as_str() {
-            "pacbio" | "pb" => Ok(Platform::PacBio),
-            "nanopore" | "ont" => Ok(Platform::Nanopore),
-            _ => Err(error::LrgeError::InvalidPlatform(s.to_string())),
-        }
+        let name = s.to_lowercase();
+        Platform::ALL
+            .into_iter()
+            .find(|platform| platform.names().contains(&name.as_str()))
+            .ok_or_else(|| error::LrgeError::InvalidPlatform(s.to_string()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Platform {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
     }
 }
 
-/// Generate a shuffled list of `k` indices from 0 to `n`.
+/// Accepts the same names as [`FromStr`].
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Platform {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Platform::from_str(&name).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Allows the platform to be used directly as a command-line argument, accepting the same names
+/// as [`FromStr`].
+#[cfg(feature = "clap")]
+impl clap::ValueEnum for Platform {
+    fn value_variants<'a>() -> &'a [Self] {
+        &Platform::ALL
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        let (name, aliases) = self.names().split_first()?;
+        let help = match self {
+            Platform::Nanopore => "Oxford Nanopore reads, including direct RNA",
+            Platform::NanoporeDuplex => "Oxford Nanopore duplex reads",
+            Platform::PacBio => "PacBio HiFi or CLR reads",
+        };
+        Some(
+            clap::builder::PossibleValue::new(*name)
+                .aliases(aliases.iter().copied())
+                .help(help),
+        )
+    }
+}
+
+/// The format the overlaps are written in, within a strategy's temporary directory.
+///
+/// minimap2 is not asked for base-level alignments when overlapping reads, so only PAF has all of
+/// the information about an overlap. The other formats are for downstream tools that expect them:
 ///
-/// # Arguments
+/// - **SAM** records have no CIGAR, sequence, or qualities (`*`), and the query coordinates are in
+///   the `qs`, `qe`, and `ql` tags (start, end, and length), with the target end in `te`. The
+///   header has an `@SQ` line for each read that was overlapped.
+/// - **BLAST tabular** (`-outfmt 6`) identity and mismatches are from the number of matching bases
+///   and the alignment block length, gap openings are always 0, the e-value is always 0, and the
+///   bit score is minimap2's chaining score.
 ///
-/// * `k`: The number of indices to generate.
-/// * `n`: The maximum value for the range (exclusive).
-/// * `seed`: An optional seed for the random number generator.
-pub(crate) fn unique_random_set(k: usize, n: u32, seed: Option<u64>) -> Vec<u32> {
-    // Initialize RNG, using the seed if provided
-    let mut rng = match seed {
-        Some(seed_value) => StdRng::seed_from_u64(seed_value),
-        None => StdRng::from_rng(&mut rand::rng()),
-    };
-
-    if k > n as usize {
-        panic!("Cannot generate {k} unique values from a range of 0 to {n}",);
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use liblrge::OverlapFormat;
+///
+/// assert_eq!(OverlapFormat::from_str("SAM").unwrap(), OverlapFormat::Sam);
+/// assert_eq!(OverlapFormat::default().filename(), "overlaps.paf");
+/// ```
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum OverlapFormat {
+    /// [Pairwise mApping Format](https://github.com/lh3/miniasm/blob/master/PAF.md)
+    #[default]
+    Paf,
+    /// [Sequence Alignment/Map](https://samtools.github.io/hts-specs/SAMv1.pdf) format
+    Sam,
+    /// BLAST tabular output (`-outfmt 6`)
+    Blast6,
+}
+
+impl OverlapFormat {
+    /// All overlap formats.
+    pub const ALL: [OverlapFormat; 3] = [
+        OverlapFormat::Paf,
+        OverlapFormat::Sam,
+        OverlapFormat::Blast6,
+    ];
+
+    /// The name of the format, which is also what it is parsed from and displayed as.
+    pub fn name(&self) -> &'static str {
+        match self {
+            OverlapFormat::Paf => "paf",
+            OverlapFormat::Sam => "sam",
+            OverlapFormat::Blast6 => "blast6",
+        }
     }
 
-    rand::seq::index::sample(&mut rng, n as usize, k)
-        .into_iter()
-        .map(|x| x as u32)
-        .collect()
+    /// The name of the overlaps file, within a strategy's temporary directory, in this format.
+    pub fn filename(&self) -> &'static str {
+        match self {
+            OverlapFormat::Paf => estimate::OVERLAPS_FILENAME,
+            OverlapFormat::Sam => "overlaps.sam",
+            OverlapFormat::Blast6 => "overlaps.blast6",
+        }
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::collections::HashSet;
+impl fmt::Display for OverlapFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
 
-    #[test]
-    fn test_unique_random_set_basic_functionality() {
-        let k = 5;
-        let n = 100;
+impl FromStr for OverlapFormat {
+    type Err = error::LrgeError;
 
-        for _ in 0..1000 {
-            let result = unique_random_set(k, n, None);
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.to_lowercase();
+        OverlapFormat::ALL
+            .into_iter()
+            .find(|format| format.name() == name)
+            .ok_or_else(|| error::LrgeError::InvalidOverlapFormat(s.to_string()))
+    }
+}
 
-            // Check that result has exactly k elements
-            assert_eq!(result.len(), k);
+/// Allows the overlap format to be used directly as a command-line argument.
+#[cfg(feature = "clap")]
+impl clap::ValueEnum for OverlapFormat {
+    fn value_variants<'a>() -> &'a [Self] {
+        &OverlapFormat::ALL
+    }
 
-            // Check that all elements are within the range 0 to n-1
-            assert!(result.iter().all(|&x| x < n));
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        let help = match self {
+            OverlapFormat::Paf => "PAF, as written by minimap2",
+            OverlapFormat::Sam => "SAM, without CIGAR or sequences",
+            OverlapFormat::Blast6 => "BLAST tabular (-outfmt 6)",
+        };
+        Some(clap::builder::PossibleValue::new(self.name()).help(help))
+    }
+}
+
+/// Resolve the number of threads to use. A value of 0 means use all available cores, as reported
+/// by [`std::thread::available_parallelism`]. If that cannot be determined, 1 thread is used.
+pub(crate) fn resolve_threads(threads: usize) -> usize {
+    if threads > 0 {
+        return threads;
+    }
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
 
-            // check that all elements are unique
-            assert_eq!(result.len(), result.iter().collect::<HashSet<_>>().len());
+/// Check the builder options that are shared by all strategies. `max_overhang_ratio` is only
+/// given if internal overlaps are being removed.
+pub(crate) fn validate_builder(
+    input: &Path,
+    tmpdir: &Path,
+    max_overhang_ratio: Option<f32>,
+    max_divergence: Option<f32>,
+    max_memory: Option<usize>,
+) -> Result<()> {
+    if let Some(ratio) = max_overhang_ratio {
+        if !(0.0..=1.0).contains(&ratio) {
+            return Err(error::LrgeError::invalid_configuration(
+                "max_overhang_ratio",
+                format!("must be between 0 and 1, but got {ratio}"),
+            ));
         }
     }
+    if let Some(divergence) = max_divergence {
+        if !(0.0..=1.0).contains(&divergence) {
+            return Err(error::LrgeError::invalid_configuration(
+                "max_divergence",
+                format!("must be between 0 and 1, but got {divergence}"),
+            ));
+        }
+    }
+    if max_memory == Some(0) {
+        return Err(error::LrgeError::invalid_configuration(
+            "max_memory",
+            "must be greater than 0",
+        ));
+    }
+    if !tmpdir.is_dir() {
+        return Err(error::LrgeError::invalid_configuration(
+            "tmpdir",
+            format!("{} is not an existing directory", tmpdir.display()),
+        ));
+    }
+    if !input.exists() {
+        return Err(error::LrgeError::invalid_configuration(
+            "input",
+            format!("{} does not exist", input.display()),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Get the thread pool to map reads in. This is the caller-provided `pool` if there is one,
+/// otherwise a new pool with `threads` threads is built.
+pub(crate) fn mapping_pool(
+    pool: Option<&Arc<rayon::ThreadPool>>,
+    threads: usize,
+) -> Result<Arc<rayon::ThreadPool>> {
+    if let Some(pool) = pool {
+        return Ok(Arc::clone(pool));
+    }
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map(Arc::new)
+        .map_err(error::LrgeError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
     #[test]
-    fn test_unique_random_set_with_seed() {
-        let k = 5;
-        let n = 1000000;
-        let seed = Some(42);
+    fn test_resolve_threads() {
+        assert_eq!(resolve_threads(4), 4);
+        assert!(resolve_threads(0) >= 1);
+    }
 
-        // Generate two sets with the same seed
-        let result1 = unique_random_set(k, n, seed);
-        let result2 = unique_random_set(k, n, seed);
+    #[test]
+    fn test_platform_names() {
+        for platform in Platform::ALL {
+            for name in platform.names() {
+                assert_eq!(Platform::from_str(name).unwrap(), platform);
+                assert_eq!(Platform::from_str(&name.to_uppercase()).unwrap(), platform);
+            }
+        }
+        assert_eq!(Platform::PacBio.to_string(), "pb");
+        assert_eq!(Platform::Nanopore.to_string(), "ont");
+        assert_eq!(Platform::NanoporeDuplex.to_string(), "duplex");
+        assert!(matches!(
+            Platform::from_str("illumina"),
+            Err(error::LrgeError::InvalidPlatform(_))
+        ));
+    }
 
-        // They should be the same due to the same seed
-        assert_eq!(result1, result2);
+    #[test]
+    fn test_platform_preset() {
+        assert_eq!(Platform::Nanopore.preset().as_bytes(), b"ava-ont\0");
+        assert_eq!(Platform::NanoporeDuplex.preset().as_bytes(), b"ava-pb\0");
+        assert_eq!(Platform::PacBio.preset().as_bytes(), b"ava-pb\0");
+        assert_eq!(Platform::NanoporeDuplex.minimap2_preset(), "ava-pb");
     }
 
     #[test]
-    fn test_unique_random_set_without_seed() {
-        let k = 5;
-        let n = 10000000;
+    fn test_validate_builder() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("reads.fq");
+        std::fs::write(&input, "").unwrap();
 
-        // Generate two sets without a seed
-        let result1 = unique_random_set(k, n, None);
-        let result2 = unique_random_set(k, n, None);
+        assert!(validate_builder(&input, dir.path(), Some(0.2), Some(0.1), Some(1024)).is_ok());
+        assert!(validate_builder(&input, dir.path(), None, None, None).is_ok());
 
-        // They should generally be different
-        assert_ne!(result1, result2);
+        let invalid_option = |result: Result<()>| match result {
+            Err(error::LrgeError::InvalidConfiguration { option, .. }) => option,
+            other => panic!("expected an invalid configuration error, got {other:?}"),
+        };
+        assert_eq!(
+            invalid_option(validate_builder(&input, dir.path(), Some(1.5), None, None)),
+            "max_overhang_ratio"
+        );
+        assert_eq!(
+            invalid_option(validate_builder(&input, dir.path(), None, Some(-0.1), None)),
+            "max_divergence"
+        );
+        assert_eq!(
+            invalid_option(validate_builder(&input, dir.path(), None, None, Some(0))),
+            "max_memory"
+        );
+        assert_eq!(
+            invalid_option(validate_builder(
+                &input,
+                &dir.path().join("nope"),
+                None,
+                None,
+                None
+            )),
+            "tmpdir"
+        );
+        assert_eq!(
+            invalid_option(validate_builder(
+                &dir.path().join("nope.fq"),
+                dir.path(),
+                None,
+                None,
+                None
+            )),
+            "input"
+        );
     }
 
     #[test]
-    #[should_panic(expected = "Cannot generate")]
-    fn test_unique_random_set_k_greater_than_n() {
-        let k = 10;
-        let n = 5;
+    fn test_mapping_pool() {
+        let pool = Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(2)
+                .build()
+                .unwrap(),
+        );
+        let provided = mapping_pool(Some(&pool), 4).unwrap();
+        assert!(Arc::ptr_eq(&pool, &provided));
+
+        let built = mapping_pool(None, 3).unwrap();
+        assert_eq!(built.current_num_threads(), 3);
+    }
 
-        // This should panic as k > n is impossible for unique values
-        unique_random_set(k, n, None);
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_platform_serde() {
+        let json = serde_json::to_string(&Platform::PacBio).unwrap();
+        assert_eq!(json, r#""pb""#);
+        assert_eq!(
+            serde_json::from_str::<Platform>(r#""nanopore""#).unwrap(),
+            Platform::Nanopore
+        );
+        let json = serde_json::to_string(&Platform::NanoporeDuplex).unwrap();
+        assert_eq!(json, r#""duplex""#);
+        assert!(serde_json::from_str::<Platform>(r#""illumina""#).is_err());
+
+        // the serialized name is the displayed name, and both round-trip
+        for platform in Platform::ALL {
+            let json = serde_json::to_string(&platform).unwrap();
+            assert_eq!(json, format!("\"{platform}\""));
+            assert_eq!(serde_json::from_str::<Platform>(&json).unwrap(), platform);
+            assert_eq!(Platform::from_str(&platform.to_string()).unwrap(), platform);
+        }
     }
 }
@@ -0,0 +1,310 @@
+//! Approximate memory accounting, used to fit a strategy within a user-specified memory budget.
+//!
+//! The numbers used here are deliberately conservative approximations - they are not a guarantee
+//! of peak memory usage, but they are good enough to decide whether a run can fit within a budget
+//! before we commit to building a (potentially large) minimap2 index.
+use std::path::Path;
+
+use log::debug;
+
+use crate::error::LrgeError;
+
+/// Approximate number of bytes a minimap2 index uses per indexed base. The all-vs-all presets
+/// use a minimizer window of 5, which gives roughly one minimizer every three bases. Each
+/// minimizer takes ~16 bytes in the hash table, and the sequence itself is packed into 4 bits per base.
+const INDEX_BYTES_PER_BASE: usize = 6;
+/// Approximate working memory minimap2 needs per mapping thread.
+const BYTES_PER_THREAD: usize = 32 * 1024 * 1024;
+/// Approximate number of bytes of bookkeeping (read names, lengths, and overlap counts) per read.
+const BOOKKEEPING_BYTES_PER_READ: usize = 256;
+/// Approximate number of bytes buffered per overlap between a pair of reads. Each overlap takes
+/// 24 bytes, a mapping thread's buffer may hold twice as many overlaps as pairs before it is
+/// deduplicated, and merging two buffers briefly holds a copy of both.
+const BYTES_PER_OVERLAP: usize = 96;
+
+/// The resources a strategy should use, given its memory budget.
+#[derive(Debug, PartialEq)]
+pub(crate) struct MemoryPlan {
+    /// The capacity of the channel between the reader and the mapping threads.
+    pub channel_capacity: usize,
+    /// The maximum number of bases minimap2 may load into an index. A value of 0 means no limit.
+    pub index_batch_size: usize,
+}
+
+impl MemoryPlan {
+    /// Plan the memory usage of a strategy.
+    ///
+    /// # Arguments
+    ///
+    /// * `budget`: The memory budget, in bytes. If `None`, there is no limit.
+    /// * `index_bases`: The number of bases that will be indexed by minimap2.
+    /// * `num_reads`: The number of reads we will need to keep track of.
+    /// * `avg_read_len`: The average length of the reads sent through the channel to the mapping threads.
+    /// * `threads`: The number of mapping threads.
+    /// * `channel_capacity`: The requested capacity of the channel.
+    /// * `tmp_bytes`: The number of bytes of intermediate files that are held in memory, because the
+    ///   temporary directory is [RAM-backed][is_ram_backed].
+    /// * `num_overlaps`: The number of overlaps between pairs of reads that are buffered until
+    ///   all reads are mapped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LrgeError::MemoryBudgetError`] if the index, bookkeeping, per-thread memory,
+    /// buffered overlaps, and intermediate files in memory, along with at least one record per thread in the channel,
+    /// cannot fit within the budget.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        budget: Option<usize>,
+        index_bases: usize,
+        num_reads: usize,
+        avg_read_len: f32,
+        threads: usize,
+        channel_capacity: usize,
+        tmp_bytes: usize,
+        num_overlaps: usize,
+    ) -> crate::Result<Self> {
+        let Some(budget) = budget else {
+            return Ok(Self {
+                channel_capacity,
+                index_batch_size: 0,
+            });
+        };
+
+        let index_bytes = index_bases.saturating_mul(INDEX_BYTES_PER_BASE);
+        let fixed_bytes = index_bytes
+            .saturating_add(num_reads.saturating_mul(BOOKKEEPING_BYTES_PER_READ))
+            .saturating_add(threads.saturating_mul(BYTES_PER_THREAD))
+            .saturating_add(tmp_bytes)
+            .saturating_add(num_overlaps.saturating_mul(BYTES_PER_OVERLAP));
+
+        // the minimum is one record per thread in the channel
+        let record_bytes = (avg_read_len.ceil() as usize).max(1);
+        let min_channel_bytes = threads.max(1).saturating_mul(record_bytes);
+
+        if fixed_bytes.saturating_add(min_channel_bytes) > budget {
+            return Err(LrgeError::MemoryBudgetError {
+                required: fixed_bytes.saturating_add(min_channel_bytes),
+                budget,
+                index_bases,
+                num_reads,
+                threads,
+            });
+        }
+
+        let max_capacity = (budget - fixed_bytes) / record_bytes;
+
+        Ok(Self {
+            channel_capacity: channel_capacity.min(max_capacity).max(threads.max(1)),
+            // the index must be built in a single part, as every read needs to be compared to all
+            // indexed reads. So the batch size is the largest index that fits in the budget.
+            index_batch_size: (budget - (fixed_bytes - index_bytes)) / INDEX_BYTES_PER_BASE,
+        })
+    }
+
+    /// Check the parts of the budget that do not depend on the sampled reads - the bookkeeping,
+    /// per-thread memory, and buffered overlaps - so that a budget that cannot fit them fails
+    /// before the input is sampled. The index, intermediate files, and channel are counted at their
+    /// smallest, so a budget that passes this may still fail [`MemoryPlan::new`] after sampling.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LrgeError::MemoryBudgetError`] if even the smallest plan cannot fit within the
+    /// budget.
+    pub(crate) fn check(
+        budget: Option<usize>,
+        num_reads: usize,
+        threads: usize,
+        num_overlaps: usize,
+    ) -> crate::Result<()> {
+        Self::new(budget, 0, num_reads, 1.0, threads, 0, 0, num_overlaps).map(|_| ())
+    }
+}
+
+/// Whether files in `path` are held in memory - i.e., it is on a `tmpfs` (e.g., `/dev/shm`) or
+/// `ramfs` filesystem. This is only detected on Linux, and is `false` if it cannot be determined.
+///
+/// # Examples
+///
+/// ```
+/// let in_ram = liblrge::is_ram_backed(std::env::temp_dir());
+/// ```
+pub fn is_ram_backed<P: AsRef<Path>>(path: P) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        const TMPFS_MAGIC: libc::c_long = 0x0102_1994;
+        const RAMFS_MAGIC: libc::c_long = 0x8584_58f6;
+
+        let Ok(path) = CString::new(path.as_ref().as_os_str().as_bytes()) else {
+            return false;
+        };
+        let mut stats = std::mem::MaybeUninit::<libc::statfs>::uninit();
+        // SAFETY: `path` is a valid C string, and `stats` is only read if statfs succeeds
+        if unsafe { libc::statfs(path.as_ptr(), stats.as_mut_ptr()) } != 0 {
+            return false;
+        }
+        let fs_type = unsafe { stats.assume_init() }.f_type as libc::c_long;
+        fs_type == TMPFS_MAGIC || fs_type == RAMFS_MAGIC
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// The total size of the files in `paths` that are held in memory, as they are on a
+/// [RAM-backed][is_ram_backed] filesystem. Files that do not exist are ignored.
+pub(crate) fn bytes_in_ram(paths: &[&Path]) -> usize {
+    let bytes = paths
+        .iter()
+        .filter(|path| is_ram_backed(path))
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|metadata| metadata.len() as usize)
+        .sum();
+    if bytes > 0 {
+        debug!(
+            "{} of intermediate files are held in RAM-backed storage",
+            format_bytes(bytes)
+        );
+    }
+    bytes
+}
+
+/// Format a number of bytes in human-readable binary units.
+pub(crate) fn format_bytes(bytes: usize) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = units[0];
+    for next_unit in &units[1..] {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next_unit;
+    }
+    format!("{value:.2} {unit}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: usize = 1024 * 1024 * 1024;
+
+    #[test]
+    fn test_no_budget() {
+        let plan = MemoryPlan::new(None, 1_000_000, 1000, 1000.0, 4, 10_000, 0, 0).unwrap();
+        assert_eq!(
+            plan,
+            MemoryPlan {
+                channel_capacity: 10_000,
+                index_batch_size: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_budget_fits() {
+        let plan = MemoryPlan::new(
+            Some(4 * GIB),
+            100_000_000,
+            10_000,
+            10_000.0,
+            4,
+            10_000,
+            0,
+            0,
+        )
+        .unwrap();
+        assert_eq!(plan.channel_capacity, 10_000);
+        assert!(plan.index_batch_size >= 100_000_000);
+    }
+
+    #[test]
+    fn test_budget_limits_channel_capacity() {
+        let fixed = 1_000_000 * INDEX_BYTES_PER_BASE + BYTES_PER_THREAD;
+        let budget = fixed + 100 * 10_000;
+        let plan = MemoryPlan::new(Some(budget), 1_000_000, 0, 10_000.0, 1, 10_000, 0, 0).unwrap();
+        assert_eq!(plan.channel_capacity, 100);
+        assert_eq!(
+            plan.index_batch_size,
+            1_000_000 + 100 * 10_000 / INDEX_BYTES_PER_BASE
+        );
+    }
+
+    #[test]
+    fn test_budget_too_small() {
+        let result = MemoryPlan::new(Some(GIB), 1_000_000_000, 10_000, 10_000.0, 4, 10_000, 0, 0);
+        assert!(matches!(result, Err(LrgeError::MemoryBudgetError { .. })));
+    }
+
+    #[test]
+    fn test_budget_includes_tmp_bytes() {
+        let fixed = 1_000_000 * INDEX_BYTES_PER_BASE + BYTES_PER_THREAD;
+        let budget = fixed + 100 * 10_000;
+        assert!(MemoryPlan::new(Some(budget), 1_000_000, 0, 10_000.0, 1, 10_000, 0, 0).is_ok());
+        let result = MemoryPlan::new(Some(budget), 1_000_000, 0, 10_000.0, 1, 10_000, budget, 0);
+        assert!(matches!(result, Err(LrgeError::MemoryBudgetError { .. })));
+    }
+
+    #[test]
+    fn test_check_before_sampling() {
+        assert!(MemoryPlan::check(None, 1_000_000, 64, 1_000_000).is_ok());
+        assert!(MemoryPlan::check(Some(GIB), 10_000, 4, 0).is_ok());
+        // 16 threads need 512MiB
+        let result = MemoryPlan::check(Some(256 * 1024 * 1024), 10_000, 16, 0);
+        assert!(matches!(result, Err(LrgeError::MemoryBudgetError { .. })));
+        // 10M buffered overlaps need ~900MiB
+        let result = MemoryPlan::check(Some(GIB / 2), 100_000, 1, 10_000_000);
+        assert!(matches!(result, Err(LrgeError::MemoryBudgetError { .. })));
+    }
+
+    #[test]
+    fn test_budget_includes_overlaps() {
+        let fixed = 1_000_000 * INDEX_BYTES_PER_BASE + BYTES_PER_THREAD;
+        let budget = fixed + 100 * 10_000;
+        let plan = MemoryPlan::new(Some(budget), 1_000_000, 0, 10_000.0, 1, 10_000, 0, 0).unwrap();
+        let with_overlaps = MemoryPlan::new(
+            Some(budget),
+            1_000_000,
+            0,
+            10_000.0,
+            1,
+            10_000,
+            0,
+            50 * 10_000 / BYTES_PER_OVERLAP,
+        )
+        .unwrap();
+        assert!(with_overlaps.channel_capacity < plan.channel_capacity);
+        let result = MemoryPlan::new(Some(budget), 1_000_000, 0, 10_000.0, 1, 10_000, 0, budget);
+        assert!(matches!(result, Err(LrgeError::MemoryBudgetError { .. })));
+    }
+
+    #[test]
+    fn test_bytes_in_ram() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("reads.fa");
+        std::fs::write(&path, ">r\nACGT\n").unwrap();
+        let missing = dir.path().join("missing.fa");
+        let expected = if is_ram_backed(dir.path()) { 8 } else { 0 };
+        assert_eq!(bytes_in_ram(&[&path, &missing]), expected);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_is_ram_backed() {
+        assert!(!is_ram_backed("/this/path/does/not/exist"));
+        // /proc is neither tmpfs nor ramfs
+        assert!(!is_ram_backed("/proc"));
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512.00 B");
+        assert_eq!(format_bytes(2048), "2.00 KiB");
+        assert_eq!(format_bytes(4 * GIB), "4.00 GiB");
+    }
+}
@@ -1,11 +1,22 @@
 pub(crate) mod aligner;
+pub(crate) mod ffi;
+pub(crate) mod index;
 pub(crate) mod mapping;
+pub(crate) mod options;
 pub(crate) mod preset;
 pub(crate) mod thread_buf;
+pub(crate) mod writer;
 
-use minimap2_sys::*;
+use self::ffi::{mm_idxopt_t, mm_mapopt_t};
 
 pub(crate) use self::aligner::AlignerWrapper;
-pub(crate) type MapOpt = mm_mapopt_t;
-pub(crate) type IdxOpt = mm_idxopt_t;
+/// minimap2's mapping options (`mm_mapopt_t`), as changed by `with_mapopt` on the strategy builders
+/// (e.g., [`twoset::Builder::with_mapopt`][crate::twoset::Builder::with_mapopt]).
+pub type MapOpt = mm_mapopt_t;
+/// minimap2's index options (`mm_idxopt_t`), as changed by `with_idxopt` on the strategy builders
+/// (e.g., [`twoset::Builder::with_idxopt`][crate::twoset::Builder::with_idxopt]).
+pub type IdxOpt = mm_idxopt_t;
+pub use self::mapping::{PafReader, PafRecord};
+pub(crate) use self::options::OptionOverrides;
 pub(crate) use self::preset::Preset;
+pub(crate) use self::writer::{MappingCallback, MappingSink, OverlapWriter};
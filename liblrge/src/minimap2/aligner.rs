@@ -1,21 +1,24 @@
 //! Data structures and methods for working with the C bindings of minimap2.
 //!
 //! The code in this module has been adapted from the [`minimap2` crate](https://crates.io/crates/minimap2).
-use std::ffi::{c_void, CString};
+use std::ffi::{c_void, CStr};
 use std::mem::MaybeUninit;
 use std::os::unix::ffi::OsStrExt;
 use std::path::Path;
 use std::sync::Arc;
 
-use minimap2_sys::*;
-
+use super::ffi::*;
+use super::index::Index;
 use super::mapping::PafRecord;
 use super::thread_buf::BUF;
-use super::{IdxOpt, MapOpt, Preset};
+use super::{IdxOpt, MapOpt, OptionOverrides, Preset};
 use crate::error::LrgeError;
 
-/// An aligner for mapping sequences to an index created by minimap2
-#[derive(Clone)]
+/// An aligner for mapping sequences to an index created by minimap2.
+///
+/// The index is held in an [`Arc`], so it can be shared (see [`Aligner::index`]), and it is
+/// destroyed once the aligner, and anything sharing the index, is dropped. The aligner cannot be
+/// cloned - share it with an [`Arc`] instead (see [`AlignerWrapper`]).
 pub(crate) struct Aligner {
     /// Index options passed to minimap2 (mm_idxopt_t)
     pub idxopt: IdxOpt,
@@ -27,7 +30,7 @@ pub(crate) struct Aligner {
     pub threads: usize,
 
     /// Index created by minimap2
-    pub idx: Option<*mut mm_idx_t>,
+    idx: Option<Arc<Index>>,
 }
 
 /// Create a default aligner
@@ -42,6 +45,10 @@ impl Default for Aligner {
     }
 }
 
+// SAFETY: the mapping options contain pointers (e.g., `split_prefix`) that we never set - a caller
+// could with `with_mapopt`, but must then keep what they point to alive and unchanged - and the
+// options are not modified once the index is built. The index is `Send` and `Sync` itself, so the
+// options are the only reason the aligner is not automatically `Send` and `Sync`.
 mod send {
     use super::*;
     unsafe impl Sync for Aligner {}
@@ -88,8 +95,9 @@ impl Aligner {
 
     /// Set `--dual=yes` (`true`) or `--dual=no` (`false`). From the docs:
     /// --dual=yes|no - If no, skip query-target pairs wherein the query name is lexicographically greater than the target name.
-    /// When using the TwoSet strategy, we set this to `true`, otherwise we ignore ~half of the
-    /// potential overlaps. For the AvaStrategy, we don't need to set this as the preset takes care of it.
+    /// By default, the TwoSet strategy sets this to `true`, otherwise we ignore ~half of the
+    /// potential overlaps, and the AvaStrategy sets this to `false`, as in the ava presets. Both can be
+    /// changed with their builder's `dual` method.
     pub fn dual(mut self, yes: bool) -> Self {
         if yes {
             // Set the `--dual=yes` flag. to do this, we need to clear the bit corresponding to
@@ -121,6 +129,13 @@ impl Aligner {
         self
     }
 
+    /// Apply the caller's changes to the options. These come after all other options, so they
+    /// take precedence.
+    pub fn with_overrides(mut self, overrides: &OptionOverrides) -> Self {
+        overrides.apply(&mut self.idxopt, &mut self.mapopt);
+        self
+    }
+
     /// Set index parameters for minimap2 using builder pattern
     /// Creates the index as well with the given number of threads (set at struct creation).
     /// You must set the number of threads before calling this function.
@@ -156,7 +171,10 @@ impl Aligner {
             return Err("File does not exist");
         }
 
-        if path.as_ref().metadata().unwrap().len() == 0 {
+        let Ok(metadata) = path.as_ref().metadata() else {
+            return Err("Unable to read file metadata");
+        };
+        if metadata.len() == 0 {
             return Err("File is empty");
         }
 
@@ -168,34 +186,49 @@ impl Aligner {
             None => std::ffi::CString::new(Vec::new()).unwrap(),
         };
 
-        let idx_reader = MaybeUninit::new(unsafe {
-            mm_idx_reader_open(path_str.as_ptr(), &self.idxopt, output.as_ptr())
-        });
-
-        let idx: MaybeUninit<*mut mm_idx_t>;
+        let idx_reader =
+            unsafe { mm_idx_reader_open(path_str.as_ptr(), &self.idxopt, output.as_ptr()) };
+        if idx_reader.is_null() {
+            return Err("Unable to open file for indexing");
+        }
 
-        let idx_reader = unsafe { idx_reader.assume_init() };
+        // Following: https://github.com/lh3/minimap2/blob/master/python/mappy.pyx#L147
+        let (idx, split) = unsafe {
+            let idx = mm_idx_reader_read(idx_reader, self.threads as i32);
+            // only the first part of a multi-part index is read, so the reads after it would
+            // silently be left out of the index
+            let split = mm_idx_reader_eof(idx_reader) == 0;
+            mm_idx_reader_close(idx_reader);
+            // we own the index from here on, so it is destroyed if anything below fails
+            (Index::from_raw(idx), split)
+        };
+        let Some(mut idx) = idx else {
+            return Err("Unable to build index");
+        };
+        if split {
+            return Err(
+                "The index would be split into parts, as the index batch size is smaller than the number of bases to index",
+            );
+        }
 
         unsafe {
-            // Just a test read? Just following: https://github.com/lh3/minimap2/blob/master/python/mappy.pyx#L147
-            idx = MaybeUninit::new(mm_idx_reader_read(
-                // self.idx_reader.as_mut().unwrap() as *mut mm_idx_reader_t,
-                &mut *idx_reader as *mut mm_idx_reader_t,
-                self.threads as i32,
-            ));
-            // Close the reader
-            mm_idx_reader_close(idx_reader);
             // Set index opts
-            mm_mapopt_update(&mut self.mapopt, *idx.as_ptr());
+            mm_mapopt_update(&mut self.mapopt, idx.as_ptr());
             // Idx index name
-            mm_idx_index_name(idx.assume_init());
+            mm_idx_index_name(idx.as_mut_ptr());
         }
 
-        self.idx = Some(unsafe { idx.assume_init() });
+        // any previous index is destroyed when it is replaced, unless it is still shared elsewhere
+        self.idx = Some(Arc::new(idx));
 
         Ok(())
     }
 
+    /// The index reads are mapped to, if it has been built.
+    pub fn index(&self) -> Option<&Arc<Index>> {
+        self.idx.as_ref()
+    }
+
     /// Aligns a given sequence (as bytes) to the index associated with this aligner
     ///
     /// Parameters:
@@ -204,12 +237,12 @@ impl Aligner {
     pub fn map(
         &self,
         seq: &[u8],
-        query_name: Option<&CString>,
+        query_name: Option<&CStr>,
     ) -> Result<Vec<PafRecord>, &'static str> {
         // Make sure index is set
-        if self.idx.is_none() {
+        let Some(idx) = self.idx.as_deref() else {
             return Err("No index");
-        }
+        };
 
         if seq.is_empty() {
             return Err("Sequence is empty");
@@ -224,13 +257,13 @@ impl Aligner {
             None => std::ptr::null(),
             Some(qname) => qname.as_ptr(),
         };
-        let query_name = query_name.map(|q| q.as_bytes()).unwrap_or(b"*");
+        let query_name = query_name.map(|q| q.to_bytes()).unwrap_or(b"*");
         let query_len = seq.len() as i32;
 
         let mappings = BUF.with(|buf| {
             mm_reg = MaybeUninit::new(unsafe {
                 mm_map(
-                    self.idx.unwrap() as *const mm_idx_t,
+                    idx.as_ptr(),
                     seq.len() as i32,
                     seq.as_ptr() as *const ::std::os::raw::c_char,
                     &mut n_regs,
@@ -246,9 +279,7 @@ impl Aligner {
                     let reg_ptr = (*mm_reg.as_ptr()).offset(i as isize);
                     let reg: mm_reg1_t = *reg_ptr;
 
-                    let contig: *mut ::std::os::raw::c_char =
-                        (*((*(self.idx.unwrap())).seq.offset(reg.rid as isize))).name;
-                    let target_name = std::ffi::CStr::from_ptr(contig).to_bytes().to_vec();
+                    let target_name = idx.seq_name(reg.rid as usize).to_vec();
 
                     let strand = if reg.rev() == 0 { '+' } else { '-' };
 
@@ -270,8 +301,7 @@ impl Aligner {
 
                     mappings.push(PafRecord {
                         target_name,
-                        target_len: (*((*(self.idx.unwrap())).seq.offset(reg.rid as isize))).len
-                            as i32,
+                        target_len: idx.seq_len(reg.rid as usize) as i32,
                         target_start: reg.rs,
                         target_end: reg.re,
                         query_name: query_name.to_vec(),
@@ -287,6 +317,7 @@ impl Aligner {
                         s1,
                         dv,
                         rl,
+                        target_id: reg.rid as u32,
                     });
                     libc::free(reg.p as *mut c_void);
                 }
@@ -303,27 +334,248 @@ impl Aligner {
     }
 }
 
+/// Shares an [`Aligner`], and so its index, across threads.
+///
+/// The wrapper is the owner of the aligner - threads take a clone of the [`Arc`] for as long as they
+/// map reads, and the index is destroyed when the wrapper and all of those clones are dropped.
 pub(crate) struct AlignerWrapper {
     pub(crate) aligner: Arc<Aligner>, // Shared aligner across threads
 }
 
 impl AlignerWrapper {
+    /// Build an aligner with an index of the reads in `target_file`.
+    ///
+    /// Returns an [`IndexError`][LrgeError::IndexError] if the index cannot be built.
     pub fn new(
         target_file: &Path,
-        threads: usize,
+        index_threads: usize,
         preset: Preset,
         dual: bool,
+        index_size: usize,
+        overrides: &OptionOverrides,
     ) -> Result<Self, LrgeError> {
         let aligner = Aligner::builder()
             .preset(preset.as_bytes())
             .dual(dual)
-            .with_index_threads(threads)
-            .with_index_size(0)
+            .with_index_threads(index_threads)
+            .with_index_size(index_size)
+            .with_overrides(overrides)
             .with_index(target_file, None)
-            .unwrap();
+            .map_err(|reason| LrgeError::IndexError {
+                path: target_file.to_path_buf(),
+                reason: reason.to_string(),
+            })?;
 
         Ok(Self {
             aligner: Arc::new(aligner),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_seq(len: usize, seed: u64) -> Vec<u8> {
+        // a simple xorshift, so the reads are not repetitive
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                b"ACGT"[(state % 4) as usize]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_aligner_wrapper_index_and_map() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("target.fa");
+        let seq = random_seq(5_000, 42);
+        let mut fasta = b">t1\n".to_vec();
+        fasta.extend_from_slice(&seq);
+        fasta.push(b'\n');
+        std::fs::write(&path, fasta).unwrap();
+
+        let wrapper = AlignerWrapper::new(
+            &path,
+            1,
+            Preset::AvaOnt,
+            true,
+            0,
+            &OptionOverrides::default(),
+        )
+        .unwrap();
+        let qname = c"q1";
+        let mappings = wrapper
+            .aligner
+            .map(&seq[1_000..4_000], Some(qname))
+            .unwrap();
+        assert!(!mappings.is_empty());
+        assert_eq!(mappings[0].target_name, b"t1");
+        assert_eq!(mappings[0].target_len, 5_000);
+
+        // the index is destroyed once the last reference to the aligner is dropped
+        let shared = Arc::clone(&wrapper.aligner);
+        drop(wrapper);
+        assert!(shared.map(&seq[..2_000], Some(qname)).is_ok());
+    }
+
+    #[test]
+    fn test_aligner_wrapper_applies_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("target.fa");
+        let seq = random_seq(5_000, 7);
+        std::fs::write(
+            &path,
+            format!(">t1\n{}\n", String::from_utf8(seq.clone()).unwrap()),
+        )
+        .unwrap();
+
+        // no chain of a 3 kbp read can score more than its length
+        let mut overrides = OptionOverrides::default();
+        overrides.add_mapopt(|opt| opt.min_chain_score = 10_000);
+        overrides.add_idxopt(|opt| opt.k = 17);
+        let wrapper = AlignerWrapper::new(&path, 1, Preset::AvaOnt, true, 0, &overrides).unwrap();
+        assert_eq!(wrapper.aligner.idxopt.k, 17);
+        assert_eq!(wrapper.aligner.mapopt.min_chain_score, 10_000);
+        let mappings = wrapper
+            .aligner
+            .map(&seq[1_000..4_000], Some(c"q1"))
+            .unwrap();
+        assert!(mappings.is_empty());
+    }
+
+    #[test]
+    fn test_aligner_wrapper_rejects_split_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("target.fa");
+        let fasta = format!(
+            ">t1\n{}\n>t2\n{}\n",
+            String::from_utf8(random_seq(3_000, 1)).unwrap(),
+            String::from_utf8(random_seq(2_000, 2)).unwrap()
+        );
+        std::fs::write(&path, fasta).unwrap();
+
+        // a batch size smaller than the reads puts them in separate parts
+        let mut overrides = OptionOverrides::default();
+        overrides.add_idxopt(|opt| opt.batch_size = 1_000);
+        let result = AlignerWrapper::new(&path, 1, Preset::AvaOnt, true, 0, &overrides);
+        let Err(LrgeError::IndexError { reason, .. }) = result else {
+            panic!("a split index should be rejected");
+        };
+        assert!(reason.contains("split into parts"), "{reason}");
+
+        // the batch size lrge sets fits all of the reads
+        let overrides = OptionOverrides::default();
+        let wrapper = AlignerWrapper::new(&path, 1, Preset::AvaOnt, true, 0, &overrides).unwrap();
+        assert_eq!(wrapper.aligner.index().unwrap().n_seq(), 2);
+    }
+
+    #[test]
+    fn test_aligner_wrapper_empty_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("empty.fa");
+        std::fs::write(&path, "").unwrap();
+
+        let result = AlignerWrapper::new(
+            &path,
+            1,
+            Preset::AvaOnt,
+            true,
+            0,
+            &OptionOverrides::default(),
+        );
+        assert!(matches!(result, Err(LrgeError::IndexError { .. })));
+    }
+
+    #[test]
+    fn test_index_outlives_aligner() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("target.fa");
+        let fasta = format!(
+            ">t1\n{}\n>t2\n{}\n",
+            String::from_utf8(random_seq(3_000, 1)).unwrap(),
+            String::from_utf8(random_seq(2_000, 2)).unwrap()
+        );
+        std::fs::write(&path, fasta).unwrap();
+
+        let wrapper = AlignerWrapper::new(
+            &path,
+            1,
+            Preset::AvaOnt,
+            true,
+            0,
+            &OptionOverrides::default(),
+        )
+        .unwrap();
+        let index = Arc::clone(wrapper.aligner.index().unwrap());
+        drop(wrapper);
+
+        assert_eq!(index.n_seq(), 2);
+        assert_eq!(index.seq_name(0), b"t1");
+        assert_eq!(index.seq_len(0), 3_000);
+        assert_eq!(index.seq_name(1), b"t2");
+        assert_eq!(index.seq_cname(1), c"t2");
+        assert_eq!(index.seq_len(1), 2_000);
+    }
+
+    #[test]
+    fn test_mapping_target_id_is_index_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("target.fa");
+        let seq = random_seq(4_000, 5);
+        let fasta = format!(
+            ">t1\n{}\n>t2\n{}\n",
+            String::from_utf8(random_seq(3_000, 4)).unwrap(),
+            String::from_utf8(seq.clone()).unwrap()
+        );
+        std::fs::write(&path, fasta).unwrap();
+
+        let wrapper = AlignerWrapper::new(
+            &path,
+            1,
+            Preset::AvaOnt,
+            true,
+            0,
+            &OptionOverrides::default(),
+        )
+        .unwrap();
+        let qname = c"q1";
+        let mappings = wrapper.aligner.map(&seq[500..3_500], Some(qname)).unwrap();
+        assert!(!mappings.is_empty());
+        let index = wrapper.aligner.index().unwrap();
+        for mapping in &mappings {
+            assert_eq!(mapping.target_id, 1);
+            assert_eq!(
+                index.seq_name(mapping.target_id as usize),
+                mapping.target_name
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_index_seq_out_of_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("target.fa");
+        let fasta = format!(
+            ">t1\n{}\n",
+            String::from_utf8(random_seq(1_000, 3)).unwrap()
+        );
+        std::fs::write(&path, fasta).unwrap();
+
+        let wrapper = AlignerWrapper::new(
+            &path,
+            1,
+            Preset::AvaOnt,
+            true,
+            0,
+            &OptionOverrides::default(),
+        )
+        .unwrap();
+        wrapper.aligner.index().unwrap().seq_len(1);
+    }
+}
@@ -0,0 +1,348 @@
+//! The parts of minimap2's C API (`minimap.h`) that are used to overlap reads.
+//!
+//! By default (the `vendored-minimap2` feature), these are the bindings of the [`minimap2-sys`
+//! crate](https://crates.io/crates/minimap2-sys), which compiles its own copy of minimap2. With the
+//! `system-minimap2` feature, they are declared here instead, and linked against a minimap2
+//! library installed on the system by the build script. The declarations follow the `minimap.h`
+//! that `minimap2-sys` generates its bindings from, and are tested against those bindings; the
+//! build script refuses to link a library of any other minimap2 release. If both
+//! features are enabled, the `minimap2-sys` bindings are used.
+#[cfg(not(any(feature = "vendored-minimap2", feature = "system-minimap2")))]
+compile_error!("one of the `vendored-minimap2` or `system-minimap2` features must be enabled");
+
+#[cfg(feature = "vendored-minimap2")]
+pub use minimap2_sys::{
+    mm_idx_destroy, mm_idx_index_name, mm_idx_reader_close, mm_idx_reader_eof, mm_idx_reader_open,
+    mm_idx_reader_read, mm_idx_seq_t, mm_idx_t, mm_idxopt_t, mm_map, mm_mapopt_t, mm_mapopt_update,
+    mm_reg1_t, mm_set_opt, mm_tbuf_destroy, mm_tbuf_init, mm_tbuf_t, MM_VERSION,
+};
+
+#[cfg(all(feature = "system-minimap2", not(feature = "vendored-minimap2")))]
+pub use self::system::*;
+
+#[cfg(feature = "system-minimap2")]
+#[allow(non_camel_case_types, non_snake_case)]
+#[cfg_attr(feature = "vendored-minimap2", allow(dead_code))]
+mod system {
+    use std::ffi::{c_char, c_int, c_short, c_void, CStr};
+
+    /// The version of minimap2 these declarations follow. The build script checks that the
+    /// `minimap.h` of the library it links is this version.
+    pub const MM_VERSION: &CStr =
+        match CStr::from_bytes_with_nul(concat!(env!("LRGE_MINIMAP2_VERSION"), "\0").as_bytes()) {
+            Ok(version) => version,
+            Err(_) => panic!("the minimap2 version is a valid C string"),
+        };
+
+    /// Declares a struct that is only used behind a pointer.
+    macro_rules! opaque {
+        ($($name:ident),*) => {
+            $(
+                #[repr(C)]
+                pub struct $name {
+                    _private: [u8; 0],
+                }
+            )*
+        };
+    }
+
+    opaque!(
+        mm_idx_bucket_s,
+        mm_idx_intv_s,
+        mm_idx_spsc_s,
+        mm_idx_jjump_s,
+        mm_extra_t,
+        mm_idx_reader_t
+    );
+
+    #[repr(C)]
+    #[derive(Debug, Default, Copy, Clone)]
+    pub struct mm_idxopt_t {
+        pub k: c_short,
+        pub w: c_short,
+        pub flag: c_short,
+        pub bucket_bits: c_short,
+        pub mini_batch_size: i64,
+        pub batch_size: u64,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct mm_mapopt_t {
+        pub flag: i64,
+        pub seed: c_int,
+        pub sdust_thres: c_int,
+        pub max_qlen: c_int,
+        pub bw: c_int,
+        pub bw_long: c_int,
+        pub max_gap: c_int,
+        pub max_gap_ref: c_int,
+        pub max_frag_len: c_int,
+        pub max_chain_skip: c_int,
+        pub max_chain_iter: c_int,
+        pub min_cnt: c_int,
+        pub min_chain_score: c_int,
+        pub chain_gap_scale: f32,
+        pub chain_skip_scale: f32,
+        pub rmq_size_cap: c_int,
+        pub rmq_inner_dist: c_int,
+        pub rmq_rescue_size: c_int,
+        pub rmq_rescue_ratio: f32,
+        pub mask_level: f32,
+        pub mask_len: c_int,
+        pub pri_ratio: f32,
+        pub best_n: c_int,
+        pub alt_drop: f32,
+        pub a: c_int,
+        pub b: c_int,
+        pub q: c_int,
+        pub e: c_int,
+        pub q2: c_int,
+        pub e2: c_int,
+        pub transition: c_int,
+        pub sc_ambi: c_int,
+        pub noncan: c_int,
+        pub junc_bonus: c_int,
+        pub junc_pen: c_int,
+        pub zdrop: c_int,
+        pub zdrop_inv: c_int,
+        pub end_bonus: c_int,
+        pub min_dp_max: c_int,
+        pub min_ksw_len: c_int,
+        pub anchor_ext_len: c_int,
+        pub anchor_ext_shift: c_int,
+        pub max_clip_ratio: f32,
+        pub rank_min_len: c_int,
+        pub rank_frac: f32,
+        pub pe_ori: c_int,
+        pub pe_bonus: c_int,
+        pub jump_min_match: i32,
+        pub mid_occ_frac: f32,
+        pub q_occ_frac: f32,
+        pub min_mid_occ: i32,
+        pub max_mid_occ: i32,
+        pub mid_occ: i32,
+        pub max_occ: i32,
+        pub max_max_occ: i32,
+        pub occ_dist: i32,
+        pub mini_batch_size: i64,
+        pub max_sw_mat: i64,
+        pub cap_kalloc: i64,
+        pub split_prefix: *const c_char,
+    }
+
+    impl Default for mm_mapopt_t {
+        fn default() -> Self {
+            // all zeros is a valid value of every field, including the null `split_prefix`
+            unsafe { std::mem::zeroed() }
+        }
+    }
+
+    // the options only point to a string that is never changed
+    unsafe impl Send for mm_mapopt_t {}
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct mm_idx_seq_t {
+        pub name: *mut c_char,
+        pub offset: u64,
+        pub len: u32,
+        pub is_alt: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct mm_idx_t {
+        pub b: i32,
+        pub w: i32,
+        pub k: i32,
+        pub flag: i32,
+        pub n_seq: u32,
+        pub index: i32,
+        pub n_alt: i32,
+        pub seq: *mut mm_idx_seq_t,
+        pub S: *mut u32,
+        pub B: *mut mm_idx_bucket_s,
+        pub I: *mut mm_idx_intv_s,
+        pub spsc: *mut mm_idx_spsc_s,
+        pub J: *mut mm_idx_jjump_s,
+        pub km: *mut c_void,
+        pub h: *mut c_void,
+    }
+
+    unsafe impl Send for mm_idx_t {}
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct mm_reg1_t {
+        pub id: i32,
+        pub cnt: i32,
+        pub rid: i32,
+        pub score: i32,
+        pub qs: i32,
+        pub qe: i32,
+        pub rs: i32,
+        pub re: i32,
+        pub parent: i32,
+        pub subsc: i32,
+        pub as_: i32,
+        pub mlen: i32,
+        pub blen: i32,
+        pub n_sub: i32,
+        pub score0: i32,
+        /// The bit fields `mapq:8, split:2, rev:1, inv:1, sam_pri:1, proper_frag:1, pe_thru:1,
+        /// seg_split:1, seg_id:8, split_inv:1, is_alt:1, strand_retained:1, is_spliced:1, dummy:4`
+        pub _bitfield_1: [u8; 4],
+        pub hash: u32,
+        pub div: f32,
+        pub p: *mut mm_extra_t,
+    }
+
+    impl mm_reg1_t {
+        /// The `width` bits of the bit fields from bit `offset`, as laid out by C compilers.
+        fn bits(&self, offset: usize, width: usize) -> u32 {
+            let mut value = 0;
+            for i in 0..width {
+                let bit = offset + i;
+                let index = if cfg!(target_endian = "big") {
+                    7 - bit % 8
+                } else {
+                    bit % 8
+                };
+                if self._bitfield_1[bit / 8] & (1 << index) != 0 {
+                    value |= 1
+                        << if cfg!(target_endian = "big") {
+                            width - 1 - i
+                        } else {
+                            i
+                        };
+                }
+            }
+            value
+        }
+
+        pub fn mapq(&self) -> u32 {
+            self.bits(0, 8)
+        }
+
+        pub fn rev(&self) -> u32 {
+            self.bits(10, 1)
+        }
+
+        pub fn inv(&self) -> u32 {
+            self.bits(11, 1)
+        }
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct mm_tbuf_s {
+        pub km: *mut c_void,
+        pub rep_len: c_int,
+        pub frag_gap: c_int,
+    }
+
+    pub type mm_tbuf_t = mm_tbuf_s;
+
+    extern "C" {
+        pub fn mm_set_opt(
+            preset: *const c_char,
+            io: *mut mm_idxopt_t,
+            mo: *mut mm_mapopt_t,
+        ) -> c_int;
+        pub fn mm_mapopt_update(opt: *mut mm_mapopt_t, mi: *const mm_idx_t);
+        pub fn mm_idx_reader_open(
+            fn_: *const c_char,
+            opt: *const mm_idxopt_t,
+            fn_out: *const c_char,
+        ) -> *mut mm_idx_reader_t;
+        pub fn mm_idx_reader_read(r: *mut mm_idx_reader_t, n_threads: c_int) -> *mut mm_idx_t;
+        pub fn mm_idx_reader_close(r: *mut mm_idx_reader_t);
+        pub fn mm_idx_reader_eof(r: *const mm_idx_reader_t) -> c_int;
+        pub fn mm_idx_destroy(mi: *mut mm_idx_t);
+        pub fn mm_idx_index_name(mi: *mut mm_idx_t) -> c_int;
+        pub fn mm_tbuf_init() -> *mut mm_tbuf_t;
+        pub fn mm_tbuf_destroy(b: *mut mm_tbuf_t);
+        pub fn mm_map(
+            mi: *const mm_idx_t,
+            l_seq: c_int,
+            seq: *const c_char,
+            n_regs: *mut c_int,
+            b: *mut mm_tbuf_t,
+            opt: *const mm_mapopt_t,
+            name: *const c_char,
+        ) -> *mut mm_reg1_t;
+    }
+
+    #[cfg(all(test, feature = "vendored-minimap2"))]
+    mod tests {
+        use std::mem::{offset_of, size_of};
+
+        // the declarations must match the bindings generated by minimap2-sys
+        #[test]
+        fn test_layouts_match_minimap2_sys() {
+            assert_eq!(
+                size_of::<super::mm_idxopt_t>(),
+                size_of::<minimap2_sys::mm_idxopt_t>()
+            );
+            assert_eq!(
+                offset_of!(super::mm_idxopt_t, batch_size),
+                offset_of!(minimap2_sys::mm_idxopt_t, batch_size)
+            );
+            assert_eq!(
+                size_of::<super::mm_mapopt_t>(),
+                size_of::<minimap2_sys::mm_mapopt_t>()
+            );
+            assert_eq!(
+                offset_of!(super::mm_mapopt_t, split_prefix),
+                offset_of!(minimap2_sys::mm_mapopt_t, split_prefix)
+            );
+            assert_eq!(
+                size_of::<super::mm_idx_t>(),
+                size_of::<minimap2_sys::mm_idx_t>()
+            );
+            assert_eq!(
+                offset_of!(super::mm_idx_t, seq),
+                offset_of!(minimap2_sys::mm_idx_t, seq)
+            );
+            assert_eq!(
+                size_of::<super::mm_idx_seq_t>(),
+                size_of::<minimap2_sys::mm_idx_seq_t>()
+            );
+            assert_eq!(
+                size_of::<super::mm_reg1_t>(),
+                size_of::<minimap2_sys::mm_reg1_t>()
+            );
+            assert_eq!(
+                offset_of!(super::mm_reg1_t, div),
+                offset_of!(minimap2_sys::mm_reg1_t, div)
+            );
+            assert_eq!(
+                size_of::<super::mm_tbuf_t>(),
+                size_of::<minimap2_sys::mm_tbuf_t>()
+            );
+        }
+
+        #[test]
+        fn test_version_matches_minimap2_sys() {
+            assert_eq!(super::MM_VERSION, minimap2_sys::MM_VERSION);
+        }
+
+        #[test]
+        fn test_reg_bit_fields() {
+            let mut vendored: minimap2_sys::mm_reg1_t = unsafe { std::mem::zeroed() };
+            vendored.set_mapq(60);
+            vendored.set_rev(1);
+            vendored.set_seg_id(3);
+            let reg: super::mm_reg1_t = unsafe { std::mem::transmute(vendored) };
+            assert_eq!(reg.mapq(), 60);
+            assert_eq!(reg.rev(), 1);
+            assert_eq!(reg.inv(), 0);
+
+            vendored.set_rev(0);
+            vendored.set_inv(1);
+            let reg: super::mm_reg1_t = unsafe { std::mem::transmute(vendored) };
+            assert_eq!((reg.mapq(), reg.rev(), reg.inv()), (60, 0, 1));
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Ownership of the index created by minimap2.
+//!
+//! All reads of the index's memory go through the methods here, so that the unsafe code for doing
+//! so is in one place.
+use std::ffi::CStr;
+use std::ptr::NonNull;
+
+use super::ffi::{mm_idx_destroy, mm_idx_seq_t, mm_idx_t};
+
+/// A minimap2 index. The index is destroyed when this is dropped.
+///
+/// The index is immutable once built, so it can be shared between threads with an
+/// [`Arc`][std::sync::Arc].
+#[derive(Debug)]
+pub(crate) struct Index {
+    ptr: NonNull<mm_idx_t>,
+}
+
+// SAFETY: the index is only read (by `mm_map`) after it is built, which minimap2 itself does from
+// many threads at once, and it is only destroyed once, when it is dropped.
+unsafe impl Send for Index {}
+unsafe impl Sync for Index {}
+
+impl Index {
+    /// Take ownership of an index created by minimap2. Returns `None` if `ptr` is null.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to an index created by minimap2 that nothing else will destroy.
+    pub(crate) unsafe fn from_raw(ptr: *mut mm_idx_t) -> Option<Self> {
+        NonNull::new(ptr).map(|ptr| Self { ptr })
+    }
+
+    /// A pointer to the index, for passing to minimap2. The index must not be modified through it.
+    pub(crate) fn as_ptr(&self) -> *const mm_idx_t {
+        self.ptr.as_ptr()
+    }
+
+    /// A mutable pointer to the index, for passing to minimap2 functions that modify it.
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut mm_idx_t {
+        self.ptr.as_ptr()
+    }
+
+    /// The number of sequences in the index.
+    pub(crate) fn n_seq(&self) -> usize {
+        // SAFETY: the pointer is valid for as long as self is
+        unsafe { self.ptr.as_ref() }.n_seq as usize
+    }
+
+    /// The name of the `i`th sequence in the index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is not less than [`Index::n_seq`].
+    pub(crate) fn seq_name(&self, i: usize) -> &[u8] {
+        self.seq_cname(i).to_bytes()
+    }
+
+    /// The name of the `i`th sequence in the index, as a C string - e.g., to map it as a query
+    /// without copying the name.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is not less than [`Index::n_seq`].
+    pub(crate) fn seq_cname(&self, i: usize) -> &CStr {
+        let seq = self.seq(i);
+        // SAFETY: minimap2 null-terminates the names, which live as long as the index
+        unsafe { CStr::from_ptr(seq.name) }
+    }
+
+    /// The length of the `i`th sequence in the index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is not less than [`Index::n_seq`].
+    pub(crate) fn seq_len(&self, i: usize) -> usize {
+        self.seq(i).len as usize
+    }
+
+    fn seq(&self, i: usize) -> &mm_idx_seq_t {
+        let n_seq = self.n_seq();
+        assert!(
+            i < n_seq,
+            "sequence {i} is out of range for an index of {n_seq} sequences"
+        );
+        // SAFETY: `seq` is an array of `n_seq` sequences, which lives as long as the index
+        unsafe { &*self.ptr.as_ref().seq.add(i) }
+    }
+}
+
+/// Handle destruction of the index properly.
+impl Drop for Index {
+    fn drop(&mut self) {
+        unsafe { mm_idx_destroy(self.ptr.as_ptr()) };
+    }
+}
@@ -1,18 +1,26 @@
-//! Data structure for PAF records along with serialization and deserialization methods.
+//! Data structure for PAF records along with methods for writing and parsing them.
 use std::cmp;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serializer};
 
-/// Mapping result - i.e., PafRecord
-/// See https://lh3.github.io/minimap2/minimap2.html for full details of the PAF format provided by minimap2
-#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
-pub(crate) struct PafRecord {
-    #[serde(
-        serialize_with = "serialize_bytes",
-        deserialize_with = "deserialize_bytes"
-    )]
+use crate::error::LrgeError;
+use crate::OverlapFormat;
+
+/// An overlap between two reads, as found by minimap2 - i.e., a record of its PAF output.
+///
+/// See <https://lh3.github.io/minimap2/minimap2.html> for full details of the PAF format provided
+/// by minimap2. Overlaps can be passed to a callback as they are found with `on_mapping` on the
+/// strategy builders (e.g., [`twoset::Builder::on_mapping`][crate::twoset::Builder::on_mapping]).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PafRecord {
+    /// Query read name
     pub query_name: Vec<u8>,
+    /// Query read length
     pub query_len: i32,
     /// Query start coordinate (0-based)
     pub query_start: i32,
@@ -20,11 +28,9 @@ pub(crate) struct PafRecord {
     pub query_end: i32,
     /// ‘+’ if query/target on the same strand; ‘-’ if opposite
     pub strand: char,
-    #[serde(
-        serialize_with = "serialize_bytes",
-        deserialize_with = "deserialize_bytes"
-    )]
+    /// Target read name
     pub target_name: Vec<u8>,
+    /// Target read length
     pub target_len: i32,
     /// Target start coordinate on the original strand
     pub target_start: i32,
@@ -37,48 +43,376 @@ pub(crate) struct PafRecord {
     /// Mapping quality (0-255 with 255 for missing)
     pub mapq: u32,
     /// Type of aln: P/primary, S/secondary and I,i/inversion
-    #[serde(serialize_with = "serialize_tp", deserialize_with = "deserialize_tag")]
     pub tp: char,
     /// Number of minimizers on the chain
-    #[serde(serialize_with = "serialize_cm", deserialize_with = "deserialize_tag")]
     pub cm: i32,
     /// Number of residues in the matching chain (chaining score)
-    #[serde(serialize_with = "serialize_s1", deserialize_with = "deserialize_tag")]
     pub s1: i32,
     /// Approximate per-base sequence divergence
-    #[serde(serialize_with = "serialize_dv", deserialize_with = "deserialize_tag")]
     pub dv: f32,
     /// Length of query regions harboring repetitive seeds
-    #[serde(serialize_with = "serialize_rl", deserialize_with = "deserialize_tag")]
     pub rl: i32,
+    /// The index of the target in the minimap2 index (its `rid`). This is not part of the PAF
+    /// format, so it is not written or parsed.
+    pub(crate) target_id: u32,
+}
+
+/// The minimum fraction of both reads an overlap must cover for them to be duplicates. See
+/// [`PafRecord::is_duplicate`].
+pub(crate) const DUPLICATE_MIN_COVERAGE: f32 = 0.95;
+/// The maximum divergence of an overlap between duplicate reads. See [`PafRecord::is_duplicate`].
+pub(crate) const DUPLICATE_MAX_DIVERGENCE: f32 = 0.05;
+
+/// Which of the two reads in an overlap is contained in the other, from
+/// [`PafRecord::contained_read`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Contained {
+    /// The query read is contained in the target read
+    Query,
+    /// The target read is contained in the query read
+    Target,
 }
 
 impl PafRecord {
-    /// Checks if the target or query read are internal to the other, within a specified overhang ratio.
-    /// This is used to filter out internal reads that are not useful for estimation.
-    pub(crate) fn is_internal(&self, max_overhang_ratio: f32) -> bool {
-        let overhang = if self.strand == '+' {
-            cmp::min(self.query_start, self.target_start)
-                + cmp::min(
-                    self.query_len - self.query_end,
-                    self.target_len - self.target_end,
-                )
+    /// The number of unaligned bases of the target before and after the overlap, in the orientation
+    /// of the query.
+    fn target_ends(&self) -> (i32, i32) {
+        if self.strand == '+' {
+            (self.target_start, self.target_len - self.target_end)
         } else {
-            cmp::min(self.query_start, self.target_len - self.target_end)
-                + cmp::min(self.query_len - self.query_end, self.target_start)
-        };
+            (self.target_len - self.target_end, self.target_start)
+        }
+    }
+
+    /// Checks if the overlap is an internal match - i.e., the reads only match in part, so their
+    /// unaligned ends (the overhang) are too long for one read to overlap or contain the other. As
+    /// in miniasm, the overhang is too long if it is more than `max_overhang_ratio` of the mapping
+    /// length, or more than `max_overhang_size` bases (if given). Internal matches, such as those
+    /// between repeats, are not useful for estimation.
+    pub(crate) fn is_internal(
+        &self,
+        max_overhang_ratio: f32,
+        max_overhang_size: Option<usize>,
+    ) -> bool {
+        let (target5, target3) = self.target_ends();
+        let overhang = cmp::min(self.query_start, target5)
+            + cmp::min(self.query_len - self.query_end, target3);
         let maplen = cmp::max(
             self.query_end - self.query_start,
             self.target_end - self.target_start,
         );
 
-        let overhang_ratio = overhang as f32 / maplen as f32;
-        overhang_ratio < max_overhang_ratio
+        overhang > ((maplen as f32) * max_overhang_ratio) as i32
+            || max_overhang_size.is_some_and(|max| overhang as usize > max)
+    }
+
+    /// Checks if one read of the overlap is contained in the other - i.e., the other read extends
+    /// at least as far at both ends of the overlap. As in miniasm, if both reads extend equally
+    /// far, the query is contained, and an [internal match][PafRecord::is_internal] is not a
+    /// containment.
+    pub(crate) fn contained_read(
+        &self,
+        max_overhang_ratio: f32,
+        max_overhang_size: Option<usize>,
+    ) -> Option<Contained> {
+        if self.is_internal(max_overhang_ratio, max_overhang_size) {
+            return None;
+        }
+        let (target5, target3) = self.target_ends();
+        let query3 = self.query_len - self.query_end;
+        if self.query_start <= target5 && query3 <= target3 {
+            Some(Contained::Query)
+        } else if self.query_start >= target5 && query3 >= target3 {
+            Some(Contained::Target)
+        } else {
+            None
+        }
+    }
+
+    /// Checks if the reads of the overlap are near-identical - e.g., PCR duplicates from an
+    /// amplified library. Duplicates have the same length, the overlap covers at least
+    /// [`DUPLICATE_MIN_COVERAGE`] of both, and its divergence is at most
+    /// [`DUPLICATE_MAX_DIVERGENCE`].
+    pub(crate) fn is_duplicate(&self) -> bool {
+        let covers = |start: i32, end: i32, len: i32| {
+            (end - start) as f32 >= len as f32 * DUPLICATE_MIN_COVERAGE
+        };
+        self.query_len == self.target_len
+            && covers(self.query_start, self.query_end, self.query_len)
+            && covers(self.target_start, self.target_end, self.target_len)
+            && self.dv <= DUPLICATE_MAX_DIVERGENCE
+    }
+
+    /// Write the record as a line of PAF, with the tags minimap2 writes for overlaps.
+    pub(crate) fn write_paf_record<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(trim_null_bytes(&self.query_name))?;
+        write!(
+            writer,
+            "\t{}\t{}\t{}\t{}\t",
+            self.query_len, self.query_start, self.query_end, self.strand
+        )?;
+        writer.write_all(trim_null_bytes(&self.target_name))?;
+        writeln!(
+            writer,
+            "\t{}\t{}\t{}\t{}\t{}\t{}\ttp:A:{}\tcm:i:{}\ts1:i:{}\tdv:f:{}\trl:i:{}",
+            self.target_len,
+            self.target_start,
+            self.target_end,
+            self.match_len,
+            self.block_len,
+            self.mapq,
+            self.tp,
+            self.cm,
+            self.s1,
+            format_dv(self.dv),
+            self.rl
+        )
+    }
+
+    /// Parse a line of PAF (without the line terminator). The 12 mandatory columns are required, as
+    /// are the `tp`, `cm`, `s1`, `dv`, and `rl` tags, which can be in any order. Other tags are
+    /// ignored. On failure, the reason the line is invalid is returned.
+    pub(crate) fn parse_line(line: &[u8]) -> Result<Self, String> {
+        let mut fields = line.split(|&byte| byte == b'\t');
+        let mut next = |name: &str| {
+            fields
+                .next()
+                .ok_or_else(|| format!("missing the {name} column"))
+        };
+
+        let mut record = PafRecord {
+            query_name: next("query name")?.to_vec(),
+            query_len: parse_field(next("query length")?, "query length")?,
+            query_start: parse_field(next("query start")?, "query start")?,
+            query_end: parse_field(next("query end")?, "query end")?,
+            strand: parse_field(next("strand")?, "strand")?,
+            target_name: next("target name")?.to_vec(),
+            target_len: parse_field(next("target length")?, "target length")?,
+            target_start: parse_field(next("target start")?, "target start")?,
+            target_end: parse_field(next("target end")?, "target end")?,
+            match_len: parse_field(next("match length")?, "match length")?,
+            block_len: parse_field(next("block length")?, "block length")?,
+            mapq: parse_field(next("mapping quality")?, "mapping quality")?,
+            ..Default::default()
+        };
+
+        // a bit for each of tp, cm, s1, dv, and rl, set when the tag is seen
+        let mut seen = 0u8;
+        for tag in fields {
+            let (name, value) = match tag {
+                [a, b, b':', _, b':', value @ ..] => ([*a, *b], value),
+                _ => return Err(format!("invalid tag {}", String::from_utf8_lossy(tag))),
+            };
+            match &name {
+                b"tp" => {
+                    record.tp = parse_field(value, "tp tag")?;
+                    seen |= 1;
+                }
+                b"cm" => {
+                    record.cm = parse_field(value, "cm tag")?;
+                    seen |= 1 << 1;
+                }
+                b"s1" => {
+                    record.s1 = parse_field(value, "s1 tag")?;
+                    seen |= 1 << 2;
+                }
+                b"dv" => {
+                    record.dv = parse_field(value, "dv tag")?;
+                    seen |= 1 << 3;
+                }
+                b"rl" => {
+                    record.rl = parse_field(value, "rl tag")?;
+                    seen |= 1 << 4;
+                }
+                _ => {}
+            }
+        }
+
+        for (i, name) in ["tp", "cm", "s1", "dv", "rl"].iter().enumerate() {
+            if seen & (1 << i) == 0 {
+                return Err(format!("missing the {name} tag"));
+            }
+        }
+
+        Ok(record)
+    }
+
+    /// Write the record as a SAM alignment line. minimap2 does not compute a CIGAR for overlaps, so
+    /// the CIGAR, sequence, and qualities are `*`, and the query coordinates and target end are
+    /// written as tags instead.
+    pub(crate) fn write_sam<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut flag = 0;
+        if self.strand == '-' {
+            flag |= 0x10;
+        }
+        if self.tp == 'S' {
+            flag |= 0x100;
+        }
+        writer.write_all(trim_null_bytes(&self.query_name))?;
+        write!(writer, "\t{flag}\t")?;
+        writer.write_all(trim_null_bytes(&self.target_name))?;
+        writeln!(
+            writer,
+            "\t{}\t{}\t*\t*\t0\t0\t*\t*\tqs:i:{}\tqe:i:{}\tql:i:{}\tte:i:{}\ttp:A:{}\tcm:i:{}\ts1:i:{}\tdv:f:{}\trl:i:{}",
+            self.target_start + 1,
+            self.mapq,
+            self.query_start,
+            self.query_end,
+            self.query_len,
+            self.target_end,
+            self.tp,
+            self.cm,
+            self.s1,
+            format_dv(self.dv),
+            self.rl
+        )
+    }
+
+    /// Write the record as a line of BLAST tabular output (`-outfmt 6`). As there is no base-level
+    /// alignment, mismatches include gaps, gap openings and the e-value are 0, and the bit score is
+    /// the chaining score.
+    pub(crate) fn write_blast6<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let identity = if self.block_len > 0 {
+            self.match_len as f32 / self.block_len as f32 * 100.0
+        } else {
+            0.0
+        };
+        // BLAST gives the subject coordinates in decreasing order for reverse strand hits
+        let (target_start, target_end) = if self.strand == '-' {
+            (self.target_end, self.target_start + 1)
+        } else {
+            (self.target_start + 1, self.target_end)
+        };
+        writer.write_all(trim_null_bytes(&self.query_name))?;
+        writer.write_all(b"\t")?;
+        writer.write_all(trim_null_bytes(&self.target_name))?;
+        writeln!(
+            writer,
+            "\t{identity:.3}\t{}\t{}\t0\t{}\t{}\t{target_start}\t{target_end}\t0\t{}",
+            self.block_len,
+            self.block_len - self.match_len,
+            self.query_start + 1,
+            self.query_end,
+            self.s1
+        )
+    }
+}
+
+/// A lazy iterator over the records of a PAF file, such as the overlaps written by a strategy (see
+/// [`TwoSetStrategy::overlaps`][crate::TwoSetStrategy::overlaps]). Each line is parsed as it is
+/// read, so the file is never held in memory. Empty lines are skipped.
+///
+/// Each item is a [`PafParseError`][LrgeError::PafParseError] if its line is not valid PAF, or an
+/// [`IoError`][LrgeError::IoError] if the file cannot be read. The iterator can carry on past a
+/// line that is not valid PAF.
+///
+/// # Examples
+///
+/// ```no_run
+/// use liblrge::PafReader;
+///
+/// let overlaps = PafReader::from_path("overlaps.paf").unwrap();
+/// for record in overlaps {
+///     let record = record.unwrap();
+///     println!("{}", String::from_utf8_lossy(&record.query_name));
+/// }
+/// ```
+pub struct PafReader {
+    reader: BufReader<File>,
+    path: PathBuf,
+    line: Vec<u8>,
+    line_number: usize,
+}
+
+impl PafReader {
+    /// Open the PAF file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`IoError`][LrgeError::IoError] if the file cannot be opened.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        let path = path.as_ref();
+        Ok(Self {
+            reader: BufReader::new(File::open(path)?),
+            path: path.to_path_buf(),
+            line: Vec::new(),
+            line_number: 0,
+        })
+    }
+
+    /// Open the overlaps written by a strategy, which can only be read back if they were written in
+    /// PAF.
+    pub(crate) fn from_overlaps(path: &Path, format: OverlapFormat) -> crate::Result<Self> {
+        if format != OverlapFormat::Paf {
+            return Err(LrgeError::invalid_configuration(
+                "overlap_format",
+                format!(
+                    "the overlaps can only be read back if they are written in PAF, not {format}"
+                ),
+            ));
+        }
+        Self::from_path(path)
+    }
+}
+
+impl Iterator for PafReader {
+    type Item = crate::Result<PafRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.reader.read_until(b'\n', &mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e.into())),
+            }
+            self.line_number += 1;
+            let trimmed = self
+                .line
+                .strip_suffix(b"\n")
+                .map_or(&self.line[..], |l| l.strip_suffix(b"\r").unwrap_or(l));
+            if trimmed.is_empty() {
+                continue;
+            }
+            return Some(PafRecord::parse_line(trimmed).map_err(|reason| {
+                LrgeError::PafParseError {
+                    path: self.path.clone(),
+                    line: self.line_number,
+                    reason,
+                }
+            }));
+        }
+    }
+}
+
+/// Call `f` with each record of the PAF file at `path`. Empty lines are skipped.
+///
+/// # Errors
+///
+/// Returns an [`IoError`][LrgeError::IoError] if the file cannot be read, or a
+/// [`PafParseError`][LrgeError::PafParseError] if it is not valid PAF.
+pub(crate) fn read_paf<P, F>(path: P, mut f: F) -> crate::Result<()>
+where
+    P: AsRef<Path>,
+    F: FnMut(PafRecord),
+{
+    for record in PafReader::from_path(path)? {
+        f(record?);
     }
+    Ok(())
+}
+
+/// Parse a PAF column or tag value, naming it in the error if it is invalid.
+fn parse_field<T: FromStr>(field: &[u8], name: &str) -> Result<T, String> {
+    std::str::from_utf8(field)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| format!("invalid {name} {}", String::from_utf8_lossy(field)))
 }
 
 /// Serialize `Vec<u8>` as a UTF-8 string
-fn serialize_bytes<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+#[cfg(feature = "serde")]
+pub(crate) fn serialize_bytes<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
@@ -97,97 +431,22 @@ fn trim_null_bytes(data: &[u8]) -> &[u8] {
 }
 
 /// Deserialize a UTF-8 string into `Vec<u8>`
-fn deserialize_bytes<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+#[cfg(feature = "serde")]
+pub(crate) fn deserialize_bytes<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let s: &str = Deserialize::deserialize(deserializer)?;
-    Ok(s.as_bytes().to_vec())
-}
-
-/// Serialize the tp tag
-fn serialize_tp<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-    T: std::fmt::Display,
-{
-    serialize_tag_with_name("tp", value, serializer)
-}
-
-/// Serialize the cm tag
-fn serialize_cm<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-    T: std::fmt::Display,
-{
-    serialize_tag_with_name("cm", value, serializer)
-}
-
-/// Serialize the s1 tag
-fn serialize_s1<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-    T: std::fmt::Display,
-{
-    serialize_tag_with_name("s1", value, serializer)
+    let s: String = Deserialize::deserialize(deserializer)?;
+    Ok(s.into_bytes())
 }
 
-/// Serialize the dv tag - format the float with 4 decimal places
-fn serialize_dv<S>(value: &f32, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    // format the float with 4 decimal places, or if the value is zero, just serialize it as an integer
-    let value = if *value < f32::EPSILON {
+/// Format the dv tag's value with 4 decimal places, or if the value is zero, as an integer
+fn format_dv(value: f32) -> String {
+    if value < f32::EPSILON {
         "0".to_string()
     } else {
         format!("{value:.4}",)
-    };
-    serialize_tag_with_name("dv", &value, serializer)
-}
-
-/// Serialize the rl tag
-fn serialize_rl<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-    T: std::fmt::Display,
-{
-    serialize_tag_with_name("rl", value, serializer)
-}
-
-/// Generic serialization for fields like `cm:i:123`
-fn serialize_tag_with_name<S, T>(name: &str, value: &T, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-    T: std::fmt::Display,
-{
-    let mut prefix = match std::any::type_name::<T>() {
-        "char" => "A",
-        "i32" => "i",
-        "f32" => "f",
-        s => s,
-    };
-
-    if name == "dv" {
-        prefix = "f";
     }
-
-    let formatted = format!("{name}:{prefix}:{value}",);
-    serializer.serialize_str(&formatted)
-}
-
-/// Generic deserialization for fields like `cm:i:123`
-fn deserialize_tag<'de, T, D>(deserializer: D) -> Result<T, D::Error>
-where
-    T: FromStr,
-    T::Err: std::fmt::Display,
-    D: Deserializer<'de>,
-{
-    let s: &str = Deserialize::deserialize(deserializer)?;
-    s.split(':')
-        .next_back()
-        .ok_or_else(|| serde::de::Error::custom("Invalid field format"))
-        .and_then(|val| val.parse::<T>().map_err(serde::de::Error::custom))
 }
 
 #[cfg(test)]
@@ -214,19 +473,116 @@ mod tests {
             s1: 190,
             dv: 0.0022,
             rl: 56,
+            target_id: 0,
         };
-        let mut rdr = csv::ReaderBuilder::new()
-            .delimiter(b'\t')
-            .has_headers(false)
-            .from_reader(&buf[..]);
-        for result in rdr.deserialize() {
-            // Notice that we need to provide a type hint for automatic
-            // deserialization.
-            let mapping: PafRecord = result.unwrap();
-            assert_eq!(mapping, expected);
+        let mapping = PafRecord::parse_line(buf).unwrap();
+        assert_eq!(mapping, expected);
+    }
+
+    #[test]
+    fn test_parse_line_tags_in_any_order() {
+        let buf = b"SRR28370649.1\t4402\t40\t237\t-\tSRR28370649.7311\t5094\t41\t238\t190\t197\t0\trl:i:56\tde:f:0.01\tdv:f:0.0022\ts1:i:190\tcm:i:59\ttp:A:S";
+        assert_eq!(PafRecord::parse_line(buf).unwrap(), example_record());
+    }
+
+    #[test]
+    fn test_parse_line_round_trip() {
+        let mut buf = Vec::new();
+        example_record().write_paf_record(&mut buf).unwrap();
+        let line = buf.strip_suffix(b"\n").unwrap();
+        assert_eq!(PafRecord::parse_line(line).unwrap(), example_record());
+    }
+
+    #[test]
+    fn test_parse_line_invalid() {
+        let err = PafRecord::parse_line(b"not\ta\tpaf").unwrap_err();
+        assert_eq!(err, "invalid query length a");
+
+        let err = PafRecord::parse_line(b"q\t10\t0\t10\t+\tt").unwrap_err();
+        assert_eq!(err, "missing the target length column");
+
+        let buf = b"q\t10\t0\t10\t+\tt\t10\t0\t10\t10\t10\t0\ttp:A:S\tcm:i:1\ts1:i:10\tdv:f:0";
+        let err = PafRecord::parse_line(buf).unwrap_err();
+        assert_eq!(err, "missing the rl tag");
+
+        let buf = b"q\t10\t0\t10\t+\tt\t10\t0\t10\t10\t10\t0\ttp:A:S\tcm:i:x";
+        let err = PafRecord::parse_line(buf).unwrap_err();
+        assert_eq!(err, "invalid cm tag x");
+    }
+
+    #[test]
+    fn test_paf_reader() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("overlaps.paf");
+        let mut buf = Vec::new();
+        example_record().write_paf_record(&mut buf).unwrap();
+        buf.extend_from_slice(b"\r\nnot\ta\tpaf\n");
+        example_record().write_paf_record(&mut buf).unwrap();
+        std::fs::write(&path, buf).unwrap();
+
+        let mut reader = PafReader::from_path(&path).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap(), example_record());
+        // the empty line is skipped, but counted
+        assert!(matches!(
+            reader.next(),
+            Some(Err(LrgeError::PafParseError { line: 3, .. }))
+        ));
+        assert_eq!(reader.next().unwrap().unwrap(), example_record());
+        assert!(reader.next().is_none());
+
+        assert!(PafReader::from_path(dir.path().join("missing.paf")).is_err());
+    }
+
+    fn example_record() -> PafRecord {
+        PafRecord {
+            query_name: b"SRR28370649.1".to_vec(),
+            query_len: 4402,
+            query_start: 40,
+            query_end: 237,
+            strand: '-',
+            target_name: b"SRR28370649.7311".to_vec(),
+            target_len: 5094,
+            target_start: 41,
+            target_end: 238,
+            match_len: 190,
+            block_len: 197,
+            mapq: 0,
+            tp: 'S',
+            cm: 59,
+            s1: 190,
+            dv: 0.0022,
+            rl: 56,
+            target_id: 0,
         }
     }
 
+    #[test]
+    fn test_write_sam() {
+        let mut buf = Vec::new();
+        example_record().write_sam(&mut buf).unwrap();
+        let expected = "SRR28370649.1\t272\tSRR28370649.7311\t42\t0\t*\t*\t0\t0\t*\t*\tqs:i:40\tqe:i:237\tql:i:4402\tte:i:238\ttp:A:S\tcm:i:59\ts1:i:190\tdv:f:0.0022\trl:i:56\n";
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_write_blast6() {
+        let mut buf = Vec::new();
+        example_record().write_blast6(&mut buf).unwrap();
+        let expected =
+            "SRR28370649.1\tSRR28370649.7311\t96.447\t197\t7\t0\t41\t237\t238\t42\t0\t190\n";
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+
+        let mut buf = Vec::new();
+        let record = PafRecord {
+            strand: '+',
+            ..example_record()
+        };
+        record.write_blast6(&mut buf).unwrap();
+        assert!(String::from_utf8(buf)
+            .unwrap()
+            .contains("\t41\t237\t42\t238\t"));
+    }
+
     #[test]
     fn test_serialize_mapping() {
         let mapping = PafRecord {
@@ -247,13 +603,10 @@ mod tests {
             s1: 190,
             dv: 0.0022,
             rl: 56,
+            target_id: 0,
         };
-        let mut wtr = csv::WriterBuilder::new()
-            .delimiter(b'\t')
-            .has_headers(false)
-            .from_writer(vec![]);
-        wtr.serialize(mapping).unwrap();
-        let result = wtr.into_inner().unwrap();
+        let mut result = Vec::new();
+        mapping.write_paf_record(&mut result).unwrap();
         let result = String::from_utf8(result).unwrap();
         let expected = "SRR28370649.1\t4402\t40\t237\t-\tSRR28370649.7311\t5094\t41\t238\t190\t197\t0\ttp:A:S\tcm:i:59\ts1:i:190\tdv:f:0.0022\trl:i:56\n";
         assert_eq!(result, expected);
@@ -279,13 +632,10 @@ mod tests {
             s1: 190,
             dv: 0.0022,
             rl: 56,
+            target_id: 0,
         };
-        let mut wtr = csv::WriterBuilder::new()
-            .delimiter(b'\t')
-            .has_headers(false)
-            .from_writer(vec![]);
-        wtr.serialize(mapping).unwrap();
-        let result = wtr.into_inner().unwrap();
+        let mut result = Vec::new();
+        mapping.write_paf_record(&mut result).unwrap();
         let result = String::from_utf8(result).unwrap();
         let expected = "SRR28370649.1\t4402\t40\t237\t-\tSRR28370649.7311\t5094\t41\t238\t190\t197\t0\ttp:A:S\tcm:i:59\ts1:i:190\tdv:f:0.0022\trl:i:56\n";
         assert_eq!(result, expected);
@@ -311,13 +661,10 @@ mod tests {
             s1: 190,
             dv: 0.0022111,
             rl: 56,
+            target_id: 0,
         };
-        let mut wtr = csv::WriterBuilder::new()
-            .delimiter(b'\t')
-            .has_headers(false)
-            .from_writer(vec![]);
-        wtr.serialize(mapping).unwrap();
-        let result = wtr.into_inner().unwrap();
+        let mut result = Vec::new();
+        mapping.write_paf_record(&mut result).unwrap();
         let result = String::from_utf8(result).unwrap();
         let expected = "SRR28370649.1\t4402\t40\t237\t-\tSRR28370649.7311\t5094\t41\t238\t190\t197\t0\ttp:A:S\tcm:i:59\ts1:i:190\tdv:f:0.0022\trl:i:56\n";
         assert_eq!(result, expected);
@@ -343,13 +690,10 @@ mod tests {
             s1: 190,
             dv: 0.0021999,
             rl: 56,
+            target_id: 0,
         };
-        let mut wtr = csv::WriterBuilder::new()
-            .delimiter(b'\t')
-            .has_headers(false)
-            .from_writer(vec![]);
-        wtr.serialize(mapping).unwrap();
-        let result = wtr.into_inner().unwrap();
+        let mut result = Vec::new();
+        mapping.write_paf_record(&mut result).unwrap();
         let result = String::from_utf8(result).unwrap();
         let expected = "SRR28370649.1\t4402\t40\t237\t-\tSRR28370649.7311\t5094\t41\t238\t190\t197\t0\ttp:A:S\tcm:i:59\ts1:i:190\tdv:f:0.0022\trl:i:56\n";
         assert_eq!(result, expected);
@@ -375,13 +719,10 @@ mod tests {
             s1: 190,
             dv: 0.004,
             rl: 56,
+            target_id: 0,
         };
-        let mut wtr = csv::WriterBuilder::new()
-            .delimiter(b'\t')
-            .has_headers(false)
-            .from_writer(vec![]);
-        wtr.serialize(mapping).unwrap();
-        let result = wtr.into_inner().unwrap();
+        let mut result = Vec::new();
+        mapping.write_paf_record(&mut result).unwrap();
         let result = String::from_utf8(result).unwrap();
         let expected = "SRR28370649.1\t4402\t40\t237\t-\tSRR28370649.7311\t5094\t41\t238\t190\t197\t0\ttp:A:S\tcm:i:59\ts1:i:190\tdv:f:0.0040\trl:i:56\n";
         assert_eq!(result, expected);
@@ -407,13 +748,10 @@ mod tests {
             s1: 190,
             dv: 0.0000,
             rl: 56,
+            target_id: 0,
         };
-        let mut wtr = csv::WriterBuilder::new()
-            .delimiter(b'\t')
-            .has_headers(false)
-            .from_writer(vec![]);
-        wtr.serialize(mapping).unwrap();
-        let result = wtr.into_inner().unwrap();
+        let mut result = Vec::new();
+        mapping.write_paf_record(&mut result).unwrap();
         let result = String::from_utf8(result).unwrap();
         let expected = "SRR28370649.1\t4402\t40\t237\t-\tSRR28370649.7311\t5094\t41\t238\t190\t197\t0\ttp:A:S\tcm:i:59\ts1:i:190\tdv:f:0\trl:i:56\n";
         assert_eq!(result, expected);
@@ -439,8 +777,10 @@ mod tests {
             s1: 260,
             dv: 0.0,
             rl: 0,
+            target_id: 0,
         };
-        assert!(mapping.is_internal(0.2));
+        // one read contains the other
+        assert!(!mapping.is_internal(0.2, None));
     }
 
     #[test]
@@ -463,8 +803,10 @@ mod tests {
             s1: 260,
             dv: 0.0,
             rl: 0,
+            target_id: 0,
         };
-        assert!(mapping.is_internal(0.2));
+        // one read contains the other
+        assert!(!mapping.is_internal(0.2, None));
     }
 
     #[test]
@@ -487,7 +829,96 @@ mod tests {
             s1: 260,
             dv: 0.0,
             rl: 0,
+            target_id: 0,
+        };
+        // the overhang of 24 is more than 5% of the mapping length, but not 10%
+        assert!(mapping.is_internal(0.05, None));
+        assert!(!mapping.is_internal(0.1, None));
+        assert!(mapping.is_internal(0.1, Some(20)));
+        assert!(!mapping.is_internal(0.1, Some(24)));
+    }
+
+    #[test]
+    fn test_contained_read() {
+        let mut mapping = PafRecord {
+            query_name: b"q".to_vec(),
+            query_len: 1000,
+            query_start: 0,
+            query_end: 600,
+            strand: '+',
+            target_name: b"t".to_vec(),
+            target_len: 1000,
+            target_start: 400,
+            target_end: 1000,
+            match_len: 600,
+            block_len: 600,
+            mapq: 0,
+            tp: 'S',
+            cm: 100,
+            s1: 600,
+            dv: 0.0,
+            rl: 0,
+            target_id: 0,
+        };
+        // a dovetail overlap
+        assert_eq!(mapping.contained_read(0.2, None), None);
+
+        // the target extends past both ends of the query
+        mapping.query_end = 1000;
+        mapping.target_start = 100;
+        mapping.target_end = 1100;
+        mapping.target_len = 1500;
+        assert_eq!(mapping.contained_read(0.2, None), Some(Contained::Query));
+
+        // on the reverse strand, the target ends are swapped
+        mapping.strand = '-';
+        mapping.target_start = 450;
+        mapping.target_end = 1450;
+        assert_eq!(mapping.contained_read(0.2, None), Some(Contained::Query));
+
+        // the query extends past both ends of the target
+        mapping.strand = '+';
+        mapping.query_len = 2000;
+        mapping.target_start = 0;
+        mapping.target_end = 1000;
+        mapping.target_len = 1000;
+        assert_eq!(mapping.contained_read(0.2, None), Some(Contained::Target));
+
+        // an internal match is not a containment
+        mapping.query_start = 500;
+        mapping.query_end = 800;
+        mapping.target_start = 300;
+        mapping.target_end = 600;
+        assert_eq!(mapping.contained_read(0.2, None), None);
+    }
+
+    #[test]
+    fn test_is_duplicate() {
+        let mut mapping = PafRecord {
+            query_len: 1000,
+            query_start: 10,
+            query_end: 995,
+            strand: '-',
+            target_len: 1000,
+            target_start: 0,
+            target_end: 990,
+            dv: 0.02,
+            ..Default::default()
         };
-        assert!(!mapping.is_internal(0.05));
+        assert!(mapping.is_duplicate());
+
+        // too divergent
+        mapping.dv = 0.1;
+        assert!(!mapping.is_duplicate());
+
+        // the overlap does not cover enough of the target
+        mapping.dv = 0.02;
+        mapping.target_start = 100;
+        assert!(!mapping.is_duplicate());
+
+        // the reads are different lengths, even though the overlap covers both
+        mapping.target_start = 0;
+        mapping.target_len = 1001;
+        assert!(!mapping.is_duplicate());
     }
 }
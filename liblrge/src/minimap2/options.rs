@@ -0,0 +1,76 @@
+//! Changes to minimap2's index and mapping options made by the caller, for options that have no
+//! setter of their own on the strategy builders.
+use std::sync::Arc;
+
+use super::{IdxOpt, MapOpt};
+
+/// A change to the mapping options, given to a strategy builder's `with_mapopt`.
+type MapOptFn = Arc<dyn Fn(&mut MapOpt) + Send + Sync>;
+/// A change to the index options, given to a strategy builder's `with_idxopt`.
+type IdxOptFn = Arc<dyn Fn(&mut IdxOpt) + Send + Sync>;
+
+/// The caller's changes to the minimap2 options, which are applied after the preset and all of the
+/// options lrge sets itself, so they take precedence. The changes are shared, so a
+/// [`StrategyBuilder`][crate::StrategyBuilder] can pass them on to the builder of its strategy, and
+/// each index a strategy builds gets the same options.
+#[derive(Clone, Default)]
+pub(crate) struct OptionOverrides {
+    mapopt: Vec<MapOptFn>,
+    idxopt: Vec<IdxOptFn>,
+}
+
+impl OptionOverrides {
+    /// Add a change to the mapping options. Changes are applied in the order they are added.
+    pub(crate) fn add_mapopt<F>(&mut self, f: F)
+    where
+        F: Fn(&mut MapOpt) + Send + Sync + 'static,
+    {
+        self.mapopt.push(Arc::new(f));
+    }
+
+    /// Add a change to the index options. Changes are applied in the order they are added.
+    pub(crate) fn add_idxopt<F>(&mut self, f: F)
+    where
+        F: Fn(&mut IdxOpt) + Send + Sync + 'static,
+    {
+        self.idxopt.push(Arc::new(f));
+    }
+
+    /// Add the changes in `other` after those already added.
+    pub(crate) fn extend(&mut self, other: OptionOverrides) {
+        self.mapopt.extend(other.mapopt);
+        self.idxopt.extend(other.idxopt);
+    }
+
+    /// Apply the changes to the options.
+    pub(crate) fn apply(&self, idxopt: &mut IdxOpt, mapopt: &mut MapOpt) {
+        for f in &self.idxopt {
+            f(idxopt);
+        }
+        for f in &self.mapopt {
+            f(mapopt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_applied_in_order() {
+        let mut overrides = OptionOverrides::default();
+        overrides.add_mapopt(|opt| opt.min_chain_score = 200);
+        overrides.add_idxopt(|opt| opt.k = 17);
+
+        let mut later = OptionOverrides::default();
+        later.add_mapopt(|opt| opt.min_chain_score = 300);
+        overrides.extend(later);
+
+        let mut idxopt = IdxOpt::default();
+        let mut mapopt = MapOpt::default();
+        overrides.apply(&mut idxopt, &mut mapopt);
+        assert_eq!(idxopt.k, 17);
+        assert_eq!(mapopt.min_chain_score, 300);
+    }
+}
@@ -1,7 +1,7 @@
 //! The code in this module is adapted from the [`minimap2` crate](https://github.com/jguhlin/minimap2-rs).
 use std::cell::RefCell;
 
-use minimap2_sys::{mm_tbuf_destroy, mm_tbuf_init, mm_tbuf_t};
+use super::ffi::{mm_tbuf_destroy, mm_tbuf_init, mm_tbuf_t};
 
 // Thread local buffer (memory management) for minimap2
 thread_local! {
@@ -0,0 +1,336 @@
+//! An overlap writer that runs on its own thread, so that mapping threads do not contend on a lock
+//! to write their overlaps.
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crossbeam_channel as channel;
+
+use super::mapping::PafRecord;
+use crate::error::LrgeError;
+use crate::hash::HashSet;
+use crate::OverlapFormat;
+
+/// The callback given to a strategy's builder for each overlap.
+pub(crate) type MappingCallback = Box<dyn FnMut(&PafRecord) + Send>;
+
+/// Passes each overlap to a caller's callback, from the writer thread. It is shared, so the writer
+/// thread of every run of a strategy can use it.
+#[derive(Clone)]
+pub(crate) struct MappingSink(Arc<Mutex<MappingCallback>>);
+
+impl MappingSink {
+    pub(crate) fn new(callback: MappingCallback) -> Self {
+        Self(Arc::new(Mutex::new(callback)))
+    }
+}
+
+/// Writes batches of [`PafRecord`]s to a file, in an [`OverlapFormat`], on a dedicated thread.
+///
+/// Mapping threads send all of the mappings for a read as a single batch, which the writer thread
+/// serializes to the file. Call [`OverlapWriter::finish`] once all batches have been sent to flush
+/// the file and surface any errors from the writer thread.
+///
+/// The records are written in the order they are received, which depends on how the mapping
+/// threads are scheduled. If `sort` is set, the writer thread instead keeps all records in memory
+/// and writes them [sorted and deduplicated][sort_and_dedup] once all batches have been sent.
+///
+/// If there is an `on_mapping` sink, each record is passed to it just before it is written.
+pub(crate) struct OverlapWriter {
+    sender: channel::Sender<Vec<PafRecord>>,
+    handle: JoinHandle<Result<(), LrgeError>>,
+}
+
+impl OverlapWriter {
+    /// Create the overlaps file at `path` and start the writer thread.
+    ///
+    /// At most `capacity` batches are buffered before senders block.
+    pub(crate) fn new(
+        path: &Path,
+        format: OverlapFormat,
+        capacity: usize,
+        sort: bool,
+        on_mapping: Option<MappingSink>,
+    ) -> Result<Self, LrgeError> {
+        let buf = File::create(path).map(BufWriter::new)?;
+        // the SAM header needs every target, so the alignments are written to a separate file
+        // until all of the targets are known
+        let body_path = path.with_extension("sam.body");
+        let body = match format {
+            OverlapFormat::Sam => Some(File::create(&body_path).map(BufWriter::new)?),
+            _ => None,
+        };
+        let (sender, receiver) = channel::bounded::<Vec<PafRecord>>(capacity);
+
+        let handle = std::thread::spawn(move || -> Result<(), LrgeError> {
+            let records: Box<dyn Iterator<Item = PafRecord>> = if sort {
+                Box::new(sort_and_dedup(receiver.into_iter().flatten().collect()).into_iter())
+            } else {
+                Box::new(receiver.into_iter().flatten())
+            };
+            // a panic in the callback has already been propagated, so a poisoned lock can be ignored
+            let mut callback = on_mapping
+                .as_ref()
+                .map(|sink| sink.0.lock().unwrap_or_else(|e| e.into_inner()));
+            let records = records.inspect(|record| {
+                if let Some(callback) = callback.as_mut() {
+                    (**callback)(record);
+                }
+            });
+            match (format, body) {
+                (OverlapFormat::Paf, _) => write_paf(buf, records),
+                (OverlapFormat::Blast6, _) => write_blast6(buf, records),
+                (OverlapFormat::Sam, Some(body)) => write_sam(buf, body, body_path, records),
+                (OverlapFormat::Sam, None) => unreachable!("the SAM body file is always created"),
+            }
+        });
+
+        Ok(Self { sender, handle })
+    }
+
+    /// Send a batch of records to the writer thread.
+    pub(crate) fn write(&self, records: Vec<PafRecord>) -> Result<(), LrgeError> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        self.sender.send(records).map_err(|_| {
+            LrgeError::ThreadError("the overlap writer thread stopped unexpectedly".to_string())
+        })
+    }
+
+    /// Close the channel and wait for the writer thread to write all outstanding records.
+    pub(crate) fn finish(self) -> Result<(), LrgeError> {
+        drop(self.sender);
+        self.handle
+            .join()
+            .map_err(|e| LrgeError::ThreadError(format!("Overlap writer thread panicked: {e:?}")))?
+    }
+}
+
+/// Sort records by query name and coordinates (then by target), and remove duplicates. As well as
+/// identical records, an overlap that was found in both directions (i.e., with each read as the
+/// query) is a duplicate, so only the direction with the lexicographically smaller query name is
+/// kept.
+fn sort_and_dedup(mut records: Vec<PafRecord>) -> Vec<PafRecord> {
+    let pairs: HashSet<(Vec<u8>, Vec<u8>)> = records
+        .iter()
+        .filter(|r| r.query_name < r.target_name)
+        .map(|r| (r.query_name.clone(), r.target_name.clone()))
+        .collect();
+    records.retain(|r| {
+        r.query_name <= r.target_name
+            || !pairs.contains(&(r.target_name.clone(), r.query_name.clone()))
+    });
+
+    records.sort_by(|a, b| {
+        (&a.query_name, a.query_start, a.query_end, &a.target_name)
+            .cmp(&(&b.query_name, b.query_start, b.query_end, &b.target_name))
+            .then_with(|| {
+                (a.target_start, a.target_end, a.strand).cmp(&(
+                    b.target_start,
+                    b.target_end,
+                    b.strand,
+                ))
+            })
+    });
+    records.dedup();
+    records
+}
+
+fn write_paf(
+    mut buf: BufWriter<File>,
+    records: impl Iterator<Item = PafRecord>,
+) -> Result<(), LrgeError> {
+    for record in records {
+        record.write_paf_record(&mut buf)?;
+    }
+
+    buf.flush()?;
+    Ok(())
+}
+
+fn write_blast6(
+    mut buf: BufWriter<File>,
+    records: impl Iterator<Item = PafRecord>,
+) -> Result<(), LrgeError> {
+    for record in records {
+        record.write_blast6(&mut buf)?;
+    }
+
+    buf.flush()?;
+    Ok(())
+}
+
+fn write_sam(
+    mut buf: BufWriter<File>,
+    mut body: BufWriter<File>,
+    body_path: PathBuf,
+    records: impl Iterator<Item = PafRecord>,
+) -> Result<(), LrgeError> {
+    let mut targets = BTreeMap::new();
+    for record in records {
+        record.write_sam(&mut body)?;
+        targets
+            .entry(record.target_name)
+            .or_insert(record.target_len);
+    }
+    body.flush()?;
+    drop(body);
+
+    writeln!(buf, "@HD\tVN:1.6\tSO:unsorted")?;
+    for (name, len) in &targets {
+        buf.write_all(b"@SQ\tSN:")?;
+        buf.write_all(name)?;
+        writeln!(buf, "\tLN:{len}")?;
+    }
+    writeln!(
+        buf,
+        "@PG\tID:liblrge\tPN:liblrge\tVN:{}",
+        env!("CARGO_PKG_VERSION")
+    )?;
+    io::copy(&mut File::open(&body_path)?, &mut buf)?;
+    buf.flush()?;
+    std::fs::remove_file(&body_path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_records(format: OverlapFormat) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(format.filename());
+        let writer = OverlapWriter::new(&path, format, 2, false, None).unwrap();
+
+        let record = PafRecord {
+            query_name: b"q".to_vec(),
+            target_name: b"t".to_vec(),
+            target_len: 100,
+            strand: '+',
+            tp: 'P',
+            ..Default::default()
+        };
+        for _ in 0..5 {
+            writer.write(vec![record.clone(), record.clone()]).unwrap();
+        }
+        writer.write(vec![]).unwrap();
+        writer.finish().unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn test_paf_writer_writes_all_batches() {
+        let (_dir, path) = write_records(OverlapFormat::Paf);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 10);
+        assert!(contents.lines().all(|l| l.starts_with("q\t")));
+    }
+
+    #[test]
+    fn test_blast6_writer() {
+        let (_dir, path) = write_records(OverlapFormat::Blast6);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 10);
+        assert!(contents.lines().all(|l| l.split('\t').count() == 12));
+    }
+
+    #[test]
+    fn test_sam_writer_has_header() {
+        let (dir, path) = write_records(OverlapFormat::Sam);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 13);
+        assert_eq!(lines[0], "@HD\tVN:1.6\tSO:unsorted");
+        assert_eq!(lines[1], "@SQ\tSN:t\tLN:100");
+        assert!(lines[2].starts_with("@PG\t"));
+        assert!(lines[3..].iter().all(|l| l.starts_with("q\t0\tt\t1\t")));
+        // the temporary body file is removed
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_writer_passes_records_to_sink() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("overlaps.paf");
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink_seen = Arc::clone(&seen);
+        let sink = MappingSink::new(Box::new(move |record: &PafRecord| {
+            sink_seen.lock().unwrap().push(record.query_name.clone())
+        }));
+
+        let writer = OverlapWriter::new(&path, OverlapFormat::Paf, 2, true, Some(sink)).unwrap();
+        writer.write(vec![record("b", "c", 0)]).unwrap();
+        writer
+            .write(vec![record("a", "b", 0), record("b", "a", 0)])
+            .unwrap();
+        writer.finish().unwrap();
+
+        // the sink sees the records that are written, in the order they are written
+        assert_eq!(*seen.lock().unwrap(), vec![b"a".to_vec(), b"b".to_vec()]);
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 2);
+    }
+
+    fn record(query: &str, target: &str, query_start: i32) -> PafRecord {
+        PafRecord {
+            query_name: query.as_bytes().to_vec(),
+            target_name: target.as_bytes().to_vec(),
+            query_start,
+            strand: '+',
+            tp: 'P',
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sort_and_dedup() {
+        let records = vec![
+            record("b", "a", 0),
+            record("c", "a", 5),
+            record("a", "b", 0),
+            record("c", "a", 1),
+            record("a", "c", 3),
+            record("a", "b", 0),
+        ];
+        let sorted = sort_and_dedup(records);
+        let names: Vec<(&[u8], &[u8], i32)> = sorted
+            .iter()
+            .map(|r| (&r.query_name[..], &r.target_name[..], r.query_start))
+            .collect();
+        assert_eq!(
+            names,
+            vec![(&b"a"[..], &b"b"[..], 0), (&b"a"[..], &b"c"[..], 3)]
+        );
+
+        // an overlap only found in one direction is kept, whichever read is the query
+        let sorted = sort_and_dedup(vec![record("c", "a", 5), record("c", "a", 1)]);
+        let starts: Vec<i32> = sorted.iter().map(|r| r.query_start).collect();
+        assert_eq!(starts, vec![1, 5]);
+    }
+
+    #[test]
+    fn test_sorted_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("overlaps.paf");
+        let writer = OverlapWriter::new(&path, OverlapFormat::Paf, 2, true, None).unwrap();
+        writer.write(vec![record("b", "c", 0)]).unwrap();
+        writer
+            .write(vec![record("a", "b", 0), record("b", "a", 0)])
+            .unwrap();
+        writer.finish().unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let queries: Vec<&str> = contents
+            .lines()
+            .map(|l| l.split('\t').next().unwrap())
+            .collect();
+        assert_eq!(queries, vec!["a", "b"]);
+    }
+}
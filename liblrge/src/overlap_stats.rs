@@ -0,0 +1,216 @@
+//! Summaries of the distributions of the overlaps found by a strategy - the number of overlaps per
+//! read, the overlap lengths, and the sequence divergence of the overlaps.
+//!
+//! When an estimate deviates from what is expected, these distributions help to explain why
+//! without crunching the PAF file by hand. For example, many short, divergent overlaps suggest
+//! spurious overlaps between repeats, which inflate the overlap counts and so shrink the estimate.
+//!
+//! All overlaps in the file are summarised, including those the strategy did not count (e.g.,
+//! internal matches). Reads without any overlaps are not in the PAF file, so are not included in
+//! the overlaps per read.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use liblrge::estimate::OVERLAPS_FILENAME;
+//! use liblrge::overlap_stats::OverlapStats;
+//!
+//! let overlaps = format!("path/to/tmpdir/{OVERLAPS_FILENAME}");
+//! if let Some(stats) = OverlapStats::from_paf(overlaps).unwrap() {
+//!     println!("Median overlap length: {}", stats.overlap_len.quantiles[2]);
+//! }
+//! ```
+use std::path::Path;
+
+use crate::estimate::interpolate_quantile;
+use crate::hash::{HashMap, HashSet};
+use crate::minimap2::mapping::read_paf;
+
+/// The quantiles that are reported in [`Distribution::quantiles`].
+pub const DISTRIBUTION_QUANTILES: [f32; 5] = [0.05, 0.25, 0.5, 0.75, 0.95];
+
+/// The number of bins in the histogram of a [`Distribution`].
+pub const NUM_BINS: usize = 10;
+
+/// A summary of the distribution of some values.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Distribution {
+    /// The number of values
+    pub count: usize,
+    /// The quantiles, given by [`DISTRIBUTION_QUANTILES`], of the values
+    pub quantiles: [f32; 5],
+    /// The [`NUM_BINS`] + 1 edges of the histogram bins, which are of equal width between the
+    /// smallest and largest values
+    pub bin_edges: Vec<f32>,
+    /// The number of values in each histogram bin. The last bin includes its upper edge
+    pub bin_counts: Vec<usize>,
+}
+
+impl Distribution {
+    /// Summarise the distribution of `values`. Returns `None` if there are no values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::overlap_stats::Distribution;
+    ///
+    /// let dist = Distribution::new((0..=100).map(|x| x as f32).collect()).unwrap();
+    /// assert_eq!(dist.quantiles[2], 50.0);
+    /// assert_eq!(dist.bin_edges[1], 10.0);
+    /// assert_eq!(dist.bin_counts[0], 10);
+    /// assert_eq!(dist.bin_counts[9], 11);
+    /// assert_eq!(Distribution::new(Vec::new()), None);
+    /// ```
+    pub fn new(mut values: Vec<f32>) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(|a, b| a.total_cmp(b));
+        let quantiles = DISTRIBUTION_QUANTILES.map(|q| interpolate_quantile(&values, q));
+
+        let min = values[0];
+        let max = values[values.len() - 1];
+        let width = (max - min) / NUM_BINS as f32;
+        let bin_edges = (0..NUM_BINS)
+            .map(|i| min + width * i as f32)
+            .chain([max])
+            .collect();
+        let mut bin_counts = vec![0; NUM_BINS];
+        for value in &values {
+            let bin = if width > 0.0 {
+                ((value - min) / width) as usize
+            } else {
+                0
+            };
+            bin_counts[bin.min(NUM_BINS - 1)] += 1;
+        }
+
+        Some(Self {
+            count: values.len(),
+            quantiles,
+            bin_edges,
+            bin_counts,
+        })
+    }
+}
+
+/// The distributions of the overlaps in a PAF file.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OverlapStats {
+    /// The number of other reads that each read overlaps
+    pub overlaps_per_read: Distribution,
+    /// The length of each overlap - i.e., the number of bases, including gaps, in the alignment
+    pub overlap_len: Distribution,
+    /// The approximate per-base sequence divergence (the `dv` tag) of each overlap
+    pub divergence: Distribution,
+}
+
+impl OverlapStats {
+    /// Summarise the overlaps in the PAF file at `path`. Returns `None` if there are no overlaps
+    /// between different reads.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`IoError`][crate::error::LrgeError::IoError] if the file cannot be opened, or a
+    /// [`PafParseError`][crate::error::LrgeError::PafParseError] if it is not valid PAF.
+    pub fn from_paf<P: AsRef<Path>>(path: P) -> crate::Result<Option<Self>> {
+        let mut partners: HashMap<Vec<u8>, HashSet<Vec<u8>>> = HashMap::default();
+        let mut overlap_lens = Vec::new();
+        let mut divergences = Vec::new();
+
+        read_paf(path, |record| {
+            if record.query_name == record.target_name {
+                return;
+            }
+            overlap_lens.push(record.block_len as f32);
+            divergences.push(record.dv);
+            partners
+                .entry(record.query_name.clone())
+                .or_default()
+                .insert(record.target_name.clone());
+            partners
+                .entry(record.target_name)
+                .or_default()
+                .insert(record.query_name);
+        })?;
+
+        let per_read = partners.values().map(|p| p.len() as f32).collect();
+        Ok(Distribution::new(per_read).and_then(|overlaps_per_read| {
+            Some(Self {
+                overlaps_per_read,
+                overlap_len: Distribution::new(overlap_lens)?,
+                divergence: Distribution::new(divergences)?,
+            })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::LrgeError;
+    use crate::estimate::OVERLAPS_FILENAME;
+
+    fn paf_line(query: &str, target: &str, block_len: i32, dv: f32) -> String {
+        format!(
+            "{query}\t10000\t0\t{block_len}\t+\t{target}\t10000\t0\t{block_len}\t{block_len}\t{block_len}\t0\ttp:A:S\tcm:i:59\ts1:i:190\tdv:f:{dv}\trl:i:0\n"
+        )
+    }
+
+    #[test]
+    fn test_distribution() {
+        let dist = Distribution::new(vec![3.0, 1.0, 2.0]).unwrap();
+        assert_eq!(dist.count, 3);
+        assert_eq!(dist.quantiles[0], 1.1);
+        assert_eq!(dist.quantiles[2], 2.0);
+        assert_eq!(dist.bin_edges.len(), NUM_BINS + 1);
+        assert_eq!((dist.bin_edges[0], dist.bin_edges[NUM_BINS]), (1.0, 3.0));
+        assert_eq!(dist.bin_counts.iter().sum::<usize>(), 3);
+        assert_eq!(dist.bin_counts[0], 1);
+        assert_eq!(dist.bin_counts[5], 1);
+        assert_eq!(dist.bin_counts[NUM_BINS - 1], 1);
+    }
+
+    #[test]
+    fn test_distribution_of_equal_values() {
+        let dist = Distribution::new(vec![5.0; 4]).unwrap();
+        assert_eq!(dist.quantiles, [5.0; 5]);
+        assert!(dist.bin_edges.iter().all(|&e| e == 5.0));
+        assert_eq!(dist.bin_counts[0], 4);
+    }
+
+    #[test]
+    fn test_overlap_stats_from_paf() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(OVERLAPS_FILENAME);
+        let lines = [
+            paf_line("a", "b", 1000, 0.01),
+            // a second chain between the same reads is not another overlapping read
+            paf_line("b", "a", 500, 0.05),
+            paf_line("a", "c", 3000, 0.02),
+            paf_line("c", "c", 10000, 0.0),
+        ];
+        std::fs::write(&path, lines.concat()).unwrap();
+
+        let stats = OverlapStats::from_paf(&path).unwrap().unwrap();
+        assert_eq!(stats.overlaps_per_read.count, 3);
+        // a overlaps b and c, which only overlap a
+        assert_eq!(stats.overlaps_per_read.quantiles[2], 1.0);
+        assert_eq!(stats.overlaps_per_read.bin_edges[NUM_BINS], 2.0);
+        assert_eq!(stats.overlap_len.count, 3);
+        assert_eq!(stats.overlap_len.quantiles[2], 1000.0);
+        assert_eq!(stats.divergence.bin_edges[0], 0.01);
+        assert_eq!(stats.divergence.bin_edges[NUM_BINS], 0.05);
+
+        std::fs::write(&path, paf_line("c", "c", 10000, 0.0)).unwrap();
+        assert_eq!(OverlapStats::from_paf(&path).unwrap(), None);
+
+        std::fs::write(&path, "a\tnot-a-number\n").unwrap();
+        assert!(matches!(
+            OverlapStats::from_paf(&path),
+            Err(LrgeError::PafParseError { .. })
+        ));
+    }
+}
@@ -0,0 +1,117 @@
+//! A model-based confidence interval for the genome size estimate.
+//!
+//! The bounds of an [`EstimateResult`][crate::estimate::EstimateResult] are quantiles of the
+//! per-read estimates, so describe how much the reads disagree, not how certain the estimate is.
+//! Treating the number of overlaps found as Poisson distributed - reads land on the genome at
+//! random, so each read overlaps a target with a probability set by the coverage - gives an interval
+//! that does. The estimate is inversely proportional to the number of overlaps, so the interval of
+//! the estimate is that of the total number of overlaps, inverted. Its width shrinks with the
+//! square root of the number of overlaps, so sampling four times as many reads roughly halves it.
+//!
+//! The interval only accounts for the randomness of the overlaps, so it is narrower than the
+//! quantiles when something else - e.g., repeats or contamination - skews the per-read estimates.
+//!
+//! # Examples
+//!
+//! ```
+//! use liblrge::poisson::PoissonInterval;
+//!
+//! let interval = PoissonInterval::new(5e6, 400).unwrap();
+//! assert!(interval.lower < 5e6 && 5e6 < interval.upper);
+//! // four times the overlaps give roughly half the width
+//! let narrower = PoissonInterval::new(5e6, 1600).unwrap();
+//! let ratio = (narrower.upper - narrower.lower) / (interval.upper - interval.lower);
+//! assert!((ratio - 0.5).abs() < 0.01);
+//! ```
+
+/// The confidence level of a [`PoissonInterval`].
+pub const POISSON_CONFIDENCE: f32 = 0.95;
+
+/// The standard normal quantile for a two-sided [`POISSON_CONFIDENCE`] interval.
+const Z: f64 = 1.959_963_984_540_054;
+
+/// A [`POISSON_CONFIDENCE`] confidence interval of the genome size estimate, from the number of
+/// overlaps it was calculated from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PoissonInterval {
+    /// The lower bound of the interval
+    pub lower: f32,
+    /// The upper bound of the interval
+    pub upper: f32,
+    /// The number of independent overlaps the estimate was calculated from. In all-vs-all, each
+    /// overlap is found by both of its reads, but only counted once here
+    pub num_overlaps: u64,
+}
+
+impl PoissonInterval {
+    /// The interval around `estimate`, from the `num_overlaps` overlaps it was calculated from.
+    /// The bounds of the number of overlaps use Byar's approximation of the exact Poisson interval,
+    /// which is close even for a handful of overlaps.
+    ///
+    /// Returns `None` if the estimate is not finite or there are no overlaps.
+    pub fn new(estimate: f32, num_overlaps: u64) -> Option<Self> {
+        if !estimate.is_finite() || num_overlaps == 0 {
+            return None;
+        }
+
+        let k = num_overlaps as f64;
+        let k_lower = k * (1.0 - 1.0 / (9.0 * k) - Z / (3.0 * k.sqrt())).powi(3);
+        let k_upper =
+            (k + 1.0) * (1.0 - 1.0 / (9.0 * (k + 1.0)) + Z / (3.0 * (k + 1.0).sqrt())).powi(3);
+        let estimate = estimate as f64;
+
+        // more overlaps mean a smaller genome, so the upper bound of the overlaps gives the lower
+        // bound of the estimate
+        Some(Self {
+            lower: (estimate * k / k_upper) as f32,
+            upper: (estimate * k / k_lower) as f32,
+            num_overlaps,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_contains_estimate() {
+        for k in [1, 2, 10, 100, 10_000] {
+            let interval = PoissonInterval::new(1e6, k).unwrap();
+            assert!(interval.lower > 0.0);
+            assert!(
+                interval.lower < 1e6 && 1e6 < interval.upper,
+                "{k}: {interval:?}"
+            );
+            assert_eq!(interval.num_overlaps, k);
+        }
+    }
+
+    #[test]
+    fn test_new_matches_exact_interval() {
+        // the exact 95% interval of 100 Poisson events is 81.37 - 121.63
+        let interval = PoissonInterval::new(100.0, 100).unwrap();
+        assert!((interval.lower - 100.0 * 100.0 / 121.63).abs() < 0.05);
+        assert!((interval.upper - 100.0 * 100.0 / 81.37).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_new_narrows_with_more_overlaps() {
+        let widths: Vec<f32> = [10, 100, 1000]
+            .into_iter()
+            .map(|k| {
+                let interval = PoissonInterval::new(1e6, k).unwrap();
+                interval.upper - interval.lower
+            })
+            .collect();
+        assert!(widths[0] > widths[1] && widths[1] > widths[2]);
+    }
+
+    #[test]
+    fn test_new_without_overlaps_or_estimate() {
+        assert_eq!(PoissonInterval::new(1e6, 0), None);
+        assert_eq!(PoissonInterval::new(f32::INFINITY, 10), None);
+        assert_eq!(PoissonInterval::new(f32::NAN, 10), None);
+    }
+}
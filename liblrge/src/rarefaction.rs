@@ -0,0 +1,170 @@
+//! Rarefaction curves of the genome size estimate against the number of reads.
+//!
+//! A rarefaction curve shows how the estimate changes as more per-read estimates are included. If
+//! the estimate has plateaued well before all reads are included, the number of (query) reads was
+//! large enough.
+//!
+//! The curve is calculated from the per-read estimates of a single run, so no reads are overlapped
+//! again. With the [`TwoSetStrategy`][crate::TwoSetStrategy], a query read's estimate only depends
+//! on the target reads, so each point is the estimate that running with that many query reads
+//! would have given. With the [`AvaStrategy`][crate::AvaStrategy], each read's estimate depends on
+//! all of the reads, so the curve only shows how many of those estimates are needed.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use liblrge::estimate::SavedEstimates;
+//! use liblrge::rarefaction::{default_sizes, rarefaction};
+//!
+//! // the per-read estimates of a previous run
+//! let estimates = SavedEstimates::new("path/to/tmpdir").read().unwrap();
+//! let sizes = default_sizes(estimates.len());
+//! let curve = rarefaction(&estimates, &sizes, true, Some(0.15), Some(0.65), Some(42)).unwrap();
+//! for point in curve {
+//!     println!("{}\t{:?}", point.num_reads, point.estimate);
+//! }
+//! ```
+
+use crate::estimate::{validate_quantiles, EstimateResult, PerReadEstimate};
+use crate::rng::{SamplingRng, Stream};
+use crate::stream::RunningEstimate;
+
+/// The smallest number of reads in the [default sizes][default_sizes].
+pub const DEFAULT_MIN_SIZE: usize = 500;
+
+/// The default numbers of reads for a rarefaction curve of `num_reads` reads - i.e.,
+/// [`DEFAULT_MIN_SIZE`] reads, doubling until `num_reads` is reached. `num_reads` is always the last
+/// size.
+///
+/// # Examples
+///
+/// ```
+/// use liblrge::rarefaction::default_sizes;
+///
+/// assert_eq!(default_sizes(5000), vec![500, 1000, 2000, 4000, 5000]);
+/// assert_eq!(default_sizes(100), vec![100]);
+/// ```
+pub fn default_sizes(num_reads: usize) -> Vec<usize> {
+    let mut sizes: Vec<usize> = std::iter::successors(Some(DEFAULT_MIN_SIZE), |s| s.checked_mul(2))
+        .take_while(|s| *s < num_reads)
+        .collect();
+    sizes.push(num_reads);
+    sizes
+}
+
+/// Calculate the estimate from random subsets of the per-read estimates, of each of the given
+/// `sizes`. The subsets are nested - each includes all of the reads of the smaller subsets.
+///
+/// The `finite`, `lower_quant`, and `upper_quant` arguments are the same as for
+/// [`Estimate::estimate`][crate::Estimate::estimate]. The reads are shuffled with the given `seed`
+/// (or a random one, if `None`), so the curve is reproducible, whatever order the estimates are
+/// given in. Sizes of 0 or more than the number of estimates are ignored.
+///
+/// The returned results are in increasing order of size, and their
+/// [`num_reads`][EstimateResult::num_reads] is the size. The input read counts and GC content are
+/// `None`.
+///
+/// # Errors
+///
+/// Returns an [`InvalidQuantile`][crate::error::LrgeError::InvalidQuantile] error if the quantiles
+/// are invalid.
+pub fn rarefaction(
+    estimates: &[PerReadEstimate],
+    sizes: &[usize],
+    finite: bool,
+    lower_quant: Option<f32>,
+    upper_quant: Option<f32>,
+    seed: Option<u64>,
+) -> crate::Result<Vec<EstimateResult>> {
+    validate_quantiles(lower_quant, upper_quant)?;
+
+    let mut sizes: Vec<usize> = sizes
+        .iter()
+        .copied()
+        .filter(|size| (1..=estimates.len()).contains(size))
+        .collect();
+    sizes.sort_unstable();
+    sizes.dedup();
+
+    // sort first, as the estimates are in the order their reads finished mapping
+    let mut order: Vec<&PerReadEstimate> = estimates.iter().collect();
+    order.sort_by(|a, b| a.read_id.cmp(&b.read_id));
+    SamplingRng::new(seed, Stream::Rarefaction).shuffle(&mut order);
+
+    let mut running = RunningEstimate::new(finite, lower_quant, upper_quant)?;
+    let mut curve = Vec::with_capacity(sizes.len());
+    let mut sizes = sizes.into_iter().peekable();
+    for est in order {
+        running.push(est);
+        if sizes.next_if_eq(&(running.num_reads() as usize)).is_some() {
+            curve.push(running.result());
+        }
+    }
+
+    Ok(curve)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn estimates(n: usize) -> Vec<PerReadEstimate> {
+        (0..n)
+            .map(|i| PerReadEstimate {
+                read_id: format!("read{i}").into_bytes(),
+                estimate: if i % 10 == 0 {
+                    f32::INFINITY
+                } else {
+                    (i * 100) as f32
+                },
+                mapped: i % 10 != 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_default_sizes() {
+        assert_eq!(default_sizes(500), vec![500]);
+        assert_eq!(default_sizes(501), vec![500, 501]);
+        assert_eq!(default_sizes(2000), vec![500, 1000, 2000]);
+        assert_eq!(default_sizes(0), vec![0]);
+    }
+
+    #[test]
+    fn test_rarefaction() {
+        let estimates = estimates(100);
+        let curve = rarefaction(
+            &estimates,
+            &[50, 0, 10, 200, 100, 10],
+            true,
+            None,
+            None,
+            Some(1),
+        )
+        .unwrap();
+
+        let sizes: Vec<u32> = curve.iter().map(|r| r.num_reads).collect();
+        assert_eq!(sizes, vec![10, 50, 100]);
+
+        // the last point includes every read, so is the same as the full estimate
+        let all = &curve[2];
+        assert_eq!(all.no_mapping_count, 10);
+        assert_eq!(all.estimate, Some(5000.0));
+    }
+
+    #[test]
+    fn test_rarefaction_is_reproducible() {
+        let estimates = estimates(100);
+        let mut reversed = estimates.clone();
+        reversed.reverse();
+
+        let curve = rarefaction(&estimates, &[10, 20], false, Some(0.25), None, Some(7)).unwrap();
+        let same = rarefaction(&reversed, &[10, 20], false, Some(0.25), None, Some(7)).unwrap();
+        assert_eq!(curve, same);
+    }
+
+    #[test]
+    fn test_rarefaction_invalid_quantile() {
+        assert!(rarefaction(&estimates(10), &[5], true, None, Some(0.2), None).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+//! Restricting which reads of the input can be sampled, by their IDs.
+//!
+//! A file of read IDs has one ID per line. Only the first whitespace-delimited field of each line
+//! is used, so a FASTQ/FASTA header (without the `@`/`>`) or a table with the IDs in its first
+//! column can be given as is. Empty lines are ignored.
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use crate::error::LrgeError;
+use crate::hash::HashSet;
+
+/// Which reads of the input can be sampled. Reads that are not allowed are skipped as if they were
+/// not in the input.
+#[derive(Debug, Default)]
+pub(crate) struct ReadFilter {
+    /// If given, only the reads with these IDs are allowed.
+    allowed: Option<HashSet<Vec<u8>>>,
+    /// The reads with these IDs are never allowed.
+    blocked: Option<HashSet<Vec<u8>>>,
+}
+
+impl ReadFilter {
+    /// Load a filter that only allows the reads in the `allowlist` file, if there is one, and that
+    /// are not in the `blocklist` file, if there is one. Without either, all reads are allowed.
+    pub(crate) fn new(allowlist: Option<&Path>, blocklist: Option<&Path>) -> io::Result<Self> {
+        let allowed = allowlist.map(read_ids).transpose()?;
+        let blocked = blocklist.map(read_ids).transpose()?;
+        Ok(Self { allowed, blocked })
+    }
+
+    /// Whether the read with this ID can be sampled.
+    pub(crate) fn allows(&self, id: &[u8]) -> bool {
+        self.allowed.as_ref().is_none_or(|ids| ids.contains(id))
+            && !self.blocked.as_ref().is_some_and(|ids| ids.contains(id))
+    }
+
+    /// Whether any reads are filtered out.
+    pub(crate) fn is_active(&self) -> bool {
+        self.allowed.is_some() || self.blocked.is_some()
+    }
+}
+
+/// Read the IDs in the file at `path`.
+fn read_ids(path: &Path) -> io::Result<HashSet<Vec<u8>>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut ids = HashSet::default();
+    for line in reader.split(b'\n') {
+        let line = line?;
+        if let Some(id) = line
+            .split(|b| b.is_ascii_whitespace())
+            .find(|field| !field.is_empty())
+        {
+            ids.insert(id.to_vec());
+        }
+    }
+    Ok(ids)
+}
+
+/// Check that the file of read IDs given as `option` exists, if there is one.
+pub(crate) fn validate(option: &'static str, path: Option<&Path>) -> crate::Result<()> {
+    match path {
+        Some(path) if !path.is_file() => Err(LrgeError::invalid_configuration(
+            option,
+            format!("{} is not an existing file", path.display()),
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_allowlist() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, "read1\n\nread2 comment\r\n  read3\tother\n").unwrap();
+        let filter = ReadFilter::new(Some(file.path()), None).unwrap();
+
+        assert!(filter.is_active());
+        assert!(filter.allows(b"read1"));
+        assert!(filter.allows(b"read2"));
+        assert!(filter.allows(b"read3"));
+        assert!(!filter.allows(b"read4"));
+        assert!(!filter.allows(b"comment"));
+    }
+
+    #[test]
+    fn test_blocklist() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "read1\nread2").unwrap();
+        let filter = ReadFilter::new(None, Some(file.path())).unwrap();
+
+        assert!(filter.is_active());
+        assert!(!filter.allows(b"read1"));
+        assert!(!filter.allows(b"read2"));
+        assert!(filter.allows(b"read3"));
+    }
+
+    #[test]
+    fn test_allowlist_and_blocklist() {
+        let mut allowlist = tempfile::NamedTempFile::new().unwrap();
+        writeln!(allowlist, "read1\nread2").unwrap();
+        let mut blocklist = tempfile::NamedTempFile::new().unwrap();
+        writeln!(blocklist, "read2\nread3").unwrap();
+        let filter = ReadFilter::new(Some(allowlist.path()), Some(blocklist.path())).unwrap();
+
+        assert!(filter.allows(b"read1"));
+        assert!(!filter.allows(b"read2"));
+        assert!(!filter.allows(b"read3"));
+        assert!(!filter.allows(b"read4"));
+    }
+
+    #[test]
+    fn test_no_lists() {
+        let filter = ReadFilter::new(None, None).unwrap();
+        assert!(!filter.is_active());
+        assert!(filter.allows(b"read1"));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("allowlist", None).is_ok());
+        assert!(validate("allowlist", Some(Path::new("missing/ids.txt"))).is_err());
+    }
+}
@@ -0,0 +1,279 @@
+//! Decompose the genome size estimate into its replicons - e.g., the chromosome and plasmids of a
+//! bacterial isolate.
+//!
+//! Reads from different replicons do not overlap, so each replicon is a separate connected
+//! component of the overlap graph, provided enough reads were sampled to connect it. A read's
+//! estimate is (roughly) the size of its replicon divided by the fraction of the sampled reads that
+//! came from that replicon - a plasmid with a high copy number has many reads, so its reads overlap
+//! many others and have small estimates. The size of each component's replicon is therefore the
+//! median estimate of its reads, multiplied by the fraction of the reads that are in it.
+//!
+//! Only overlaps that are not internal matches join reads into a component, so that a repeat
+//! shared by a plasmid and the chromosome (e.g., an insertion sequence) does not merge them.
+//! Components with too few estimated reads - e.g., the fragments of a chromosome that was not
+//! sampled deeply enough to be connected - are not replicons, but their reads still count towards
+//! the fractions of the other components.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use liblrge::estimate::{SavedEstimates, OVERLAPS_FILENAME};
+//! use liblrge::replicon::{decompose, MIN_REPLICON_READS};
+//!
+//! let estimates = SavedEstimates::new("path/to/tmpdir").read().unwrap();
+//! let overlaps = format!("path/to/tmpdir/{OVERLAPS_FILENAME}");
+//! let decomposition = decompose(overlaps, &estimates, MIN_REPLICON_READS).unwrap();
+//! for replicon in &decomposition.replicons {
+//!     println!("{} bp from {} reads", replicon.size, replicon.num_reads);
+//! }
+//! println!("Plasmid fraction: {:?}", decomposition.plasmid_fraction());
+//! ```
+use std::path::Path;
+
+use crate::estimate::{calculate_quantile, PerReadEstimate};
+use crate::hash::HashMap;
+use crate::minimap2::mapping::read_paf;
+
+/// The default minimum number of estimated reads in a component for it to be a replicon.
+pub const MIN_REPLICON_READS: usize = 5;
+
+/// The maximum overhang to overlap length ratio for an overlap to join two reads into a component.
+/// This is the default of the strategies' internal match filter.
+pub(crate) const MAX_OVERHANG_RATIO: f32 = 0.2;
+
+/// A putative replicon - a connected component of the overlap graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replicon {
+    /// The estimated size of the replicon, in bp
+    pub size: f32,
+    /// The number of reads in the component with a finite estimate
+    pub num_reads: usize,
+    /// The fraction of all reads with a finite estimate that are in the component
+    pub read_fraction: f32,
+}
+
+/// The replicons found by [`decompose`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Decomposition {
+    /// The replicons, largest first. The largest is assumed to be the chromosome
+    pub replicons: Vec<Replicon>,
+}
+
+impl Decomposition {
+    /// The largest replicon, which is assumed to be the chromosome.
+    pub fn chromosome(&self) -> Option<&Replicon> {
+        self.replicons.first()
+    }
+
+    /// The replicons other than the chromosome - i.e., the putative plasmids.
+    pub fn plasmids(&self) -> &[Replicon] {
+        self.replicons.get(1..).unwrap_or_default()
+    }
+
+    /// The fraction of the total size of the replicons that is in the putative plasmids. This is
+    /// `None` if there are no replicons.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::replicon::{Decomposition, Replicon};
+    ///
+    /// let replicon = |size| Replicon {
+    ///     size,
+    ///     num_reads: 100,
+    ///     read_fraction: 0.5,
+    /// };
+    /// let decomposition = Decomposition {
+    ///     replicons: vec![replicon(4.5e6), replicon(0.5e6)],
+    /// };
+    /// assert_eq!(decomposition.plasmid_fraction(), Some(0.1));
+    /// assert_eq!(Decomposition::default().plasmid_fraction(), None);
+    /// ```
+    pub fn plasmid_fraction(&self) -> Option<f32> {
+        let total = self.replicons.iter().fold(0.0, |sum, r| sum + r.size);
+        let plasmids = self.plasmids().iter().fold(0.0, |sum, r| sum + r.size);
+        (total > 0.0).then(|| plasmids / total)
+    }
+}
+
+/// A disjoint set of reads, for finding the connected components of the overlap graph.
+#[derive(Debug, Default)]
+pub(crate) struct Components {
+    index: HashMap<Vec<u8>, usize>,
+    parent: Vec<usize>,
+}
+
+impl Components {
+    /// The index of a read, adding it as its own component if it is new.
+    fn add(&mut self, read: &[u8]) -> usize {
+        if let Some(&i) = self.index.get(read) {
+            return i;
+        }
+        let i = self.parent.len();
+        self.index.insert(read.to_vec(), i);
+        self.parent.push(i);
+        i
+    }
+
+    /// The representative read of the component containing read `i`.
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    /// Merge the components of two reads.
+    pub(crate) fn union(&mut self, a: &[u8], b: &[u8]) {
+        let a = self.add(a);
+        let b = self.add(b);
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[a.max(b)] = a.min(b);
+        }
+    }
+
+    /// The component of a read, if it is in any overlap.
+    pub(crate) fn component(&mut self, read: &[u8]) -> Option<usize> {
+        let i = *self.index.get(read)?;
+        Some(self.find(i))
+    }
+}
+
+/// Decompose the per-read estimates into replicons, using the connected components of the overlaps
+/// in the PAF file `overlaps`. Components with fewer than `min_reads` reads with a finite estimate
+/// are not replicons.
+///
+/// # Errors
+///
+/// Returns an [`IoError`][crate::error::LrgeError::IoError] if the file cannot be opened, or a
+/// [`PafParseError`][crate::error::LrgeError::PafParseError] if it is not valid PAF.
+pub fn decompose<P: AsRef<Path>>(
+    overlaps: P,
+    estimates: &[PerReadEstimate],
+    min_reads: usize,
+) -> crate::Result<Decomposition> {
+    let mut components = Components::default();
+    read_paf(overlaps, |record| {
+        if record.query_name != record.target_name && !record.is_internal(MAX_OVERHANG_RATIO, None)
+        {
+            components.union(&record.query_name, &record.target_name);
+        }
+    })?;
+
+    let mut by_component: HashMap<usize, Vec<f32>> = HashMap::default();
+    let mut num_finite = 0;
+    for estimate in estimates.iter().filter(|e| e.estimate.is_finite()) {
+        num_finite += 1;
+        if let Some(component) = components.component(&estimate.read_id) {
+            by_component
+                .entry(component)
+                .or_default()
+                .push(estimate.estimate);
+        }
+    }
+
+    let mut replicons = Vec::new();
+    for mut values in by_component.into_values() {
+        if values.len() < min_reads {
+            continue;
+        }
+        values.sort_by(|a, b| a.total_cmp(b));
+        let median = calculate_quantile(&values, 0.5)?.unwrap_or_default();
+        let read_fraction = values.len() as f32 / num_finite as f32;
+        replicons.push(Replicon {
+            size: median * read_fraction,
+            num_reads: values.len(),
+            read_fraction,
+        });
+    }
+    replicons.sort_by(|a, b| b.size.total_cmp(&a.size));
+
+    Ok(Decomposition { replicons })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::LrgeError;
+    use std::io::Write;
+
+    fn estimate(read: &str, estimate: f32) -> PerReadEstimate {
+        PerReadEstimate {
+            read_id: read.as_bytes().to_vec(),
+            estimate,
+            mapped: estimate.is_finite(),
+        }
+    }
+
+    const TAGS: &str = "\ttp:A:S\tcm:i:59\ts1:i:190\tdv:f:0.0022\trl:i:0\n";
+
+    /// A PAF line where the end of `query` overlaps the start of `target`.
+    fn dovetail(query: &str, target: &str) -> String {
+        format!("{query}\t10000\t6000\t10000\t+\t{target}\t10000\t0\t4000\t4000\t4000\t0{TAGS}")
+    }
+
+    fn write_paf(lines: &[String]) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        for line in lines {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+        file
+    }
+
+    #[test]
+    fn test_decompose() {
+        // a chromosome of 6 reads, and a high copy number plasmid of 4 reads
+        let mut lines: Vec<String> = (0..5)
+            .map(|i| dovetail(&format!("c{i}"), &format!("c{}", i + 1)))
+            .collect();
+        lines.extend((0..3).map(|i| dovetail(&format!("p{i}"), &format!("p{}", i + 1))));
+        // an internal match between the two, such as a shared repeat, does not merge them
+        lines.push(format!(
+            "c0\t10000\t4000\t5000\t+\tp0\t10000\t4000\t5000\t1000\t1000\t0{TAGS}"
+        ));
+        let paf = write_paf(&lines);
+
+        let mut estimates: Vec<PerReadEstimate> = (0..6)
+            .map(|i| estimate(&format!("c{i}"), 5e6))
+            .chain((0..4).map(|i| estimate(&format!("p{i}"), 2.5e5)))
+            .collect();
+        estimates.push(estimate("unmapped", f32::INFINITY));
+
+        let decomposition = decompose(paf.path(), &estimates, 3).unwrap();
+        assert_eq!(decomposition.replicons.len(), 2);
+        let chromosome = decomposition.chromosome().unwrap();
+        assert_eq!(chromosome.num_reads, 6);
+        assert_eq!(chromosome.read_fraction, 0.6);
+        assert_eq!(chromosome.size, 3e6);
+        let plasmids = decomposition.plasmids();
+        assert_eq!(plasmids.len(), 1);
+        assert_eq!(plasmids[0].size, 1e5);
+        assert!((decomposition.plasmid_fraction().unwrap() - 1e5 / 3.1e6).abs() < 1e-6);
+
+        // the plasmid is too small a component
+        let decomposition = decompose(paf.path(), &estimates, 5).unwrap();
+        assert_eq!(decomposition.replicons.len(), 1);
+        assert_eq!(decomposition.plasmid_fraction(), Some(0.0));
+    }
+
+    #[test]
+    fn test_decompose_no_overlaps() {
+        let paf = write_paf(&[]);
+        let decomposition = decompose(paf.path(), &[estimate("r1", f32::INFINITY)], 1).unwrap();
+        assert_eq!(decomposition, Decomposition::default());
+        assert_eq!(decomposition.chromosome(), None);
+        assert!(decomposition.plasmids().is_empty());
+    }
+
+    #[test]
+    fn test_decompose_invalid_paf() {
+        let paf = write_paf(&["not\ta\tpaf\n".to_string()]);
+        assert!(matches!(
+            decompose(paf.path(), &[], 1),
+            Err(LrgeError::PafParseError { .. })
+        ));
+    }
+}
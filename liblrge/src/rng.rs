@@ -0,0 +1,246 @@
+//! The random number generator behind the sampling of reads.
+//!
+//! The reads that are sampled for a seed must not change when a dependency is upgraded, or on
+//! another platform, so the generator is implemented here rather than taken from `rand`, whose
+//! generators and algorithms (e.g., for shuffling) may change between versions. It is ChaCha with
+//! 12 rounds, keyed from a seed the same way as `rand` 0.9's `StdRng::seed_from_u64`, so the reads
+//! sampled for a seed are the same as they were with `rand` 0.9:
+//!
+//! 1. The 32-byte key is eight little-endian words from PCG32 (multiplier
+//!    `6364136223846793005`, increment `11634580027462260723`), which is started at the seed and
+//!    advanced before each word.
+//! 2. Each block of 16 words is the ChaCha12 function of the key, a 64-bit block counter (starting
+//!    at 0) in words 12 and 13, and the 64-bit [`Stream`] in words 14 and 15.
+//! 3. The blocks are generated four at a time, and their words are consumed in order, two for each
+//!    `u64` - the first word is the low half.
+//!
+//! Each use of randomness has its own [`Stream`], so, e.g., the order of the rarefaction curve is
+//! independent of the keys the reads were sampled with.
+use rand::Rng;
+
+/// The number of double rounds of ChaCha12.
+const DOUBLE_ROUNDS: usize = 6;
+/// The number of words in a ChaCha block.
+const BLOCK_WORDS: usize = 16;
+/// The number of blocks generated at a time.
+const BUFFER_BLOCKS: usize = 4;
+/// The number of words generated at a time.
+const BUFFER_WORDS: usize = BLOCK_WORDS * BUFFER_BLOCKS;
+/// "expand 32-byte k", the ChaCha constant.
+const CONSTANTS: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
+
+/// What the random numbers are used for. Each is an independent stream of the generator for a
+/// seed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stream {
+    /// The keys of the reads in the reservoir sample (including resampling the reserve reads)
+    Sampling = 0,
+    /// The order the per-read estimates are added in for the rarefaction curve
+    Rarefaction = 1,
+}
+
+/// The ChaCha12 random number generator used for sampling - see the [module documentation][self].
+#[derive(Debug, Clone)]
+pub(crate) struct SamplingRng {
+    key: [u32; 8],
+    stream: u64,
+    /// The counter of the next block to generate
+    counter: u64,
+    buffer: [u32; BUFFER_WORDS],
+    /// The index of the next word of the buffer to use
+    index: usize,
+}
+
+impl SamplingRng {
+    /// The generator for `stream` of `seed`. Without a seed, one is drawn from the operating
+    /// system's entropy, so the generator is not reproducible.
+    pub(crate) fn new(seed: Option<u64>, stream: Stream) -> Self {
+        let mut state = seed.unwrap_or_else(|| rand::rng().random());
+        let key = std::array::from_fn(|_| pcg32(&mut state));
+        Self {
+            key,
+            stream: stream as u64,
+            counter: 0,
+            buffer: [0; BUFFER_WORDS],
+            index: BUFFER_WORDS,
+        }
+    }
+
+    /// The next random `u64`.
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        // only whole u64s are drawn, so one never straddles two batches of blocks
+        if self.index >= BUFFER_WORDS {
+            self.refill();
+            self.index = 0;
+        }
+        let index = self.index;
+        self.index += 2;
+        u64::from(self.buffer[index]) | (u64::from(self.buffer[index + 1]) << 32)
+    }
+
+    /// A random number that is less than `n`, which must be greater than 0. This uses Lemire's
+    /// multiply-and-reject method, so is unbiased.
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        debug_assert!(n > 0);
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let product = u128::from(self.next_u64()) * u128::from(n);
+            if product as u64 >= threshold {
+                return (product >> 64) as u64;
+            }
+        }
+    }
+
+    /// Shuffle `items` in place with the Fisher-Yates shuffle, swapping each item, from the last,
+    /// with a random item at or before it.
+    pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+
+    /// Generate the next [`BUFFER_BLOCKS`] blocks.
+    fn refill(&mut self) {
+        for (i, block) in self.buffer.chunks_exact_mut(BLOCK_WORDS).enumerate() {
+            let counter = self.counter.wrapping_add(i as u64);
+            chacha_block(&self.key, counter, self.stream, block);
+        }
+        self.counter = self.counter.wrapping_add(BUFFER_BLOCKS as u64);
+    }
+}
+
+/// Advance the PCG32 `state`, and return its output.
+fn pcg32(state: &mut u64) -> u32 {
+    const MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+    const INCREMENT: u64 = 11_634_580_027_462_260_723;
+
+    *state = state.wrapping_mul(MULTIPLIER).wrapping_add(INCREMENT);
+    let xorshifted = (((*state >> 18) ^ *state) >> 27) as u32;
+    let rotation = (*state >> 59) as u32;
+    xorshifted.rotate_right(rotation)
+}
+
+/// Write the ChaCha12 block for `counter` of `stream` to `out`.
+fn chacha_block(key: &[u32; 8], counter: u64, stream: u64, out: &mut [u32]) {
+    let mut input = [0u32; BLOCK_WORDS];
+    input[..4].copy_from_slice(&CONSTANTS);
+    input[4..12].copy_from_slice(key);
+    input[12] = counter as u32;
+    input[13] = (counter >> 32) as u32;
+    input[14] = stream as u32;
+    input[15] = (stream >> 32) as u32;
+
+    let mut x = input;
+    for _ in 0..DOUBLE_ROUNDS {
+        quarter_round(&mut x, 0, 4, 8, 12);
+        quarter_round(&mut x, 1, 5, 9, 13);
+        quarter_round(&mut x, 2, 6, 10, 14);
+        quarter_round(&mut x, 3, 7, 11, 15);
+        quarter_round(&mut x, 0, 5, 10, 15);
+        quarter_round(&mut x, 1, 6, 11, 12);
+        quarter_round(&mut x, 2, 7, 8, 13);
+        quarter_round(&mut x, 3, 4, 9, 14);
+    }
+    for ((out, x), input) in out.iter_mut().zip(x).zip(input) {
+        *out = x.wrapping_add(input);
+    }
+}
+
+fn quarter_round(x: &mut [u32; BLOCK_WORDS], a: usize, b: usize, c: usize, d: usize) {
+    x[a] = x[a].wrapping_add(x[b]);
+    x[d] = (x[d] ^ x[a]).rotate_left(16);
+    x[c] = x[c].wrapping_add(x[d]);
+    x[b] = (x[b] ^ x[c]).rotate_left(12);
+    x[a] = x[a].wrapping_add(x[b]);
+    x[d] = (x[d] ^ x[a]).rotate_left(8);
+    x[c] = x[c].wrapping_add(x[d]);
+    x[b] = (x[b] ^ x[c]).rotate_left(7);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_u64_is_pinned() {
+        // these are the values of rand 0.9's StdRng::seed_from_u64(6), and must never change
+        let mut rng = SamplingRng::new(Some(6), Stream::Sampling);
+        let first: Vec<u64> = (0..3).map(|_| rng.next_u64()).collect();
+        assert_eq!(
+            first,
+            [
+                6174163183852698189,
+                12591657660360415645,
+                14847347490978081802
+            ]
+        );
+
+        // across the boundary of the first batch of blocks
+        for _ in 3..31 {
+            rng.next_u64();
+        }
+        let boundary: Vec<u64> = (0..3).map(|_| rng.next_u64()).collect();
+        assert_eq!(
+            boundary,
+            [
+                17681511817577076594,
+                15878825293774991567,
+                6682935073175226140
+            ]
+        );
+    }
+
+    #[test]
+    fn test_streams_are_independent() {
+        let mut rng = SamplingRng::new(Some(6), Stream::Rarefaction);
+        let first: Vec<u64> = (0..3).map(|_| rng.next_u64()).collect();
+        assert_eq!(
+            first,
+            [
+                1391479339563184001,
+                2566661465543526482,
+                11708044966391906478
+            ]
+        );
+    }
+
+    #[test]
+    fn test_seeds_are_repeatable() {
+        let draw = |seed| {
+            let mut rng = SamplingRng::new(seed, Stream::Sampling);
+            (0..100).map(|_| rng.next_u64()).collect::<Vec<_>>()
+        };
+        assert_eq!(draw(Some(42)), draw(Some(42)));
+        assert_ne!(draw(Some(42)), draw(Some(43)));
+        assert_ne!(draw(None), draw(None));
+    }
+
+    #[test]
+    fn test_below() {
+        let mut rng = SamplingRng::new(Some(1), Stream::Sampling);
+        let mut counts = [0; 3];
+        for _ in 0..3000 {
+            counts[rng.below(3) as usize] += 1;
+        }
+        assert!(
+            counts.iter().all(|&n| (900..1100).contains(&n)),
+            "{counts:?}"
+        );
+        assert_eq!(rng.below(1), 0);
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let mut items: Vec<u32> = (0..10).collect();
+        SamplingRng::new(Some(6), Stream::Rarefaction).shuffle(&mut items);
+        assert_eq!(items, [4, 8, 7, 6, 3, 9, 2, 5, 1, 0]);
+
+        let mut sorted = items.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..10).collect::<Vec<_>>());
+
+        let mut empty: [u32; 0] = [];
+        SamplingRng::new(Some(6), Stream::Rarefaction).shuffle(&mut empty);
+    }
+}
@@ -0,0 +1,867 @@
+//! Single-pass random sampling of reads from an input file.
+//!
+//! Reads are sampled with a priority-based reservoir: every record is assigned a uniform random
+//! key and the records with the `k` smallest keys are retained. This means the input only needs to
+//! be parsed once - we do not need to know how many records it contains before sampling.
+//!
+//! Records that enter the reservoir are spilled to a temporary FASTA file, rather than held in
+//! memory, so memory usage is independent of the length and number of the sampled reads. The
+//! expected number of spilled records is `k * (1 + ln(n / k))` for an input of `n` records.
+//!
+//! Because the keys are independent and identically distributed, ordering the retained records by
+//! their key gives a uniformly random ordering of the sample. This is how samples are split into
+//! multiple groups - e.g., target and query reads.
+//!
+//! When a fraction of the reads is to be sampled, rather than a number of them, the records are
+//! [counted][count_records] first, so the input is read twice. Unless they are to be validated, the
+//! records are only scanned for their IDs and lengths when they are counted, rather than parsed.
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use log::debug;
+
+use crate::adapter::{AdapterScan, AdapterSummary};
+use crate::count_cache;
+use crate::error::LrgeError;
+use crate::gc::{GcCounter, GcSummary};
+use crate::io::{iter_records, iter_records_with_quality, scan_records};
+use crate::read_filter::ReadFilter;
+use crate::rng::{SamplingRng, Stream};
+use crate::trim::QualityTrim;
+
+/// A record retained in the reservoir.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd)]
+struct Entry {
+    /// The random priority of the record. The records with the smallest keys are retained.
+    key: u64,
+    /// The offset of the record in the spill file.
+    offset: u64,
+    /// The number of bytes the record occupies in the spill file.
+    size: u64,
+    /// The length of the record's sequence.
+    seq_len: usize,
+    /// Whether the record's sequence had uracil (`U`) bases, which were converted to `T`.
+    is_rna: bool,
+}
+
+/// The result of sampling records from an input file.
+pub(crate) struct Sample {
+    /// The number of records in the input file.
+    num_records: usize,
+    /// The total number of bases in the input file.
+    num_bases: u64,
+    /// The number of malformed records in the input file that were skipped.
+    num_skipped: usize,
+    /// The number of records in the input file that were excluded by the read filter.
+    num_excluded: usize,
+    /// The number of sampled records with uracil (`U`) bases, e.g., direct RNA reads.
+    num_rna: usize,
+    /// The number of records in the input file whose low-quality ends were trimmed.
+    num_trimmed: usize,
+    /// The number of bases trimmed from the records in the input file.
+    num_trimmed_bases: u64,
+    /// The retained records, in random order.
+    entries: Vec<Entry>,
+    /// The file the retained records were spilled to.
+    spill_path: PathBuf,
+}
+
+impl Sample {
+    /// The number of records in the input file.
+    pub(crate) fn num_records(&self) -> usize {
+        self.num_records
+    }
+
+    /// The total number of bases in the input file.
+    pub(crate) fn num_bases(&self) -> u64 {
+        self.num_bases
+    }
+
+    /// The number of malformed records in the input file that were skipped.
+    pub(crate) fn num_skipped(&self) -> usize {
+        self.num_skipped
+    }
+
+    /// The number of records in the input file that were excluded by the read filter. These are
+    /// not included in the number of records or bases.
+    pub(crate) fn num_excluded(&self) -> usize {
+        self.num_excluded
+    }
+
+    /// The number of sampled records with uracil (`U`) bases, e.g., direct RNA reads. These bases
+    /// are converted to `T`, so minimap2 can overlap them. Only the records that enter the
+    /// reservoir are checked, so the rest of the input is not scanned for them.
+    pub(crate) fn num_rna(&self) -> usize {
+        self.num_rna
+    }
+
+    /// The number of records in the input file whose low-quality ends were trimmed, including those
+    /// that were trimmed entirely, and so could not be sampled.
+    pub(crate) fn num_trimmed(&self) -> usize {
+        self.num_trimmed
+    }
+
+    /// The number of bases trimmed from the records in the input file.
+    pub(crate) fn num_trimmed_bases(&self) -> u64 {
+        self.num_trimmed_bases
+    }
+
+    /// Write the sampled records to FASTA files, split into groups of the given sizes.
+    ///
+    /// Records are assigned to the groups randomly, and each group is written in the same order
+    /// the records appear in the input file. If there are fewer sampled records than requested, the
+    /// later groups will be smaller than requested.
+    ///
+    /// The spill file is removed once all groups have been written.
+    ///
+    /// # Returns
+    ///
+    /// The total number of bases written to each group, and a summary of the GC content of all
+    /// written records.
+    pub(crate) fn write_groups(
+        self,
+        groups: &[(usize, &Path)],
+    ) -> io::Result<(Vec<usize>, Option<GcSummary>)> {
+        self.write_groups_with_adapters(groups, None)
+            .map(|(sum_lens, gc, _)| (sum_lens, gc))
+    }
+
+    /// Write the sampled records to FASTA files, as with [`Sample::write_groups`], removing any
+    /// adapters found by `adapters` from them first - see [`AdapterScan`].
+    ///
+    /// # Returns
+    ///
+    /// The total number of bases written to each group, a summary of the GC content of all
+    /// written records, and how many of them had adapters removed, if they were scanned.
+    pub(crate) fn write_groups_with_adapters(
+        self,
+        groups: &[(usize, &Path)],
+        adapters: Option<&AdapterScan>,
+    ) -> io::Result<(Vec<usize>, Option<GcSummary>, Option<AdapterSummary>)> {
+        // the entries are in random order, so each group is the next run of them, and a record's
+        // group is found from its index by binary search of where the groups end
+        let group_ends: Vec<usize> = groups
+            .iter()
+            .scan(0, |end, (size, _)| {
+                *end += size;
+                Some(*end)
+            })
+            .collect();
+        let num_assigned = group_ends
+            .last()
+            .map_or(0, |&end| end.min(self.entries.len()));
+        let num_assigned = u32::try_from(num_assigned).map_err(|_| {
+            io::Error::other(format!("cannot split {num_assigned} records into groups"))
+        })?;
+        // sort the indices by offset so that we read through the spill file sequentially. These
+        // take 4 bytes per record, rather than copying the entries
+        let mut order: Vec<u32> = (0..num_assigned).collect();
+        order.sort_unstable_by_key(|&i| self.entries[i as usize].offset);
+
+        let mut writers = groups
+            .iter()
+            .map(|(_, path)| File::create(path).map(BufWriter::new))
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut sum_lens = vec![0; groups.len()];
+        let mut gc = GcCounter::default();
+        let mut adapter_summary = adapters.map(|_| AdapterSummary::default());
+
+        let mut reader = File::open(&self.spill_path).map(BufReader::new)?;
+        let mut pos = 0;
+        let mut buf = Vec::new();
+        for i in order {
+            let Entry {
+                offset,
+                size,
+                seq_len,
+                ..
+            } = self.entries[i as usize];
+            let group = group_of(&group_ends, i as usize);
+            reader.seek_relative((offset - pos) as i64)?;
+            buf.resize(size as usize, 0);
+            reader.read_exact(&mut buf)?;
+            // records are spilled as `>id\nseq\n`
+            let (header, seq) = buf.split_at(buf.len() - 1 - seq_len);
+            let mut seq = &seq[..seq_len];
+            if let (Some(adapters), Some(summary)) = (adapters, adapter_summary.as_mut()) {
+                let cleaned = adapters.clean(seq);
+                summary.add(&cleaned);
+                seq = &seq[cleaned.range];
+            }
+            writers[group].write_all(header)?;
+            writers[group].write_all(seq)?;
+            writers[group].write_all(b"\n")?;
+            sum_lens[group] += seq.len();
+            gc.add(seq);
+            pos = offset + size;
+        }
+
+        for writer in writers.iter_mut() {
+            writer.flush()?;
+        }
+        std::fs::remove_file(&self.spill_path)?;
+
+        Ok((sum_lens, gc.summary(), adapter_summary))
+    }
+}
+
+/// The group of the record at `index` of the sample, where `group_ends` are the cumulative sizes
+/// of the groups.
+fn group_of(group_ends: &[usize], index: usize) -> usize {
+    group_ends.partition_point(|&end| end <= index)
+}
+
+/// Randomly sample (up to) `k` records from `input` in a single pass.
+///
+/// The records entering the reservoir are written to `spill_path`, which is removed when the
+/// sample is written out with [`Sample::write_groups`].
+///
+/// # Arguments
+///
+/// * `input`: The file to sample records from.
+/// * `k`: The number of records to sample. If the input has fewer records, all are retained.
+/// * `seed`: An optional seed for the random number generator.
+/// * `spill_path`: The file to write candidate records to.
+/// * `max_bad_records`: The maximum number of malformed records to skip. If `None`, a malformed
+///   record is an error.
+/// * `filter`: Which records can be sampled. Other records are skipped, and not counted.
+/// * `trim`: How to trim the low-quality ends of the records, if at all. Records are counted, in
+///   the number of records and bases, before they are trimmed. Records that are trimmed entirely
+///   are not sampled.
+///
+/// Any `U` bases (e.g., in direct RNA reads) are converted to `T` as the records are spilled.
+pub(crate) fn sample_records<P: AsRef<Path>>(
+    input: P,
+    k: usize,
+    seed: Option<u64>,
+    spill_path: &Path,
+    max_bad_records: Option<usize>,
+    filter: &ReadFilter,
+    trim: Option<QualityTrim>,
+) -> io::Result<Sample> {
+    let mut rng = SamplingRng::new(seed, Stream::Sampling);
+
+    let mut heap: BinaryHeap<Entry> = BinaryHeap::with_capacity(k);
+    let mut spill = File::create(spill_path).map(BufWriter::new)?;
+    let mut offset: u64 = 0;
+    let mut num_records: usize = 0;
+    let mut num_bases: u64 = 0;
+    let mut num_excluded: usize = 0;
+    let mut num_trimmed: usize = 0;
+    let mut num_trimmed_bases: u64 = 0;
+    let mut converted = Vec::new();
+
+    let num_skipped = iter_records_with_quality(&input, max_bad_records, |id, seq, qual| {
+        if !filter.allows(id) {
+            num_excluded += 1;
+            return Ok(());
+        }
+        num_records += 1;
+        num_bases += seq.len() as u64;
+        let key = rng.next_u64();
+        let seq = match (trim, qual) {
+            (Some(trim), Some(qual)) => {
+                let range = trim.range(qual);
+                if range.len() < seq.len() {
+                    num_trimmed += 1;
+                    num_trimmed_bases += (seq.len() - range.len()) as u64;
+                }
+                if range.is_empty() {
+                    return Ok(());
+                }
+                &seq[range]
+            }
+            _ => seq,
+        };
+        if heap.len() < k || heap.peek().is_some_and(|max| key < max.key) {
+            let is_rna = seq.iter().any(|b| matches!(b, b'U' | b'u'));
+            spill.write_all(b">")?;
+            spill.write_all(id)?;
+            spill.write_all(b"\n")?;
+            if is_rna {
+                converted.clear();
+                converted.extend(seq.iter().map(|&b| match b {
+                    b'U' => b'T',
+                    b'u' => b't',
+                    b => b,
+                }));
+                spill.write_all(&converted)?;
+            } else {
+                spill.write_all(seq)?;
+            }
+            spill.write_all(b"\n")?;
+            let size = (id.len() + seq.len() + 3) as u64;
+
+            if heap.len() == k {
+                heap.pop();
+            }
+            heap.push(Entry {
+                key,
+                offset,
+                size,
+                seq_len: seq.len(),
+                is_rna,
+            });
+            offset += size;
+        }
+
+        Ok(())
+    })?;
+
+    spill.flush()?;
+
+    if num_records == 0 && num_excluded > 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("All {num_excluded} reads in the file were excluded by their IDs"),
+        ));
+    } else if num_records == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Is the file empty?",
+        ));
+    }
+
+    let entries = heap.into_sorted_vec();
+    let num_rna = entries.iter().filter(|entry| entry.is_rna).count();
+    Ok(Sample {
+        num_records,
+        num_bases,
+        num_skipped,
+        num_excluded,
+        num_rna,
+        num_trimmed,
+        num_trimmed_bases,
+        entries,
+        spill_path: spill_path.to_path_buf(),
+    })
+}
+
+/// Count the records in `input` allowed by `filter`, without sampling any of them.
+///
+/// This is needed to turn a [fraction of the reads][resolve_fraction] into a number of them before
+/// they are sampled. See [`count_records_and_bases`] for `validate` and `cache`.
+pub(crate) fn count_records<P: AsRef<Path>>(
+    input: P,
+    max_bad_records: Option<usize>,
+    filter: &ReadFilter,
+    validate: bool,
+    cache: bool,
+) -> io::Result<usize> {
+    count_records_and_bases(input, max_bad_records, filter, validate, cache)
+        .map(|(num_records, _)| num_records)
+}
+
+/// Count the records in `input` allowed by `filter`, and their total number of bases, without
+/// sampling any of them.
+///
+/// If `validate` is `true`, every record is parsed, so a malformed record is an error (or skipped,
+/// as when sampling). Otherwise, FASTQ/FASTA records are only [scanned][scan_records] for their IDs
+/// and lengths, and malformed records are counted like any other.
+///
+/// If `cache` is `true`, the counts are [cached][crate::count_cache] in a sidecar file next to
+/// `input`, and the cached counts are used if `input` has not changed since. Counts are only
+/// cached when every record is counted without validating them.
+pub(crate) fn count_records_and_bases<P: AsRef<Path>>(
+    input: P,
+    max_bad_records: Option<usize>,
+    filter: &ReadFilter,
+    validate: bool,
+    cache: bool,
+) -> io::Result<(usize, u64)> {
+    let input = input.as_ref();
+    let cache = cache && !validate && !filter.is_active();
+    if cache {
+        if let Some(counts) = count_cache::read(input) {
+            debug!(
+                "Using the read count cached in {}",
+                count_cache::sidecar_path(input).display()
+            );
+            return Ok(counts);
+        }
+    }
+
+    let mut num_records = 0;
+    let mut num_bases = 0;
+    let mut count = |id: &[u8], seq_len: usize| {
+        if filter.allows(id) {
+            num_records += 1;
+            num_bases += seq_len as u64;
+        }
+        Ok(())
+    };
+    if validate {
+        iter_records(input, max_bad_records, |id, seq| count(id, seq.len()))?;
+    } else {
+        scan_records(input, max_bad_records, count)?;
+    }
+    if cache {
+        count_cache::write(input, (num_records, num_bases));
+    }
+    Ok((num_records, num_bases))
+}
+
+/// The number of records that is `fraction` of `num_records`, rounded to the nearest record, but at
+/// least 1.
+pub(crate) fn resolve_fraction(fraction: f32, num_records: usize) -> usize {
+    ((f64::from(fraction) * num_records as f64).round() as usize).max(1)
+}
+
+/// Check that a fraction of the reads to sample, given as `option`, is greater than 0 and at most 1.
+pub(crate) fn validate_fraction(option: &'static str, fraction: Option<f32>) -> crate::Result<()> {
+    match fraction {
+        Some(fraction) if !(fraction > 0.0 && fraction <= 1.0) => {
+            Err(LrgeError::invalid_configuration(
+                option,
+                format!("must be greater than 0 and at most 1, but got {fraction}"),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::io::Write;
+
+    fn write_fastq(n: usize) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        for i in 0..n {
+            let seq = "A".repeat(i + 1);
+            let qual = "!".repeat(i + 1);
+            writeln!(file, "@read{i}\n{seq}\n+\n{qual}").unwrap();
+        }
+        file.flush().unwrap();
+        file
+    }
+
+    fn read_ids(path: &Path) -> Vec<String> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .filter_map(|l| l.strip_prefix('>').map(|s| s.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_sample_records_counts_input() {
+        let input = write_fastq(100);
+        let dir = tempfile::tempdir().unwrap();
+        let sample = sample_records(
+            input.path(),
+            10,
+            Some(1),
+            &dir.path().join("spill.fa"),
+            None,
+            &ReadFilter::default(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(sample.num_records(), 100);
+        assert_eq!(sample.num_bases(), 5050);
+        assert_eq!(sample.entries.len(), 10);
+    }
+
+    #[test]
+    fn test_sample_records_fewer_than_requested() {
+        let input = write_fastq(5);
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out.fa");
+        let sample = sample_records(
+            input.path(),
+            10,
+            None,
+            &dir.path().join("spill.fa"),
+            None,
+            &ReadFilter::default(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(sample.num_records(), 5);
+        let (lens, gc) = sample.write_groups(&[(10, &out)]).unwrap();
+        assert_eq!(lens, vec![1 + 2 + 3 + 4 + 5]);
+        // the reads are all A's
+        let gc = gc.unwrap();
+        assert_eq!(gc.overall, 0.0);
+        assert_eq!(gc.num_reads, 5);
+        assert_eq!(read_ids(&out).len(), 5);
+    }
+
+    #[test]
+    fn test_sample_records_groups_are_disjoint() {
+        let input = write_fastq(200);
+        let dir = tempfile::tempdir().unwrap();
+        let spill = dir.path().join("spill.fa");
+        let first = dir.path().join("first.fa");
+        let second = dir.path().join("second.fa");
+        let sample = sample_records(
+            input.path(),
+            30,
+            Some(42),
+            &spill,
+            None,
+            &ReadFilter::default(),
+            None,
+        )
+        .unwrap();
+        sample.write_groups(&[(20, &first), (10, &second)]).unwrap();
+
+        let first_ids: HashSet<_> = read_ids(&first).into_iter().collect();
+        let second_ids: HashSet<_> = read_ids(&second).into_iter().collect();
+        assert_eq!(first_ids.len(), 20);
+        assert_eq!(second_ids.len(), 10);
+        assert!(first_ids.is_disjoint(&second_ids));
+        assert!(!spill.exists());
+    }
+
+    #[test]
+    fn test_sample_records_with_seed() {
+        let input = write_fastq(1000);
+        let dir = tempfile::tempdir().unwrap();
+        let out1 = dir.path().join("out1.fa");
+        let out2 = dir.path().join("out2.fa");
+
+        for out in [&out1, &out2] {
+            let sample = sample_records(
+                input.path(),
+                10,
+                Some(42),
+                &dir.path().join("spill.fa"),
+                None,
+                &ReadFilter::default(),
+                None,
+            )
+            .unwrap();
+            sample.write_groups(&[(10, out)]).unwrap();
+        }
+
+        assert_eq!(read_ids(&out1), read_ids(&out2));
+    }
+
+    #[test]
+    fn test_sample_records_empty_file() {
+        let input = tempfile::NamedTempFile::new().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let result = sample_records(
+            input.path(),
+            10,
+            None,
+            &dir.path().join("spill.fa"),
+            None,
+            &ReadFilter::default(),
+            None,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_sample_records_skips_bad_records() {
+        let mut input = write_fastq(20);
+        write!(input, "@truncated\nACGT\n+\n!!").unwrap();
+        input.flush().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let spill = dir.path().join("spill.fa");
+
+        assert!(sample_records(
+            input.path(),
+            10,
+            None,
+            &spill,
+            None,
+            &ReadFilter::default(),
+            None
+        )
+        .is_err());
+        assert!(sample_records(
+            input.path(),
+            10,
+            None,
+            &spill,
+            Some(0),
+            &ReadFilter::default(),
+            None,
+        )
+        .is_err());
+
+        let sample = sample_records(
+            input.path(),
+            10,
+            None,
+            &spill,
+            Some(1),
+            &ReadFilter::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(sample.num_records(), 20);
+        assert_eq!(sample.num_skipped(), 1);
+        assert_eq!(sample.num_bases(), 210);
+    }
+
+    #[test]
+    fn test_sample_records_converts_uracil() {
+        let mut input = tempfile::NamedTempFile::new().unwrap();
+        writeln!(input, "@rna\nACGUuacg\n+\n!!!!!!!!\n@dna\nACGT\n+\n!!!!").unwrap();
+        input.flush().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out.fa");
+
+        let sample = sample_records(
+            input.path(),
+            10,
+            None,
+            &dir.path().join("spill.fa"),
+            None,
+            &ReadFilter::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(sample.num_rna(), 1);
+        sample.write_groups(&[(10, &out)]).unwrap();
+        let written = std::fs::read_to_string(&out).unwrap();
+        assert!(written.contains(">rna\nACGTtacg\n"));
+        assert!(written.contains(">dna\nACGT\n"));
+    }
+
+    #[test]
+    fn test_sample_records_counts_uracil_of_sampled_records() {
+        let mut input = tempfile::NamedTempFile::new().unwrap();
+        for i in 0..100 {
+            writeln!(input, "@rna{i}\nACGU\n+\n!!!!").unwrap();
+        }
+        input.flush().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        let sample = sample_records(
+            input.path(),
+            5,
+            Some(1),
+            &dir.path().join("spill.fa"),
+            None,
+            &ReadFilter::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(sample.num_records(), 100);
+        assert_eq!(sample.num_rna(), 5);
+    }
+
+    #[test]
+    fn test_sample_records_with_filter() {
+        let input = write_fastq(100);
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out.fa");
+        let mut ids = tempfile::NamedTempFile::new().unwrap();
+        writeln!(ids, "read3\nread7\nread11\nmissing").unwrap();
+        let filter = ReadFilter::new(Some(ids.path()), None).unwrap();
+
+        let sample = sample_records(
+            input.path(),
+            10,
+            None,
+            &dir.path().join("spill.fa"),
+            None,
+            &filter,
+            None,
+        )
+        .unwrap();
+        assert_eq!(sample.num_records(), 3);
+        assert_eq!(sample.num_excluded(), 97);
+        assert_eq!(sample.num_bases(), 4 + 8 + 12);
+        sample.write_groups(&[(10, &out)]).unwrap();
+        assert_eq!(read_ids(&out), vec!["read3", "read7", "read11"]);
+        assert_eq!(
+            count_records(input.path(), None, &filter, false, false).unwrap(),
+            3
+        );
+        assert_eq!(
+            count_records(input.path(), None, &filter, true, false).unwrap(),
+            3
+        );
+
+        let mut ids = tempfile::NamedTempFile::new().unwrap();
+        writeln!(ids, "missing").unwrap();
+        let filter = ReadFilter::new(Some(ids.path()), None).unwrap();
+        let result = sample_records(
+            input.path(),
+            10,
+            None,
+            &dir.path().join("spill.fa"),
+            None,
+            &filter,
+            None,
+        );
+        assert!(result
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("All 100 reads in the file were excluded"));
+    }
+
+    #[test]
+    fn test_sample_records_trims_quality() {
+        let mut input = tempfile::NamedTempFile::new().unwrap();
+        // qualities of ! are 0 and of I are 40
+        writeln!(
+            input,
+            "@r0\nAAAACCCCGGGG\n+\n!!!!IIIIIIII\n@r1\nACGT\n+\n!!!!\n@r2\nTTTT\n+\nIIII"
+        )
+        .unwrap();
+        input.flush().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out.fa");
+
+        let sample = sample_records(
+            input.path(),
+            10,
+            None,
+            &dir.path().join("spill.fa"),
+            None,
+            &ReadFilter::default(),
+            Some(QualityTrim::new(10).window_size(1)),
+        )
+        .unwrap();
+        // reads are counted before they are trimmed
+        assert_eq!(sample.num_records(), 3);
+        assert_eq!(sample.num_bases(), 20);
+        assert_eq!(sample.num_trimmed(), 2);
+        assert_eq!(sample.num_trimmed_bases(), 8);
+        let (sum_lens, _) = sample.write_groups(&[(10, &out)]).unwrap();
+        assert_eq!(sum_lens, vec![12]);
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            ">r0\nCCCCGGGG\n>r2\nTTTT\n"
+        );
+    }
+
+    #[test]
+    fn test_write_groups_with_adapters() {
+        let adapter = crate::adapter::ONT_ADAPTERS[0];
+        let clean: String = "ACGGTCATTGCA".repeat(50);
+        let mut input = tempfile::NamedTempFile::new().unwrap();
+        writeln!(input, ">r0\n{adapter}{clean}\n>r1\n{clean}").unwrap();
+        input.flush().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out.fa");
+
+        let sample = sample_records(
+            input.path(),
+            10,
+            None,
+            &dir.path().join("spill.fa"),
+            None,
+            &ReadFilter::default(),
+            None,
+        )
+        .unwrap();
+        let scan = AdapterScan::new([adapter]);
+        let (sum_lens, _, summary) = sample
+            .write_groups_with_adapters(&[(10, &out)], Some(&scan))
+            .unwrap();
+        assert_eq!(sum_lens, vec![2 * clean.len()]);
+        assert_eq!(
+            summary,
+            Some(AdapterSummary {
+                num_reads: 2,
+                num_trimmed: 1,
+                num_split: 0
+            })
+        );
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            format!(">r0\n{clean}\n>r1\n{clean}\n")
+        );
+    }
+
+    #[test]
+    fn test_group_of() {
+        let group_ends = [2, 2, 5];
+        let groups: Vec<usize> = (0..5).map(|i| group_of(&group_ends, i)).collect();
+        assert_eq!(groups, [0, 0, 2, 2, 2]);
+    }
+
+    #[test]
+    fn test_count_records() {
+        let input = write_fastq(20);
+        for validate in [false, true] {
+            assert_eq!(
+                count_records(input.path(), None, &ReadFilter::default(), validate, false).unwrap(),
+                20
+            );
+            // the reads are 1 to 20 bases long
+            assert_eq!(
+                count_records_and_bases(
+                    input.path(),
+                    None,
+                    &ReadFilter::default(),
+                    validate,
+                    false
+                )
+                .unwrap(),
+                (20, 210)
+            );
+        }
+    }
+
+    #[test]
+    fn test_count_records_validate() {
+        let mut input = write_fastq(2);
+        // the quality of the last record is truncated
+        write!(input, "@read2\nACG\n+\n!").unwrap();
+        input.flush().unwrap();
+        let filter = ReadFilter::default();
+        assert_eq!(
+            count_records(input.path(), None, &filter, false, false).unwrap(),
+            3
+        );
+        assert!(count_records(input.path(), None, &filter, true, false).is_err());
+        assert_eq!(
+            count_records(input.path(), Some(1), &filter, true, false).unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_count_records_cache() {
+        let input = write_fastq(20);
+        let filter = ReadFilter::default();
+        // validated counts are not cached
+        assert_eq!(
+            count_records(input.path(), None, &filter, true, true).unwrap(),
+            20
+        );
+        assert_eq!(count_cache::read(input.path()), None);
+
+        assert_eq!(
+            count_records(input.path(), None, &filter, false, true).unwrap(),
+            20
+        );
+        assert_eq!(count_cache::read(input.path()), Some((20, 210)));
+        std::fs::remove_file(count_cache::sidecar_path(input.path())).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_fraction() {
+        assert_eq!(resolve_fraction(0.1, 1000), 100);
+        assert_eq!(resolve_fraction(0.01, 1000), 10);
+        assert_eq!(resolve_fraction(1.0, 1000), 1000);
+        // at least one record
+        assert_eq!(resolve_fraction(0.0001, 1000), 1);
+    }
+
+    #[test]
+    fn test_validate_fraction() {
+        assert!(validate_fraction("fraction", None).is_ok());
+        assert!(validate_fraction("fraction", Some(0.5)).is_ok());
+        assert!(validate_fraction("fraction", Some(1.0)).is_ok());
+        assert!(validate_fraction("fraction", Some(0.0)).is_err());
+        assert!(validate_fraction("fraction", Some(1.5)).is_err());
+        assert!(validate_fraction("fraction", Some(f32::NAN)).is_err());
+    }
+}
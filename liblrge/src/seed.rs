@@ -0,0 +1,106 @@
+//! Seeds for the random sampling of reads.
+//!
+//! A seed can be a number or any string - such as a sample name - so that a pipeline gets
+//! reproducible, but different, samples of reads for each of its samples without keeping a table
+//! of seeds. A string that is a valid `u64` is that number, so `"42"` and `42` are the same seed.
+//! Any other string is hashed with [FNV-1a][fnv], which, unlike the hashers in the standard
+//! library, is stable across versions and platforms.
+//!
+//! # Reproducibility
+//!
+//! The same seed samples the same reads from the same input across liblrge versions and
+//! platforms. The random numbers come from ChaCha12, keyed from the seed with PCG32 as in `rand`
+//! 0.9's `StdRng::seed_from_u64`, but implemented in liblrge so that upgrading `rand` cannot
+//! change them. Each use of randomness - sampling the reads and shuffling them for a
+//! [rarefaction curve][crate::rarefaction] - draws from its own ChaCha stream of the seed. Each
+//! read in the input, in order, is given a random 64-bit key, and the reads with the smallest keys
+//! are sampled.
+//!
+//! [fnv]: https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function
+//!
+//! # Examples
+//!
+//! ```
+//! use liblrge::seed::{seed_from_str, IntoSeed};
+//!
+//! assert_eq!(seed_from_str("42"), 42);
+//! assert_eq!("sample1".into_seed(), Some(seed_from_str("sample1")));
+//! assert_ne!(seed_from_str("sample1"), seed_from_str("sample2"));
+//! ```
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// The seed for a string. If the string is a valid `u64`, it is that number, otherwise it is the
+/// FNV-1a hash of the string.
+///
+/// # Examples
+///
+/// ```
+/// use liblrge::seed::seed_from_str;
+///
+/// assert_eq!(seed_from_str("6"), 6);
+/// assert_eq!(seed_from_str(""), 0xcbf29ce484222325);
+/// assert_eq!(seed_from_str("a"), 0xaf63dc4c8601ec8c);
+/// ```
+pub fn seed_from_str(s: &str) -> u64 {
+    s.parse().unwrap_or_else(|_| {
+        s.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+        })
+    })
+}
+
+/// A value that can be used as the seed of a strategy. `None` means the seed is randomly
+/// generated.
+pub trait IntoSeed {
+    /// The seed, or `None` if it should be randomly generated.
+    fn into_seed(self) -> Option<u64>;
+}
+
+impl IntoSeed for Option<u64> {
+    fn into_seed(self) -> Option<u64> {
+        self
+    }
+}
+
+impl IntoSeed for u64 {
+    fn into_seed(self) -> Option<u64> {
+        Some(self)
+    }
+}
+
+impl IntoSeed for &str {
+    fn into_seed(self) -> Option<u64> {
+        Some(seed_from_str(self))
+    }
+}
+
+impl IntoSeed for String {
+    fn into_seed(self) -> Option<u64> {
+        Some(seed_from_str(&self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_from_str() {
+        assert_eq!(seed_from_str("18446744073709551615"), u64::MAX);
+        // too big to be a u64, so it is hashed
+        assert_ne!(seed_from_str("18446744073709551616"), u64::MAX);
+        assert_ne!(seed_from_str("-1"), u64::MAX);
+        assert_eq!(seed_from_str("foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn test_into_seed() {
+        assert_eq!(None.into_seed(), None);
+        assert_eq!(Some(7).into_seed(), Some(7));
+        assert_eq!(7.into_seed(), Some(7));
+        assert_eq!("7".into_seed(), Some(7));
+        assert_eq!(String::from("sample").into_seed(), "sample".into_seed());
+    }
+}
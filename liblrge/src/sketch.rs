@@ -0,0 +1,566 @@
+//! A strategy that finds overlaps between reads from sketches of their k-mers, rather than by
+//! aligning them - i.e., a very fast, but less accurate, first pass.
+//!
+//! Each sampled read is reduced to a [FracMinHash][fmh] sketch - the hashes of its canonical
+//! k-mers that fall in the smallest `1 / scale` of the hash space. Two reads that overlap share the
+//! k-mers in the overlap, so they share roughly `overlap length / scale` hashes (fewer with
+//! sequencing errors), while unrelated reads share (almost) none. Two reads are taken to overlap if
+//! they share at least [`min_shared_hashes`][Builder::min_shared_hashes] hashes, and the genome
+//! size estimate of each read is calculated from its number of overlaps, as in the
+//! [`AvaStrategy`][crate::AvaStrategy].
+//!
+//! As no alignments are done, the overlaps cannot be filtered (e.g., for internal matches) and no
+//! overlaps file is written. The smallest overlap that can be found depends on the error rate of
+//! the reads, so the estimate is less accurate than those of the other strategies - it is best
+//! used to get a rough idea of the genome size quickly, e.g., to choose the number of reads to
+//! sample for a more accurate estimate.
+//!
+//! [fmh]: https://doi.org/10.1093/bioinformatics/btac237
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use liblrge::{Estimate, SketchStrategy};
+//! use liblrge::sketch::{Builder, DEFAULT_SKETCH_NUM_READS};
+//!
+//! let input = "path/to/reads.fastq";
+//! let mut strategy = Builder::new()
+//!    .num_reads(DEFAULT_SKETCH_NUM_READS)
+//!    .threads(4)
+//!    .seed(Some(42))  // makes the estimate reproducible
+//!    .build(input);
+//!
+//! let est_result = strategy.estimate(true, None, None).expect("Failed to generate estimate");
+//! let estimate = est_result.estimate;
+//! ```
+//!
+//! The sampled reads are written to a temporary directory, as `reads.fa`, along with the per-read
+//! estimates, `estimates.tsv`. You can set your own temporary directory by using the
+//! [`Builder::tmpdir`] method, or have the strategy create a directory inside it, which is removed
+//! when the strategy is dropped, with [`Builder::create_tmpdir`].
+mod builder;
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use log::{debug, info, warn};
+use rayon::prelude::*;
+
+pub use self::builder::Builder;
+use crate::adapter::{log_adapter_summary, AdapterScan, AdapterSummary};
+use crate::artifact::{Artifact, Artifacts};
+use crate::error::LrgeError;
+use crate::estimate::{
+    check_no_mapping_fraction, write_per_read_estimates, PerReadEstimate, PerReadEstimator,
+    RawEstimates, ESTIMATES_FILENAME,
+};
+use crate::gc::GcSummary;
+use crate::hash::{HashMap, HashSet};
+use crate::read_filter::ReadFilter;
+use crate::stream::EstimateSink;
+use crate::tmpdir::ManagedTmpdir;
+use crate::trim::QualityTrim;
+use crate::{sample, Estimate};
+
+/// The default number of reads to use in the sketch strategy.
+pub const DEFAULT_SKETCH_NUM_READS: usize = 10_000;
+/// The default length of the k-mers that are sketched.
+pub const DEFAULT_KMER_SIZE: usize = 15;
+/// The default fraction (`1 / scale`) of the k-mers that are kept in a sketch.
+pub const DEFAULT_SCALE: u64 = 10;
+/// The default number of hashes two reads must share to overlap.
+pub const DEFAULT_MIN_SHARED_HASHES: usize = 3;
+
+/// A strategy that finds overlaps between the reads from sketches of their k-mers.
+///
+/// See the [module-level documentation](crate::sketch) for more information and examples.
+pub struct SketchStrategy {
+    /// Path to the FASTQ file.
+    input: PathBuf,
+    /// The number of reads to use in the strategy.
+    num_reads: usize,
+    /// The fraction of the input to use, instead of `num_reads`.
+    read_fraction: Option<f32>,
+    /// The length of the k-mers that are sketched.
+    kmer_size: usize,
+    /// A sketch keeps `1 / scale` of the k-mers.
+    scale: u64,
+    /// The number of hashes two reads must share to overlap.
+    min_shared_hashes: usize,
+    /// The directory to which all intermediate files will be written.
+    tmpdir: PathBuf,
+    /// The directory created inside `tmpdir` for the intermediate files, if there is one.
+    managed_tmpdir: Option<ManagedTmpdir>,
+    /// Number of threads to sketch and compare the reads with.
+    threads: usize,
+    /// A caller-provided thread pool to sketch and compare the reads in.
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+    /// The (optional) seed to use for randomly selecting reads.
+    seed: Option<u64>,
+    /// The maximum fraction of reads allowed to have no overlaps.
+    max_no_mapping_fraction: Option<f32>,
+    /// The maximum number of malformed input records to skip. If `None`, they are an error.
+    max_bad_records: Option<usize>,
+    /// Whether every record is parsed, and so validated, when the reads are counted.
+    validate_records: bool,
+    /// Whether the read count is cached in a sidecar file next to the input.
+    cache_count: bool,
+    /// A file of the IDs of the only reads that can be sampled.
+    allowlist: Option<PathBuf>,
+    /// A file of the IDs of reads that cannot be sampled.
+    blocklist: Option<PathBuf>,
+    /// How to trim the low-quality ends of the sampled reads, if at all.
+    quality_trim: Option<QualityTrim>,
+    /// The scan for adapters to remove from the sampled reads, if any.
+    adapters: Option<AdapterScan>,
+    /// Where the intermediate files are written, and which are kept.
+    artifacts: Artifacts,
+    /// Passes each per-read estimate to the caller's callback, if there is one.
+    on_estimate: Option<EstimateSink>,
+    /// Calculates the genome size estimate for each read from its overlaps.
+    estimator: Arc<dyn PerReadEstimator>,
+    /// The number of reads in the input file. This is only known once the reads are sampled.
+    input_num_reads: Option<u64>,
+    /// The total number of bases in the input file. This is only known once the reads are sampled.
+    input_num_bases: Option<u64>,
+    /// The number of malformed input records skipped. This is only known once the reads are sampled.
+    input_num_skipped: Option<u64>,
+    /// The GC content of the sampled reads. This is only known once the reads are sampled.
+    gc_content: Option<GcSummary>,
+    /// How many sampled reads had adapters removed. This is only known once the reads are sampled.
+    adapter_summary: Option<AdapterSummary>,
+    /// The number of overlaps the estimates were calculated from. This is only known once the
+    /// reads are compared.
+    num_overlaps: Option<u64>,
+    /// The per-read estimates, once the genome size has been estimated.
+    raw_estimates: Option<RawEstimates>,
+}
+
+impl SketchStrategy {
+    /// Create a new `SketchStrategy` with the default settings, using the given input file.
+    ///
+    /// To customise the strategy, use the [`Builder`] interface.
+    pub fn new<P: AsRef<Path>>(input: P) -> Self {
+        Builder::default().build(input)
+    }
+
+    /// The number of reads being sketched.
+    pub fn num_reads(&self) -> usize {
+        self.num_reads
+    }
+
+    /// The path an intermediate file is written to, or `None` if this strategy does not write it.
+    /// Only the sampled [reads][Artifact::Reads] are written. The path is returned even if the file
+    /// has not been written yet, or has been removed (see [`Builder::keep_artifact`]).
+    pub fn artifact_path(&self, artifact: Artifact) -> Option<PathBuf> {
+        match artifact {
+            Artifact::Reads => Some(
+                self.artifacts
+                    .path(Artifact::Reads, &self.tmpdir, "reads.fa"),
+            ),
+            Artifact::Target | Artifact::Query | Artifact::Overlaps => None,
+        }
+    }
+
+    /// Count the reads in the input file to turn the fraction of them to use (see
+    /// [`Builder::read_fraction`]) into a [number of reads][Self::num_reads], if a fraction was
+    /// given. This is done when the estimate is generated, but can be done beforehand to see how
+    /// many reads will be sketched - e.g., for a dry run.
+    pub fn resolve_fraction(&mut self) -> crate::Result<()> {
+        let Some(fraction) = self.read_fraction.take() else {
+            return Ok(());
+        };
+        let filter = ReadFilter::new(self.allowlist.as_deref(), self.blocklist.as_deref())?;
+        debug!("Counting reads in input file...");
+        let n_fq_reads = sample::count_records(
+            &self.input,
+            self.max_bad_records,
+            &filter,
+            self.validate_records,
+            self.cache_count,
+        )?;
+        // each read must be compared with at least one other
+        self.num_reads = sample::resolve_fraction(fraction, n_fq_reads).max(2);
+        Ok(())
+    }
+
+    /// The number of threads reads are sketched and compared with.
+    pub fn threads(&self) -> usize {
+        self.thread_pool
+            .as_ref()
+            .map_or(self.threads, |pool| pool.current_num_threads())
+    }
+
+    /// Sketch and compare reads in the given thread pool, rather than building a new one.
+    pub(crate) fn set_thread_pool(&mut self, pool: Arc<rayon::ThreadPool>) {
+        self.thread_pool = Some(pool);
+    }
+
+    /// The directory the intermediate files are written to. If the strategy creates its own
+    /// directory (see [`Builder::create_tmpdir`]), this is the directory it is created in until the
+    /// estimate is generated.
+    pub fn tmpdir(&self) -> &Path {
+        &self.tmpdir
+    }
+
+    /// Set the directory to which all intermediate files will be written.
+    pub(crate) fn set_tmpdir(&mut self, tmpdir: PathBuf) {
+        self.tmpdir = tmpdir;
+    }
+
+    /// Create the strategy's own temporary directory, if it has one, and retain the intermediate
+    /// files that are explicitly kept when it is removed.
+    fn prepare_tmpdir(&mut self) -> crate::Result<()> {
+        let Some(managed) = &mut self.managed_tmpdir else {
+            return Ok(());
+        };
+        self.tmpdir = managed.create(&self.tmpdir)?;
+        let kept: Vec<PathBuf> = self
+            .artifacts
+            .kept()
+            .filter_map(|artifact| self.artifact_path(artifact))
+            .collect();
+        if let Some(managed) = &mut self.managed_tmpdir {
+            kept.into_iter().for_each(|path| managed.retain(path));
+        }
+        Ok(())
+    }
+
+    /// Subsample the reads in the input file to `num_reads`.
+    fn subsample_reads(&mut self) -> crate::Result<PathBuf> {
+        self.resolve_fraction()?;
+        let filter = ReadFilter::new(self.allowlist.as_deref(), self.blocklist.as_deref())?;
+        debug!("Sampling {} reads from input file...", self.num_reads);
+        let spill_file = self.tmpdir.join("sample.fa");
+        let sample = sample::sample_records(
+            &self.input,
+            self.num_reads,
+            self.seed,
+            &spill_file,
+            self.max_bad_records,
+            &filter,
+            self.quality_trim,
+        )?;
+        let n_fq_reads = sample.num_records();
+        self.input_num_reads = Some(n_fq_reads as u64);
+        self.input_num_bases = Some(sample.num_bases());
+        self.input_num_skipped = Some(sample.num_skipped() as u64);
+        debug!(
+            "Found {} reads ({} bases) in input file",
+            n_fq_reads,
+            sample.num_bases()
+        );
+        if filter.is_active() {
+            info!(
+                "Excluded {} reads of the input file by their IDs",
+                sample.num_excluded()
+            );
+        }
+        if sample.num_rna() > 0 {
+            info!(
+                "{} sampled reads contain U bases (direct RNA?); these are converted to T",
+                sample.num_rna()
+            );
+        }
+        if sample.num_trimmed() > 0 {
+            info!(
+                "Trimmed {} low-quality bases from the ends of {} input reads",
+                sample.num_trimmed_bases(),
+                sample.num_trimmed()
+            );
+        }
+
+        if n_fq_reads < self.num_reads {
+            warn!(
+                "Number of reads in input file ({}) is less than the number requested ({})",
+                n_fq_reads, self.num_reads
+            );
+            self.num_reads = n_fq_reads;
+        }
+
+        let out_file = self
+            .artifact_path(Artifact::Reads)
+            .expect("the strategy writes reads");
+        let (_, gc_content, adapter_summary) = sample
+            .write_groups_with_adapters(&[(self.num_reads, &out_file)], self.adapters.as_ref())?;
+        self.gc_content = gc_content;
+        self.adapter_summary = adapter_summary;
+        log_adapter_summary(adapter_summary);
+        debug!("Reads written to: {}", out_file.display());
+
+        Ok(out_file)
+    }
+
+    /// Sketch the reads in `reads_file`, and calculate the genome size estimate for each read from
+    /// the number of reads it shares enough hashes with.
+    fn compare_reads(&self, reads_file: &Path) -> crate::Result<(Vec<PerReadEstimate>, u32, u64)> {
+        let mut reads = Vec::with_capacity(self.num_reads);
+        crate::io::iter_records(reads_file, None, |id, seq| {
+            reads.push((id.to_vec(), seq.to_vec()));
+            Ok(())
+        })?;
+        let mut names = HashSet::with_capacity_and_hasher(reads.len(), Default::default());
+        for (id, _) in &reads {
+            if !names.insert(id.as_slice()) {
+                return Err(LrgeError::DuplicateReadIdentifier {
+                    read_id: String::from_utf8_lossy(id).to_string(),
+                    path: self.input.clone(),
+                });
+            }
+        }
+        drop(names);
+
+        let pool = crate::mapping_pool(self.thread_pool.as_ref(), self.threads)?;
+        let max_hash = u64::MAX / self.scale;
+        debug!("Sketching {} reads...", reads.len());
+        let sketches: Vec<Vec<u64>> = pool.install(|| {
+            reads
+                .par_iter()
+                .map(|(_, seq)| sketch(seq, self.kmer_size, max_hash))
+                .collect()
+        });
+
+        // the reads each hash is in
+        let mut postings: HashMap<u64, Vec<u32>> = HashMap::default();
+        for (i, sketch) in sketches.iter().enumerate() {
+            for &hash in sketch {
+                postings.entry(hash).or_default().push(i as u32);
+            }
+        }
+
+        let sum_len: usize = reads.iter().map(|(_, seq)| seq.len()).sum();
+        let num_others = reads.len().saturating_sub(1);
+        // the smallest overlap that could share enough hashes, if the reads had no errors
+        let overlap_threshold = (self.min_shared_hashes as u64 * self.scale) as u32;
+
+        debug!("Comparing sketches...");
+        let total_overlaps = AtomicU64::new(0);
+        let estimates: Vec<PerReadEstimate> = pool.install(|| {
+            reads
+                .par_iter()
+                .zip(&sketches)
+                .enumerate()
+                .map_init(HashMap::default, |shared, (i, ((id, seq), sketch))| {
+                    shared.clear();
+                    for hash in sketch {
+                        for &other in &postings[hash] {
+                            if other as usize != i {
+                                *shared.entry(other).or_insert(0usize) += 1;
+                            }
+                        }
+                    }
+                    let num_overlaps = shared
+                        .values()
+                        .filter(|&&n| n >= self.min_shared_hashes)
+                        .count();
+                    total_overlaps.fetch_add(num_overlaps as u64, Ordering::Relaxed);
+                    let estimate = if num_overlaps == 0 {
+                        f32::INFINITY
+                    } else {
+                        let avg_read_len = (sum_len - seq.len()) as f32 / num_others as f32;
+                        self.estimator.estimate(
+                            seq.len(),
+                            avg_read_len,
+                            num_others,
+                            num_overlaps,
+                            overlap_threshold,
+                        )
+                    };
+                    PerReadEstimate {
+                        read_id: id.clone(),
+                        estimate,
+                        mapped: num_overlaps > 0,
+                    }
+                })
+                .collect()
+        });
+
+        if let Some(sink) = &self.on_estimate {
+            estimates.iter().for_each(|est| sink.send(est));
+        }
+
+        let no_mapping_count = estimates.iter().filter(|e| !e.mapped).count() as u32;
+        if no_mapping_count > 0 {
+            let percent = (no_mapping_count as f32 / estimates.len() as f32) * 100.0;
+            info!(
+                "{} ({:.2}%) read(s) did not share enough hashes with any other reads",
+                no_mapping_count, percent
+            );
+        } else {
+            debug!("All reads had at least one overlap");
+        }
+
+        // each overlap is counted by both of its reads
+        let num_overlaps = total_overlaps.into_inner().div_ceil(2);
+
+        Ok((estimates, no_mapping_count, num_overlaps))
+    }
+}
+
+/// The hashes of the canonical k-mers of `seq` that are no greater than `max_hash`, sorted and
+/// without duplicates. K-mers containing a base other than `A`, `C`, `G`, or `T` are skipped.
+pub(crate) fn sketch(seq: &[u8], k: usize, max_hash: u64) -> Vec<u64> {
+    debug_assert!((1..=32).contains(&k));
+    let shift = 2 * (k as u64 - 1);
+    let mask = if k == 32 {
+        u64::MAX
+    } else {
+        (1 << (2 * k)) - 1
+    };
+    let mut forward = 0u64;
+    let mut reverse = 0u64;
+    let mut len = 0;
+    let mut hashes = Vec::new();
+
+    for &base in seq {
+        let code = match base {
+            b'A' | b'a' => 0,
+            b'C' | b'c' => 1,
+            b'G' | b'g' => 2,
+            b'T' | b't' => 3,
+            _ => {
+                len = 0;
+                continue;
+            }
+        };
+        forward = ((forward << 2) | code) & mask;
+        reverse = (reverse >> 2) | ((3 - code) << shift);
+        len += 1;
+        if len >= k {
+            let hash = mix(forward.min(reverse));
+            if hash <= max_hash {
+                hashes.push(hash);
+            }
+        }
+    }
+
+    hashes.sort_unstable();
+    hashes.dedup();
+    hashes
+}
+
+/// Scramble the bits of a k-mer, so that the kept hashes are a random sample of the k-mers. This
+/// is the finaliser of MurmurHash3, which is invertible, so distinct k-mers have distinct hashes.
+fn mix(mut x: u64) -> u64 {
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    x ^ (x >> 33)
+}
+
+impl Estimate for SketchStrategy {
+    fn input_num_reads(&self) -> Option<u64> {
+        self.input_num_reads
+    }
+
+    fn input_num_bases(&self) -> Option<u64> {
+        self.input_num_bases
+    }
+
+    fn input_num_skipped(&self) -> Option<u64> {
+        self.input_num_skipped
+    }
+
+    fn gc_content(&self) -> Option<GcSummary> {
+        self.gc_content.clone()
+    }
+
+    fn adapter_summary(&self) -> Option<AdapterSummary> {
+        self.adapter_summary
+    }
+
+    fn num_overlaps(&self) -> Option<u64> {
+        self.num_overlaps
+    }
+
+    fn raw_estimates(&self) -> Option<&RawEstimates> {
+        self.raw_estimates.as_ref()
+    }
+
+    fn retain_raw_estimates(&mut self, raw: RawEstimates) {
+        self.raw_estimates = Some(raw);
+    }
+
+    fn generate_estimates(&mut self) -> crate::Result<RawEstimates> {
+        let started = Instant::now();
+        self.prepare_tmpdir()?;
+        let reads_file = self.subsample_reads()?;
+        let (estimates, no_mapping_count, num_overlaps) = self.compare_reads(&reads_file)?;
+        self.num_overlaps = Some(num_overlaps);
+
+        write_per_read_estimates(&self.tmpdir.join(ESTIMATES_FILENAME), &estimates)?;
+        self.artifacts.clean_up(Artifact::Reads, &reads_file)?;
+        check_no_mapping_fraction(
+            no_mapping_count,
+            estimates.len(),
+            self.max_no_mapping_fraction,
+        )?;
+        Ok(RawEstimates::new(
+            estimates,
+            no_mapping_count,
+            started.elapsed(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REVCOMP: fn(&[u8]) -> Vec<u8> = |seq| {
+        seq.iter()
+            .rev()
+            .map(|b| match b {
+                b'A' => b'T',
+                b'C' => b'G',
+                b'G' => b'C',
+                _ => b'A',
+            })
+            .collect()
+    };
+
+    #[test]
+    fn test_sketch_is_canonical() {
+        let seq = b"ACGTTGCAAGGCTTACGATCGATCGGATCCAGT";
+        let sketch = sketch(seq, 5, u64::MAX);
+        assert_eq!(super::sketch(&REVCOMP(seq), 5, u64::MAX), sketch);
+        assert_eq!(
+            super::sketch(&seq.to_ascii_lowercase(), 5, u64::MAX),
+            sketch
+        );
+        assert!(sketch.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_sketch_skips_ambiguous_bases() {
+        assert_eq!(sketch(b"ACGTNACGT", 5, u64::MAX), Vec::<u64>::new());
+        // ACGTA and CGTAC are the reverse complements of TACGT and GTACG
+        assert_eq!(sketch(b"ACGTACGT", 5, u64::MAX).len(), 2);
+        assert_eq!(sketch(b"ACG", 5, u64::MAX), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn test_sketch_scale() {
+        let seq: Vec<u8> = (0..10_000u64)
+            .map(|i| b"ACGT"[(mix(i) % 4) as usize])
+            .collect();
+        let full = sketch(&seq, 21, u64::MAX);
+        let scaled = sketch(&seq, 21, u64::MAX / 10);
+        assert!(scaled.iter().all(|h| full.contains(h)));
+        // about a tenth of the k-mers are kept
+        assert!((800..1200).contains(&scaled.len()), "{}", scaled.len());
+    }
+
+    #[test]
+    fn test_sketch_of_32mers() {
+        let seq = b"ACGTTGCAAGGCTTACGATCGATCGGATCCAGT";
+        assert_eq!(sketch(seq, 32, u64::MAX).len(), 2);
+        assert_eq!(
+            sketch(seq, 32, u64::MAX),
+            sketch(&REVCOMP(seq), 32, u64::MAX)
+        );
+    }
+}
@@ -0,0 +1,670 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::{
+    SketchStrategy, DEFAULT_KMER_SIZE, DEFAULT_MIN_SHARED_HASHES, DEFAULT_SCALE,
+    DEFAULT_SKETCH_NUM_READS,
+};
+use crate::adapter::AdapterScan;
+use crate::artifact::{Artifact, Artifacts};
+use crate::env::EnvDefaults;
+use crate::error::LrgeError;
+use crate::estimate::{Equation3, PerReadEstimate, PerReadEstimator};
+use crate::seed::IntoSeed;
+use crate::stream::{Callback, EstimateSink};
+use crate::tmpdir::ManagedTmpdir;
+use crate::trim::QualityTrim;
+use crate::{read_filter, sample};
+
+/// A builder for [`SketchStrategy`].
+pub struct Builder {
+    num_reads: usize,
+    read_fraction: Option<f32>,
+    kmer_size: usize,
+    scale: u64,
+    min_shared_hashes: usize,
+    tmpdir: PathBuf,
+    create_tmpdir: bool,
+    keep_temp: bool,
+    threads: usize,
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+    seed: Option<u64>,
+    max_no_mapping_fraction: Option<f32>,
+    max_bad_records: Option<usize>,
+    validate_records: bool,
+    cache_count: bool,
+    allowlist: Option<PathBuf>,
+    blocklist: Option<PathBuf>,
+    quality_trim: Option<QualityTrim>,
+    adapters: Option<AdapterScan>,
+    artifacts: Artifacts,
+    on_estimate: Option<Callback>,
+    estimator: Arc<dyn PerReadEstimator>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        let tmpdir = std::env::temp_dir();
+        Self {
+            num_reads: DEFAULT_SKETCH_NUM_READS,
+            read_fraction: None,
+            kmer_size: DEFAULT_KMER_SIZE,
+            scale: DEFAULT_SCALE,
+            min_shared_hashes: DEFAULT_MIN_SHARED_HASHES,
+            tmpdir,
+            create_tmpdir: false,
+            keep_temp: false,
+            threads: 1,
+            thread_pool: None,
+            seed: None,
+            max_no_mapping_fraction: None,
+            max_bad_records: None,
+            validate_records: false,
+            cache_count: false,
+            allowlist: None,
+            blocklist: None,
+            quality_trim: None,
+            adapters: None,
+            artifacts: Artifacts::default(),
+            on_estimate: None,
+            estimator: Arc::new(Equation3),
+        }
+    }
+}
+
+impl Builder {
+    /// Create a new builder with the default settings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new();
+    /// ```
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new builder with the default settings, overridden by any of the following
+    /// environment variables that are set (and not empty):
+    ///
+    /// - [`LRGE_THREADS`][crate::env::THREADS_VAR]: the number of threads (see [`Builder::threads`])
+    /// - [`LRGE_TMPDIR`][crate::env::TMPDIR_VAR]: the temporary directory (see [`Builder::tmpdir`])
+    /// - [`LRGE_SEED`][crate::env::SEED_VAR]: the random seed (see [`Builder::seed`])
+    ///
+    /// [`LRGE_PLATFORM`][crate::env::PLATFORM_VAR] is checked, but not used, as reads are not
+    /// aligned. Settings can still be changed with the other builder methods afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidConfiguration`][crate::error::LrgeError::InvalidConfiguration] error
+    /// if a variable cannot be parsed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::from_env().expect("Invalid LRGE_* environment variable");
+    /// ```
+    pub fn from_env() -> crate::Result<Self> {
+        let env = EnvDefaults::from_env()?;
+        let mut builder = Self::default();
+        if let Some(threads) = env.threads {
+            builder.threads = threads;
+        }
+        if let Some(tmpdir) = env.tmpdir {
+            builder.tmpdir = tmpdir;
+        }
+        builder.seed = env.seed;
+        Ok(builder)
+    }
+
+    /// Set the number of reads for the strategy. By default, this is [`DEFAULT_SKETCH_NUM_READS`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().num_reads(1000);
+    /// ```
+    pub fn num_reads(mut self, num_reads: usize) -> Self {
+        self.num_reads = num_reads;
+        self
+    }
+
+    /// Use this fraction (0-1) of the reads in the input, rather than a
+    /// [number of them][Builder::num_reads]. The reads are counted before they are sampled, so the
+    /// input is read twice. The fraction is rounded to the nearest read, but at least 2.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().read_fraction(0.01);
+    /// ```
+    pub fn read_fraction(mut self, fraction: f32) -> Self {
+        self.read_fraction = Some(fraction);
+        self
+    }
+
+    /// Set the length of the k-mers that are sketched. By default, this is [`DEFAULT_KMER_SIZE`].
+    ///
+    /// A k-mer is only shared by two overlapping reads if neither has an error in it, so shorter
+    /// k-mers suit reads with higher error rates, but are more likely to be shared by chance. The
+    /// maximum is 32.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().kmer_size(21);
+    /// ```
+    pub fn kmer_size(mut self, kmer_size: usize) -> Self {
+        self.kmer_size = kmer_size;
+        self
+    }
+
+    /// Set the fraction, `1 / scale`, of the k-mers that are kept in the sketch of a read. By
+    /// default, this is [`DEFAULT_SCALE`].
+    ///
+    /// A smaller scale keeps more k-mers, so shorter overlaps can be found, at the cost of speed
+    /// and memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().scale(20);
+    /// ```
+    pub fn scale(mut self, scale: u64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Set the number of hashes two reads must share for them to overlap. By default, this is
+    /// [`DEFAULT_MIN_SHARED_HASHES`].
+    ///
+    /// Overlaps shorter than about `min_shared_hashes * scale` bases (longer, with sequencing
+    /// errors) are not found, and this is used as the overlap threshold of the
+    /// [per-read estimator][Builder::per_read_estimator].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().min_shared_hashes(5);
+    /// ```
+    pub fn min_shared_hashes(mut self, min_shared_hashes: usize) -> Self {
+        self.min_shared_hashes = min_shared_hashes;
+        self
+    }
+
+    /// Set the temporary directory for the strategy. By default, this is the value of the `TMPDIR`
+    /// environment variable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    /// use std::path::PathBuf;
+    ///
+    /// let builder = Builder::new().tmpdir(PathBuf::from("/tmp"));
+    /// ```
+    pub fn tmpdir<P: AsRef<Path>>(mut self, tmpdir: P) -> Self {
+        self.tmpdir = tmpdir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Create a new, uniquely named directory (`lrge-XXXXXX`) inside the [temporary
+    /// directory][Builder::tmpdir] for the intermediate files, when the estimate is generated. The
+    /// directory, and everything in it, is removed when the strategy is dropped - unless it is
+    /// kept with [`Builder::keep_temp`]. Individual files can be kept with
+    /// [`Builder::keep_artifact`], in which case only they are left in the directory. By default, this is `false`, and the intermediate files are
+    /// written directly to the temporary directory, which is neither created nor removed.
+    ///
+    /// The directory's path is available from [`SketchStrategy::tmpdir`][crate::SketchStrategy::tmpdir]
+    /// once the estimate is generated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().create_tmpdir(true);
+    /// ```
+    pub fn create_tmpdir(mut self, create_tmpdir: bool) -> Self {
+        self.create_tmpdir = create_tmpdir;
+        self
+    }
+
+    /// Don't remove the directory created with [`Builder::create_tmpdir`] when the strategy is
+    /// dropped. By default, this is `false`. It has no effect if the strategy does not create its
+    /// own directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().create_tmpdir(true).keep_temp(true);
+    /// ```
+    pub fn keep_temp(mut self, keep_temp: bool) -> Self {
+        self.keep_temp = keep_temp;
+        self
+    }
+
+    /// Set the number of threads to sketch and compare the reads with. By default, this is `1`.
+    ///
+    /// A value of 0 means use all available cores, as reported by
+    /// [`std::thread::available_parallelism`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().threads(4);
+    /// ```
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Sketch and compare reads in a caller-provided [rayon](https://docs.rs/rayon) thread pool,
+    /// rather than building a new pool for each estimate. By default (`None`), a new pool with the
+    /// number of threads given to [`Builder::threads`] is built.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+    /// let builder = Builder::new().thread_pool(Some(Arc::new(pool)));
+    /// ```
+    pub fn thread_pool(mut self, thread_pool: Option<Arc<rayon::ThreadPool>>) -> Self {
+        self.thread_pool = thread_pool;
+        self
+    }
+
+    /// Set the seed for the strategy. By default (`None`), the seed will be
+    /// [randomly generated](https://docs.rs/rand/latest/rand/fn.random.html).
+    ///
+    /// The seed can be a number or any string, such as a sample name - see [`crate::seed`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().seed(Some(42));
+    /// let builder = Builder::new().seed("sample1");
+    /// ```
+    pub fn seed<S: IntoSeed>(mut self, seed: S) -> Self {
+        self.seed = seed.into_seed();
+        self
+    }
+
+    /// Set the maximum fraction (0-1) of reads allowed to have no overlaps. By default, there is no
+    /// maximum.
+    ///
+    /// If more than this fraction of reads did not overlap, the estimate fails with a
+    /// [`QualityControl`][crate::error::LrgeError::QualityControl] error. The per-read estimates
+    /// are still written to the temporary directory. The fraction is clamped to `[0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().max_no_mapping_fraction(0.2);
+    /// ```
+    pub fn max_no_mapping_fraction(mut self, max_no_mapping_fraction: f32) -> Self {
+        self.max_no_mapping_fraction = Some(max_no_mapping_fraction.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Skip up to `max_bad_records` malformed records in the input file - e.g., a truncated record
+    /// at the end of the file - rather than failing. By default (`None`), a malformed record is an
+    /// error.
+    ///
+    /// Each skipped record is logged, and the number skipped is available from
+    /// [`Estimate::input_num_skipped`][crate::Estimate::input_num_skipped]. If there are more than
+    /// `max_bad_records` malformed records, sampling fails with an
+    /// [`IoError`][crate::error::LrgeError::IoError].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().skip_bad_records(Some(100));
+    /// ```
+    pub fn skip_bad_records(mut self, max_bad_records: Option<usize>) -> Self {
+        self.max_bad_records = max_bad_records;
+        self
+    }
+
+    /// Parse, and so validate, every record of the input file when the reads are counted - i.e.,
+    /// when a [fraction of the reads][Self::read_fraction] is sampled. By default (`false`), the
+    /// records are only scanned for their IDs and lengths when they are counted, rather than
+    /// parsed. Malformed records are then counted like any other, though they are still found when
+    /// the reads are sampled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().validate_records(true);
+    /// ```
+    pub fn validate_records(mut self, validate_records: bool) -> Self {
+        self.validate_records = validate_records;
+        self
+    }
+
+    /// Cache the number of reads in the input file in a sidecar file next to it - the input's path
+    /// with `.lrge-count` appended - when the reads are counted, and use the cached number,
+    /// rather than count them again, while the input's size and modification time are unchanged.
+    /// This saves reading the input twice when the same input is estimated repeatedly - e.g., in a
+    /// parameter sweep. The sidecar is not used when the records are
+    /// [validated][Self::validate_records], or an [allowlist][Self::allowlist] or
+    /// [blocklist][Self::blocklist] is given. A sidecar that cannot be written is logged, and
+    /// otherwise ignored. By default (`false`), the reads are always counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().cache_count(true);
+    /// ```
+    pub fn cache_count(mut self, cache_count: bool) -> Self {
+        self.cache_count = cache_count;
+        self
+    }
+
+    /// Only sample the reads whose IDs are in the file at `path` - e.g., the reads that passed an
+    /// external QC filter. The file has one ID per line, and only the first whitespace-delimited
+    /// field of each line is used. The reads are still randomly sampled from those in the file.
+    ///
+    /// The reads that are not in the file are skipped as if they were not in the input, so they are
+    /// not included in [`Estimate::input_num_reads`][crate::Estimate::input_num_reads].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().allowlist("passed_qc.txt");
+    /// ```
+    pub fn allowlist<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.allowlist = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Never sample the reads whose IDs are in the file at `path` - e.g., known contaminants or
+    /// control reads, such as the lambda DNA control strand. The file has the same format as the
+    /// [allowlist][Builder::allowlist]. If both are given, a read must be in the allowlist and not
+    /// in the blocklist to be sampled.
+    ///
+    /// Like the reads that are not in the allowlist, the reads in the file are skipped as if they
+    /// were not in the input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().blocklist("contaminants.txt");
+    /// ```
+    pub fn blocklist<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.blocklist = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Trim the low-quality ends of the sampled reads before they are overlapped - see
+    /// [`QualityTrim`]. Low-quality tails reduce the overlaps that are found. Reads without
+    /// qualities (e.g., FASTA) are not trimmed, and the reads are counted in
+    /// [`Estimate::input_num_bases`][crate::Estimate::input_num_bases] before they are trimmed. By
+    /// default, the reads are not trimmed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    /// use liblrge::trim::QualityTrim;
+    ///
+    /// let builder = Builder::new().trim_quality(Some(QualityTrim::new(10).window_size(50)));
+    /// ```
+    pub fn trim_quality(mut self, trim: Option<QualityTrim>) -> Self {
+        self.quality_trim = trim;
+        self
+    }
+
+    /// Remove the adapters found by `adapters` from the sampled reads before they are sketched -
+    /// see [`AdapterScan`]. Adapters near the ends of a read are trimmed, and a read with an
+    /// adapter in the middle (i.e., a chimeric read) is split there, keeping its longest part. How
+    /// many reads were changed is available from
+    /// [`Estimate::adapter_summary`][crate::Estimate::adapter_summary]. By default, adapters are
+    /// not removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::adapter::AdapterScan;
+    /// use liblrge::sketch::Builder;
+    /// use liblrge::Platform;
+    ///
+    /// let builder = Builder::new().remove_adapters(Some(AdapterScan::for_platform(Platform::Nanopore)));
+    /// ```
+    pub fn remove_adapters(mut self, adapters: Option<AdapterScan>) -> Self {
+        self.adapters = adapters;
+        self
+    }
+
+    /// Write an intermediate file to `path`, rather than to the temporary directory. Only the
+    /// sampled [reads][Artifact::Reads] are written by this strategy, so other artifacts are
+    /// ignored. See the [`artifact`][crate::artifact] module.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::artifact::Artifact;
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().artifact_path(Artifact::Reads, "reads.fa");
+    /// ```
+    pub fn artifact_path<P: AsRef<Path>>(mut self, artifact: Artifact, path: P) -> Self {
+        self.artifacts
+            .set_path(artifact, path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set whether an intermediate file is kept once the estimates have been generated. By
+    /// default, all are kept - though, if the strategy creates its own temporary directory (see
+    /// [`Builder::create_tmpdir`]), they are removed along with it unless they are explicitly kept
+    /// with this. Artifacts that this strategy does not write are ignored. See the
+    /// [`artifact`][crate::artifact] module.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::artifact::Artifact;
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().keep_artifact(Artifact::Reads, false);
+    /// ```
+    pub fn keep_artifact(mut self, artifact: Artifact, keep: bool) -> Self {
+        self.artifacts.set_keep(artifact, keep);
+        self
+    }
+
+    /// Replace all of the artifact settings.
+    pub(crate) fn artifacts(mut self, artifacts: Artifacts) -> Self {
+        self.artifacts = artifacts;
+        self
+    }
+
+    /// Set a callback that is called with each per-read estimate as soon as it is known. By
+    /// default, there is no callback.
+    ///
+    /// A read's overlaps are only all known once every read has been sketched, so the estimates
+    /// are passed to the callback together, at the end.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().on_estimate(|est| println!("{}", est.estimate));
+    /// ```
+    pub fn on_estimate<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&PerReadEstimate) + Send + 'static,
+    {
+        self.on_estimate = Some(Box::new(callback));
+        self
+    }
+
+    /// Set how the genome size estimate for each read is calculated from its overlaps. By default,
+    /// this is [`Equation3`], the formula from the paper.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::estimate::Equation3;
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().per_read_estimator(Equation3);
+    /// ```
+    pub fn per_read_estimator<E>(self, estimator: E) -> Self
+    where
+        E: PerReadEstimator + 'static,
+    {
+        self.shared_per_read_estimator(Arc::new(estimator))
+    }
+
+    /// Set the per-read estimator, from a [`StrategyBuilder`][crate::StrategyBuilder].
+    pub(crate) fn shared_per_read_estimator(
+        mut self,
+        estimator: Arc<dyn PerReadEstimator>,
+    ) -> Self {
+        self.estimator = estimator;
+        self
+    }
+
+    /// Build the [`SketchStrategy`], using the reads from the given `input` file, after checking
+    /// that the configuration is valid.
+    ///
+    /// Unlike [`Builder::build`], which accepts any configuration, this fails up front with an
+    /// [`InvalidConfiguration`][LrgeError::InvalidConfiguration] error if:
+    ///
+    /// - the number of reads is less than 2, as each read must be compared with at least one other
+    /// - the fraction of reads is not greater than 0 and at most 1
+    /// - the k-mer size is not between 1 and 32
+    /// - the scale or the minimum number of shared hashes is 0
+    /// - the temporary directory does not exist
+    /// - the directory an [artifact][Builder::artifact_path] is to be written to does not exist
+    /// - the [allowlist][Builder::allowlist] or [blocklist][Builder::blocklist] does not exist
+    /// - the `input` file does not exist
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let result = Builder::new().kmer_size(33).try_build("reads.fq");
+    /// assert!(result.is_err());
+    /// ```
+    pub fn try_build<P: AsRef<Path>>(self, input: P) -> crate::Result<SketchStrategy> {
+        if self.num_reads < 2 {
+            return Err(LrgeError::invalid_configuration(
+                "num_reads",
+                format!("must be at least 2, but got {}", self.num_reads),
+            ));
+        }
+        sample::validate_fraction("read_fraction", self.read_fraction)?;
+        if !(1..=32).contains(&self.kmer_size) {
+            return Err(LrgeError::invalid_configuration(
+                "kmer_size",
+                format!("must be between 1 and 32, but got {}", self.kmer_size),
+            ));
+        }
+        if self.scale == 0 {
+            return Err(LrgeError::invalid_configuration(
+                "scale",
+                "must be at least 1",
+            ));
+        }
+        if self.min_shared_hashes == 0 {
+            return Err(LrgeError::invalid_configuration(
+                "min_shared_hashes",
+                "must be at least 1",
+            ));
+        }
+        crate::validate_builder(input.as_ref(), &self.tmpdir, None, None, None)?;
+        self.artifacts.validate()?;
+        read_filter::validate("allowlist", self.allowlist.as_deref())?;
+        read_filter::validate("blocklist", self.blocklist.as_deref())?;
+
+        Ok(self.build(input))
+    }
+
+    /// Build the [`SketchStrategy`], using the reads from the given `input` file.
+    ///
+    /// The k-mer size is clamped to between 1 and 32, and the scale and minimum number of shared
+    /// hashes to at least 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::sketch::Builder;
+    ///
+    /// let builder = Builder::new().num_reads(1000);
+    /// let strategy = builder.build("reads.fq");
+    /// ```
+    pub fn build<P: AsRef<Path>>(self, input: P) -> SketchStrategy {
+        SketchStrategy {
+            input: input.as_ref().to_path_buf(),
+            num_reads: self.num_reads,
+            read_fraction: self.read_fraction,
+            kmer_size: self.kmer_size.clamp(1, 32),
+            scale: self.scale.max(1),
+            min_shared_hashes: self.min_shared_hashes.max(1),
+            tmpdir: self.tmpdir,
+            managed_tmpdir: self
+                .create_tmpdir
+                .then(|| ManagedTmpdir::new(self.keep_temp)),
+            threads: crate::resolve_threads(self.threads),
+            thread_pool: self.thread_pool,
+            seed: self.seed,
+            max_no_mapping_fraction: self.max_no_mapping_fraction,
+            max_bad_records: self.max_bad_records,
+            validate_records: self.validate_records,
+            cache_count: self.cache_count,
+            allowlist: self.allowlist,
+            blocklist: self.blocklist,
+            quality_trim: self.quality_trim,
+            adapters: self.adapters,
+            artifacts: self.artifacts,
+            on_estimate: self.on_estimate.map(EstimateSink::new),
+            estimator: self.estimator,
+            input_num_reads: None,
+            input_num_bases: None,
+            input_num_skipped: None,
+            gc_content: None,
+            adapter_summary: None,
+            num_overlaps: None,
+            raw_estimates: None,
+        }
+    }
+}
@@ -0,0 +1,1532 @@
+//! A single builder for any of the genome size estimation strategies.
+//!
+//! The [`twoset::Builder`] and [`ava::Builder`] share most of their settings, and the
+//! [`sketch::Builder`] those that are not about alignment. [`StrategyBuilder`] takes those settings
+//! once, along with the [`StrategyKind`] to run, so that frontends choosing the strategy at runtime
+//! do not need a code path per strategy. Settings a strategy does not use are ignored.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use liblrge::{Estimate, StrategyBuilder, StrategyKind};
+//!
+//! let mut strategy = StrategyBuilder::new("path/to/reads.fastq")
+//!     .strategy(StrategyKind::Ava { num_reads: 10_000 })
+//!     .threads(4)
+//!     .build();
+//!
+//! let est_result = strategy.estimate(false, None, None).expect("Failed to generate estimate");
+//! ```
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::adapter::{AdapterScan, AdapterSummary};
+use crate::artifact::{Artifact, Artifacts};
+use crate::ava::{self, DEFAULT_AVA_NUM_READS};
+use crate::env::EnvDefaults;
+use crate::estimate::{PerReadEstimate, PerReadEstimator, RawEstimates};
+use crate::gc::GcSummary;
+use crate::minimap2::{MappingCallback, OptionOverrides};
+use crate::read_filter::ReadFilter;
+use crate::sample;
+use crate::seed::IntoSeed;
+use crate::sketch::{self, DEFAULT_SKETCH_NUM_READS};
+use crate::stream::Callback;
+use crate::trim::QualityTrim;
+use crate::twoset::{self, DEFAULT_QUERY_NUM_READS, DEFAULT_TARGET_NUM_READS};
+use crate::{
+    AvaStrategy, Estimate, IdxOpt, MapOpt, OverlapFormat, PafReader, PafRecord, Platform,
+    SketchStrategy, TwoSetStrategy,
+};
+
+/// The strategy for a [`StrategyBuilder`] to build, and its number of reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrategyKind {
+    /// A [`TwoSetStrategy`], which overlaps the query reads against the target reads.
+    TwoSet {
+        /// The number of target reads
+        target_num_reads: usize,
+        /// The number of query reads
+        query_num_reads: usize,
+    },
+    /// An [`AvaStrategy`], which overlaps the reads against themselves.
+    Ava {
+        /// The number of reads
+        num_reads: usize,
+    },
+    /// A [`SketchStrategy`], which finds overlaps between the reads from sketches of their k-mers.
+    Sketch {
+        /// The number of reads
+        num_reads: usize,
+    },
+}
+
+/// The default is a two-set strategy with [`DEFAULT_TARGET_NUM_READS`] target reads and
+/// [`DEFAULT_QUERY_NUM_READS`] query reads.
+impl Default for StrategyKind {
+    fn default() -> Self {
+        StrategyKind::TwoSet {
+            target_num_reads: DEFAULT_TARGET_NUM_READS,
+            query_num_reads: DEFAULT_QUERY_NUM_READS,
+        }
+    }
+}
+
+impl StrategyKind {
+    /// An all-vs-all strategy with [`DEFAULT_AVA_NUM_READS`] reads.
+    pub fn ava() -> Self {
+        StrategyKind::Ava {
+            num_reads: DEFAULT_AVA_NUM_READS,
+        }
+    }
+
+    /// A sketch strategy with [`DEFAULT_SKETCH_NUM_READS`] reads.
+    pub fn sketch() -> Self {
+        StrategyKind::Sketch {
+            num_reads: DEFAULT_SKETCH_NUM_READS,
+        }
+    }
+
+    /// Choose between the two-set and all-vs-all strategies for an input with `input_num_reads`
+    /// reads and `input_num_bases` bases. The two-set strategy, with its default numbers of reads,
+    /// is chosen for large inputs - at least [`AUTO_MIN_TWOSET_READS`] reads and
+    /// [`AUTO_MIN_TWOSET_BASES`] bases. Otherwise, the all-vs-all strategy is chosen, with up to
+    /// [`DEFAULT_AVA_NUM_READS`] reads, as it finds more overlaps in fewer reads.
+    ///
+    /// To count the reads in an input and choose its strategy, use
+    /// [`StrategyBuilder::choose_strategy`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::StrategyKind;
+    ///
+    /// let choice = StrategyKind::auto(1_000, 5_000_000);
+    /// assert_eq!(choice.kind, StrategyKind::Ava { num_reads: 1_000 });
+    ///
+    /// let choice = StrategyKind::auto(200_000, 2_000_000_000);
+    /// assert_eq!(choice.kind, StrategyKind::default());
+    /// ```
+    pub fn auto(input_num_reads: u64, input_num_bases: u64) -> AutoStrategy {
+        // each read must be overlapped with at least one other
+        let ava = StrategyKind::Ava {
+            num_reads: input_num_reads.clamp(2, DEFAULT_AVA_NUM_READS as u64) as usize,
+        };
+        let (kind, reason) = if input_num_reads < AUTO_MIN_TWOSET_READS {
+            (
+                ava,
+                format!(
+                    "the input has {input_num_reads} reads, fewer than the \
+                     {AUTO_MIN_TWOSET_READS} needed for the two-set strategy"
+                ),
+            )
+        } else if input_num_bases < AUTO_MIN_TWOSET_BASES {
+            (
+                ava,
+                format!(
+                    "the input has {input_num_bases} bases, fewer than the \
+                     {AUTO_MIN_TWOSET_BASES} needed for the two-set strategy to find enough overlaps"
+                ),
+            )
+        } else {
+            (
+                StrategyKind::default(),
+                format!(
+                    "the input has {input_num_reads} reads and {input_num_bases} bases, enough for \
+                     the two-set strategy"
+                ),
+            )
+        };
+        AutoStrategy {
+            kind,
+            input_num_reads,
+            input_num_bases,
+            reason,
+        }
+    }
+}
+
+/// The fewest reads in the input for [`StrategyKind::auto`] to choose the two-set strategy - enough
+/// for its default numbers of target and query reads.
+pub const AUTO_MIN_TWOSET_READS: u64 = (DEFAULT_TARGET_NUM_READS + DEFAULT_QUERY_NUM_READS) as u64;
+
+/// The fewest bases in the input for [`StrategyKind::auto`] to choose the two-set strategy. With a
+/// lower yield, there are too few overlaps between the query and target reads, so overlapping all
+/// of the reads with each other gives a better estimate.
+pub const AUTO_MIN_TWOSET_BASES: u64 = 100_000_000;
+
+/// The strategy chosen by [`StrategyKind::auto`] for an input, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoStrategy {
+    /// The chosen strategy, and its number of reads
+    pub kind: StrategyKind,
+    /// The number of reads in the input
+    pub input_num_reads: u64,
+    /// The total number of bases in the input
+    pub input_num_bases: u64,
+    /// Why the strategy was chosen
+    pub reason: String,
+}
+
+/// A builder for a [`Strategy`] of any [`StrategyKind`].
+///
+/// Settings that are not given fall back to the defaults of the builder for that kind of strategy
+/// ([`twoset::Builder`], [`ava::Builder`], or [`sketch::Builder`]), so they can differ - e.g., the channel capacity.
+pub struct StrategyBuilder {
+    input: PathBuf,
+    kind: StrategyKind,
+    remove_internal: Option<(bool, f32)>,
+    max_overhang_size: Option<usize>,
+    max_divergence: Option<f32>,
+    min_mapq: Option<u8>,
+    min_overlap_len: Option<usize>,
+    primary_only: Option<bool>,
+    length_weighted: Option<bool>,
+    exclude_contained: Option<bool>,
+    exclude_duplicates: Option<bool>,
+    use_min_ref: Option<bool>,
+    resample_no_mapping_fraction: Option<f32>,
+    target_fraction: Option<f32>,
+    query_fraction: Option<f32>,
+    read_fraction: Option<f32>,
+    tmpdir: Option<PathBuf>,
+    create_tmpdir: Option<bool>,
+    keep_temp: Option<bool>,
+    threads: Option<usize>,
+    index_threads: Option<usize>,
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+    seed: Option<u64>,
+    platform: Option<Platform>,
+    max_memory: Option<usize>,
+    channel_capacity: Option<usize>,
+    max_no_mapping_fraction: Option<f32>,
+    max_bad_records: Option<usize>,
+    validate_records: Option<bool>,
+    cache_count: Option<bool>,
+    allowlist: Option<PathBuf>,
+    blocklist: Option<PathBuf>,
+    quality_trim: Option<QualityTrim>,
+    adapters: Option<AdapterScan>,
+    overlap_format: Option<OverlapFormat>,
+    sort_overlaps: Option<bool>,
+    artifacts: Artifacts,
+    dual: Option<bool>,
+    on_estimate: Option<Callback>,
+    on_mapping: Option<MappingCallback>,
+    estimator: Option<Arc<dyn PerReadEstimator>>,
+    overrides: OptionOverrides,
+}
+
+/// Apply the settings shared by all strategies from a [`StrategyBuilder`] to the builder for a
+/// specific strategy. Both builders have the same methods for these settings.
+macro_rules! apply_shared_settings {
+    ($builder:expr, $settings:expr) => {{
+        let settings = $settings;
+        let mut builder = $builder
+            .artifacts(settings.artifacts)
+            .thread_pool(settings.thread_pool)
+            .seed(settings.seed)
+            .max_memory(settings.max_memory)
+            .max_overhang_size(settings.max_overhang_size)
+            .max_divergence(settings.max_divergence)
+            .min_mapq(settings.min_mapq)
+            .min_overlap_len(settings.min_overlap_len)
+            .skip_bad_records(settings.max_bad_records)
+            .trim_quality(settings.quality_trim)
+            .remove_adapters(settings.adapters);
+        if let Some((remove_internal, ratio)) = settings.remove_internal {
+            builder = builder.remove_internal(remove_internal, ratio);
+        }
+        if let Some(primary_only) = settings.primary_only {
+            builder = builder.primary_only(primary_only);
+        }
+        if let Some(length_weighted) = settings.length_weighted {
+            builder = builder.length_weighted(length_weighted);
+        }
+        if let Some(exclude_contained) = settings.exclude_contained {
+            builder = builder.exclude_contained(exclude_contained);
+        }
+        if let Some(exclude_duplicates) = settings.exclude_duplicates {
+            builder = builder.exclude_duplicates(exclude_duplicates);
+        }
+        if let Some(validate_records) = settings.validate_records {
+            builder = builder.validate_records(validate_records);
+        }
+        if let Some(cache_count) = settings.cache_count {
+            builder = builder.cache_count(cache_count);
+        }
+        if let Some(tmpdir) = settings.tmpdir {
+            builder = builder.tmpdir(tmpdir);
+        }
+        if let Some(create_tmpdir) = settings.create_tmpdir {
+            builder = builder.create_tmpdir(create_tmpdir);
+        }
+        if let Some(keep_temp) = settings.keep_temp {
+            builder = builder.keep_temp(keep_temp);
+        }
+        if let Some(allowlist) = settings.allowlist {
+            builder = builder.allowlist(allowlist);
+        }
+        if let Some(blocklist) = settings.blocklist {
+            builder = builder.blocklist(blocklist);
+        }
+        if let Some(threads) = settings.threads {
+            builder = builder.threads(threads);
+        }
+        if let Some(index_threads) = settings.index_threads {
+            builder = builder.index_threads(index_threads);
+        }
+        if let Some(platform) = settings.platform {
+            builder = builder.platform(platform);
+        }
+        if let Some(capacity) = settings.channel_capacity {
+            builder = builder.channel_capacity(capacity);
+        }
+        if let Some(fraction) = settings.max_no_mapping_fraction {
+            builder = builder.max_no_mapping_fraction(fraction);
+        }
+        if let Some(format) = settings.overlap_format {
+            builder = builder.overlap_format(format);
+        }
+        if let Some(sort) = settings.sort_overlaps {
+            builder = builder.sort_overlaps(sort);
+        }
+        if let Some(dual) = settings.dual {
+            builder = builder.dual(dual);
+        }
+        if let Some(callback) = settings.on_estimate {
+            builder = builder.on_estimate(callback);
+        }
+        if let Some(callback) = settings.on_mapping {
+            builder = builder.on_mapping(callback);
+        }
+        if let Some(estimator) = settings.estimator {
+            builder = builder.shared_per_read_estimator(estimator);
+        }
+        builder.option_overrides(settings.overrides)
+    }};
+}
+
+impl StrategyBuilder {
+    /// Create a new builder for a strategy using the reads in the given `input` file. By default,
+    /// the strategy is a two-set strategy (see [`StrategyKind::default`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::StrategyBuilder;
+    ///
+    /// let builder = StrategyBuilder::new("input.fastq");
+    /// ```
+    pub fn new<P: AsRef<Path>>(input: P) -> Self {
+        Self {
+            input: input.as_ref().to_path_buf(),
+            kind: StrategyKind::default(),
+            remove_internal: None,
+            max_overhang_size: None,
+            max_divergence: None,
+            min_mapq: None,
+            min_overlap_len: None,
+            primary_only: None,
+            length_weighted: None,
+            exclude_contained: None,
+            exclude_duplicates: None,
+            use_min_ref: None,
+            resample_no_mapping_fraction: None,
+            target_fraction: None,
+            query_fraction: None,
+            read_fraction: None,
+            tmpdir: None,
+            create_tmpdir: None,
+            keep_temp: None,
+            threads: None,
+            index_threads: None,
+            thread_pool: None,
+            seed: None,
+            platform: None,
+            max_memory: None,
+            channel_capacity: None,
+            max_no_mapping_fraction: None,
+            max_bad_records: None,
+            validate_records: None,
+            cache_count: None,
+            allowlist: None,
+            blocklist: None,
+            quality_trim: None,
+            adapters: None,
+            overlap_format: None,
+            sort_overlaps: None,
+            artifacts: Artifacts::default(),
+            dual: None,
+            on_estimate: None,
+            on_mapping: None,
+            estimator: None,
+            overrides: OptionOverrides::default(),
+        }
+    }
+
+    /// Create a new builder, as with [`StrategyBuilder::new`], with the settings given by any
+    /// `LRGE_*` [environment variables][crate::env] that are set. See
+    /// [`twoset::Builder::from_env`] for the variables.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidConfiguration`][crate::error::LrgeError::InvalidConfiguration] error
+    /// if a variable cannot be parsed.
+    pub fn from_env<P: AsRef<Path>>(input: P) -> crate::Result<Self> {
+        let env = EnvDefaults::from_env()?;
+        let mut builder = Self::new(input);
+        builder.threads = env.threads;
+        builder.tmpdir = env.tmpdir;
+        builder.seed = env.seed;
+        builder.platform = env.platform;
+        Ok(builder)
+    }
+
+    /// Set the kind of strategy to build, and its number of reads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::{StrategyBuilder, StrategyKind};
+    ///
+    /// let builder = StrategyBuilder::new("input.fastq").strategy(StrategyKind::TwoSet {
+    ///     target_num_reads: 5000,
+    ///     query_num_reads: 2500,
+    /// });
+    /// ```
+    pub fn strategy(mut self, kind: StrategyKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Set option for removing the overlaps representing internal matches. See
+    /// [`twoset::Builder::remove_internal`].
+    pub fn remove_internal(mut self, remove_internal: bool, ratio: f32) -> Self {
+        self.remove_internal = Some((remove_internal, ratio));
+        self
+    }
+
+    /// Set the maximum overhang, in bases, of an overlap when removing internal matches. See
+    /// [`twoset::Builder::max_overhang_size`].
+    pub fn max_overhang_size(mut self, max_overhang_size: Option<usize>) -> Self {
+        self.max_overhang_size = max_overhang_size;
+        self
+    }
+
+    /// Set the maximum divergence of the overlaps that are counted. See
+    /// [`twoset::Builder::max_divergence`].
+    pub fn max_divergence(mut self, max_divergence: Option<f32>) -> Self {
+        self.max_divergence = max_divergence;
+        self
+    }
+
+    /// Set the minimum mapping quality of the overlaps that are counted. See
+    /// [`twoset::Builder::min_mapq`].
+    pub fn min_mapq(mut self, min_mapq: Option<u8>) -> Self {
+        self.min_mapq = min_mapq;
+        self
+    }
+
+    /// Set the minimum length of the overlaps that are counted. See
+    /// [`twoset::Builder::min_overlap_len`].
+    pub fn min_overlap_len(mut self, min_overlap_len: Option<usize>) -> Self {
+        self.min_overlap_len = min_overlap_len;
+        self
+    }
+
+    /// Set whether only the primary chain to each read is considered when counting overlaps. See
+    /// [`twoset::Builder::primary_only`].
+    pub fn primary_only(mut self, primary_only: bool) -> Self {
+        self.primary_only = Some(primary_only);
+        self
+    }
+
+    /// Set whether overlaps are weighted by their length. See
+    /// [`twoset::Builder::length_weighted`].
+    pub fn length_weighted(mut self, length_weighted: bool) -> Self {
+        self.length_weighted = Some(length_weighted);
+        self
+    }
+
+    /// Set whether reads contained in a read they overlap are excluded from the estimate. See
+    /// [`twoset::Builder::exclude_contained`].
+    pub fn exclude_contained(mut self, exclude_contained: bool) -> Self {
+        self.exclude_contained = Some(exclude_contained);
+        self
+    }
+
+    /// Set whether reads that are a duplicate of a read they overlap are excluded from the
+    /// estimate. See [`twoset::Builder::exclude_duplicates`].
+    pub fn exclude_duplicates(mut self, exclude_duplicates: bool) -> Self {
+        self.exclude_duplicates = Some(exclude_duplicates);
+        self
+    }
+
+    /// Set option for using the smaller set of reads as the minimap2 reference. This only applies
+    /// to the two-set strategy. See [`twoset::Builder::use_min_ref`].
+    pub fn use_min_ref(mut self, use_min_ref: bool) -> Self {
+        self.use_min_ref = Some(use_min_ref);
+        self
+    }
+
+    /// Set the fraction of query reads without overlaps above which more query reads are mapped.
+    /// This only applies to the two-set strategy. See
+    /// [`twoset::Builder::resample_no_mapping_fraction`].
+    pub fn resample_no_mapping_fraction(mut self, fraction: f32) -> Self {
+        self.resample_no_mapping_fraction = Some(fraction);
+        self
+    }
+
+    /// Use this fraction of the input as target reads, rather than the number given by the
+    /// [`StrategyKind`]. This only applies to the two-set strategy. See
+    /// [`twoset::Builder::target_fraction`].
+    pub fn target_fraction(mut self, fraction: f32) -> Self {
+        self.target_fraction = Some(fraction);
+        self
+    }
+
+    /// Use this fraction of the input as query reads, rather than the number given by the
+    /// [`StrategyKind`]. This only applies to the two-set strategy. See
+    /// [`twoset::Builder::query_fraction`].
+    pub fn query_fraction(mut self, fraction: f32) -> Self {
+        self.query_fraction = Some(fraction);
+        self
+    }
+
+    /// Use this fraction of the input, rather than the number of reads given by the
+    /// [`StrategyKind`]. This only applies to the all-vs-all and sketch strategies. See
+    /// [`ava::Builder::read_fraction`].
+    pub fn read_fraction(mut self, fraction: f32) -> Self {
+        self.read_fraction = Some(fraction);
+        self
+    }
+
+    /// Set the temporary directory for the strategy. See [`twoset::Builder::tmpdir`].
+    pub fn tmpdir<P: AsRef<Path>>(mut self, tmpdir: P) -> Self {
+        self.tmpdir = Some(tmpdir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Create a new directory inside the temporary directory for the intermediate files, and
+    /// remove it when the strategy is dropped. See [`twoset::Builder::create_tmpdir`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::StrategyBuilder;
+    ///
+    /// let builder = StrategyBuilder::new("input.fastq").create_tmpdir(true);
+    /// ```
+    pub fn create_tmpdir(mut self, create_tmpdir: bool) -> Self {
+        self.create_tmpdir = Some(create_tmpdir);
+        self
+    }
+
+    /// Don't remove the directory created with [`StrategyBuilder::create_tmpdir`] when the
+    /// strategy is dropped. See [`twoset::Builder::keep_temp`].
+    pub fn keep_temp(mut self, keep_temp: bool) -> Self {
+        self.keep_temp = Some(keep_temp);
+        self
+    }
+
+    /// Set the number of threads to use with minimap2 (0 means all available cores). See
+    /// [`twoset::Builder::threads`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::StrategyBuilder;
+    ///
+    /// let builder = StrategyBuilder::new("input.fastq").threads(4);
+    /// ```
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Set the number of threads minimap2 uses to build its index. See
+    /// [`twoset::Builder::index_threads`].
+    pub fn index_threads(mut self, index_threads: usize) -> Self {
+        self.index_threads = Some(index_threads);
+        self
+    }
+
+    /// Map reads in a caller-provided thread pool. See [`twoset::Builder::thread_pool`].
+    pub fn thread_pool(mut self, thread_pool: Option<Arc<rayon::ThreadPool>>) -> Self {
+        self.thread_pool = thread_pool;
+        self
+    }
+
+    /// Set the seed for the strategy. See [`twoset::Builder::seed`].
+    pub fn seed<S: IntoSeed>(mut self, seed: S) -> Self {
+        self.seed = seed.into_seed();
+        self
+    }
+
+    /// Set the sequencing platform for the strategy. See [`twoset::Builder::platform`].
+    pub fn platform(mut self, platform: Platform) -> Self {
+        self.platform = Some(platform);
+        self
+    }
+
+    /// Set an (approximate) memory budget, in bytes, for the strategy. See
+    /// [`twoset::Builder::max_memory`].
+    pub fn max_memory(mut self, max_memory: Option<usize>) -> Self {
+        self.max_memory = max_memory;
+        self
+    }
+
+    /// Set the number of reads buffered between the thread reading the input and the mapping
+    /// threads. By default, this is the default of the chosen strategy. See
+    /// [`twoset::Builder::channel_capacity`].
+    pub fn channel_capacity(mut self, channel_capacity: usize) -> Self {
+        self.channel_capacity = Some(channel_capacity);
+        self
+    }
+
+    /// Set the maximum fraction (0-1) of reads allowed to have no overlaps. See
+    /// [`twoset::Builder::max_no_mapping_fraction`].
+    pub fn max_no_mapping_fraction(mut self, max_no_mapping_fraction: f32) -> Self {
+        self.max_no_mapping_fraction = Some(max_no_mapping_fraction);
+        self
+    }
+
+    /// Skip up to `max_bad_records` malformed records in the input file, rather than failing. See
+    /// [`twoset::Builder::skip_bad_records`].
+    pub fn skip_bad_records(mut self, max_bad_records: Option<usize>) -> Self {
+        self.max_bad_records = max_bad_records;
+        self
+    }
+
+    /// Parse, and so validate, every record of the input file when the reads are counted. See
+    /// [`twoset::Builder::validate_records`].
+    pub fn validate_records(mut self, validate_records: bool) -> Self {
+        self.validate_records = Some(validate_records);
+        self
+    }
+
+    /// Cache the number of reads in the input file in a sidecar file next to it. See
+    /// [`twoset::Builder::cache_count`].
+    pub fn cache_count(mut self, cache_count: bool) -> Self {
+        self.cache_count = Some(cache_count);
+        self
+    }
+
+    /// Only sample the reads whose IDs are in the file at `path`. See
+    /// [`twoset::Builder::allowlist`].
+    pub fn allowlist<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.allowlist = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Never sample the reads whose IDs are in the file at `path`. See
+    /// [`twoset::Builder::blocklist`].
+    pub fn blocklist<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.blocklist = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Trim the low-quality ends of the sampled reads. See [`twoset::Builder::trim_quality`].
+    pub fn trim_quality(mut self, trim: Option<QualityTrim>) -> Self {
+        self.quality_trim = trim;
+        self
+    }
+
+    /// Remove adapters from the sampled reads, and split chimeric reads. See
+    /// [`twoset::Builder::remove_adapters`].
+    pub fn remove_adapters(mut self, adapters: Option<AdapterScan>) -> Self {
+        self.adapters = adapters;
+        self
+    }
+
+    /// Set the format the overlaps are written in. See [`twoset::Builder::overlap_format`].
+    pub fn overlap_format(mut self, overlap_format: OverlapFormat) -> Self {
+        self.overlap_format = Some(overlap_format);
+        self
+    }
+
+    /// Set whether the overlaps are sorted and deduplicated before they are written. See
+    /// [`twoset::Builder::sort_overlaps`].
+    pub fn sort_overlaps(mut self, sort_overlaps: bool) -> Self {
+        self.sort_overlaps = Some(sort_overlaps);
+        self
+    }
+
+    /// Write an intermediate file to `path`, rather than to the temporary directory. Artifacts
+    /// that the chosen strategy does not write are ignored. See [`twoset::Builder::artifact_path`].
+    pub fn artifact_path<P: AsRef<Path>>(mut self, artifact: Artifact, path: P) -> Self {
+        self.artifacts
+            .set_path(artifact, path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set whether an intermediate file is kept once the estimates have been generated. See
+    /// [`twoset::Builder::keep_artifact`].
+    pub fn keep_artifact(mut self, artifact: Artifact, keep: bool) -> Self {
+        self.artifacts.set_keep(artifact, keep);
+        self
+    }
+
+    /// Set whether minimap2 maps pairs of reads in both directions. By default, this is the
+    /// default of the chosen strategy. See [`twoset::Builder::dual`] and [`ava::Builder::dual`].
+    pub fn dual(mut self, dual: bool) -> Self {
+        self.dual = Some(dual);
+        self
+    }
+
+    /// Set a callback that is called with each per-read estimate as soon as it is known. See
+    /// [`twoset::Builder::on_estimate`] and [`ava::Builder::on_estimate`] for when the estimates
+    /// are known.
+    pub fn on_estimate<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&PerReadEstimate) + Send + 'static,
+    {
+        self.on_estimate = Some(Box::new(callback));
+        self
+    }
+
+    /// Set a callback that is called with every overlap between the reads. See
+    /// [`twoset::Builder::on_mapping`].
+    pub fn on_mapping<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&PafRecord) + Send + 'static,
+    {
+        self.on_mapping = Some(Box::new(callback));
+        self
+    }
+
+    /// Change any of minimap2's mapping options - an escape hatch for experts. See
+    /// [`twoset::Builder::with_mapopt`]. The sketch strategy does not use minimap2, so ignores it.
+    pub fn with_mapopt<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut MapOpt) + Send + Sync + 'static,
+    {
+        self.overrides.add_mapopt(f);
+        self
+    }
+
+    /// Change any of minimap2's index options - an escape hatch for experts. See
+    /// [`twoset::Builder::with_idxopt`]. The sketch strategy does not use minimap2, so ignores it.
+    pub fn with_idxopt<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut IdxOpt) + Send + Sync + 'static,
+    {
+        self.overrides.add_idxopt(f);
+        self
+    }
+
+    /// Set how the genome size estimate for each read is calculated from its overlaps. See
+    /// [`twoset::Builder::per_read_estimator`].
+    pub fn per_read_estimator<E>(mut self, estimator: E) -> Self
+    where
+        E: PerReadEstimator + 'static,
+    {
+        self.estimator = Some(Arc::new(estimator));
+        self
+    }
+
+    /// Count the reads and bases in the input, and choose the strategy for it with
+    /// [`StrategyKind::auto`]. Only the reads allowed by the [allowlist][Self::allowlist] and
+    /// [blocklist][Self::blocklist] are counted. The records are only checked if
+    /// [`validate_records`][Self::validate_records] is set, and malformed records are then skipped
+    /// if [`skip_bad_records`][Self::skip_bad_records] is set. The builder's strategy is not changed -
+    /// set it to the choice with [`strategy`][Self::strategy].
+    ///
+    /// This reads the whole input, so the input is read twice when the estimate is made, unless
+    /// the count is [cached][Self::cache_count].
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`IoError`][crate::error::LrgeError::IoError] if the input, allowlist, or
+    /// blocklist cannot be read.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use liblrge::{Estimate, StrategyBuilder};
+    ///
+    /// let builder = StrategyBuilder::new("reads.fastq");
+    /// let choice = builder.choose_strategy().unwrap();
+    /// println!("Using {:?}, as {}", choice.kind, choice.reason);
+    /// let mut strategy = builder.strategy(choice.kind).build();
+    /// let est_result = strategy.estimate(true, None, None).unwrap();
+    /// ```
+    pub fn choose_strategy(&self) -> crate::Result<AutoStrategy> {
+        let filter = ReadFilter::new(self.allowlist.as_deref(), self.blocklist.as_deref())?;
+        let (num_reads, num_bases) = sample::count_records_and_bases(
+            &self.input,
+            self.max_bad_records,
+            &filter,
+            self.validate_records.unwrap_or_default(),
+            self.cache_count.unwrap_or_default(),
+        )?;
+        Ok(StrategyKind::auto(num_reads as u64, num_bases))
+    }
+
+    /// Build the [`Strategy`], after checking that the configuration is valid. See
+    /// [`twoset::Builder::try_build`], [`ava::Builder::try_build`], and
+    /// [`sketch::Builder::try_build`] for the checks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::{StrategyBuilder, StrategyKind};
+    ///
+    /// let result = StrategyBuilder::new("input.fastq")
+    ///     .strategy(StrategyKind::Ava { num_reads: 1 })
+    ///     .try_build();
+    /// assert!(result.is_err());
+    /// ```
+    pub fn try_build(self) -> crate::Result<Strategy> {
+        let input = self.input.clone();
+        match self.kind {
+            StrategyKind::TwoSet { .. } => {
+                self.twoset_builder().try_build(input).map(Strategy::from)
+            }
+            StrategyKind::Ava { .. } => self.ava_builder().try_build(input).map(Strategy::from),
+            StrategyKind::Sketch { .. } => {
+                self.sketch_builder().try_build(input).map(Strategy::from)
+            }
+        }
+    }
+
+    /// Build the [`Strategy`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::StrategyBuilder;
+    ///
+    /// let strategy = StrategyBuilder::new("input.fastq").threads(4).build();
+    /// ```
+    pub fn build(self) -> Strategy {
+        let input = self.input.clone();
+        match self.kind {
+            StrategyKind::TwoSet { .. } => Strategy::from(self.twoset_builder().build(input)),
+            StrategyKind::Ava { .. } => Strategy::from(self.ava_builder().build(input)),
+            StrategyKind::Sketch { .. } => Strategy::from(self.sketch_builder().build(input)),
+        }
+    }
+
+    fn twoset_builder(self) -> twoset::Builder {
+        let StrategyKind::TwoSet {
+            target_num_reads,
+            query_num_reads,
+        } = self.kind
+        else {
+            unreachable!("only called for the two-set strategy")
+        };
+        let mut builder = twoset::Builder::new()
+            .target_num_reads(target_num_reads)
+            .query_num_reads(query_num_reads);
+        if let Some(use_min_ref) = self.use_min_ref {
+            builder = builder.use_min_ref(use_min_ref);
+        }
+        if let Some(fraction) = self.resample_no_mapping_fraction {
+            builder = builder.resample_no_mapping_fraction(fraction);
+        }
+        if let Some(fraction) = self.target_fraction {
+            builder = builder.target_fraction(fraction);
+        }
+        if let Some(fraction) = self.query_fraction {
+            builder = builder.query_fraction(fraction);
+        }
+        apply_shared_settings!(builder, self)
+    }
+
+    fn ava_builder(self) -> ava::Builder {
+        let StrategyKind::Ava { num_reads } = self.kind else {
+            unreachable!("only called for the all-vs-all strategy")
+        };
+        let mut builder = ava::Builder::new().num_reads(num_reads);
+        if let Some(fraction) = self.read_fraction {
+            builder = builder.read_fraction(fraction);
+        }
+        apply_shared_settings!(builder, self)
+    }
+
+    /// The sketch strategy does not align reads, so only takes the settings that are not about
+    /// alignment or overlaps.
+    fn sketch_builder(self) -> sketch::Builder {
+        let StrategyKind::Sketch { num_reads } = self.kind else {
+            unreachable!("only called for the sketch strategy")
+        };
+        let mut builder = sketch::Builder::new()
+            .num_reads(num_reads)
+            .artifacts(self.artifacts)
+            .thread_pool(self.thread_pool)
+            .seed(self.seed)
+            .skip_bad_records(self.max_bad_records);
+        if let Some(validate_records) = self.validate_records {
+            builder = builder.validate_records(validate_records);
+        }
+        if let Some(cache_count) = self.cache_count {
+            builder = builder.cache_count(cache_count);
+        }
+        if let Some(fraction) = self.read_fraction {
+            builder = builder.read_fraction(fraction);
+        }
+        if let Some(allowlist) = self.allowlist {
+            builder = builder.allowlist(allowlist);
+        }
+        if let Some(blocklist) = self.blocklist {
+            builder = builder.blocklist(blocklist);
+        }
+        if let Some(tmpdir) = self.tmpdir {
+            builder = builder.tmpdir(tmpdir);
+        }
+        if let Some(create_tmpdir) = self.create_tmpdir {
+            builder = builder.create_tmpdir(create_tmpdir);
+        }
+        if let Some(keep_temp) = self.keep_temp {
+            builder = builder.keep_temp(keep_temp);
+        }
+        if let Some(threads) = self.threads {
+            builder = builder.threads(threads);
+        }
+        if let Some(fraction) = self.max_no_mapping_fraction {
+            builder = builder.max_no_mapping_fraction(fraction);
+        }
+        if let Some(callback) = self.on_estimate {
+            builder = builder.on_estimate(callback);
+        }
+        if let Some(estimator) = self.estimator {
+            builder = builder.shared_per_read_estimator(estimator);
+        }
+        builder
+    }
+}
+
+/// Any of the genome size estimation strategies.
+///
+/// This allows frontends to handle both strategies with one type - e.g., when running a
+/// [batch][crate::batch] of estimates - rather than a `Box<dyn Estimate>`. Build it with a
+/// [`StrategyBuilder`], or convert a [`TwoSetStrategy`], [`AvaStrategy`], or [`SketchStrategy`]
+/// into it with [`From`].
+pub enum Strategy {
+    /// A [`TwoSetStrategy`].
+    TwoSet(TwoSetStrategy),
+    /// An [`AvaStrategy`].
+    Ava(AvaStrategy),
+    /// A [`SketchStrategy`].
+    Sketch(SketchStrategy),
+}
+
+impl Strategy {
+    pub(crate) fn set_thread_pool(&mut self, pool: Arc<rayon::ThreadPool>) {
+        match self {
+            Strategy::TwoSet(s) => s.set_thread_pool(pool),
+            Strategy::Ava(s) => s.set_thread_pool(pool),
+            Strategy::Sketch(s) => s.set_thread_pool(pool),
+        }
+    }
+
+    /// The path an intermediate file is written to, or `None` if the strategy does not write it.
+    /// See [`TwoSetStrategy::artifact_path`].
+    pub fn artifact_path(&self, artifact: Artifact) -> Option<PathBuf> {
+        match self {
+            Strategy::TwoSet(s) => s.artifact_path(artifact),
+            Strategy::Ava(s) => s.artifact_path(artifact),
+            Strategy::Sketch(s) => s.artifact_path(artifact),
+        }
+    }
+
+    /// The overlaps between the reads, read lazily from the overlaps file, or `None` for the
+    /// sketch strategy, which does not overlap the reads. See [`TwoSetStrategy::overlaps`].
+    pub fn overlaps(&self) -> crate::Result<Option<PafReader>> {
+        match self {
+            Strategy::TwoSet(s) => s.overlaps().map(Some),
+            Strategy::Ava(s) => s.overlaps().map(Some),
+            Strategy::Sketch(_) => Ok(None),
+        }
+    }
+
+    /// The directory the intermediate files are written to. See [`TwoSetStrategy::tmpdir`].
+    pub fn tmpdir(&self) -> &Path {
+        match self {
+            Strategy::TwoSet(s) => s.tmpdir(),
+            Strategy::Ava(s) => s.tmpdir(),
+            Strategy::Sketch(s) => s.tmpdir(),
+        }
+    }
+
+    pub(crate) fn set_tmpdir(&mut self, tmpdir: PathBuf) {
+        match self {
+            Strategy::TwoSet(s) => s.set_tmpdir(tmpdir),
+            Strategy::Ava(s) => s.set_tmpdir(tmpdir),
+            Strategy::Sketch(s) => s.set_tmpdir(tmpdir),
+        }
+    }
+
+    /// The kind of strategy, and its numbers of reads. If a fraction of the reads is used, the
+    /// numbers are only known once it is resolved with [`Strategy::resolve_fractions`].
+    pub fn kind(&self) -> StrategyKind {
+        match self {
+            Strategy::TwoSet(s) => StrategyKind::TwoSet {
+                target_num_reads: s.target_num_reads(),
+                query_num_reads: s.query_num_reads(),
+            },
+            Strategy::Ava(s) => StrategyKind::Ava {
+                num_reads: s.num_reads(),
+            },
+            Strategy::Sketch(s) => StrategyKind::Sketch {
+                num_reads: s.num_reads(),
+            },
+        }
+    }
+
+    /// Turn the fractions of the reads to use into numbers of reads, by counting the reads in the
+    /// input. See [`TwoSetStrategy::resolve_fractions`].
+    pub fn resolve_fractions(&mut self) -> crate::Result<()> {
+        match self {
+            Strategy::TwoSet(s) => s.resolve_fractions(),
+            Strategy::Ava(s) => s.resolve_fraction(),
+            Strategy::Sketch(s) => s.resolve_fraction(),
+        }
+    }
+
+    /// The number of threads the reads are overlapped with.
+    pub fn threads(&self) -> usize {
+        match self {
+            Strategy::TwoSet(s) => s.mapping_threads(),
+            Strategy::Ava(s) => s.mapping_threads(),
+            Strategy::Sketch(s) => s.threads(),
+        }
+    }
+}
+
+impl From<TwoSetStrategy> for Strategy {
+    fn from(strategy: TwoSetStrategy) -> Self {
+        Strategy::TwoSet(strategy)
+    }
+}
+
+impl From<AvaStrategy> for Strategy {
+    fn from(strategy: AvaStrategy) -> Self {
+        Strategy::Ava(strategy)
+    }
+}
+
+impl From<SketchStrategy> for Strategy {
+    fn from(strategy: SketchStrategy) -> Self {
+        Strategy::Sketch(strategy)
+    }
+}
+
+impl Estimate for Strategy {
+    fn generate_estimates(&mut self) -> crate::Result<RawEstimates> {
+        match self {
+            Strategy::TwoSet(s) => s.generate_estimates(),
+            Strategy::Ava(s) => s.generate_estimates(),
+            Strategy::Sketch(s) => s.generate_estimates(),
+        }
+    }
+
+    fn input_num_reads(&self) -> Option<u64> {
+        match self {
+            Strategy::TwoSet(s) => s.input_num_reads(),
+            Strategy::Ava(s) => s.input_num_reads(),
+            Strategy::Sketch(s) => s.input_num_reads(),
+        }
+    }
+
+    fn input_num_bases(&self) -> Option<u64> {
+        match self {
+            Strategy::TwoSet(s) => s.input_num_bases(),
+            Strategy::Ava(s) => s.input_num_bases(),
+            Strategy::Sketch(s) => s.input_num_bases(),
+        }
+    }
+
+    fn input_num_skipped(&self) -> Option<u64> {
+        match self {
+            Strategy::TwoSet(s) => s.input_num_skipped(),
+            Strategy::Ava(s) => s.input_num_skipped(),
+            Strategy::Sketch(s) => s.input_num_skipped(),
+        }
+    }
+
+    fn raw_estimates(&self) -> Option<&RawEstimates> {
+        match self {
+            Strategy::TwoSet(s) => s.raw_estimates(),
+            Strategy::Ava(s) => s.raw_estimates(),
+            Strategy::Sketch(s) => s.raw_estimates(),
+        }
+    }
+
+    fn retain_raw_estimates(&mut self, raw: RawEstimates) {
+        match self {
+            Strategy::TwoSet(s) => s.retain_raw_estimates(raw),
+            Strategy::Ava(s) => s.retain_raw_estimates(raw),
+            Strategy::Sketch(s) => s.retain_raw_estimates(raw),
+        }
+    }
+
+    fn gc_content(&self) -> Option<GcSummary> {
+        match self {
+            Strategy::TwoSet(s) => s.gc_content(),
+            Strategy::Ava(s) => s.gc_content(),
+            Strategy::Sketch(s) => s.gc_content(),
+        }
+    }
+
+    fn contained_fraction(&self) -> Option<f32> {
+        match self {
+            Strategy::TwoSet(s) => s.contained_fraction(),
+            Strategy::Ava(s) => s.contained_fraction(),
+            Strategy::Sketch(s) => s.contained_fraction(),
+        }
+    }
+
+    fn duplicate_fraction(&self) -> Option<f32> {
+        match self {
+            Strategy::TwoSet(s) => s.duplicate_fraction(),
+            Strategy::Ava(s) => s.duplicate_fraction(),
+            Strategy::Sketch(s) => s.duplicate_fraction(),
+        }
+    }
+
+    fn adapter_summary(&self) -> Option<AdapterSummary> {
+        match self {
+            Strategy::TwoSet(s) => s.adapter_summary(),
+            Strategy::Ava(s) => s.adapter_summary(),
+            Strategy::Sketch(s) => s.adapter_summary(),
+        }
+    }
+
+    fn num_overlaps(&self) -> Option<u64> {
+        match self {
+            Strategy::TwoSet(s) => s.num_overlaps(),
+            Strategy::Ava(s) => s.num_overlaps(),
+            Strategy::Sketch(s) => s.num_overlaps(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::LrgeError;
+    use crate::estimate::ESTIMATES_FILENAME;
+
+    #[test]
+    fn test_build_kind() {
+        let strategy = StrategyBuilder::new("input.fastq").build();
+        assert!(matches!(
+            strategy,
+            Strategy::TwoSet(s) if s.target_num_reads() == DEFAULT_TARGET_NUM_READS
+                && s.query_num_reads() == DEFAULT_QUERY_NUM_READS
+        ));
+
+        let strategy = StrategyBuilder::new("input.fastq")
+            .strategy(StrategyKind::TwoSet {
+                target_num_reads: 20,
+                query_num_reads: 10,
+            })
+            .build();
+        assert!(matches!(
+            strategy,
+            Strategy::TwoSet(s) if s.target_num_reads() == 20 && s.query_num_reads() == 10
+        ));
+
+        let strategy = StrategyBuilder::new("input.fastq")
+            .strategy(StrategyKind::ava())
+            .build();
+        assert!(matches!(strategy, Strategy::Ava(s) if s.num_reads() == DEFAULT_AVA_NUM_READS));
+
+        let strategy = StrategyBuilder::new("input.fastq")
+            .strategy(StrategyKind::sketch())
+            .build();
+        assert!(
+            matches!(strategy, Strategy::Sketch(s) if s.num_reads() == DEFAULT_SKETCH_NUM_READS)
+        );
+    }
+
+    #[test]
+    fn test_try_build() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("reads.fq");
+        std::fs::write(&input, "").unwrap();
+
+        let builder = || StrategyBuilder::new(&input).tmpdir(dir.path());
+        assert!(builder().try_build().is_ok());
+        assert!(builder()
+            .strategy(StrategyKind::Ava { num_reads: 2 })
+            .try_build()
+            .is_ok());
+
+        let invalid_option = |result: crate::Result<Strategy>| match result {
+            Err(LrgeError::InvalidConfiguration { option, .. }) => option,
+            Err(e) => panic!("expected an invalid configuration error, got {e:?}"),
+            Ok(_) => panic!("expected an invalid configuration error"),
+        };
+        assert_eq!(
+            invalid_option(
+                builder()
+                    .strategy(StrategyKind::Ava { num_reads: 1 })
+                    .try_build()
+            ),
+            "num_reads"
+        );
+        assert_eq!(
+            invalid_option(
+                builder()
+                    .strategy(StrategyKind::TwoSet {
+                        target_num_reads: 0,
+                        query_num_reads: 10
+                    })
+                    .try_build()
+            ),
+            "target_num_reads"
+        );
+        // the shared settings are passed on to the strategy's builder
+        assert_eq!(
+            invalid_option(builder().max_memory(Some(0)).try_build()),
+            "max_memory"
+        );
+        assert_eq!(
+            invalid_option(
+                builder()
+                    .strategy(StrategyKind::ava())
+                    .remove_internal(true, 2.0)
+                    .try_build()
+            ),
+            "max_overhang_ratio"
+        );
+        assert_eq!(
+            invalid_option(builder().max_divergence(Some(1.5)).try_build()),
+            "max_divergence"
+        );
+        assert_eq!(
+            invalid_option(
+                builder()
+                    .strategy(StrategyKind::Sketch { num_reads: 1 })
+                    .try_build()
+            ),
+            "num_reads"
+        );
+        assert_eq!(
+            invalid_option(
+                builder()
+                    .artifact_path(Artifact::Overlaps, dir.path().join("missing/overlaps.paf"))
+                    .try_build()
+            ),
+            "artifact_path"
+        );
+        assert_eq!(
+            invalid_option(builder().query_fraction(0.0).try_build()),
+            "query_fraction"
+        );
+        assert_eq!(
+            invalid_option(
+                builder()
+                    .strategy(StrategyKind::sketch())
+                    .read_fraction(1.5)
+                    .try_build()
+            ),
+            "read_fraction"
+        );
+        // fractions for another strategy are ignored
+        assert!(builder().read_fraction(1.5).try_build().is_ok());
+        assert_eq!(
+            invalid_option(
+                builder()
+                    .allowlist(dir.path().join("missing.txt"))
+                    .try_build()
+            ),
+            "allowlist"
+        );
+        assert_eq!(
+            invalid_option(
+                builder()
+                    .blocklist(dir.path().join("missing.txt"))
+                    .try_build()
+            ),
+            "blocklist"
+        );
+    }
+
+    #[test]
+    fn test_artifact_path() {
+        let builder = || {
+            StrategyBuilder::new("input.fastq")
+                .tmpdir("/tmp/lrge")
+                .overlap_format(OverlapFormat::Sam)
+                .artifact_path(Artifact::Query, "query.fa")
+        };
+
+        let strategy = builder().build();
+        assert_eq!(
+            strategy.artifact_path(Artifact::Target),
+            Some(PathBuf::from("/tmp/lrge/target.fa"))
+        );
+        assert_eq!(
+            strategy.artifact_path(Artifact::Query),
+            Some(PathBuf::from("query.fa"))
+        );
+        assert_eq!(
+            strategy.artifact_path(Artifact::Overlaps),
+            Some(PathBuf::from("/tmp/lrge/overlaps.sam"))
+        );
+        assert_eq!(strategy.artifact_path(Artifact::Reads), None);
+
+        let strategy = builder().strategy(StrategyKind::ava()).build();
+        assert_eq!(strategy.artifact_path(Artifact::Query), None);
+        assert_eq!(
+            strategy.artifact_path(Artifact::Reads),
+            Some(PathBuf::from("/tmp/lrge/reads.fa"))
+        );
+
+        let strategy = builder().strategy(StrategyKind::sketch()).build();
+        assert_eq!(strategy.artifact_path(Artifact::Overlaps), None);
+        assert_eq!(
+            strategy.artifact_path(Artifact::Reads),
+            Some(PathBuf::from("/tmp/lrge/reads.fa"))
+        );
+    }
+
+    #[test]
+    fn test_create_tmpdir() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("reads.fa");
+        let seq = "ACGTTGCAAGCTAGCTAGGATCCATGCAGT".repeat(20);
+        let reads: String = (0..10).map(|i| format!(">r{i}\n{seq}\n")).collect();
+        std::fs::write(&input, reads).unwrap();
+        let builder = |keep_temp| {
+            StrategyBuilder::new(&input)
+                .strategy(StrategyKind::Sketch { num_reads: 10 })
+                .tmpdir(dir.path())
+                .create_tmpdir(true)
+                .keep_temp(keep_temp)
+        };
+
+        let mut strategy = builder(false).build();
+        assert_eq!(strategy.tmpdir(), dir.path());
+        strategy.estimate(true, None, None).unwrap();
+        let tmpdir = strategy.tmpdir().to_path_buf();
+        assert_eq!(tmpdir.parent(), Some(dir.path()));
+        assert!(tmpdir.join(ESTIMATES_FILENAME).is_file());
+        drop(strategy);
+        assert!(!tmpdir.exists());
+
+        let mut strategy = builder(true).build();
+        strategy.estimate(true, None, None).unwrap();
+        let tmpdir = strategy.tmpdir().to_path_buf();
+        drop(strategy);
+        assert!(tmpdir.join(ESTIMATES_FILENAME).is_file());
+
+        let mut strategy = builder(false).keep_artifact(Artifact::Reads, true).build();
+        strategy.estimate(true, None, None).unwrap();
+        let tmpdir = strategy.tmpdir().to_path_buf();
+        drop(strategy);
+        assert!(tmpdir.join("reads.fa").is_file());
+        assert!(!tmpdir.join(ESTIMATES_FILENAME).exists());
+    }
+
+    #[test]
+    fn test_resolve_fractions() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("reads.fa");
+        let reads: String = (0..100).map(|i| format!(">r{i}\nACGT\n")).collect();
+        std::fs::write(&input, reads).unwrap();
+
+        let mut strategy = StrategyBuilder::new(&input)
+            .strategy(StrategyKind::ava())
+            .read_fraction(0.1)
+            .build();
+        assert_eq!(
+            strategy.kind(),
+            StrategyKind::Ava {
+                num_reads: DEFAULT_AVA_NUM_READS
+            }
+        );
+        strategy.resolve_fractions().unwrap();
+        assert_eq!(strategy.kind(), StrategyKind::Ava { num_reads: 10 });
+
+        let mut strategy = StrategyBuilder::new(&input)
+            .target_fraction(0.2)
+            .query_fraction(0.05)
+            .threads(2)
+            .build();
+        strategy.resolve_fractions().unwrap();
+        assert_eq!(
+            strategy.kind(),
+            StrategyKind::TwoSet {
+                target_num_reads: 20,
+                query_num_reads: 5
+            }
+        );
+        assert_eq!(strategy.threads(), 2);
+    }
+
+    #[test]
+    fn test_minimap2_option_overrides() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("reads.fa");
+        let reads: String = (0..20)
+            .map(|i| format!(">r{i}\n{}\n", "ACGTTGCAAGCTAGCTAGGATCCATGCAGT".repeat(20)))
+            .collect();
+        std::fs::write(&input, reads).unwrap();
+
+        for kind in [
+            StrategyKind::Ava { num_reads: 20 },
+            StrategyKind::TwoSet {
+                target_num_reads: 10,
+                query_num_reads: 10,
+            },
+        ] {
+            let indexes = Arc::new(AtomicUsize::new(0));
+            let counter = Arc::clone(&indexes);
+            let mut strategy = StrategyBuilder::new(&input)
+                .strategy(kind)
+                .tmpdir(dir.path())
+                .seed(Some(1))
+                .with_idxopt(move |_| {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                })
+                // no chain of these reads can score this much, so none of them overlap
+                .with_mapopt(|opt| opt.min_chain_score = 100_000)
+                .build();
+            let estimate = strategy.estimate(true, None, None).unwrap();
+            assert_eq!(indexes.load(Ordering::Relaxed), 1, "{kind:?}");
+            assert_eq!(estimate.num_finite, 0, "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("reads.fa");
+        let reads: String = (0..20)
+            .map(|i| format!(">r{i}\n{}\n", "ACGTTGCAAGCTAGCTAGGATCCATGCAGT".repeat(20)))
+            .collect();
+        std::fs::write(&input, reads).unwrap();
+        let builder = |kind| {
+            StrategyBuilder::new(&input)
+                .strategy(kind)
+                .tmpdir(dir.path())
+                .create_tmpdir(true)
+                .seed(Some(1))
+        };
+
+        let mut strategy = builder(StrategyKind::Ava { num_reads: 20 }).build();
+        assert!(strategy.overlaps().is_err());
+        let estimate = strategy.estimate(true, None, None).unwrap();
+        let overlaps: Vec<PafRecord> = strategy
+            .overlaps()
+            .unwrap()
+            .unwrap()
+            .collect::<crate::Result<_>>()
+            .unwrap();
+        assert!(!overlaps.is_empty());
+        assert!(overlaps.iter().all(|o| o.query_name.starts_with(b"r")));
+        assert_eq!(estimate.num_finite, 20);
+
+        let strategy = builder(StrategyKind::TwoSet {
+            target_num_reads: 10,
+            query_num_reads: 10,
+        })
+        .overlap_format(OverlapFormat::Sam)
+        .build();
+        assert!(matches!(
+            strategy.overlaps(),
+            Err(LrgeError::InvalidConfiguration {
+                option: "overlap_format",
+                ..
+            })
+        ));
+
+        let strategy = builder(StrategyKind::Sketch { num_reads: 20 }).build();
+        assert!(strategy.overlaps().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_raw_estimates_retained() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("reads.fa");
+        let reads: String = (0..20)
+            .map(|i| format!(">r{i}\n{}\n", "ACGTTGCAAGCTAGCTAGGATCCATGCAGT".repeat(20)))
+            .collect();
+        std::fs::write(&input, reads).unwrap();
+
+        for kind in [
+            StrategyKind::Ava { num_reads: 20 },
+            StrategyKind::Sketch { num_reads: 20 },
+        ] {
+            let mut strategy = StrategyBuilder::new(&input)
+                .strategy(kind)
+                .tmpdir(dir.path())
+                .create_tmpdir(true)
+                .seed(Some(1))
+                .build();
+            assert!(strategy.raw_estimates().is_none());
+            let result = strategy.estimate(true, None, None).unwrap();
+            let raw = strategy.raw_estimates().unwrap();
+            assert_eq!(raw.num_reads(), result.num_reads as usize, "{kind:?}");
+            assert_eq!(raw.no_mapping_count, result.no_mapping_count, "{kind:?}");
+            assert_eq!(
+                raw.sorted_values(true).len(),
+                result.num_finite as usize,
+                "{kind:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_auto() {
+        let choice = StrategyKind::auto(1, 100);
+        assert_eq!(choice.kind, StrategyKind::Ava { num_reads: 2 });
+        assert_eq!(choice.input_num_reads, 1);
+        assert_eq!(choice.input_num_bases, 100);
+
+        let choice = StrategyKind::auto(AUTO_MIN_TWOSET_READS - 1, AUTO_MIN_TWOSET_BASES);
+        assert_eq!(
+            choice.kind,
+            StrategyKind::Ava {
+                num_reads: AUTO_MIN_TWOSET_READS as usize - 1
+            }
+        );
+        assert!(choice.reason.contains("reads"));
+
+        let choice = StrategyKind::auto(100_000, AUTO_MIN_TWOSET_BASES - 1);
+        assert_eq!(
+            choice.kind,
+            StrategyKind::Ava {
+                num_reads: DEFAULT_AVA_NUM_READS
+            }
+        );
+        assert!(choice.reason.contains("bases"));
+
+        let choice = StrategyKind::auto(AUTO_MIN_TWOSET_READS, AUTO_MIN_TWOSET_BASES);
+        assert_eq!(choice.kind, StrategyKind::default());
+    }
+
+    #[test]
+    fn test_choose_strategy() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("reads.fa");
+        let reads: String = (0..20)
+            .map(|i| format!(">r{i}\n{}\n", "ACGTTGCAAGCTAGCTAGGATCCATGCAGT".repeat(20)))
+            .collect();
+        std::fs::write(&input, reads).unwrap();
+        let blocklist = dir.path().join("blocklist.txt");
+        std::fs::write(&blocklist, "r0\nr1\n").unwrap();
+
+        let choice = StrategyBuilder::new(&input).choose_strategy().unwrap();
+        assert_eq!(choice.kind, StrategyKind::Ava { num_reads: 20 });
+        assert_eq!(choice.input_num_reads, 20);
+        assert_eq!(choice.input_num_bases, 20 * 600);
+
+        let choice = StrategyBuilder::new(&input)
+            .blocklist(&blocklist)
+            .choose_strategy()
+            .unwrap();
+        assert_eq!(choice.kind, StrategyKind::Ava { num_reads: 18 });
+
+        let missing = StrategyBuilder::new(dir.path().join("missing.fa")).choose_strategy();
+        assert!(matches!(missing, Err(LrgeError::IoError(_))));
+    }
+}
@@ -0,0 +1,364 @@
+//! Follow the genome size estimate as the per-read estimates are computed.
+//!
+//! A strategy can be given a callback, with `on_estimate` on its builder (e.g.,
+//! [`twoset::Builder::on_estimate`][crate::twoset::Builder::on_estimate]), which is called with each
+//! [`PerReadEstimate`] as soon as it is known. Feeding these into a [`RunningEstimate`] keeps the
+//! median and quantiles up to date, so an interactive caller can watch the estimate converge rather
+//! than waiting for all reads to be overlapped.
+//!
+//! With the [`TwoSetStrategy`][crate::TwoSetStrategy], a query read's estimate is known as soon as it
+//! has been mapped to the target reads. So, when a callback is given, the target reads are used as
+//! the minimap2 reference, unless [`use_min_ref`][crate::twoset::Builder::use_min_ref] is set
+//! explicitly. With the [`AvaStrategy`][crate::AvaStrategy], a read's overlaps are only all known
+//! once every read has been mapped, so the estimates are all passed to the callback at the end.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::sync::{Arc, Mutex};
+//! use liblrge::estimate::{LOWER_QUANTILE, UPPER_QUANTILE};
+//! use liblrge::stream::RunningEstimate;
+//! use liblrge::{twoset::Builder, Estimate};
+//!
+//! let running = RunningEstimate::new(true, Some(LOWER_QUANTILE), Some(UPPER_QUANTILE)).unwrap();
+//! let running = Arc::new(Mutex::new(running));
+//!
+//! let progress = Arc::clone(&running);
+//! let mut strategy = Builder::new()
+//!     .on_estimate(move |est| {
+//!         let mut running = progress.lock().unwrap();
+//!         running.push(est);
+//!         println!("{} reads: {:?}", running.num_reads(), running.estimate());
+//!     })
+//!     .build("path/to/reads.fastq");
+//!
+//! let est_result = strategy.estimate(true, Some(LOWER_QUANTILE), Some(UPPER_QUANTILE)).unwrap();
+//! // the running estimate ends up the same as the final estimate
+//! assert_eq!(running.lock().unwrap().estimate(), est_result.estimate);
+//! ```
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::sync::Mutex;
+
+use crate::estimate::{validate_quantiles, EstimateResult, PerReadEstimate};
+
+/// The callback given to a strategy's builder for each per-read estimate.
+pub(crate) type Callback = Box<dyn FnMut(&PerReadEstimate) + Send>;
+
+/// Passes per-read estimates to a caller's callback. The calls are serialised, so the callback
+/// does not need to be `Sync`, even though estimates are computed in many threads.
+pub(crate) struct EstimateSink(Mutex<Callback>);
+
+impl EstimateSink {
+    pub(crate) fn new(callback: Callback) -> Self {
+        Self(Mutex::new(callback))
+    }
+
+    /// Pass an estimate to the callback.
+    pub(crate) fn send(&self, estimate: &PerReadEstimate) {
+        // a panic in the callback has already been propagated, so a poisoned lock can be ignored
+        let mut callback = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        callback(estimate);
+    }
+}
+
+/// An `f32` ordered by [`f32::total_cmp`], so it can be kept in a [`BinaryHeap`].
+#[derive(Debug, Clone, Copy)]
+struct Value(f32);
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// A quantile of a growing set of values, kept up to date with two heaps.
+///
+/// The low heap holds the values up to and including the one at the quantile's (floored) position
+/// in the sorted values, and the high heap holds the rest. So the two values either side of the
+/// quantile are the tops of the heaps, and the quantile is interpolated between them in the same
+/// way as the final estimate (see [`Estimate::estimate`][crate::Estimate::estimate]).
+#[derive(Debug)]
+struct RunningQuantile {
+    quantile: f32,
+    low: BinaryHeap<Value>,
+    high: BinaryHeap<Reverse<Value>>,
+}
+
+impl RunningQuantile {
+    fn new(quantile: f32) -> Self {
+        Self {
+            quantile,
+            low: BinaryHeap::new(),
+            high: BinaryHeap::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.low.len() + self.high.len()
+    }
+
+    /// The position of the quantile in the sorted values, and its index.
+    fn position(&self) -> (f32, usize) {
+        let pos = self.quantile * (self.len() - 1) as f32;
+        (pos, pos.floor() as usize)
+    }
+
+    fn push(&mut self, value: f32) {
+        let value = Value(value);
+        if self.low.peek().is_none_or(|top| value <= *top) {
+            self.low.push(value);
+        } else {
+            self.high.push(Reverse(value));
+        }
+
+        let (_, idx) = self.position();
+        while self.low.len() > idx + 1 {
+            let top = self.low.pop().expect("the low heap is not empty");
+            self.high.push(Reverse(top));
+        }
+        while self.low.len() < idx + 1 {
+            let Reverse(top) = self.high.pop().expect("the high heap is not empty");
+            self.low.push(top);
+        }
+    }
+
+    fn value(&self) -> Option<f32> {
+        let Value(below) = *self.low.peek()?;
+        let (pos, idx) = self.position();
+        let frac = pos - idx as f32;
+
+        // interpolating between equal values (e.g., two infinities) would give NaN
+        match self.high.peek() {
+            Some(Reverse(Value(above))) if frac > 0.0 && below != *above => {
+                Some(below * (1.0 - frac) + above * frac)
+            }
+            _ => Some(below),
+        }
+    }
+}
+
+/// The genome size estimate, and its quantiles, of the per-read estimates seen so far.
+///
+/// Once all per-read estimates have been [pushed][RunningEstimate::push], this is the same as the
+/// estimate given by [`Estimate::estimate`][crate::Estimate::estimate] with the same arguments.
+/// Each push takes `O(log n)` time.
+#[derive(Debug)]
+pub struct RunningEstimate {
+    finite: bool,
+    lower: Option<RunningQuantile>,
+    median: RunningQuantile,
+    upper: Option<RunningQuantile>,
+    num_reads: u32,
+    num_finite: u32,
+    no_mapping_count: u32,
+}
+
+impl RunningEstimate {
+    /// Create a running estimate. The arguments are the same as for
+    /// [`Estimate::estimate`][crate::Estimate::estimate].
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidQuantile`][crate::error::LrgeError::InvalidQuantile] error if the
+    /// quantiles are invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::stream::RunningEstimate;
+    ///
+    /// // the median and interquartile range of the finite estimates
+    /// let running = RunningEstimate::new(true, Some(0.25), Some(0.75)).unwrap();
+    /// assert_eq!(running.estimate(), None);
+    /// ```
+    pub fn new(
+        finite: bool,
+        lower_quant: Option<f32>,
+        upper_quant: Option<f32>,
+    ) -> crate::Result<Self> {
+        validate_quantiles(lower_quant, upper_quant)?;
+        Ok(Self {
+            finite,
+            lower: lower_quant.map(RunningQuantile::new),
+            median: RunningQuantile::new(0.5),
+            upper: upper_quant.map(RunningQuantile::new),
+            num_reads: 0,
+            num_finite: 0,
+            no_mapping_count: 0,
+        })
+    }
+
+    /// Add a per-read estimate.
+    pub fn push(&mut self, estimate: &PerReadEstimate) {
+        self.num_reads += 1;
+        if !estimate.mapped {
+            self.no_mapping_count += 1;
+        }
+        if estimate.estimate.is_finite() {
+            self.num_finite += 1;
+        } else if self.finite {
+            return;
+        }
+
+        let quantiles = [
+            self.lower.as_mut(),
+            Some(&mut self.median),
+            self.upper.as_mut(),
+        ];
+        for quantile in quantiles.into_iter().flatten() {
+            quantile.push(estimate.estimate);
+        }
+    }
+
+    /// The current genome size estimate - i.e., the median of the per-read estimates so far.
+    pub fn estimate(&self) -> Option<f32> {
+        self.median.value()
+    }
+
+    /// The current lower quantile of the per-read estimates, if one was requested.
+    pub fn lower(&self) -> Option<f32> {
+        self.lower.as_ref().and_then(RunningQuantile::value)
+    }
+
+    /// The current upper quantile of the per-read estimates, if one was requested.
+    pub fn upper(&self) -> Option<f32> {
+        self.upper.as_ref().and_then(RunningQuantile::value)
+    }
+
+    /// The number of per-read estimates so far.
+    pub fn num_reads(&self) -> u32 {
+        self.num_reads
+    }
+
+    /// The number of reads so far that did not have an overlap.
+    pub fn no_mapping_count(&self) -> u32 {
+        self.no_mapping_count
+    }
+
+    /// The estimate so far, as an [`EstimateResult`]. The input read counts and GC content are not
+    /// known until the strategy is finished, so they are `None`.
+    pub fn result(&self) -> EstimateResult {
+        EstimateResult {
+            lower: self.lower(),
+            estimate: self.estimate(),
+            upper: self.upper(),
+            no_mapping_count: self.no_mapping_count,
+            num_reads: self.num_reads,
+            num_finite: self.num_finite,
+            input_num_reads: None,
+            input_num_bases: None,
+            input_num_skipped: None,
+            gc_content: None,
+            contained_fraction: None,
+            duplicate_fraction: None,
+            adapters: None,
+            poisson_interval: None,
+            quantiles: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::estimate::SavedEstimates;
+    use crate::Estimate;
+
+    fn per_read(estimate: f32) -> PerReadEstimate {
+        PerReadEstimate {
+            read_id: b"read".to_vec(),
+            estimate,
+            mapped: estimate.is_finite(),
+        }
+    }
+
+    #[test]
+    fn test_running_quantile() {
+        let mut median = RunningQuantile::new(0.5);
+        assert_eq!(median.value(), None);
+        median.push(5.0);
+        assert_eq!(median.value(), Some(5.0));
+        median.push(1.0);
+        assert_eq!(median.value(), Some(3.0));
+        median.push(9.0);
+        assert_eq!(median.value(), Some(5.0));
+        median.push(f32::INFINITY);
+        median.push(f32::INFINITY);
+        assert_eq!(median.value(), Some(9.0));
+        median.push(f32::INFINITY);
+        assert_eq!(median.value(), Some(f32::INFINITY));
+    }
+
+    #[test]
+    fn test_running_estimate_matches_final_estimate() {
+        // a deterministic, unordered sequence of estimates, with some reads that did not overlap
+        let values: Vec<f32> = (0..501u32)
+            .map(|i| match (i * 7919) % 1009 {
+                v if v % 13 == 0 => f32::INFINITY,
+                v => v as f32 * 1000.0,
+            })
+            .collect();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("estimates.tsv");
+        let estimates: Vec<PerReadEstimate> = values.iter().map(|v| per_read(*v)).collect();
+        crate::estimate::write_per_read_estimates(&path, &estimates).unwrap();
+
+        for finite in [true, false] {
+            let mut running = RunningEstimate::new(finite, Some(0.15), Some(0.65)).unwrap();
+            for est in &estimates {
+                running.push(est);
+            }
+            let expected = SavedEstimates::new(&path)
+                .estimate(finite, Some(0.15), Some(0.65))
+                .unwrap();
+            assert_eq!(running.result(), expected);
+        }
+    }
+
+    #[test]
+    fn test_running_estimate_finite() {
+        let mut running = RunningEstimate::new(true, None, None).unwrap();
+        running.push(&per_read(f32::INFINITY));
+        assert_eq!(running.estimate(), None);
+        assert_eq!(running.num_reads(), 1);
+        assert_eq!(running.no_mapping_count(), 1);
+
+        running.push(&per_read(10.0));
+        assert_eq!(running.estimate(), Some(10.0));
+        assert_eq!(running.lower(), None);
+        assert_eq!(running.result().num_finite, 1);
+    }
+
+    #[test]
+    fn test_running_estimate_invalid_quantile() {
+        assert!(RunningEstimate::new(true, Some(0.6), None).is_err());
+    }
+
+    #[test]
+    fn test_estimate_sink() {
+        let seen = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let sink_seen = std::sync::Arc::clone(&seen);
+        let sink = EstimateSink::new(Box::new(move |est: &PerReadEstimate| {
+            sink_seen.lock().unwrap().push(est.estimate)
+        }));
+        sink.send(&per_read(1.0));
+        sink.send(&per_read(2.0));
+        assert_eq!(*seen.lock().unwrap(), vec![1.0, 2.0]);
+    }
+}
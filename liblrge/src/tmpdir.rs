@@ -0,0 +1,141 @@
+//! Temporary directories that a strategy creates, and removes, itself.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+
+/// A uniquely named directory that a strategy creates inside its temporary directory when it
+/// generates an estimate. The directory, and everything in it, is removed when the strategy is
+/// dropped, unless it is kept. Individual files in it can be [retained][ManagedTmpdir::retain], in
+/// which case everything else in the directory is removed.
+#[derive(Debug)]
+pub(crate) struct ManagedTmpdir {
+    /// Whether the directory is left in place when it is dropped.
+    keep: bool,
+    /// The directory, once it has been created.
+    dir: Option<tempfile::TempDir>,
+    /// The files in the directory that are left in place when it is dropped.
+    retained: Vec<PathBuf>,
+}
+
+impl ManagedTmpdir {
+    pub(crate) fn new(keep: bool) -> Self {
+        Self {
+            keep,
+            dir: None,
+            retained: Vec::new(),
+        }
+    }
+
+    /// Leave the file at `path` in place when the directory is dropped. Paths outside the
+    /// directory are never removed, so they can be retained or not.
+    pub(crate) fn retain(&mut self, path: PathBuf) {
+        if !self.retained.contains(&path) {
+            self.retained.push(path);
+        }
+    }
+
+    /// Create the directory inside `parent`, if it has not been created already, and return its
+    /// path.
+    pub(crate) fn create(&mut self, parent: &Path) -> io::Result<PathBuf> {
+        if let Some(dir) = &self.dir {
+            return Ok(dir.path().to_path_buf());
+        }
+        let dir = tempfile::Builder::new()
+            .prefix("lrge-")
+            .disable_cleanup(self.keep)
+            .tempdir_in(parent)?;
+        debug!("Created temporary directory {}", dir.path().display());
+        let path = dir.path().to_path_buf();
+        self.dir = Some(dir);
+        Ok(path)
+    }
+}
+
+impl Drop for ManagedTmpdir {
+    fn drop(&mut self) {
+        // without any retained files, the whole directory is removed when it is dropped
+        if self.keep || self.retained.is_empty() {
+            return;
+        }
+        let Some(dir) = self.dir.take() else {
+            return;
+        };
+        let dir = dir.keep();
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return;
+        };
+        for path in entries.filter_map(|e| Some(e.ok()?.path())) {
+            if self.retained.contains(&path) {
+                debug!("Kept {} in temporary directory", path.display());
+                continue;
+            }
+            let _ = if path.is_dir() {
+                fs::remove_dir_all(&path)
+            } else {
+                fs::remove_file(&path)
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_managed_tmpdir() {
+        let parent = tempfile::tempdir().unwrap();
+        let mut managed = ManagedTmpdir::new(false);
+        let path = managed.create(parent.path()).unwrap();
+        assert!(path.is_dir());
+        assert_eq!(path.parent(), Some(parent.path()));
+        assert!(path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("lrge-"));
+        // the same directory is used every time
+        assert_eq!(managed.create(parent.path()).unwrap(), path);
+        assert_eq!(managed.create(&path).unwrap(), path);
+
+        drop(managed);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_kept_managed_tmpdir() {
+        let parent = tempfile::tempdir().unwrap();
+        let mut managed = ManagedTmpdir::new(true);
+        let path = managed.create(parent.path()).unwrap();
+        drop(managed);
+        assert!(path.is_dir());
+    }
+
+    #[test]
+    fn test_retained_files() {
+        let parent = tempfile::tempdir().unwrap();
+        let mut managed = ManagedTmpdir::new(false);
+        let path = managed.create(parent.path()).unwrap();
+        for name in ["overlaps.paf", "target.fa", "query.fa"] {
+            fs::write(path.join(name), "").unwrap();
+        }
+        fs::create_dir(path.join("subdir")).unwrap();
+        managed.retain(path.join("overlaps.paf"));
+        managed.retain(parent.path().join("elsewhere.paf"));
+        drop(managed);
+
+        let remaining: Vec<_> = fs::read_dir(&path)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(remaining, vec!["overlaps.paf"]);
+    }
+
+    #[test]
+    fn test_managed_tmpdir_missing_parent() {
+        let mut managed = ManagedTmpdir::new(false);
+        assert!(managed.create(Path::new("/missing/parent")).is_err());
+    }
+}
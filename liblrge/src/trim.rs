@@ -0,0 +1,130 @@
+//! Quality trimming of the ends of the sampled reads.
+//!
+//! Long reads often have low-quality tails. These reduce the overlaps that are found, and make the
+//! overhangs of the overlaps that are found look longer than they are, which can make an overlap
+//! look like an internal match. When [`QualityTrim`] is given to a strategy, the ends of each
+//! sampled read are trimmed with a sliding window: bases are removed from each end until a window
+//! of [`window_size`][QualityTrim::window_size] bases has a mean quality of at least
+//! [`min_quality`][QualityTrim::min_quality]. A read with no such window is not sampled.
+//!
+//! Only reads with qualities - i.e., from FASTQ, BAM, CRAM, or SAM files - are trimmed. FASTA reads
+//! are sampled as they are.
+use std::ops::Range;
+
+/// The default number of bases in the sliding window that quality trimming uses.
+pub const DEFAULT_TRIM_WINDOW_SIZE: usize = 20;
+
+/// The offset of the quality scores in FASTQ files, as they are given to [`QualityTrim::range`].
+const PHRED_OFFSET: u8 = 33;
+
+/// Settings for trimming the low-quality ends of the sampled reads.
+///
+/// # Examples
+///
+/// ```
+/// use liblrge::trim::QualityTrim;
+///
+/// let trim = QualityTrim::new(10).window_size(5);
+/// assert_eq!(trim.min_quality, 10);
+/// assert_eq!(trim.window_size, 5);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QualityTrim {
+    /// The lowest mean (Phred) quality of a window that is kept
+    pub min_quality: u8,
+    /// The number of bases in the sliding window
+    pub window_size: usize,
+}
+
+impl QualityTrim {
+    /// Trim the ends of reads whose windows have a mean quality below `min_quality`, with a window
+    /// of [`DEFAULT_TRIM_WINDOW_SIZE`] bases.
+    pub fn new(min_quality: u8) -> Self {
+        Self {
+            min_quality,
+            window_size: DEFAULT_TRIM_WINDOW_SIZE,
+        }
+    }
+
+    /// Set the number of bases in the sliding window. A window of 0 bases is treated as 1.
+    pub fn window_size(mut self, window_size: usize) -> Self {
+        self.window_size = window_size;
+        self
+    }
+
+    /// The range of a read, with FASTQ (Phred+33) qualities `qual`, that is kept after trimming.
+    /// The range is empty if no window of the read has a high enough mean quality.
+    pub(crate) fn range(&self, qual: &[u8]) -> Range<usize> {
+        let window = self.window_size.clamp(1, qual.len().max(1));
+        if qual.len() < window {
+            return 0..0;
+        }
+        let min_sum = u64::from(self.min_quality) * window as u64;
+        let score = |q: u8| u64::from(q.saturating_sub(PHRED_OFFSET));
+        let window_sum =
+            |start: usize| -> u64 { qual[start..start + window].iter().map(|&q| score(q)).sum() };
+
+        // the first window from the start with a high enough mean quality
+        let mut sum = window_sum(0);
+        let mut start = 0;
+        while sum < min_sum {
+            if start + window == qual.len() {
+                return 0..0;
+            }
+            sum = sum + score(qual[start + window]) - score(qual[start]);
+            start += 1;
+        }
+
+        // the last window from the end with a high enough mean quality, which is found before the
+        // start is reached
+        let mut end = qual.len();
+        let mut sum = window_sum(end - window);
+        while sum < min_sum {
+            end -= 1;
+            sum = sum + score(qual[end - window]) - score(qual[end]);
+        }
+        start..end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// FASTQ qualities with the given Phred scores.
+    fn qual(scores: &[u8]) -> Vec<u8> {
+        scores.iter().map(|q| q + PHRED_OFFSET).collect()
+    }
+
+    #[test]
+    fn test_range_keeps_high_quality_read() {
+        let trim = QualityTrim::new(10).window_size(3);
+        assert_eq!(trim.range(&qual(&[20; 10])), 0..10);
+    }
+
+    #[test]
+    fn test_range_trims_both_ends() {
+        let trim = QualityTrim::new(10).window_size(2);
+        let scores = [2, 2, 2, 20, 20, 20, 20, 2, 2];
+        // the first window with a mean of at least 10 is [2, 20], and the last is [20, 2]
+        assert_eq!(trim.range(&qual(&scores)), 2..8);
+
+        let trim = QualityTrim::new(10).window_size(1);
+        assert_eq!(trim.range(&qual(&scores)), 3..7);
+    }
+
+    #[test]
+    fn test_range_of_low_quality_read_is_empty() {
+        let trim = QualityTrim::new(10).window_size(3);
+        assert!(trim.range(&qual(&[5; 10])).is_empty());
+        assert!(trim.range(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_range_with_window_longer_than_read() {
+        let trim = QualityTrim::new(10).window_size(100);
+        assert_eq!(trim.range(&qual(&[20, 5, 20])), 0..3);
+        assert!(trim.range(&qual(&[5, 5, 15])).is_empty());
+    }
+}
@@ -33,23 +33,21 @@
 //! // you might want to handle cases where some proportion of query reads did not overlap with target reads
 //! ```
 //!
-//! By default, the intermediate target and query reads and overlap files are written to a temporary
-//! directory and cleaned up after the strategy object is dropped. This is done via the use of the
-//! [`tempfile`](https://crates.io/crates/tempfile) crate. The intermediate read files are placed in
+//! By default, the intermediate target and query reads and overlap files are written to the
+//! temporary directory and left there. With [`Builder::create_tmpdir`], they are instead written to
+//! a new directory inside it, which is cleaned up after the strategy object is dropped. This is done
+//! via the use of the [`tempfile`](https://crates.io/crates/tempfile) crate. The intermediate read files are placed in
 //! the temporary directory and named `target.fq` and `query.fq`, while the overlap file is named
-//! `overlaps.paf`.
+//! `overlaps.paf` (or another extension, with [`Builder::overlap_format`]). The per-read estimates are written to `estimates.tsv`, which can be used to
+//! recalculate the estimate with [`SavedEstimates`][crate::estimate::SavedEstimates].
 //!
 //! You can set your own temporary directory by using the [`Builder::tmpdir`] method.
-use std::io::Write;
 mod builder;
-use std::cmp;
-use std::collections::{HashMap, HashSet};
 use std::ffi::CString;
-use std::fs::File;
-use std::io::BufWriter;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::AtomicU32;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use crossbeam_channel as channel;
 use log::{debug, info, trace, warn};
@@ -57,13 +55,29 @@ use needletail::parse_fastx_file;
 use rayon::prelude::*;
 
 pub use self::builder::Builder;
-use crate::estimate::per_read_estimate;
+use crate::adapter::{log_adapter_summary, AdapterScan, AdapterSummary};
+use crate::artifact::{Artifact, Artifacts};
+use crate::estimate::{
+    check_no_mapping_fraction, contained_fraction, duplicate_fraction, num_estimated_reads,
+    write_per_read_estimates, FlaggedReads, PerReadEstimate, PerReadEstimator, RawEstimates,
+    ESTIMATES_FILENAME,
+};
+use crate::gc::GcSummary;
+use crate::hash::HashSet;
 use crate::io::FastqRecordExt;
-use crate::minimap2::{AlignerWrapper, Preset};
-use crate::{error::LrgeError, io, unique_random_set, Estimate, Platform};
+use crate::memory::{bytes_in_ram, MemoryPlan};
+use crate::minimap2::mapping::Contained;
+use crate::minimap2::{AlignerWrapper, MappingSink, OptionOverrides, OverlapWriter};
+use crate::read_filter::ReadFilter;
+use crate::stream::EstimateSink;
+use crate::tmpdir::ManagedTmpdir;
+use crate::trim::QualityTrim;
+use crate::{error::LrgeError, io, sample, Estimate, OverlapFormat, PafReader, Platform};
 
 pub const DEFAULT_TARGET_NUM_READS: usize = 10_000;
 pub const DEFAULT_QUERY_NUM_READS: usize = 5_000;
+/// The default number of reads buffered in the channel between the reader and the mapping threads.
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 10_000;
 
 /// A strategy that compares overlaps between two sets of reads.
 ///
@@ -83,20 +97,105 @@ pub struct TwoSetStrategy {
     query_num_reads: usize,
     /// The number of query bases to use in the strategy.
     query_num_bases: usize,
+    /// The fraction of the input to use as target reads, instead of `target_num_reads`.
+    target_fraction: Option<f32>,
+    /// The fraction of the input to use as query reads, instead of `query_num_reads`.
+    query_fraction: Option<f32>,
     /// Remove overlaps for internal matches.
     remove_internal: bool,
     /// Maximum overhang ratio
     max_overhang_ratio: f32,
-    /// Use the smaller Q/T dataset as minimap2 reference
-    use_min_ref: bool,
+    /// Maximum overhang, in bases
+    max_overhang_size: Option<usize>,
+    /// Overlaps with a greater divergence (`dv`) than this are not counted.
+    max_divergence: Option<f32>,
+    /// Overlaps with a lower mapping quality than this are not counted.
+    min_mapq: Option<u8>,
+    /// Overlaps with a shorter alignment block than this are not counted.
+    min_overlap_len: Option<usize>,
+    /// Only count the highest-scoring chain to each read.
+    primary_only: bool,
+    /// Weight each overlap by its length, rather than counting it once.
+    length_weighted: bool,
+    /// Exclude reads contained in a read they overlap from the estimate.
+    exclude_contained: bool,
+    /// Exclude reads that are a duplicate of a read they overlap from the estimate.
+    exclude_duplicates: bool,
+    /// Use the smaller Q/T dataset as minimap2 reference. If `None`, this is decided automatically
+    use_min_ref: Option<bool>,
+    /// Map more query reads if more than this fraction of them did not overlap any target reads.
+    resample_no_mapping_fraction: Option<f32>,
     /// The directory to which all intermediate files will be written.
     tmpdir: PathBuf,
+    /// The directory created inside `tmpdir` for the intermediate files, if there is one.
+    managed_tmpdir: Option<ManagedTmpdir>,
     /// Number of threads to use with minimap2.
     threads: usize,
+    /// Number of threads to use when building the minimap2 index.
+    index_threads: usize,
+    /// A caller-provided thread pool to map reads in.
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
     /// The (optional) seed to use for randomly selecting reads.
     seed: Option<u64>,
     /// Sequencing platform of the reads.
     platform: Platform,
+    /// Approximate memory budget, in bytes.
+    max_memory: Option<usize>,
+    /// The number of reads buffered between the reader and the mapping threads.
+    channel_capacity: usize,
+    /// The maximum fraction of reads allowed to have no overlaps.
+    max_no_mapping_fraction: Option<f32>,
+    /// The maximum number of malformed input records to skip. If `None`, they are an error.
+    max_bad_records: Option<usize>,
+    /// Whether every record is parsed, and so validated, when the reads are counted.
+    validate_records: bool,
+    /// Whether the read count is cached in a sidecar file next to the input.
+    cache_count: bool,
+    /// A file of the IDs of the only reads that can be sampled.
+    allowlist: Option<PathBuf>,
+    /// A file of the IDs of reads that cannot be sampled.
+    blocklist: Option<PathBuf>,
+    /// How to trim the low-quality ends of the sampled reads, if at all.
+    quality_trim: Option<QualityTrim>,
+    /// The scan for adapters to remove from the sampled reads, if any.
+    adapters: Option<AdapterScan>,
+    /// The format the overlaps are written in.
+    overlap_format: OverlapFormat,
+    /// Whether the overlaps are sorted and deduplicated before they are written.
+    sort_overlaps: bool,
+    /// Where the intermediate files are written, and which are kept.
+    artifacts: Artifacts,
+    /// Whether minimap2 maps pairs of reads in both directions.
+    dual: bool,
+    /// Passes each per-read estimate to the caller's callback, if there is one.
+    on_estimate: Option<EstimateSink>,
+    /// Passes each overlap to the caller's callback, if there is one.
+    on_mapping: Option<MappingSink>,
+    /// Calculates the genome size estimate for each read from its overlaps.
+    estimator: Arc<dyn PerReadEstimator>,
+    /// The caller's changes to the minimap2 options.
+    overrides: OptionOverrides,
+    /// The number of reads in the input file. This is only known once the reads are sampled.
+    input_num_reads: Option<u64>,
+    /// The total number of bases in the input file. This is only known once the reads are sampled.
+    input_num_bases: Option<u64>,
+    /// The number of malformed input records skipped. This is only known once the reads are sampled.
+    input_num_skipped: Option<u64>,
+    /// The GC content of the sampled reads. This is only known once the reads are sampled.
+    gc_content: Option<GcSummary>,
+    /// How many sampled reads had adapters removed. This is only known once the reads are sampled.
+    adapter_summary: Option<AdapterSummary>,
+    /// The fraction of reads contained in a read they overlap. This is only known once the reads are
+    /// overlapped.
+    contained_fraction: Option<f32>,
+    /// The fraction of reads that are a duplicate of a read they overlap. This is only known once
+    /// the reads are overlapped.
+    duplicate_fraction: Option<f32>,
+    /// The number of overlaps the estimates were calculated from. This is only known once the
+    /// reads are overlapped.
+    num_overlaps: Option<u64>,
+    /// The per-read estimates, once the genome size has been estimated.
+    raw_estimates: Option<RawEstimates>,
 }
 
 impl TwoSetStrategy {
@@ -119,71 +218,263 @@ impl TwoSetStrategy {
         self.query_num_reads
     }
 
-    fn split_fastq(&mut self) -> crate::Result<(PathBuf, PathBuf, f32)> {
-        debug!("Counting records in input file...");
-        let n_fq_reads = io::count_records(&self.input)?;
-        debug!("Found {} reads in input file", n_fq_reads);
+    /// The path an intermediate file is written to, or `None` if this strategy does not write it.
+    /// See [`Builder::artifact_path`]. The path is returned even if the file has not been written
+    /// yet, or has been removed (see [`Builder::keep_artifact`]).
+    pub fn artifact_path(&self, artifact: Artifact) -> Option<PathBuf> {
+        let path = match artifact {
+            Artifact::Target => self
+                .artifacts
+                .path(Artifact::Target, &self.tmpdir, "target.fa"),
+            Artifact::Query => self
+                .artifacts
+                .path(Artifact::Query, &self.tmpdir, "query.fa"),
+            Artifact::Overlaps => self.artifacts.path(
+                Artifact::Overlaps,
+                &self.tmpdir,
+                self.overlap_format.filename(),
+            ),
+            Artifact::Reads => return None,
+        };
+        Some(path)
+    }
 
-        if n_fq_reads > u32::MAX as usize {
-            let msg = format!(
-                "Number of reads in input file ({n_fq_reads}) exceeds maximum allowed value ({})",
-                u32::MAX
-            );
-            return Err(LrgeError::TooManyReadsError(msg));
+    /// The overlaps between the reads, as a lazy iterator over the records of the overlaps file,
+    /// so they can be post-processed after the estimate is generated without parsing the file
+    /// yourself. They are read back from the file as they are iterated over, so must still be
+    /// there - e.g., not [removed][Builder::keep_artifact], and, if the strategy creates its own
+    /// temporary directory, the strategy has not been dropped. To see the overlaps as they are
+    /// found instead, use [`Builder::on_mapping`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidConfiguration`][LrgeError::InvalidConfiguration] error if the overlaps
+    /// are not written in [PAF][OverlapFormat::Paf], or an [`IoError`][LrgeError::IoError] if the
+    /// file cannot be opened - e.g., the estimate has not been generated yet.
+    pub fn overlaps(&self) -> crate::Result<PafReader> {
+        let path = self
+            .artifact_path(Artifact::Overlaps)
+            .expect("the overlaps are always written");
+        PafReader::from_overlaps(&path, self.overlap_format)
+    }
+
+    /// Remove the intermediate files that are not being kept.
+    fn clean_up_artifacts(&self) -> crate::Result<()> {
+        for artifact in [Artifact::Target, Artifact::Query, Artifact::Overlaps] {
+            if let Some(path) = self.artifact_path(artifact) {
+                self.artifacts.clean_up(artifact, &path)?;
+            }
         }
+        Ok(())
+    }
 
-        let mut n_req_reads = self.target_num_reads + self.query_num_reads;
+    /// Whether to use the query reads as the minimap2 reference, rather than the target reads. This
+    /// is only the case if the query reads have fewer bases and using the smaller set as reference
+    /// was requested or, by default, decided automatically. The automatic choice is always the
+    /// target reads if there is an estimate callback, so that estimates are known as reads are mapped.
+    fn index_query(&self) -> bool {
+        let query_is_smaller = self.query_num_bases < self.target_num_bases;
+        match self.use_min_ref {
+            Some(false) => false,
+            Some(true) => query_is_smaller,
+            None if self.on_estimate.is_some() => {
+                debug!("Using the target reads as the minimap2 reference, so estimates can be streamed");
+                false
+            }
+            None if self.resample_no_mapping_fraction.is_some() => {
+                debug!("Using the target reads as the minimap2 reference, so more query reads can be mapped");
+                false
+            }
+            None => {
+                let reference = if query_is_smaller { "query" } else { "target" };
+                info!(
+                    "Using the {reference} reads as the minimap2 reference ({} target vs {} query bases)",
+                    self.target_num_bases, self.query_num_bases
+                );
+                query_is_smaller
+            }
+        }
+    }
 
-        if n_fq_reads <= self.query_num_reads {
-            let msg = format!(
-                "Number of reads in input file ({n_fq_reads}) is <= query number of reads ({})",
-                self.query_num_reads
+    /// Count the reads in the input file to turn the fractions of them to use as target and query
+    /// reads (see [`Builder::target_fraction`] and [`Builder::query_fraction`]) into numbers of
+    /// reads, if either fraction was given. This is done when the estimate is generated, but can be
+    /// done beforehand to see how many reads will be overlapped - e.g., for a dry run.
+    pub fn resolve_fractions(&mut self) -> crate::Result<()> {
+        if self.target_fraction.is_none() && self.query_fraction.is_none() {
+            return Ok(());
+        }
+        let filter = ReadFilter::new(self.allowlist.as_deref(), self.blocklist.as_deref())?;
+        debug!("Counting reads in input file...");
+        let n_fq_reads = sample::count_records(
+            &self.input,
+            self.max_bad_records,
+            &filter,
+            self.validate_records,
+            self.cache_count,
+        )?;
+        if let Some(fraction) = self.target_fraction.take() {
+            self.target_num_reads = sample::resolve_fraction(fraction, n_fq_reads);
+            debug!("Using {} target reads", self.target_num_reads);
+        }
+        if let Some(fraction) = self.query_fraction.take() {
+            self.query_num_reads = sample::resolve_fraction(fraction, n_fq_reads);
+            debug!("Using {} query reads", self.query_num_reads);
+        }
+        Ok(())
+    }
+
+    /// The number of threads reads are mapped with.
+    pub fn mapping_threads(&self) -> usize {
+        self.thread_pool
+            .as_ref()
+            .map_or(self.threads, |pool| pool.current_num_threads())
+    }
+
+    /// Map reads in the given thread pool, rather than building a new one.
+    pub(crate) fn set_thread_pool(&mut self, pool: Arc<rayon::ThreadPool>) {
+        self.thread_pool = Some(pool);
+    }
+
+    /// The directory the intermediate files are written to. If the strategy creates its own
+    /// directory (see [`Builder::create_tmpdir`]), this is the directory it is created in until the
+    /// estimate is generated.
+    pub fn tmpdir(&self) -> &Path {
+        &self.tmpdir
+    }
+
+    /// Set the directory to which all intermediate files will be written.
+    pub(crate) fn set_tmpdir(&mut self, tmpdir: PathBuf) {
+        self.tmpdir = tmpdir;
+    }
+
+    /// Create the strategy's own temporary directory, if it has one, and retain the intermediate
+    /// files that are explicitly kept when it is removed.
+    fn prepare_tmpdir(&mut self) -> crate::Result<()> {
+        let Some(managed) = &mut self.managed_tmpdir else {
+            return Ok(());
+        };
+        self.tmpdir = managed.create(&self.tmpdir)?;
+        let kept: Vec<PathBuf> = self
+            .artifacts
+            .kept()
+            .filter_map(|artifact| self.artifact_path(artifact))
+            .collect();
+        if let Some(managed) = &mut self.managed_tmpdir {
+            kept.into_iter().for_each(|path| managed.retain(path));
+        }
+        Ok(())
+    }
+
+    /// Sample the target and query reads, and write them to files. If query reads may be resampled,
+    /// up to as many reads again as the query reads are written to a reserve file, which is returned
+    /// too.
+    fn split_fastq(&mut self) -> crate::Result<(PathBuf, PathBuf, Option<PathBuf>, f32)> {
+        self.resolve_fractions()?;
+        MemoryPlan::check(
+            self.max_memory,
+            self.query_num_reads,
+            self.mapping_threads(),
+            0,
+        )?;
+        let filter = ReadFilter::new(self.allowlist.as_deref(), self.blocklist.as_deref())?;
+
+        let mut reserve_num_reads = if self.resample_no_mapping_fraction.is_some() {
+            self.query_num_reads
+        } else {
+            0
+        };
+        let mut n_req_reads = self.target_num_reads + self.query_num_reads + reserve_num_reads;
+
+        debug!("Sampling {n_req_reads} reads from input file...");
+        let spill_file = self.tmpdir.join("sample.fa");
+        let sample = sample::sample_records(
+            &self.input,
+            n_req_reads,
+            self.seed,
+            &spill_file,
+            self.max_bad_records,
+            &filter,
+            self.quality_trim,
+        )?;
+        let n_fq_reads = sample.num_records();
+        self.input_num_reads = Some(n_fq_reads as u64);
+        self.input_num_bases = Some(sample.num_bases());
+        self.input_num_skipped = Some(sample.num_skipped() as u64);
+        debug!(
+            "Found {} reads ({} bases) in input file",
+            n_fq_reads,
+            sample.num_bases()
+        );
+        if filter.is_active() {
+            info!(
+                "Excluded {} reads of the input file by their IDs",
+                sample.num_excluded()
             );
-            return Err(LrgeError::TooFewReadsError(msg));
-        } else if n_fq_reads < n_req_reads {
+        }
+        if sample.num_rna() > 0 {
+            info!(
+                "{} sampled reads contain U bases (direct RNA?); these are converted to T",
+                sample.num_rna()
+            );
+        }
+        if sample.num_trimmed() > 0 {
+            info!(
+                "Trimmed {} low-quality bases from the ends of {} input reads",
+                sample.num_trimmed_bases(),
+                sample.num_trimmed()
+            );
+        }
+
+        if n_fq_reads <= self.query_num_reads {
+            return Err(LrgeError::TooFewReadsError {
+                available: n_fq_reads,
+                required: self.query_num_reads,
+            });
+        } else if n_fq_reads < self.target_num_reads + self.query_num_reads {
             warn!(
                 "Number of reads in input file ({}) is less than the sum of target and query reads ({})",
-                n_fq_reads, n_req_reads
+                n_fq_reads,
+                self.target_num_reads + self.query_num_reads
             );
             self.target_num_reads = n_fq_reads - self.query_num_reads;
+            reserve_num_reads = 0;
             n_req_reads = n_fq_reads;
             warn!("Using {} target reads", self.target_num_reads);
+        } else if n_fq_reads < n_req_reads {
+            reserve_num_reads = n_fq_reads - self.target_num_reads - self.query_num_reads;
+            n_req_reads = n_fq_reads;
+            debug!("Only {reserve_num_reads} reads are left to resample query reads from");
         }
-
-        let indices = unique_random_set(n_req_reads, n_fq_reads as u32, self.seed);
-        let (mut target_indices, mut query_indices) =
-            split_into_hashsets(indices, self.target_num_reads);
-
-        let target_file = self.tmpdir.join("target.fa");
-        let query_file = self.tmpdir.join("query.fa");
+        debug_assert_eq!(
+            n_req_reads,
+            self.target_num_reads + self.query_num_reads + reserve_num_reads
+        );
+
+        let target_file = self
+            .artifacts
+            .path(Artifact::Target, &self.tmpdir, "target.fa");
+        let query_file = self
+            .artifacts
+            .path(Artifact::Query, &self.tmpdir, "query.fa");
+        let reserve_file = (reserve_num_reads > 0).then(|| self.tmpdir.join("reserve.fa"));
 
         debug!("Writing target and query reads to temporary files...");
-        let mut target_writer = File::create(&target_file).map(BufWriter::new)?;
-        let mut query_writer = File::create(&query_file).map(BufWriter::new)?;
-        let mut sum_target_len = 0;
-        let mut sum_query_len: usize = 0;
-        let mut idx: u32 = 0;
-
-        io::iter_records(&self.input, |id, seq| {
-            if target_indices.remove(&idx) {
-                target_writer.write_all(b">")?;
-                target_writer.write_all(id)?;
-                target_writer.write_all(b"\n")?;
-                target_writer.write_all(seq)?;
-                target_writer.write_all(b"\n")?;
-                sum_target_len += seq.len();
-            } else if query_indices.remove(&idx) {
-                query_writer.write_all(b">")?;
-                query_writer.write_all(id)?;
-                query_writer.write_all(b"\n")?;
-                query_writer.write_all(seq)?;
-                query_writer.write_all(b"\n")?;
-                sum_query_len += seq.len();
-            }
-
-            idx += 1;
-            Ok(())
-        })?;
+        // the reserve reads are the last group, so sampling them does not change the target and
+        // query reads
+        let mut groups = vec![
+            (self.target_num_reads, target_file.as_path()),
+            (self.query_num_reads, query_file.as_path()),
+        ];
+        if let Some(reserve_file) = &reserve_file {
+            groups.push((reserve_num_reads, reserve_file.as_path()));
+        }
+        let (sum_lens, gc_content, adapter_summary) =
+            sample.write_groups_with_adapters(&groups, self.adapters.as_ref())?;
+        let (sum_target_len, sum_query_len) = (sum_lens[0], sum_lens[1]);
+        self.gc_content = gc_content;
+        self.adapter_summary = adapter_summary;
+        log_adapter_summary(adapter_summary);
 
         self.target_num_bases = sum_target_len;
         self.query_num_bases = sum_query_len;
@@ -197,40 +488,197 @@ impl TwoSetStrategy {
         debug!("Average target read length: {}", avg_target_len);
         debug!("Average query read length: {}", avg_query_len);
 
-        Ok((target_file, query_file, avg_target_len))
+        Ok((target_file, query_file, reserve_file, avg_target_len))
     }
 
-    /// Align the query reads to the target reads and write the overlaps to a PAF file.
+    /// Align the query reads to the target reads and write the overlaps to a file. If too many query
+    /// reads do not overlap any target reads, and there is a `reserve_file`, more query reads are
+    /// sampled from it, aligned, and appended to the query file.
+    fn map_query_reads(
+        &mut self,
+        aln_wrapper: AlignerWrapper,
+        query_file: PathBuf,
+        reserve_file: Option<&Path>,
+        avg_target_len: f32,
+        channel_capacity: usize,
+    ) -> Result<(Vec<PerReadEstimate>, u32, FlaggedReads), LrgeError> {
+        let overlaps_path = self
+            .artifact_path(Artifact::Overlaps)
+            .expect("the strategy writes overlaps");
+        let overlap_writer = OverlapWriter::new(
+            &overlaps_path,
+            self.overlap_format,
+            channel_capacity,
+            self.sort_overlaps,
+            self.on_mapping.clone(),
+        )?;
+
+        debug!(
+            "Aligning reads and writing overlaps to {} file...",
+            self.overlap_format
+        );
+        let mapped = self
+            .align_reads(
+                &aln_wrapper,
+                &overlap_writer,
+                query_file.clone(),
+                avg_target_len,
+                channel_capacity,
+            )
+            .and_then(|first| {
+                let Some(reserve_file) = reserve_file else {
+                    return Ok((first, None));
+                };
+                let extra = self.resample(
+                    &aln_wrapper,
+                    &overlap_writer,
+                    reserve_file,
+                    &first,
+                    avg_target_len,
+                    channel_capacity,
+                )?;
+                Ok((first, extra))
+            });
+
+        // a mapping error may be caused by the writer thread failing, so check the writer first
+        overlap_writer.finish()?;
+        let (first, extra) = mapped?;
+        debug!("Overlaps written to: {}", overlaps_path.to_string_lossy());
+
+        let (mut estimates, mut no_mapping_count, mut flagged) = first;
+        if let Some(extra) = extra {
+            // the query file holds every query read that was mapped
+            let mut query = std::fs::OpenOptions::new().append(true).open(&query_file)?;
+            std::io::copy(&mut std::fs::File::open(&extra.file)?, &mut query)?;
+            std::fs::remove_file(&extra.file)?;
+
+            self.query_num_reads += extra.num_reads;
+            self.query_num_bases += extra.num_bases;
+            estimates.extend(extra.estimates);
+            no_mapping_count += extra.no_mapping_count;
+            flagged += extra.flagged;
+            debug!(
+                "{} of {} query reads did not overlap any target reads after resampling",
+                no_mapping_count, self.query_num_reads
+            );
+        }
+
+        Ok((estimates, no_mapping_count, flagged))
+    }
+
+    /// Map more query reads, from the `reserve_file`, if more than the resampling fraction of the
+    /// query reads in the `first` batch did not overlap any target reads. Enough reads are mapped to
+    /// replace those without overlaps, assuming the same fraction of them overlap, up to the number
+    /// of reads in the reserve file.
+    ///
+    /// Returns `None` if no more reads were needed.
+    fn resample(
+        &self,
+        aln_wrapper: &AlignerWrapper,
+        overlap_writer: &OverlapWriter,
+        reserve_file: &Path,
+        first: &(Vec<PerReadEstimate>, u32, FlaggedReads),
+        avg_target_len: f32,
+        channel_capacity: usize,
+    ) -> Result<Option<Resampled>, LrgeError> {
+        let Some(max_fraction) = self.resample_no_mapping_fraction else {
+            return Ok(None);
+        };
+        let (estimates, no_mapping_count, flagged) = first;
+        let num_reads = num_estimated_reads(estimates, flagged);
+        let no_mapping_count = *no_mapping_count as usize;
+        if num_reads == 0 || no_mapping_count as f32 / num_reads as f32 <= max_fraction {
+            return Ok(None);
+        }
+
+        // if no reads overlapped, there is no telling how many more are needed, so all are mapped
+        let num_mapped = num_reads - no_mapping_count;
+        let needed = (num_mapped > 0).then(|| (no_mapping_count * num_reads).div_ceil(num_mapped));
+        // the reserve has at most as many reads as the query reads
+        let wanted = needed.unwrap_or(usize::MAX).min(self.query_num_reads);
+        let spill_file = self.tmpdir.join("resample.fa");
+        let sample = sample::sample_records(
+            reserve_file,
+            wanted,
+            self.seed,
+            &spill_file,
+            None,
+            &ReadFilter::default(),
+            None,
+        )?;
+        let num_extra = sample.num_records().min(wanted);
+        info!(
+            "{:.2}% of the query reads did not overlap any target reads, which is more than {:.2}%, so mapping {} more query reads",
+            no_mapping_count as f32 / num_reads as f32 * 100.0,
+            max_fraction * 100.0,
+            num_extra
+        );
+        if let Some(needed) = needed.filter(|&n| num_extra < n) {
+            warn!(
+                "Only {num_extra} reads were available to resample, rather than the {needed} needed to replace the query reads without overlaps"
+            );
+        }
+
+        let extra_file = self.tmpdir.join("resample.query.fa");
+        let (sum_lens, _) = sample.write_groups(&[(num_extra, &extra_file)])?;
+        let (estimates, no_mapping_count, flagged) = self.align_reads(
+            aln_wrapper,
+            overlap_writer,
+            extra_file.clone(),
+            avg_target_len,
+            channel_capacity,
+        )?;
+
+        Ok(Some(Resampled {
+            file: extra_file,
+            num_reads: num_extra,
+            num_bases: sum_lens[0],
+            estimates,
+            no_mapping_count,
+            flagged,
+        }))
+    }
+
+    /// Align the query reads in `query_file` to the target reads, sending the overlaps to the
+    /// `overlap_writer`.
     fn align_reads(
         &self,
-        aln_wrapper: AlignerWrapper,
+        aln_wrapper: &AlignerWrapper,
+        overlap_writer: &OverlapWriter,
         query_file: PathBuf,
         avg_target_len: f32,
-    ) -> Result<(Vec<f32>, u32), LrgeError> {
-        // Bounded channel to control memory usage - i.e., 10000 records in the channel at a time
-        let (sender, receiver) = channel::bounded(10000);
+        channel_capacity: usize,
+    ) -> Result<(Vec<PerReadEstimate>, u32, FlaggedReads), LrgeError> {
+        // Bounded channel to control memory usage - i.e., channel_capacity records in the channel at a time
+        let (sender, receiver) = channel::bounded(channel_capacity);
         let aligner = Arc::clone(&aln_wrapper.aligner); // Shared reference for the producer thread
         let overlap_threshold = aln_wrapper.aligner.mapopt.min_chain_score as u32;
 
         // Producer: Read FASTQ records and send them to the channel
         let producer = std::thread::spawn(move || -> Result<(), LrgeError> {
-            let mut fastx_reader = parse_fastx_file(query_file).map_err(|e| {
-                LrgeError::FastqParseError(format!("Error parsing query FASTQ file: {e}",))
-            })?;
+            let mut fastx_reader =
+                parse_fastx_file(&query_file).map_err(|source| LrgeError::FastqParseError {
+                    path: query_file.clone(),
+                    source,
+                })?;
 
             while let Some(record) = fastx_reader.next() {
                 match record {
                     Ok(rec) => {
-                        let msg =
-                            io::Message::Data((rec.read_id().to_owned(), rec.seq().into_owned()));
+                        let rid = CString::new(rec.read_id()).map_err(|e| LrgeError::MapError {
+                            read_id: String::from_utf8_lossy(rec.read_id()).to_string(),
+                            reason: format!("Error converting read ID to CString: {e}"),
+                        })?;
+                        let msg = io::Message::Data((rid, rec.seq().into_owned()));
                         if sender.send(msg).is_err() {
                             break; // Exit if the receiver is dropped
                         }
                     }
-                    Err(e) => {
-                        return Err(LrgeError::FastqParseError(format!(
-                            "Error parsing query FASTQ file: {e}",
-                        )));
+                    Err(source) => {
+                        return Err(LrgeError::FastqParseError {
+                            path: query_file,
+                            source,
+                        });
                     }
                 }
             }
@@ -240,109 +688,175 @@ impl TwoSetStrategy {
             Ok(())
         });
 
-        // Open the output PAF file for writing
-        let paf_path = self.tmpdir.join("overlaps.paf");
-        let mut buf = File::create(&paf_path).map(BufWriter::new)?;
-        let writer = csv::WriterBuilder::new()
-            .has_headers(false)
-            .delimiter(b'\t')
-            .from_writer(&mut buf);
-        let writer = Arc::new(Mutex::new(writer)); // thread-safe writer
-
-        // set the number of threads to use with rayon in the following mapping code
-        let pool = rayon::ThreadPoolBuilder::new()
-            .num_threads(self.threads)
-            .build()
-            .map_err(|e| {
-                LrgeError::ThreadError(format!("Error setting number of threads: {e}",))
-            })?;
+        // the thread pool to use with rayon in the following mapping code
+        let pool = crate::mapping_pool(self.thread_pool.as_ref(), self.threads)?;
 
         let estimates = Vec::with_capacity(self.query_num_reads);
         let estimates = Arc::new(Mutex::new(estimates));
         let no_mapping_count = AtomicU32::new(0);
+        let num_contained = AtomicU32::new(0);
+        let num_duplicates = AtomicU32::new(0);
+        let num_excluded = AtomicU32::new(0);
+        let num_overlaps = AtomicU64::new(0);
 
-        debug!("Aligning reads and writing overlaps to PAF file...");
         // Consumer: Process records from the channel in parallel
-        pool.install(|| -> Result<(), LrgeError> {
+        let mapped = pool.install(|| -> Result<(), LrgeError> {
             receiver
                 .into_iter()
                 .par_bridge() // Parallelize the processing
-                .try_for_each(|record| -> Result<(), LrgeError> {
-                    let io::Message::Data((rid, seq)) = record;
-                    trace!("Processing read: {}", String::from_utf8_lossy(&rid));
-
-                    let qname = CString::new(rid).map_err(|e| {
-                        LrgeError::MapError(format!("Error converting read ID to CString: {e}",))
-                    })?;
+                // each worker reuses the sets of the targets a read overlaps and has a chain to
+                .try_for_each_init(
+                    || (HashSet::default(), HashSet::default()),
+                    |(unique_overlaps, chained), record| -> Result<(), LrgeError> {
+                        let io::Message::Data((qname, seq)) = record;
+                        let rid = qname.as_bytes();
+                        trace!("Processing read: {}", String::from_utf8_lossy(rid));
+
+                        // Use the shared aligner to perform alignment
+                        let mappings =
+                            aligner
+                                .map(&seq, Some(&qname))
+                                .map_err(|e| LrgeError::MapError {
+                                    read_id: String::from_utf8_lossy(rid).to_string(),
+                                    reason: e.to_string(),
+                                })?;
+
+                        unique_overlaps.clear();
+                        chained.clear();
+                        // the total length of the overlaps, on this read
+                        let mut overlap_bases = 0;
+                        let mut contained = false;
+                        let mut duplicate = false;
+
+                        for mapping in &mappings {
+                            // minimap2 reports the chains in order of score, so the first is
+                            // primary
+                            if self.primary_only && !chained.insert(mapping.target_id) {
+                                continue;
+                            }
+                            if self.remove_internal
+                                && mapping
+                                    .is_internal(self.max_overhang_ratio, self.max_overhang_size)
+                            {
+                                continue;
+                            }
+                            if self.max_divergence.is_some_and(|max| mapping.dv > max) {
+                                continue;
+                            }
+                            if self
+                                .min_mapq
+                                .is_some_and(|min| mapping.mapq < u32::from(min))
+                            {
+                                continue;
+                            }
+                            if self
+                                .min_overlap_len
+                                .is_some_and(|min| (mapping.block_len as usize) < min)
+                            {
+                                continue;
+                            }
+                            if unique_overlaps.insert(mapping.target_id) {
+                                overlap_bases += (mapping.query_end - mapping.query_start) as usize;
+                            }
+                            contained |= mapping
+                                .contained_read(self.max_overhang_ratio, self.max_overhang_size)
+                                == Some(Contained::Query);
+                            duplicate |= mapping.is_duplicate();
+                        }
 
-                    // Use the shared aligner to perform alignment
-                    let mappings = aligner.map(&seq, Some(&qname)).map_err(|e| {
-                        LrgeError::MapError(format!(
-                            "Error mapping read {}: {e}",
-                            String::from_utf8_lossy(qname.as_bytes()),
-                        ))
-                    })?;
+                        if contained {
+                            num_contained.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        }
+                        if duplicate {
+                            num_duplicates.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        }
+                        let excluded = if duplicate && self.exclude_duplicates {
+                            Some("duplicate")
+                        } else if contained && self.exclude_contained {
+                            Some("contained")
+                        } else {
+                            None
+                        };
+                        if let Some(reason) = excluded {
+                            trace!("Excluding {reason} read: {}", String::from_utf8_lossy(rid));
+                            num_excluded.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                            overlap_writer.write(mappings)?;
+                            return Ok(());
+                        }
 
-                    let mut unique_overlaps = HashSet::new();
+                        num_overlaps.fetch_add(
+                            unique_overlaps.len() as u64,
+                            std::sync::atomic::Ordering::Relaxed,
+                        );
+                        let est = if unique_overlaps.is_empty() {
+                            trace!(
+                                "No overlaps found for read: {}",
+                                String::from_utf8_lossy(rid)
+                            );
+                            no_mapping_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                            f32::INFINITY
+                        } else if self.length_weighted {
+                            self.estimator.estimate_length_weighted(
+                                seq.len(),
+                                avg_target_len,
+                                self.target_num_reads,
+                                overlap_bases,
+                                overlap_threshold,
+                            )
+                        } else {
+                            self.estimator.estimate(
+                                seq.len(),
+                                avg_target_len,
+                                self.target_num_reads,
+                                unique_overlaps.len(),
+                                overlap_threshold,
+                            )
+                        };
+
+                        trace!("Estimate for {}: {}", String::from_utf8_lossy(rid), est);
+
+                        let est = PerReadEstimate {
+                            read_id: qname.into_bytes(),
+                            estimate: est,
+                            mapped: !unique_overlaps.is_empty(),
+                        };
+                        if let Some(sink) = &self.on_estimate {
+                            sink.send(&est);
+                        }
 
-                    if !mappings.is_empty() {
                         {
-                            let mut writer_lock = writer.lock().unwrap();
-                            for mapping in &mappings {
-                                // write the PafRecord to the PAF file
-                                writer_lock.serialize(mapping)?;
-
-                                if self.remove_internal
-                                    && mapping.is_internal(self.max_overhang_ratio)
-                                {
-                                    continue;
-                                }
-                                unique_overlaps.insert(mapping.target_name.clone());
-                            }
+                            // Lock the estimates vector and push the estimate
+                            let mut estimates_lock = estimates.lock().unwrap();
+                            estimates_lock.push(est);
                         }
-                    } else {
-                        trace!(
-                            "No overlaps found for read: {}",
-                            String::from_utf8_lossy(qname.as_bytes())
-                        );
-                        no_mapping_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-                    }
-
-                    let est = per_read_estimate(
-                        seq.len(),
-                        avg_target_len,
-                        self.target_num_reads,
-                        unique_overlaps.len(),
-                        overlap_threshold,
-                    );
 
-                    trace!(
-                        "Estimate for {}: {}",
-                        String::from_utf8_lossy(qname.as_bytes()),
-                        est
-                    );
+                        // hand the mappings to the writer thread to write to the overlaps file
+                        overlap_writer.write(mappings)?;
 
-                    {
-                        // Lock the estimates vector and push the estimate
-                        let mut estimates_lock = estimates.lock().unwrap();
-                        estimates_lock.push(est);
-                    }
-
-                    Ok(())
-                })?;
+                        Ok(())
+                    },
+                )?;
             Ok(())
-        })?;
+        });
+
+        mapped?;
 
         // Wait for the producer to finish
         producer.join().map_err(|e| {
             LrgeError::ThreadError(format!("Thread panicked when joining: {e:?}",))
         })??;
 
-        debug!("Overlaps written to: {}", paf_path.to_string_lossy());
-
         let no_mapping_count = no_mapping_count.load(std::sync::atomic::Ordering::Relaxed);
+        let flagged = FlaggedReads {
+            contained: num_contained.into_inner(),
+            duplicates: num_duplicates.into_inner(),
+            excluded: num_excluded.into_inner(),
+            overlaps: num_overlaps.into_inner(),
+        };
         if no_mapping_count > 0 {
-            let percent = (no_mapping_count as f32 / self.query_num_reads as f32) * 100.0;
+            let estimates = estimates.lock().unwrap();
+            let num_reads = num_estimated_reads(&estimates, &flagged);
+            let percent = (no_mapping_count as f32 / num_reads as f32) * 100.0;
             info!(
                 "{} ({:.2}%) query read(s) did not overlap any target reads",
                 no_mapping_count, percent
@@ -355,48 +869,57 @@ impl TwoSetStrategy {
         let estimates = Arc::try_unwrap(estimates)
             .map_err(|_| {
                 LrgeError::ThreadError(
-                    "Error unwrapping estimates Arc<Mutex<Vec<f32>>>".to_string(),
+                    "Error unwrapping estimates Arc<Mutex<Vec<PerReadEstimate>>>".to_string(),
                 )
             })?
             .into_inner()
             .map_err(|_| {
-                LrgeError::ThreadError("Error unwrapping estimates Mutex<Vec<f32>>".to_string())
+                LrgeError::ThreadError(
+                    "Error unwrapping estimates Mutex<Vec<PerReadEstimate>>".to_string(),
+                )
             })?;
 
-        Ok((estimates, no_mapping_count))
+        Ok((estimates, no_mapping_count, flagged))
     }
 
-    /// Align the target reads to the query reads and write the overlaps to a PAF file.
+    /// Align the target reads to the query reads and write the overlaps to a file.
     fn align_reads_inverse(
         &self,
         aln_wrapper: AlignerWrapper,
         target_file: PathBuf,
         avg_target_len: f32,
-    ) -> Result<(Vec<f32>, u32), LrgeError> {
-        // Bounded channel to control memory usage - i.e., 10000 records in the channel at a time
-        let (sender, receiver) = channel::bounded(10000);
+        channel_capacity: usize,
+    ) -> Result<(Vec<PerReadEstimate>, u32, FlaggedReads), LrgeError> {
+        // Bounded channel to control memory usage - i.e., channel_capacity records in the channel at a time
+        let (sender, receiver) = channel::bounded(channel_capacity);
         let aligner = Arc::clone(&aln_wrapper.aligner); // Shared reference for the producer thread
         let overlap_threshold = aln_wrapper.aligner.mapopt.min_chain_score as u32;
 
         // Producer: Read FASTQ records and send them to the channel
         let producer = std::thread::spawn(move || -> Result<(), LrgeError> {
-            let mut fastx_reader = parse_fastx_file(target_file).map_err(|e| {
-                LrgeError::FastqParseError(format!("Error parsing query FASTQ file: {e}",))
-            })?;
+            let mut fastx_reader =
+                parse_fastx_file(&target_file).map_err(|source| LrgeError::FastqParseError {
+                    path: target_file.clone(),
+                    source,
+                })?;
 
             while let Some(record) = fastx_reader.next() {
                 match record {
                     Ok(rec) => {
-                        let msg =
-                            io::Message::Data((rec.read_id().to_owned(), rec.seq().into_owned()));
+                        let rid = CString::new(rec.read_id()).map_err(|e| LrgeError::MapError {
+                            read_id: String::from_utf8_lossy(rec.read_id()).to_string(),
+                            reason: format!("Error converting read ID to CString: {e}"),
+                        })?;
+                        let msg = io::Message::Data((rid, rec.seq().into_owned()));
                         if sender.send(msg).is_err() {
                             break; // Exit if the receiver is dropped
                         }
                     }
-                    Err(e) => {
-                        return Err(LrgeError::FastqParseError(format!(
-                            "Error parsing query FASTQ file: {e}",
-                        )));
+                    Err(source) => {
+                        return Err(LrgeError::FastqParseError {
+                            path: target_file,
+                            source,
+                        });
                     }
                 }
             }
@@ -406,172 +929,234 @@ impl TwoSetStrategy {
             Ok(())
         });
 
-        // Open the output PAF file for writing
-        let paf_path = self.tmpdir.join("overlaps.paf");
-        let mut buf = File::create(&paf_path).map(BufWriter::new)?;
-        let writer = csv::WriterBuilder::new()
-            .has_headers(false)
-            .delimiter(b'\t')
-            .from_writer(&mut buf);
-        let writer = Arc::new(Mutex::new(writer)); // thread-safe writer
-
-        // set the number of threads to use with rayon in the following mapping code
-        let pool = rayon::ThreadPoolBuilder::new()
-            .num_threads(self.threads)
-            .build()
-            .map_err(|e| {
-                LrgeError::ThreadError(format!("Error setting number of threads: {e}",))
-            })?;
-
-        let mut read_lengths: HashMap<Vec<u8>, usize> =
-            HashMap::with_capacity(self.query_num_reads);
-        let mut ovlap_counter: HashMap<Vec<u8>, usize> =
-            HashMap::with_capacity(self.query_num_reads);
-
-        for i in 0..self.query_num_reads {
-            unsafe {
-                let qname: *mut ::std::os::raw::c_char =
-                    (*((*(aln_wrapper.aligner.idx.unwrap())).seq.add(i))).name;
-                let qname = std::ffi::CStr::from_ptr(qname).to_bytes().to_vec();
-                let qlens: usize =
-                    (*((*(aln_wrapper.aligner.idx.unwrap())).seq.add(i))).len as usize;
-                // add to read_lengths
-                if read_lengths.insert(qname.clone(), qlens).is_some() {
-                    return Err(LrgeError::DuplicateReadIdentifier(
-                        String::from_utf8_lossy(&qname).to_string(),
-                    ));
-                }
-                // add to ovlap_counter, we insert it with 0 overlaps
-                if ovlap_counter.insert(qname.clone(), 0).is_some() {
-                    return Err(LrgeError::DuplicateReadIdentifier(
-                        String::from_utf8_lossy(&qname).to_string(),
-                    ));
-                }
+        // Open the output overlaps file for writing
+        let overlaps_path = self
+            .artifact_path(Artifact::Overlaps)
+            .expect("the strategy writes overlaps");
+        let overlap_writer = OverlapWriter::new(
+            &overlaps_path,
+            self.overlap_format,
+            channel_capacity,
+            self.sort_overlaps,
+            self.on_mapping.clone(),
+        )?;
+
+        // the thread pool to use with rayon in the following mapping code
+        let pool = crate::mapping_pool(self.thread_pool.as_ref(), self.threads)?;
+
+        // the query reads are the index, so they are counted by their index ID (rid), and their
+        // names and lengths are looked up in the index
+        let index = Arc::clone(
+            aln_wrapper
+                .aligner
+                .index()
+                .expect("the aligner wrapper always builds an index"),
+        );
+        let mut names = HashSet::with_capacity_and_hasher(index.n_seq(), Default::default());
+        for i in 0..index.n_seq() {
+            if !names.insert(index.seq_name(i)) {
+                return Err(LrgeError::DuplicateReadIdentifier {
+                    read_id: String::from_utf8_lossy(index.seq_name(i)).to_string(),
+                    path: self.input.clone(),
+                });
             }
         }
-
-        let ovlap_counter = Arc::new(Mutex::new(ovlap_counter));
-
-        debug!("Aligning reads and writing overlaps to PAF file...");
+        drop(names);
+
+        let ovlap_counter: Vec<AtomicUsize> =
+            (0..index.n_seq()).map(|_| AtomicUsize::new(0)).collect();
+        // the total length of the overlaps of each read, on that read
+        let ovlap_bases: Vec<AtomicUsize> =
+            (0..index.n_seq()).map(|_| AtomicUsize::new(0)).collect();
+        let contained: Vec<AtomicBool> =
+            (0..index.n_seq()).map(|_| AtomicBool::new(false)).collect();
+        let duplicate: Vec<AtomicBool> =
+            (0..index.n_seq()).map(|_| AtomicBool::new(false)).collect();
+
+        debug!(
+            "Aligning reads and writing overlaps to {} file...",
+            self.overlap_format
+        );
         // Consumer: Process records from the channel in parallel
-        pool.install(|| -> Result<(), LrgeError> {
+        let mapped = pool.install(|| -> Result<(), LrgeError> {
             receiver
                 .into_iter()
                 .par_bridge() // Parallelize the processing
-                .try_for_each(|record| -> Result<(), LrgeError> {
-                    let io::Message::Data((rid, seq)) = record;
-                    trace!("Processing read: {}", String::from_utf8_lossy(&rid));
-
-                    let tname: CString = CString::new(rid.clone()).map_err(|e| {
-                        LrgeError::MapError(format!("Error converting read name to CString: {e}",))
-                    })?;
-
-                    // Use the shared aligner to perform alignment
-                    let mappings = aligner.map(&seq, Some(&tname)).map_err(|e| {
-                        LrgeError::MapError(format!(
-                            "Error mapping read {}: {e}",
-                            String::from_utf8_lossy(&rid),
-                        ))
-                    })?;
-
-                    {
-                        if !mappings.is_empty() {
-                            let mut writer_lock = writer.lock().unwrap();
-                            let mut ovlap_counter_lock = ovlap_counter.lock().unwrap();
-                            let mut unique_overlaps: HashSet<Vec<u8>> = HashSet::new();
-                            let mut overhang: i32;
-                            let mut maplen: i32;
-
-                            for mapping in &mappings {
-                                // write the PafRecord to the PAF file
-                                writer_lock.serialize(mapping)?;
-
-                                if unique_overlaps.contains(&mapping.target_name) {
-                                    continue;
-                                }
-
-                                if self.remove_internal {
-                                    if mapping.strand == '+' {
-                                        overhang =
-                                            cmp::min(mapping.query_start, mapping.target_start)
-                                                + cmp::min(
-                                                    mapping.query_len - mapping.query_end,
-                                                    mapping.target_len - mapping.target_end,
-                                                );
-                                    } else {
-                                        overhang = cmp::min(
-                                            mapping.query_start,
-                                            mapping.target_len - mapping.target_end,
-                                        ) + cmp::min(
-                                            mapping.query_len - mapping.query_end,
-                                            mapping.target_start,
-                                        );
-                                    }
-                                    maplen = cmp::max(
-                                        mapping.query_end - mapping.query_start,
-                                        mapping.target_end - mapping.target_start,
-                                    );
-                                    if overhang > ((maplen as f32) * self.max_overhang_ratio) as i32
-                                    {
-                                        continue;
-                                    }
-                                }
-
-                                *ovlap_counter_lock
-                                    .entry(mapping.target_name.clone())
-                                    .or_insert(0) += 1;
-                                unique_overlaps.insert(mapping.target_name.clone());
+                // each worker reuses the sets of the queries a read overlaps and has a chain to
+                .try_for_each_init(
+                    || (HashSet::default(), HashSet::default()),
+                    |(unique_overlaps, chained), record| -> Result<(), LrgeError> {
+                        let io::Message::Data((tname, seq)) = record;
+                        let rid = tname.as_bytes();
+                        trace!("Processing read: {}", String::from_utf8_lossy(rid));
+
+                        // Use the shared aligner to perform alignment
+                        let mappings =
+                            aligner
+                                .map(&seq, Some(&tname))
+                                .map_err(|e| LrgeError::MapError {
+                                    read_id: String::from_utf8_lossy(rid).to_string(),
+                                    reason: e.to_string(),
+                                })?;
+
+                        unique_overlaps.clear();
+                        chained.clear();
+
+                        for mapping in &mappings {
+                            // minimap2 reports the chains in order of score, so the first is primary
+                            if self.primary_only && !chained.insert(mapping.target_id) {
+                                continue;
+                            }
+
+                            if unique_overlaps.contains(&mapping.target_id) {
+                                continue;
+                            }
+
+                            if self.max_divergence.is_some_and(|max| mapping.dv > max) {
+                                continue;
+                            }
+                            if self
+                                .min_mapq
+                                .is_some_and(|min| mapping.mapq < u32::from(min))
+                            {
+                                continue;
+                            }
+                            if self
+                                .min_overlap_len
+                                .is_some_and(|min| (mapping.block_len as usize) < min)
+                            {
+                                continue;
                             }
+
+                            if self.remove_internal
+                                && mapping
+                                    .is_internal(self.max_overhang_ratio, self.max_overhang_size)
+                            {
+                                continue;
+                            }
+
+                            ovlap_counter[mapping.target_id as usize]
+                                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                            ovlap_bases[mapping.target_id as usize].fetch_add(
+                                (mapping.target_end - mapping.target_start) as usize,
+                                std::sync::atomic::Ordering::Relaxed,
+                            );
+                            if mapping
+                                .contained_read(self.max_overhang_ratio, self.max_overhang_size)
+                                == Some(Contained::Target)
+                            {
+                                contained[mapping.target_id as usize]
+                                    .store(true, std::sync::atomic::Ordering::Relaxed);
+                            }
+                            if mapping.is_duplicate() {
+                                duplicate[mapping.target_id as usize]
+                                    .store(true, std::sync::atomic::Ordering::Relaxed);
+                            }
+                            unique_overlaps.insert(mapping.target_id);
                         }
-                    }
 
-                    Ok(())
-                })?;
+                        // hand the mappings to the writer thread to write to the overlaps file
+                        overlap_writer.write(mappings)?;
+
+                        Ok(())
+                    },
+                )?;
             Ok(())
-        })?;
+        });
+
+        // a mapping error may be caused by the writer thread failing, so check the writer first
+        overlap_writer.finish()?;
+        mapped?;
 
         // Wait for the producer to finish
         producer.join().map_err(|e| {
             LrgeError::ThreadError(format!("Thread panicked when joining: {e:?}",))
         })??;
 
-        debug!("Overlaps written to: {}", paf_path.to_string_lossy());
+        debug!("Overlaps written to: {}", overlaps_path.to_string_lossy());
 
-        let ovlap_counter = Arc::try_unwrap(ovlap_counter)
-            .unwrap()
-            .into_inner()
-            .unwrap();
         let no_mapping_count = AtomicU32::new(0);
-        let estimates = ovlap_counter
-            .par_iter()
-            .map(|(rid, n_ovlaps)| {
-                let est = if *n_ovlaps == 0 {
+        let num_contained = AtomicU32::new(0);
+        let num_duplicates = AtomicU32::new(0);
+        let num_excluded = AtomicU32::new(0);
+        let num_overlaps = AtomicU64::new(0);
+        let estimates: Vec<PerReadEstimate> = ovlap_counter
+            .into_par_iter()
+            .zip(ovlap_bases)
+            .zip(contained)
+            .zip(duplicate)
+            .enumerate()
+            .filter_map(|(id, (((n_ovlaps, n_bases), contained), duplicate))| {
+                let n_ovlaps = n_ovlaps.into_inner();
+                let rid = index.seq_name(id);
+                let contained = contained.into_inner();
+                let duplicate = duplicate.into_inner();
+                if contained {
+                    num_contained.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                }
+                if duplicate {
+                    num_duplicates.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                }
+                if duplicate && self.exclude_duplicates {
+                    trace!("Excluding duplicate read: {}", String::from_utf8_lossy(rid));
+                    num_excluded.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    return None;
+                }
+                if contained && self.exclude_contained {
+                    trace!("Excluding contained read: {}", String::from_utf8_lossy(rid));
+                    num_excluded.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    return None;
+                }
+                num_overlaps.fetch_add(n_ovlaps as u64, std::sync::atomic::Ordering::Relaxed);
+                let est = if n_ovlaps == 0 {
                     no_mapping_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                     trace!(
                         "No overlaps found for read: {}",
                         String::from_utf8_lossy(rid)
                     );
                     f32::INFINITY
+                } else if self.length_weighted {
+                    self.estimator.estimate_length_weighted(
+                        index.seq_len(id),
+                        avg_target_len,
+                        self.target_num_reads,
+                        n_bases.into_inner(),
+                        overlap_threshold,
+                    )
                 } else {
-                    // safe to unwrap the Option here because we know the key exists
-                    let read_len = read_lengths.get(rid).unwrap();
-                    per_read_estimate(
-                        *read_len,
+                    self.estimator.estimate(
+                        index.seq_len(id),
                         avg_target_len,
                         self.target_num_reads,
-                        *n_ovlaps,
+                        n_ovlaps,
                         overlap_threshold,
                     )
                 };
                 trace!("Estimate for {}: {}", String::from_utf8_lossy(rid), est);
-                est
+                Some(PerReadEstimate {
+                    read_id: rid.to_vec(),
+                    estimate: est,
+                    mapped: n_ovlaps > 0,
+                })
             })
             .collect();
 
+        // the overlaps of a read are only all known once every read has been mapped
+        if let Some(sink) = &self.on_estimate {
+            estimates.iter().for_each(|est| sink.send(est));
+        }
+
         let no_mapping_count = no_mapping_count.load(std::sync::atomic::Ordering::Relaxed);
+        let flagged = FlaggedReads {
+            contained: num_contained.into_inner(),
+            duplicates: num_duplicates.into_inner(),
+            excluded: num_excluded.into_inner(),
+            overlaps: num_overlaps.into_inner(),
+        };
 
         if no_mapping_count > 0 {
-            let percent = (no_mapping_count as f32 / self.query_num_reads as f32) * 100.0;
+            let num_reads = num_estimated_reads(&estimates, &flagged);
+            let percent = (no_mapping_count as f32 / num_reads as f32) * 100.0;
             info!(
                 "{} ({:.2}%) read(s) did not overlap any other reads",
                 no_mapping_count, percent
@@ -580,123 +1165,160 @@ impl TwoSetStrategy {
             debug!("All reads had at least one overlap");
         }
 
-        Ok((estimates, no_mapping_count))
+        Ok((estimates, no_mapping_count, flagged))
     }
 }
 
-impl Estimate for TwoSetStrategy {
-    fn generate_estimates(&mut self) -> crate::Result<(Vec<f32>, u32)> {
-        let (target_file, query_file, avg_target_len) = self.split_fastq()?;
-
-        let preset = match self.platform {
-            Platform::PacBio => Preset::AvaPb,
-            Platform::Nanopore => Preset::AvaOnt,
-        };
-
-        if self.use_min_ref && self.target_num_bases > self.query_num_bases {
-            // align target to query
-            let aligner = AlignerWrapper::new(&query_file, self.threads, preset, true)?;
-            self.align_reads_inverse(aligner, target_file, avg_target_len)
-        } else {
-            // align query to target
-            let aligner = AlignerWrapper::new(&target_file, self.threads, preset, true)?;
-            self.align_reads(aligner, query_file, avg_target_len)
-        }
-    }
+/// An additional batch of query reads, mapped because too many of the query reads did not overlap
+/// any target reads.
+struct Resampled {
+    /// The file the reads were written to
+    file: PathBuf,
+    /// The number of reads
+    num_reads: usize,
+    /// The number of bases in the reads
+    num_bases: usize,
+    /// The estimates of the reads, which do not include the excluded reads
+    estimates: Vec<PerReadEstimate>,
+    /// The number of reads that did not overlap any target reads
+    no_mapping_count: u32,
+    /// The number of reads flagged as contained in, or a duplicate of, a target read they overlap
+    flagged: FlaggedReads,
 }
 
-/// Splits a `Vec` into two separate sets with potentially different sizes.
-///
-/// This function consumes the original `Vec` and divides its elements into
-/// two new sets, `set1` and `set2`. The size of `set1` is specified by `size_first`,
-/// while `set2` will contain the remaining elements. If `size_first` is larger than
-/// the number of elements in `original`, all elements are placed in `set1`, and `set2`
-/// will be empty.
-///
-/// # Arguments
-///
-/// * `original` - The `Vec` to be split. This set will be consumed by the function, so it will no
-///   longer be accessible after the function call.
-/// * `size_first` - The number of elements to place in the first set, `set1`.
-///
-/// # Returns
-///
-/// A tuple containing:
-/// * `HashSet<T>` - The first set (`set1`), containing up to `size_first` elements.
-/// * `HashSet<T>` - The second set (`set2`), containing the remaining elements.
-///
-/// # Panics
-///
-/// This function will panic if `size_first` is larger than `original.len()`.
-///
-pub(crate) fn split_into_hashsets<T: std::hash::Hash + Eq>(
-    mut original: Vec<T>,
-    size_first: usize,
-) -> (HashSet<T>, HashSet<T>) {
-    let mut first_set = HashSet::with_capacity(size_first);
-    let mut second_set = HashSet::with_capacity(original.len().saturating_sub(size_first));
-
-    // Fill the first set
-    for _ in 0..size_first.min(original.len()) {
-        if let Some(element) = original.pop() {
-            first_set.insert(element);
-        }
+impl Estimate for TwoSetStrategy {
+    fn input_num_reads(&self) -> Option<u64> {
+        self.input_num_reads
     }
 
-    // Fill the second set with the remaining elements
-    while let Some(element) = original.pop() {
-        second_set.insert(element);
+    fn input_num_bases(&self) -> Option<u64> {
+        self.input_num_bases
     }
 
-    (first_set, second_set)
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::collections::HashSet;
-
-    #[test]
-    fn test_basic_split() {
-        let original = vec![1, 2, 3, 4, 5];
-
-        let (set1, set2) = split_into_hashsets(original, 3);
+    fn input_num_skipped(&self) -> Option<u64> {
+        self.input_num_skipped
+    }
 
-        assert_eq!(set1.len(), 3);
-        assert_eq!(set2.len(), 2);
+    fn gc_content(&self) -> Option<GcSummary> {
+        self.gc_content.clone()
     }
 
-    #[test]
-    fn test_all_elements_in_set1() {
-        let original = vec![1, 2, 3];
+    fn adapter_summary(&self) -> Option<AdapterSummary> {
+        self.adapter_summary
+    }
 
-        let (set1, set2) = split_into_hashsets(original, 5);
+    fn contained_fraction(&self) -> Option<f32> {
+        self.contained_fraction
+    }
 
-        assert_eq!(set1.len(), 3);
-        assert_eq!(set2.len(), 0);
+    fn duplicate_fraction(&self) -> Option<f32> {
+        self.duplicate_fraction
     }
 
-    #[test]
-    fn test_all_elements_in_set2() {
-        let original = vec![1, 2, 3];
+    fn num_overlaps(&self) -> Option<u64> {
+        self.num_overlaps
+    }
 
-        let (set1, set2) = split_into_hashsets(original, 0);
+    fn raw_estimates(&self) -> Option<&RawEstimates> {
+        self.raw_estimates.as_ref()
+    }
 
-        assert_eq!(set1.len(), 0);
-        assert_eq!(set2.len(), 3);
+    fn retain_raw_estimates(&mut self, raw: RawEstimates) {
+        self.raw_estimates = Some(raw);
     }
 
-    #[test]
-    fn test_no_elements_lost() {
-        let original = vec![1, 2, 3, 4];
+    fn generate_estimates(&mut self) -> crate::Result<RawEstimates> {
+        let started = Instant::now();
+        self.prepare_tmpdir()?;
+        let (target_file, query_file, reserve_file, avg_target_len) = self.split_fastq()?;
 
-        let (set1, set2) = split_into_hashsets(original.clone(), 2);
+        let preset = self.platform.preset();
 
-        // Verify no elements were lost
-        let combined: HashSet<_> = set1.union(&set2).collect();
-        assert_eq!(combined.len(), original.len());
-        for elem in &original {
-            assert!(combined.contains(elem));
+        let mut tmp_files = vec![target_file.as_path(), query_file.as_path()];
+        tmp_files.extend(reserve_file.as_deref());
+        let tmp_bytes = bytes_in_ram(&tmp_files);
+        let (estimates, no_mapping_count, flagged) = if self.index_query() {
+            if reserve_file.is_some() {
+                warn!("Query reads cannot be resampled when they are the minimap2 reference");
+            }
+            // align target to query
+            let plan = MemoryPlan::new(
+                self.max_memory,
+                self.query_num_bases,
+                self.query_num_reads,
+                avg_target_len,
+                self.mapping_threads(),
+                self.channel_capacity,
+                tmp_bytes,
+                0,
+            )?;
+            let aligner = AlignerWrapper::new(
+                &query_file,
+                self.index_threads,
+                preset,
+                self.dual,
+                plan.index_batch_size,
+                &self.overrides,
+            )?;
+            self.align_reads_inverse(aligner, target_file, avg_target_len, plan.channel_capacity)?
+        } else {
+            // align query to target
+            let avg_query_len = self.query_num_bases as f32 / self.query_num_reads as f32;
+            let plan = MemoryPlan::new(
+                self.max_memory,
+                self.target_num_bases,
+                self.query_num_reads,
+                avg_query_len,
+                self.mapping_threads(),
+                self.channel_capacity,
+                tmp_bytes,
+                0,
+            )?;
+            let aligner = AlignerWrapper::new(
+                &target_file,
+                self.index_threads,
+                preset,
+                self.dual,
+                plan.index_batch_size,
+                &self.overrides,
+            )?;
+            self.map_query_reads(
+                aligner,
+                query_file,
+                reserve_file.as_deref(),
+                avg_target_len,
+                plan.channel_capacity,
+            )?
+        };
+        if let Some(reserve_file) = &reserve_file {
+            std::fs::remove_file(reserve_file)?;
         }
+
+        // the excluded reads are not in the estimates
+        let num_estimated = num_estimated_reads(&estimates, &flagged);
+        self.contained_fraction = Some(contained_fraction(
+            flagged.contained,
+            num_estimated,
+            self.exclude_contained,
+        ));
+        self.duplicate_fraction = Some(duplicate_fraction(
+            flagged.duplicates,
+            num_estimated,
+            self.exclude_duplicates,
+        ));
+        self.num_overlaps = Some(flagged.overlaps);
+
+        write_per_read_estimates(&self.tmpdir.join(ESTIMATES_FILENAME), &estimates)?;
+        self.clean_up_artifacts()?;
+        check_no_mapping_fraction(
+            no_mapping_count,
+            num_estimated,
+            self.max_no_mapping_fraction,
+        )?;
+        Ok(RawEstimates::new(
+            estimates,
+            no_mapping_count,
+            started.elapsed(),
+        ))
     }
 }
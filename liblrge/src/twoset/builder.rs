@@ -1,8 +1,22 @@
-use crate::Platform;
+use crate::adapter::AdapterScan;
+use crate::artifact::{Artifact, Artifacts};
+use crate::env::EnvDefaults;
+use crate::error::LrgeError;
+use crate::estimate::{Equation3, PerReadEstimate, PerReadEstimator};
+use crate::minimap2::{MappingCallback, MappingSink, OptionOverrides};
+use crate::seed::IntoSeed;
+use crate::stream::{Callback, EstimateSink};
+use crate::tmpdir::ManagedTmpdir;
+use crate::trim::QualityTrim;
+use crate::{read_filter, sample};
+use crate::{IdxOpt, MapOpt, OverlapFormat, PafRecord, Platform};
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
 
-use super::{TwoSetStrategy, DEFAULT_QUERY_NUM_READS, DEFAULT_TARGET_NUM_READS};
+use super::{
+    TwoSetStrategy, DEFAULT_CHANNEL_CAPACITY, DEFAULT_QUERY_NUM_READS, DEFAULT_TARGET_NUM_READS,
+};
 
 /// A builder for [`TwoSetStrategy`].
 pub struct Builder {
@@ -10,13 +24,46 @@ pub struct Builder {
     target_num_bases: usize,
     query_num_reads: usize,
     query_num_bases: usize,
+    target_fraction: Option<f32>,
+    query_fraction: Option<f32>,
     remove_internal: bool,
     max_overhang_ratio: f32,
-    use_min_ref: bool,
+    max_overhang_size: Option<usize>,
+    max_divergence: Option<f32>,
+    min_mapq: Option<u8>,
+    min_overlap_len: Option<usize>,
+    primary_only: bool,
+    length_weighted: bool,
+    exclude_contained: bool,
+    exclude_duplicates: bool,
+    use_min_ref: Option<bool>,
+    resample_no_mapping_fraction: Option<f32>,
     tmpdir: PathBuf,
+    create_tmpdir: bool,
+    keep_temp: bool,
     threads: usize,
+    index_threads: Option<usize>,
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
     seed: Option<u64>,
     platform: Platform,
+    max_memory: Option<usize>,
+    channel_capacity: usize,
+    max_no_mapping_fraction: Option<f32>,
+    max_bad_records: Option<usize>,
+    validate_records: bool,
+    cache_count: bool,
+    allowlist: Option<PathBuf>,
+    blocklist: Option<PathBuf>,
+    quality_trim: Option<QualityTrim>,
+    adapters: Option<AdapterScan>,
+    overlap_format: OverlapFormat,
+    sort_overlaps: bool,
+    artifacts: Artifacts,
+    dual: Option<bool>,
+    on_estimate: Option<Callback>,
+    on_mapping: Option<MappingCallback>,
+    estimator: Arc<dyn PerReadEstimator>,
+    overrides: OptionOverrides,
 }
 
 impl Default for Builder {
@@ -27,13 +74,46 @@ impl Default for Builder {
             target_num_bases: 0,
             query_num_reads: DEFAULT_QUERY_NUM_READS,
             query_num_bases: 0,
+            target_fraction: None,
+            query_fraction: None,
             remove_internal: false,
             max_overhang_ratio: 0.2,
-            use_min_ref: false,
+            max_overhang_size: None,
+            max_divergence: None,
+            min_mapq: None,
+            min_overlap_len: None,
+            primary_only: false,
+            length_weighted: false,
+            exclude_contained: false,
+            exclude_duplicates: false,
+            use_min_ref: None,
+            resample_no_mapping_fraction: None,
             tmpdir,
+            create_tmpdir: false,
+            keep_temp: false,
             threads: 1,
+            index_threads: None,
+            thread_pool: None,
             seed: None,
             platform: Platform::default(),
+            max_memory: None,
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            max_no_mapping_fraction: None,
+            max_bad_records: None,
+            validate_records: false,
+            cache_count: false,
+            allowlist: None,
+            blocklist: None,
+            quality_trim: None,
+            adapters: None,
+            overlap_format: OverlapFormat::default(),
+            sort_overlaps: false,
+            artifacts: Artifacts::default(),
+            dual: None,
+            on_estimate: None,
+            on_mapping: None,
+            estimator: Arc::new(Equation3),
+            overrides: OptionOverrides::default(),
         }
     }
 }
@@ -52,6 +132,44 @@ impl Builder {
         Self::default()
     }
 
+    /// Create a new builder with the default settings, overridden by any of the following
+    /// environment variables that are set (and not empty):
+    ///
+    /// - [`LRGE_THREADS`][crate::env::THREADS_VAR]: the number of threads (see [`Builder::threads`])
+    /// - [`LRGE_TMPDIR`][crate::env::TMPDIR_VAR]: the temporary directory (see [`Builder::tmpdir`])
+    /// - [`LRGE_SEED`][crate::env::SEED_VAR]: the random seed (see [`Builder::seed`])
+    /// - [`LRGE_PLATFORM`][crate::env::PLATFORM_VAR]: the sequencing platform (see [`Builder::platform`])
+    ///
+    /// Settings can still be changed with the other builder methods afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidConfiguration`][crate::error::LrgeError::InvalidConfiguration] error
+    /// if a variable cannot be parsed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::from_env().expect("Invalid LRGE_* environment variable");
+    /// ```
+    pub fn from_env() -> crate::Result<Self> {
+        let env = EnvDefaults::from_env()?;
+        let mut builder = Self::default();
+        if let Some(threads) = env.threads {
+            builder.threads = threads;
+        }
+        if let Some(tmpdir) = env.tmpdir {
+            builder.tmpdir = tmpdir;
+        }
+        if let Some(platform) = env.platform {
+            builder.platform = platform;
+        }
+        builder.seed = env.seed;
+        Ok(builder)
+    }
+
     /// Set the number of target reads for the strategy. By default, this is [`DEFAULT_TARGET_NUM_READS`].
     ///
     /// The target reads are the (generally) smaller set of reads that the query reads are
@@ -86,6 +204,38 @@ impl Builder {
         self
     }
 
+    /// Use this fraction (0-1) of the reads in the input as target reads, rather than a
+    /// [number of them][Builder::target_num_reads]. The reads are counted before they are sampled,
+    /// so the input is read twice. The fraction is rounded to the nearest read, but at least 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().target_fraction(0.01);
+    /// ```
+    pub fn target_fraction(mut self, fraction: f32) -> Self {
+        self.target_fraction = Some(fraction);
+        self
+    }
+
+    /// Use this fraction (0-1) of the reads in the input as query reads, rather than a
+    /// [number of them][Builder::query_num_reads]. The reads are counted before they are sampled,
+    /// so the input is read twice. The fraction is rounded to the nearest read, but at least 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().query_fraction(0.05);
+    /// ```
+    pub fn query_fraction(mut self, fraction: f32) -> Self {
+        self.query_fraction = Some(fraction);
+        self
+    }
+
     /// Set option for removing the overlaps representing internal matches
     pub fn remove_internal(mut self, filter_contained: bool, ratio: f32) -> Self {
         self.remove_internal = filter_contained;
@@ -95,18 +245,271 @@ impl Builder {
         self
     }
 
-    /// Set option for using the smaller Q/T dataset as minimap2 reference
+    /// Set the maximum overhang, in bases, of an overlap when [removing internal
+    /// matches][Builder::remove_internal]. An overlap is an internal match if its overhang is more
+    /// than this, or more than the maximum overhang ratio of its mapping length. By default
+    /// (`None`), only the ratio is used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new()
+    ///     .remove_internal(true, 0.2)
+    ///     .max_overhang_size(Some(1000));
+    /// ```
+    pub fn max_overhang_size(mut self, max_overhang_size: Option<usize>) -> Self {
+        self.max_overhang_size = max_overhang_size;
+        self
+    }
+
+    /// Ignore overlaps whose approximate per-base sequence divergence (the `dv` tag) is greater
+    /// than `max_divergence` when counting the overlaps of each read. By default (`None`), no
+    /// overlaps are ignored.
+    ///
+    /// Spurious overlaps between repeats, which inflate the overlap counts and so shrink the
+    /// estimate, tend to be more divergent than true overlaps. All overlaps are still written to
+    /// the overlaps file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().max_divergence(Some(0.2));
+    /// ```
+    pub fn max_divergence(mut self, max_divergence: Option<f32>) -> Self {
+        self.max_divergence = max_divergence;
+        self
+    }
+
+    /// Ignore overlaps with a lower mapping quality (MAPQ) than `min_mapq` when counting the
+    /// overlaps of each read. By default (`None`), no overlaps are ignored.
+    ///
+    /// This stops low-confidence chains from contributing to the overlap counts. All overlaps,
+    /// including those that are ignored, are still written to the overlaps file for inspection.
+    ///
+    /// Note that minimap2 gives a low MAPQ (often 0) to an overlap when other reads overlap the
+    /// same region of the query, which is common for true overlaps at any appreciable depth, so a
+    /// threshold can ignore far more overlaps than expected. Check the MAPQs in the overlaps file
+    /// before choosing one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().min_mapq(Some(5));
+    /// ```
+    pub fn min_mapq(mut self, min_mapq: Option<u8>) -> Self {
+        self.min_mapq = min_mapq;
+        self
+    }
+
+    /// Ignore overlaps that are shorter than `min_overlap_len` bases when counting the overlaps of
+    /// each read. The length of an overlap is its alignment block length - i.e., the number of
+    /// bases, including gaps, in the mapping. By default (`None`), no overlaps are ignored.
+    ///
+    /// Short overlaps are more likely to be spurious, so a higher threshold trades sensitivity
+    /// for specificity. This complements [`Builder::remove_internal`], which ignores overlaps by
+    /// their overhang rather than their length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().min_overlap_len(Some(1000));
+    /// ```
+    pub fn min_overlap_len(mut self, min_overlap_len: Option<usize>) -> Self {
+        self.min_overlap_len = min_overlap_len;
+        self
+    }
+
+    /// Set whether only the primary chain between a read and each of the reads it overlaps is
+    /// considered when counting overlaps. By default, this is `false`, and a read is counted as an
+    /// overlap if any of the chains to it pass the overlap filters - e.g., a secondary chain in a
+    /// repeat can be counted when the primary chain is [internal][Builder::remove_internal].
+    ///
+    /// The overlap presets report every chain, so minimap2 marks them all as secondary
+    /// (`tp:A:S`). Instead, the primary chain is taken to be the highest-scoring chain to each
+    /// read, which minimap2 reports first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().primary_only(true);
+    /// ```
+    pub fn primary_only(mut self, primary_only: bool) -> Self {
+        self.primary_only = primary_only;
+        self
+    }
+
+    /// Set whether overlaps are weighted by their length. By default, this is `false`, and each
+    /// read a read overlaps counts once, however short the overlap.
+    ///
+    /// When `true`, each overlap instead contributes its length as a proportion of the read's
+    /// length, and the per-read estimate is from the total length of the overlaps (see
+    /// [`PerReadEstimator::estimate_length_weighted`]). This follows coverage-style reasoning and
+    /// is more robust to tiny dovetail overlaps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().length_weighted(true);
+    /// ```
+    pub fn length_weighted(mut self, length_weighted: bool) -> Self {
+        self.length_weighted = length_weighted;
+        self
+    }
+
+    /// Set whether reads that are contained in (i.e., fully covered by) a read they overlap are
+    /// excluded from the estimate. By default, this is `false`.
+    ///
+    /// A read is contained if the other read extends at least as far at both ends of an overlap
+    /// that is not an [internal match][Builder::remove_internal]. Either way, the fraction of
+    /// reads that are contained is available from
+    /// [`Estimate::contained_fraction`][crate::Estimate::contained_fraction].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().exclude_contained(true);
+    /// ```
+    pub fn exclude_contained(mut self, exclude_contained: bool) -> Self {
+        self.exclude_contained = exclude_contained;
+        self
+    }
+
+    /// Set whether reads that are a near-identical duplicate of a read they overlap - e.g., PCR
+    /// duplicates from an amplified library, which inflate the overlap counts - are excluded from
+    /// the estimate. By default, this is `false`.
+    ///
+    /// Two reads are duplicates if they are the same length and an overlap between them covers at
+    /// least 95% of both with at most 5% divergence. Only query reads that duplicate a target read
+    /// are detected. Either way, the fraction of reads that are duplicates is available from
+    /// [`Estimate::duplicate_fraction`][crate::Estimate::duplicate_fraction].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().exclude_duplicates(true);
+    /// ```
+    pub fn exclude_duplicates(mut self, exclude_duplicates: bool) -> Self {
+        self.exclude_duplicates = exclude_duplicates;
+        self
+    }
+
+    /// Set option for using the smaller Q/T dataset (in bases) as minimap2 reference. If `false`,
+    /// the target reads are always the reference.
+    ///
+    /// By default (if this is not set), the smaller set is chosen automatically once the reads are
+    /// sampled, and the decision is logged. Setting this to `true` makes the same choice, without
+    /// logging it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().use_min_ref(false); // always index the target reads
+    /// ```
     pub fn use_min_ref(mut self, use_min_ref: bool) -> Self {
-        self.use_min_ref = use_min_ref;
+        self.use_min_ref = Some(use_min_ref);
+        self
+    }
+
+    /// Map an additional batch of query reads if more than this fraction (0-1) of the query reads
+    /// did not overlap any target reads. By default, no additional reads are mapped.
+    ///
+    /// When many query reads have no overlaps, the estimate is the median of far fewer finite
+    /// estimates than requested. So, when this is set, up to as many reads again as the
+    /// [number of query reads][Builder::query_num_reads] are set aside when the reads are sampled,
+    /// and enough of them to replace the reads without overlaps (assuming the same fraction
+    /// overlap) are mapped to the same target index. The fraction is clamped to `[0, 1]`.
+    ///
+    /// The additional reads are mapped to the target reads, so, unless
+    /// [`Builder::use_min_ref`] is set, the target reads are always used as the minimap2 reference
+    /// when this is set. If the query reads are used as the reference, no reads are resampled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().resample_no_mapping_fraction(0.5);
+    /// ```
+    pub fn resample_no_mapping_fraction(mut self, fraction: f32) -> Self {
+        self.resample_no_mapping_fraction = Some(fraction.clamp(0.0, 1.0));
         self
     }
 
     /// Set the number of threads to use with minimap2. By default, this is 1.
+    ///
+    /// A value of 0 means use all available cores, as reported by
+    /// [`std::thread::available_parallelism`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().threads(0); // use all available cores
+    /// ```
     pub fn threads(mut self, threads: usize) -> Self {
         self.threads = threads;
         self
     }
 
+    /// Set the number of threads minimap2 uses to build its index. By default (if this is not
+    /// set), the index is built with the same number of threads given to [`Builder::threads`].
+    ///
+    /// Index construction does not scale with threads as well as mapping does, so it can be
+    /// useful to use fewer threads for indexing. As with [`Builder::threads`], a value of 0 means
+    /// use all available cores.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().threads(32).index_threads(4);
+    /// ```
+    pub fn index_threads(mut self, index_threads: usize) -> Self {
+        self.index_threads = Some(index_threads);
+        self
+    }
+
+    /// Map reads in a caller-provided [rayon](https://docs.rs/rayon) thread pool, rather than
+    /// building a new pool for each estimate. By default (`None`), a new pool with the number of
+    /// threads given to [`Builder::threads`] is built.
+    ///
+    /// The number of threads given to [`Builder::threads`] is still used to build the minimap2
+    /// index, unless [`Builder::index_threads`] is set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+    /// let builder = Builder::new().thread_pool(Some(Arc::new(pool)));
+    /// ```
+    pub fn thread_pool(mut self, thread_pool: Option<Arc<rayon::ThreadPool>>) -> Self {
+        self.thread_pool = thread_pool;
+        self
+    }
+
     /// Set the temporary directory for the strategy. By default, this is the `TMPDIR` environment
     /// variable.
     ///
@@ -128,18 +531,59 @@ impl Builder {
         self
     }
 
+    /// Create a new, uniquely named directory (`lrge-XXXXXX`) inside the [temporary
+    /// directory][Builder::tmpdir] for the intermediate files, when the estimate is generated. The
+    /// directory, and everything in it, is removed when the strategy is dropped - unless it is
+    /// kept with [`Builder::keep_temp`]. Individual files can be kept with
+    /// [`Builder::keep_artifact`], in which case only they are left in the directory. By default, this is `false`, and the intermediate files are
+    /// written directly to the temporary directory, which is neither created nor removed.
+    ///
+    /// The directory's path is available from [`TwoSetStrategy::tmpdir`][crate::TwoSetStrategy::tmpdir]
+    /// once the estimate is generated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().create_tmpdir(true);
+    /// ```
+    pub fn create_tmpdir(mut self, create_tmpdir: bool) -> Self {
+        self.create_tmpdir = create_tmpdir;
+        self
+    }
+
+    /// Don't remove the directory created with [`Builder::create_tmpdir`] when the strategy is
+    /// dropped. By default, this is `false`. It has no effect if the strategy does not create its
+    /// own directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().create_tmpdir(true).keep_temp(true);
+    /// ```
+    pub fn keep_temp(mut self, keep_temp: bool) -> Self {
+        self.keep_temp = keep_temp;
+        self
+    }
+
     /// Set the seed for the strategy. By default (`None`), the seed will be
     /// [randomly generated](https://docs.rs/rand/latest/rand/fn.random.html).
     ///
+    /// The seed can be a number or any string, such as a sample name - see [`crate::seed`].
+    ///
     /// # Examples
     ///
     /// ```
     /// use liblrge::twoset::Builder;
     ///
     /// let builder = Builder::new().seed(Some(42));
+    /// let builder = Builder::new().seed("sample1");
     /// ```
-    pub fn seed(mut self, seed: Option<u64>) -> Self {
-        self.seed = seed;
+    pub fn seed<S: IntoSeed>(mut self, seed: S) -> Self {
+        self.seed = seed.into_seed();
         self
     }
 
@@ -157,6 +601,497 @@ impl Builder {
         self
     }
 
+    /// Set an (approximate) memory budget, in bytes, for the strategy. By default (`None`), there
+    /// is no limit.
+    ///
+    /// The budget constrains the minimap2 index batch size and the number of reads buffered for the
+    /// mapping threads. If the threads and the requested number of reads cannot fit within the
+    /// budget, the strategy will return a
+    /// [`MemoryBudgetError`][crate::error::LrgeError::MemoryBudgetError] before sampling the input
+    /// (though after counting its reads, if a fraction of them is used). If the sampled reads cannot
+    /// fit, it will return one after sampling, before building the index.
+    ///
+    /// If the sampled reads are written to [RAM-backed storage][crate::is_ram_backed] - e.g., a
+    /// temporary directory in `/dev/shm` - they also count against the budget. The overlaps file is
+    /// not counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().max_memory(Some(4 * 1024 * 1024 * 1024));
+    /// ```
+    pub fn max_memory(mut self, max_memory: Option<usize>) -> Self {
+        self.max_memory = max_memory;
+        self
+    }
+
+    /// Set the number of reads buffered in the channel between the thread reading the input and
+    /// the mapping threads. By default, this is [`DEFAULT_CHANNEL_CAPACITY`].
+    ///
+    /// Lowering the capacity reduces memory usage with very long reads, while raising it can
+    /// improve throughput with many threads and fast storage. If a memory budget is set with
+    /// [`Builder::max_memory`], the capacity may be reduced to fit within it. The minimum capacity is 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().channel_capacity(1000);
+    /// ```
+    pub fn channel_capacity(mut self, channel_capacity: usize) -> Self {
+        self.channel_capacity = channel_capacity.max(1);
+        self
+    }
+
+    /// Set the maximum fraction (0-1) of reads allowed to have no overlaps. By default, there is no
+    /// maximum.
+    ///
+    /// If more than this fraction of reads did not overlap, the estimate fails with a
+    /// [`QualityControl`][crate::error::LrgeError::QualityControl] error. A high fraction of
+    /// reads without overlaps can indicate contamination or poor quality reads. The per-read
+    /// estimates are still written to the temporary directory. The fraction is clamped to `[0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().max_no_mapping_fraction(0.2);
+    /// ```
+    pub fn max_no_mapping_fraction(mut self, max_no_mapping_fraction: f32) -> Self {
+        self.max_no_mapping_fraction = Some(max_no_mapping_fraction.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Skip up to `max_bad_records` malformed records in the input file - e.g., a truncated record
+    /// at the end of the file - rather than failing. By default (`None`), a malformed record is an
+    /// error.
+    ///
+    /// Each skipped record is logged, and the number skipped is available from
+    /// [`Estimate::input_num_skipped`][crate::Estimate::input_num_skipped]. If there are more than
+    /// `max_bad_records` malformed records, sampling fails with an
+    /// [`IoError`][crate::error::LrgeError::IoError]. Skipping FASTQ and FASTA records uses a
+    /// simpler, slower parser than the default, so is best left off unless it is needed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().skip_bad_records(Some(100));
+    /// ```
+    pub fn skip_bad_records(mut self, max_bad_records: Option<usize>) -> Self {
+        self.max_bad_records = max_bad_records;
+        self
+    }
+
+    /// Parse, and so validate, every record of the input file when the reads are counted - i.e.,
+    /// when a [fraction of the target][Self::target_fraction] or [query][Self::query_fraction]
+    /// reads is sampled. By default (`false`), the records are only scanned for their IDs and
+    /// lengths when they are counted, rather than parsed. Malformed records are then counted like
+    /// any other, though they are still found when the reads are sampled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().validate_records(true);
+    /// ```
+    pub fn validate_records(mut self, validate_records: bool) -> Self {
+        self.validate_records = validate_records;
+        self
+    }
+
+    /// Cache the number of reads in the input file in a sidecar file next to it - the input's path
+    /// with `.lrge-count` appended - when the reads are counted, and use the cached number,
+    /// rather than count them again, while the input's size and modification time are unchanged.
+    /// This saves reading the input twice when the same input is estimated repeatedly - e.g., in a
+    /// parameter sweep. The sidecar is not used when the records are
+    /// [validated][Self::validate_records], or an [allowlist][Self::allowlist] or
+    /// [blocklist][Self::blocklist] is given. A sidecar that cannot be written is logged, and
+    /// otherwise ignored. By default (`false`), the reads are always counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().cache_count(true);
+    /// ```
+    pub fn cache_count(mut self, cache_count: bool) -> Self {
+        self.cache_count = cache_count;
+        self
+    }
+
+    /// Only sample the reads whose IDs are in the file at `path` - e.g., the reads that passed an
+    /// external QC filter. The file has one ID per line, and only the first whitespace-delimited
+    /// field of each line is used. The reads are still randomly sampled from those in the file.
+    ///
+    /// The reads that are not in the file are skipped as if they were not in the input, so they are
+    /// not included in [`Estimate::input_num_reads`][crate::Estimate::input_num_reads].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().allowlist("passed_qc.txt");
+    /// ```
+    pub fn allowlist<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.allowlist = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Never sample the reads whose IDs are in the file at `path` - e.g., known contaminants or
+    /// control reads, such as the lambda DNA control strand. The file has the same format as the
+    /// [allowlist][Builder::allowlist]. If both are given, a read must be in the allowlist and not
+    /// in the blocklist to be sampled.
+    ///
+    /// Like the reads that are not in the allowlist, the reads in the file are skipped as if they
+    /// were not in the input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().blocklist("contaminants.txt");
+    /// ```
+    pub fn blocklist<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.blocklist = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Trim the low-quality ends of the sampled reads before they are overlapped - see
+    /// [`QualityTrim`]. Low-quality tails reduce the overlaps that are found, and can make overlaps
+    /// look like internal matches. Reads without qualities (e.g., FASTA) are not trimmed, and the
+    /// reads are counted in [`Estimate::input_num_bases`][crate::Estimate::input_num_bases] before
+    /// they are trimmed. By default, the reads are not trimmed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    /// use liblrge::trim::QualityTrim;
+    ///
+    /// let builder = Builder::new().trim_quality(Some(QualityTrim::new(10).window_size(50)));
+    /// ```
+    pub fn trim_quality(mut self, trim: Option<QualityTrim>) -> Self {
+        self.quality_trim = trim;
+        self
+    }
+
+    /// Remove the adapters found by `adapters` from the sampled reads before they are overlapped -
+    /// see [`AdapterScan`]. Adapters near the ends of a read are trimmed, and a read with an
+    /// adapter in the middle (i.e., a chimeric read) is split there, keeping its longest part. How
+    /// many reads were changed is available from
+    /// [`Estimate::adapter_summary`][crate::Estimate::adapter_summary]. By default, adapters are
+    /// not removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::adapter::AdapterScan;
+    /// use liblrge::twoset::Builder;
+    /// use liblrge::Platform;
+    ///
+    /// let builder = Builder::new().remove_adapters(Some(AdapterScan::for_platform(Platform::Nanopore)));
+    /// ```
+    pub fn remove_adapters(mut self, adapters: Option<AdapterScan>) -> Self {
+        self.adapters = adapters;
+        self
+    }
+
+    /// Set the format the overlaps are written in, in the temporary directory. By default, this is
+    /// [PAF][OverlapFormat::Paf]. The format does not change the estimate - see [`OverlapFormat`]
+    /// for what each format contains.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::{twoset::Builder, OverlapFormat};
+    ///
+    /// let builder = Builder::new().overlap_format(OverlapFormat::Sam);
+    /// ```
+    pub fn overlap_format(mut self, overlap_format: OverlapFormat) -> Self {
+        self.overlap_format = overlap_format;
+        self
+    }
+
+    /// Set whether the overlaps are sorted and deduplicated before they are written. By default,
+    /// this is `false`, and the overlaps are written in the order they are found, which varies
+    /// between runs with more than one thread.
+    ///
+    /// When `true`, the overlaps are sorted by query name and coordinates, and an overlap found
+    /// in both directions is only written once, with the lexicographically smaller read name as
+    /// the query. This makes the overlaps of runs with the same seed identical, which is easier to
+    /// diff and parse, but all of the overlaps are held in memory until mapping is finished. The
+    /// estimate is the same either way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().sort_overlaps(true);
+    /// ```
+    pub fn sort_overlaps(mut self, sort_overlaps: bool) -> Self {
+        self.sort_overlaps = sort_overlaps;
+        self
+    }
+
+    /// Write an intermediate file to `path`, rather than to the temporary directory. Artifacts
+    /// that this strategy does not write are ignored. See the [`artifact`][crate::artifact] module.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::artifact::Artifact;
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().artifact_path(Artifact::Overlaps, "overlaps.paf");
+    /// ```
+    pub fn artifact_path<P: AsRef<Path>>(mut self, artifact: Artifact, path: P) -> Self {
+        self.artifacts
+            .set_path(artifact, path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set whether an intermediate file is kept once the estimates have been generated. By
+    /// default, all are kept - though, if the strategy creates its own temporary directory (see
+    /// [`Builder::create_tmpdir`]), they are removed along with it unless they are explicitly kept
+    /// with this. Artifacts that this strategy does not write are ignored. See the
+    /// [`artifact`][crate::artifact] module.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::artifact::Artifact;
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().keep_artifact(Artifact::Target, false);
+    /// ```
+    pub fn keep_artifact(mut self, artifact: Artifact, keep: bool) -> Self {
+        self.artifacts.set_keep(artifact, keep);
+        self
+    }
+
+    /// Replace all of the artifact settings.
+    pub(crate) fn artifacts(mut self, artifacts: Artifacts) -> Self {
+        self.artifacts = artifacts;
+        self
+    }
+
+    /// Set whether minimap2 maps query-target pairs in both directions (`--dual=yes`). By default,
+    /// this is `true`.
+    ///
+    /// With `--dual=no`, minimap2 skips a query-target pair if the query name is lexicographically
+    /// greater than the target name. As the query and target reads are different sets, this drops
+    /// roughly half of the real overlaps and will inflate the estimate, so only turn this off if you
+    /// are experimenting with the method.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().dual(false);
+    /// ```
+    pub fn dual(mut self, dual: bool) -> Self {
+        self.dual = Some(dual);
+        self
+    }
+
+    /// Set a callback that is called with each per-read estimate as soon as it is known. By
+    /// default, there is no callback.
+    ///
+    /// Along with a [`RunningEstimate`][crate::stream::RunningEstimate], this lets the caller
+    /// follow the estimate as it converges. The callback is never called from more than one thread
+    /// at a time, but it holds up the mapping threads while it runs, so should be quick.
+    ///
+    /// With the two-set strategy, the estimates are passed to the callback as each query read is
+    /// mapped. So, unless [`Builder::use_min_ref`] is set, the target reads are always used as the
+    /// minimap2 reference when there is a callback.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().on_estimate(|est| println!("{}", est.estimate));
+    /// ```
+    pub fn on_estimate<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&PerReadEstimate) + Send + 'static,
+    {
+        self.on_estimate = Some(Box::new(callback));
+        self
+    }
+
+    /// Set a callback that is called with every overlap (mapping) between the reads, just before it
+    /// is written to the overlaps file. This lets you stream the overlaps into your own storage or
+    /// analysis, rather than parsing the overlaps file afterwards. If you don't need the file as
+    /// well, remove it with [`keep_artifact`][Builder::keep_artifact].
+    ///
+    /// The overlaps are passed in the order they are written - so they are only sorted if
+    /// [`sort_overlaps`][Builder::sort_overlaps] is set. The callback is called from the thread that
+    /// writes the overlaps file, not the mapping threads, but a slow callback will hold up the
+    /// mapping threads once the writer falls behind.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().on_mapping(|overlap| {
+    ///     println!("{}", String::from_utf8_lossy(&overlap.query_name));
+    /// });
+    /// ```
+    pub fn on_mapping<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&PafRecord) + Send + 'static,
+    {
+        self.on_mapping = Some(Box::new(callback));
+        self
+    }
+
+    /// Set how the genome size estimate for each read is calculated from its overlaps. By default,
+    /// this is [`Equation3`], the formula from the paper.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::estimate::Equation3;
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let builder = Builder::new().per_read_estimator(Equation3);
+    /// ```
+    pub fn per_read_estimator<E>(self, estimator: E) -> Self
+    where
+        E: PerReadEstimator + 'static,
+    {
+        self.shared_per_read_estimator(Arc::new(estimator))
+    }
+
+    /// Set the per-read estimator, from a [`StrategyBuilder`][crate::StrategyBuilder].
+    pub(crate) fn shared_per_read_estimator(
+        mut self,
+        estimator: Arc<dyn PerReadEstimator>,
+    ) -> Self {
+        self.estimator = estimator;
+        self
+    }
+
+    /// Change any of minimap2's mapping options ([`MapOpt`]) - an escape hatch for experts, for
+    /// options that have no setter of their own. The change is made after the
+    /// [platform's][Builder::platform] preset and all of the options set by this builder (e.g.,
+    /// [`dual`][Builder::dual]), so it takes precedence. It can be called more than once, and the
+    /// changes are made in the order they are given.
+    ///
+    /// The options are minimap2's own, so see its documentation for what they mean. Nothing is
+    /// checked, so a change minimap2 does not expect can give a meaningless estimate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// // the same as minimap2's `-m 200`
+    /// let builder = Builder::new().with_mapopt(|opt| opt.min_chain_score = 200);
+    /// ```
+    pub fn with_mapopt<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut MapOpt) + Send + Sync + 'static,
+    {
+        self.overrides.add_mapopt(f);
+        self
+    }
+
+    /// Change any of minimap2's index options ([`IdxOpt`]) - an escape hatch for experts, for
+    /// options that have no setter of their own. As with [`with_mapopt`][Builder::with_mapopt], the
+    /// change is made after the preset and all of the options set by this builder, and nothing is
+    /// checked - except that the reads must still fit in one index, so a `batch_size` smaller than
+    /// the bases to index makes building the index fail with an [`IndexError`][LrgeError::IndexError].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// // the same as minimap2's `-k 17`
+    /// let builder = Builder::new().with_idxopt(|opt| opt.k = 17);
+    /// ```
+    pub fn with_idxopt<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut IdxOpt) + Send + Sync + 'static,
+    {
+        self.overrides.add_idxopt(f);
+        self
+    }
+
+    /// Add the changes to minimap2's options from a [`StrategyBuilder`][crate::StrategyBuilder].
+    pub(crate) fn option_overrides(mut self, overrides: OptionOverrides) -> Self {
+        self.overrides.extend(overrides);
+        self
+    }
+
+    /// Build the [`TwoSetStrategy`], using the reads from the given `input` file, after checking
+    /// that the configuration is valid.
+    ///
+    /// Unlike [`Builder::build`], which accepts any configuration, this fails up front with an
+    /// [`InvalidConfiguration`][LrgeError::InvalidConfiguration] error if:
+    ///
+    /// - the number of target or query reads is 0
+    /// - the fraction of target or query reads is not greater than 0 and at most 1
+    /// - the maximum overhang ratio is not between 0 and 1 (when removing internal overlaps)
+    /// - the maximum divergence is not between 0 and 1
+    /// - the memory budget is 0
+    /// - the temporary directory does not exist
+    /// - the directory an [artifact][Builder::artifact_path] is to be written to does not exist
+    /// - the [allowlist][Builder::allowlist] or [blocklist][Builder::blocklist] does not exist
+    /// - the `input` file does not exist
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use liblrge::twoset::Builder;
+    ///
+    /// let result = Builder::new().target_num_reads(0).try_build("input.fastq");
+    /// assert!(result.is_err());
+    /// ```
+    pub fn try_build<P: AsRef<Path>>(self, input: P) -> crate::Result<TwoSetStrategy> {
+        if self.target_num_reads == 0 {
+            return Err(LrgeError::invalid_configuration(
+                "target_num_reads",
+                "must be greater than 0",
+            ));
+        }
+        if self.query_num_reads == 0 {
+            return Err(LrgeError::invalid_configuration(
+                "query_num_reads",
+                "must be greater than 0",
+            ));
+        }
+        sample::validate_fraction("target_fraction", self.target_fraction)?;
+        sample::validate_fraction("query_fraction", self.query_fraction)?;
+        crate::validate_builder(
+            input.as_ref(),
+            &self.tmpdir,
+            self.remove_internal.then_some(self.max_overhang_ratio),
+            self.max_divergence,
+            self.max_memory,
+        )?;
+        self.artifacts.validate()?;
+        read_filter::validate("allowlist", self.allowlist.as_deref())?;
+        read_filter::validate("blocklist", self.blocklist.as_deref())?;
+
+        Ok(self.build(input))
+    }
+
     /// Build the [`TwoSetStrategy`], using the reads from the given `input` file.
     ///
     /// # Examples
@@ -167,19 +1102,63 @@ impl Builder {
     /// let strategy = Builder::new().target_num_reads(1000).build("input.fastq");
     /// ```
     pub fn build<P: AsRef<Path>>(self, input: P) -> TwoSetStrategy {
+        let threads = crate::resolve_threads(self.threads);
         TwoSetStrategy {
             input: input.as_ref().to_path_buf(),
             target_num_reads: self.target_num_reads,
             target_num_bases: self.target_num_bases,
             query_num_reads: self.query_num_reads,
             query_num_bases: self.query_num_bases,
+            target_fraction: self.target_fraction,
+            query_fraction: self.query_fraction,
             remove_internal: self.remove_internal,
             max_overhang_ratio: self.max_overhang_ratio,
+            max_overhang_size: self.max_overhang_size,
+            max_divergence: self.max_divergence,
+            min_mapq: self.min_mapq,
+            min_overlap_len: self.min_overlap_len,
+            primary_only: self.primary_only,
+            length_weighted: self.length_weighted,
+            exclude_contained: self.exclude_contained,
+            exclude_duplicates: self.exclude_duplicates,
             use_min_ref: self.use_min_ref,
+            resample_no_mapping_fraction: self.resample_no_mapping_fraction,
             tmpdir: self.tmpdir,
-            threads: self.threads,
+            managed_tmpdir: self
+                .create_tmpdir
+                .then(|| ManagedTmpdir::new(self.keep_temp)),
+            threads,
+            index_threads: self.index_threads.map_or(threads, crate::resolve_threads),
+            thread_pool: self.thread_pool,
             seed: self.seed,
             platform: self.platform,
+            max_memory: self.max_memory,
+            channel_capacity: self.channel_capacity,
+            max_no_mapping_fraction: self.max_no_mapping_fraction,
+            max_bad_records: self.max_bad_records,
+            validate_records: self.validate_records,
+            cache_count: self.cache_count,
+            allowlist: self.allowlist,
+            blocklist: self.blocklist,
+            quality_trim: self.quality_trim,
+            adapters: self.adapters,
+            overlap_format: self.overlap_format,
+            sort_overlaps: self.sort_overlaps,
+            artifacts: self.artifacts,
+            dual: self.dual.unwrap_or(true),
+            on_estimate: self.on_estimate.map(EstimateSink::new),
+            on_mapping: self.on_mapping.map(MappingSink::new),
+            estimator: self.estimator,
+            overrides: self.overrides,
+            input_num_reads: None,
+            input_num_bases: None,
+            input_num_skipped: None,
+            gc_content: None,
+            contained_fraction: None,
+            duplicate_fraction: None,
+            adapter_summary: None,
+            num_overlaps: None,
+            raw_estimates: None,
         }
     }
 }
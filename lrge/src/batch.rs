@@ -0,0 +1,229 @@
+//! Estimate the genome size of many samples in a single invocation.
+use crate::build_strategy;
+use crate::cli::BatchArgs;
+use crate::qc;
+use crate::utils::OutputFormat;
+use anyhow::{bail, Context, Result};
+use liblrge::batch::Batch;
+use log::{error, info};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// A sample and the file containing its reads.
+#[derive(Debug, PartialEq)]
+struct Sample {
+    name: String,
+    path: PathBuf,
+}
+
+/// Parse the samples file. Each line holds a sample name and the path to its reads, separated by
+/// a tab. Empty lines and lines starting with `#` are ignored.
+fn parse_samples<R: BufRead>(reader: R) -> Result<Vec<Sample>> {
+    let mut samples = Vec::new();
+    let mut names = HashSet::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [name, path] = fields[..] else {
+            bail!(
+                "Line {} of the samples file should have 2 tab-separated columns, but has {}",
+                i + 1,
+                fields.len()
+            );
+        };
+
+        if !names.insert(name.to_string()) {
+            bail!("Sample {name} appears more than once in the samples file");
+        }
+
+        let path = PathBuf::from(path);
+        if !path.exists() {
+            bail!(
+                "Reads file for sample {name} does not exist: {}",
+                path.to_string_lossy()
+            );
+        }
+
+        samples.push(Sample {
+            name: name.to_string(),
+            path,
+        });
+    }
+
+    if samples.is_empty() {
+        bail!("No samples were found in the samples file");
+    }
+
+    Ok(samples)
+}
+
+/// Format an optional value for the results table.
+pub(crate) fn format_value(value: Option<f32>, format: OutputFormat) -> String {
+    match value {
+        Some(v) => format.format(v),
+        None => String::from("NA"),
+    }
+}
+
+/// Format an optional count for the results table.
+fn format_count(count: Option<u64>) -> String {
+    count.map_or_else(|| String::from("NA"), |c| c.to_string())
+}
+
+/// Estimate the genome size of every sample in the samples file and write a table of the results.
+///
+/// Samples are estimated `jobs` at a time, all sharing a single thread pool for mapping. If a
+/// sample fails, the error is logged, its row is filled with `NA`, and the remaining samples are
+/// still estimated.
+pub(crate) fn run(args: &BatchArgs, tmpdir: &Path) -> Result<ExitCode> {
+    let opts = &args.opts;
+    let reader = File::open(&args.samples)
+        .map(BufReader::new)
+        .context("Failed to open samples file")?;
+    let samples = parse_samples(reader)?;
+
+    let mut output: Box<dyn Write> = if args.output == "-" {
+        Box::new(io::stdout())
+    } else {
+        Box::new(File::create(&args.output).context("Failed to create output file")?)
+    };
+
+    let strategies = samples
+        .iter()
+        .map(|sample| build_strategy(opts, &sample.path, tmpdir))
+        .collect::<Result<Vec<_>>>()?;
+    let outcome = Batch::new()
+        .concurrency(args.jobs as usize)
+        .threads(opts.threads)
+        .tmpdir(tmpdir)
+        .keep_temp(opts.keep_temp)
+        .finite(!opts.with_infinity)
+        .quantiles(Some(opts.lower_q), Some(opts.upper_q))
+        .run(strategies)
+        .context("Failed to run batch")?;
+
+    // the relative error is only reported when there is an expected size to compare against
+    let mut header = String::from(
+        "sample\testimate\tlower\tupper\tno_mapping_count\tinput_num_reads\tinput_num_bases",
+    );
+    if opts.expected_size.is_some() {
+        header.push_str("\trelative_error");
+    }
+    writeln!(output, "{header}")?;
+    let mut num_failed = 0;
+    let mut qc_failures = Vec::new();
+    for (sample, result) in samples.iter().zip(outcome.results) {
+        match result {
+            Ok(r) => {
+                write!(
+                    output,
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    sample.name,
+                    format_value(r.estimate, opts.output_format()),
+                    format_value(r.lower, opts.output_format()),
+                    format_value(r.upper, opts.output_format()),
+                    r.no_mapping_count,
+                    format_count(r.input_num_reads),
+                    format_count(r.input_num_bases)
+                )?;
+                if let Some(expected) = opts.expected_size {
+                    match r.estimate {
+                        Some(est) => write!(output, "\t{}", qc::relative_error(est, expected))?,
+                        None => write!(output, "\tNA")?,
+                    }
+                }
+                writeln!(output)?;
+                for failure in qc::check(&r, opts) {
+                    error!("Sample {} failed quality control: {failure}", sample.name);
+                    qc_failures.push(failure);
+                }
+            }
+            Err(e) => {
+                error!("Sample {} failed: {e}", sample.name);
+                num_failed += 1;
+                write!(output, "{}\tNA\tNA\tNA\tNA\tNA\tNA", sample.name)?;
+                if opts.expected_size.is_some() {
+                    write!(output, "\tNA")?;
+                }
+                writeln!(output)?
+            }
+        }
+    }
+    output.flush()?;
+
+    if num_failed > 0 {
+        bail!("{num_failed} of {} samples failed", samples.len());
+    }
+
+    info!("Done!");
+    // the exit code is that of the lowest failed check across all samples
+    qc_failures.sort_by_key(|f| f.exit_code());
+    Ok(qc::exit_code(&qc_failures))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_samples() {
+        let text = "# comment\ns1\tCargo.toml\n\ns2\tsrc/main.rs\n";
+        let samples = parse_samples(text.as_bytes()).unwrap();
+        assert_eq!(
+            samples,
+            vec![
+                Sample {
+                    name: "s1".to_string(),
+                    path: PathBuf::from("Cargo.toml")
+                },
+                Sample {
+                    name: "s2".to_string(),
+                    path: PathBuf::from("src/main.rs")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_samples_wrong_number_of_columns() {
+        let result = parse_samples("s1\tCargo.toml\textra\n".as_bytes());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_samples_duplicate_name() {
+        let result = parse_samples("s1\tCargo.toml\ns1\tCargo.toml\n".as_bytes());
+        assert!(result.unwrap_err().to_string().contains("more than once"));
+    }
+
+    #[test]
+    fn test_parse_samples_missing_file() {
+        let result = parse_samples("s1\tfake.path\n".as_bytes());
+        assert!(result.unwrap_err().to_string().contains("does not exist"));
+    }
+
+    #[test]
+    fn test_parse_samples_empty() {
+        assert!(parse_samples("# only a comment\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_format_value() {
+        let precise = OutputFormat {
+            precise: true,
+            ..Default::default()
+        };
+        assert_eq!(format_value(Some(1234.4), OutputFormat::default()), "1234");
+        assert_eq!(format_value(Some(1234.5), precise), "1234.5");
+        assert_eq!(format_value(None, OutputFormat::default()), "NA");
+    }
+}
@@ -0,0 +1,295 @@
+//! Pre-flight checks of an input file, with `lrge check`, so that problems are found before a long
+//! estimate is run.
+use crate::cli::{CheckArgs, Options};
+use crate::utils::{format_bytes, format_estimate, ram_temp_dir};
+use anyhow::{Context, Result};
+use liblrge::check::{available_space, summarise_input, InputSummary};
+use log::{debug, error, info, warn};
+use std::fmt;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// How serious a problem is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Severity {
+    /// The estimate will run, but perhaps not as intended
+    Warning,
+    /// The estimate will fail, or is unlikely to be trustworthy
+    Error,
+}
+
+/// A problem with the input, or with the options it would be estimated with.
+#[derive(Debug, PartialEq)]
+struct Problem {
+    severity: Severity,
+    message: String,
+}
+
+impl Problem {
+    fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+        }
+    }
+
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// The free space in the temporary directory an estimate would use.
+#[derive(Debug)]
+struct TempSpace {
+    /// The temporary directory
+    dir: PathBuf,
+    /// The space available, in bytes
+    available: u64,
+}
+
+/// Check the input for problems, logging them, and exit unsuccessfully if any are errors.
+pub(crate) fn run(args: &CheckArgs) -> Result<ExitCode> {
+    let opts = &args.opts;
+    info!("Checking {}...", args.input.display());
+    let summary = summarise_input(&args.input).context("Failed to read the input")?;
+    info!(
+        "Input contains {} reads and {} bases ({})",
+        summary.num_reads,
+        summary.num_bases,
+        format_estimate(summary.num_bases as f32)
+    );
+
+    let temp_space = temp_space(opts);
+    let problems = review(&summary, opts, temp_space.as_ref());
+    for problem in &problems {
+        match problem.severity {
+            Severity::Warning => warn!("{problem}"),
+            Severity::Error => error!("{problem}"),
+        }
+    }
+
+    if problems.iter().any(|p| p.severity == Severity::Error) {
+        return Ok(ExitCode::FAILURE);
+    }
+    if problems.is_empty() {
+        info!("No problems found");
+    } else {
+        info!("No errors found, but see the warnings above");
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The free space in the temporary directory that an estimate with `opts` would use, if known.
+fn temp_space(opts: &Options) -> Option<TempSpace> {
+    let ram_dir = opts.temp_in_ram.then(ram_temp_dir).flatten();
+    let dir = ram_dir
+        .or_else(|| opts.temp_dir.clone())
+        .unwrap_or_else(std::env::temp_dir);
+    // the temporary directory is created if it does not exist, so check where it would be
+    let existing = dir.ancestors().find(|p| p.exists()).unwrap_or(&dir);
+    let available = available_space(existing);
+    if available.is_none() {
+        debug!(
+            "Could not determine the free space in {}",
+            existing.display()
+        );
+    }
+    available.map(|available| TempSpace { dir, available })
+}
+
+/// Review the summary of the input against the options it would be estimated with.
+fn review(summary: &InputSummary, opts: &Options, temp_space: Option<&TempSpace>) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    if summary.num_malformed > 0 {
+        let n = summary.num_malformed;
+        match opts.max_bad_records {
+            Some(max) if max as u64 >= n => problems.push(Problem::warning(format!(
+                "{n} malformed record(s) will be skipped (see the warnings above)"
+            ))),
+            Some(max) => problems.push(Problem::error(format!(
+                "{n} malformed record(s) found (see the warnings above), which is more than the \
+                 --skip-bad-records limit of {max}"
+            ))),
+            None => problems.push(Problem::error(format!(
+                "{n} malformed record(s) found (see the warnings above). Use --skip-bad-records to \
+                 skip them"
+            ))),
+        }
+    }
+
+    if summary.num_duplicates > 0 {
+        problems.push(Problem::error(format!(
+            "{} read(s) have the same ID as an earlier read (e.g., {}). The estimate will fail if \
+             both are sampled",
+            summary.num_duplicates,
+            summary.duplicate_ids.join(", ")
+        )));
+    }
+
+    let num_reads = summary.num_reads;
+    // fractions of the reads are resolved as they are by liblrge
+    let resolve =
+        |fraction: f32| ((f64::from(fraction) * num_reads as f64).round() as usize).max(1);
+    let num_requested = opts.num_frac.map(|f| resolve(f).max(2)).or(opts.num_reads);
+    let sample_size = if let Some(requested) = num_requested {
+        if num_reads < 2 {
+            problems.push(Problem::error(format!(
+                "The input has {num_reads} read(s), but at least 2 are needed"
+            )));
+        } else if num_reads < requested as u64 {
+            problems.push(Problem::warning(format!(
+                "The input has {num_reads} reads, fewer than the {requested} requested (-n), so \
+                 all will be used"
+            )));
+        }
+        requested
+    } else {
+        let target = opts
+            .target_frac
+            .map(resolve)
+            .or(opts.target_num_reads)
+            .unwrap_or_default();
+        let query = opts
+            .query_frac
+            .map(resolve)
+            .or(opts.query_num_reads)
+            .unwrap_or_default();
+        if num_reads <= query as u64 {
+            problems.push(Problem::error(format!(
+                "The input has {num_reads} reads, but more than the {query} query reads (-Q) are \
+                 needed"
+            )));
+        } else if num_reads < (target + query) as u64 {
+            problems.push(Problem::warning(format!(
+                "The input has {num_reads} reads, fewer than the {} target (-T) and query (-Q) \
+                 reads requested, so only {} target reads will be used",
+                target + query,
+                num_reads - query as u64
+            )));
+        }
+        target + query
+    };
+
+    if let Some(space) = temp_space {
+        let needed = summary.temp_space_needed(sample_size);
+        if needed > space.available {
+            problems.push(Problem::error(format!(
+                "Approximately {} is needed for the sampled reads in the temporary directory {}, \
+                 but only {} is available. Use --temp to choose another directory",
+                format_bytes(needed),
+                space.dir.display(),
+                format_bytes(space.available)
+            )));
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Args;
+    use clap::Parser;
+
+    fn options(argv: &[&str]) -> Options {
+        let mut args = vec!["lrge", "Cargo.toml"];
+        args.extend_from_slice(argv);
+        Args::try_parse_from(args).unwrap().opts
+    }
+
+    fn summary(num_reads: u64) -> InputSummary {
+        InputSummary {
+            num_reads,
+            num_bases: num_reads * 10_000,
+            ..Default::default()
+        }
+    }
+
+    fn severities(problems: &[Problem]) -> Vec<Severity> {
+        problems.iter().map(|p| p.severity).collect()
+    }
+
+    #[test]
+    fn test_review_no_problems() {
+        assert!(review(&summary(20_000), &options(&[]), None).is_empty());
+        assert!(review(&summary(20), &options(&["-n", "20"]), None).is_empty());
+    }
+
+    #[test]
+    fn test_review_sample_sizes() {
+        let problems = review(&summary(10_000), &options(&[]), None);
+        assert_eq!(severities(&problems), vec![Severity::Warning]);
+        assert!(problems[0].message.contains("only 5000 target reads"));
+
+        let problems = review(&summary(5_000), &options(&[]), None);
+        assert_eq!(severities(&problems), vec![Severity::Error]);
+
+        let problems = review(&summary(10), &options(&["-n", "20"]), None);
+        assert_eq!(severities(&problems), vec![Severity::Warning]);
+
+        let problems = review(&summary(1), &options(&["-n", "20"]), None);
+        assert_eq!(severities(&problems), vec![Severity::Error]);
+    }
+
+    #[test]
+    fn test_review_malformed_records() {
+        let mut input = summary(20_000);
+        input.num_malformed = 3;
+
+        let problems = review(&input, &options(&[]), None);
+        assert_eq!(severities(&problems), vec![Severity::Error]);
+        assert!(problems[0].message.contains("Use --skip-bad-records"));
+
+        let problems = review(&input, &options(&["--skip-bad-records", "2"]), None);
+        assert_eq!(severities(&problems), vec![Severity::Error]);
+
+        let problems = review(&input, &options(&["--skip-bad-records"]), None);
+        assert_eq!(severities(&problems), vec![Severity::Warning]);
+    }
+
+    #[test]
+    fn test_review_duplicates() {
+        let mut input = summary(20_000);
+        input.num_duplicates = 2;
+        input.duplicate_ids = vec!["r1".to_string(), "r7".to_string()];
+
+        let problems = review(&input, &options(&[]), None);
+        assert_eq!(severities(&problems), vec![Severity::Error]);
+        assert!(problems[0].message.contains("(e.g., r1, r7)"));
+    }
+
+    #[test]
+    fn test_review_temp_space() {
+        let input = summary(20_000);
+        let mut space = TempSpace {
+            dir: PathBuf::from("/tmp"),
+            available: 1 << 40,
+        };
+        assert!(review(&input, &options(&[]), Some(&space)).is_empty());
+
+        space.available = 1 << 20;
+        let problems = review(&input, &options(&[]), Some(&space));
+        assert_eq!(severities(&problems), vec![Severity::Error]);
+        assert!(problems[0].message.contains("only 1.00 MiB is available"));
+    }
+
+    #[test]
+    fn test_temp_space_of_missing_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("not").join("yet");
+        let opts = options(&["--temp", missing.to_str().unwrap()]);
+        let space = temp_space(&opts).unwrap();
+        assert_eq!(space.dir, missing);
+    }
+}
@@ -1,56 +1,494 @@
-use clap::{builder::ArgPredicate, Parser};
+use crate::qc::SizeRange;
+use crate::utils::OutputFormat;
+use clap::{builder::ArgPredicate, Parser, Subcommand, ValueEnum};
+use liblrge::trim::DEFAULT_TRIM_WINDOW_SIZE;
+use liblrge::{OverlapFormat, Platform};
 use std::ffi::OsStr;
 use std::path::PathBuf;
+use std::sync::LazyLock;
 
 const TARGET_NUM_READS: &str = "10000";
 const QUERY_NUM_READS: &str = "5000";
+const SKETCH_NUM_READS: &str = "10000";
 const MAX_OVERHANG_RATIO: &str = "0.2";
+const MAX_BAD_RECORDS: &str = "100";
+
+/// The version shown by `--version`, which includes the version of minimap2 lrge is built with, as
+/// it can change the estimate.
+static LONG_VERSION: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        "{}\nminimap2 {}",
+        env!("CARGO_PKG_VERSION"),
+        liblrge::minimap2_version()
+    )
+});
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    long_version = LONG_VERSION.as_str(),
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct Args {
+    /// Input FASTQ, FASTA, or unaligned BAM/CRAM/SAM file
+    #[arg(name = "INPUT", required = true, value_parser = check_path_exists)]
+    pub input: Option<PathBuf>,
+
+    /// Output file for the estimate
+    #[arg(short, long, value_name = "OUTPUT", default_value = "-")]
+    pub output: String,
+
+    /// Start the output with `#`-commented lines recording the lrge version, date, input, command, strategy, seed, and quantiles, so the output is self-describing
+    #[arg(long, hide_short_help = true)]
+    pub header: bool,
+
+    /// Write a report of the estimate, its quantiles, quality control metrics, and provenance (versions, parameters, input checksum, host, and run time) to this file
+    #[arg(long = "report", value_name = "FILE", hide_short_help = true)]
+    pub report: Option<PathBuf>,
+
+    /// Format of the report
+    #[arg(long = "report-format", value_name = "FORMAT", value_enum, default_value_t = ReportFormat::Json, hide_short_help = true)]
+    pub report_format: ReportFormat,
+
+    /// Write a rarefaction table of the estimate at increasing numbers of reads to this file
+    ///
+    /// The estimate is recalculated from random, nested subsets of the per-read estimates, so no
+    /// reads are overlapped again. If the estimate has plateaued well before the last row, the
+    /// number of (query) reads was large enough.
+    #[arg(long, value_name = "FILE", hide_short_help = true)]
+    pub rarefaction: Option<PathBuf>,
+
+    /// Comma-separated numbers of reads for the rarefaction table [default: 500, doubling up to all reads]
+    #[arg(
+        long,
+        value_name = "INT,...",
+        value_delimiter = ',',
+        requires = "rarefaction",
+        hide_short_help = true
+    )]
+    pub rarefaction_sizes: Option<Vec<usize>>,
+
+    /// Write the overlap graph of the sampled reads to this file, in GFA format
+    ///
+    /// Each read with an overlap is a segment, and each dovetail overlap is a link. Contained and
+    /// internal overlaps are not included. Handy for inspecting the graph in Bandage - e.g., a
+    /// contaminant shows up as a separate component.
+    #[arg(long, value_name = "FILE", hide_short_help = true)]
+    pub gfa: Option<PathBuf>,
+
+    /// Write the IDs and lengths of the reads with no overlaps to this file, as a tab-delimited table
+    ///
+    /// BLASTing a few of these reads can show whether a high proportion of reads without overlaps
+    /// is due to contamination, adapters, or short junk reads.
+    #[arg(long, value_name = "FILE", hide_short_help = true)]
+    pub no_overlap_reads: Option<PathBuf>,
+
+    /// Decompose the estimate into putative replicons, such as the chromosome and plasmids of a bacterial isolate
+    ///
+    /// Each connected component of the overlap graph with enough reads is a replicon, and its size
+    /// is estimated from the estimates of its reads and the fraction of reads in it. The largest is
+    /// assumed to be the chromosome, and the fraction of the total size in the others is reported
+    /// as the plasmid fraction. Enough reads must be sampled for each replicon to be connected.
+    #[arg(long, hide_short_help = true)]
+    pub replicons: bool,
+
+    /// Summarise the distributions of overlaps per read, overlap lengths, and divergence (dv)
+    ///
+    /// The quantiles are logged, and the quantiles and histograms are added to the --report. All
+    /// overlaps are summarised, including those that were not counted (e.g., internal matches).
+    /// Handy for diagnosing why an estimate deviates from what was expected.
+    #[arg(long, hide_short_help = true)]
+    pub overlap_stats: bool,
+
+    /// Also estimate the genome size from the number of islands (connected components) of the overlap graph, using Lander-Waterman theory (for all-vs-all strategy)
+    ///
+    /// This is an independent cross-check of the estimate, as it uses how the reads are connected
+    /// rather than the number of overlaps of each read. The number of islands, apparent coverage,
+    /// and estimate are logged, and added to the --report.
+    #[arg(long, hide_short_help = true)]
+    pub islands: bool,
+
+    /// Log the running estimate and quantiles as the per-read estimates are computed
+    ///
+    /// With the two-set strategy, this means the target reads are used as the minimap2 reference,
+    /// unless --use-min-ref is given. With the all-vs-all strategy, the per-read estimates are only
+    /// known once all reads are overlapped, so the running estimate is only logged at the end.
+    #[arg(long, hide_short_help = true)]
+    pub stream: bool,
+
+    /// Print the resolved strategy, numbers of reads, minimap2 preset, threads, temporary directory, and seed as JSON, and exit without overlapping any reads
+    ///
+    /// If a fraction of the reads is used (e.g., --num-frac), the input reads are counted to
+    /// resolve it, but no reads are sampled. Handy for cheaply checking a pipeline's parameters.
+    #[arg(long, hide_short_help = true)]
+    pub dry_run: bool,
+
+    #[command(flatten)]
+    pub opts: Options,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Estimate the genome size of many samples, writing the results to a single table
+    Batch(Box<BatchArgs>),
+    /// Estimate the genome size of two or more inputs with the same settings, and compare their estimates and interquantile ranges
+    Compare(Box<CompareArgs>),
+    /// Recalculate the estimate from the per-read estimates of a previous run, without overlapping the reads again
+    Reestimate(ReestimateArgs),
+    /// Check an input file for problems before running an estimate with the same options
+    Check(Box<CheckArgs>),
+    /// Plot the distribution of the per-read estimates of a previous run, with the estimate and quantiles marked
+    Plot(PlotArgs),
+    /// Render the man page for lrge, including the options hidden from `-h`
+    Man(ManArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct BatchArgs {
+    /// Tab-delimited file of sample names and their read files - i.e., `<sample>\t<path>` per line
+    #[arg(name = "SAMPLES", value_parser = check_path_exists)]
+    pub samples: PathBuf,
+
+    /// Output file for the results table
+    #[arg(short, long, value_name = "OUTPUT", default_value = "-")]
+    pub output: String,
+
+    /// Number of samples to estimate concurrently. All samples share the same mapping threads
+    #[arg(short, long, value_name = "INT", default_value = "1", value_parser = clap::value_parser!(u64).range(1..))]
+    pub jobs: u64,
+
+    #[command(flatten)]
+    pub opts: Options,
+}
+
+#[derive(Parser, Debug)]
+pub struct CompareArgs {
+    /// Input FASTQ, FASTA, or unaligned BAM/CRAM/SAM files - e.g., the reads before and after filtering
+    #[arg(name = "INPUTS", required = true, num_args = 2.., value_parser = check_path_exists)]
+    pub inputs: Vec<PathBuf>,
+
+    /// Output file for the comparison table. The overlap of each input's interquantile range with that of the first input is reported
+    #[arg(short, long, value_name = "OUTPUT", default_value = "-")]
+    pub output: String,
+
+    /// Number of inputs to estimate concurrently. All inputs share the same mapping threads
+    #[arg(short, long, value_name = "INT", default_value = "1", value_parser = clap::value_parser!(u64).range(1..))]
+    pub jobs: u64,
+
+    #[command(flatten)]
+    pub opts: Options,
+}
+
+#[derive(Parser, Debug)]
+pub struct CheckArgs {
     /// Input FASTQ, FASTA, or unaligned BAM/CRAM/SAM file
     #[arg(name = "INPUT", value_parser = check_path_exists)]
     pub input: PathBuf,
 
+    #[command(flatten)]
+    pub opts: Options,
+}
+
+#[derive(Parser, Debug)]
+pub struct ReestimateArgs {
+    /// Temporary directory of a previous run (see --keep-temp), or the estimates.tsv file within it
+    #[arg(name = "PATH", value_parser = check_path_exists)]
+    pub path: PathBuf,
+
     /// Output file for the estimate
     #[arg(short, long, value_name = "OUTPUT", default_value = "-")]
     pub output: String,
 
+    /// Take the estimate as the median of all estimates, *including infinite estimates*
+    #[arg(short = '8', long = "inf")]
+    pub with_infinity: bool,
+
+    /// I neeeeeed that precision! Output the estimate as a floating point number
+    #[arg(short = 'f', long = "float-my-boat")]
+    pub precise: bool,
+
+    /// Unit of the output estimate. `auto` chooses the largest unit that keeps the estimate at least 1
+    #[arg(short = 'u', long = "units", value_name = "UNIT", value_enum, default_value_t = Unit::Bp)]
+    pub units: Unit,
+
+    /// Round the output estimate to this many significant figures
+    #[arg(long = "sig-figs", value_name = "INT", value_parser = clap::value_parser!(u8).range(1..))]
+    pub sig_figs: Option<u8>,
+
+    /// The lower quantile to use for the estimate
+    #[arg(long = "q1", value_name = "FLOAT", default_value_t = liblrge::estimate::LOWER_QUANTILE, value_parser = validate_low_quantile)]
+    pub lower_q: f32,
+
+    /// The upper quantile to use for the estimate
+    #[arg(long = "q3", value_name = "FLOAT", default_value_t = liblrge::estimate::UPPER_QUANTILE, value_parser = validate_high_quantile)]
+    pub upper_q: f32,
+
+    /// Fail, rather than report an unreliable estimate, if fewer than this fraction of reads overlapped another read
+    #[arg(long = "min-finite-fraction", value_name = "FLOAT", default_value_t = liblrge::estimate::MIN_FINITE_FRACTION, value_parser = validate_fraction)]
+    pub min_finite_fraction: f32,
+
+    /// `-q` only show errors and warnings. `-qq` only show errors. `-qqq` shows nothing.
+    #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "verbose")]
+    pub quiet: u8,
+
+    /// `-v` show debug output. `-vv` show trace output.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Also write the log to this file, at the level given by --log-file-level
+    ///
+    /// The log file is independent of -q and -v, which only control the log written to stderr - e.g., `-qqq --log-file lrge.log` writes the log to the file, and nothing to stderr. This is useful when stdout is the estimate and stderr is lost, as on some cluster schedulers.
+    #[arg(long = "log-file", value_name = "FILE", hide_short_help = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// The level of the log written to --log-file
+    #[arg(long = "log-file-level", value_name = "LEVEL", value_enum, default_value_t = LogLevel::Info, requires = "log_file", hide_short_help = true)]
+    pub log_file_level: LogLevel,
+}
+
+#[derive(Parser, Debug)]
+pub struct PlotArgs {
+    /// Temporary directory of a previous run (see --keep-temp), or the estimates.tsv file within it
+    #[arg(name = "PATH", value_parser = check_path_exists)]
+    pub path: PathBuf,
+
+    /// Output file for the plot. It is an HTML page if the file ends in .html, otherwise an SVG image
+    #[arg(short, long, value_name = "OUTPUT")]
+    pub output: PathBuf,
+
+    /// Number of histogram bins. The bins are of equal width on a log scale
+    #[arg(short, long, value_name = "INT", default_value_t = 30, value_parser = clap::value_parser!(u16).range(1..))]
+    pub bins: u16,
+
+    /// Take the estimate as the median of all estimates, *including infinite estimates*
+    #[arg(short = '8', long = "inf")]
+    pub with_infinity: bool,
+
+    /// The lower quantile to mark
+    #[arg(long = "q1", value_name = "FLOAT", default_value_t = liblrge::estimate::LOWER_QUANTILE, value_parser = validate_low_quantile)]
+    pub lower_q: f32,
+
+    /// The upper quantile to mark
+    #[arg(long = "q3", value_name = "FLOAT", default_value_t = liblrge::estimate::UPPER_QUANTILE, value_parser = validate_high_quantile)]
+    pub upper_q: f32,
+
+    /// `-q` only show errors and warnings. `-qq` only show errors. `-qqq` shows nothing.
+    #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "verbose")]
+    pub quiet: u8,
+
+    /// `-v` show debug output. `-vv` show trace output.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Also write the log to this file, at the level given by --log-file-level
+    ///
+    /// The log file is independent of -q and -v, which only control the log written to stderr - e.g., `-qqq --log-file lrge.log` writes the log to the file, and nothing to stderr. This is useful when stdout is the estimate and stderr is lost, as on some cluster schedulers.
+    #[arg(long = "log-file", value_name = "FILE", hide_short_help = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// The level of the log written to --log-file
+    #[arg(long = "log-file-level", value_name = "LEVEL", value_enum, default_value_t = LogLevel::Info, requires = "log_file", hide_short_help = true)]
+    pub log_file_level: LogLevel,
+}
+
+#[derive(Parser, Debug)]
+pub struct ManArgs {
+    /// Output file for the man page
+    #[arg(short, long, value_name = "OUTPUT", default_value = "-")]
+    pub output: String,
+}
+
+impl ReestimateArgs {
+    /// How the estimate is written to the output
+    pub fn output_format(&self) -> OutputFormat {
+        OutputFormat {
+            unit: self.units,
+            sig_figs: self.sig_figs,
+            precise: self.precise,
+        }
+    }
+}
+
+impl Options {
+    /// How the estimate is written to the output
+    pub fn output_format(&self) -> OutputFormat {
+        OutputFormat {
+            unit: self.units,
+            sig_figs: self.sig_figs,
+            precise: self.precise,
+        }
+    }
+}
+
+/// The unit the estimate is output in
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Unit {
+    /// Base pairs
+    #[default]
+    Bp,
+    /// Kilobase pairs
+    Kbp,
+    /// Megabase pairs
+    Mbp,
+    /// Gigabase pairs
+    Gbp,
+    /// The largest unit that keeps the estimate at least 1
+    Auto,
+}
+
+/// The format of the report
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// A JSON object
+    #[default]
+    Json,
+    /// A header line and a single row of tab-separated values
+    Tsv,
+}
+
+/// An estimation strategy, with its default number of reads
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StrategyName {
+    /// The two-set strategy, as with -T and -Q
+    TwoSet,
+    /// The all-vs-all strategy, as with -n
+    Ava,
+    /// The sketch strategy, as with --sketch
+    Sketch,
+    /// Count the reads, then use the two-set strategy for large inputs and the all-vs-all strategy for small ones
+    Auto,
+}
+
+/// The level of a log
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogLevel {
+    /// Only errors
+    Error,
+    /// Errors and warnings
+    Warn,
+    /// Errors, warnings, and progress
+    #[default]
+    Info,
+    /// Debug output, as with -v
+    Debug,
+    /// Trace output, as with -vv
+    Trace,
+}
+
+impl From<LogLevel> for log::LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+/// Options shared by all ways of running an estimate
+#[derive(clap::Args, Debug)]
+pub struct Options {
     /// Target number of reads to use (for two-set strategy; default)
-    #[arg(short = 'T', long = "target", value_name = "INT", default_value_if("num_reads", ArgPredicate::IsPresent, None), default_value = TARGET_NUM_READS)]
+    ///
+    /// Metric suffixes are accepted - e.g., 5k, 10K, or 1.5m.
+    #[arg(short = 'T', long = "target", value_name = "INT", value_parser = parse_count, default_value_ifs([("num_reads", ArgPredicate::IsPresent, None), ("num_frac", ArgPredicate::IsPresent, None), ("sketch", "true".into(), None)]), default_value = TARGET_NUM_READS)]
     pub target_num_reads: Option<usize>,
 
     /// Query number of reads to use (for two-set strategy; default)
-    #[arg(short = 'Q', long = "query", value_name = "INT", default_value_if("num_reads", ArgPredicate::IsPresent, None), default_value = QUERY_NUM_READS)]
+    ///
+    /// Metric suffixes are accepted - e.g., 5k, 10K, or 1.5m.
+    #[arg(short = 'Q', long = "query", value_name = "INT", value_parser = parse_count, default_value_ifs([("num_reads", ArgPredicate::IsPresent, None), ("num_frac", ArgPredicate::IsPresent, None), ("sketch", "true".into(), None)]), default_value = QUERY_NUM_READS)]
     pub query_num_reads: Option<usize>,
 
     /// Number of reads to use (for all-vs-all strategy)
-    #[arg(short, long = "num", value_name = "INT", conflicts_with_all = &["target_num_reads", "query_num_reads"])]
+    ///
+    /// Metric suffixes are accepted - e.g., 5k, 10K, or 1.5m.
+    #[arg(short, long = "num", value_name = "INT", value_parser = parse_count, conflicts_with_all = &["target_num_reads", "query_num_reads"], default_value_if("sketch", "true", SKETCH_NUM_READS))]
     pub num_reads: Option<usize>,
 
+    /// Fraction (0-1) of the reads in the input to use as target reads, instead of a number (-T). The reads are counted first, so the input is read twice
+    #[arg(long = "target-frac", value_name = "FLOAT", value_parser = validate_sample_fraction, conflicts_with_all = &["target_num_reads", "num_reads"], hide_short_help = true)]
+    pub target_frac: Option<f32>,
+
+    /// Fraction (0-1) of the reads in the input to use as query reads, instead of a number (-Q). The reads are counted first, so the input is read twice
+    #[arg(long = "query-frac", value_name = "FLOAT", value_parser = validate_sample_fraction, conflicts_with_all = &["query_num_reads", "num_reads"], hide_short_help = true)]
+    pub query_frac: Option<f32>,
+
+    /// Fraction (0-1) of the reads in the input to use (for all-vs-all strategy), instead of a number (-n). The reads are counted first, so the input is read twice
+    #[arg(long = "num-frac", value_name = "FLOAT", value_parser = validate_sample_fraction, conflicts_with_all = &["target_num_reads", "query_num_reads", "num_reads", "target_frac", "query_frac"], hide_short_help = true)]
+    pub num_frac: Option<f32>,
+
+    /// Find overlaps from k-mer sketches of the -n reads [default: 10000], rather than aligning them. Much faster, but less accurate - e.g., for a rough first estimate
+    #[arg(long, conflicts_with_all = &["target_num_reads", "query_num_reads", "target_frac", "query_frac"])]
+    pub sketch: bool,
+
+    /// Estimation strategy to use with its default number of reads, instead of choosing it with -T/-Q, -n, or --sketch. `auto` counts the reads first, so the input is read twice
+    #[arg(long, value_name = "STRATEGY", value_enum, conflicts_with_all = &["target_num_reads", "query_num_reads", "num_reads", "target_frac", "query_frac", "num_frac", "sketch"])]
+    pub strategy: Option<StrategyName>,
+
     /// Sequencing platform of the reads
-    #[arg(short = 'P', long, value_name = "PLATFORM", value_parser = ["ont", "pb"], default_value = "ont")]
-    pub platform: String,
+    #[arg(short = 'P', long, value_name = "PLATFORM", value_enum, ignore_case = true, default_value_t = Platform::Nanopore, env = "LRGE_PLATFORM")]
+    pub platform: Platform,
 
     /// Exclude overlaps for internal matches
     #[arg(short = 'F', long = "filter-contained")]
     pub filter_contained: bool,
 
-    /// Number of threads to use
-    #[arg(short, long, value_name = "INT", default_value = "1")]
+    /// Number of threads to use. 0 means use all available cores
+    #[arg(
+        short,
+        long,
+        value_name = "INT",
+        default_value = "0",
+        env = "LRGE_THREADS"
+    )]
     pub threads: usize,
 
+    /// Number of threads to use when building the minimap2 index [default: same as --threads]
+    #[arg(long = "index-threads", value_name = "INT", hide_short_help = true)]
+    pub index_threads: Option<usize>,
+
     /// Don't clean up temporary files
+    ///
+    /// A manifest.json describing each kept file - what it is, how it was produced, and its
+    /// checksum - and the provenance of the estimate is written to the temporary directory.
     #[arg(short = 'C', long)]
     pub keep_temp: bool,
 
+    /// Format of the overlaps file in the temporary directory (see --keep-temp)
+    #[arg(long, value_name = "FORMAT", value_enum, ignore_case = true, default_value_t = OverlapFormat::Paf, hide_short_help = true)]
+    pub overlap_format: OverlapFormat,
+
+    /// Sort the overlaps file by query and remove overlaps found in both directions
+    ///
+    /// This makes the overlaps of runs with the same seed identical, but holds all of the overlaps
+    /// in memory until mapping is finished.
+    #[arg(long, hide_short_help = true)]
+    pub sort_overlaps: bool,
+
     /// Temporary directory for storing intermediate files
-    #[arg(short = 'D', long = "temp", value_name = "DIR")]
+    #[arg(short = 'D', long = "temp", value_name = "DIR", env = "LRGE_TMPDIR")]
     pub temp_dir: Option<PathBuf>,
 
-    /// Random seed to use - making the estimate repeatable
-    #[clap(short = 's', long = "seed", value_name = "INT")]
+    /// Put the temporary directory in RAM-backed storage (/dev/shm), if available
+    ///
+    /// This avoids disk I/O for the intermediate files, and takes precedence over --temp. The
+    /// sampled reads then count against --max-memory. Falls back to --temp, or the default
+    /// temporary directory, if /dev/shm is not a tmpfs.
+    #[arg(long, hide_short_help = true)]
+    pub temp_in_ram: bool,
+
+    /// Random seed to use - making the estimate repeatable. Any string, such as a sample name, can be used as the seed
+    #[clap(short = 's', long = "seed", value_name = "SEED", env = "LRGE_SEED", value_parser = parse_seed)]
     pub seed: Option<u64>,
 
     /// Take the estimate as the median of all estimates, *including infinite estimates*
@@ -61,6 +499,14 @@ pub struct Args {
     #[arg(short = 'f', long = "float-my-boat", hide_short_help = true)]
     pub precise: bool,
 
+    /// Unit of the output estimate. `auto` chooses the largest unit that keeps the estimate at least 1
+    #[arg(short = 'u', long = "units", value_name = "UNIT", value_enum, default_value_t = Unit::Bp, hide_short_help = true)]
+    pub units: Unit,
+
+    /// Round the output estimate to this many significant figures
+    #[arg(long = "sig-figs", value_name = "INT", value_parser = clap::value_parser!(u8).range(1..), hide_short_help = true)]
+    pub sig_figs: Option<u8>,
+
     /// The lower quantile to use for the estimate
     #[arg(long = "q1", value_name = "FLOAT", default_value_t = liblrge::estimate::LOWER_QUANTILE, value_parser = validate_low_quantile, hide_short_help = true)]
     pub lower_q: f32,
@@ -69,13 +515,129 @@ pub struct Args {
     #[arg(long = "q3", value_name = "FLOAT", default_value_t = liblrge::estimate::UPPER_QUANTILE, value_parser = validate_high_quantile, hide_short_help = true)]
     pub upper_q: f32,
 
+    /// Also report these quantiles of the per-read estimates - e.g., `0.05,0.25,0.75,0.95`. They are logged, and included in --report
+    #[arg(long, value_name = "FLOAT,...", value_delimiter = ',', value_parser = validate_fraction, hide_short_help = true)]
+    pub quantiles: Vec<f32>,
+
+    /// Also report these intervals between quantiles of the per-read estimates - e.g., `0.15-0.65,0.25-0.75`. Intervals whose confidence was calibrated in the paper are labelled with it (e.g., 0.15-0.65 is a 92% CI)
+    #[arg(long, value_name = "LOW-HIGH,...", value_delimiter = ',', value_parser = validate_interval, hide_short_help = true)]
+    pub intervals: Vec<(f32, f32)>,
+
+    /// Fail, rather than report an unreliable estimate, if fewer than this fraction of reads overlapped another read. When coverage is very low, most per-read estimates are infinite and their median is meaningless
+    #[arg(long = "min-finite-fraction", value_name = "FLOAT", default_value_t = liblrge::estimate::MIN_FINITE_FRACTION, value_parser = validate_fraction, hide_short_help = true)]
+    pub min_finite_fraction: f32,
+
     /// Maximum overhang size to alignment length ratio for internal overlap filtering
-    #[arg(long = "max-overhang-ratio", value_name = "FLOAT", default_value = MAX_OVERHANG_RATIO, value_parser = validate_overhang_ratio, hide_short_help = true)]
+    #[arg(long = "max-overhang-ratio", value_name = "FLOAT", default_value = MAX_OVERHANG_RATIO, value_parser = validate_fraction, hide_short_help = true)]
     pub max_overhang_ratio: f32,
 
-    /// Use the smaller Q/T dataset as minimap2 reference (for two-set strategy)
-    #[arg(long = "use-min-ref", hide_short_help = true)]
-    pub use_min_ref: bool,
+    /// Maximum overhang size, in bases, for internal overlap filtering. An overlap with a longer overhang is an internal match, whatever its ratio
+    #[arg(long = "max-overhang-size", value_name = "INT", hide_short_help = true)]
+    pub max_overhang_size: Option<usize>,
+
+    /// Ignore overlaps with a greater divergence (the dv tag) than this when counting overlaps. Spurious overlaps between repeats tend to be more divergent, and inflate the overlap counts
+    #[arg(long = "max-divergence", value_name = "FLOAT", value_parser = validate_fraction, hide_short_help = true)]
+    pub max_divergence: Option<f32>,
+
+    /// Ignore overlaps with a lower mapping quality (MAPQ) than this when counting overlaps. Ignored overlaps are still written to the overlaps file (see --keep-temp). minimap2 often gives true overlaps a MAPQ of 0 when other reads overlap the same region, so check the overlaps file before choosing a threshold
+    #[arg(long = "min-mapq", value_name = "INT", hide_short_help = true)]
+    pub min_mapq: Option<u8>,
+
+    /// Ignore overlaps with an alignment block shorter than this many bases when counting overlaps. A higher threshold trades sensitivity for specificity
+    #[arg(long = "min-overlap-len", value_name = "INT", hide_short_help = true)]
+    pub min_overlap_len: Option<usize>,
+
+    /// Only consider the highest-scoring (primary) chain to each read when counting overlaps, so a secondary chain in a repeat cannot be counted when the primary chain is filtered out
+    #[arg(long = "primary-only", hide_short_help = true)]
+    pub primary_only: bool,
+
+    /// Weight each overlap by its length, as a proportion of the read's length, rather than counting each overlapping read once. This is more robust to tiny dovetail overlaps
+    #[arg(long = "length-weighted", hide_short_help = true)]
+    pub length_weighted: bool,
+
+    /// Exclude reads that are contained in (fully covered by) a read they overlap from the estimate. The fraction of reads that are contained is logged either way
+    #[arg(long = "exclude-contained", hide_short_help = true)]
+    pub exclude_contained: bool,
+
+    /// Exclude reads that are a near-identical duplicate (e.g., a PCR duplicate) of a read they overlap from the estimate, keeping one read of each set of duplicates. The fraction of reads that are duplicates is logged either way
+    #[arg(long = "exclude-duplicates", hide_short_help = true)]
+    pub exclude_duplicates: bool,
+
+    /// Use the smaller Q/T dataset as minimap2 reference (for two-set strategy). `--use-min-ref false` always uses the target reads [default: chosen automatically]
+    #[arg(long = "use-min-ref", value_name = "BOOL", num_args = 0..=1, default_missing_value = "true", hide_short_help = true)]
+    pub use_min_ref: Option<bool>,
+
+    /// If more than this fraction of query reads do not overlap any target reads, map an additional batch of query reads to the same target index, so the estimate is based on about as many finite estimates as requested (for two-set strategy)
+    #[arg(long = "resample-no-overlap", value_name = "FLOAT", value_parser = validate_fraction, hide_short_help = true)]
+    pub resample_no_overlap: Option<f32>,
+
+    /// Write the versions of lrge, liblrge, and minimap2 to this file, in the YAML format of nf-core's versions.yml
+    #[arg(long = "versions-out", value_name = "FILE", hide_short_help = true)]
+    pub versions_out: Option<PathBuf>,
+
+    /// TOML file of default option values [default: $XDG_CONFIG_HOME/lrge/config.toml, if it exists]
+    #[arg(long = "config", value_name = "FILE", value_parser = check_path_exists, hide_short_help = true)]
+    pub config: Option<PathBuf>,
+
+    /// Approximate memory budget (e.g., 4G, 512M). Errors before sampling if the threads and number of reads cannot fit, and before indexing if the sampled reads cannot
+    #[arg(long = "max-memory", value_name = "SIZE", value_parser = parse_memory, hide_short_help = true)]
+    pub max_memory: Option<usize>,
+
+    /// Skip malformed input records (e.g., a truncated record), rather than failing, up to this many [default if no value is given: 100]
+    #[arg(long = "skip-bad-records", value_name = "INT", num_args = 0..=1, default_missing_value = MAX_BAD_RECORDS, hide_short_help = true)]
+    pub max_bad_records: Option<usize>,
+
+    /// Parse every record when counting the reads (e.g., for --num-frac), rather than only scanning for their IDs and lengths, so malformed records are found before sampling
+    #[arg(long, hide_short_help = true)]
+    pub validate: bool,
+
+    /// Cache the read count in a sidecar file next to the input (<INPUT>.lrge-count), and reuse it while the input is unchanged, so repeated runs on the same input (e.g., --num-frac or --strategy auto) do not count its reads again
+    #[arg(long, hide_short_help = true)]
+    pub cache_count: bool,
+
+    /// Only sample the reads whose IDs are in this file (one per line) - e.g., the reads that passed an external QC filter. Reads are still randomly sampled from those in the file
+    #[arg(long, value_name = "FILE", value_parser = check_path_exists, hide_short_help = true)]
+    pub allowlist: Option<PathBuf>,
+
+    /// Never sample the reads whose IDs are in this file (one per line) - e.g., known contaminants or the lambda DNA control strand
+    #[arg(long, value_name = "FILE", value_parser = check_path_exists, hide_short_help = true)]
+    pub blocklist: Option<PathBuf>,
+
+    /// Trim the ends of the sampled reads until a window of --trim-window bases has at least this mean quality. Low-quality tails reduce the overlaps found. Reads without qualities (e.g., FASTA) are not trimmed
+    #[arg(long, value_name = "INT", hide_short_help = true)]
+    pub trim_quality: Option<u8>,
+
+    /// Number of bases in the sliding window of --trim-quality
+    #[arg(long, value_name = "INT", default_value_t = DEFAULT_TRIM_WINDOW_SIZE, requires = "trim_quality", hide_short_help = true)]
+    pub trim_window: usize,
+
+    /// Trim sequencing adapters from the ends of the sampled reads and split chimeric reads at internal adapters, keeping the longest piece. Adapter sequences are those of --platform
+    #[arg(long, hide_short_help = true)]
+    pub remove_adapters: bool,
+
+    /// Number of reads buffered between the reader and mapping threads [default: 25000 for all-vs-all, 10000 for two-set]
+    #[arg(long = "channel-capacity", value_name = "INT", value_parser = clap::value_parser!(u64).range(1..), hide_short_help = true)]
+    pub channel_capacity: Option<u64>,
+
+    /// Exit with code 3 if more than this percentage of reads did not overlap any other reads
+    #[arg(long = "fail-if-no-overlap-pct", value_name = "FLOAT", value_parser = validate_percentage, hide_short_help = true)]
+    pub max_no_overlap_pct: Option<f32>,
+
+    /// Exit with code 4 if the estimate is outside this range (e.g., 4m:6m). Either bound can be omitted
+    #[arg(long = "fail-if-estimate-outside", value_name = "MIN:MAX", value_parser = parse_size_range, hide_short_help = true)]
+    pub estimate_range: Option<SizeRange>,
+
+    /// Expected genome size (e.g., 5.2m). The relative error of the estimate is reported
+    #[arg(long = "expected-size", value_name = "SIZE", value_parser = parse_genome_size, hide_short_help = true)]
+    pub expected_size: Option<f32>,
+
+    /// Exit with code 5 if the relative error versus --expected-size is larger than this (e.g., 0.1 for 10%)
+    #[arg(long = "max-relative-error", value_name = "FLOAT", value_parser = validate_relative_error, requires = "expected_size", hide_short_help = true)]
+    pub max_relative_error: Option<f32>,
+
+    /// Exit with code 6 if the estimated depth of coverage (input bases / estimate) is below this
+    #[arg(long = "min-coverage", value_name = "FLOAT", value_parser = validate_coverage, hide_short_help = true)]
+    pub min_coverage: Option<f32>,
 
     /// `-q` only show errors and warnings. `-qq` only show errors. `-qqq` shows nothing.
     #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "verbose")]
@@ -84,6 +646,16 @@ pub struct Args {
     /// `-v` show debug output. `-vv` show trace output.
     #[arg(short, long, action = clap::ArgAction::Count)]
     pub verbose: u8,
+
+    /// Also write the log to this file, at the level given by --log-file-level
+    ///
+    /// The log file is independent of -q and -v, which only control the log written to stderr - e.g., `-qqq --log-file lrge.log` writes the log to the file, and nothing to stderr. This is useful when stdout is the estimate and stderr is lost, as on some cluster schedulers.
+    #[arg(long = "log-file", value_name = "FILE", hide_short_help = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// The level of the log written to --log-file
+    #[arg(long = "log-file-level", value_name = "LEVEL", value_enum, default_value_t = LogLevel::Info, requires = "log_file", hide_short_help = true)]
+    pub log_file_level: LogLevel,
 }
 
 /// A utility function that allows the CLI to error if a path doesn't exist
@@ -111,17 +683,17 @@ fn validate_quantile(s: &str, min: f32, max: f32) -> Result<f32, String> {
 }
 
 /// A value parser for the lower quantile
-fn validate_low_quantile(s: &str) -> Result<f32, String> {
+pub(crate) fn validate_low_quantile(s: &str) -> Result<f32, String> {
     validate_quantile(s, 0.0, 0.5)
 }
 
 /// A value parser for the upper quantile
-fn validate_high_quantile(s: &str) -> Result<f32, String> {
+pub(crate) fn validate_high_quantile(s: &str) -> Result<f32, String> {
     validate_quantile(s, 0.5, 1.0)
 }
 
-/// A value parser for the maximum overhang ratio
-fn validate_overhang_ratio(s: &str) -> Result<f32, String> {
+/// A value parser for values between 0 and 1 - e.g., the maximum overhang ratio
+fn validate_fraction(s: &str) -> Result<f32, String> {
     let value: f32 = s
         .parse()
         .map_err(|_| format!("`{s}` is not a valid number",))?;
@@ -132,6 +704,177 @@ fn validate_overhang_ratio(s: &str) -> Result<f32, String> {
     }
 }
 
+/// A value parser for an interval between two quantiles, given as `LOW-HIGH`
+fn validate_interval(s: &str) -> Result<(f32, f32), String> {
+    let (low, high) = s
+        .split_once('-')
+        .ok_or_else(|| format!("`{s}` is not an interval of the form LOW-HIGH"))?;
+    let (low, high) = (validate_fraction(low)?, validate_fraction(high)?);
+    if low < high {
+        Ok((low, high))
+    } else {
+        Err(format!(
+            "The lower quantile of `{s}` must be less than the upper"
+        ))
+    }
+}
+
+/// A value parser for the fraction of the input to sample, which must be greater than 0
+fn validate_sample_fraction(s: &str) -> Result<f32, String> {
+    match validate_fraction(s)? {
+        0.0 => Err(format!("Value `{s}` must be greater than 0.0")),
+        value => Ok(value),
+    }
+}
+
+/// Parse a seed, which is a number or any other string - see [`liblrge::seed`].
+fn parse_seed(s: &str) -> Result<u64, String> {
+    Ok(liblrge::seed::seed_from_str(s))
+}
+
+/// A value parser for memory sizes, with optional binary unit suffixes - e.g., 512M or 4G
+fn parse_memory(s: &str) -> Result<usize, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("`{s}` is not a valid memory size",))?;
+    let multiplier: u64 = match unit.to_ascii_uppercase().trim_end_matches(['B', 'I']) {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return Err(format!("`{unit}` is not a valid memory unit",)),
+    };
+    if number < 0.0 {
+        return Err(format!("Memory size `{s}` must be positive",));
+    }
+    Ok((number * multiplier as f64) as usize)
+}
+
+/// Parse a count - e.g., of reads - with optional metric unit suffixes - e.g., 5k, 10K, 1m, or 0.5g
+fn parse_count(s: &str) -> Result<usize, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let exponent = match unit.to_ascii_lowercase().as_str() {
+        "" => 0,
+        "k" => 3,
+        "m" => 6,
+        "g" => 9,
+        _ => {
+            return Err(format!(
+                "`{unit}` is not a valid suffix. Expected k, m, or g",
+            ))
+        }
+    };
+    // shift the decimal point by hand, as floating point would make e.g. 1.1k 1100.0000000000002
+    let (whole, frac) = number.split_once('.').unwrap_or((number, ""));
+    let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if whole.is_empty() && frac.is_empty() || !is_digits(whole) || !is_digits(frac) {
+        return Err(format!("`{s}` is not a valid number",));
+    }
+    let (frac, remainder) = frac.split_at(frac.len().min(exponent));
+    if remainder.bytes().any(|b| b != b'0') {
+        return Err(format!("`{s}` is not a whole number",));
+    }
+    format!("{whole}{frac:0<exponent$}")
+        .parse()
+        .map_err(|_| format!("`{s}` is too large",))
+}
+
+/// A value parser for percentages
+fn validate_percentage(s: &str) -> Result<f32, String> {
+    let value: f32 = s
+        .parse()
+        .map_err(|_| format!("`{s}` is not a valid number",))?;
+    if (0.0..=100.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(format!("Percentage `{s}` must be between 0 and 100",))
+    }
+}
+
+/// A value parser for the minimum depth of coverage
+fn validate_coverage(s: &str) -> Result<f32, String> {
+    let value: f32 = s
+        .parse()
+        .map_err(|_| format!("`{s}` is not a valid number",))?;
+    if value >= 0.0 {
+        Ok(value)
+    } else {
+        Err(format!("Coverage `{s}` must not be negative",))
+    }
+}
+
+/// A value parser for the maximum relative error
+fn validate_relative_error(s: &str) -> Result<f32, String> {
+    let value: f32 = s
+        .parse()
+        .map_err(|_| format!("`{s}` is not a valid number",))?;
+    if value >= 0.0 {
+        Ok(value)
+    } else {
+        Err(format!("Relative error `{s}` must not be negative",))
+    }
+}
+
+/// Parse a genome size, with optional metric unit suffixes - e.g., 4.4m, 4400k, or 4.4Mbp
+pub(crate) fn parse_genome_size(s: &str) -> Result<f32, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("`{s}` is not a valid genome size",))?;
+    let lower_unit = unit.to_ascii_lowercase();
+    let multiplier = match lower_unit.strip_suffix("bp").unwrap_or(&lower_unit) {
+        "" => 1e0,
+        "k" => 1e3,
+        "m" => 1e6,
+        "g" => 1e9,
+        "t" => 1e12,
+        _ => return Err(format!("`{unit}` is not a valid genome size unit",)),
+    };
+    if number < 0.0 {
+        return Err(format!("Genome size `{s}` must be positive",));
+    }
+    Ok((number * multiplier) as f32)
+}
+
+/// A value parser for a range of genome sizes - e.g., 4m:6m, 4m:, or :6m
+fn parse_size_range(s: &str) -> Result<SizeRange, String> {
+    let (min, max) = s
+        .split_once(':')
+        .ok_or_else(|| format!("`{s}` is not a valid range. Expected MIN:MAX - e.g., 4m:6m",))?;
+    let parse_bound = |b: &str| {
+        if b.trim().is_empty() {
+            Ok(None)
+        } else {
+            parse_genome_size(b).map(Some)
+        }
+    };
+    let range = SizeRange {
+        min: parse_bound(min)?,
+        max: parse_bound(max)?,
+    };
+    match range {
+        SizeRange {
+            min: None,
+            max: None,
+        } => Err(format!("Range `{s}` must have at least one bound",)),
+        SizeRange {
+            min: Some(min),
+            max: Some(max),
+        } if min > max => Err(format!(
+            "The minimum of range `{s}` is larger than the maximum",
+        )),
+        _ => Ok(range),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -160,6 +903,19 @@ mod tests {
         assert!(validate_quantile("1.0", 0.5, 1.0).is_err());
     }
 
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(parse_memory("1024"), Ok(1024));
+        assert_eq!(parse_memory("2K"), Ok(2048));
+        assert_eq!(parse_memory("512M"), Ok(512 * 1024 * 1024));
+        assert_eq!(parse_memory("4G"), Ok(4 * 1024 * 1024 * 1024));
+        assert_eq!(parse_memory("4GiB"), Ok(4 * 1024 * 1024 * 1024));
+        assert_eq!(parse_memory("1.5g"), Ok(1536 * 1024 * 1024));
+        assert!(parse_memory("4X").is_err());
+        assert!(parse_memory("abc").is_err());
+        assert!(parse_memory("-1G").is_err());
+    }
+
     #[test]
     fn cli_no_args() {
         let opts = Args::try_parse_from([BIN]);
@@ -174,32 +930,157 @@ mod tests {
     fn cli_with_input() {
         let opts = Args::try_parse_from([BIN, "Cargo.toml"]).unwrap();
 
-        assert_eq!(opts.input, PathBuf::from("Cargo.toml"));
+        assert_eq!(opts.input, Some(PathBuf::from("Cargo.toml")));
         assert_eq!(
-            opts.target_num_reads,
+            opts.opts.target_num_reads,
             Some(TARGET_NUM_READS.parse().unwrap())
         );
-        assert_eq!(opts.query_num_reads, Some(QUERY_NUM_READS.parse().unwrap()));
+        assert_eq!(
+            opts.opts.query_num_reads,
+            Some(QUERY_NUM_READS.parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn cli_use_min_ref() {
+        let parse = |argv: &[&str]| {
+            let mut args = vec![BIN, "Cargo.toml"];
+            args.extend_from_slice(argv);
+            Args::try_parse_from(args).unwrap().opts.use_min_ref
+        };
+        assert_eq!(parse(&[]), None);
+        assert_eq!(parse(&["--use-min-ref"]), Some(true));
+        assert_eq!(parse(&["--use-min-ref", "false"]), Some(false));
+    }
+
+    #[test]
+    fn cli_seed() {
+        let parse = |argv: &[&str]| {
+            let mut args = vec![BIN, "Cargo.toml"];
+            args.extend_from_slice(argv);
+            Args::try_parse_from(args).unwrap().opts.seed
+        };
+        assert_eq!(parse(&["-s", "42"]), Some(42));
+        assert_eq!(
+            parse(&["--seed", "sample1"]),
+            Some(liblrge::seed::seed_from_str("sample1"))
+        );
+    }
+
+    #[test]
+    fn cli_skip_bad_records() {
+        let parse = |argv: &[&str]| {
+            let mut args = vec![BIN, "Cargo.toml"];
+            args.extend_from_slice(argv);
+            Args::try_parse_from(args).unwrap().opts.max_bad_records
+        };
+        assert_eq!(parse(&[]), None);
+        assert_eq!(parse(&["--skip-bad-records"]), Some(100));
+        assert_eq!(parse(&["--skip-bad-records", "5"]), Some(5));
+    }
+
+    #[test]
+    fn cli_platform_aliases() {
+        let opts = Args::try_parse_from([BIN, "Cargo.toml"]).unwrap();
+        assert_eq!(opts.opts.platform, Platform::Nanopore);
+
+        for (name, expected) in [
+            ("pb", Platform::PacBio),
+            ("HiFi", Platform::PacBio),
+            ("clr", Platform::PacBio),
+            ("ont-r10", Platform::Nanopore),
+            ("nanopore-r9", Platform::Nanopore),
+            ("rna", Platform::Nanopore),
+            ("duplex", Platform::NanoporeDuplex),
+            ("ONT-duplex", Platform::NanoporeDuplex),
+        ] {
+            let opts = Args::try_parse_from([BIN, "Cargo.toml", "-P", name]).unwrap();
+            assert_eq!(opts.opts.platform, expected);
+        }
+
+        assert!(Args::try_parse_from([BIN, "Cargo.toml", "-P", "illumina"]).is_err());
     }
 
     #[test]
     fn cli_with_num_reads() {
         let opts = Args::try_parse_from([BIN, "Cargo.toml", "--num", "100"]).unwrap();
 
-        assert_eq!(opts.input, PathBuf::from("Cargo.toml"));
-        assert_eq!(opts.num_reads, Some(100));
-        assert_eq!(opts.target_num_reads, None);
-        assert_eq!(opts.query_num_reads, None);
+        assert_eq!(opts.input, Some(PathBuf::from("Cargo.toml")));
+        assert_eq!(opts.opts.num_reads, Some(100));
+        assert_eq!(opts.opts.target_num_reads, None);
+        assert_eq!(opts.opts.query_num_reads, None);
+    }
+
+    #[test]
+    fn cli_with_sketch() {
+        let opts = Args::try_parse_from([BIN, "Cargo.toml", "--sketch"]).unwrap();
+        assert!(opts.opts.sketch);
+        assert_eq!(opts.opts.num_reads, Some(10_000));
+        assert_eq!(opts.opts.target_num_reads, None);
+        assert_eq!(opts.opts.query_num_reads, None);
+
+        let opts = Args::try_parse_from([BIN, "Cargo.toml", "--sketch", "-n", "100"]).unwrap();
+        assert_eq!(opts.opts.num_reads, Some(100));
+
+        assert!(Args::try_parse_from([BIN, "Cargo.toml", "--sketch", "-T", "100"]).is_err());
+    }
+
+    #[test]
+    fn cli_with_strategy() {
+        let opts = Args::try_parse_from([BIN, "Cargo.toml", "--strategy", "auto"]).unwrap();
+        assert_eq!(opts.opts.strategy, Some(StrategyName::Auto));
+        let opts = Args::try_parse_from([BIN, "Cargo.toml", "--strategy", "two-set"]).unwrap();
+        assert_eq!(opts.opts.strategy, Some(StrategyName::TwoSet));
+
+        for conflict in [["-n", "100"], ["-T", "100"], ["--num-frac", "0.1"]] {
+            let mut args = vec![BIN, "Cargo.toml", "--strategy", "auto"];
+            args.extend(conflict);
+            assert!(Args::try_parse_from(args).is_err(), "{conflict:?}");
+        }
+        assert!(
+            Args::try_parse_from([BIN, "Cargo.toml", "--strategy", "ava", "--sketch"]).is_err()
+        );
+    }
+
+    #[test]
+    fn cli_with_fractions() {
+        let opts = Args::try_parse_from([BIN, "Cargo.toml", "--query-frac", "0.01"]).unwrap();
+        assert_eq!(opts.opts.query_frac, Some(0.01));
+        assert_eq!(opts.opts.target_num_reads, Some(10_000));
+
+        let opts = Args::try_parse_from([BIN, "Cargo.toml", "--num-frac", "0.5"]).unwrap();
+        assert_eq!(opts.opts.num_frac, Some(0.5));
+        assert_eq!(opts.opts.num_reads, None);
+        assert_eq!(opts.opts.target_num_reads, None);
+        assert_eq!(opts.opts.query_num_reads, None);
+
+        assert!(Args::try_parse_from([BIN, "Cargo.toml", "--num-frac", "0"]).is_err());
+        assert!(Args::try_parse_from([BIN, "Cargo.toml", "--num-frac", "1.5"]).is_err());
+        assert!(
+            Args::try_parse_from([BIN, "Cargo.toml", "--num-frac", "0.5", "-n", "10"]).is_err()
+        );
+        assert!(
+            Args::try_parse_from([BIN, "Cargo.toml", "--target-frac", "0.5", "-T", "10"]).is_err()
+        );
+        assert!(Args::try_parse_from([
+            BIN,
+            "Cargo.toml",
+            "--query-frac",
+            "0.5",
+            "--num-frac",
+            "0.5"
+        ])
+        .is_err());
     }
 
     #[test]
     fn cli_with_target_and_query_reads() {
         let opts =
             Args::try_parse_from([BIN, "Cargo.toml", "--target", "100", "--query", "200"]).unwrap();
-        assert_eq!(opts.input, PathBuf::from("Cargo.toml"));
-        assert_eq!(opts.num_reads, None);
-        assert_eq!(opts.target_num_reads, Some(100));
-        assert_eq!(opts.query_num_reads, Some(200));
+        assert_eq!(opts.input, Some(PathBuf::from("Cargo.toml")));
+        assert_eq!(opts.opts.num_reads, None);
+        assert_eq!(opts.opts.target_num_reads, Some(100));
+        assert_eq!(opts.opts.query_num_reads, Some(200));
     }
 
     #[test]
@@ -244,16 +1125,19 @@ mod tests {
     #[test]
     fn cli_with_target_reads_no_query_reads() {
         let opts = Args::try_parse_from([BIN, "Cargo.toml", "--target", "100"]).unwrap();
-        assert_eq!(opts.target_num_reads, Some(100));
-        assert_eq!(opts.query_num_reads, Some(QUERY_NUM_READS.parse().unwrap()));
+        assert_eq!(opts.opts.target_num_reads, Some(100));
+        assert_eq!(
+            opts.opts.query_num_reads,
+            Some(QUERY_NUM_READS.parse().unwrap())
+        );
     }
 
     #[test]
     fn cli_with_query_reads_no_target_reads() {
         let opts = Args::try_parse_from([BIN, "Cargo.toml", "--query", "100"]).unwrap();
-        assert_eq!(opts.query_num_reads, Some(100));
+        assert_eq!(opts.opts.query_num_reads, Some(100));
         assert_eq!(
-            opts.target_num_reads,
+            opts.opts.target_num_reads,
             Some(TARGET_NUM_READS.parse().unwrap())
         );
     }
@@ -261,25 +1145,25 @@ mod tests {
     #[test]
     fn cli_with_quiet() {
         let opts = Args::try_parse_from([BIN, "Cargo.toml", "-q"]).unwrap();
-        assert_eq!(opts.quiet, 1);
+        assert_eq!(opts.opts.quiet, 1);
     }
 
     #[test]
     fn cli_with_verbose() {
         let opts = Args::try_parse_from([BIN, "Cargo.toml", "-v"]).unwrap();
-        assert_eq!(opts.verbose, 1);
+        assert_eq!(opts.opts.verbose, 1);
     }
 
     #[test]
     fn cli_with_verbose_verbose() {
         let opts = Args::try_parse_from([BIN, "Cargo.toml", "-vv"]).unwrap();
-        assert_eq!(opts.verbose, 2);
+        assert_eq!(opts.opts.verbose, 2);
     }
 
     #[test]
     fn cli_with_verbose_verbose_verbose() {
         let opts = Args::try_parse_from([BIN, "Cargo.toml", "-vvv"]).unwrap();
-        assert_eq!(opts.verbose, 3);
+        assert_eq!(opts.opts.verbose, 3);
     }
 
     #[test]
@@ -291,4 +1175,165 @@ mod tests {
             .to_string()
             .contains("error: the argument '--quiet...' cannot be used with"));
     }
+
+    #[test]
+    fn cli_batch() {
+        let opts =
+            Args::try_parse_from([BIN, "batch", "Cargo.toml", "-j", "2", "-T", "100"]).unwrap();
+        let Some(Command::Batch(batch)) = opts.command else {
+            panic!("expected the batch subcommand");
+        };
+        assert_eq!(opts.input, None);
+        assert_eq!(batch.samples, PathBuf::from("Cargo.toml"));
+        assert_eq!(batch.jobs, 2);
+        assert_eq!(batch.opts.target_num_reads, Some(100));
+    }
+
+    #[test]
+    fn cli_batch_with_input() {
+        let opts = Args::try_parse_from([BIN, "Cargo.toml", "batch", "Cargo.toml"]);
+        assert!(opts.is_err());
+    }
+
+    #[test]
+    fn cli_reestimate() {
+        let opts = Args::try_parse_from([
+            BIN,
+            "reestimate",
+            "Cargo.toml",
+            "--q1",
+            "0.25",
+            "--q3",
+            "0.75",
+            "--inf",
+        ])
+        .unwrap();
+        let Some(Command::Reestimate(reestimate)) = opts.command else {
+            panic!("expected the reestimate subcommand");
+        };
+        assert_eq!(reestimate.path, PathBuf::from("Cargo.toml"));
+        assert_eq!(reestimate.lower_q, 0.25);
+        assert_eq!(reestimate.upper_q, 0.75);
+        assert!(reestimate.with_infinity);
+    }
+
+    #[test]
+    fn cli_man() {
+        let opts = Args::try_parse_from([BIN, "man", "-o", "lrge.1"]).unwrap();
+        let Some(Command::Man(man)) = opts.command else {
+            panic!("expected the man subcommand");
+        };
+        assert_eq!(man.output, "lrge.1");
+    }
+
+    #[test]
+    fn parse_genome_size_with_units() {
+        assert_eq!(parse_genome_size("4400000"), Ok(4_400_000.0));
+        assert_eq!(parse_genome_size("4.4m"), Ok(4_400_000.0));
+        assert_eq!(parse_genome_size("4.4Mbp"), Ok(4_400_000.0));
+        assert_eq!(parse_genome_size("120K"), Ok(120_000.0));
+        assert_eq!(parse_genome_size("3.1g"), Ok(3_100_000_000.0));
+        assert!(parse_genome_size("4x").is_err());
+        assert!(parse_genome_size("m").is_err());
+        assert!(parse_genome_size("-4m").is_err());
+    }
+
+    #[test]
+    fn parse_count_with_suffixes() {
+        assert_eq!(parse_count("25000"), Ok(25_000));
+        assert_eq!(parse_count("5k"), Ok(5_000));
+        assert_eq!(parse_count("10K"), Ok(10_000));
+        assert_eq!(parse_count("1m"), Ok(1_000_000));
+        assert_eq!(parse_count("1.5m"), Ok(1_500_000));
+        assert_eq!(parse_count("0.5g"), Ok(500_000_000));
+        assert_eq!(parse_count("1.1k"), Ok(1_100));
+        assert_eq!(parse_count(".5k"), Ok(500));
+        assert_eq!(parse_count("2.00"), Ok(2));
+        assert!(parse_count("1.5").is_err());
+        assert!(parse_count("1.0001k").is_err());
+        assert!(parse_count("5x").is_err());
+        assert!(parse_count("5kb").is_err());
+        assert!(parse_count("k").is_err());
+        assert!(parse_count("-5k").is_err());
+    }
+
+    #[test]
+    fn cli_counts_with_suffixes() {
+        let opts = Args::try_parse_from([BIN, "Cargo.toml", "-T", "5k", "-Q", "2.5K"]).unwrap();
+        assert_eq!(opts.opts.target_num_reads, Some(5_000));
+        assert_eq!(opts.opts.query_num_reads, Some(2_500));
+
+        let opts = Args::try_parse_from([BIN, "Cargo.toml", "-n", "1m"]).unwrap();
+        assert_eq!(opts.opts.num_reads, Some(1_000_000));
+
+        assert!(Args::try_parse_from([BIN, "Cargo.toml", "-n", "1.5"]).is_err());
+    }
+
+    #[test]
+    fn parse_size_range_bounds() {
+        assert_eq!(
+            parse_size_range("4m:6m"),
+            Ok(SizeRange {
+                min: Some(4e6),
+                max: Some(6e6)
+            })
+        );
+        assert_eq!(
+            parse_size_range(":6m"),
+            Ok(SizeRange {
+                min: None,
+                max: Some(6e6)
+            })
+        );
+        assert_eq!(
+            parse_size_range("4m:"),
+            Ok(SizeRange {
+                min: Some(4e6),
+                max: None
+            })
+        );
+        assert!(parse_size_range("4m").is_err());
+        assert!(parse_size_range(":").is_err());
+        assert!(parse_size_range("6m:4m").is_err());
+    }
+
+    #[test]
+    fn validate_percentage_bounds() {
+        assert_eq!(validate_percentage("20"), Ok(20.0));
+        assert!(validate_percentage("101").is_err());
+        assert!(validate_percentage("-1").is_err());
+    }
+
+    #[test]
+    fn cli_units() {
+        let opts =
+            Args::try_parse_from([BIN, "Cargo.toml", "-u", "mbp", "--sig-figs", "3"]).unwrap();
+        assert_eq!(opts.opts.units, Unit::Mbp);
+        assert_eq!(opts.opts.sig_figs, Some(3));
+
+        let opts = Args::try_parse_from([BIN, "Cargo.toml"]).unwrap();
+        assert_eq!(opts.opts.units, Unit::Bp);
+        assert_eq!(opts.opts.sig_figs, None);
+
+        assert!(Args::try_parse_from([BIN, "Cargo.toml", "--sig-figs", "0"]).is_err());
+    }
+
+    #[test]
+    fn cli_expected_size() {
+        let opts = Args::try_parse_from([
+            BIN,
+            "Cargo.toml",
+            "--expected-size",
+            "5.2m",
+            "--max-relative-error",
+            "0.1",
+        ])
+        .unwrap();
+        assert_eq!(opts.opts.expected_size, Some(5_200_000.0));
+        assert_eq!(opts.opts.max_relative_error, Some(0.1));
+
+        // a tolerance needs an expected size
+        assert!(Args::try_parse_from([BIN, "Cargo.toml", "--max-relative-error", "0.1"]).is_err());
+        assert!(validate_relative_error("-0.1").is_err());
+    }
 }
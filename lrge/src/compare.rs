@@ -0,0 +1,155 @@
+//! Estimate the genome size of two or more inputs with the same settings, and compare them.
+use crate::batch::format_value;
+use crate::build_strategy;
+use crate::cli::CompareArgs;
+use crate::qc;
+use crate::utils::format_estimate;
+use anyhow::{bail, Context, Result};
+use liblrge::batch::Batch;
+use liblrge::estimate::EstimateResult;
+use log::{error, info};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+/// The fraction of the union of two intervals that they share - 1 if they are the same, and 0 if
+/// they do not overlap.
+fn interval_overlap((a_lo, a_hi): (f32, f32), (b_lo, b_hi): (f32, f32)) -> f32 {
+    let shared = a_hi.min(b_hi) - a_lo.max(b_lo);
+    let union = a_hi.max(b_hi) - a_lo.min(b_lo);
+    if shared < 0.0 {
+        0.0
+    } else if union > 0.0 {
+        shared / union
+    } else {
+        // both intervals are the same single value
+        1.0
+    }
+}
+
+/// The interval between the lower and upper quantiles of an estimate, if both are finite.
+fn quantile_interval(result: &EstimateResult) -> Option<(f32, f32)> {
+    result
+        .lower
+        .zip(result.upper)
+        .filter(|(lo, hi)| lo.is_finite() && hi.is_finite())
+}
+
+/// Estimate the genome size of every input, and write a table of the results, with the overlap of
+/// each input's interquantile range with that of the first input. The overlap of every pair of
+/// inputs is logged.
+pub(crate) fn run(args: &CompareArgs, tmpdir: &Path) -> Result<ExitCode> {
+    let opts = &args.opts;
+    let mut output: Box<dyn Write> = if args.output == "-" {
+        Box::new(io::stdout())
+    } else {
+        Box::new(File::create(&args.output).context("Failed to create output file")?)
+    };
+
+    let strategies = args
+        .inputs
+        .iter()
+        .map(|input| build_strategy(opts, input, tmpdir))
+        .collect::<Result<Vec<_>>>()?;
+    let outcome = Batch::new()
+        .concurrency(args.jobs as usize)
+        .threads(opts.threads)
+        .tmpdir(tmpdir)
+        .keep_temp(opts.keep_temp)
+        .finite(!opts.with_infinity)
+        .quantiles(Some(opts.lower_q), Some(opts.upper_q))
+        .run(strategies)
+        .context("Failed to run comparison")?;
+
+    let mut results = Vec::with_capacity(args.inputs.len());
+    for (input, result) in args.inputs.iter().zip(outcome.results) {
+        match result {
+            Ok(r) => results.push(r),
+            Err(e) => bail!("Failed to estimate {}: {e}", input.display()),
+        }
+    }
+
+    let format = opts.output_format();
+    let reference = quantile_interval(&results[0]);
+    writeln!(
+        output,
+        "input\testimate\tlower\tupper\tno_mapping_count\tinput_num_reads\tiqr_overlap"
+    )?;
+    for (input, r) in args.inputs.iter().zip(&results) {
+        let overlap = reference
+            .zip(quantile_interval(r))
+            .map(|(a, b)| interval_overlap(a, b));
+        writeln!(
+            output,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            input.display(),
+            format_value(r.estimate, format),
+            format_value(r.lower, format),
+            format_value(r.upper, format),
+            r.no_mapping_count,
+            r.input_num_reads
+                .map_or_else(|| String::from("NA"), |n| n.to_string()),
+            overlap.map_or_else(|| String::from("NA"), |o| format!("{o:.3}"))
+        )?;
+    }
+    output.flush()?;
+
+    for (i, (a_input, a)) in args.inputs.iter().zip(&results).enumerate() {
+        for (b_input, b) in args.inputs.iter().zip(&results).skip(i + 1) {
+            let (a_name, b_name) = (a_input.display(), b_input.display());
+            match (quantile_interval(a), quantile_interval(b)) {
+                (Some(a_iqr), Some(b_iqr)) => {
+                    let overlap = interval_overlap(a_iqr, b_iqr);
+                    if overlap > 0.0 {
+                        info!(
+                            "The interquantile ranges of {a_name} and {b_name} overlap ({:.1}% of their union)",
+                            overlap * 100.0
+                        );
+                    } else {
+                        info!(
+                            "The interquantile ranges of {a_name} ({} - {}) and {b_name} ({} - {}) do not overlap",
+                            format_estimate(a_iqr.0),
+                            format_estimate(a_iqr.1),
+                            format_estimate(b_iqr.0),
+                            format_estimate(b_iqr.1)
+                        );
+                    }
+                }
+                _ => info!(
+                    "The interquantile ranges of {a_name} and {b_name} cannot be compared, as one is not finite"
+                ),
+            }
+        }
+    }
+
+    let mut qc_failures = Vec::new();
+    for (input, r) in args.inputs.iter().zip(&results) {
+        for failure in qc::check(r, opts) {
+            error!("{} failed quality control: {failure}", input.display());
+            qc_failures.push(failure);
+        }
+    }
+
+    info!("Done!");
+    // the exit code is that of the lowest failed check across all inputs
+    qc_failures.sort_by_key(|f| f.exit_code());
+    Ok(qc::exit_code(&qc_failures))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_overlap() {
+        assert_eq!(interval_overlap((0.0, 10.0), (0.0, 10.0)), 1.0);
+        assert_eq!(interval_overlap((0.0, 10.0), (5.0, 15.0)), 5.0 / 15.0);
+        assert_eq!(interval_overlap((5.0, 15.0), (0.0, 10.0)), 5.0 / 15.0);
+        assert_eq!(interval_overlap((0.0, 10.0), (2.0, 4.0)), 0.2);
+        assert_eq!(interval_overlap((0.0, 10.0), (11.0, 12.0)), 0.0);
+        // touching intervals share a single value, but none of their width
+        assert_eq!(interval_overlap((0.0, 10.0), (10.0, 12.0)), 0.0);
+        assert_eq!(interval_overlap((3.0, 3.0), (3.0, 3.0)), 1.0);
+    }
+}
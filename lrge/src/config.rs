@@ -0,0 +1,227 @@
+//! Default option values from a TOML config file.
+//!
+//! Any option given on the command line, or with an `LRGE_*` environment variable, takes precedence
+//! over the config file.
+use crate::cli::{validate_high_quantile, validate_low_quantile, Options};
+use anyhow::{bail, Context, Result};
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use liblrge::Platform;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The options that can be set in a config file.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Config {
+    /// Target number of reads (for the two-set strategy)
+    target: Option<usize>,
+    /// Query number of reads (for the two-set strategy)
+    query: Option<usize>,
+    /// Number of reads (for the all-vs-all strategy)
+    num: Option<usize>,
+    /// Number of threads
+    threads: Option<usize>,
+    /// Sequencing platform of the reads
+    #[serde(default, deserialize_with = "deserialize_platform")]
+    platform: Option<Platform>,
+    /// The lower quantile
+    q1: Option<f32>,
+    /// The upper quantile
+    q3: Option<f32>,
+    /// Temporary directory for storing intermediate files
+    temp: Option<PathBuf>,
+}
+
+/// Parse the platform from any of the names accepted on the command line.
+fn deserialize_platform<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<Platform>, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    let names: Vec<_> = Platform::ALL.iter().map(|p| p.to_string()).collect();
+    Platform::from_str(&name).map(Some).map_err(|_| {
+        D::Error::custom(format!(
+            "`{name}` is not a valid platform. Possible values: {}",
+            names.join(", ")
+        ))
+    })
+}
+
+/// The config file used when none is given with `--config`.
+fn default_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("lrge").join("config.toml"))
+}
+
+impl Config {
+    /// Parse a config file.
+    pub(crate) fn from_path(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        Self::from_toml(&text)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))
+    }
+
+    fn from_toml(text: &str) -> Result<Self> {
+        let config: Self = toml::from_str(text)?;
+
+        if config.num.is_some() && (config.target.is_some() || config.query.is_some()) {
+            bail!("`num` cannot be used with `target` or `query`");
+        }
+        if let Some(q1) = config.q1 {
+            validate_low_quantile(&q1.to_string()).map_err(anyhow::Error::msg)?;
+        }
+        if let Some(q3) = config.q3 {
+            validate_high_quantile(&q3.to_string()).map_err(anyhow::Error::msg)?;
+        }
+
+        Ok(config)
+    }
+
+    /// Load the config file given with `--config`, or the default config file if it exists.
+    ///
+    /// Returns the path of the config file along with the config, or `None` if there is no config file.
+    pub(crate) fn load(explicit: Option<&Path>) -> Result<Option<(PathBuf, Self)>> {
+        let path = match explicit {
+            Some(path) => path.to_path_buf(),
+            None => match default_path() {
+                Some(path) if path.is_file() => path,
+                _ => return Ok(None),
+            },
+        };
+        let config = Self::from_path(&path)?;
+        Ok(Some((path, config)))
+    }
+
+    /// Apply the config to any options that were not given on the command line or with an
+    /// environment variable.
+    pub(crate) fn apply(&self, opts: &mut Options, matches: &ArgMatches) {
+        let given = |id: &str| {
+            matches!(
+                matches.value_source(id),
+                Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+            )
+        };
+        let two_set_given = given("target_num_reads")
+            || given("query_num_reads")
+            || given("target_frac")
+            || given("query_frac");
+
+        if !given("num_reads") && !given("num_frac") {
+            if let (Some(num), false) = (self.num, two_set_given) {
+                opts.num_reads = Some(num);
+                opts.target_num_reads = None;
+                opts.query_num_reads = None;
+            }
+            if opts.num_reads.is_none() {
+                if let (Some(target), false) = (self.target, given("target_num_reads")) {
+                    opts.target_num_reads = Some(target);
+                }
+                if let (Some(query), false) = (self.query, given("query_num_reads")) {
+                    opts.query_num_reads = Some(query);
+                }
+            }
+        }
+        if let (Some(threads), false) = (self.threads, given("threads")) {
+            opts.threads = threads;
+        }
+        if let (Some(platform), false) = (self.platform, given("platform")) {
+            opts.platform = platform;
+        }
+        if let (Some(q1), false) = (self.q1, given("lower_q")) {
+            opts.lower_q = q1;
+        }
+        if let (Some(q3), false) = (self.q3, given("upper_q")) {
+            opts.upper_q = q3;
+        }
+        if let (Some(temp), false) = (&self.temp, given("temp_dir")) {
+            opts.temp_dir = Some(temp.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Args;
+    use clap::{CommandFactory, FromArgMatches};
+
+    const BIN: &str = env!("CARGO_BIN_NAME");
+
+    fn parse_and_apply(argv: &[&str], config: &Config) -> Options {
+        let matches = Args::command().try_get_matches_from(argv).unwrap();
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        config.apply(&mut args.opts, &matches);
+        args.opts
+    }
+
+    #[test]
+    fn test_from_toml() {
+        let config =
+            Config::from_toml("target = 100\nthreads = 4\nplatform = \"pb\"\nq1 = 0.2\n").unwrap();
+        assert_eq!(
+            config,
+            Config {
+                target: Some(100),
+                threads: Some(4),
+                platform: Some(Platform::PacBio),
+                q1: Some(0.2),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_toml_invalid() {
+        assert!(Config::from_toml("unknown = 1").is_err());
+        assert!(Config::from_toml("num = 1\ntarget = 2").is_err());
+        assert!(Config::from_toml("platform = \"illumina\"").is_err());
+        assert!(Config::from_toml("q1 = 0.6").is_err());
+        assert!(Config::from_toml("q3 = 0.4").is_err());
+    }
+
+    #[test]
+    fn test_apply_config_defaults() {
+        let config = Config::from_toml("target = 100\nquery = 50\nthreads = 4\nq3 = 0.9").unwrap();
+        let opts = parse_and_apply(&[BIN, "Cargo.toml"], &config);
+        assert_eq!(opts.target_num_reads, Some(100));
+        assert_eq!(opts.query_num_reads, Some(50));
+        assert_eq!(opts.threads, 4);
+        assert_eq!(opts.upper_q, 0.9);
+    }
+
+    #[test]
+    fn test_apply_cli_overrides_config() {
+        let config = Config::from_toml("target = 100\nthreads = 4").unwrap();
+        let opts = parse_and_apply(&[BIN, "Cargo.toml", "-T", "7", "-t", "2"], &config);
+        assert_eq!(opts.target_num_reads, Some(7));
+        assert_eq!(opts.threads, 2);
+    }
+
+    #[test]
+    fn test_apply_config_num() {
+        let config = Config::from_toml("num = 100").unwrap();
+        let opts = parse_and_apply(&[BIN, "Cargo.toml"], &config);
+        assert_eq!(opts.num_reads, Some(100));
+        assert_eq!(opts.target_num_reads, None);
+        assert_eq!(opts.query_num_reads, None);
+
+        // choosing the two-set strategy on the command line ignores the config's num
+        let opts = parse_and_apply(&[BIN, "Cargo.toml", "-Q", "10"], &config);
+        assert_eq!(opts.num_reads, None);
+        assert_eq!(opts.query_num_reads, Some(10));
+    }
+
+    #[test]
+    fn test_apply_cli_num_ignores_config_two_set() {
+        let config = Config::from_toml("target = 100").unwrap();
+        let opts = parse_and_apply(&[BIN, "Cargo.toml", "-n", "10"], &config);
+        assert_eq!(opts.num_reads, Some(10));
+        assert_eq!(opts.target_num_reads, None);
+    }
+}
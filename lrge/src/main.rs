@@ -1,16 +1,89 @@
-use crate::utils::{create_temp_dir, format_estimate};
+use crate::manifest::Manifest;
+use crate::parameters::Parameters;
+use crate::provenance::Provenance;
+use crate::utils::{
+    create_temp_dir, format_estimate, ram_temp_dir, write_versions_file, OutputFormat,
+};
 use anyhow::{bail, Context, Result};
-use clap::Parser;
-use liblrge::Estimate;
-use log::{debug, info, LevelFilter};
+use clap::{CommandFactory, FromArgMatches};
+use liblrge::adapter::AdapterScan;
+use liblrge::artifact::Artifact;
+use liblrge::estimate::{
+    calibrated_confidence, reads_without_overlaps, EstimateOutcome, EstimateResult, Median,
+    NoOverlapRead, PerReadEstimate, QuantileInterval, SavedEstimates,
+};
+use liblrge::gfa::OverlapGraph;
+use liblrge::islands::{island_estimate, DEFAULT_MIN_OVERLAP};
+use liblrge::overlap_stats::OverlapStats;
+use liblrge::poisson::POISSON_CONFIDENCE;
+use liblrge::replicon::{decompose, Decomposition, MIN_REPLICON_READS};
+use liblrge::stream::RunningEstimate;
+use liblrge::trim::QualityTrim;
+use liblrge::{AutoStrategy, Estimate, OverlapFormat, Strategy, StrategyBuilder, StrategyKind};
+use log::{debug, error, info, warn, LevelFilter};
 use std::fs::File;
 use std::io;
 use std::io::Write;
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Instant;
 
+mod batch;
+mod check;
 mod cli;
+mod compare;
+mod config;
+mod man;
+mod manifest;
+mod parameters;
+mod plot;
+mod provenance;
+mod qc;
+mod rarefaction;
+mod report;
 mod utils;
 
-fn setup_logging(quiet: u8, verbose: u8) {
+/// A logger for the given level, with the modules we never want logs from turned off.
+fn logger(level: LevelFilter) -> env_logger::Builder {
+    let mut log_builder = env_logger::Builder::new();
+    log_builder
+        .filter(None, level)
+        .filter_module("mio", LevelFilter::Off)
+        .filter_module("reqwest", LevelFilter::Off);
+    log_builder
+}
+
+/// Writes each log record to both stderr and the log file, each at its own level.
+struct TeeLogger {
+    stderr: env_logger::Logger,
+    file: env_logger::Logger,
+}
+
+impl log::Log for TeeLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.stderr.enabled(metadata) || self.file.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        // each logger only writes the records that match its own level
+        self.stderr.log(record);
+        self.file.log(record);
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+        self.file.flush();
+    }
+}
+
+/// Log to stderr at the level given by `quiet` and `verbose`, and, if there is a `log_file`, to
+/// that file at `log_file_level`.
+fn setup_logging(
+    quiet: u8,
+    verbose: u8,
+    log_file: Option<&Path>,
+    log_file_level: cli::LogLevel,
+) -> Result<()> {
     let sum = verbose as i8 - quiet as i8;
 
     let lvl = match sum {
@@ -21,21 +94,354 @@ fn setup_logging(quiet: u8, verbose: u8) {
         i if i < -2 => LevelFilter::Off,
         _ => LevelFilter::Info,
     };
-    let mut log_builder = env_logger::Builder::new();
-    log_builder
-        .filter(None, lvl)
-        .filter_module("mio", LevelFilter::Off)
-        .filter_module("reqwest", LevelFilter::Off);
-    log_builder.init();
+    let Some(path) = log_file else {
+        logger(lvl).init();
+        return Ok(());
+    };
+
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create log file {}", path.display()))?;
+    let file = logger(log_file_level.into())
+        .target(env_logger::Target::Pipe(Box::new(file)))
+        .write_style(env_logger::WriteStyle::Never)
+        .build();
+    let stderr = logger(lvl).build();
+    let max_level = stderr.filter().max(file.filter());
+    log::set_boxed_logger(Box::new(TeeLogger { stderr, file }))
+        .context("Failed to set up logging")?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+/// The logging options of the options shared by all ways of running an estimate.
+fn log_options(opts: &cli::Options) -> (u8, u8, Option<&Path>, cli::LogLevel) {
+    (
+        opts.quiet,
+        opts.verbose,
+        opts.log_file.as_deref(),
+        opts.log_file_level,
+    )
 }
 
-fn main() -> Result<()> {
-    let args = cli::Args::parse();
-    setup_logging(args.quiet, args.verbose);
+/// The number of times the running estimate is logged with `--stream`.
+const STREAM_UPDATES: usize = 20;
+
+/// Build the estimation strategy for `input` from the CLI options.
+fn build_strategy(opts: &cli::Options, input: &Path, tmpdir: &Path) -> Result<Strategy> {
+    let (builder, _) = strategy_builder(opts, input, tmpdir)?;
+    builder
+        .try_build()
+        .context("Invalid strategy configuration")
+}
+
+/// The kind of estimation strategy chosen by the CLI options. With `--strategy auto`, the kind is
+/// chosen by counting the reads in the input - see [`strategy_builder`].
+fn strategy_kind(opts: &cli::Options) -> StrategyKind {
+    if let Some(name) = opts.strategy {
+        match name {
+            cli::StrategyName::TwoSet => StrategyKind::default(),
+            cli::StrategyName::Ava => StrategyKind::ava(),
+            cli::StrategyName::Sketch => StrategyKind::sketch(),
+            cli::StrategyName::Auto => {
+                unreachable!("--strategy auto is chosen from the reads in the input")
+            }
+        }
+    } else if let (true, Some(num_reads)) = (opts.sketch, opts.num_reads) {
+        StrategyKind::Sketch { num_reads }
+    } else if let Some(num_reads) = opts.num_reads {
+        StrategyKind::Ava { num_reads }
+    } else if opts.num_frac.is_some() {
+        // the number of reads is resolved from the fraction
+        StrategyKind::ava()
+    } else if let (Some(target_num_reads), Some(query_num_reads)) =
+        (opts.target_num_reads, opts.query_num_reads)
+    {
+        StrategyKind::TwoSet {
+            target_num_reads,
+            query_num_reads,
+        }
+    } else {
+        unreachable!("No strategy could be determined. Please raise an issue at <https://github.com/mbhall88/lrge/issues>")
+    }
+}
+
+/// A description of a strategy and its number of reads - e.g., "all-vs-all strategy with 100 reads".
+/// If a fraction of the reads is used, it is given instead - e.g., "all-vs-all strategy with 0.01 of
+/// the reads".
+fn describe_strategy(opts: &cli::Options, kind: StrategyKind) -> String {
+    let reads = |num_reads: usize, fraction: Option<f32>, name: &str| match fraction {
+        Some(fraction) if name.is_empty() => format!("{fraction} of the reads"),
+        Some(fraction) => format!("{fraction} of the reads as {name} reads"),
+        None if name.is_empty() => format!("{num_reads} reads"),
+        None => format!("{num_reads} {name} reads"),
+    };
+    match kind {
+        StrategyKind::TwoSet {
+            target_num_reads,
+            query_num_reads,
+        } => format!(
+            "two-set strategy with {} and {}",
+            reads(target_num_reads, opts.target_frac, "target"),
+            reads(query_num_reads, opts.query_frac, "query")
+        ),
+        StrategyKind::Ava { num_reads } => format!(
+            "all-vs-all strategy with {}",
+            reads(num_reads, opts.num_frac, "")
+        ),
+        StrategyKind::Sketch { num_reads } => format!(
+            "sketch strategy with {}",
+            reads(num_reads, opts.num_frac, "")
+        ),
+    }
+}
+
+/// The `#`-commented lines that start the output with `--header`, recording how the estimate was
+/// made.
+fn output_header(opts: &cli::Options, input: &Path, kind: StrategyKind) -> String {
+    let command: Vec<String> = std::env::args().collect();
+    let seed = opts
+        .seed
+        .map_or_else(|| String::from("random"), |seed| seed.to_string());
+    format!(
+        "# lrge {} (liblrge {}, minimap2 {})\n\
+         # date: {}\n\
+         # input: {}\n\
+         # command: {}\n\
+         # strategy: {}\n\
+         # seed: {seed}\n\
+         # quantiles: {} {}\n",
+        env!("CARGO_PKG_VERSION"),
+        liblrge::VERSION,
+        liblrge::minimap2_version(),
+        jiff::Timestamp::now().strftime("%Y-%m-%dT%H:%M:%SZ"),
+        input.display(),
+        command.join(" "),
+        describe_strategy(opts, kind),
+        opts.lower_q,
+        opts.upper_q
+    )
+}
+
+/// Configure a builder for the estimation strategy for `input` from the CLI options. With
+/// `--strategy auto`, the reads in the input are counted to choose the strategy, and the choice is
+/// also returned.
+fn strategy_builder(
+    opts: &cli::Options,
+    input: &Path,
+    tmpdir: &Path,
+) -> Result<(StrategyBuilder, Option<AutoStrategy>)> {
+    let mut builder = StrategyBuilder::new(input)
+        .remove_internal(opts.filter_contained, opts.max_overhang_ratio)
+        .max_overhang_size(opts.max_overhang_size)
+        .max_divergence(opts.max_divergence)
+        .min_mapq(opts.min_mapq)
+        .min_overlap_len(opts.min_overlap_len)
+        .primary_only(opts.primary_only)
+        .length_weighted(opts.length_weighted)
+        .exclude_contained(opts.exclude_contained)
+        .exclude_duplicates(opts.exclude_duplicates)
+        .threads(opts.threads)
+        .tmpdir(tmpdir)
+        .seed(opts.seed)
+        .platform(opts.platform)
+        .overlap_format(opts.overlap_format)
+        .sort_overlaps(opts.sort_overlaps)
+        .max_memory(opts.max_memory)
+        .skip_bad_records(opts.max_bad_records)
+        .validate_records(opts.validate)
+        .cache_count(opts.cache_count);
+    if let Some(capacity) = opts.channel_capacity {
+        builder = builder.channel_capacity(capacity as usize);
+    }
+    if let Some(index_threads) = opts.index_threads {
+        builder = builder.index_threads(index_threads);
+    }
+    if let Some(use_min_ref) = opts.use_min_ref {
+        builder = builder.use_min_ref(use_min_ref);
+    }
+    if let Some(fraction) = opts.resample_no_overlap {
+        builder = builder.resample_no_mapping_fraction(fraction);
+    }
+    if let Some(fraction) = opts.target_frac {
+        builder = builder.target_fraction(fraction);
+    }
+    if let Some(fraction) = opts.query_frac {
+        builder = builder.query_fraction(fraction);
+    }
+    if let Some(fraction) = opts.num_frac {
+        builder = builder.read_fraction(fraction);
+    }
+    if let Some(allowlist) = &opts.allowlist {
+        builder = builder.allowlist(allowlist);
+    }
+    if let Some(blocklist) = &opts.blocklist {
+        builder = builder.blocklist(blocklist);
+    }
+    if let Some(min_quality) = opts.trim_quality {
+        builder = builder.trim_quality(Some(
+            QualityTrim::new(min_quality).window_size(opts.trim_window),
+        ));
+    }
+    if opts.remove_adapters {
+        builder = builder.remove_adapters(Some(AdapterScan::for_platform(opts.platform)));
+    }
+
+    let (kind, choice) = if opts.strategy == Some(cli::StrategyName::Auto) {
+        let choice = builder
+            .choose_strategy()
+            .context("Failed to count the reads to choose a strategy")?;
+        info!(
+            "Chose the {}, as {}",
+            describe_strategy(opts, choice.kind),
+            choice.reason
+        );
+        (choice.kind, Some(choice))
+    } else {
+        (strategy_kind(opts), None)
+    };
+    info!("Running {}", describe_strategy(opts, kind));
+
+    Ok((builder.strategy(kind), choice))
+}
+
+/// A callback that logs the running estimate as the per-read estimates are computed. The estimate
+/// is logged [`STREAM_UPDATES`] times over the course of the reads.
+fn log_running_estimate(
+    opts: &cli::Options,
+    kind: StrategyKind,
+) -> Result<impl FnMut(&PerReadEstimate) + Send + 'static> {
+    let mut running =
+        RunningEstimate::new(!opts.with_infinity, Some(opts.lower_q), Some(opts.upper_q))
+            .context("Invalid quantiles")?;
+    let num_reads = match (opts.strategy, kind) {
+        (
+            Some(_),
+            StrategyKind::TwoSet {
+                query_num_reads, ..
+            },
+        ) => query_num_reads,
+        (Some(_), StrategyKind::Ava { num_reads } | StrategyKind::Sketch { num_reads }) => {
+            num_reads
+        }
+        (None, _) => opts.num_reads.or(opts.query_num_reads).unwrap_or_default(),
+    };
+    let interval = (num_reads / STREAM_UPDATES).max(1) as u32;
+
+    Ok(move |est: &PerReadEstimate| {
+        running.push(est);
+        if running.num_reads() % interval == 0 {
+            let format = |v: Option<f32>| v.map_or_else(|| "NA".to_string(), format_estimate);
+            info!(
+                "Running estimate after {} reads: {} ({} - {})",
+                running.num_reads(),
+                format(running.estimate()),
+                format(running.lower()),
+                format(running.upper())
+            );
+        }
+    })
+}
+
+fn main() -> Result<ExitCode> {
+    let matches = cli::Args::command().get_matches();
+    let mut args = cli::Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let paf_flags = [
+        (args.gfa.is_some(), "--gfa"),
+        (args.replicons, "--replicons"),
+        (args.overlap_stats, "--overlap-stats"),
+        (args.islands, "--islands"),
+    ];
+    for (used, flag) in paf_flags {
+        if used && args.opts.sketch {
+            cli::Args::command()
+                .error(
+                    clap::error::ErrorKind::ArgumentConflict,
+                    format!("{flag} needs overlaps, which --sketch does not write"),
+                )
+                .exit();
+        }
+        if used && args.opts.overlap_format != OverlapFormat::Paf {
+            cli::Args::command()
+                .error(
+                    clap::error::ErrorKind::ArgumentConflict,
+                    format!(
+                        "{flag} requires the overlaps to be written as PAF (--overlap-format paf)"
+                    ),
+                )
+                .exit();
+        }
+    }
+    let (quiet, verbose, log_file, log_file_level) = match &args.command {
+        Some(cli::Command::Batch(batch_args)) => log_options(&batch_args.opts),
+        Some(cli::Command::Compare(compare_args)) => log_options(&compare_args.opts),
+        Some(cli::Command::Check(check_args)) => log_options(&check_args.opts),
+        Some(cli::Command::Reestimate(reestimate_args)) => (
+            reestimate_args.quiet,
+            reestimate_args.verbose,
+            reestimate_args.log_file.as_deref(),
+            reestimate_args.log_file_level,
+        ),
+        Some(cli::Command::Plot(plot_args)) => (
+            plot_args.quiet,
+            plot_args.verbose,
+            plot_args.log_file.as_deref(),
+            plot_args.log_file_level,
+        ),
+        Some(cli::Command::Man(_)) => (0, 0, None, cli::LogLevel::default()),
+        None => log_options(&args.opts),
+    };
+    setup_logging(quiet, verbose, log_file, log_file_level)?;
+
+    // options given on the command line take precedence over the config file
+    let config_target = match &mut args.command {
+        Some(cli::Command::Batch(batch_args)) => matches
+            .subcommand_matches("batch")
+            .map(|m| (&mut batch_args.opts, m)),
+        Some(cli::Command::Compare(compare_args)) => matches
+            .subcommand_matches("compare")
+            .map(|m| (&mut compare_args.opts, m)),
+        Some(cli::Command::Check(check_args)) => matches
+            .subcommand_matches("check")
+            .map(|m| (&mut check_args.opts, m)),
+        Some(cli::Command::Reestimate(_))
+        | Some(cli::Command::Plot(_))
+        | Some(cli::Command::Man(_)) => None,
+        None => Some((&mut args.opts, &matches)),
+    };
+    if let Some((opts, opts_matches)) = config_target {
+        if let Some((path, config)) = config::Config::load(opts.config.as_deref())? {
+            info!("Using default options from config file {}", path.display());
+            config.apply(opts, opts_matches);
+        }
+    }
     debug!("{:?}", args);
 
-    let tmpdir = create_temp_dir(args.temp_dir.as_ref(), args.keep_temp)?;
-    if args.keep_temp {
+    let opts = match &args.command {
+        Some(cli::Command::Reestimate(reestimate_args)) => {
+            return reestimate(reestimate_args).map(|_| ExitCode::SUCCESS)
+        }
+        Some(cli::Command::Plot(plot_args)) => return plot(plot_args).map(|_| ExitCode::SUCCESS),
+        Some(cli::Command::Man(man_args)) => return write_man(man_args).map(|_| ExitCode::SUCCESS),
+        Some(cli::Command::Check(check_args)) => return check::run(check_args),
+        Some(cli::Command::Batch(batch_args)) => &batch_args.opts,
+        Some(cli::Command::Compare(compare_args)) => &compare_args.opts,
+        None => &args.opts,
+    };
+
+    if let Some(path) = &opts.versions_out {
+        write_versions_file(path)?;
+        debug!("Versions written to {}", path.display());
+    }
+
+    let ram_dir = opts.temp_in_ram.then(ram_temp_dir).flatten();
+    if opts.temp_in_ram && ram_dir.is_none() {
+        warn!("No RAM-backed storage found for --temp-in-ram; falling back to the usual temporary directory");
+    }
+    let temp_dir = ram_dir.or_else(|| opts.temp_dir.clone());
+    // a dry run writes nothing to the temporary directory, so there is nothing to keep
+    let keep_temp = opts.keep_temp && !args.dry_run;
+    let tmpdir = create_temp_dir(temp_dir.as_ref(), keep_temp)?;
+    if keep_temp {
         info!(
             "Created temporary directory at {}",
             tmpdir.path().to_string_lossy()
@@ -47,77 +453,466 @@ fn main() -> Result<()> {
         );
     }
 
-    let mut output: Box<dyn Write> = if args.output == "-" {
-        Box::new(io::stdout())
-    } else {
-        Box::new(File::create(&args.output).context("Failed to create output file")?)
-    };
+    match &args.command {
+        Some(cli::Command::Batch(batch_args)) => batch::run(batch_args, tmpdir.path()),
+        Some(cli::Command::Compare(compare_args)) => compare::run(compare_args, tmpdir.path()),
+        _ => {
+            // clap guarantees the input is present when there is no subcommand
+            let input = args.input.as_deref().expect("INPUT is required");
+            run(&args, input, tmpdir.path())
+        }
+    }
+}
 
-    let mut strategy: Box<dyn Estimate> = if let Some(num) = args.num_reads {
-        info!("Running all-vs-all strategy with {} reads", num);
-        let builder = liblrge::ava::Builder::new()
-            .num_reads(num)
-            .remove_internal(args.filter_contained, args.max_overhang_ratio)
-            .threads(args.threads)
-            .tmpdir(tmpdir.path())
-            .seed(args.seed);
+/// Estimate the genome size of a single input file.
+///
+/// The exit code is not successful if any of the quality control checks fail.
+fn run(args: &cli::Args, input: &Path, tmpdir: &Path) -> Result<ExitCode> {
+    let started = Instant::now();
+    let opts = &args.opts;
+    let (mut builder, choice) = strategy_builder(opts, input, tmpdir)?;
+    if args.stream {
+        let kind = choice
+            .as_ref()
+            .map_or_else(|| strategy_kind(opts), |c| c.kind);
+        builder = builder.on_estimate(log_running_estimate(opts, kind)?);
+    }
+    let mut strategy = builder
+        .try_build()
+        .context("Invalid strategy configuration")?;
+    if args.islands && !matches!(strategy, Strategy::Ava(_)) {
+        bail!("--islands requires the all-vs-all strategy (-n), as the two-set strategy does not overlap the target reads with each other");
+    }
 
-        Box::new(builder.build(args.input))
-    } else if let (Some(target_num_reads), Some(query_num_reads)) =
-        (args.target_num_reads, args.query_num_reads)
-    {
+    if args.dry_run {
+        let output: Box<dyn Write> = if args.output == "-" {
+            Box::new(io::stdout())
+        } else {
+            Box::new(File::create(&args.output).context("Failed to create output file")?)
+        };
+        Parameters::new(&mut strategy, opts, input)?.write(output)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let median = Median::new(Some(opts.lower_q), Some(opts.upper_q))
+        .and_then(|median| median.with_quantiles(&requested_quantiles(opts)))
+        .context("Invalid quantiles")?;
+    let est_result = strategy
+        .estimate_with(!opts.with_infinity, &median)
+        .context("Failed to generate estimate")?;
+
+    let header = args
+        .header
+        .then(|| output_header(opts, input, strategy.kind()));
+    write_estimate(
+        &est_result,
+        (opts.lower_q, opts.upper_q),
+        opts.min_finite_fraction,
+        opts.output_format(),
+        &args.output,
+        header.as_deref(),
+    )?;
+
+    if let (Some(expected), Some(est)) = (opts.expected_size, est_result.estimate) {
+        info!(
+            "Relative error versus the expected size of {}: {:+.2}%",
+            format_estimate(expected),
+            qc::relative_error(est, expected) * 100.0
+        );
+    }
+
+    if let (Some(reads), Some(bases)) = (est_result.input_num_reads, est_result.input_num_bases) {
+        info!(
+            "Input contains {reads} reads and {bases} bases ({})",
+            format_estimate(bases as f32)
+        );
+    }
+    if let Some(skipped) = est_result.input_num_skipped.filter(|&n| n > 0) {
+        warn!("Skipped {skipped} malformed record(s) in the input");
+    }
+    if let Some(gc) = &est_result.gc_content {
+        let [p5, .., p95] = gc.quantiles;
         info!(
-            "Running two-set strategy with {} target reads and {} query reads",
-            target_num_reads, query_num_reads
+            "GC content of sampled reads: {:.1}% (5th-95th percentile of reads: {p5:.1}% - {p95:.1}%)",
+            gc.overall
+        );
+    }
+    // the ends of the intervals are logged with them
+    let quantiles: Vec<String> = est_result
+        .quantiles
+        .iter()
+        .filter(|(q, _)| opts.quantiles.contains(q))
+        .map(|&(q, value)| format!("q{q}: {}", format_estimate(value)))
+        .collect();
+    if !quantiles.is_empty() {
+        info!(
+            "Quantiles of the per-read estimates: {}",
+            quantiles.join(", ")
+        );
+    }
+    for &(lower_q, upper_q) in &opts.intervals {
+        if let Some(interval) = est_result.interval(lower_q, upper_q) {
+            info!(
+                "Estimate {}: {} - {}",
+                interval.label(),
+                format_estimate(interval.lower),
+                format_estimate(interval.upper)
+            );
+        }
+    }
+    if let Some(interval) = &est_result.poisson_interval {
+        info!(
+            "{:.0}% confidence interval from a Poisson model of the {} overlaps: {} - {}",
+            POISSON_CONFIDENCE * 100.0,
+            interval.num_overlaps,
+            format_estimate(interval.lower),
+            format_estimate(interval.upper)
+        );
+    }
+    if let Some(coverage) = qc::estimated_coverage(&est_result) {
+        info!("Estimated depth of coverage: {coverage:.1}x");
+    }
+
+    let failures = qc::check(&est_result, opts);
+    for failure in &failures {
+        error!("Quality control failed: {failure}");
+    }
+
+    if let Some(path) = &args.rarefaction {
+        let estimates = SavedEstimates::new(tmpdir)
+            .read()
+            .context("Failed to read per-read estimates")?;
+        let sizes = args
+            .rarefaction_sizes
+            .clone()
+            .unwrap_or_else(|| liblrge::rarefaction::default_sizes(estimates.len()));
+        let curve = liblrge::rarefaction::rarefaction(
+            &estimates,
+            &sizes,
+            !opts.with_infinity,
+            Some(opts.lower_q),
+            Some(opts.upper_q),
+            opts.seed,
+        )
+        .context("Failed to calculate rarefaction curve")?;
+        rarefaction::write(path, &curve, opts.output_format())?;
+        debug!("Rarefaction table written to {}", path.display());
+    }
+
+    if let Some(path) = &args.gfa {
+        let overlaps = strategy
+            .artifact_path(Artifact::Overlaps)
+            .expect("overlaps are written unless sketching");
+        let graph = OverlapGraph::from_paf(overlaps).context("Failed to read overlaps")?;
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create GFA file {}", path.display()))?;
+        graph
+            .write(io::BufWriter::new(file))
+            .context("Failed to write overlap graph")?;
+        debug!(
+            "Overlap graph with {} segments and {} links written to {}",
+            graph.num_segments(),
+            graph.num_links(),
+            path.display()
+        );
+    }
+
+    if let Some(path) = &args.no_overlap_reads {
+        let estimates = SavedEstimates::new(tmpdir)
+            .read()
+            .context("Failed to read per-read estimates")?;
+        let reads = strategy
+            .artifact_path(Artifact::Query)
+            .or_else(|| strategy.artifact_path(Artifact::Reads))
+            .expect("all strategies write the reads they estimate");
+        let no_overlap = reads_without_overlaps(&estimates, reads)
+            .context("Failed to find the reads without overlaps")?;
+        write_no_overlap_reads(path, &no_overlap)?;
+        debug!(
+            "{} read(s) without overlaps written to {}",
+            no_overlap.len(),
+            path.display()
         );
-        let builder = liblrge::twoset::Builder::new()
-            .target_num_reads(target_num_reads)
-            .query_num_reads(query_num_reads)
-            .remove_internal(args.filter_contained, args.max_overhang_ratio)
-            .use_min_ref(args.use_min_ref)
-            .threads(args.threads)
-            .tmpdir(tmpdir.path())
-            .seed(args.seed);
-
-        Box::new(builder.build(args.input))
+    }
+
+    let decomposition = if args.replicons {
+        let estimates = SavedEstimates::new(tmpdir)
+            .read()
+            .context("Failed to read per-read estimates")?;
+        let overlaps = strategy
+            .artifact_path(Artifact::Overlaps)
+            .expect("overlaps are written unless sketching");
+        let decomposition = decompose(overlaps, &estimates, MIN_REPLICON_READS)
+            .context("Failed to decompose the estimate into replicons")?;
+        log_replicons(&decomposition);
+        Some(decomposition)
     } else {
-        unreachable!("No strategy could be determined. Please raise an issue at <https://github.com/mbhall88/lrge/issues>")
+        None
     };
 
-    let est_result = strategy
+    let overlap_stats = if args.overlap_stats {
+        let overlaps = strategy
+            .artifact_path(Artifact::Overlaps)
+            .expect("overlaps are written unless sketching");
+        let stats = OverlapStats::from_paf(overlaps).context("Failed to summarise overlaps")?;
+        match &stats {
+            Some(stats) => log_overlap_stats(stats),
+            None => warn!("There are no overlaps to summarise"),
+        }
+        stats
+    } else {
+        None
+    };
+
+    let islands = if args.islands {
+        let overlaps = strategy
+            .artifact_path(Artifact::Overlaps)
+            .expect("overlaps are written unless sketching");
+        let reads = strategy
+            .artifact_path(Artifact::Reads)
+            .expect("the all-vs-all strategy writes the reads");
+        let islands = island_estimate(overlaps, reads, DEFAULT_MIN_OVERLAP)
+            .context("Failed to count the islands of the overlap graph")?;
+        match &islands {
+            Some(islands) => info!(
+                "Lander-Waterman estimate from {} islands of {} reads: {} (apparent coverage: {:.1}x)",
+                islands.num_islands,
+                islands.num_reads,
+                format_estimate(islands.estimate),
+                islands.coverage
+            ),
+            None => warn!("No reads overlap, so there is no Lander-Waterman estimate"),
+        }
+        islands
+    } else {
+        None
+    };
+
+    let provenance = if args.report.is_some() || opts.keep_temp {
+        Some(Provenance::new(&mut strategy, opts, input, started)?)
+    } else {
+        None
+    };
+    if let (true, Some(provenance)) = (opts.keep_temp, &provenance) {
+        Manifest::new(&strategy, provenance.clone(), opts.overlap_format)?
+            .write(strategy.tmpdir())?;
+        debug!("Manifest written to the temporary directory");
+    }
+
+    if let Some(path) = &args.report {
+        report::Report::new(&est_result, opts, &failures)
+            .with_replicons(decomposition.as_ref())
+            .with_overlap_stats(overlap_stats.as_ref())
+            .with_islands(islands.as_ref())
+            .with_provenance(provenance)
+            .with_strategy_choice(choice.as_ref())
+            .write(path, args.report_format)?;
+        debug!("Report written to {}", path.display());
+    }
+
+    info!("Done!");
+    Ok(qc::exit_code(&failures))
+}
+
+/// Write the IDs and lengths of the reads without overlaps to `path`.
+fn write_no_overlap_reads(path: &Path, reads: &[NoOverlapRead]) -> Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("Failed to create file {}", path.display()))?;
+    let mut writer = io::BufWriter::new(file);
+    writeln!(writer, "read_id\tlength")?;
+    for read in reads {
+        writeln!(
+            writer,
+            "{}\t{}",
+            String::from_utf8_lossy(&read.read_id),
+            read.len
+        )?;
+    }
+    writer
+        .flush()
+        .context("Failed to write the reads without overlaps")
+}
+
+/// Log the size of each putative replicon, and the fraction of the total size in the plasmids.
+fn log_replicons(decomposition: &Decomposition) {
+    let Some(chromosome) = decomposition.chromosome() else {
+        warn!(
+            "No replicons were found, as no component of the overlap graph has at least \
+             {MIN_REPLICON_READS} reads. Try sampling more reads"
+        );
+        return;
+    };
+    info!(
+        "Putative chromosome: {} ({:.2}% of reads)",
+        format_estimate(chromosome.size),
+        chromosome.read_fraction * 100.0
+    );
+    for (i, plasmid) in decomposition.plasmids().iter().enumerate() {
+        info!(
+            "Putative plasmid {}: {} ({:.2}% of reads)",
+            i + 1,
+            format_estimate(plasmid.size),
+            plasmid.read_fraction * 100.0
+        );
+    }
+    if let Some(fraction) = decomposition.plasmid_fraction() {
+        info!("Putative plasmid size fraction: {:.2}%", fraction * 100.0);
+    }
+}
+
+/// Log the median and 5th-95th percentiles of the overlap distributions.
+fn log_overlap_stats(stats: &OverlapStats) {
+    let [p5, _, median, _, p95] = stats.overlaps_per_read.quantiles;
+    info!("Overlaps per read: median {median:.1} (5th-95th percentile: {p5:.1} - {p95:.1})");
+    let [p5, _, median, _, p95] = stats.overlap_len.quantiles.map(format_estimate);
+    info!("Overlap length: median {median} (5th-95th percentile: {p5} - {p95})");
+    let [p5, _, median, _, p95] = stats.divergence.quantiles.map(|dv| dv * 100.0);
+    info!("Overlap divergence: median {median:.2}% (5th-95th percentile: {p5:.2}% - {p95:.2}%)");
+}
+
+/// Recalculate the estimate from the per-read estimates of a previous run.
+fn reestimate(args: &cli::ReestimateArgs) -> Result<()> {
+    match manifest::estimates_match(&args.path)? {
+        Some(true) => debug!("The per-read estimates match the checksum in the manifest"),
+        Some(false) => warn!(
+            "The per-read estimates in {} have changed since they were written, as their checksum does not match the one in the manifest",
+            args.path.display()
+        ),
+        None => {}
+    }
+    let mut saved = SavedEstimates::new(&args.path);
+    let est_result = saved
         .estimate(!args.with_infinity, Some(args.lower_q), Some(args.upper_q))
-        .context("Failed to generate estimate")?;
+        .context("Failed to recalculate estimate")?;
+
+    write_estimate(
+        &est_result,
+        (args.lower_q, args.upper_q),
+        args.min_finite_fraction,
+        args.output_format(),
+        &args.output,
+        None,
+    )?;
+
+    info!("Done!");
+    Ok(())
+}
+
+/// Plot the distribution of the per-read estimates of a previous run.
+fn plot(args: &cli::PlotArgs) -> Result<()> {
+    let mut saved = SavedEstimates::new(&args.path);
+    let estimates = saved.read().context("Failed to read per-read estimates")?;
+    let est_result = saved
+        .estimate(!args.with_infinity, Some(args.lower_q), Some(args.upper_q))
+        .context("Failed to calculate estimate")?;
+    let estimates: Vec<f32> = estimates.iter().map(|e| e.estimate).collect();
+
+    plot::Plot {
+        estimates: &estimates,
+        result: &est_result,
+        lower_q: args.lower_q,
+        upper_q: args.upper_q,
+        bins: args.bins as usize,
+    }
+    .write(&args.output)?;
+    debug!("Plot written to {}", args.output.display());
+
+    info!("Done!");
+    Ok(())
+}
+
+/// Write the man page to the output file.
+fn write_man(args: &cli::ManArgs) -> Result<()> {
+    let mut output: Box<dyn Write> = if args.output == "-" {
+        Box::new(io::stdout())
+    } else {
+        Box::new(File::create(&args.output).context("Failed to create output file")?)
+    };
+    man::render(&mut output).context("Failed to write man page")
+}
 
-    let estimate = est_result.estimate;
-    let low_q = est_result.lower;
-    let upper_q = est_result.upper;
+/// The quantiles of the per-read estimates to report besides the median and its bounds - those
+/// given with `--quantiles`, followed by the ends of the `--intervals`.
+fn requested_quantiles(opts: &cli::Options) -> Vec<f32> {
+    let mut quantiles = opts.quantiles.clone();
+    for &(lower_q, upper_q) in &opts.intervals {
+        for q in [lower_q, upper_q] {
+            if !quantiles.contains(&q) {
+                quantiles.push(q);
+            }
+        }
+    }
+    quantiles
+}
 
-    match estimate {
-        Some(est) => {
+/// Log the estimate and its quantiles, and write the estimate to `output`. Fails with an explanation
+/// if too few reads overlapped for the estimate to be meaningful. The quantiles are labelled with
+/// their calibrated confidence, if they have one.
+fn write_estimate(
+    est_result: &EstimateResult,
+    (lower_q, upper_q): (f32, f32),
+    min_finite_fraction: f32,
+    format: OutputFormat,
+    output: &str,
+    header: Option<&str>,
+) -> Result<()> {
+    match est_result.outcome(min_finite_fraction) {
+        EstimateOutcome::Estimated(est) => {
             let formatted_est = format_estimate(est);
             let mut msg = format!("Estimated genome size: {formatted_est}");
-            if let (Some(low), Some(high)) = (low_q, upper_q) {
-                let formatted_low = format_estimate(low);
-                let formatted_high = format_estimate(high);
-                msg.push_str(&format!(" (IQR: {formatted_low} - {formatted_high})"));
+            if let (Some(lower), Some(upper)) = (est_result.lower, est_result.upper) {
+                let interval = QuantileInterval {
+                    lower_quantile: lower_q,
+                    upper_quantile: upper_q,
+                    lower,
+                    upper,
+                    confidence: calibrated_confidence(lower_q, upper_q),
+                };
+                msg.push_str(&format!(
+                    " ({}: {} - {})",
+                    interval.label(),
+                    format_estimate(lower),
+                    format_estimate(upper)
+                ));
             }
             info!("{}", msg);
 
-            if args.precise {
-                writeln!(output, "{est}")?;
+            // the output is only created once there is an estimate to write, so a failed run does
+            // not leave an empty file behind
+            let mut output: Box<dyn Write> = if output == "-" {
+                Box::new(io::stdout())
             } else {
-                writeln!(output, "{est:.0}")?;
+                Box::new(File::create(output).context("Failed to create output file")?)
+            };
+            if let Some(header) = header {
+                write!(output, "{header}")?;
             }
+            writeln!(output, "{}", format.format(est))?;
         }
-        None => {
-            if args.with_infinity {
-                bail!("No estimates were generated")
+        EstimateOutcome::InsufficientOverlap { .. } if est_result.num_reads == 0 => {
+            bail!("No estimates were generated")
+        }
+        EstimateOutcome::InsufficientOverlap { finite_fraction } => {
+            let summary = if est_result.num_finite == 0 {
+                format!(
+                    "No finite estimates were generated: none of the {} reads overlapped another read",
+                    est_result.num_reads
+                )
             } else {
-                bail!("No finite estimates were generated")
-            }
+                format!(
+                    "Only {} of the {} reads ({:.2}%) overlapped another read, which is too few \
+                     for a meaningful estimate (see --min-finite-fraction)",
+                    est_result.num_finite,
+                    est_result.num_reads,
+                    finite_fraction * 100.0
+                )
+            };
+            bail!(
+                "{summary}. The coverage is likely too low, or the reads too short or error-prone \
+                 to overlap - try sampling more reads (-n, or -T and -Q)"
+            )
         }
     }
 
-    info!("Done!");
     Ok(())
 }
@@ -0,0 +1,81 @@
+//! Render the man page for lrge from its command-line definition.
+use crate::cli::Args;
+use clap::{Command, CommandFactory};
+use std::io::Write;
+
+/// The exit codes of lrge, in groff.
+const EXIT_STATUS: &str = r#".SH "EXIT STATUS"
+.TP
+\fB0\fR
+The genome size was estimated successfully.
+.TP
+\fB1\fR
+An error occurred - e.g., the input could not be read, no estimates were generated, or a sample in a batch failed.
+.TP
+\fB2\fR
+The command-line arguments are invalid.
+.TP
+\fB3\fR
+More than \fB\-\-fail\-if\-no\-overlap\-pct\fR percent of reads did not overlap any other reads. The estimate is still written.
+.TP
+\fB4\fR
+The estimate is outside the range given to \fB\-\-fail\-if\-estimate\-outside\fR. The estimate is still written.
+.TP
+\fB5\fR
+The relative error of the estimate versus \fB\-\-expected\-size\fR is larger than \fB\-\-max\-relative\-error\fR. The estimate is still written.
+.TP
+\fB6\fR
+The estimated depth of coverage (the number of input bases divided by the estimate) is below \fB\-\-min\-coverage\fR. The estimate is still written.
+.PP
+If several quality control checks fail, the lowest of their exit codes is used. In \fBbatch\fR mode, a failed sample takes precedence over quality control checks.
+"#;
+
+/// Show the help of options that are hidden from `-h`, as the man page is the complete reference.
+fn show_hidden_help(cmd: Command) -> Command {
+    cmd.mut_args(|arg| {
+        if arg.is_hide_short_help_set() {
+            arg.hide_short_help(false)
+        } else {
+            arg
+        }
+    })
+    .mut_subcommands(show_hidden_help)
+}
+
+/// Render the man page for lrge into `w`.
+pub(crate) fn render(w: &mut dyn Write) -> std::io::Result<()> {
+    let cmd = show_hidden_help(Args::command());
+    let man = clap_mangen::Man::new(cmd.clone());
+
+    man.render_title(w)?;
+    man.render_name_section(w)?;
+    man.render_synopsis_section(w)?;
+    man.render_description_section(w)?;
+    man.render_options_section(w)?;
+    man.render_subcommands_section(w)?;
+    w.write_all(EXIT_STATUS.as_bytes())?;
+    man.render_version_section(w)?;
+    if cmd.get_author().is_some() {
+        man.render_authors_section(w)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut buf = Vec::new();
+        render(&mut buf).unwrap();
+        let page = String::from_utf8(buf).unwrap();
+
+        assert!(page.starts_with(".ie \\n(.g .ds Aq \\(aq"));
+        assert!(page.contains(".SH \"EXIT STATUS\""));
+        // options hidden from -h are documented
+        assert!(page.contains("channel\\-capacity"));
+        assert!(page.contains("Number of reads buffered between the reader and mapping threads"));
+        assert!(page.contains("reestimate"));
+    }
+}
@@ -0,0 +1,195 @@
+//! A manifest of the files in a kept temporary directory (`--keep-temp`), so that the directory
+//! documents itself - what each file is, how it was produced, and its checksum - along with the
+//! provenance of the estimate. `lrge reestimate` uses it to check that the per-read estimates have
+//! not changed since they were written.
+use crate::provenance::Provenance;
+use crate::utils::sha256;
+use anyhow::{Context, Result};
+use liblrge::artifact::Artifact;
+use liblrge::estimate::ESTIMATES_FILENAME;
+use liblrge::{OverlapFormat, Strategy};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// The name of the manifest file in the temporary directory.
+pub(crate) const MANIFEST_FILENAME: &str = "manifest.json";
+
+/// The manifest of a temporary directory.
+#[derive(Debug, Serialize)]
+pub(crate) struct Manifest {
+    /// The versions, parameters, input, host, and time the estimate was made with
+    pub provenance: Provenance,
+    /// The files written during the estimate
+    pub artifacts: Vec<ManifestEntry>,
+}
+
+/// A file written during the estimate.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub(crate) struct ManifestEntry {
+    /// The path of the file, relative to the temporary directory if it is inside it
+    pub path: PathBuf,
+    /// What the file is
+    pub description: String,
+    /// How the file was produced
+    pub produced_by: String,
+    /// The size of the file, in bytes
+    pub size: u64,
+    /// The SHA-256 checksum of the file
+    pub sha256: String,
+}
+
+impl ManifestEntry {
+    /// Describe the file at `path`, which must exist.
+    fn new(path: &Path, tmpdir: &Path, description: String, produced_by: String) -> Result<Self> {
+        Ok(Self {
+            path: path.strip_prefix(tmpdir).unwrap_or(path).to_path_buf(),
+            description,
+            produced_by,
+            size: std::fs::metadata(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?
+                .len(),
+            sha256: sha256(&[path])?,
+        })
+    }
+}
+
+impl Manifest {
+    /// The manifest of the files `strategy` wrote to its temporary directory, and the per-read
+    /// estimates. Files that were not written, or were removed, are left out.
+    pub(crate) fn new(
+        strategy: &Strategy,
+        provenance: Provenance,
+        overlap_format: OverlapFormat,
+    ) -> Result<Self> {
+        let tmpdir = strategy.tmpdir();
+        let params = &provenance.parameters;
+        let sampled_by = format!(
+            "Randomly sampled from {} (seed: {})",
+            params.input.display(),
+            params
+                .seed
+                .map_or_else(|| String::from("random"), |seed| seed.to_string())
+        );
+        let overlapped_by = match params.minimap2_preset {
+            Some(preset) => format!("minimap2 {} -x {preset}", provenance.minimap2_version),
+            None => String::from("Sketches of the reads' k-mers"),
+        };
+
+        let mut artifacts = Vec::new();
+        for artifact in [
+            Artifact::Target,
+            Artifact::Query,
+            Artifact::Reads,
+            Artifact::Overlaps,
+        ] {
+            let Some(path) = strategy.artifact_path(artifact).filter(|p| p.is_file()) else {
+                continue;
+            };
+            let (description, produced_by) = match artifact {
+                Artifact::Overlaps => (
+                    format!(
+                        "The overlaps between the reads, in {} format",
+                        overlap_format.to_string().to_uppercase()
+                    ),
+                    overlapped_by.clone(),
+                ),
+                _ => (
+                    format!("The sampled {artifact}, in FASTA format"),
+                    sampled_by.clone(),
+                ),
+            };
+            artifacts.push(ManifestEntry::new(&path, tmpdir, description, produced_by)?);
+        }
+
+        let estimates = tmpdir.join(ESTIMATES_FILENAME);
+        if estimates.is_file() {
+            artifacts.push(ManifestEntry::new(
+                &estimates,
+                tmpdir,
+                String::from("The per-read genome size estimates, as a tab-delimited table"),
+                format!(
+                    "lrge {} {} strategy, from the overlaps of each read",
+                    provenance.lrge_version, params.strategy
+                ),
+            )?);
+        }
+
+        Ok(Self {
+            provenance,
+            artifacts,
+        })
+    }
+
+    /// Write the manifest to [`MANIFEST_FILENAME`] in `tmpdir`.
+    pub(crate) fn write(&self, tmpdir: &Path) -> Result<()> {
+        let path = tmpdir.join(MANIFEST_FILENAME);
+        let file = File::create(&path)
+            .with_context(|| format!("Failed to create manifest {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        writer.flush().context("Failed to write manifest")
+    }
+}
+
+/// The files listed in a manifest that has been written.
+#[derive(Debug, Deserialize)]
+struct SavedManifest {
+    artifacts: Vec<ManifestEntry>,
+}
+
+/// Whether the per-read estimates in the temporary directory `path` have the checksum recorded in
+/// its manifest. `None` if `path` is not a directory with a manifest listing the estimates - e.g.,
+/// it is the estimates file itself.
+pub(crate) fn estimates_match(path: &Path) -> Result<Option<bool>> {
+    let manifest_path = path.join(MANIFEST_FILENAME);
+    if !manifest_path.is_file() {
+        return Ok(None);
+    }
+    let file = File::open(&manifest_path)
+        .with_context(|| format!("Failed to open manifest {}", manifest_path.display()))?;
+    let manifest: SavedManifest = serde_json::from_reader(std::io::BufReader::new(file))
+        .with_context(|| format!("Failed to parse manifest {}", manifest_path.display()))?;
+    let Some(entry) = manifest
+        .artifacts
+        .iter()
+        .find(|entry| entry.path == Path::new(ESTIMATES_FILENAME))
+    else {
+        return Ok(None);
+    };
+    let checksum = sha256(&[path.join(ESTIMATES_FILENAME)])?;
+    Ok(Some(checksum == entry.sha256))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimates_match() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(estimates_match(dir.path()).unwrap(), None);
+
+        let estimates = dir.path().join(ESTIMATES_FILENAME);
+        std::fs::write(&estimates, "read_id\testimate\tmapped\nr1\t5000000\ttrue\n").unwrap();
+        let entry = ManifestEntry::new(
+            &estimates,
+            dir.path(),
+            String::from("estimates"),
+            String::from("a test"),
+        )
+        .unwrap();
+        assert_eq!(entry.path, Path::new(ESTIMATES_FILENAME));
+        assert_eq!(entry.size, 40);
+        let manifest = serde_json::json!({ "artifacts": [entry] });
+        std::fs::write(dir.path().join(MANIFEST_FILENAME), manifest.to_string()).unwrap();
+        assert_eq!(estimates_match(dir.path()).unwrap(), Some(true));
+
+        std::fs::write(&estimates, "read_id\testimate\tmapped\nr1\t6000000\ttrue\n").unwrap();
+        assert_eq!(estimates_match(dir.path()).unwrap(), Some(false));
+        // the estimates file itself has no manifest
+        assert_eq!(estimates_match(&estimates).unwrap(), None);
+    }
+}
@@ -0,0 +1,80 @@
+//! The resolved parameters of an estimate - printed by `--dry-run` instead of running it, and
+//! recorded in the provenance of the report.
+use crate::cli::Options;
+use anyhow::{Context, Result};
+use liblrge::{Strategy, StrategyKind};
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// The resolved parameters of an estimate.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Parameters {
+    /// The input file
+    pub input: PathBuf,
+    /// The strategy - two-set, all-vs-all, or sketch
+    pub strategy: &'static str,
+    /// The number of target reads, for the two-set strategy
+    pub target_num_reads: Option<usize>,
+    /// The number of query reads, for the two-set strategy
+    pub query_num_reads: Option<usize>,
+    /// The number of reads, for the all-vs-all and sketch strategies
+    pub num_reads: Option<usize>,
+    /// The sequencing platform
+    pub platform: String,
+    /// The minimap2 preset the reads are overlapped with. The sketch strategy does not use minimap2
+    pub minimap2_preset: Option<&'static str>,
+    /// The number of threads the reads are overlapped with
+    pub threads: usize,
+    /// The directory the intermediate files are written to
+    pub tmpdir: PathBuf,
+    /// Whether the intermediate files are kept
+    pub keep_temp: bool,
+    /// The seed for sampling the reads, if there is one
+    pub seed: Option<u64>,
+}
+
+impl Parameters {
+    /// Resolve the parameters of `strategy`, counting the input reads if a fraction of them is used.
+    pub(crate) fn new(strategy: &mut Strategy, opts: &Options, input: &Path) -> Result<Self> {
+        strategy
+            .resolve_fractions()
+            .context("Failed to count the reads in the input")?;
+        let (name, target_num_reads, query_num_reads, num_reads) = match strategy.kind() {
+            StrategyKind::TwoSet {
+                target_num_reads,
+                query_num_reads,
+            } => (
+                "two-set",
+                Some(target_num_reads),
+                Some(query_num_reads),
+                None,
+            ),
+            StrategyKind::Ava { num_reads } => ("all-vs-all", None, None, Some(num_reads)),
+            StrategyKind::Sketch { num_reads } => ("sketch", None, None, Some(num_reads)),
+        };
+        let minimap2_preset =
+            (!matches!(strategy, Strategy::Sketch(_))).then(|| opts.platform.minimap2_preset());
+
+        Ok(Self {
+            input: input.to_path_buf(),
+            strategy: name,
+            target_num_reads,
+            query_num_reads,
+            num_reads,
+            platform: opts.platform.to_string(),
+            minimap2_preset,
+            threads: strategy.threads(),
+            tmpdir: strategy.tmpdir().to_path_buf(),
+            keep_temp: opts.keep_temp,
+            seed: opts.seed,
+        })
+    }
+
+    /// Write the parameters to `writer` as JSON.
+    pub(crate) fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        writer.flush().context("Failed to write the parameters")
+    }
+}
@@ -0,0 +1,310 @@
+//! A plot of the distribution of the per-read estimates, as an SVG image or an HTML page.
+use crate::utils::format_estimate;
+use anyhow::{Context, Result};
+use liblrge::estimate::EstimateResult;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const WIDTH: f32 = 800.0;
+const HEIGHT: f32 = 450.0;
+const MARGIN_LEFT: f32 = 60.0;
+const MARGIN_RIGHT: f32 = 20.0;
+const MARGIN_TOP: f32 = 50.0;
+const MARGIN_BOTTOM: f32 = 50.0;
+
+/// The estimates to plot, and the summary of them to mark.
+pub(crate) struct Plot<'a> {
+    /// The per-read estimates. Infinite (and non-positive) estimates are counted, but not plotted
+    pub estimates: &'a [f32],
+    /// The estimate and its quantiles, which are marked on the plot
+    pub result: &'a EstimateResult,
+    /// The lower quantile used
+    pub lower_q: f32,
+    /// The upper quantile used
+    pub upper_q: f32,
+    /// The number of histogram bins
+    pub bins: usize,
+}
+
+impl Plot<'_> {
+    /// Render the plot as an SVG image - a histogram of the estimates, on a log scale, with the
+    /// estimate and its quantiles marked.
+    pub(crate) fn svg(&self) -> String {
+        let finite: Vec<f32> = self
+            .estimates
+            .iter()
+            .copied()
+            .filter(|e| e.is_finite() && *e > 0.0)
+            .collect();
+        let num_other = self.estimates.len() - finite.len();
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" font-family="sans-serif" font-size="12">"#
+        );
+        let _ = writeln!(
+            svg,
+            r#"<rect width="{WIDTH}" height="{HEIGHT}" fill="white"/>"#
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="20" text-anchor="middle" font-size="16">Per-read estimates ({} finite, {} infinite)</text>"#,
+            WIDTH / 2.0,
+            finite.len(),
+            num_other
+        );
+
+        if finite.is_empty() {
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}" text-anchor="middle">No finite estimates to plot</text>"#,
+                WIDTH / 2.0,
+                HEIGHT / 2.0
+            );
+            svg.push_str("</svg>\n");
+            return svg;
+        }
+
+        let logs: Vec<f32> = finite.iter().map(|e| e.log10()).collect();
+        let mut lo = logs.iter().copied().fold(f32::INFINITY, f32::min);
+        let mut hi = logs.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        for marker in [self.result.lower, self.result.estimate, self.result.upper]
+            .into_iter()
+            .flatten()
+            .filter(|e| e.is_finite() && *e > 0.0)
+        {
+            lo = lo.min(marker.log10());
+            hi = hi.max(marker.log10());
+        }
+        if hi <= lo {
+            lo -= 0.5;
+            hi += 0.5;
+        }
+
+        let bins = self.bins.max(1);
+        let width = (hi - lo) / bins as f32;
+        let mut counts = vec![0usize; bins];
+        for log in &logs {
+            let bin = ((log - lo) / width) as usize;
+            counts[bin.min(bins - 1)] += 1;
+        }
+        let max_count = counts.iter().copied().max().unwrap_or(1).max(1);
+
+        let plot_width = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+        let plot_height = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+        let bottom = HEIGHT - MARGIN_BOTTOM;
+        let x = |log: f32| MARGIN_LEFT + (log - lo) / (hi - lo) * plot_width;
+        let y = |count: usize| bottom - count as f32 / max_count as f32 * plot_height;
+
+        // bars
+        let bar_width = plot_width / bins as f32;
+        for (i, &count) in counts.iter().enumerate() {
+            let left = lo + width * i as f32;
+            let _ = writeln!(
+                svg,
+                r##"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="#9ecae1" stroke="white"><title>{} - {}: {}</title></rect>"##,
+                x(left),
+                y(count),
+                bar_width,
+                bottom - y(count),
+                format_estimate(10f32.powf(left)),
+                format_estimate(10f32.powf(left + width)),
+                count
+            );
+        }
+
+        // axes, with a tick at each power of 10 on the x-axis
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{MARGIN_LEFT}" y1="{bottom}" x2="{}" y2="{bottom}" stroke="black"/>"#,
+            WIDTH - MARGIN_RIGHT
+        );
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{MARGIN_LEFT}" y1="{MARGIN_TOP}" x2="{MARGIN_LEFT}" y2="{bottom}" stroke="black"/>"#
+        );
+        for power in (lo.ceil() as i32)..=(hi.floor() as i32) {
+            let tick = x(power as f32);
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{tick:.2}" y1="{bottom}" x2="{tick:.2}" y2="{}" stroke="black"/><text x="{tick:.2}" y="{}" text-anchor="middle">{}</text>"#,
+                bottom + 5.0,
+                bottom + 18.0,
+                format_estimate(10f32.powi(power))
+            );
+        }
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="middle">Per-read estimate (log scale)</text>"#,
+            MARGIN_LEFT + plot_width / 2.0,
+            HEIGHT - 10.0
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="{MARGIN_LEFT}" y="{}" text-anchor="end" dx="-5">{max_count}</text><text x="{MARGIN_LEFT}" y="{bottom}" text-anchor="end" dx="-5">0</text>"#,
+            MARGIN_TOP + 4.0
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text transform="translate(15 {}) rotate(-90)" text-anchor="middle">Reads</text>"#,
+            MARGIN_TOP + plot_height / 2.0
+        );
+
+        // the estimate and its quantiles
+        let markers = [
+            (
+                self.result.lower,
+                format!("q{}", self.lower_q),
+                "#3182bd",
+                "4 3",
+            ),
+            (
+                self.result.estimate,
+                String::from("median"),
+                "#de2d26",
+                "none",
+            ),
+            (
+                self.result.upper,
+                format!("q{}", self.upper_q),
+                "#3182bd",
+                "4 3",
+            ),
+        ];
+        for (i, (value, label, colour, dash)) in markers.into_iter().enumerate() {
+            let Some(value) = value.filter(|e| e.is_finite() && *e > 0.0) else {
+                continue;
+            };
+            let pos = x(value.log10());
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{pos:.2}" y1="{MARGIN_TOP}" x2="{pos:.2}" y2="{bottom}" stroke="{colour}" stroke-width="2" stroke-dasharray="{dash}"/>"#
+            );
+            let _ = writeln!(
+                svg,
+                r#"<text x="{pos:.2}" y="{}" text-anchor="middle" fill="{colour}">{label}: {}</text>"#,
+                MARGIN_TOP - 18.0 + 12.0 * (i % 2) as f32,
+                format_estimate(value)
+            );
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Render the plot as an HTML page containing the SVG image.
+    pub(crate) fn html(&self) -> String {
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>lrge per-read estimates</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+            self.svg()
+        )
+    }
+
+    /// Write the plot to `path` - as an HTML page if it ends in `.html` or `.htm`, otherwise as an
+    /// SVG image.
+    pub(crate) fn write(&self, path: &Path) -> Result<()> {
+        let is_html = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm"));
+        let contents = if is_html { self.html() } else { self.svg() };
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create plot file {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(contents.as_bytes())?;
+        writer.flush().context("Failed to write plot")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(lower: f32, estimate: f32, upper: f32) -> EstimateResult {
+        EstimateResult {
+            lower: Some(lower),
+            estimate: Some(estimate),
+            upper: Some(upper),
+            no_mapping_count: 1,
+            num_reads: 5,
+            num_finite: 4,
+            input_num_reads: None,
+            input_num_bases: None,
+            input_num_skipped: None,
+            gc_content: None,
+            contained_fraction: None,
+            duplicate_fraction: None,
+            adapters: None,
+            poisson_interval: None,
+            quantiles: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_svg() {
+        let estimates = [1e5, 2e5, 3e5, 1e6, f32::INFINITY];
+        let result = result(1.5e5, 2.5e5, 5e5);
+        let plot = Plot {
+            estimates: &estimates,
+            result: &result,
+            lower_q: 0.25,
+            upper_q: 0.75,
+            bins: 10,
+        };
+        let svg = plot.svg();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.ends_with("</svg>\n"));
+        assert!(svg.contains("(4 finite, 1 infinite)"));
+        assert!(svg.contains("median: 250.00 kbp"));
+        assert!(svg.contains("q0.25: 150.00 kbp"));
+        assert!(svg.contains("q0.75: 500.00 kbp"));
+        // a tick at 1 Mbp, and one bar per bin
+        assert!(svg.contains(">1.00 Mbp</text>"));
+        assert_eq!(svg.matches("<title>").count(), 10);
+
+        let html = plot.html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains(&svg));
+    }
+
+    #[test]
+    fn test_svg_without_finite_estimates() {
+        let estimates = [f32::INFINITY; 3];
+        let result = EstimateResult {
+            estimate: None,
+            lower: None,
+            upper: None,
+            ..result(0.0, 0.0, 0.0)
+        };
+        let plot = Plot {
+            estimates: &estimates,
+            result: &result,
+            lower_q: 0.25,
+            upper_q: 0.75,
+            bins: 10,
+        };
+        let svg = plot.svg();
+        assert!(svg.contains("No finite estimates to plot"));
+        assert!(!svg.contains("<title>"));
+    }
+
+    #[test]
+    fn test_svg_of_equal_estimates() {
+        let estimates = [1e5; 4];
+        let result = result(1e5, 1e5, 1e5);
+        let plot = Plot {
+            estimates: &estimates,
+            result: &result,
+            lower_q: 0.25,
+            upper_q: 0.75,
+            bins: 5,
+        };
+        let svg = plot.svg();
+        assert!(!svg.contains("NaN"));
+        // all of the estimates are in the middle bin
+        assert_eq!(svg.matches(": 4</title>").count(), 1);
+    }
+}
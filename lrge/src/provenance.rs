@@ -0,0 +1,131 @@
+//! Where an estimate came from - the versions, parameters, input, host, and time it was made with -
+//! recorded in the report so that it is auditable.
+use crate::cli::Options;
+use crate::parameters::Parameters;
+use crate::utils::sha256;
+use anyhow::{Context, Result};
+use liblrge::artifact::Artifact;
+use liblrge::Strategy;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// The provenance of an estimate.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Provenance {
+    /// The version of lrge
+    pub lrge_version: &'static str,
+    /// The version of liblrge
+    pub liblrge_version: &'static str,
+    /// The version of minimap2
+    pub minimap2_version: &'static str,
+    /// The command the estimate was made with
+    pub command: String,
+    /// The resolved parameters of the estimate
+    pub parameters: Parameters,
+    /// The input file, and the reads sampled from it
+    pub input: InputProvenance,
+    /// The name of the host the estimate was made on
+    pub hostname: Option<String>,
+    /// When the estimate was finished, in UTC
+    pub date: String,
+    /// The wall-clock time taken, in seconds
+    pub wall_time_secs: f64,
+    /// The CPU time (user and system) used by all threads, in seconds
+    pub cpu_time_secs: Option<f64>,
+}
+
+/// The input file, and the reads sampled from it.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct InputProvenance {
+    /// The path of the input file
+    pub path: PathBuf,
+    /// The size of the input file, in bytes
+    pub size: Option<u64>,
+    /// The SHA-256 checksum of the sampled reads, as written to the temporary directory. For the
+    /// two-set strategy, the target reads are followed by the query reads
+    pub sampled_reads_sha256: Option<String>,
+}
+
+impl Provenance {
+    /// The provenance of the estimate made by `strategy`, which was started at `started`.
+    pub(crate) fn new(
+        strategy: &mut Strategy,
+        opts: &Options,
+        input: &Path,
+        started: Instant,
+    ) -> Result<Self> {
+        let command: Vec<String> = std::env::args().collect();
+        Ok(Self {
+            lrge_version: env!("CARGO_PKG_VERSION"),
+            liblrge_version: liblrge::VERSION,
+            minimap2_version: liblrge::minimap2_version(),
+            command: command.join(" "),
+            parameters: Parameters::new(strategy, opts, input)?,
+            input: InputProvenance {
+                path: input.to_path_buf(),
+                size: std::fs::metadata(input).map(|m| m.len()).ok(),
+                sampled_reads_sha256: sampled_reads_checksum(strategy)?,
+            },
+            hostname: hostname(),
+            date: jiff::Timestamp::now()
+                .strftime("%Y-%m-%dT%H:%M:%SZ")
+                .to_string(),
+            wall_time_secs: started.elapsed().as_secs_f64(),
+            cpu_time_secs: cpu_time(),
+        })
+    }
+}
+
+/// The SHA-256 checksum, as a hex string, of the sampled reads files written by `strategy`, or
+/// `None` if none of them exist - e.g., they were removed.
+fn sampled_reads_checksum(strategy: &Strategy) -> Result<Option<String>> {
+    let paths: Vec<PathBuf> = [Artifact::Target, Artifact::Query, Artifact::Reads]
+        .into_iter()
+        .filter_map(|artifact| strategy.artifact_path(artifact))
+        .filter(|path| path.is_file())
+        .collect();
+    if paths.is_empty() {
+        return Ok(None);
+    }
+    sha256(&paths)
+        .context("Failed to checksum the sampled reads")
+        .map(Some)
+}
+
+/// The name of the host lrge is running on.
+fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: gethostname writes at most `buf.len()` bytes to the buffer
+    let ret = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) };
+    if ret != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    Some(String::from_utf8_lossy(&buf[..len]).into_owned())
+}
+
+/// The CPU time (user and system) used by lrge so far, across all of its threads, in seconds.
+fn cpu_time() -> Option<f64> {
+    let mut usage = std::mem::MaybeUninit::<libc::rusage>::uninit();
+    // SAFETY: getrusage initialises the struct when it succeeds
+    let usage = unsafe {
+        if libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) != 0 {
+            return None;
+        }
+        usage.assume_init()
+    };
+    let secs = |t: libc::timeval| t.tv_sec as f64 + t.tv_usec as f64 / 1e6;
+    Some(secs(usage.ru_utime) + secs(usage.ru_stime))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hostname_and_cpu_time() {
+        assert!(hostname().is_some_and(|name| !name.is_empty()));
+        assert!(cpu_time().is_some_and(|secs| secs >= 0.0));
+    }
+}
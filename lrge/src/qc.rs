@@ -0,0 +1,322 @@
+//! Quality control checks on an estimate, and the exit codes used when they fail.
+use crate::cli::Options;
+use crate::utils::format_estimate;
+use liblrge::estimate::EstimateResult;
+use std::fmt;
+use std::process::ExitCode;
+
+/// Exit code when more than `--fail-if-no-overlap-pct` percent of reads did not overlap.
+pub(crate) const EXIT_NO_OVERLAP: u8 = 3;
+/// Exit code when the estimate is outside `--fail-if-estimate-outside`.
+pub(crate) const EXIT_ESTIMATE_OUTSIDE: u8 = 4;
+/// Exit code when the relative error versus `--expected-size` is larger than `--max-relative-error`.
+pub(crate) const EXIT_RELATIVE_ERROR: u8 = 5;
+/// Exit code when the estimated depth of coverage is below `--min-coverage`.
+pub(crate) const EXIT_LOW_COVERAGE: u8 = 6;
+
+/// A range of genome sizes, in bp. Either bound may be open.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct SizeRange {
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+}
+
+impl SizeRange {
+    fn contains(&self, size: f32) -> bool {
+        self.min.is_none_or(|min| size >= min) && self.max.is_none_or(|max| size <= max)
+    }
+}
+
+impl fmt::Display for SizeRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let min = self.min.map(format_estimate).unwrap_or_default();
+        let max = self.max.map(format_estimate).unwrap_or_default();
+        write!(f, "{min}:{max}")
+    }
+}
+
+/// A failed quality control check.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum QcFailure {
+    /// Too many reads did not overlap any other reads
+    NoOverlap { percent: f32, threshold: f32 },
+    /// The estimate is outside the expected range. A missing estimate is always outside the range
+    EstimateOutside {
+        estimate: Option<f32>,
+        range: SizeRange,
+    },
+    /// The estimate deviates too much from the expected genome size. A missing estimate always
+    /// deviates too much
+    RelativeError {
+        relative_error: Option<f32>,
+        expected: f32,
+        tolerance: f32,
+    },
+    /// The estimated depth of coverage is too low. Coverage cannot be estimated without an
+    /// estimate, so a missing estimate always fails
+    LowCoverage {
+        coverage: Option<f32>,
+        threshold: f32,
+    },
+}
+
+impl QcFailure {
+    /// The exit code for this failure.
+    pub(crate) fn exit_code(&self) -> u8 {
+        match self {
+            QcFailure::NoOverlap { .. } => EXIT_NO_OVERLAP,
+            QcFailure::EstimateOutside { .. } => EXIT_ESTIMATE_OUTSIDE,
+            QcFailure::RelativeError { .. } => EXIT_RELATIVE_ERROR,
+            QcFailure::LowCoverage { .. } => EXIT_LOW_COVERAGE,
+        }
+    }
+}
+
+impl fmt::Display for QcFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QcFailure::NoOverlap { percent, threshold } => write!(
+                f,
+                "{percent:.2}% of reads did not overlap any other reads (threshold: {threshold}%)"
+            ),
+            QcFailure::EstimateOutside { estimate, range } => {
+                let estimate = estimate.map_or_else(|| "NA".to_string(), format_estimate);
+                write!(f, "Estimate {estimate} is outside the range {range}")
+            }
+            QcFailure::RelativeError {
+                relative_error,
+                expected,
+                tolerance,
+            } => {
+                let relative_error =
+                    relative_error.map_or_else(|| "NA".to_string(), |e| format!("{e:+.4}"));
+                write!(
+                    f,
+                    "Relative error {relative_error} versus the expected size {} is larger than {tolerance}",
+                    format_estimate(*expected)
+                )
+            }
+            QcFailure::LowCoverage {
+                coverage,
+                threshold,
+            } => {
+                let coverage = coverage.map_or_else(|| "NA".to_string(), |c| format!("{c:.1}x"));
+                write!(
+                    f,
+                    "Estimated depth of coverage {coverage} is below {threshold}x"
+                )
+            }
+        }
+    }
+}
+
+/// Run the quality control checks requested in `opts` on an estimate.
+///
+/// The failures are returned in the order of their exit codes.
+pub(crate) fn check(result: &EstimateResult, opts: &Options) -> Vec<QcFailure> {
+    let mut failures = Vec::new();
+
+    if let Some(threshold) = opts.max_no_overlap_pct {
+        let percent = if result.num_reads == 0 {
+            0.0
+        } else {
+            result.no_mapping_count as f32 * 100.0 / result.num_reads as f32
+        };
+        if percent > threshold {
+            failures.push(QcFailure::NoOverlap { percent, threshold });
+        }
+    }
+
+    if let Some(range) = opts.estimate_range {
+        if !result.estimate.is_some_and(|est| range.contains(est)) {
+            failures.push(QcFailure::EstimateOutside {
+                estimate: result.estimate,
+                range,
+            });
+        }
+    }
+
+    if let (Some(expected), Some(tolerance)) = (opts.expected_size, opts.max_relative_error) {
+        let relative_error = result.estimate.map(|est| relative_error(est, expected));
+        if !relative_error.is_some_and(|e| e.abs() <= tolerance) {
+            failures.push(QcFailure::RelativeError {
+                relative_error,
+                expected,
+                tolerance,
+            });
+        }
+    }
+
+    if let Some(threshold) = opts.min_coverage {
+        let coverage = estimated_coverage(result);
+        if !coverage.is_some_and(|c| c >= threshold) {
+            failures.push(QcFailure::LowCoverage {
+                coverage,
+                threshold,
+            });
+        }
+    }
+
+    failures
+}
+
+/// The estimated depth of coverage of the input - i.e., the number of input bases divided by the
+/// genome size estimate. This is `None` if either is unknown.
+pub(crate) fn estimated_coverage(result: &EstimateResult) -> Option<f32> {
+    let bases = result.input_num_bases?;
+    result
+        .estimate
+        .filter(|est| *est > 0.0)
+        .map(|est| bases as f32 / est)
+}
+
+/// The relative error of an estimate versus the expected genome size. This is positive if the
+/// estimate is larger than expected.
+pub(crate) fn relative_error(estimate: f32, expected: f32) -> f32 {
+    (estimate - expected) / expected
+}
+
+/// The exit code for a set of failures - that of the first failure, or success if there are none.
+pub(crate) fn exit_code(failures: &[QcFailure]) -> ExitCode {
+    failures
+        .first()
+        .map_or(ExitCode::SUCCESS, |f| ExitCode::from(f.exit_code()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Args;
+    use clap::Parser;
+
+    fn options(argv: &[&str]) -> Options {
+        let mut args = vec![env!("CARGO_BIN_NAME"), "Cargo.toml"];
+        args.extend_from_slice(argv);
+        Args::try_parse_from(args).unwrap().opts
+    }
+
+    fn result(estimate: Option<f32>, no_mapping_count: u32, num_reads: u32) -> EstimateResult {
+        EstimateResult {
+            lower: None,
+            estimate,
+            upper: None,
+            no_mapping_count,
+            num_reads,
+            num_finite: num_reads - no_mapping_count,
+            input_num_reads: Some(10_000),
+            input_num_bases: Some(100_000_000),
+            input_num_skipped: None,
+            gc_content: None,
+            contained_fraction: None,
+            duplicate_fraction: None,
+            adapters: None,
+            poisson_interval: None,
+            quantiles: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_size_range_contains() {
+        let range = SizeRange {
+            min: Some(10.0),
+            max: None,
+        };
+        assert!(range.contains(10.0));
+        assert!(range.contains(f32::INFINITY));
+        assert!(!range.contains(9.9));
+    }
+
+    #[test]
+    fn test_check_no_checks() {
+        let opts = options(&[]);
+        assert!(check(&result(None, 10, 10), &opts).is_empty());
+    }
+
+    #[test]
+    fn test_check_no_overlap() {
+        let opts = options(&["--fail-if-no-overlap-pct", "20"]);
+        assert!(check(&result(Some(5e6), 2, 10), &opts).is_empty());
+
+        let failures = check(&result(Some(5e6), 3, 10), &opts);
+        assert_eq!(
+            failures,
+            vec![QcFailure::NoOverlap {
+                percent: 30.0,
+                threshold: 20.0
+            }]
+        );
+        assert_eq!(exit_code(&failures), ExitCode::from(EXIT_NO_OVERLAP));
+    }
+
+    #[test]
+    fn test_check_estimate_outside() {
+        let opts = options(&["--fail-if-estimate-outside", "4m:6m"]);
+        assert!(check(&result(Some(5e6), 0, 10), &opts).is_empty());
+        assert_eq!(check(&result(Some(7e6), 0, 10), &opts).len(), 1);
+        assert_eq!(check(&result(Some(3e6), 0, 10), &opts).len(), 1);
+
+        let failures = check(&result(None, 0, 10), &opts);
+        assert_eq!(exit_code(&failures), ExitCode::from(EXIT_ESTIMATE_OUTSIDE));
+    }
+
+    #[test]
+    fn test_check_multiple_failures() {
+        let opts = options(&[
+            "--fail-if-no-overlap-pct",
+            "0",
+            "--fail-if-estimate-outside",
+            ":1k",
+        ]);
+        let failures = check(&result(Some(5e6), 1, 10), &opts);
+        assert_eq!(failures.len(), 2);
+        assert_eq!(exit_code(&failures), ExitCode::from(EXIT_NO_OVERLAP));
+    }
+
+    #[test]
+    fn test_relative_error() {
+        assert_eq!(relative_error(5.5e6, 5e6), 0.1);
+        assert_eq!(relative_error(4e6, 5e6), -0.2);
+    }
+
+    #[test]
+    fn test_check_relative_error() {
+        let opts = options(&["--expected-size", "5m", "--max-relative-error", "0.1"]);
+        assert!(check(&result(Some(5.4e6), 0, 10), &opts).is_empty());
+        assert!(check(&result(Some(4.6e6), 0, 10), &opts).is_empty());
+
+        let failures = check(&result(Some(4e6), 0, 10), &opts);
+        assert_eq!(exit_code(&failures), ExitCode::from(EXIT_RELATIVE_ERROR));
+        assert_eq!(check(&result(None, 0, 10), &opts).len(), 1);
+
+        // without a tolerance, the expected size is only reported
+        let opts = options(&["--expected-size", "5m"]);
+        assert!(check(&result(Some(4e6), 0, 10), &opts).is_empty());
+    }
+
+    #[test]
+    fn test_estimated_coverage() {
+        assert_eq!(estimated_coverage(&result(Some(5e6), 0, 10)), Some(20.0));
+        assert_eq!(estimated_coverage(&result(None, 0, 10)), None);
+
+        let mut unknown_yield = result(Some(5e6), 0, 10);
+        unknown_yield.input_num_bases = None;
+        assert_eq!(estimated_coverage(&unknown_yield), None);
+    }
+
+    #[test]
+    fn test_check_low_coverage() {
+        let opts = options(&["--min-coverage", "30"]);
+        // 100 Mbp of reads for a 5 Mbp genome is 20x
+        let failures = check(&result(Some(5e6), 0, 10), &opts);
+        assert_eq!(
+            failures,
+            vec![QcFailure::LowCoverage {
+                coverage: Some(20.0),
+                threshold: 30.0
+            }]
+        );
+        assert_eq!(exit_code(&failures), ExitCode::from(EXIT_LOW_COVERAGE));
+
+        assert!(check(&result(Some(2e6), 0, 10), &opts).is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+//! A rarefaction table of the estimate at increasing numbers of reads.
+use crate::batch::format_value;
+use crate::utils::OutputFormat;
+use anyhow::{Context, Result};
+use liblrge::estimate::EstimateResult;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Write the rarefaction curve as a tab-delimited table, with one row per number of reads.
+pub(crate) fn write_table<W: Write>(
+    mut writer: W,
+    curve: &[EstimateResult],
+    format: OutputFormat,
+) -> Result<()> {
+    writeln!(
+        writer,
+        "num_reads\testimate\tlower\tupper\tno_mapping_count"
+    )?;
+    for point in curve {
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{}",
+            point.num_reads,
+            format_value(point.estimate, format),
+            format_value(point.lower, format),
+            format_value(point.upper, format),
+            point.no_mapping_count
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the rarefaction table to the file at `path`.
+pub(crate) fn write(path: &Path, curve: &[EstimateResult], format: OutputFormat) -> Result<()> {
+    let file = File::create(path).context("Failed to create rarefaction file")?;
+    write_table(BufWriter::new(file), curve, format).context("Failed to write rarefaction table")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(num_reads: u32, estimate: Option<f32>) -> EstimateResult {
+        EstimateResult {
+            lower: None,
+            estimate,
+            upper: None,
+            no_mapping_count: 1,
+            num_reads,
+            num_finite: num_reads,
+            input_num_reads: None,
+            input_num_bases: None,
+            input_num_skipped: None,
+            gc_content: None,
+            contained_fraction: None,
+            duplicate_fraction: None,
+            adapters: None,
+            poisson_interval: None,
+            quantiles: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_write_table() {
+        let curve = vec![point(500, None), point(1000, Some(4.4e6))];
+        let mut buf = Vec::new();
+        write_table(&mut buf, &curve, OutputFormat::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "num_reads\testimate\tlower\tupper\tno_mapping_count\n\
+             500\tNA\tNA\tNA\t1\n\
+             1000\t4400000\tNA\tNA\t1\n"
+        );
+    }
+}
@@ -0,0 +1,596 @@
+//! A machine-readable report of an estimate and its quality control metrics.
+use crate::cli::{Options, ReportFormat};
+use crate::provenance::Provenance;
+use crate::qc::{self, QcFailure};
+use anyhow::{Context, Result};
+use liblrge::adapter::AdapterSummary;
+use liblrge::estimate::{calibrated_confidence, EstimateResult, QuantileInterval};
+use liblrge::gc::GcSummary;
+use liblrge::islands::IslandEstimate;
+use liblrge::overlap_stats::{Distribution, OverlapStats};
+use liblrge::poisson::{PoissonInterval, POISSON_CONFIDENCE};
+use liblrge::replicon::Decomposition;
+use liblrge::{AutoStrategy, StrategyKind};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// The report of a single estimate.
+#[derive(Debug, Serialize)]
+pub(crate) struct Report {
+    /// The genome size estimate, in bp
+    pub estimate: Option<f32>,
+    /// The lower quantile of the per-read estimates
+    pub lower: Option<f32>,
+    /// The upper quantile of the per-read estimates
+    pub upper: Option<f32>,
+    /// The lower quantile used
+    pub lower_quantile: f32,
+    /// The upper quantile used
+    pub upper_quantile: f32,
+    /// The calibrated confidence of the interval between the lower and upper quantiles, if it has
+    /// one
+    pub confidence: Option<f32>,
+    /// Any other quantiles of the per-read estimates that were requested, keyed by quantile
+    pub quantiles: Option<QuantilesReport>,
+    /// Any intervals between quantiles of the per-read estimates that were requested
+    pub intervals: Option<Vec<IntervalReport>>,
+    /// The confidence interval of the estimate from a Poisson model of the number of overlaps
+    pub poisson_interval: Option<PoissonReport>,
+    /// The number of reads an estimate was generated for
+    pub num_reads: u32,
+    /// The number of reads without an overlap
+    pub no_mapping_count: u32,
+    /// The expected genome size, in bp
+    pub expected_size: Option<f32>,
+    /// The relative error of the estimate versus the expected genome size
+    pub relative_error: Option<f32>,
+    /// The number of reads in the input
+    pub input_num_reads: Option<u64>,
+    /// The total number of bases in the input
+    pub input_num_bases: Option<u64>,
+    /// The number of malformed records in the input that were skipped
+    pub input_num_skipped: Option<u64>,
+    /// The estimated depth of coverage of the input
+    pub coverage: Option<f32>,
+    /// The GC content of the sampled reads
+    pub gc: Option<GcReport>,
+    /// The fraction of the reads that are contained in a read they overlap
+    pub contained_fraction: Option<f32>,
+    /// The fraction of the reads that are a duplicate of a read they overlap
+    pub duplicate_fraction: Option<f32>,
+    /// How many of the sampled reads had adapters removed, if they were scanned for them
+    pub adapters: Option<AdapterReport>,
+    /// The estimated sizes of the putative replicons, largest first, if they were requested
+    pub replicon_sizes: Option<Vec<f32>>,
+    /// The fraction of the total size of the replicons in the putative plasmids
+    pub plasmid_fraction: Option<f32>,
+    /// The distributions of the overlaps, if they were requested
+    pub overlaps: Option<OverlapReport>,
+    /// The Lander-Waterman estimate from the islands of the overlap graph, if it was requested
+    pub islands: Option<IslandReport>,
+    /// The strategy chosen with `--strategy auto`, and why
+    pub strategy_choice: Option<StrategyChoiceReport>,
+    /// The version of minimap2 the overlaps were found with
+    pub minimap2_version: &'static str,
+    /// The quality control checks that failed
+    pub qc_failures: Vec<String>,
+    /// The versions, parameters, input, host, and time the estimate was made with
+    pub provenance: Option<Provenance>,
+}
+
+/// The GC content of the sampled reads, as percentages.
+#[derive(Debug, Serialize)]
+pub(crate) struct GcReport {
+    /// The GC content of all sampled bases
+    pub overall: f32,
+    /// The 5th percentile of the per-read GC content
+    pub p5: f32,
+    /// The 25th percentile of the per-read GC content
+    pub p25: f32,
+    /// The median of the per-read GC content
+    pub median: f32,
+    /// The 75th percentile of the per-read GC content
+    pub p75: f32,
+    /// The 95th percentile of the per-read GC content
+    pub p95: f32,
+}
+
+impl From<&GcSummary> for GcReport {
+    fn from(gc: &GcSummary) -> Self {
+        // the quantiles are those in GC_QUANTILES
+        let [p5, p25, median, p75, p95] = gc.quantiles;
+        Self {
+            overall: gc.overall,
+            p5,
+            p25,
+            median,
+            p75,
+            p95,
+        }
+    }
+}
+
+/// The confidence interval of the estimate from a Poisson model of the number of overlaps.
+#[derive(Debug, Serialize)]
+pub(crate) struct PoissonReport {
+    /// The lower bound of the interval
+    pub lower: f32,
+    /// The upper bound of the interval
+    pub upper: f32,
+    /// The confidence level of the interval
+    pub confidence: f32,
+    /// The number of independent overlaps the estimate was calculated from
+    pub num_overlaps: u64,
+}
+
+impl From<&PoissonInterval> for PoissonReport {
+    fn from(interval: &PoissonInterval) -> Self {
+        Self {
+            lower: interval.lower,
+            upper: interval.upper,
+            confidence: POISSON_CONFIDENCE,
+            num_overlaps: interval.num_overlaps,
+        }
+    }
+}
+
+/// Quantiles of the per-read estimates, as `(quantile, value)` pairs, serialized as a map from
+/// each quantile to its value in the order they were requested.
+#[derive(Debug)]
+pub(crate) struct QuantilesReport(pub Vec<(f32, f32)>);
+
+impl Serialize for QuantilesReport {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (quantile, value) in &self.0 {
+            map.serialize_entry(&quantile.to_string(), value)?;
+        }
+        map.end()
+    }
+}
+
+/// An interval between two quantiles of the per-read estimates.
+#[derive(Debug, Serialize)]
+pub(crate) struct IntervalReport {
+    /// The calibrated confidence of the interval (e.g., `92% CI`), or its quantiles if it has none
+    pub label: String,
+    /// The lower quantile
+    pub lower_quantile: f32,
+    /// The upper quantile
+    pub upper_quantile: f32,
+    /// The lower quantile of the per-read estimates
+    pub lower: f32,
+    /// The upper quantile of the per-read estimates
+    pub upper: f32,
+    /// The calibrated confidence of the interval, if it has one
+    pub confidence: Option<f32>,
+}
+
+impl From<&QuantileInterval> for IntervalReport {
+    fn from(interval: &QuantileInterval) -> Self {
+        Self {
+            label: interval.label(),
+            lower_quantile: interval.lower_quantile,
+            upper_quantile: interval.upper_quantile,
+            lower: interval.lower,
+            upper: interval.upper,
+            confidence: interval.confidence,
+        }
+    }
+}
+
+/// How many of the sampled reads had adapters removed.
+#[derive(Debug, Serialize)]
+pub(crate) struct AdapterReport {
+    /// The number of reads that were scanned for adapters
+    pub num_reads: usize,
+    /// The number of reads with an adapter trimmed from either end
+    pub num_trimmed: usize,
+    /// The number of chimeric reads, split at an adapter in the middle
+    pub num_split: usize,
+}
+
+impl From<&AdapterSummary> for AdapterReport {
+    fn from(summary: &AdapterSummary) -> Self {
+        Self {
+            num_reads: summary.num_reads,
+            num_trimmed: summary.num_trimmed,
+            num_split: summary.num_split,
+        }
+    }
+}
+
+/// The quantiles and histogram of a distribution.
+#[derive(Debug, Serialize)]
+pub(crate) struct DistributionReport {
+    /// The number of values
+    pub count: usize,
+    /// The 5th percentile
+    pub p5: f32,
+    /// The 25th percentile
+    pub p25: f32,
+    /// The median
+    pub median: f32,
+    /// The 75th percentile
+    pub p75: f32,
+    /// The 95th percentile
+    pub p95: f32,
+    /// The edges of the histogram bins
+    pub bin_edges: Vec<f32>,
+    /// The number of values in each histogram bin
+    pub bin_counts: Vec<usize>,
+}
+
+impl From<&Distribution> for DistributionReport {
+    fn from(dist: &Distribution) -> Self {
+        // the quantiles are those in DISTRIBUTION_QUANTILES
+        let [p5, p25, median, p75, p95] = dist.quantiles;
+        Self {
+            count: dist.count,
+            p5,
+            p25,
+            median,
+            p75,
+            p95,
+            bin_edges: dist.bin_edges.clone(),
+            bin_counts: dist.bin_counts.clone(),
+        }
+    }
+}
+
+/// The distributions of the overlaps.
+#[derive(Debug, Serialize)]
+pub(crate) struct OverlapReport {
+    /// The number of other reads that each read overlaps
+    pub per_read: DistributionReport,
+    /// The overlap lengths, in bp
+    pub length: DistributionReport,
+    /// The sequence divergence (dv) of the overlaps
+    pub divergence: DistributionReport,
+}
+
+impl From<&OverlapStats> for OverlapReport {
+    fn from(stats: &OverlapStats) -> Self {
+        Self {
+            per_read: (&stats.overlaps_per_read).into(),
+            length: (&stats.overlap_len).into(),
+            divergence: (&stats.divergence).into(),
+        }
+    }
+}
+
+/// The Lander-Waterman estimate from the islands of the overlap graph.
+#[derive(Debug, Serialize)]
+pub(crate) struct IslandReport {
+    /// The genome size estimate, in bp
+    pub estimate: f32,
+    /// The number of islands, including reads without overlaps
+    pub num_islands: usize,
+    /// The apparent depth of coverage of the sampled reads
+    pub coverage: f32,
+}
+
+impl From<&IslandEstimate> for IslandReport {
+    fn from(islands: &IslandEstimate) -> Self {
+        Self {
+            estimate: islands.estimate,
+            num_islands: islands.num_islands,
+            coverage: islands.coverage,
+        }
+    }
+}
+
+/// The strategy chosen from the reads in the input.
+#[derive(Debug, Serialize)]
+pub(crate) struct StrategyChoiceReport {
+    /// The chosen strategy - two-set or all-vs-all
+    pub strategy: &'static str,
+    /// The number of reads in the input the choice was made from
+    pub input_num_reads: u64,
+    /// The total number of bases in the input the choice was made from
+    pub input_num_bases: u64,
+    /// Why the strategy was chosen
+    pub reason: String,
+}
+
+impl From<&AutoStrategy> for StrategyChoiceReport {
+    fn from(choice: &AutoStrategy) -> Self {
+        Self {
+            strategy: match choice.kind {
+                StrategyKind::TwoSet { .. } => "two-set",
+                StrategyKind::Ava { .. } => "all-vs-all",
+                StrategyKind::Sketch { .. } => "sketch",
+            },
+            input_num_reads: choice.input_num_reads,
+            input_num_bases: choice.input_num_bases,
+            reason: choice.reason.clone(),
+        }
+    }
+}
+
+impl Report {
+    pub(crate) fn new(result: &EstimateResult, opts: &Options, failures: &[QcFailure]) -> Self {
+        Self {
+            estimate: result.estimate,
+            lower: result.lower,
+            upper: result.upper,
+            lower_quantile: opts.lower_q,
+            upper_quantile: opts.upper_q,
+            confidence: calibrated_confidence(opts.lower_q, opts.upper_q),
+            poisson_interval: result.poisson_interval.as_ref().map(PoissonReport::from),
+            quantiles: (!result.quantiles.is_empty())
+                .then(|| QuantilesReport(result.quantiles.clone())),
+            intervals: (!opts.intervals.is_empty()).then(|| {
+                opts.intervals
+                    .iter()
+                    .filter_map(|&(lower_q, upper_q)| result.interval(lower_q, upper_q))
+                    .map(|interval| IntervalReport::from(&interval))
+                    .collect()
+            }),
+            num_reads: result.num_reads,
+            no_mapping_count: result.no_mapping_count,
+            expected_size: opts.expected_size,
+            relative_error: opts
+                .expected_size
+                .zip(result.estimate)
+                .map(|(expected, est)| qc::relative_error(est, expected)),
+            input_num_reads: result.input_num_reads,
+            input_num_bases: result.input_num_bases,
+            input_num_skipped: result.input_num_skipped,
+            coverage: qc::estimated_coverage(result),
+            gc: result.gc_content.as_ref().map(GcReport::from),
+            contained_fraction: result.contained_fraction,
+            duplicate_fraction: result.duplicate_fraction,
+            adapters: result.adapters.as_ref().map(AdapterReport::from),
+            replicon_sizes: None,
+            plasmid_fraction: None,
+            overlaps: None,
+            islands: None,
+            strategy_choice: None,
+            minimap2_version: liblrge::minimap2_version(),
+            qc_failures: failures.iter().map(|f| f.to_string()).collect(),
+            provenance: None,
+        }
+    }
+
+    /// Add the putative replicons, if the estimate was decomposed into them.
+    pub(crate) fn with_replicons(mut self, decomposition: Option<&Decomposition>) -> Self {
+        if let Some(decomposition) = decomposition {
+            self.replicon_sizes = Some(decomposition.replicons.iter().map(|r| r.size).collect());
+            self.plasmid_fraction = decomposition.plasmid_fraction();
+        }
+        self
+    }
+
+    /// Add the distributions of the overlaps, if they were summarised.
+    pub(crate) fn with_overlap_stats(mut self, stats: Option<&OverlapStats>) -> Self {
+        self.overlaps = stats.map(OverlapReport::from);
+        self
+    }
+
+    /// Add the Lander-Waterman estimate, if the islands were counted.
+    pub(crate) fn with_islands(mut self, islands: Option<&IslandEstimate>) -> Self {
+        self.islands = islands.map(IslandReport::from);
+        self
+    }
+
+    /// Add the strategy chosen from the reads in the input, if it was chosen automatically.
+    pub(crate) fn with_strategy_choice(mut self, choice: Option<&AutoStrategy>) -> Self {
+        self.strategy_choice = choice.map(StrategyChoiceReport::from);
+        self
+    }
+
+    /// Add the provenance of the estimate, if it was recorded.
+    pub(crate) fn with_provenance(mut self, provenance: Option<Provenance>) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Write the report to `path` in the given format.
+    pub(crate) fn write(&self, path: &Path, format: ReportFormat) -> Result<()> {
+        let file = File::create(path).context("Failed to create report file")?;
+        let mut writer = BufWriter::new(file);
+        match format {
+            ReportFormat::Json => {
+                serde_json::to_writer_pretty(&mut writer, self)?;
+                writeln!(writer)?;
+            }
+            ReportFormat::Tsv => self.write_tsv(&mut writer)?,
+        }
+        writer.flush().context("Failed to write report")
+    }
+
+    /// Write the report as a header line and a single row of tab-separated values. Nested fields
+    /// are flattened into dot-separated columns, and lists are joined with `;`.
+    fn write_tsv<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let mut columns = Vec::new();
+        flatten("", &serde_json::to_value(self)?, &mut columns);
+        let (header, row): (Vec<_>, Vec<_>) = columns.into_iter().unzip();
+        writeln!(writer, "{}", header.join("\t"))?;
+        writeln!(writer, "{}", row.join("\t"))?;
+        Ok(())
+    }
+}
+
+/// Flatten a JSON value into (column, value) pairs.
+fn flatten(prefix: &str, value: &Value, columns: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) => flatten_object(prefix, map, columns),
+        Value::Array(values) => {
+            let joined = values
+                .iter()
+                .map(|v| match v {
+                    Value::String(s) => s.clone(),
+                    v => v.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(";");
+            columns.push((prefix.to_string(), joined));
+        }
+        Value::Null => columns.push((prefix.to_string(), String::from("NA"))),
+        Value::String(s) => columns.push((prefix.to_string(), s.clone())),
+        v => columns.push((prefix.to_string(), v.to_string())),
+    }
+}
+
+fn flatten_object(prefix: &str, map: &Map<String, Value>, columns: &mut Vec<(String, String)>) {
+    for (key, value) in map {
+        let column = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        flatten(&column, value, columns);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Args;
+    use clap::Parser;
+
+    fn report(argv: &[&str]) -> Report {
+        let mut args = vec![env!("CARGO_BIN_NAME"), "Cargo.toml"];
+        args.extend_from_slice(argv);
+        let opts = Args::try_parse_from(args).unwrap().opts;
+        let result = EstimateResult {
+            lower: Some(4e6),
+            estimate: Some(4.5e6),
+            upper: Some(5e6),
+            no_mapping_count: 1,
+            num_reads: 10,
+            num_finite: 9,
+            input_num_reads: Some(9_000),
+            input_num_bases: Some(90_000_000),
+            input_num_skipped: Some(2),
+            gc_content: Some(GcSummary {
+                overall: 65.5,
+                quantiles: [60.0, 64.0, 65.0, 67.0, 70.0],
+                num_reads: 10,
+            }),
+            contained_fraction: Some(0.1),
+            duplicate_fraction: Some(0.02),
+            adapters: Some(AdapterSummary {
+                num_reads: 10,
+                num_trimmed: 2,
+                num_split: 1,
+            }),
+            poisson_interval: PoissonInterval::new(4.5e6, 100),
+            quantiles: vec![(0.05, 1e6), (0.15, 3e6), (0.65, 5e6), (0.95, f32::INFINITY)],
+        };
+        let failures = qc::check(&result, &opts);
+        Report::new(&result, &opts, &failures)
+    }
+
+    #[test]
+    fn test_report_intervals() {
+        let with_intervals = report(&["--intervals", "0.15-0.65,0.05-0.95,0.25-0.75"]);
+        assert_eq!(with_intervals.confidence, Some(0.92));
+        let intervals = with_intervals.intervals.unwrap();
+        // the quantiles of the last interval were not calculated
+        assert_eq!(intervals.len(), 2);
+        assert_eq!(intervals[0].label, "92% CI");
+        assert_eq!((intervals[0].lower, intervals[0].upper), (3e6, 5e6));
+        assert_eq!(intervals[1].label, "q0.05-q0.95");
+        assert_eq!(intervals[1].confidence, None);
+
+        let iqr = report(&["--q1", "0.25", "--q3", "0.75"]);
+        assert_eq!(iqr.confidence, None);
+        assert!(iqr.intervals.is_none());
+    }
+
+    #[test]
+    fn test_report_relative_error() {
+        let with_expected = report(&["--expected-size", "5m", "--max-relative-error", "0.05"]);
+        assert_eq!(with_expected.expected_size, Some(5e6));
+        assert_eq!(with_expected.relative_error, Some(-0.1));
+        assert_eq!(with_expected.qc_failures.len(), 1);
+
+        let without_expected = report(&[]);
+        assert_eq!(without_expected.expected_size, None);
+        assert_eq!(without_expected.relative_error, None);
+        assert!(without_expected.qc_failures.is_empty());
+    }
+
+    #[test]
+    fn test_write_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.json");
+        report(&["--expected-size", "5m"])
+            .write(&path, ReportFormat::Json)
+            .unwrap();
+
+        let value: Value = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+        assert_eq!(value["estimate"], 4.5e6);
+        assert_eq!(value["num_reads"], 10);
+        assert_eq!(value["expected_size"], 5e6);
+        assert!(value["relative_error"].is_number());
+        assert_eq!(value["input_num_reads"], 9_000);
+        assert_eq!(value["input_num_bases"], 90_000_000);
+        assert_eq!(value["input_num_skipped"], 2);
+        assert_eq!(value["coverage"], 20.0);
+        assert_eq!(value["gc"]["overall"], 65.5);
+        assert!((value["contained_fraction"].as_f64().unwrap() - 0.1).abs() < 1e-6);
+        assert!((value["duplicate_fraction"].as_f64().unwrap() - 0.02).abs() < 1e-6);
+        assert_eq!(value["gc"]["p95"], 70.0);
+        assert_eq!(value["adapters"]["num_split"], 1);
+        assert_eq!(value["poisson_interval"]["num_overlaps"], 100);
+        assert_eq!(value["quantiles"]["0.05"], 1e6);
+        assert!(value["quantiles"]["0.95"].is_null());
+        assert_eq!(value["minimap2_version"], liblrge::minimap2_version());
+        assert!(value["strategy_choice"].is_null());
+    }
+
+    #[test]
+    fn test_report_strategy_choice() {
+        let choice = StrategyKind::auto(500, 1_000_000);
+        let report = report(&["--strategy", "auto"]).with_strategy_choice(Some(&choice));
+        let strategy_choice = report.strategy_choice.unwrap();
+        assert_eq!(strategy_choice.strategy, "all-vs-all");
+        assert_eq!(strategy_choice.input_num_reads, 500);
+        assert_eq!(strategy_choice.reason, choice.reason);
+    }
+
+    #[test]
+    fn test_write_tsv() {
+        let mut buf = Vec::new();
+        report(&[]).write_tsv(&mut buf).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines.len(), 2);
+        let header: Vec<&str> = lines[0].split('\t').collect();
+        let row: Vec<&str> = lines[1].split('\t').collect();
+        assert_eq!(header.len(), row.len());
+        assert_eq!(header[0], "estimate");
+        assert_eq!(row[0], "4500000.0");
+        let expected_idx = header.iter().position(|&h| h == "expected_size").unwrap();
+        assert_eq!(row[expected_idx], "NA");
+        let gc_idx = header.iter().position(|&h| h == "gc.median").unwrap();
+        assert_eq!(row[gc_idx], "65.0");
+    }
+
+    #[test]
+    fn test_flatten_nested() {
+        let value = serde_json::json!({"a": {"b": 1, "c": [1, 2]}, "d": "x"});
+        let mut columns = Vec::new();
+        flatten("", &value, &mut columns);
+        assert_eq!(
+            columns,
+            vec![
+                ("a.b".to_string(), "1".to_string()),
+                ("a.c".to_string(), "1;2".to_string()),
+                ("d".to_string(), "x".to_string()),
+            ]
+        );
+    }
+}
@@ -1,5 +1,9 @@
-use anyhow::Result;
-use std::path::PathBuf;
+use crate::cli::Unit;
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 pub(crate) fn create_temp_dir(temp_dir: Option<&PathBuf>, keep: bool) -> Result<tempfile::TempDir> {
     let mut binding = tempfile::Builder::new();
@@ -16,11 +20,73 @@ pub(crate) fn create_temp_dir(temp_dir: Option<&PathBuf>, keep: bool) -> Result<
     Ok(tmpdir)
 }
 
+/// The SHA-256 checksum, as a hex string, of the contents of `paths`, one after the other.
+pub(crate) fn sha256<P: AsRef<Path>>(paths: &[P]) -> Result<String> {
+    let mut hasher = Sha256::new();
+    for path in paths {
+        let path = path.as_ref();
+        let mut file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        std::io::copy(&mut file, &mut hasher)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Write the versions of lrge, liblrge, and minimap2 as YAML, in the style of the `versions.yml`
+/// files of nf-core modules.
+pub(crate) fn write_versions<W: Write>(mut writer: W) -> std::io::Result<()> {
+    writeln!(writer, "lrge:")?;
+    writeln!(writer, "    lrge: {}", env!("CARGO_PKG_VERSION"))?;
+    writeln!(writer, "    liblrge: {}", liblrge::VERSION)?;
+    writeln!(writer, "    minimap2: {}", liblrge::minimap2_version())?;
+    writer.flush()
+}
+
+/// Write the versions of lrge, liblrge, and minimap2 to the YAML file at `path`.
+pub(crate) fn write_versions_file(path: &Path) -> Result<()> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create versions file {}", path.display()))?;
+    write_versions(std::io::BufWriter::new(file)).context("Failed to write versions file")
+}
+
+/// The RAM-backed (tmpfs) directory used for `--temp-in-ram`.
+const RAM_TEMP_DIR: &str = "/dev/shm";
+
+/// The RAM-backed directory to create the temporary directory in, if there is one.
+pub(crate) fn ram_temp_dir() -> Option<PathBuf> {
+    let path = PathBuf::from(RAM_TEMP_DIR);
+    (path.is_dir() && liblrge::is_ram_backed(&path)).then_some(path)
+}
+
+/// Format a number of bytes with the largest binary unit that keeps it at least 1 - e.g., 1.50 GiB.
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = units[0];
+    for next_unit in &units[1..] {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next_unit;
+    }
+    format!("{value:.2} {unit}")
+}
+
 pub(crate) fn format_estimate(estimate: f32) -> String {
     if estimate.is_infinite() {
         return String::from("∞ bp");
     }
 
+    let (value, suffix) = scale_estimate(estimate, Unit::Auto);
+
+    // Format the value with the determined suffix
+    format!("{value:.2} {suffix}",)
+}
+
+/// Scale an estimate, in bp, to the given unit. Returns the scaled value and the unit's suffix.
+fn scale_estimate(estimate: f32, unit: Unit) -> (f32, &'static str) {
     // Define the metric suffixes and their corresponding powers of 10
     let units = [
         ("bp", 0),
@@ -31,21 +97,73 @@ pub(crate) fn format_estimate(estimate: f32) -> String {
         ("Pbp", 5),
     ];
 
-    // Determine the appropriate unit
-    let mut value = estimate;
-    let mut suffix = "bp";
-    for (unit, power) in units {
-        let threshold = 10f32.powi(power * 3); // 10^(power * 3) for 10^0, 10^3, etc.
-        if estimate >= threshold {
-            value = estimate / threshold;
-            suffix = unit;
-        } else {
-            break;
+    let power = match unit {
+        Unit::Bp => 0,
+        Unit::Kbp => 1,
+        Unit::Mbp => 2,
+        Unit::Gbp => 3,
+        Unit::Auto => {
+            // Determine the appropriate unit
+            let mut power = 0;
+            for (_, p) in units {
+                if estimate >= 10f32.powi(p * 3) {
+                    power = p;
+                } else {
+                    break;
+                }
+            }
+            power
         }
+    };
+
+    let threshold = 10f32.powi(power * 3); // 10^(power * 3) for 10^0, 10^3, etc.
+    (estimate / threshold, units[power as usize].0)
+}
+
+/// Round a value to the given number of significant figures, returning the value and the number of
+/// decimal places needed to show those figures.
+fn round_sig_figs(value: f64, sig_figs: u8) -> (f64, usize) {
+    if value == 0.0 || !value.is_finite() {
+        return (value, 0);
     }
+    let magnitude = value.abs().log10().floor() as i32;
+    let decimals = sig_figs as i32 - 1 - magnitude;
+    let factor = 10f64.powi(decimals);
+    let rounded = (value * factor).round() / factor;
+    (rounded, decimals.max(0) as usize)
+}
 
-    // Format the value with the determined suffix
-    format!("{value:.2} {suffix}",)
+/// How the estimate is written to the output.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct OutputFormat {
+    /// The unit of the estimate
+    pub unit: Unit,
+    /// The number of significant figures to round the estimate to
+    pub sig_figs: Option<u8>,
+    /// Output the estimate as a floating point number
+    pub precise: bool,
+}
+
+impl OutputFormat {
+    /// Format an estimate, in bp, for the output. Estimates in bp are written without a unit.
+    pub(crate) fn format(&self, estimate: f32) -> String {
+        let (value, suffix) = scale_estimate(estimate, self.unit);
+        let number = match self.sig_figs {
+            Some(sig_figs) => {
+                let (rounded, decimals) = round_sig_figs(value as f64, sig_figs);
+                format!("{rounded:.decimals$}")
+            }
+            None if self.precise => format!("{value}"),
+            None if suffix == "bp" => format!("{value:.0}"),
+            None => format!("{value:.2}"),
+        };
+
+        if self.unit == Unit::Bp {
+            number
+        } else {
+            format!("{number} {suffix}")
+        }
+    }
 }
 
 #[cfg(test)]
@@ -54,6 +172,19 @@ mod tests {
     use std::fs;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_write_versions() {
+        let mut buf = Vec::new();
+        write_versions(&mut buf).unwrap();
+        let yaml = String::from_utf8(buf).unwrap();
+        let lines: Vec<&str> = yaml.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "lrge:");
+        assert_eq!(lines[1], format!("    lrge: {}", env!("CARGO_PKG_VERSION")));
+        assert_eq!(lines[2], format!("    liblrge: {}", liblrge::VERSION));
+        assert!(lines[3].starts_with("    minimap2: 2."));
+    }
+
     #[test]
     fn test_create_temp_dir_with_none() {
         let result = create_temp_dir(None, false);
@@ -83,6 +214,14 @@ mod tests {
         fs::remove_dir_all(temp_path).unwrap(); // Clean up
     }
 
+    #[test]
+    fn test_ram_temp_dir() {
+        if let Some(path) = ram_temp_dir() {
+            assert_eq!(path, PathBuf::from(RAM_TEMP_DIR));
+            assert!(liblrge::is_ram_backed(path));
+        }
+    }
+
     #[test]
     fn test_create_temp_dur_with_some_that_does_not_exist() {
         let base_dir = TempDir::new().unwrap();
@@ -145,4 +284,50 @@ mod tests {
         assert_eq!(format_estimate(10.0), "10.00 bp");
         assert_eq!(format_estimate(999.99), "999.99 bp");
     }
+
+    #[test]
+    fn test_output_format_default() {
+        let format = OutputFormat::default();
+        assert_eq!(format.format(4426642.4), "4426642");
+
+        let format = OutputFormat {
+            precise: true,
+            ..Default::default()
+        };
+        assert_eq!(format.format(4426642.5), "4426642.5");
+    }
+
+    #[test]
+    fn test_output_format_units() {
+        let format = |unit| OutputFormat {
+            unit,
+            ..Default::default()
+        };
+        assert_eq!(format(Unit::Kbp).format(4426642.0), "4426.64 kbp");
+        assert_eq!(format(Unit::Mbp).format(4426642.0), "4.43 Mbp");
+        assert_eq!(format(Unit::Gbp).format(4426642.0), "0.00 Gbp");
+        assert_eq!(format(Unit::Auto).format(4426642.0), "4.43 Mbp");
+        assert_eq!(format(Unit::Auto).format(500.0), "500 bp");
+    }
+
+    #[test]
+    fn test_output_format_sig_figs() {
+        let format = |unit, sig_figs| OutputFormat {
+            unit,
+            sig_figs: Some(sig_figs),
+            precise: false,
+        };
+        assert_eq!(format(Unit::Bp, 3).format(4426642.0), "4430000");
+        assert_eq!(format(Unit::Mbp, 3).format(4426642.0), "4.43 Mbp");
+        assert_eq!(format(Unit::Mbp, 1).format(4426642.0), "4 Mbp");
+        assert_eq!(format(Unit::Gbp, 2).format(4426642.0), "0.0044 Gbp");
+        assert_eq!(format(Unit::Auto, 5).format(12345678.0), "12.346 Mbp");
+    }
+
+    #[test]
+    fn test_round_sig_figs() {
+        assert_eq!(round_sig_figs(1234.0, 2), (1200.0, 0));
+        assert_eq!(round_sig_figs(0.012345, 3), (0.0123, 4));
+        assert_eq!(round_sig_figs(0.0, 3), (0.0, 0));
+    }
 }
@@ -2,6 +2,9 @@ use assert_cmd::Command;
 use std::io::Write;
 use tempfile::NamedTempFile;
 
+mod common;
+use common::toy_bam;
+
 #[test]
 fn test_sam_input() {
     let mut temp_file = NamedTempFile::new().unwrap();
@@ -51,7 +54,7 @@ fn test_mapped_sam_fails() {
 #[test]
 fn test_toy_bam_input() {
     let mut cmd = Command::cargo_bin("lrge").unwrap();
-    let bam_path = std::path::Path::new("tests").join("data").join("toy.bam");
+    let bam_path = toy_bam();
 
     if bam_path.exists() {
         // Use a fixed seed to ensure deterministic behavior across platforms
@@ -0,0 +1,70 @@
+use predicates::str::contains;
+
+mod common;
+use common::toy_cmd;
+
+/// The IDs of the reads sampled by `-n 60 -s 6`.
+fn sampled_ids(dir: &std::path::Path) -> Vec<String> {
+    toy_cmd(["-s", "6"])
+        .args(["-n", "60", "--keep-temp", "-D"])
+        .arg(dir)
+        .assert()
+        .success()
+        .stdout("94794\n");
+    let tmpdir = std::fs::read_dir(dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    std::fs::read_to_string(tmpdir.join("reads.fa"))
+        .unwrap()
+        .lines()
+        .filter_map(|l| l.strip_prefix('>').map(String::from))
+        .collect()
+}
+
+#[test]
+fn test_allowlist_restricts_sampling() {
+    let dir = tempfile::tempdir().unwrap();
+    let ids = sampled_ids(dir.path());
+    assert_eq!(ids.len(), 60);
+    let allowlist = dir.path().join("allowlist.txt");
+    std::fs::write(&allowlist, ids.join("\n")).unwrap();
+
+    // asking for more reads than are allowed uses all of the allowed reads, which are the reads
+    // sampled before, so the estimate is the same
+    toy_cmd(["-s", "6"])
+        .args(["-n", "100", "--allowlist"])
+        .arg(&allowlist)
+        .assert()
+        .success()
+        .stdout("94794\n")
+        .stderr(contains(
+            "Excluded 440 reads of the input file by their IDs",
+        ));
+}
+
+#[test]
+fn test_allowlist_without_matching_reads() {
+    let dir = tempfile::tempdir().unwrap();
+    let allowlist = dir.path().join("allowlist.txt");
+    std::fs::write(&allowlist, "not_a_read\n").unwrap();
+
+    toy_cmd(["-s", "6"])
+        .args(["-n", "100", "--allowlist"])
+        .arg(&allowlist)
+        .assert()
+        .failure()
+        .stderr(contains(
+            "All 500 reads in the file were excluded by their IDs",
+        ));
+}
+
+#[test]
+fn test_allowlist_must_exist() {
+    toy_cmd(["-s", "6"])
+        .args(["--allowlist", "missing.txt"])
+        .assert()
+        .failure();
+}
@@ -0,0 +1,67 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod common;
+use common::toy_bam;
+
+#[test]
+fn cli_auto_strategy_small_input_uses_ava() {
+    let bam_path = toy_bam();
+
+    let auto = Command::cargo_bin("lrge")
+        .unwrap()
+        .arg(&bam_path)
+        .args(["-s", "6", "--strategy", "auto"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Chose the all-vs-all strategy with 500 reads",
+        ))
+        .get_output()
+        .stdout
+        .clone();
+
+    // the toy input has 500 reads, so all of them are overlapped with each other
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg(&bam_path)
+        .args(["-s", "6", "-n", "500"])
+        .assert()
+        .success()
+        .stdout(String::from_utf8(auto).unwrap());
+}
+
+#[test]
+fn cli_auto_strategy_in_report() {
+    let bam_path = toy_bam();
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("report.json");
+
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg(&bam_path)
+        .args(["-s", "6", "--strategy", "auto", "--report"])
+        .arg(&report)
+        .assert()
+        .success();
+
+    let value: serde_json::Value =
+        serde_json::from_reader(std::fs::File::open(&report).unwrap()).unwrap();
+    let choice = &value["strategy_choice"];
+    assert_eq!(choice["strategy"], "all-vs-all");
+    assert_eq!(choice["input_num_reads"], 500);
+    assert!(choice["reason"].as_str().unwrap().contains("500 reads"));
+}
+
+#[test]
+fn cli_strategy_conflicts_with_num_reads() {
+    let bam_path = toy_bam();
+
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg(&bam_path)
+        .args(["--strategy", "auto", "-n", "100"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
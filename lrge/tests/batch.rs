@@ -0,0 +1,69 @@
+use assert_cmd::Command;
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+mod common;
+use common::toy_bam;
+
+#[test]
+fn test_batch_toy_bam() {
+    let bam_path = toy_bam();
+    let mut samples = NamedTempFile::new().unwrap();
+    writeln!(
+        samples,
+        "# sample\tpath\ntoy1\t{0}\ntoy2\t{0}",
+        bam_path.to_string_lossy()
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("lrge").unwrap();
+    let output = cmd
+        .arg("batch")
+        .arg(samples.path())
+        .arg("-T")
+        .arg("10")
+        .arg("-Q")
+        .arg("5")
+        .arg("--seed")
+        .arg("6")
+        .arg("-j")
+        .arg("2")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(
+        lines[0],
+        "sample\testimate\tlower\tupper\tno_mapping_count\tinput_num_reads\tinput_num_bases"
+    );
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with("toy1\t"));
+    assert!(lines[2].starts_with("toy2\t"));
+    assert!(lines[1].ends_with("\t500\t1272286"));
+    // the same reads and seed give the same result
+    assert_eq!(lines[1]["toy1".len()..], lines[2]["toy2".len()..]);
+}
+
+#[test]
+fn test_batch_failed_sample() {
+    let mut reads = NamedTempFile::new().unwrap();
+    writeln!(reads, ">read1\nGATTACA").unwrap();
+    let mut samples = NamedTempFile::new().unwrap();
+    writeln!(samples, "tiny\t{}", reads.path().to_string_lossy()).unwrap();
+
+    let mut cmd = Command::cargo_bin("lrge").unwrap();
+    cmd.arg("batch")
+        .arg(samples.path())
+        .arg("-T")
+        .arg("1")
+        .arg("-Q")
+        .arg("1")
+        .assert()
+        .failure()
+        .stdout(predicates::str::contains("tiny\tNA\tNA\tNA\tNA"))
+        .stderr(predicates::str::contains("1 of 1 samples failed"));
+}
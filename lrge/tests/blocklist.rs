@@ -0,0 +1,91 @@
+use predicates::str::contains;
+
+mod common;
+use common::toy_cmd;
+
+/// The IDs of the reads sampled into the single temporary directory under `dir`.
+fn sampled_ids(dir: &std::path::Path) -> Vec<String> {
+    let tmpdir = std::fs::read_dir(dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    std::fs::read_to_string(tmpdir.join("reads.fa"))
+        .unwrap()
+        .lines()
+        .filter_map(|l| l.strip_prefix('>').map(String::from))
+        .collect()
+}
+
+#[test]
+fn test_blocklist_excludes_reads() {
+    let dir = tempfile::tempdir().unwrap();
+    toy_cmd(["-s", "6", "-n", "60"])
+        .args(["--keep-temp", "-D"])
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout("94794\n");
+    let ids = sampled_ids(dir.path());
+    let blocked = &ids[..10];
+
+    let lists = tempfile::tempdir().unwrap();
+    let blocklist = lists.path().join("blocklist.txt");
+    std::fs::write(&blocklist, blocked.join("\n")).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    toy_cmd(["-s", "6", "-n", "60"])
+        .args(["--keep-temp", "-D"])
+        .arg(dir.path())
+        .arg("--blocklist")
+        .arg(&blocklist)
+        .assert()
+        .success()
+        .stderr(contains("Excluded 10 reads of the input file by their IDs"));
+    let ids = sampled_ids(dir.path());
+    assert_eq!(ids.len(), 60);
+    assert!(ids.iter().all(|id| !blocked.contains(id)));
+}
+
+#[test]
+fn test_blocklist_with_allowlist() {
+    let dir = tempfile::tempdir().unwrap();
+    toy_cmd(["-s", "6", "-n", "60"])
+        .args(["--keep-temp", "-D"])
+        .arg(dir.path())
+        .assert()
+        .success();
+    let ids = sampled_ids(dir.path());
+
+    let lists = tempfile::tempdir().unwrap();
+    let allowlist = lists.path().join("allowlist.txt");
+    std::fs::write(&allowlist, ids.join("\n")).unwrap();
+    let blocklist = lists.path().join("blocklist.txt");
+    std::fs::write(&blocklist, ids[..10].join("\n")).unwrap();
+
+    // only the 50 reads in the allowlist and not the blocklist can be sampled
+    let dir = tempfile::tempdir().unwrap();
+    toy_cmd(["-s", "6", "-n", "60"])
+        .args(["--keep-temp", "-D"])
+        .arg(dir.path())
+        .arg("--allowlist")
+        .arg(&allowlist)
+        .arg("--blocklist")
+        .arg(&blocklist)
+        .assert()
+        .success()
+        .stderr(contains(
+            "Excluded 450 reads of the input file by their IDs",
+        ));
+    let sampled = sampled_ids(dir.path());
+    assert_eq!(sampled, ids[10..]);
+}
+
+#[test]
+fn test_blocklist_must_exist() {
+    toy_cmd(["-s", "6", "-n", "60"])
+        .args(["--blocklist", "missing.txt"])
+        .assert()
+        .failure();
+}
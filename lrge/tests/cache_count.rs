@@ -0,0 +1,61 @@
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+
+mod common;
+use common::toy_bam;
+
+/// Copy the toy data into `dir`, so its sidecar file is written there.
+fn copy_toy_bam(dir: &Path) -> PathBuf {
+    let input = dir.join("toy.bam");
+    std::fs::copy(toy_bam(), &input).unwrap();
+    input
+}
+
+/// The number of reads a dry run would sample from `input` with `--num-frac 0.1`.
+fn dry_run_num_reads(input: &Path, args: &[&str]) -> u64 {
+    let output = Command::cargo_bin("lrge")
+        .unwrap()
+        .arg(input)
+        .args(["-s", "6", "--num-frac", "0.1", "--dry-run"])
+        .args(args)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let params: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    params["num_reads"].as_u64().unwrap()
+}
+
+#[test]
+fn test_cache_count_writes_sidecar() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = copy_toy_bam(dir.path());
+    let sidecar = dir.path().join("toy.bam.lrge-count");
+
+    assert_eq!(dry_run_num_reads(&input, &[]), 50);
+    assert!(!sidecar.exists());
+
+    assert_eq!(dry_run_num_reads(&input, &["--cache-count"]), 50);
+    let cached = std::fs::read_to_string(&sidecar).unwrap();
+    assert!(cached.contains("reads\t500\n"));
+}
+
+#[test]
+fn test_cache_count_reuses_sidecar() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = copy_toy_bam(dir.path());
+    let sidecar = dir.path().join("toy.bam.lrge-count");
+    dry_run_num_reads(&input, &["--cache-count"]);
+
+    // the cached count is used, rather than counting the reads again
+    let cached = std::fs::read_to_string(&sidecar).unwrap();
+    std::fs::write(&sidecar, cached.replace("reads\t500\n", "reads\t1000\n")).unwrap();
+    assert_eq!(dry_run_num_reads(&input, &["--cache-count"]), 100);
+    assert_eq!(
+        dry_run_num_reads(&input, &["--cache-count", "--validate"]),
+        50
+    );
+    assert_eq!(dry_run_num_reads(&input, &[]), 50);
+}
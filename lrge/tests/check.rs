@@ -0,0 +1,86 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+mod common;
+use common::toy_bam;
+
+fn check_cmd() -> Command {
+    let mut cmd = Command::cargo_bin("lrge").unwrap();
+    cmd.arg("check").arg(toy_bam());
+    cmd
+}
+
+#[test]
+fn test_check_no_problems() {
+    check_cmd()
+        .args(["-T", "10", "-Q", "5"])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr(predicate::str::contains(
+            "Input contains 500 reads and 1272286 bases",
+        ))
+        .stderr(predicate::str::contains("No problems found"));
+}
+
+#[test]
+fn test_check_too_few_reads() {
+    check_cmd()
+        .args(["-n", "1000"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "fewer than the 1000 requested (-n)",
+        ));
+
+    check_cmd()
+        .args(["-Q", "500"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "more than the 500 query reads (-Q) are needed",
+        ));
+}
+
+#[test]
+fn test_check_bad_records() {
+    let mut file = NamedTempFile::new().unwrap();
+    write!(
+        file,
+        "@r1\nACGT\n+\n!!!!\n@r2\nACGT\n+\n!!\n@r1\nACGT\n+\n!!!!\n@r3\nACGT\n+\n!!!!\n"
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("lrge").unwrap();
+    cmd.arg("check")
+        .arg(file.path())
+        .args(["-n", "3"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Skipping malformed record (line 5)",
+        ))
+        .stderr(predicate::str::contains("1 malformed record(s) found"))
+        .stderr(predicate::str::contains(
+            "1 read(s) have the same ID as an earlier read (e.g., r1)",
+        ));
+}
+
+#[test]
+fn test_check_unreadable_input() {
+    let mut file = NamedTempFile::new().unwrap();
+    // a gzip header with a truncated body
+    file.write_all(&[
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x4b,
+    ])
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("lrge").unwrap();
+    cmd.arg("check")
+        .arg(file.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to read the input"));
+}
@@ -0,0 +1,25 @@
+//! Helpers shared by the integration tests. Each test file is its own crate, so not all of them
+//! use every helper.
+#![allow(dead_code)]
+
+use std::ffi::OsStr;
+use std::path::PathBuf;
+
+use assert_cmd::Command;
+
+/// The path of the toy BAM file the tests use as input, relative to the crate root.
+pub fn toy_bam() -> PathBuf {
+    PathBuf::from("tests").join("data").join("toy.bam")
+}
+
+/// An `lrge` command to estimate the genome size of the [toy BAM file][toy_bam], with `args`
+/// after the input.
+pub fn toy_cmd<I, S>(args: I) -> Command
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut cmd = Command::cargo_bin("lrge").unwrap();
+    cmd.arg(toy_bam()).args(args);
+    cmd
+}
@@ -0,0 +1,47 @@
+use assert_cmd::Command;
+use predicates::str::contains;
+
+mod common;
+use common::toy_bam;
+
+#[test]
+fn test_compare() {
+    let bam_path = toy_bam();
+    let tmp = tempfile::tempdir().unwrap();
+    let copy = tmp.path().join("copy.bam");
+    std::fs::copy(&bam_path, &copy).unwrap();
+    let output = tmp.path().join("compare.tsv");
+
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg("compare")
+        .arg(&bam_path)
+        .arg(&copy)
+        .args(["-n", "60", "-s", "6", "-j", "2", "-o"])
+        .arg(&output)
+        .assert()
+        .success()
+        .stderr(contains("overlap (100.0% of their union)"));
+
+    let table = std::fs::read_to_string(&output).unwrap();
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(
+        lines[0],
+        "input\testimate\tlower\tupper\tno_mapping_count\tinput_num_reads\tiqr_overlap"
+    );
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with(&format!("{}\t94794\t", bam_path.display())));
+    assert!(lines[2].ends_with("\t94794\t31773\t233716\t42\t500\t1.000"));
+}
+
+#[test]
+fn test_compare_needs_two_inputs() {
+    let bam_path = toy_bam();
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg("compare")
+        .arg(&bam_path)
+        .assert()
+        .failure()
+        .stderr(contains("2 values required"));
+}
@@ -0,0 +1,58 @@
+use predicates::prelude::*;
+
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_contained_fraction_is_reported() {
+    let tmp = tempfile::tempdir().unwrap();
+    let report = tmp.path().join("report.json");
+    toy_cmd(["--seed", "6"])
+        .args(["-T", "10", "-Q", "5", "--report"])
+        .arg(&report)
+        .assert()
+        .success()
+        .stdout("84062\n")
+        .stderr(predicate::str::contains(
+            "1 (20.00%) read(s) are contained in a read they overlap",
+        ));
+
+    let report: serde_json::Value =
+        serde_json::from_reader(std::fs::File::open(&report).unwrap()).unwrap();
+    let fraction = report["contained_fraction"].as_f64().unwrap();
+    assert!((fraction - 0.2).abs() < 1e-6);
+    assert_eq!(report["num_reads"], 5);
+}
+
+#[test]
+fn test_exclude_contained_twoset() {
+    let tmp = tempfile::tempdir().unwrap();
+    let report = tmp.path().join("report.json");
+    toy_cmd(["--seed", "6"])
+        .args(["-T", "10", "-Q", "5", "--exclude-contained", "--report"])
+        .arg(&report)
+        .assert()
+        .success()
+        .stdout("156680\n")
+        .stderr(predicate::str::contains(
+            "and were excluded from the estimate",
+        ));
+
+    let report: serde_json::Value =
+        serde_json::from_reader(std::fs::File::open(&report).unwrap()).unwrap();
+    let fraction = report["contained_fraction"].as_f64().unwrap();
+    assert!((fraction - 0.2).abs() < 1e-6);
+    assert_eq!(report["num_reads"], 4);
+}
+
+#[test]
+fn test_exclude_contained_ava() {
+    toy_cmd(["--seed", "6"])
+        .args(["-n", "15", "--exclude-contained"])
+        .assert()
+        .success()
+        .stdout("88694\n")
+        .stderr(predicate::str::contains(
+            "2 (13.33%) read(s) are contained in a read they overlap",
+        ));
+}
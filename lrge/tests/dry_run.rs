@@ -0,0 +1,57 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod common;
+use common::toy_cmd;
+
+fn dry_run(cmd: &mut Command) -> serde_json::Value {
+    let output = cmd.assert().success().get_output().stdout.clone();
+    serde_json::from_slice(&output).unwrap()
+}
+
+#[test]
+fn test_dry_run_two_set() {
+    let params = dry_run(
+        toy_cmd(["-s", "6", "--dry-run"]).args(["-T", "10", "-Q", "5", "-P", "pb", "-t", "2"]),
+    );
+
+    assert_eq!(params["strategy"], "two-set");
+    assert_eq!(params["target_num_reads"], 10);
+    assert_eq!(params["query_num_reads"], 5);
+    assert_eq!(params["num_reads"], serde_json::Value::Null);
+    assert_eq!(params["platform"], "pb");
+    assert_eq!(params["minimap2_preset"], "ava-pb");
+    assert_eq!(params["threads"], 2);
+    assert_eq!(params["seed"], 6);
+}
+
+#[test]
+fn test_dry_run_resolves_fraction() {
+    let params = dry_run(toy_cmd(["-s", "6", "--dry-run"]).args(["--num-frac", "0.1"]));
+
+    assert_eq!(params["strategy"], "all-vs-all");
+    assert_eq!(params["num_reads"], 50);
+    assert_eq!(params["minimap2_preset"], "ava-ont");
+}
+
+#[test]
+fn test_dry_run_sketch_has_no_preset() {
+    let params = dry_run(toy_cmd(["-s", "6", "--dry-run"]).args(["--sketch", "-n", "100"]));
+
+    assert_eq!(params["strategy"], "sketch");
+    assert_eq!(params["num_reads"], 100);
+    assert_eq!(params["minimap2_preset"], serde_json::Value::Null);
+}
+
+#[test]
+fn test_dry_run_leaves_no_temporary_files() {
+    let dir = tempfile::tempdir().unwrap();
+    toy_cmd(["-s", "6", "--dry-run"])
+        .args(["--keep-temp", "-D"])
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Estimated genome size").not());
+
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
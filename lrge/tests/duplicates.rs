@@ -0,0 +1,87 @@
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod common;
+use common::toy_cmd;
+
+/// Write the 60 reads sampled by `-n 60 -s 6` to a FASTA file in `dir`, followed by a copy of the
+/// first 10 of them with new IDs.
+fn reads_with_duplicates(dir: &Path) -> PathBuf {
+    let tmpdir = dir.join("tmp");
+    std::fs::create_dir(&tmpdir).unwrap();
+    toy_cmd(["-s", "6"])
+        .args(["-n", "60", "--keep-temp", "-D"])
+        .arg(&tmpdir)
+        .assert()
+        .success()
+        .stdout("94794\n");
+    let run_dir = std::fs::read_dir(&tmpdir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let reads = std::fs::read_to_string(run_dir.join("reads.fa")).unwrap();
+
+    let mut fasta = reads.clone();
+    for record in reads.split('>').skip(1).take(10) {
+        let (id, seq) = record.split_once('\n').unwrap();
+        fasta.push_str(&format!(">{id}_dup\n{seq}"));
+    }
+    let path = dir.join("reads.fa");
+    std::fs::write(&path, fasta).unwrap();
+    path
+}
+
+#[test]
+fn test_duplicate_fraction_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = reads_with_duplicates(dir.path());
+    let report = dir.path().join("report.json");
+
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg(&input)
+        .args(["-s", "6", "-n", "70", "--report"])
+        .arg(&report)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "10 (14.29%) read(s) are a duplicate of a read they overlap",
+        ));
+
+    let report: serde_json::Value =
+        serde_json::from_reader(std::fs::File::open(&report).unwrap()).unwrap();
+    let fraction = report["duplicate_fraction"].as_f64().unwrap();
+    assert!((fraction - 10.0 / 70.0).abs() < 1e-6);
+    assert_eq!(report["num_reads"], 70);
+}
+
+#[test]
+fn test_exclude_duplicates_ava() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = reads_with_duplicates(dir.path());
+
+    // collapsing the duplicates gives the same estimate as the reads without them
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg(&input)
+        .args(["-s", "6", "-n", "70", "--exclude-duplicates"])
+        .assert()
+        .success()
+        .stdout("94794\n")
+        .stderr(predicate::str::contains(
+            "and were excluded from the estimate",
+        ));
+}
+
+#[test]
+fn test_no_duplicates() {
+    toy_cmd(["-s", "6"])
+        .args(["-n", "60", "--exclude-duplicates"])
+        .assert()
+        .success()
+        .stdout("94794\n");
+}
@@ -0,0 +1,31 @@
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_seed_from_env() {
+    toy_cmd(["-T", "10", "-Q", "5"])
+        .env("LRGE_SEED", "6")
+        .assert()
+        .success()
+        .stdout("84062\n");
+}
+
+#[test]
+fn test_cli_overrides_env() {
+    toy_cmd(["-T", "10", "-Q", "5"])
+        .env("LRGE_SEED", "not-a-seed")
+        .arg("--seed")
+        .arg("6")
+        .assert()
+        .success()
+        .stdout("84062\n");
+}
+
+#[test]
+fn test_invalid_env() {
+    toy_cmd(["-T", "10", "-Q", "5"])
+        .env("LRGE_THREADS", "many")
+        .assert()
+        .code(2)
+        .stderr(predicates::str::contains("--threads"));
+}
@@ -0,0 +1,39 @@
+mod common;
+use common::toy_cmd;
+
+// the toy reads are simulated, so their overlaps are dovetails or containments with tiny
+// overhangs, and none are internal matches by the overhang ratio
+
+#[test]
+fn test_filter_contained_twoset() {
+    toy_cmd(["--seed", "6"])
+        .args(["-T", "10", "-Q", "5", "-F"])
+        .assert()
+        .success()
+        .stdout("84062\n");
+
+    // the query reads are the reference
+    toy_cmd(["--seed", "6"])
+        .args(["-T", "40", "-Q", "5", "-F"])
+        .assert()
+        .success()
+        .stdout("133340\n");
+}
+
+#[test]
+fn test_filter_contained_ava() {
+    toy_cmd(["--seed", "6"])
+        .args(["-n", "15", "-F"])
+        .assert()
+        .success()
+        .stdout("33374\n");
+}
+
+#[test]
+fn test_max_overhang_size() {
+    toy_cmd(["--seed", "6"])
+        .args(["-T", "40", "-Q", "5", "-F", "--max-overhang-size", "100"])
+        .assert()
+        .success()
+        .stdout("136136\n");
+}
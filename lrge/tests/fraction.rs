@@ -0,0 +1,38 @@
+use predicates::str::contains;
+
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_num_frac_matches_num_reads() {
+    // the toy data has 500 reads, so this samples 60 of them, as -n 60 does
+    toy_cmd(["-s", "6"])
+        .args(["--num-frac", "0.12"])
+        .assert()
+        .success()
+        .stdout("94794\n")
+        .stderr(contains(
+            "Running all-vs-all strategy with 0.12 of the reads",
+        ));
+}
+
+#[test]
+fn test_target_and_query_frac_match_num_reads() {
+    toy_cmd(["-s", "6"])
+        .args(["--target-frac", "0.02", "--query-frac", "0.01"])
+        .assert()
+        .success()
+        .stdout("84062\n")
+        .stderr(contains(
+            "Running two-set strategy with 0.02 of the reads as target reads and 0.01 of the reads as query reads",
+        ));
+}
+
+#[test]
+fn test_frac_must_be_positive() {
+    toy_cmd(["-s", "6"])
+        .args(["--query-frac", "0"])
+        .assert()
+        .failure()
+        .stderr(contains("must be greater than 0.0"));
+}
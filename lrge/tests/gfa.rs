@@ -0,0 +1,47 @@
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_gfa_twoset() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("overlaps.gfa");
+
+    toy_cmd(["-s", "6"])
+        .args(["-T", "10", "-Q", "5"])
+        .arg("--gfa")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("84062\n");
+
+    let gfa = std::fs::read_to_string(&path).unwrap();
+    let mut lines = gfa.lines();
+    assert_eq!(lines.next(), Some("H\tVN:Z:1.0"));
+    let segments = gfa.lines().filter(|l| l.starts_with("S\t")).count();
+    assert!(segments > 0 && segments <= 15);
+    assert!(gfa
+        .lines()
+        .all(|l| ["H\t", "S\t", "L\t"].iter().any(|p| l.starts_with(p))));
+}
+
+#[test]
+fn test_gfa_ava() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("overlaps.gfa");
+
+    toy_cmd(["-s", "6"])
+        .args(["-n", "15"])
+        .arg("--gfa")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("33374\n");
+
+    let gfa = std::fs::read_to_string(&path).unwrap();
+    for link in gfa.lines().filter(|l| l.starts_with("L\t")) {
+        let fields: Vec<&str> = link.split('\t').collect();
+        assert_eq!(fields.len(), 6);
+        assert_ne!(fields[1], fields[3]);
+        assert!(fields[5].ends_with('M'));
+    }
+}
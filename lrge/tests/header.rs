@@ -0,0 +1,41 @@
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_header() {
+    let output = toy_cmd(["-n", "60", "-s", "6"])
+        .arg("--header")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+
+    let (estimate, header) = lines.split_last().unwrap();
+    assert_eq!(*estimate, "94794");
+    assert!(header.iter().all(|line| line.starts_with("# ")));
+    assert_eq!(
+        header[0],
+        format!(
+            "# lrge {} (liblrge {}, minimap2 {})",
+            env!("CARGO_PKG_VERSION"),
+            liblrge::VERSION,
+            liblrge::minimap2_version()
+        )
+    );
+    assert!(header.iter().any(|line| line.starts_with("# date: 20")));
+    assert!(header.iter().any(|line| line.ends_with("toy.bam")));
+    assert!(header.contains(&"# strategy: all-vs-all strategy with 60 reads"));
+    assert!(header.contains(&"# seed: 6"));
+    assert!(header.contains(&"# quantiles: 0.15 0.65"));
+}
+
+#[test]
+fn test_no_header_by_default() {
+    toy_cmd(["-n", "60", "-s", "6"])
+        .assert()
+        .success()
+        .stdout("94794\n");
+}
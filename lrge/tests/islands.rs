@@ -0,0 +1,45 @@
+use predicates::str::contains;
+
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_islands() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("report.json");
+
+    toy_cmd(["-s", "6"])
+        .args(["-n", "60", "--islands", "--report"])
+        .arg(&report)
+        .assert()
+        .success()
+        .stdout("94794\n")
+        .stderr(contains(
+            "Lander-Waterman estimate from 49 islands of 60 reads: 694.12 kbp",
+        ));
+
+    let report: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+    assert_eq!(report["islands"]["num_islands"], 49);
+    assert!(report["islands"]["estimate"].as_f64().unwrap() > 0.0);
+}
+
+#[test]
+fn test_islands_requires_ava() {
+    toy_cmd(["-s", "6"])
+        .args(["-T", "10", "-Q", "5", "--islands"])
+        .assert()
+        .failure()
+        .stderr(contains("--islands requires the all-vs-all strategy"));
+}
+
+#[test]
+fn test_islands_requires_paf() {
+    toy_cmd(["-s", "6"])
+        .args(["-n", "60", "--islands", "--overlap-format", "sam"])
+        .assert()
+        .failure()
+        .stderr(contains(
+            "--islands requires the overlaps to be written as PAF",
+        ));
+}
@@ -0,0 +1,30 @@
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_length_weighted_twoset() {
+    toy_cmd(["--seed", "6"])
+        .args(["-T", "10", "-Q", "5", "--length-weighted"])
+        .assert()
+        .success()
+        .stdout("57361\n");
+
+    // the query reads are the reference, so their overlaps are counted once all reads are mapped
+    toy_cmd(["--seed", "6"])
+        .args(["-T", "40", "-Q", "5", "--length-weighted"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "Using the query reads as the minimap2 reference",
+        ))
+        .stdout("284221\n");
+}
+
+#[test]
+fn test_length_weighted_ava() {
+    toy_cmd(["--seed", "6"])
+        .args(["-n", "15", "--length-weighted"])
+        .assert()
+        .success()
+        .stdout("45047\n");
+}
@@ -0,0 +1,70 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod common;
+use common::toy_bam;
+
+#[test]
+fn cli_log_file_independent_of_stderr() {
+    let bam_path = toy_bam();
+    let dir = tempfile::tempdir().unwrap();
+    let log_file = dir.path().join("lrge.log");
+
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg(&bam_path)
+        .args(["-s", "6", "-n", "60", "-qqq", "--log-file"])
+        .arg(&log_file)
+        .assert()
+        .success()
+        .stdout("94794\n")
+        .stderr(predicate::str::is_empty());
+
+    let log = std::fs::read_to_string(&log_file).unwrap();
+    assert!(log.contains("INFO"));
+    assert!(!log.contains("DEBUG"));
+    // the log file is never coloured
+    assert!(!log.contains('\x1b'));
+}
+
+#[test]
+fn cli_log_file_level() {
+    let bam_path = toy_bam();
+    let dir = tempfile::tempdir().unwrap();
+    let log_file = dir.path().join("lrge.log");
+
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg(&bam_path)
+        .args([
+            "-s",
+            "6",
+            "-n",
+            "60",
+            "--log-file-level",
+            "debug",
+            "--log-file",
+        ])
+        .arg(&log_file)
+        .assert()
+        .success()
+        .stdout("94794\n")
+        .stderr(predicate::str::contains("INFO"))
+        .stderr(predicate::str::contains("DEBUG").not());
+
+    let log = std::fs::read_to_string(&log_file).unwrap();
+    assert!(log.contains("DEBUG"));
+}
+
+#[test]
+fn cli_log_file_level_requires_log_file() {
+    let bam_path = toy_bam();
+
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg(&bam_path)
+        .args(["--log-file-level", "debug"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--log-file <FILE>"));
+}
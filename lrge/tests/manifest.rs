@@ -0,0 +1,99 @@
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod common;
+use common::toy_cmd;
+
+/// Run lrge with `--keep-temp` in `dir`, returning the kept temporary directory.
+fn kept_run(dir: &Path, args: &[&str]) -> PathBuf {
+    toy_cmd(["-s", "6"])
+        .args(args)
+        .args(["--keep-temp", "-D"])
+        .arg(dir)
+        .assert()
+        .success();
+    std::fs::read_dir(dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path()
+}
+
+fn read_manifest(tmpdir: &Path) -> serde_json::Value {
+    let file = std::fs::File::open(tmpdir.join("manifest.json")).unwrap();
+    serde_json::from_reader(file).unwrap()
+}
+
+#[test]
+fn test_manifest_describes_kept_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let tmpdir = kept_run(dir.path(), &["-T", "10", "-Q", "5"]);
+    let manifest = read_manifest(&tmpdir);
+
+    assert_eq!(manifest["provenance"]["parameters"]["strategy"], "two-set");
+    let artifacts = manifest["artifacts"].as_array().unwrap();
+    let paths: Vec<&str> = artifacts
+        .iter()
+        .map(|a| a["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        paths,
+        vec!["target.fa", "query.fa", "overlaps.paf", "estimates.tsv"]
+    );
+    for artifact in artifacts {
+        let path = tmpdir.join(artifact["path"].as_str().unwrap());
+        let size = std::fs::metadata(&path).unwrap().len();
+        assert_eq!(artifact["size"], size);
+        assert_eq!(artifact["sha256"].as_str().unwrap().len(), 64);
+    }
+}
+
+#[test]
+fn test_no_manifest_without_keep_temp() {
+    let dir = tempfile::tempdir().unwrap();
+    let tmpdir = kept_run(dir.path(), &["-n", "60"]);
+    let manifest = read_manifest(&tmpdir);
+    assert_eq!(manifest["artifacts"][0]["path"], "reads.fa");
+
+    // the manifest is only written when the temporary directory is kept
+    let dir = tempfile::tempdir().unwrap();
+    toy_cmd(["-s", "6"])
+        .args(["-n", "60", "-D"])
+        .arg(dir.path())
+        .assert()
+        .success();
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn test_reestimate_checks_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    let tmpdir = kept_run(dir.path(), &["-n", "60"]);
+
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg("reestimate")
+        .arg(&tmpdir)
+        .assert()
+        .success()
+        .stdout("94794\n")
+        .stderr(predicate::str::contains("have changed").not());
+
+    let estimates = tmpdir.join("estimates.tsv");
+    let mut text = std::fs::read_to_string(&estimates).unwrap();
+    text.push_str("extra_read\tinf\tfalse\n");
+    std::fs::write(&estimates, text).unwrap();
+
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg("reestimate")
+        .arg(&tmpdir)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "have changed since they were written",
+        ));
+}
@@ -0,0 +1,56 @@
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_max_divergence_twoset() {
+    toy_cmd(["--seed", "6"])
+        .args(["-T", "10", "-Q", "5", "--max-divergence", "1"])
+        .assert()
+        .success()
+        .stdout("84062\n");
+
+    // fewer overlaps are counted, so the estimate is larger
+    toy_cmd(["--seed", "6"])
+        .args(["-T", "10", "-Q", "5", "--max-divergence", "0.002"])
+        .assert()
+        .success()
+        .stdout("86654\n");
+}
+
+#[test]
+fn test_max_divergence_ava() {
+    toy_cmd(["--seed", "6"])
+        .args(["-n", "15", "--max-divergence", "0.1"])
+        .assert()
+        .success()
+        .stdout("33374\n");
+
+    toy_cmd(["--seed", "6"])
+        .args(["-n", "15", "--max-divergence", "0.05"])
+        .assert()
+        .success()
+        .stdout("50044\n");
+}
+
+#[test]
+fn test_max_divergence_out_of_range() {
+    toy_cmd(["--seed", "6"])
+        .args(["--max-divergence", "1.5"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("must be between 0.0 and 1.0"));
+}
+
+#[test]
+fn test_max_divergence_filters_every_overlap() {
+    // minimap2 finds hits for some of the reads, but none pass the filter, so none are counted as
+    // overlapping
+    toy_cmd(["--seed", "6"])
+        .args(["-T", "10", "-Q", "5", "--max-divergence", "0"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "5 (100.00%) query read(s) did not overlap any target reads",
+        ))
+        .stderr(predicates::str::contains("none of the 5 reads overlapped"));
+}
@@ -0,0 +1,39 @@
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+/// Write a FASTQ file in `dir` whose second record is truncated, so sampling it fails.
+fn truncated_fastq(dir: &Path) -> PathBuf {
+    let path = dir.join("reads.fq");
+    std::fs::write(&path, "@r1\nACGTACGT\n+\nIIIIIIII\n@r2\nACGT\n").unwrap();
+    path
+}
+
+#[test]
+fn test_budget_checked_before_sampling() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = truncated_fastq(dir.path());
+
+    for strategy_args in [&["-n", "100"][..], &["-T", "10", "-Q", "5"]] {
+        // 4 mapping threads alone need more than the budget
+        Command::cargo_bin("lrge")
+            .unwrap()
+            .arg(&input)
+            .args(strategy_args)
+            .args(["-t", "4", "--max-memory", "64M"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Memory budget exceeded"));
+
+        // without a budget, sampling the truncated input fails instead
+        Command::cargo_bin("lrge")
+            .unwrap()
+            .arg(&input)
+            .args(strategy_args)
+            .args(["-t", "4"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Memory budget exceeded").not());
+    }
+}
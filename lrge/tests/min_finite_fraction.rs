@@ -0,0 +1,48 @@
+use predicates::prelude::*;
+
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_min_finite_fraction_met() {
+    // 2 of the 5 query reads overlap a target read
+    toy_cmd(["--seed", "6"])
+        .args(["-T", "10", "-Q", "5", "--min-finite-fraction", "0.4"])
+        .assert()
+        .success()
+        .stdout("84062\n");
+}
+
+#[test]
+fn test_min_finite_fraction_not_met() {
+    toy_cmd(["--seed", "6"])
+        .args(["-T", "10", "-Q", "5", "--min-finite-fraction", "0.5"])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(predicate::str::contains(
+            "Only 2 of the 5 reads (40.00%) overlapped another read",
+        ))
+        .stderr(predicate::str::contains("try sampling more reads"));
+}
+
+#[test]
+fn test_min_finite_fraction_not_met_creates_no_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("estimate.txt");
+    toy_cmd(["--seed", "6"])
+        .args(["-T", "10", "-Q", "5", "--min-finite-fraction", "0.5", "-o"])
+        .arg(&output)
+        .assert()
+        .failure();
+    assert!(!output.exists());
+}
+
+#[test]
+fn test_min_finite_fraction_out_of_range() {
+    toy_cmd(["--seed", "6"])
+        .args(["--min-finite-fraction", "1.5"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("must be between 0.0 and 1.0"));
+}
@@ -0,0 +1,42 @@
+use predicates::prelude::*;
+
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_min_mapq_zero_keeps_all_overlaps() {
+    toy_cmd(["-T", "10", "-Q", "5", "--seed", "6"])
+        .args(["--min-mapq", "0"])
+        .assert()
+        .success()
+        .stdout("84062\n");
+}
+
+#[test]
+fn test_min_mapq_ignored_overlaps_are_still_written() {
+    // all of the toy overlaps have a MAPQ of 0, so none are counted
+    let tmp = tempfile::tempdir().unwrap();
+    toy_cmd(["-T", "10", "-Q", "5", "--seed", "6"])
+        .args(["--min-mapq", "1", "--keep-temp", "-D"])
+        .arg(tmp.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No finite estimates"));
+
+    let overlaps = std::fs::read_dir(tmp.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path().join("overlaps.paf"))
+        .find(|path| path.exists())
+        .unwrap();
+    let overlaps = std::fs::read_to_string(overlaps).unwrap();
+    assert_eq!(overlaps.lines().count(), 5);
+}
+
+#[test]
+fn test_min_mapq_out_of_range() {
+    toy_cmd(["-T", "10", "-Q", "5", "--seed", "6"])
+        .args(["--min-mapq", "256"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--min-mapq"));
+}
@@ -0,0 +1,28 @@
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_min_overlap_len_twoset() {
+    // the shortest toy overlap is 307 bp, so none are ignored
+    toy_cmd(["--seed", "6"])
+        .args(["-T", "10", "-Q", "5", "--min-overlap-len", "300"])
+        .assert()
+        .success()
+        .stdout("84062\n");
+
+    // fewer overlaps are counted, so the estimate is larger
+    toy_cmd(["--seed", "6"])
+        .args(["-T", "10", "-Q", "5", "--min-overlap-len", "1000"])
+        .assert()
+        .success()
+        .stdout("94431\n");
+}
+
+#[test]
+fn test_min_overlap_len_ava() {
+    toy_cmd(["--seed", "6"])
+        .args(["-n", "15", "--min-overlap-len", "500"])
+        .assert()
+        .success()
+        .stdout("39680\n");
+}
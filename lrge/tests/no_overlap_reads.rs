@@ -0,0 +1,48 @@
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_no_overlap_reads_twoset() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("no_overlap.tsv");
+
+    toy_cmd(["-s", "6"])
+        .args(["-T", "10", "-Q", "5", "--no-overlap-reads"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("84062\n");
+
+    let table = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            "read_id\tlength",
+            "488b2826-d823-44f7-b399-5b06e0c7d5c0\t18660",
+            "76e015a9-aaf9-4879-9f29-c202978c04ae\t8755",
+            "7a20ddac-f706-4b96-aa52-c8df6cc1bdb0\t5101",
+        ]
+    );
+}
+
+#[test]
+fn test_no_overlap_reads_ava() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("no_overlap.tsv");
+
+    toy_cmd(["-s", "6"])
+        .args(["-n", "15", "--no-overlap-reads"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("33374\n");
+
+    let table = std::fs::read_to_string(&path).unwrap();
+    // 6 of the 15 reads have an overlap
+    assert_eq!(table.lines().count(), 1 + 9);
+    for line in table.lines().skip(1) {
+        let (_, len) = line.split_once('\t').unwrap();
+        assert!(len.parse::<usize>().unwrap() > 0);
+    }
+}
@@ -0,0 +1,93 @@
+use predicates::prelude::*;
+
+mod common;
+use common::toy_cmd;
+
+/// The overlaps file written to the (only) temporary directory within `dir`.
+fn overlaps(dir: &std::path::Path, filename: &str) -> String {
+    let run_dir = std::fs::read_dir(dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    std::fs::read_to_string(run_dir.join(filename)).unwrap()
+}
+
+#[test]
+fn test_overlap_format_sam() {
+    let dir = tempfile::tempdir().unwrap();
+
+    toy_cmd(["-T", "10", "-Q", "5", "-s", "6", "-C"])
+        .arg("-D")
+        .arg(dir.path())
+        .args(["--overlap-format", "sam"])
+        .assert()
+        .success()
+        .stdout("84062\n");
+
+    let sam = overlaps(dir.path(), "overlaps.sam");
+    assert!(sam.starts_with("@HD\tVN:1.6"));
+    let records: Vec<&str> = sam.lines().filter(|l| !l.starts_with('@')).collect();
+    assert!(!records.is_empty());
+    // every target is in the header
+    for record in records {
+        let target = record.split('\t').nth(2).unwrap();
+        assert!(sam.contains(&format!("@SQ\tSN:{target}\t")));
+    }
+}
+
+#[test]
+fn test_overlap_format_blast6() {
+    let dir = tempfile::tempdir().unwrap();
+
+    toy_cmd(["-T", "10", "-Q", "5", "-s", "6", "-C"])
+        .arg("-D")
+        .arg(dir.path())
+        .args(["--overlap-format", "BLAST6"])
+        .assert()
+        .success()
+        .stdout("84062\n");
+
+    let blast = overlaps(dir.path(), "overlaps.blast6");
+    assert!(!blast.is_empty());
+    assert!(blast.lines().all(|l| l.split('\t').count() == 12));
+}
+
+#[test]
+fn test_overlap_format_gfa_requires_paf() {
+    let dir = tempfile::tempdir().unwrap();
+
+    toy_cmd(["-T", "10", "-Q", "5", "-s", "6", "-C"])
+        .arg("-D")
+        .arg(dir.path())
+        .args(["--overlap-format", "sam", "--gfa", "graph.gfa"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--gfa requires"));
+}
+
+#[test]
+fn test_sort_overlaps_is_deterministic() {
+    let runs: Vec<String> = (0..2)
+        .map(|_| {
+            let dir = tempfile::tempdir().unwrap();
+            toy_cmd(["-T", "10", "-Q", "5", "-s", "6", "-C"])
+                .arg("-D")
+                .arg(dir.path())
+                .args(["-t", "4", "--sort-overlaps"])
+                .assert()
+                .success()
+                .stdout("84062\n");
+            overlaps(dir.path(), "overlaps.paf")
+        })
+        .collect();
+
+    assert!(!runs[0].is_empty());
+    assert_eq!(runs[0], runs[1]);
+    let queries: Vec<&str> = runs[0]
+        .lines()
+        .map(|l| l.split('\t').next().unwrap())
+        .collect();
+    assert!(queries.windows(2).all(|w| w[0] <= w[1]));
+}
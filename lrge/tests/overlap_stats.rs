@@ -0,0 +1,65 @@
+use predicates::prelude::*;
+
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_overlap_stats() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("report.json");
+
+    toy_cmd(["-s", "6"])
+        .args(["-n", "15", "--overlap-stats", "--report"])
+        .arg(&report)
+        .assert()
+        .success()
+        .stdout("33374\n")
+        .stderr(predicate::str::contains(
+            "Overlaps per read: median 3.0 (5th-95th percentile: 1.0 - 3.0)",
+        ))
+        .stderr(predicate::str::contains("Overlap length: median 650.00 bp"))
+        .stderr(predicate::str::contains("Overlap divergence: median 0.96%"));
+
+    let value: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+    let overlaps = &value["overlaps"];
+    assert_eq!(overlaps["per_read"]["count"], 6);
+    assert_eq!(overlaps["length"]["count"], 9);
+    assert_eq!(overlaps["length"]["median"], 650.0);
+    assert_eq!(
+        overlaps["divergence"]["bin_edges"]
+            .as_array()
+            .unwrap()
+            .len(),
+        11
+    );
+    let counts = overlaps["divergence"]["bin_counts"].as_array().unwrap();
+    assert_eq!(counts.iter().map(|c| c.as_u64().unwrap()).sum::<u64>(), 9);
+}
+
+#[test]
+fn test_overlap_stats_not_in_report_by_default() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("report.json");
+
+    toy_cmd(["-s", "6"])
+        .args(["-n", "15", "--report"])
+        .arg(&report)
+        .assert()
+        .success();
+
+    let value: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+    assert!(value["overlaps"].is_null());
+}
+
+#[test]
+fn test_overlap_stats_requires_paf() {
+    toy_cmd(["-s", "6"])
+        .args(["--overlap-stats", "--overlap-format", "blast6"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--overlap-stats requires the overlaps to be written as PAF",
+        ));
+}
@@ -0,0 +1,84 @@
+use assert_cmd::Command;
+
+mod common;
+use common::toy_bam;
+
+/// Run an estimate on the toy data, keeping the temporary directory, and return it.
+fn kept_run(tmp: &std::path::Path) -> std::path::PathBuf {
+    let bam_path = toy_bam();
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg(&bam_path)
+        .args(["-n", "60", "--seed", "6", "--keep-temp", "--temp"])
+        .arg(tmp)
+        .assert()
+        .success();
+
+    std::fs::read_dir(tmp)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path()
+}
+
+#[test]
+fn test_plot_svg() {
+    let tmp = tempfile::tempdir().unwrap();
+    let run_dir = kept_run(tmp.path());
+    let output = tmp.path().join("estimates.svg");
+
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg("plot")
+        .arg(&run_dir)
+        .arg("-o")
+        .arg(&output)
+        .assert()
+        .success();
+
+    let svg = std::fs::read_to_string(&output).unwrap();
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.contains("(18 finite, 42 infinite)"));
+    assert!(svg.contains("median: 94.79 kbp"));
+    assert!(svg.contains("q0.15: 31.77 kbp"));
+    assert_eq!(svg.matches("<title>").count(), 30);
+}
+
+#[test]
+fn test_plot_html() {
+    let tmp = tempfile::tempdir().unwrap();
+    let run_dir = kept_run(tmp.path());
+    let output = tmp.path().join("estimates.html");
+
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg("plot")
+        .arg(run_dir.join("estimates.tsv"))
+        .args(["--bins", "10", "--q1", "0.25", "-o"])
+        .arg(&output)
+        .assert()
+        .success();
+
+    let html = std::fs::read_to_string(&output).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("q0.25: "));
+    assert_eq!(html.matches("<title>").count(), 1 + 10);
+}
+
+#[test]
+fn test_plot_missing_estimates() {
+    let tmp = tempfile::tempdir().unwrap();
+
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg("plot")
+        .arg(tmp.path())
+        .arg("-o")
+        .arg(tmp.path().join("plot.svg"))
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "Failed to read per-read estimates",
+        ));
+}
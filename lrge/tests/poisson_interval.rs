@@ -0,0 +1,55 @@
+use predicates::str::contains;
+
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_poisson_interval_logged() {
+    toy_cmd(["-s", "6"])
+        .args(["-n", "60"])
+        .assert()
+        .success()
+        .stdout("94794\n")
+        .stderr(contains(
+            "95% confidence interval from a Poisson model of the 38 overlaps: 69.06 kbp - 133.97 kbp",
+        ));
+}
+
+#[test]
+fn test_poisson_interval_in_report() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("report.json");
+    toy_cmd(["-s", "6"])
+        .args(["-n", "60", "--report"])
+        .arg(&report)
+        .assert()
+        .success();
+
+    let value: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+    let interval = &value["poisson_interval"];
+    assert_eq!(interval["num_overlaps"], 38);
+    assert_eq!(interval["confidence"], 0.95);
+    let estimate = value["estimate"].as_f64().unwrap();
+    assert!(interval["lower"].as_f64().unwrap() < estimate);
+    assert!(interval["upper"].as_f64().unwrap() > estimate);
+}
+
+#[test]
+fn test_poisson_interval_narrows_with_more_reads() {
+    let width = |num_reads: &str| {
+        let dir = tempfile::tempdir().unwrap();
+        let report = dir.path().join("report.json");
+        toy_cmd(["-s", "6"])
+            .args(["-n", num_reads, "--report"])
+            .arg(&report)
+            .assert()
+            .success();
+        let value: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+        let interval = &value["poisson_interval"];
+        (interval["upper"].as_f64().unwrap() - interval["lower"].as_f64().unwrap())
+            / value["estimate"].as_f64().unwrap()
+    };
+    assert!(width("500") < width("60"));
+}
@@ -0,0 +1,28 @@
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_primary_only_without_filters() {
+    // with no overlap filters, the primary chain to each read is always counted
+    toy_cmd(["-T", "10", "-Q", "5", "--seed", "6"])
+        .arg("--primary-only")
+        .assert()
+        .success()
+        .stdout("84062\n");
+}
+
+#[test]
+fn test_primary_only_with_filters() {
+    toy_cmd(["-T", "10", "-Q", "5", "--seed", "6"])
+        .args(["--max-divergence", "0.002"])
+        .assert()
+        .success()
+        .stdout("86654\n");
+
+    // a secondary chain can no longer be counted when the primary chain is too divergent
+    toy_cmd(["-T", "10", "-Q", "5", "--seed", "6"])
+        .args(["--max-divergence", "0.002", "--primary-only"])
+        .assert()
+        .success()
+        .stdout("94431\n");
+}
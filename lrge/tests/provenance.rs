@@ -0,0 +1,74 @@
+use sha2::{Digest, Sha256};
+
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_report_provenance() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("report.json");
+    let tmpdir = dir.path().join("tmp");
+    std::fs::create_dir(&tmpdir).unwrap();
+
+    toy_cmd(["-s", "6"])
+        .args(["-n", "60", "-P", "pb", "--keep-temp", "-D"])
+        .arg(&tmpdir)
+        .arg("--report")
+        .arg(&report)
+        .assert()
+        .success();
+
+    let report: serde_json::Value =
+        serde_json::from_reader(std::fs::File::open(&report).unwrap()).unwrap();
+    let provenance = &report["provenance"];
+    assert_eq!(provenance["lrge_version"], env!("CARGO_PKG_VERSION"));
+    assert!(provenance["liblrge_version"].is_string());
+    assert!(provenance["command"]
+        .as_str()
+        .unwrap()
+        .contains("-n 60 -P pb"));
+    assert_eq!(provenance["parameters"]["strategy"], "all-vs-all");
+    assert_eq!(provenance["parameters"]["num_reads"], 60);
+    assert_eq!(provenance["parameters"]["minimap2_preset"], "ava-pb");
+    assert_eq!(provenance["parameters"]["seed"], 6);
+    assert_eq!(
+        provenance["input"]["size"],
+        std::fs::metadata("tests/data/toy.bam").unwrap().len()
+    );
+    assert!(provenance["hostname"].is_string());
+    assert!(provenance["wall_time_secs"].as_f64().unwrap() > 0.0);
+    assert!(provenance["cpu_time_secs"].as_f64().unwrap() > 0.0);
+
+    let run_dir = std::fs::read_dir(&tmpdir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let reads = std::fs::read(run_dir.join("reads.fa")).unwrap();
+    assert_eq!(
+        provenance["input"]["sampled_reads_sha256"],
+        format!("{:x}", Sha256::digest(&reads))
+    );
+}
+
+#[test]
+fn test_report_provenance_tsv() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("report.tsv");
+
+    toy_cmd(["-s", "6"])
+        .args(["-T", "10", "-Q", "5", "--report-format", "tsv", "--report"])
+        .arg(&report)
+        .assert()
+        .success();
+
+    let text = std::fs::read_to_string(&report).unwrap();
+    let mut lines = text.lines();
+    let header: Vec<&str> = lines.next().unwrap().split('\t').collect();
+    let row: Vec<&str> = lines.next().unwrap().split('\t').collect();
+    let column = |name: &str| row[header.iter().position(|&h| h == name).unwrap()];
+    assert_eq!(column("provenance.parameters.strategy"), "two-set");
+    assert_eq!(column("provenance.parameters.target_num_reads"), "10");
+    assert_eq!(column("provenance.input.sampled_reads_sha256").len(), 64);
+}
@@ -0,0 +1,80 @@
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_qc_passes() {
+    toy_cmd(["-T", "10", "-Q", "5", "--seed", "6"])
+        .arg("--fail-if-estimate-outside")
+        .arg("50k:100k")
+        .arg("--fail-if-no-overlap-pct")
+        .arg("100")
+        .assert()
+        .success()
+        .stdout("84062\n");
+}
+
+#[test]
+fn test_qc_estimate_outside() {
+    // the estimate is still written when a quality control check fails
+    toy_cmd(["-T", "10", "-Q", "5", "--seed", "6"])
+        .arg("--fail-if-estimate-outside")
+        .arg("4m:6m")
+        .assert()
+        .code(4)
+        .stdout("84062\n")
+        .stderr(predicates::str::contains("is outside the range"));
+}
+
+#[test]
+fn test_qc_no_overlap() {
+    // 3 of the 5 query reads do not overlap
+    toy_cmd(["-T", "10", "-Q", "5", "--seed", "6"])
+        .arg("--fail-if-no-overlap-pct")
+        .arg("50")
+        .assert()
+        .code(3)
+        .stderr(predicates::str::contains(
+            "60.00% of reads did not overlap any other reads",
+        ));
+}
+
+#[test]
+fn test_qc_relative_error_report() {
+    let tmp = tempfile::tempdir().unwrap();
+    let report = tmp.path().join("report.json");
+    toy_cmd(["-T", "10", "-Q", "5", "--seed", "6"])
+        .arg("--expected-size")
+        .arg("100k")
+        .arg("--max-relative-error")
+        .arg("0.1")
+        .arg("--report")
+        .arg(&report)
+        .assert()
+        .code(5)
+        .stdout("84062\n");
+
+    let report: serde_json::Value =
+        serde_json::from_reader(std::fs::File::open(&report).unwrap()).unwrap();
+    assert_eq!(report["estimate"], 84062.0);
+    assert_eq!(report["expected_size"], 100000.0);
+    assert_eq!(report["input_num_reads"], 500);
+    assert_eq!(report["input_num_bases"], 1272286);
+    let gc = report["gc"]["overall"].as_f64().unwrap();
+    assert!((gc - 36.7).abs() < 0.1);
+    let relative_error = report["relative_error"].as_f64().unwrap();
+    assert!((relative_error + 0.15938).abs() < 1e-4);
+    assert_eq!(report["qc_failures"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_qc_low_coverage() {
+    toy_cmd(["-T", "10", "-Q", "5", "--seed", "6"])
+        .arg("--min-coverage")
+        .arg("30")
+        .assert()
+        .code(6)
+        .stdout("84062\n")
+        .stderr(predicates::str::contains(
+            "Estimated depth of coverage 15.1x is below 30x",
+        ));
+}
@@ -0,0 +1,111 @@
+use predicates::str::contains;
+
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_quantiles_logged() {
+    toy_cmd(["-s", "6", "-n", "60"])
+        .args(["--quantiles", "0.05,0.25,0.75,0.95"])
+        .assert()
+        .success()
+        .stdout("94794\n")
+        .stderr(contains(
+            "Quantiles of the per-read estimates: q0.05: 28.80 kbp, q0.25: 32.86 kbp, q0.75: 333.96 kbp, q0.95: 934.82 kbp",
+        ));
+}
+
+#[test]
+fn test_quantiles_in_report() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("report.json");
+    toy_cmd(["-s", "6", "-n", "60"])
+        .args(["--quantiles", "0.95,0.5", "--report"])
+        .arg(&report)
+        .assert()
+        .success();
+
+    let value: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+    let quantiles = value["quantiles"].as_object().unwrap();
+    let keys: Vec<&str> = quantiles.keys().map(|k| k.as_str()).collect();
+    assert_eq!(keys, ["0.95", "0.5"]);
+    assert_eq!(quantiles["0.5"], value["estimate"]);
+}
+
+#[test]
+fn test_no_quantiles_in_report_by_default() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("report.json");
+    toy_cmd(["-s", "6", "-n", "60"])
+        .arg("--report")
+        .arg(&report)
+        .assert()
+        .success();
+
+    let value: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+    assert!(value["quantiles"].is_null());
+}
+
+#[test]
+fn test_invalid_quantile() {
+    toy_cmd(["-s", "6", "-n", "60"])
+        .args(["--quantiles", "0.5,1.5"])
+        .assert()
+        .failure()
+        .stderr(contains("must be between 0.0 and 1.0"));
+}
+
+#[test]
+fn test_calibrated_interval_labelled() {
+    toy_cmd(["-s", "6", "-n", "60"])
+        .assert()
+        .success()
+        .stderr(contains(
+            "Estimated genome size: 94.79 kbp (92% CI: 31.77 kbp - 233.72 kbp)",
+        ));
+}
+
+#[test]
+fn test_uncalibrated_interval_labelled_with_quantiles() {
+    toy_cmd(["-s", "6", "-n", "60"])
+        .args(["--q1", "0.25", "--q3", "0.75"])
+        .assert()
+        .success()
+        .stderr(contains(
+            "Estimated genome size: 94.79 kbp (q0.25-q0.75: 32.86 kbp - 333.96 kbp)",
+        ));
+}
+
+#[test]
+fn test_intervals() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("report.json");
+    toy_cmd(["-s", "6", "-n", "60"])
+        .args(["--intervals", "0.15-0.65,0.25-0.75", "--report"])
+        .arg(&report)
+        .assert()
+        .success()
+        .stderr(contains("Estimate 92% CI: 31.77 kbp - 233.72 kbp"))
+        .stderr(contains("Estimate q0.25-q0.75: 32.86 kbp - 333.96 kbp"));
+
+    let value: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+    assert_eq!(value["confidence"], 0.92);
+    let intervals = value["intervals"].as_array().unwrap();
+    assert_eq!(intervals.len(), 2);
+    assert_eq!(intervals[0]["label"], "92% CI");
+    assert_eq!(intervals[0]["lower"], value["lower"]);
+    assert_eq!(intervals[1]["label"], "q0.25-q0.75");
+    assert!(intervals[1]["confidence"].is_null());
+}
+
+#[test]
+fn test_invalid_interval() {
+    toy_cmd(["-s", "6", "-n", "60"])
+        .args(["--intervals", "0.65-0.15"])
+        .assert()
+        .failure()
+        .stderr(contains("must be less than the upper"));
+}
@@ -0,0 +1,39 @@
+use predicates::prelude::*;
+
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_rarefaction_table() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("rarefaction.tsv");
+
+    toy_cmd(["-T", "10", "-Q", "5", "-s", "6"])
+        .arg("--rarefaction")
+        .arg(&path)
+        .args(["--rarefaction-sizes", "5,3,10"])
+        .assert()
+        .success()
+        .stdout("84062\n");
+
+    let table = std::fs::read_to_string(&path).unwrap();
+    let rows: Vec<&str> = table.lines().collect();
+    assert_eq!(
+        rows[0],
+        "num_reads\testimate\tlower\tupper\tno_mapping_count"
+    );
+    // sizes larger than the number of query reads are ignored
+    assert_eq!(rows.len(), 3);
+    assert!(rows[1].starts_with("3\t"));
+    // all of the reads gives the same estimate as the run
+    assert!(rows[2].starts_with("5\t84062\t"));
+}
+
+#[test]
+fn test_rarefaction_sizes_require_table() {
+    toy_cmd(["-T", "10", "-Q", "5", "-s", "6"])
+        .args(["--rarefaction-sizes", "5"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--rarefaction <FILE>"));
+}
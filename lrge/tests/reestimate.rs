@@ -0,0 +1,88 @@
+use assert_cmd::Command;
+
+mod common;
+use common::toy_bam;
+
+#[test]
+fn test_reestimate_matches_original_estimate() {
+    let bam_path = toy_bam();
+    let tmp = tempfile::tempdir().unwrap();
+
+    let mut cmd = Command::cargo_bin("lrge").unwrap();
+    let original = cmd
+        .arg(&bam_path)
+        .arg("-T")
+        .arg("10")
+        .arg("-Q")
+        .arg("5")
+        .arg("--seed")
+        .arg("6")
+        .arg("--keep-temp")
+        .arg("--temp")
+        .arg(tmp.path())
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let run_dir = std::fs::read_dir(tmp.path())
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+
+    let mut cmd = Command::cargo_bin("lrge").unwrap();
+    cmd.arg("reestimate")
+        .arg(&run_dir)
+        .assert()
+        .success()
+        .stdout(String::from_utf8(original).unwrap());
+}
+
+#[test]
+fn test_reestimate_missing_estimates() {
+    let tmp = tempfile::tempdir().unwrap();
+
+    let mut cmd = Command::cargo_bin("lrge").unwrap();
+    cmd.arg("reestimate")
+        .arg(tmp.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Failed to recalculate estimate"));
+}
+
+#[test]
+fn test_saved_estimates_unmapped_when_overlaps_filtered() {
+    let bam_path = toy_bam();
+    let tmp = tempfile::tempdir().unwrap();
+
+    // the reads have hits, but only some pass the divergence filter
+    let mut cmd = Command::cargo_bin("lrge").unwrap();
+    cmd.arg(&bam_path)
+        .args(["-T", "10", "-Q", "5", "--seed", "6"])
+        .args(["--max-divergence", "0.001", "--keep-temp", "--temp"])
+        .arg(tmp.path())
+        .assert()
+        .success();
+
+    let run_dir = std::fs::read_dir(tmp.path())
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let estimates = std::fs::read_to_string(run_dir.join("estimates.tsv")).unwrap();
+    let rows: Vec<Vec<&str>> = estimates
+        .lines()
+        .skip(1)
+        .map(|line| line.split('\t').collect())
+        .collect();
+    assert_eq!(rows.len(), 5);
+    let unmapped = rows.iter().filter(|row| row[2] == "false").count();
+    assert_eq!(unmapped, 4);
+    for row in rows {
+        assert_eq!(row[1] == "inf", row[2] == "false", "{row:?}");
+    }
+}
@@ -0,0 +1,49 @@
+use predicates::str::contains;
+
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_remove_adapters() {
+    toy_cmd(["-s", "6", "-n", "60"])
+        .arg("--remove-adapters")
+        .assert()
+        .success()
+        .stdout("94750\n")
+        .stderr(contains(
+            "Trimmed adapters from 1 and split 0 chimeric reads of 60 sampled reads",
+        ));
+}
+
+#[test]
+fn test_remove_adapters_in_report() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("report.json");
+    toy_cmd(["-s", "6", "-n", "60"])
+        .arg("--remove-adapters")
+        .arg("--report")
+        .arg(&report)
+        .assert()
+        .success();
+
+    let value: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+    assert_eq!(value["adapters"]["num_reads"], 60);
+    assert_eq!(value["adapters"]["num_trimmed"], 1);
+    assert_eq!(value["adapters"]["num_split"], 0);
+}
+
+#[test]
+fn test_adapters_not_reported_unless_removed() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("report.json");
+    toy_cmd(["-s", "6", "-n", "60"])
+        .arg("--report")
+        .arg(&report)
+        .assert()
+        .success();
+
+    let value: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+    assert!(value["adapters"].is_null());
+}
@@ -0,0 +1,47 @@
+use predicates::prelude::*;
+
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_replicons() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("report.json");
+
+    toy_cmd(["-s", "6"])
+        .args(["-n", "60", "--replicons", "--report"])
+        .arg(&report)
+        .assert()
+        .success()
+        .stdout("94794\n")
+        .stderr(predicate::str::contains("Putative chromosome: 16.42 kbp"))
+        .stderr(predicate::str::contains(
+            "Putative plasmid size fraction: 0.00%",
+        ));
+
+    let value: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+    assert_eq!(value["replicon_sizes"].as_array().unwrap().len(), 1);
+    assert_eq!(value["plasmid_fraction"], 0.0);
+}
+
+#[test]
+fn test_replicons_too_few_reads() {
+    toy_cmd(["-s", "6"])
+        .args(["-T", "10", "-Q", "5", "--replicons"])
+        .assert()
+        .success()
+        .stdout("84062\n")
+        .stderr(predicate::str::contains("No replicons were found"));
+}
+
+#[test]
+fn test_replicons_requires_paf() {
+    toy_cmd(["-s", "6"])
+        .args(["--replicons", "--overlap-format", "sam"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--replicons requires the overlaps to be written as PAF",
+        ));
+}
@@ -0,0 +1,51 @@
+use predicates::prelude::*;
+use predicates::str::contains;
+
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_resample_below_fraction() {
+    // 3 of the 5 query reads do not overlap a target read
+    toy_cmd(["-s", "6", "-T", "10", "-Q", "5"])
+        .args(["--resample-no-overlap", "0.7"])
+        .assert()
+        .success()
+        .stdout("84062\n")
+        .stderr(contains("more query reads").not());
+}
+
+#[test]
+fn test_resample_above_fraction() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("no_overlap.tsv");
+
+    toy_cmd(["-s", "6", "-T", "10", "-Q", "5"])
+        .args(["--resample-no-overlap", "0.5", "--no-overlap-reads"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("100888\n")
+        .stderr(contains("so mapping 5 more query reads"))
+        .stderr(contains("Only 5 reads were available to resample"));
+
+    // the resampled reads are included with the original query reads
+    let table = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(table.lines().count(), 1 + 7);
+    for read in [
+        "488b2826-d823-44f7-b399-5b06e0c7d5c0",
+        "76e015a9-aaf9-4879-9f29-c202978c04ae",
+        "7a20ddac-f706-4b96-aa52-c8df6cc1bdb0",
+    ] {
+        assert!(table.contains(read));
+    }
+}
+
+#[test]
+fn test_resample_out_of_range() {
+    toy_cmd(["-s", "6", "-T", "10", "-Q", "5"])
+        .args(["--resample-no-overlap", "1.5"])
+        .assert()
+        .failure()
+        .stderr(contains("must be between 0.0 and 1.0"));
+}
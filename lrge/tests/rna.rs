@@ -0,0 +1,62 @@
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod common;
+use common::toy_bam;
+
+/// Write the 60 reads sampled by `-n 60 -s 6` to a FASTA file in `dir`, with their `T` bases
+/// replaced by `U`, as in direct RNA reads.
+fn rna_reads(dir: &Path) -> PathBuf {
+    let bam_path = toy_bam();
+    let tmpdir = dir.join("tmp");
+    std::fs::create_dir(&tmpdir).unwrap();
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg(bam_path)
+        .args(["-s", "6", "-n", "60", "--keep-temp", "-D"])
+        .arg(&tmpdir)
+        .assert()
+        .success()
+        .stdout("94794\n");
+    let run_dir = std::fs::read_dir(&tmpdir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let reads = std::fs::read_to_string(run_dir.join("reads.fa")).unwrap();
+
+    let fasta = reads
+        .lines()
+        .map(|line| {
+            if line.starts_with('>') {
+                line.to_string()
+            } else {
+                line.replace('T', "U")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let path = dir.join("reads.fa");
+    std::fs::write(&path, fasta + "\n").unwrap();
+    path
+}
+
+#[test]
+fn test_rna_reads_are_converted() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = rna_reads(dir.path());
+
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg(&input)
+        .args(["-s", "6", "-n", "60", "-P", "rna"])
+        .assert()
+        .success()
+        .stdout("94794\n")
+        .stderr(predicate::str::contains(
+            "60 sampled reads contain U bases (direct RNA?); these are converted to T",
+        ));
+}
@@ -0,0 +1,38 @@
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_string_seed_is_repeatable() {
+    for _ in 0..2 {
+        toy_cmd(["-n", "60"])
+            .env_remove("LRGE_SEED")
+            .args(["--seed", "sample1"])
+            .assert()
+            .success()
+            .stdout("35421\n");
+    }
+
+    toy_cmd(["-n", "60"])
+        .env_remove("LRGE_SEED")
+        .env("LRGE_SEED", "sample1")
+        .assert()
+        .success()
+        .stdout("35421\n");
+
+    toy_cmd(["-n", "60"])
+        .env_remove("LRGE_SEED")
+        .args(["--seed", "sample2"])
+        .assert()
+        .success()
+        .stdout("247225\n");
+}
+
+#[test]
+fn test_numeric_string_seed() {
+    toy_cmd(["-n", "60"])
+        .env_remove("LRGE_SEED")
+        .args(["--seed", "6"])
+        .assert()
+        .success()
+        .stdout("94794\n");
+}
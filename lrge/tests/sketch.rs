@@ -0,0 +1,62 @@
+use predicates::str::contains;
+
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_sketch_estimate() {
+    toy_cmd(["--sketch", "-s", "6"])
+        .args(["-n", "100", "-t", "2"])
+        .assert()
+        .success()
+        .stdout("82804\n")
+        .stderr(contains("Running sketch strategy with 100 reads"));
+}
+
+#[test]
+fn test_sketch_default_num_reads() {
+    toy_cmd(["--sketch", "-s", "6"])
+        .assert()
+        .success()
+        .stdout("900715\n")
+        .stderr(contains("is less than the number requested (10000)"));
+}
+
+#[test]
+fn test_sketch_keeps_reads() {
+    let dir = tempfile::tempdir().unwrap();
+    toy_cmd(["--sketch", "-s", "6"])
+        .args(["-n", "50", "--keep-temp", "-D"])
+        .arg(dir.path())
+        .assert()
+        .success();
+
+    let tmpdir = std::fs::read_dir(dir.path())
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    assert!(tmpdir.join("reads.fa").exists());
+    assert!(tmpdir.join("estimates.tsv").exists());
+    assert!(!tmpdir.join("overlaps.paf").exists());
+}
+
+#[test]
+fn test_sketch_conflicts_with_overlap_flags() {
+    toy_cmd(["--sketch", "-s", "6"])
+        .arg("--overlap-stats")
+        .assert()
+        .failure()
+        .stderr(contains(
+            "--overlap-stats needs overlaps, which --sketch does not write",
+        ));
+}
+
+#[test]
+fn test_sketch_conflicts_with_two_set() {
+    toy_cmd(["--sketch", "-s", "6"])
+        .args(["-Q", "10"])
+        .assert()
+        .failure();
+}
@@ -0,0 +1,109 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::io::Write;
+use std::path::Path;
+
+/// Write a FASTQ file of 40 reads from a random 20 kbp genome, followed by a truncated record.
+fn write_reads(path: &Path) {
+    let mut state: u64 = 6;
+    let mut rand = move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 33) as usize
+    };
+    let genome: Vec<u8> = (0..20_000).map(|_| b"ACGT"[rand() % 4]).collect();
+
+    let mut file = std::fs::File::create(path).unwrap();
+    for i in 0..40 {
+        let len = 2_000 + rand() % 2_000;
+        let start = rand() % (genome.len() - len);
+        let seq = &genome[start..start + len];
+        writeln!(file, "@read{i}").unwrap();
+        file.write_all(seq).unwrap();
+        writeln!(file, "\n+\n{}", "5".repeat(len)).unwrap();
+    }
+    write!(file, "@truncated\nACGT\n").unwrap();
+}
+
+fn cmd(dir: &Path) -> Command {
+    let mut cmd = frac_cmd(dir);
+    cmd.args(["-n", "20"]);
+    cmd
+}
+
+fn frac_cmd(dir: &Path) -> Command {
+    let reads = dir.join("reads.fq");
+    write_reads(&reads);
+    let mut cmd = Command::cargo_bin("lrge").unwrap();
+    cmd.arg(reads).args(["-s", "6"]);
+    cmd
+}
+
+/// The number of reads a dry run of `cmd` would sample.
+fn dry_run_num_reads(cmd: &mut Command) -> u64 {
+    let output = cmd
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let params: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    params["num_reads"].as_u64().unwrap()
+}
+
+#[test]
+fn test_bad_record_is_an_error_by_default() {
+    let dir = tempfile::tempdir().unwrap();
+    cmd(dir.path()).assert().failure();
+}
+
+#[test]
+fn test_skip_bad_records() {
+    let dir = tempfile::tempdir().unwrap();
+    cmd(dir.path())
+        .arg("--skip-bad-records")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("the record is truncated"))
+        .stderr(predicate::str::contains(
+            "Skipped 1 malformed record(s) in the input",
+        ))
+        .stderr(predicate::str::contains("Input contains 40 reads"));
+}
+
+#[test]
+fn test_skip_bad_records_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    cmd(dir.path())
+        .args(["--skip-bad-records", "0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("More than 0 malformed records"));
+}
+
+#[test]
+fn test_bad_record_is_counted_without_validate() {
+    let dir = tempfile::tempdir().unwrap();
+    // the truncated record is counted, so half of 41 records is rounded up
+    let num_reads = dry_run_num_reads(frac_cmd(dir.path()).args(["--num-frac", "0.5"]));
+    assert_eq!(num_reads, 21);
+}
+
+#[test]
+fn test_validate_bad_record() {
+    let dir = tempfile::tempdir().unwrap();
+    frac_cmd(dir.path())
+        .args(["--num-frac", "0.5", "--validate", "--dry-run"])
+        .assert()
+        .failure();
+
+    let num_reads = dry_run_num_reads(frac_cmd(dir.path()).args([
+        "--num-frac",
+        "0.5",
+        "--validate",
+        "--skip-bad-records",
+    ]));
+    assert_eq!(num_reads, 20);
+}
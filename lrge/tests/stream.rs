@@ -0,0 +1,35 @@
+use predicates::prelude::*;
+
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_stream_twoset() {
+    // the running estimate after the last query read is the final estimate
+    toy_cmd(["-s", "6"])
+        .args(["-T", "10", "-Q", "5", "--stream"])
+        .assert()
+        .success()
+        .stdout("84062\n")
+        .stderr(predicate::str::contains(
+            "Running estimate after 5 reads: 84.06 kbp",
+        ));
+}
+
+#[test]
+fn test_stream_ava() {
+    toy_cmd(["-s", "6"])
+        .args(["-n", "20", "--stream"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Running estimate after 20 reads"));
+}
+
+#[test]
+fn test_no_stream() {
+    toy_cmd(["-s", "6"])
+        .args(["-T", "10", "-Q", "5"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Running estimate").not());
+}
@@ -0,0 +1,55 @@
+use predicates::prelude::*;
+
+mod common;
+use common::toy_cmd;
+
+fn dev_shm_is_ram_backed() -> bool {
+    std::path::Path::new("/dev/shm").is_dir() && liblrge::is_ram_backed("/dev/shm")
+}
+
+#[test]
+fn test_temp_in_ram_gives_same_estimate() {
+    toy_cmd(["-s", "6", "-T", "10", "-Q", "5"])
+        .arg("--temp-in-ram")
+        .assert()
+        .success()
+        .stdout("84062\n");
+}
+
+#[test]
+fn test_temp_in_ram_takes_precedence_over_temp() {
+    if !dev_shm_is_ram_backed() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+
+    toy_cmd(["-s", "6", "-T", "10", "-Q", "5"])
+        .arg("--temp-in-ram")
+        .arg("-D")
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout("84062\n");
+
+    // nothing was written to --temp
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn test_max_memory_counts_reads_in_ram() {
+    if !dev_shm_is_ram_backed() {
+        return;
+    }
+    // enough for the index and threads, but not for the sampled reads as well
+    let budget = "33730000";
+    toy_cmd(["-s", "6", "-T", "10", "-Q", "5"])
+        .args(["-t", "1", "--max-memory", budget])
+        .assert()
+        .success();
+
+    toy_cmd(["-s", "6", "-T", "10", "-Q", "5"])
+        .args(["-t", "1", "--max-memory", budget, "--temp-in-ram"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Memory budget exceeded"));
+}
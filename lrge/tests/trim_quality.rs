@@ -0,0 +1,65 @@
+use assert_cmd::Command;
+use predicates::str::contains;
+
+mod common;
+use common::toy_cmd;
+
+#[test]
+fn test_trim_quality() {
+    toy_cmd(["-s", "6", "-n", "60"])
+        .args(["--trim-quality", "10"])
+        .assert()
+        .success()
+        .stdout("95472\n")
+        .stderr(contains(
+            "Trimmed 6397 low-quality bases from the ends of 106 input reads",
+        ));
+}
+
+#[test]
+fn test_trim_window() {
+    toy_cmd(["-s", "6", "-n", "60"])
+        .args(["--trim-quality", "10", "--trim-window", "100"])
+        .assert()
+        .success()
+        .stdout("94941\n");
+}
+
+#[test]
+fn test_trim_window_requires_trim_quality() {
+    toy_cmd(["-s", "6", "-n", "60"])
+        .args(["--trim-window", "100"])
+        .assert()
+        .failure()
+        .stderr(contains("--trim-quality <INT>"));
+}
+
+#[test]
+fn test_trim_quality_of_fasta_reads_does_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    let fasta = dir.path().join("reads.fa");
+    toy_cmd(["-s", "6", "-n", "60"])
+        .args(["--keep-temp", "-D"])
+        .arg(dir.path())
+        .assert()
+        .success();
+    let tmpdir = std::fs::read_dir(dir.path())
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    std::fs::copy(tmpdir.join("reads.fa"), &fasta).unwrap();
+
+    let estimate = |args: &[&str]| {
+        let output = Command::cargo_bin("lrge")
+            .unwrap()
+            .arg(&fasta)
+            .args(["-s", "6", "-n", "60"])
+            .args(args)
+            .assert()
+            .success();
+        output.get_output().stdout.clone()
+    };
+    assert_eq!(estimate(&[]), estimate(&["--trim-quality", "40"]));
+}
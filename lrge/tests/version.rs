@@ -0,0 +1,38 @@
+use assert_cmd::Command;
+use predicates::str::contains;
+
+mod common;
+use common::toy_bam;
+
+#[test]
+fn test_long_version_includes_minimap2() {
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg("--version")
+        .assert()
+        .success()
+        .stdout(contains(format!("lrge {}", env!("CARGO_PKG_VERSION"))))
+        .stdout(contains(format!(
+            "minimap2 {}",
+            liblrge::minimap2_version()
+        )));
+}
+
+#[test]
+fn test_versions_out() {
+    let bam_path = toy_bam();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("versions.yml");
+
+    Command::cargo_bin("lrge")
+        .unwrap()
+        .arg(bam_path)
+        .args(["-n", "60", "-s", "6", "--versions-out"])
+        .arg(&path)
+        .assert()
+        .success();
+
+    let yaml = std::fs::read_to_string(&path).unwrap();
+    assert!(yaml.starts_with(&format!("lrge:\n    lrge: {}\n", env!("CARGO_PKG_VERSION"))));
+    assert!(yaml.contains("    minimap2: 2."));
+}